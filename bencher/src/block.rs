@@ -0,0 +1,48 @@
+use chain::{Block, IndexedBlock};
+use test_data;
+
+use super::Benchmark;
+
+// number of transactions in the benchmarked block
+const TRANSACTIONS: usize = 2000;
+// how many times merkle root is requested for the same block
+const SAMPLES: usize = 100;
+
+fn block_with_transactions(transactions: usize) -> Block {
+	let mut builder = test_data::block_builder()
+		.transaction().coinbase().output().value(5000000000).build().build();
+	for t in 0..transactions {
+		builder = builder
+			.transaction()
+				.lock_time(t as u32)
+				.output().value(t as u64).build()
+				.build();
+	}
+
+	builder.merkled_header().build().build()
+}
+
+// every `Block::merkle_root` call rehashes all block transactions
+pub fn merkle_root(benchmark: &mut Benchmark) {
+	let block = block_with_transactions(TRANSACTIONS);
+
+	benchmark.samples(SAMPLES);
+	benchmark.start();
+	for _ in 0..SAMPLES {
+		assert_eq!(block.merkle_root(), block.block_header.merkle_root_hash);
+	}
+	benchmark.stop();
+}
+
+// `IndexedBlock` hashes transactions once, when it is created, and then reuses cached hashes
+pub fn merkle_root_indexed(benchmark: &mut Benchmark) {
+	let block = block_with_transactions(TRANSACTIONS);
+
+	benchmark.samples(SAMPLES);
+	benchmark.start();
+	let block = IndexedBlock::from_raw(block);
+	for _ in 0..SAMPLES {
+		assert_eq!(block.merkle_root(), block.header.raw.merkle_root_hash);
+	}
+	benchmark.stop();
+}
@@ -8,6 +8,7 @@ extern crate network;
 extern crate byteorder;
 extern crate primitives;
 
+mod block;
 mod database;
 mod verifier;
 
@@ -71,9 +72,13 @@ macro_rules! benchmark {
 }
 
 fn main() {
+	benchmark!(block::merkle_root);
+	benchmark!(block::merkle_root_indexed);
 	benchmark!(database::fetch);
 	benchmark!(database::write);
 	benchmark!(database::reorg_short);
 	benchmark!(database::write_heavy);
 	benchmark!(verifier::main);
+	benchmark!(verifier::single_thread);
+	benchmark!(verifier::four_threads);
 }
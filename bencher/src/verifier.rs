@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use db::BlockChainDatabase;
 use chain::IndexedBlock;
-use verification::{BackwardsCompatibleChainVerifier as ChainVerifier, Verify, VerificationLevel};
+use verification::{BackwardsCompatibleChainVerifier as ChainVerifier, ScriptVerificationPool, Verify, VerificationLevel};
 use network::{Network, ConsensusParams};
 use test_data;
 use byteorder::{LittleEndian, ByteOrder};
@@ -12,6 +12,20 @@ use super::Benchmark;
 // 2. verify <BLOCKS> blocks that has <TRANSACTIONS> transaction each with <OUTPUTS> output each,
 //    spending outputs from last <BLOCKS*TRANSACTIONS*OUTPUTS> blocks
 pub fn main(benchmark: &mut Benchmark) {
+	verify_blocks(benchmark, None);
+}
+
+// same as main, but block scripts are verified by the single worker
+pub fn single_thread(benchmark: &mut Benchmark) {
+	verify_blocks(benchmark, Some(1));
+}
+
+// same as main, but block scripts are verified by 4 workers
+pub fn four_threads(benchmark: &mut Benchmark) {
+	verify_blocks(benchmark, Some(4));
+}
+
+fn verify_blocks(benchmark: &mut Benchmark, script_verification_threads: Option<usize>) {
 	// params
 	const BLOCKS_INITIAL: usize = 200200;
 	const BLOCKS: usize = 10;
@@ -74,7 +88,7 @@ pub fn main(benchmark: &mut Benchmark) {
 			let mut tx_builder = builder.transaction();
 
 			for o in 0..OUTPUTS {
-				let parent_hash = blocks[(b*TRANSACTIONS*OUTPUTS + t * OUTPUTS + o)].transactions[0].hash.clone();
+				let parent_hash = blocks[(b*TRANSACTIONS*OUTPUTS + t * OUTPUTS + o)].transactions()[0].hash.clone();
 
 				tx_builder = tx_builder
 					.input()
@@ -99,7 +113,8 @@ pub fn main(benchmark: &mut Benchmark) {
 
 	assert_eq!(store.best_block().hash, rolling_hash);
 
-	let chain_verifier = ChainVerifier::new(store.clone(), consensus);
+	let chain_verifier = ChainVerifier::new(store.clone(), consensus)
+		.with_script_verification_pool(script_verification_threads.map(|threads| Arc::new(ScriptVerificationPool::new(threads))));
 
 	// bench
 	benchmark.start();
@@ -1,21 +1,43 @@
+use std::io;
 use hex::FromHex;
-use ser::{deserialize};
+use ser::{deserialize, serialize, Reader, Error as ReaderError, CompactInteger, Serializable, Deserializable};
+use constants::{WITNESS_SCALE_FACTOR, MAX_BLOCK_TRANSACTIONS};
+use merkle_root::merkle_root_with_mutation;
+use transaction::transaction_hash;
+use hash::H256;
 use {BlockHeader, Transaction};
 
-#[cfg(any(test, feature = "test-helpers"))]
-use hash::H256;
 #[cfg(any(test, feature = "test-helpers"))]
 use merkle_root::merkle_root;
 
-#[derive(Debug, PartialEq, Clone, Serializable, Deserializable)]
+/// Merkle root check error.
+#[derive(Debug, PartialEq)]
+pub enum MerkleError {
+	/// Merkle root of block transactions doesn't match the root from the header.
+	Mismatch,
+	/// Block transactions list contains duplicates, which make merkle tree ambiguous (CVE-2012-2459).
+	Mutated,
+}
+
+#[derive(Debug, PartialEq, Clone, Serializable)]
 pub struct Block {
 	pub block_header: BlockHeader,
 	pub transactions: Vec<Transaction>,
 }
 
+impl Deserializable for Block {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		// declared transactions count is checked before the list is allocated
+		Ok(Block {
+			block_header: reader.read()?,
+			transactions: reader.read_list_max(MAX_BLOCK_TRANSACTIONS)?,
+		})
+	}
+}
+
 impl From<&'static str> for Block {
 	fn from(s: &'static str) -> Self {
-		deserialize(&s.from_hex::<Vec<u8>>().unwrap() as &[u8]).unwrap()
+		Block::from_hex(s).unwrap()
 	}
 }
 
@@ -24,6 +46,36 @@ impl Block {
 		Block { block_header: header, transactions: transactions }
 	}
 
+	/// Parses block from hex-encoded serialized representation.
+	pub fn from_hex(s: &str) -> Result<Self, ReaderError> {
+		let data: Vec<u8> = s.from_hex().map_err(|err| ReaderError::InvalidFormat(format!("{}", err)))?;
+		Block::from_bytes(&data)
+	}
+
+	/// Parses block from serialized representation. All bytes must be consumed.
+	pub fn from_bytes(data: &[u8]) -> Result<Self, ReaderError> {
+		deserialize(data)
+	}
+
+	/// Reads block header and transactions count from the stream, leaving transactions unread.
+	/// Transactions are then read one-by-one by the returned iterator, so the whole block
+	/// is never buffered in memory.
+	pub fn read_header_then<R: io::Read>(reader: &mut R) -> Result<BlockTransactionsReader<R>, ReaderError> {
+		let mut reader = Reader::from_read(reader);
+		let header = reader.read()?;
+		let transactions_count: usize = reader.read::<CompactInteger>()?.into();
+		if transactions_count > MAX_BLOCK_TRANSACTIONS {
+			return Err(ReaderError::MalformedData);
+		}
+
+		Ok(BlockTransactionsReader {
+			reader: reader,
+			header: header,
+			transactions_count: transactions_count,
+			transactions_left: transactions_count,
+		})
+	}
+
 	/// Returns block's merkle root.
 	#[cfg(any(test, feature = "test-helpers"))]
 	pub fn merkle_root(&self) -> H256 {
@@ -31,6 +83,46 @@ impl Block {
 		merkle_root(&hashes)
 	}
 
+	/// Checks that merkle root from the header matches block transactions and that
+	/// transactions are not duplicated to produce the same root.
+	pub fn check_merkle_root(&self) -> Result<(), MerkleError> {
+		let hashes = self.transactions.iter().map(transaction_hash).collect::<Vec<H256>>();
+		let (merkle_root, mutated) = merkle_root_with_mutation(&hashes);
+		if merkle_root != self.block_header.merkle_root_hash {
+			return Err(MerkleError::Mismatch);
+		}
+		if mutated {
+			return Err(MerkleError::Mutated);
+		}
+
+		Ok(())
+	}
+
+	/// Returns size of the serialized block.
+	pub fn size(&self) -> usize {
+		serialize(self).len()
+	}
+
+	/// Returns block weight.
+	/// Zcash has no segregated witness, so every byte of the block (including Sapling
+	/// spend and output descriptions and binding signature) has the same weight.
+	pub fn weight(&self) -> usize {
+		let header_size = serialize(&self.block_header).len()
+			+ CompactInteger::from(self.transactions.len()).serialized_size();
+		header_size * WITNESS_SCALE_FACTOR + self.transactions.iter().map(transaction_weight).sum::<usize>()
+	}
+
+	/// Returns coinbase transaction of the block, if the first transaction is a coinbase.
+	pub fn coinbase(&self) -> Option<&Transaction> {
+		self.transactions.first().and_then(|tx| if tx.is_coinbase() { Some(tx) } else { None })
+	}
+
+	/// Returns block height, encoded in the coinbase signature script (BIP34).
+	/// Returns None if there's no coinbase or the height is not minimally encoded.
+	pub fn coinbase_height(&self) -> Option<u32> {
+		self.coinbase().and_then(|coinbase| decode_coinbase_height(&coinbase.inputs[0].script_sig))
+	}
+
 	pub fn transactions(&self) -> &[Transaction] {
 		&self.transactions
 	}
@@ -45,12 +137,182 @@ impl Block {
 	}
 }
 
+/// Decodes block height from the start of the coinbase signature script (BIP34).
+/// Heights 0..16 are encoded using OP_0..OP_16. Other heights are pushed as minimally-encoded
+/// positive script numbers. Returns None if script doesn't start with the height, encoded this way.
+pub fn decode_coinbase_height(script_sig: &[u8]) -> Option<u32> {
+	const OP_0: u8 = 0x00;
+	const OP_1: u8 = 0x51;
+	const OP_16: u8 = 0x60;
+	const MAX_HEIGHT_LEN: usize = 5;
+
+	let opcode = match script_sig.first() {
+		Some(opcode) => *opcode,
+		None => return None,
+	};
+
+	match opcode {
+		OP_0 => return Some(0),
+		OP_1...OP_16 => return Some((opcode - OP_1 + 1) as u32),
+		_ => (),
+	}
+
+	let len = opcode as usize;
+	if len > MAX_HEIGHT_LEN || script_sig.len() < len + 1 {
+		return None;
+	}
+
+	let data = &script_sig[1..len + 1];
+	let last = data[len - 1];
+	// negative numbers are not heights
+	if last & 0x80 != 0 {
+		return None;
+	}
+	// the most significant byte could only be zero if it is required for the sign bit
+	if last == 0 && (len == 1 || data[len - 2] & 0x80 == 0) {
+		return None;
+	}
+
+	let height = data.iter().rev().fold(0u64, |acc, byte| (acc << 8) | *byte as u64);
+	// heights 1..16 must be encoded using OP_1..OP_16
+	if height <= 16 || height > u32::max_value() as u64 {
+		return None;
+	}
+
+	Some(height as u32)
+}
+
+/// Returns transaction weight. There's no witness discount for any transaction part.
+fn transaction_weight(transaction: &Transaction) -> usize {
+	serialize(transaction).len() * WITNESS_SCALE_FACTOR
+}
+
+/// Lazily reads transactions of the block from the underlying stream.
+///
+/// Iteration stops after the declared number of transactions is read, so the stream is left
+/// positioned right after the block. A truncated or malformed transaction is reported as an
+/// error, after which iteration stops.
+pub struct BlockTransactionsReader<'a, R: 'a> {
+	reader: Reader<&'a mut R>,
+	header: BlockHeader,
+	transactions_count: usize,
+	transactions_left: usize,
+}
+
+impl<'a, R> BlockTransactionsReader<'a, R> where R: io::Read {
+	/// Header of the block.
+	pub fn header(&self) -> &BlockHeader {
+		&self.header
+	}
+
+	/// Number of transactions in the block, as declared in the stream.
+	pub fn transactions_count(&self) -> usize {
+		self.transactions_count
+	}
+}
+
+impl<'a, R> Iterator for BlockTransactionsReader<'a, R> where R: io::Read {
+	type Item = Result<Transaction, ReaderError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.transactions_left == 0 {
+			return None;
+		}
+
+		let transaction = self.reader.read();
+		match transaction {
+			Ok(_) => self.transactions_left -= 1,
+			Err(_) => self.transactions_left = 0,
+		}
+		Some(transaction)
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(0, Some(self.transactions_left))
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use hex::FromHex;
 	use hash::H256;
-	use ser::{serialize, deserialize};
-	use super::Block;
+	use ser::{serialize, deserialize, Stream, CompactInteger, Error as ReaderError};
+	use constants::MAX_BLOCK_TRANSACTIONS;
+	use {BlockHeader, Transaction, TransactionInput, TransactionOutput};
+	use merkle_root::{merkle_branch, verify_merkle_branch};
+	use super::{Block, MerkleError};
+
+	// https://zcash.blockexplorer.com/block/00000000007ef95f986ed8309d0ed6a1b6174c90b9c7f4d0dfc40f7147315e79
+	// block with Sapling transactions
+	const SAPLING_BLOCK: &'static str = "04000000bd3c4ad2cecf0fc1b26e72d31e9ccb4a61083c23ff78757579d35600000000006be99b7c4e1c7d02f8200fa106571b0e9e1fd0ff81a5e053d07c1f5f68bf56237d71967f4b31fbaaf221a958bbe3e6bc02cc3c3fbe2fed80657bd9fb14f3be12b72f1b5ce42f021c000000008c132a8040000924000000000000a500000000000000000000000000fd400500392d6c2bd1ee9fa99041f5f659645122bb2aa73a2a452959a796e296ced7550ec4dfee2a13995b2a180f28cd4b0f86c947138db3da1d4f530146e4beaa6a40fadd2b2f1e002f770f8422fe4c6a7a346959825c02f0510b1dd6209bbcc874c1b74d4ce6d7bb7fc6c4135d4406c6cdf8ea882fd493517baa4790e65cf4551f4547824b11e569d4c79376ca5d0c97128a399b9d4c8ff58df3d9e0c9f01449a9305047f6decc5cc2fb01838b3e74d71e9fc8e25033c34e3fea9116bb649f01dd076e925946cb17db403350714341b959ded59c1691a7df4419f209c902925934d05eadfbe4fd332e1a218988888b3353dfd498c320eda3fed9e0d83ad908b0379a1c5a35571f9b244e1c7be22e0c95d8f3ba2155357086e39f7d25ba649e8dcee3b19e495110ba27c50767a517c3a5b815a51ac5f744cdc020b6b7bd3183c65561dc765f4b57155c31ca89523f0196b3e702513de4fc8363dfc66981a5dc421e20a392f5487a0305cbcec10996dee18e572dc6412cf1ddf15d3f9306a00a54c416ae40e40735f01d77b65e99d398764d241e6bbfa073c1c1a4df648f72deb60bcddd3f9e740f9144935a4c00c9a08497588eee01166501f801b73d02baa90b11bda8ed6d970e453ea166eed51973ac377e939e4fdd5818f4b4087f135ee31abc1b57b89c3f0105c3cfa923bba62e361719415bb384c3fe214c0a2022d86fcd33bf3f41641484bfd71e018735ef8f535293683e99b61fb005f9dab277796a79695524491e0d5991a198fe4b2fe7e7ef3a5a0b6ea5277cb60e2dde8b65e8a40953f890e797aebd753f297bfa1a5912dc60e79091791734c05169cf649361dd94bf1f8355792540d8eab27d9aa70664d144644688e3df108720db673b33f38a4da641c66f0f673282690b7e2b84352f6c1ba491747cc4a4057838d1e2fa468138ba240139e038ebcc4669fedaa55938671551fd3b19479241d0737b38e3a955c243a625b1c8350265045df84405a760d0990af733301b04a7d96b7f55d7ffdea3e326caa4d057e4ee69841668e19d7813fe45309e90ea04d6a43caf926da543f8824230cf52f6d58f18d580063d013ea3ea5cfb6bca36014ed5a02a5adbdaf0e61c3295c7800ba2cf93c46355eaba2b21281c7656b92cd6f239b9ee4cdd72bda74038f8a2f30f81f9a7c7053a8b9c5e0efe690b5df69362fa61da37ab37c5f715c512ae44cc769af434124484af4b7165f3daeae5084e413aa94cc047511d310b5df82edde9e92fca5321d6d4e2d892aa429ab4f7cddef4e1ca906ffd4bfe0723d9334d430a09499752be299d8312787afc345c2032b6b9bbe24d2735a5f4d58a317f69ff31dc5121149b029973c6bc486e9e530c9c3c94e5810c9b2de642c38e4e78b040efeb0ab871557bbdca3e9a3d7fc5021416ed8ba514076260d3d1217efd7170a18eda291a59a7ec3d5c46295d4165d52866d19a1ed3bd39940f6c7a6e3f594b5cd124b22df95febe518c32b3f711f805f2ca7d3316325eaf2b7d628c7cd57549df3a8050d1f22df901ac1c004411fb4d60656224ad3cd8939956a62f9a5c953d14834b22c4ae68d35d81466b307b81206f6970189097428b1307602e251f55cfee43ac5ad87e812f756e848e41327490e2b2453bb7ce2054e47bd25d90c89f59672f6267895114eab31f0b50c4a7226bf0f7166c7d4b5e633ba01d29b507f84360bc51c7008a70c07cb45e26cc7a72da1b38ed69829393952942f1040199cdb6790294526ea6621b5637e05920c84346074c5b609424c24df9a10eda7ff59a7351af3ce2b0f0c01c64df46a7fe1a18ec81576aa740a6b10c5019bfebb4958d110fdc00df73554ddcdfe0e868c6e37a3b0b3e7a222ed6ce6b35e887b15a94a080400008085202f89010000000000000000000000000000000000000000000000000000000000000000ffffffff2a0360da060004b72f1b5ce475a064e5e716a5dc6c5714316d151882eac199a88b50caae6aaa5299bf29a0ffffffff025d5f9b3b000000001976a914130f06e8110c04122ebb08bb49e3453d206cf8e488ac80b2e60e0000000017a914e38130c1e0add9fb67365f61c6978f2c1891a5d387000000000000000000000000000000000000000400008085202f89014adba0e9f805f333511f1e97ec50e95f5b70c78fce34c593f784c96761663d80020000006a47304402202a0ef385827211421f7ad629ba7ddbfbca97682f098d8fde72f0d5764f273d97022017298fc7db27efd1499e55a28c33368f45b29919b9e2fa9ac50e03f5f64151d80121027b1c69c615ade0ac84a934333b36d1c344d77bcc51937868824335c01d25ec29feffffff02b46402b2550000001976a914ed9019d8252f78c7651da69b0adba3014518640b88ac1cd51206000000001976a914df1cfef1073f717a3c7ef433ab03b172565ae91188ac4eda060074da060000000000000000000000000400008085202f89016b46468486b84c3ce8a37a7d1bed68186b692a4a4bdad2314d0b3b8c3d63762dc70000006a4730440220449f8ffaa3346152bea1825a0ff560725540ac616faeda7f334985eb1919122a02207de01f4bf86adc49cb3df34279d883f4192e1f6a296cf240b0a5222a2758a0b60121022539acfb7d76a3d0ecce4278259f3678b4693a87cc6d58e5cd6e66098463c4830000000002600bb101000000001976a9146375971b222b3f18a48f77f92c97bc9338acfc5788ac9b0e0000000000001976a91401a4db5a5b93f1733fdd83746d70b010872a211588ac000000000000000000000000000000000000000400008085202f890128a359daa6090aaa87982c3592b768a9afafb60cd9b9e068aaf0fe2d405300d8000000006b48304502210092b794d3c292abd216265373faaa05c0d017a83acdf9435e6d60d6bcf7ed8bce02206fa6ec0f85cffd6d7c9157c3f4a7e3b6ac5f84664e90f847a4a5d17209119743012102de6af5046400c030ac05e18d187c29a6c40e741706403af97f3a40b719391476feffffff02c6972e44010000001976a914337681dded0736e6c5d26a1385c5ffa6d59beb3488ace06c1f07000000001976a9140c8c7f49860e97b14b62763039e014e825c078c188ac54da060073da060000000000000000000000000400008085202f8902a999deae50bd4b318b1811d443b7c906ef49d919da451bc211c830f7ec432b8e000000006b483045022100d42dfd57dd5ea076bb198cef55e51c2486ebeb037212aa60006b93fe08e8787102206ec26c097084f09e308ad5b9e3a5454a4b9c98ad30a142526879b49044d6b78601210255eb1549442b3e21cbb98213b62ab5340da8b7d173402d7c8cab16c6659fa7f1feffffffb90eeef1500ee2e1a4a46c4bc1a999a4f434db2d6dd7fbe8878b560dcaef03ae000000006a47304402200c11af18aab29245a5ca33e1e02e06d40e3c36bc303d13bcdd106d4489d4ff9f0220718f8741adfc078ba48ccf790de2f64954e218c00ec7c21503d0df41127369870121039d9a916b2b3ae6d0abd556aec9a3a81e54049b9b123aa977e84cd00501039479feffffff020b5b5600000000001976a91444915cdec4626362e51f03045198d9c163152f0388ac4e751200000000001976a914a950d1a0d291b81e3f55a205efe415219080a4a388ac55da060074da060000000000000000000000000400008085202f890001f8680e2e030000001976a914849d7fb393d294aa4cbc3b73591ad3da95e22b1988ac0000000067da060008900e2e0300000001251bb2e003abe1421823eb492fe85ce17a8cf4f6076f34fa5d94772b143057708facfb01cde6f5723e76f8d4c4fab73517f32c2a8f1fb64ceb9f2978af40024c62c69a3890b6156fa8a6378086981bf8ceec2ece0e6cf41b9b34b25352bcd1caa9a5107e0cdce1c56433da4600169bf86f8d088c6243fc172e6626f43373660298157fdc1f0c96dcbd312ebe97b0845c7d4b51d9190b42f4347f7802556064902eacd8264d94b3dcadb14f4c345a6b38b1d80220c6440b708f8f4078f0de3c09f2b2f1a76df4633a07e2d96db6e67ab879729818d9b9d3e84f4505ee0b73daaf02cd848ac7acdce3708544aaf51f07544495ec162f9ab74f3efca1635ed23197568b44f1a266210195369a8959cfba2da3532ec89c50598ab1ff2a839cc2ad2e339e446b224e0530f15514be0f93ea1b7c70fa67787c6a9aac3da402808f8df76daf656d1a351c61c509ea0015c98de8474d7d610e5f8a8e138b135d695e80ef6322dbf389a7af048e6c1c7d821109ca57a86a90c5145feb2eb56d8e15b4ca030000fb748212e9081c616430f441a2a757d8a93fc8b0cd93d6121db78dae700dd148c70c8220bd34dd036d750f818b8bec08dda1408ee7ee35eda8b4f7f0b902e1020400008085202f8900000000000072da060010270000000000000148b1c0668fce604361fbb1b89bbd76f8fee09b51a9dc0fdfcf6c6720cd596083d970234fcc0e9a70fdfed82d32fbb9ca92c9c5c3bad5daad9ac62b5bf4255817ee5bc95a9af453bb9cc7e2c544aa29efa20011a65b624998369c849aa8f0bc83d60e7902a3cfe6eeaeb8d583a491de5982c5ded29e64cd8f8fac594a5bb4f2838e6c30876e36a18d8d935238815c8d9205a4f1f523ff76b51f614bff1064d1c5fa0a27ec0c43c8a6c2714e7234d32e9a8934a3e9c0f74f1fdac2ddf6be3b13bc933b0478cae556a2d387cc23b05e8b0bd53d9e838ad2d2cb31daccefe256087511b044dfae665f0af0fa968edeea4cbb437a8099724159471adf7946eec434cccc1129f4d1e31d7f3f8be524226c65f28897d3604c14efb64bea6a889b2705617432927229dfa382e78c0ace31cc158fbf3ec1597242955e45af1ee5cfaffd789cc80dc53d6b18d42033ec2c327170e2811fe8ec00feadeb1033eb48ab24a6dce2480ad428be57c4619466fc3181ece69b914fed30566ff853250ef19ef7370601f4c24b0125e4059eec61f63ccbe277363172f2bdee384412ea073c5aca06b94e402ba3a43e15bd9c65bbfb194c561c24a031dec43be95c59eb6b568c176b1038d5b7b057dc032488335284adebfb6607e6a995b7fa418f13c8a61b343e5df44faa1050d9d76550748d9efebe01da97ade5937afd5f007ed26e0af03f283611655e91bc6a4857f66a57a1584ff687c4baf725f4a1b32fae53a3e6e8b98bca319bb1badb704c9c1a04f401f33d813d605eef6943c2c52dbc85ab7081d1f8f69d3202aae281bf42336a949a12a7dbbd22abdd6e92996282ebd69033c22cb0539d97f83636d6a8232209a7411e8b03bef180d83e608563ea2d0becff56dc996c2049df054961bfb21b7cbef5049a7dacc18f2c977aa1b2d48291abc19c3c8ea25d2e61901048354b17ce952f6f2248cf3a0eb54c19b507b41d7281c3d227e2b142ff695d8b925a4bb942ed9492a73a17468a8332a367fd16295420bdca6c04d380271f40440709998fce3a3af3e1e505f5402e5dd464dd179cb0eede3d494a95b84d2fb2eb5abb425cf2c712af999c65259c4782a5ec97388324c67738908a5ba43b6db62a10f50cddf9b5039123437c74165921ac8cf4f13292a216baef9d00bd544106b52755986c98a462ade1149f69367e926d88eb92798c0e56cd19a1bcf264fd93293033b758da65c7901eb5b4a17ee265a3312dbc477868da0057e1b3cbf47726dead6ecfcc8e1044c6f311ff0fc83192dc2f75a89626ba33364dac747b63ff3c8337e00332c8783ba9c8dc13cdf0750d7adc3926fbe1279017d50adba35c38c5b810f73abe5d759cd7fb650f6b0a1f78dc1f62fd017090ff4de4cf54c883752ddda68083d4617ed2c38bab8da313965dd3f7b755aec23a2d9e2965d08d2134827a72ffb3bd65b1fd5410da105bfba7a74ddff0928a654aca1ee211ac9dce8019ddcbb52263ce44b2544a314355c1e8c8543f3ed3e883e7a7a8f9e3c7c11f41ab9069854fb21e9b3660a860df19d289d54b29d82522b32d187cde6261eb0a429c3994dff6f37b9ab9102281223e3cd584790a909e05ba0ea1a2d9aef8e571986e98e09312dccaf8e739d718a1edd217dc4c8a5c8a650015405b592a7c674a451d7d1686c7ea6d93e74a8fe4ade12b679ac780457f08a79bfbf96dcf7eefe9a39b99f1ae39d2c5f86aadf156b7d5ce4b2733f307cfe1e1ff6de0ff2006d9cba535b0c40dfb7a98399cdff8e681fc38c7b9aa94ee5eb89432e28d94ee27f238776ba964a87caf58eddbb64771e64de094305a8eb848d2d9ad6373903687d22170f48f1ae8d714514034ee2733857af4747312bb006e6ce3918ede8c730bacc7821b81c1b93bb50b219e79e8e0d74531ed18c1145632d9847d38783b49141ac5353aaa7d125fb2934e681467e16b28090978e74e0b0400008085202f89032d9418dc7d2882fe6be0ed319a1c549f673a622f071bde5eb52f55bdc54f549b2e0100006b483045022100d459951782ab9179738629855d970fe71a0164b3e873f541f4d8437892ebfbc4022007dd68f95d6095ce1c7437af3a0478e70449027410911b5d1cd0e11bd27d7ded012102c72bc522a7d79e6697ec01ff7c35daf3e5ee7c73ceb9b58d5af7d9814f61031dffffffff7e850495c92512a2cfe21f7cf26e1eff4a03ddb26e2d01f252995ba07382911e010000006b483045022100e02e454dad48d9f1700bdead87c33db9a2495275279a252c8348940eab53e82702205d92c7741067d68bad90dd12562a4a192ae33dd6cfd6fe930be7edc867e9c1d0012102c72bc522a7d79e6697ec01ff7c35daf3e5ee7c73ceb9b58d5af7d9814f61031dffffffffebc0256e10a516907f7442877219d20573728055033d6d64661f494e825a2775010000006b483045022100ffe35271c58cfc9584c883e8aeb5b71a51082ba6c775f9c13424b1ee11e5f01e02200e305d36736d1f18743b8d21885b26e0cbc4b08d856820a1baf0251df98010ed012102c72bc522a7d79e6697ec01ff7c35daf3e5ee7c73ceb9b58d5af7d9814f61031dffffffff02e0561d01000000001976a914535b4f24926d5e1df9ae606fcf27ad73e41888c688acde150000000000001976a914587dea6af9830d207c81290d578777b67a34c71f88ac00000000ff64cd1d0000000000000000000000";
+
+	fn test_block(time: u32, outputs: u64) -> Block {
+		let header = BlockHeader {
+			version: 4,
+			previous_header_hash: H256::from(1),
+			merkle_root_hash: H256::from(2),
+			final_sapling_root: H256::from(3),
+			time: time,
+			bits: 5.into(),
+			nonce: H256::from(6),
+			solution: Default::default(),
+		};
+		let transactions = (0..outputs).map(|value| Transaction {
+			version: 1,
+			inputs: vec![TransactionInput::coinbase(vec![value as u8; 2].into())],
+			outputs: vec![TransactionOutput { value: value, script_pubkey: Default::default() }],
+			..Default::default()
+		}).collect();
+		Block::new(header, transactions)
+	}
+
+	#[test]
+	fn test_block_read_header_then_transactions() {
+		let block1 = test_block(100, 3);
+		let block2 = test_block(200, 2);
+		let mut stream = serialize(&block1).take();
+		stream.extend(serialize(&block2).take());
+
+		// blocks are read one after another from the same stream
+		let mut reader = &stream as &[u8];
+		for block in vec![block1, block2] {
+			let transactions = Block::read_header_then(&mut reader).unwrap();
+			assert_eq!(transactions.header(), &block.block_header);
+			assert_eq!(transactions.transactions_count(), block.transactions.len());
+			let read = transactions.collect::<Result<Vec<_>, _>>().unwrap();
+			assert_eq!(read, block.transactions);
+		}
+		assert!(reader.is_empty());
+	}
+
+	#[test]
+	fn test_block_read_header_then_truncated_transaction() {
+		let block = test_block(100, 3);
+		let mut stream = serialize(&block).take();
+		let len = stream.len();
+		stream.truncate(len - 1);
+
+		let mut reader = &stream as &[u8];
+		let mut transactions = Block::read_header_then(&mut reader).unwrap();
+		assert_eq!(transactions.next(), Some(Ok(block.transactions[0].clone())));
+		assert_eq!(transactions.next(), Some(Ok(block.transactions[1].clone())));
+		assert_eq!(transactions.next(), Some(Err(ReaderError::UnexpectedEnd)));
+		assert_eq!(transactions.next(), None);
+	}
+
+	#[test]
+	fn test_block_rejects_too_many_transactions() {
+		let block = test_block(100, 0);
+		let mut stream = Stream::default();
+		stream
+			.append(&block.block_header)
+			.append(&CompactInteger::from(MAX_BLOCK_TRANSACTIONS + 1));
+		let data = stream.out();
+
+		assert_eq!(deserialize::<_, Block>(&data as &[u8]), Err(ReaderError::MalformedData));
+		let mut reader = &data as &[u8];
+		assert_eq!(Block::read_header_then(&mut reader).err(), Some(ReaderError::MalformedData));
+	}
 
 	#[test]
 	fn test_block_parse() {
@@ -72,7 +334,7 @@ mod tests {
 			(
 				// https://zcash.blockexplorer.com/block/00000000007ef95f986ed8309d0ed6a1b6174c90b9c7f4d0dfc40f7147315e79
 				// https://zcash.blockexplorer.com/api/rawblock/00000000007ef95f986ed8309d0ed6a1b6174c90b9c7f4d0dfc40f7147315e79
-				"04000000bd3c4ad2cecf0fc1b26e72d31e9ccb4a61083c23ff78757579d35600000000006be99b7c4e1c7d02f8200fa106571b0e9e1fd0ff81a5e053d07c1f5f68bf56237d71967f4b31fbaaf221a958bbe3e6bc02cc3c3fbe2fed80657bd9fb14f3be12b72f1b5ce42f021c000000008c132a8040000924000000000000a500000000000000000000000000fd400500392d6c2bd1ee9fa99041f5f659645122bb2aa73a2a452959a796e296ced7550ec4dfee2a13995b2a180f28cd4b0f86c947138db3da1d4f530146e4beaa6a40fadd2b2f1e002f770f8422fe4c6a7a346959825c02f0510b1dd6209bbcc874c1b74d4ce6d7bb7fc6c4135d4406c6cdf8ea882fd493517baa4790e65cf4551f4547824b11e569d4c79376ca5d0c97128a399b9d4c8ff58df3d9e0c9f01449a9305047f6decc5cc2fb01838b3e74d71e9fc8e25033c34e3fea9116bb649f01dd076e925946cb17db403350714341b959ded59c1691a7df4419f209c902925934d05eadfbe4fd332e1a218988888b3353dfd498c320eda3fed9e0d83ad908b0379a1c5a35571f9b244e1c7be22e0c95d8f3ba2155357086e39f7d25ba649e8dcee3b19e495110ba27c50767a517c3a5b815a51ac5f744cdc020b6b7bd3183c65561dc765f4b57155c31ca89523f0196b3e702513de4fc8363dfc66981a5dc421e20a392f5487a0305cbcec10996dee18e572dc6412cf1ddf15d3f9306a00a54c416ae40e40735f01d77b65e99d398764d241e6bbfa073c1c1a4df648f72deb60bcddd3f9e740f9144935a4c00c9a08497588eee01166501f801b73d02baa90b11bda8ed6d970e453ea166eed51973ac377e939e4fdd5818f4b4087f135ee31abc1b57b89c3f0105c3cfa923bba62e361719415bb384c3fe214c0a2022d86fcd33bf3f41641484bfd71e018735ef8f535293683e99b61fb005f9dab277796a79695524491e0d5991a198fe4b2fe7e7ef3a5a0b6ea5277cb60e2dde8b65e8a40953f890e797aebd753f297bfa1a5912dc60e79091791734c05169cf649361dd94bf1f8355792540d8eab27d9aa70664d144644688e3df108720db673b33f38a4da641c66f0f673282690b7e2b84352f6c1ba491747cc4a4057838d1e2fa468138ba240139e038ebcc4669fedaa55938671551fd3b19479241d0737b38e3a955c243a625b1c8350265045df84405a760d0990af733301b04a7d96b7f55d7ffdea3e326caa4d057e4ee69841668e19d7813fe45309e90ea04d6a43caf926da543f8824230cf52f6d58f18d580063d013ea3ea5cfb6bca36014ed5a02a5adbdaf0e61c3295c7800ba2cf93c46355eaba2b21281c7656b92cd6f239b9ee4cdd72bda74038f8a2f30f81f9a7c7053a8b9c5e0efe690b5df69362fa61da37ab37c5f715c512ae44cc769af434124484af4b7165f3daeae5084e413aa94cc047511d310b5df82edde9e92fca5321d6d4e2d892aa429ab4f7cddef4e1ca906ffd4bfe0723d9334d430a09499752be299d8312787afc345c2032b6b9bbe24d2735a5f4d58a317f69ff31dc5121149b029973c6bc486e9e530c9c3c94e5810c9b2de642c38e4e78b040efeb0ab871557bbdca3e9a3d7fc5021416ed8ba514076260d3d1217efd7170a18eda291a59a7ec3d5c46295d4165d52866d19a1ed3bd39940f6c7a6e3f594b5cd124b22df95febe518c32b3f711f805f2ca7d3316325eaf2b7d628c7cd57549df3a8050d1f22df901ac1c004411fb4d60656224ad3cd8939956a62f9a5c953d14834b22c4ae68d35d81466b307b81206f6970189097428b1307602e251f55cfee43ac5ad87e812f756e848e41327490e2b2453bb7ce2054e47bd25d90c89f59672f6267895114eab31f0b50c4a7226bf0f7166c7d4b5e633ba01d29b507f84360bc51c7008a70c07cb45e26cc7a72da1b38ed69829393952942f1040199cdb6790294526ea6621b5637e05920c84346074c5b609424c24df9a10eda7ff59a7351af3ce2b0f0c01c64df46a7fe1a18ec81576aa740a6b10c5019bfebb4958d110fdc00df73554ddcdfe0e868c6e37a3b0b3e7a222ed6ce6b35e887b15a94a080400008085202f89010000000000000000000000000000000000000000000000000000000000000000ffffffff2a0360da060004b72f1b5ce475a064e5e716a5dc6c5714316d151882eac199a88b50caae6aaa5299bf29a0ffffffff025d5f9b3b000000001976a914130f06e8110c04122ebb08bb49e3453d206cf8e488ac80b2e60e0000000017a914e38130c1e0add9fb67365f61c6978f2c1891a5d387000000000000000000000000000000000000000400008085202f89014adba0e9f805f333511f1e97ec50e95f5b70c78fce34c593f784c96761663d80020000006a47304402202a0ef385827211421f7ad629ba7ddbfbca97682f098d8fde72f0d5764f273d97022017298fc7db27efd1499e55a28c33368f45b29919b9e2fa9ac50e03f5f64151d80121027b1c69c615ade0ac84a934333b36d1c344d77bcc51937868824335c01d25ec29feffffff02b46402b2550000001976a914ed9019d8252f78c7651da69b0adba3014518640b88ac1cd51206000000001976a914df1cfef1073f717a3c7ef433ab03b172565ae91188ac4eda060074da060000000000000000000000000400008085202f89016b46468486b84c3ce8a37a7d1bed68186b692a4a4bdad2314d0b3b8c3d63762dc70000006a4730440220449f8ffaa3346152bea1825a0ff560725540ac616faeda7f334985eb1919122a02207de01f4bf86adc49cb3df34279d883f4192e1f6a296cf240b0a5222a2758a0b60121022539acfb7d76a3d0ecce4278259f3678b4693a87cc6d58e5cd6e66098463c4830000000002600bb101000000001976a9146375971b222b3f18a48f77f92c97bc9338acfc5788ac9b0e0000000000001976a91401a4db5a5b93f1733fdd83746d70b010872a211588ac000000000000000000000000000000000000000400008085202f890128a359daa6090aaa87982c3592b768a9afafb60cd9b9e068aaf0fe2d405300d8000000006b48304502210092b794d3c292abd216265373faaa05c0d017a83acdf9435e6d60d6bcf7ed8bce02206fa6ec0f85cffd6d7c9157c3f4a7e3b6ac5f84664e90f847a4a5d17209119743012102de6af5046400c030ac05e18d187c29a6c40e741706403af97f3a40b719391476feffffff02c6972e44010000001976a914337681dded0736e6c5d26a1385c5ffa6d59beb3488ace06c1f07000000001976a9140c8c7f49860e97b14b62763039e014e825c078c188ac54da060073da060000000000000000000000000400008085202f8902a999deae50bd4b318b1811d443b7c906ef49d919da451bc211c830f7ec432b8e000000006b483045022100d42dfd57dd5ea076bb198cef55e51c2486ebeb037212aa60006b93fe08e8787102206ec26c097084f09e308ad5b9e3a5454a4b9c98ad30a142526879b49044d6b78601210255eb1549442b3e21cbb98213b62ab5340da8b7d173402d7c8cab16c6659fa7f1feffffffb90eeef1500ee2e1a4a46c4bc1a999a4f434db2d6dd7fbe8878b560dcaef03ae000000006a47304402200c11af18aab29245a5ca33e1e02e06d40e3c36bc303d13bcdd106d4489d4ff9f0220718f8741adfc078ba48ccf790de2f64954e218c00ec7c21503d0df41127369870121039d9a916b2b3ae6d0abd556aec9a3a81e54049b9b123aa977e84cd00501039479feffffff020b5b5600000000001976a91444915cdec4626362e51f03045198d9c163152f0388ac4e751200000000001976a914a950d1a0d291b81e3f55a205efe415219080a4a388ac55da060074da060000000000000000000000000400008085202f890001f8680e2e030000001976a914849d7fb393d294aa4cbc3b73591ad3da95e22b1988ac0000000067da060008900e2e0300000001251bb2e003abe1421823eb492fe85ce17a8cf4f6076f34fa5d94772b143057708facfb01cde6f5723e76f8d4c4fab73517f32c2a8f1fb64ceb9f2978af40024c62c69a3890b6156fa8a6378086981bf8ceec2ece0e6cf41b9b34b25352bcd1caa9a5107e0cdce1c56433da4600169bf86f8d088c6243fc172e6626f43373660298157fdc1f0c96dcbd312ebe97b0845c7d4b51d9190b42f4347f7802556064902eacd8264d94b3dcadb14f4c345a6b38b1d80220c6440b708f8f4078f0de3c09f2b2f1a76df4633a07e2d96db6e67ab879729818d9b9d3e84f4505ee0b73daaf02cd848ac7acdce3708544aaf51f07544495ec162f9ab74f3efca1635ed23197568b44f1a266210195369a8959cfba2da3532ec89c50598ab1ff2a839cc2ad2e339e446b224e0530f15514be0f93ea1b7c70fa67787c6a9aac3da402808f8df76daf656d1a351c61c509ea0015c98de8474d7d610e5f8a8e138b135d695e80ef6322dbf389a7af048e6c1c7d821109ca57a86a90c5145feb2eb56d8e15b4ca030000fb748212e9081c616430f441a2a757d8a93fc8b0cd93d6121db78dae700dd148c70c8220bd34dd036d750f818b8bec08dda1408ee7ee35eda8b4f7f0b902e1020400008085202f8900000000000072da060010270000000000000148b1c0668fce604361fbb1b89bbd76f8fee09b51a9dc0fdfcf6c6720cd596083d970234fcc0e9a70fdfed82d32fbb9ca92c9c5c3bad5daad9ac62b5bf4255817ee5bc95a9af453bb9cc7e2c544aa29efa20011a65b624998369c849aa8f0bc83d60e7902a3cfe6eeaeb8d583a491de5982c5ded29e64cd8f8fac594a5bb4f2838e6c30876e36a18d8d935238815c8d9205a4f1f523ff76b51f614bff1064d1c5fa0a27ec0c43c8a6c2714e7234d32e9a8934a3e9c0f74f1fdac2ddf6be3b13bc933b0478cae556a2d387cc23b05e8b0bd53d9e838ad2d2cb31daccefe256087511b044dfae665f0af0fa968edeea4cbb437a8099724159471adf7946eec434cccc1129f4d1e31d7f3f8be524226c65f28897d3604c14efb64bea6a889b2705617432927229dfa382e78c0ace31cc158fbf3ec1597242955e45af1ee5cfaffd789cc80dc53d6b18d42033ec2c327170e2811fe8ec00feadeb1033eb48ab24a6dce2480ad428be57c4619466fc3181ece69b914fed30566ff853250ef19ef7370601f4c24b0125e4059eec61f63ccbe277363172f2bdee384412ea073c5aca06b94e402ba3a43e15bd9c65bbfb194c561c24a031dec43be95c59eb6b568c176b1038d5b7b057dc032488335284adebfb6607e6a995b7fa418f13c8a61b343e5df44faa1050d9d76550748d9efebe01da97ade5937afd5f007ed26e0af03f283611655e91bc6a4857f66a57a1584ff687c4baf725f4a1b32fae53a3e6e8b98bca319bb1badb704c9c1a04f401f33d813d605eef6943c2c52dbc85ab7081d1f8f69d3202aae281bf42336a949a12a7dbbd22abdd6e92996282ebd69033c22cb0539d97f83636d6a8232209a7411e8b03bef180d83e608563ea2d0becff56dc996c2049df054961bfb21b7cbef5049a7dacc18f2c977aa1b2d48291abc19c3c8ea25d2e61901048354b17ce952f6f2248cf3a0eb54c19b507b41d7281c3d227e2b142ff695d8b925a4bb942ed9492a73a17468a8332a367fd16295420bdca6c04d380271f40440709998fce3a3af3e1e505f5402e5dd464dd179cb0eede3d494a95b84d2fb2eb5abb425cf2c712af999c65259c4782a5ec97388324c67738908a5ba43b6db62a10f50cddf9b5039123437c74165921ac8cf4f13292a216baef9d00bd544106b52755986c98a462ade1149f69367e926d88eb92798c0e56cd19a1bcf264fd93293033b758da65c7901eb5b4a17ee265a3312dbc477868da0057e1b3cbf47726dead6ecfcc8e1044c6f311ff0fc83192dc2f75a89626ba33364dac747b63ff3c8337e00332c8783ba9c8dc13cdf0750d7adc3926fbe1279017d50adba35c38c5b810f73abe5d759cd7fb650f6b0a1f78dc1f62fd017090ff4de4cf54c883752ddda68083d4617ed2c38bab8da313965dd3f7b755aec23a2d9e2965d08d2134827a72ffb3bd65b1fd5410da105bfba7a74ddff0928a654aca1ee211ac9dce8019ddcbb52263ce44b2544a314355c1e8c8543f3ed3e883e7a7a8f9e3c7c11f41ab9069854fb21e9b3660a860df19d289d54b29d82522b32d187cde6261eb0a429c3994dff6f37b9ab9102281223e3cd584790a909e05ba0ea1a2d9aef8e571986e98e09312dccaf8e739d718a1edd217dc4c8a5c8a650015405b592a7c674a451d7d1686c7ea6d93e74a8fe4ade12b679ac780457f08a79bfbf96dcf7eefe9a39b99f1ae39d2c5f86aadf156b7d5ce4b2733f307cfe1e1ff6de0ff2006d9cba535b0c40dfb7a98399cdff8e681fc38c7b9aa94ee5eb89432e28d94ee27f238776ba964a87caf58eddbb64771e64de094305a8eb848d2d9ad6373903687d22170f48f1ae8d714514034ee2733857af4747312bb006e6ce3918ede8c730bacc7821b81c1b93bb50b219e79e8e0d74531ed18c1145632d9847d38783b49141ac5353aaa7d125fb2934e681467e16b28090978e74e0b0400008085202f89032d9418dc7d2882fe6be0ed319a1c549f673a622f071bde5eb52f55bdc54f549b2e0100006b483045022100d459951782ab9179738629855d970fe71a0164b3e873f541f4d8437892ebfbc4022007dd68f95d6095ce1c7437af3a0478e70449027410911b5d1cd0e11bd27d7ded012102c72bc522a7d79e6697ec01ff7c35daf3e5ee7c73ceb9b58d5af7d9814f61031dffffffff7e850495c92512a2cfe21f7cf26e1eff4a03ddb26e2d01f252995ba07382911e010000006b483045022100e02e454dad48d9f1700bdead87c33db9a2495275279a252c8348940eab53e82702205d92c7741067d68bad90dd12562a4a192ae33dd6cfd6fe930be7edc867e9c1d0012102c72bc522a7d79e6697ec01ff7c35daf3e5ee7c73ceb9b58d5af7d9814f61031dffffffffebc0256e10a516907f7442877219d20573728055033d6d64661f494e825a2775010000006b483045022100ffe35271c58cfc9584c883e8aeb5b71a51082ba6c775f9c13424b1ee11e5f01e02200e305d36736d1f18743b8d21885b26e0cbc4b08d856820a1baf0251df98010ed012102c72bc522a7d79e6697ec01ff7c35daf3e5ee7c73ceb9b58d5af7d9814f61031dffffffff02e0561d01000000001976a914535b4f24926d5e1df9ae606fcf27ad73e41888c688acde150000000000001976a914587dea6af9830d207c81290d578777b67a34c71f88ac00000000ff64cd1d0000000000000000000000",
+				SAPLING_BLOCK,
 				"00000000007ef95f986ed8309d0ed6a1b6174c90b9c7f4d0dfc40f7147315e79",
 				"2356bf685f1f7cd053e0a581ffd01f9e0e1b5706a10f20f8027d1c4e7c9be96b",
 			)
@@ -94,4 +356,101 @@ mod tests {
 			assert_eq!(origin_merkle_root, parsed.merkle_root());
 		}
 	}
+
+	#[test]
+	fn test_block_size_and_weight() {
+		let block: Block = SAPLING_BLOCK.into();
+		assert!(block.transactions.iter().any(|tx| tx.sapling.as_ref().map(|s| !s.outputs.is_empty()).unwrap_or(false)));
+		assert_eq!(block.size(), SAPLING_BLOCK.len() / 2);
+		assert_eq!(block.weight(), block.size() * 4);
+	}
+
+	#[test]
+	fn test_block_check_merkle_root() {
+		let mut block = test_block(100, 3);
+		assert_eq!(block.check_merkle_root(), Err(MerkleError::Mismatch));
+
+		block.block_header.merkle_root_hash = block.merkle_root();
+		assert_eq!(block.check_merkle_root(), Ok(()));
+
+		// padding the tree with the copy of the last transaction keeps the root, but mutates the block
+		let last = block.transactions[2].clone();
+		block.transactions.push(last);
+		assert_eq!(block.merkle_root(), block.block_header.merkle_root_hash);
+		assert_eq!(block.check_merkle_root(), Err(MerkleError::Mutated));
+
+		let parsed: Block = SAPLING_BLOCK.into();
+		assert_eq!(parsed.check_merkle_root(), Ok(()));
+	}
+
+	#[test]
+	fn test_block_from_hex() {
+		let block = Block::from_hex(SAPLING_BLOCK).unwrap();
+		assert_eq!(block, SAPLING_BLOCK.into());
+		assert_eq!(Block::from_bytes(&serialize(&block)), Ok(block));
+
+		// not a hex
+		match Block::from_hex("zz") {
+			Err(ReaderError::InvalidFormat(_)) => (),
+			result => panic!("unexpected result: {:?}", result),
+		}
+		// truncated block
+		assert_eq!(Block::from_hex(&SAPLING_BLOCK[..200]), Err(ReaderError::UnexpectedEnd));
+		// trailing data
+		assert_eq!(Block::from_hex(&format!("{}00", SAPLING_BLOCK)), Err(ReaderError::UnreadData));
+	}
+
+	#[test]
+	fn test_block_coinbase_height() {
+		// https://zcash.blockexplorer.com/block/00000000007ef95f986ed8309d0ed6a1b6174c90b9c7f4d0dfc40f7147315e79
+		let block: Block = SAPLING_BLOCK.into();
+		assert_eq!(block.coinbase(), Some(&block.transactions[0]));
+		assert_eq!(block.coinbase_height(), Some(449_120));
+
+		let block_with_script_sig = |script_sig: &'static str| {
+			let mut block = test_block(0, 1);
+			block.transactions[0].inputs[0].script_sig = script_sig.into();
+			block
+		};
+
+		// OP_0 && OP_1..OP_16
+		assert_eq!(block_with_script_sig("00").coinbase_height(), Some(0));
+		assert_eq!(block_with_script_sig("5100").coinbase_height(), Some(1));
+		assert_eq!(block_with_script_sig("60").coinbase_height(), Some(16));
+		// minimal pushes
+		assert_eq!(block_with_script_sig("0111").coinbase_height(), Some(17));
+		assert_eq!(block_with_script_sig("028000").coinbase_height(), Some(128));
+		assert_eq!(block_with_script_sig("03ffff00").coinbase_height(), Some(65_535));
+		// non-minimal pushes
+		assert_eq!(block_with_script_sig("0110").coinbase_height(), None);
+		assert_eq!(block_with_script_sig("021100").coinbase_height(), None);
+		assert_eq!(block_with_script_sig("0400000000").coinbase_height(), None);
+		// negative number
+		assert_eq!(block_with_script_sig("0181").coinbase_height(), None);
+		// truncated push
+		assert_eq!(block_with_script_sig("0311").coinbase_height(), None);
+		// empty script
+		assert_eq!(block_with_script_sig("").coinbase_height(), None);
+
+		// no coinbase
+		let mut block = test_block(0, 1);
+		block.transactions[0].inputs[0].previous_output.index = 0;
+		assert_eq!(block.coinbase(), None);
+		assert_eq!(block.coinbase_height(), None);
+	}
+
+	#[test]
+	fn test_block_merkle_branch() {
+		let block: Block = SAPLING_BLOCK.into();
+		assert!(block.transactions.len() > 2);
+
+		let root = block.merkle_root();
+		assert_eq!(root, block.block_header.merkle_root_hash);
+
+		let hashes: Vec<H256> = block.transactions.iter().map(Transaction::hash).collect();
+		for (index, hash) in hashes.iter().enumerate() {
+			let branch = merkle_branch(&hashes, index);
+			assert!(verify_merkle_branch(hash, &branch, index, hashes.len(), &root));
+		}
+	}
 }
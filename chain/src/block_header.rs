@@ -1,8 +1,9 @@
 use std::fmt;
 use hex::FromHex;
-use ser::{deserialize, serialize};
-use crypto::dhash256;
+use ser::{deserialize, serialize_to};
+use crypto::DHash256;
 use compact::Compact;
+use bigint::U256;
 use hash::H256;
 use primitives::bytes::Bytes;
 use solution::EquihashSolution;
@@ -27,6 +28,33 @@ impl BlockHeader {
 		block_header_hash(self)
 	}
 
+	/// Returns target, encoded in the `bits` field. Header hash must not exceed this value.
+	/// Returns None if `bits` encode negative, overflowed or zero target.
+	pub fn target(&self) -> Option<U256> {
+		match self.bits.to_u256() {
+			Ok(target) if !target.is_zero() => Some(target),
+			_ => None,
+		}
+	}
+
+	/// Returns work of the block header: expected number of hashes, required to find
+	/// header with the same target, i.e. 2**256 / (target + 1). Returns None if target is invalid.
+	pub fn work(&self) -> Option<U256> {
+		self.target().map(|target| {
+			// 2**256 / (target + 1) == ~target / (target + 1) + 1
+			let one = U256::from(1u64);
+			if target == U256::max_value() {
+				return one;
+			}
+			(U256::max_value() - target) / (target + one) + one
+		})
+	}
+
+	/// Returns Equihash solution of the block header.
+	pub fn equihash_solution(&self) -> &[u8] {
+		self.solution.as_ref()
+	}
+
 	pub fn equihash_input(&self) -> Bytes {
 		let mut stream = Stream::new();
 		stream.append(&self.version)
@@ -62,13 +90,18 @@ impl From<&'static str> for BlockHeader {
 
 /// Compute hash of the block header.
 pub(crate) fn block_header_hash(block_header: &BlockHeader) -> H256 {
-	dhash256(&serialize(block_header))
+	let mut hasher = DHash256::new();
+	serialize_to(block_header, &mut hasher);
+	hasher.finish()
 }
 
 #[cfg(test)]
 mod tests {
-	use ser::{Reader, Error as ReaderError, Stream};
+	use std::io::Write;
+	use crypto::{dhash256, DHash256};
+	use ser::{Reader, Error as ReaderError, Stream, serialize};
 	use solution::SOLUTION_SIZE;
+	use bigint::U256;
 	use super::BlockHeader;
 
 	fn test_block_buffer() -> Vec<u8> {
@@ -124,4 +157,73 @@ mod tests {
 		assert_eq!(expected, reader.read().unwrap());
 		assert_eq!(ReaderError::UnexpectedEnd, reader.read::<BlockHeader>().unwrap_err());
 	}
+
+	#[test]
+	fn test_block_header_reader_rejects_solution_of_wrong_size() {
+		let mut buffer = test_block_buffer();
+		// Equihash(48, 5) solution
+		buffer.truncate(buffer.len() - SOLUTION_SIZE - 3);
+		buffer.push(36);
+		buffer.extend_from_slice(&[0u8; 36]);
+
+		let mut reader = Reader::new(&buffer);
+		assert_eq!(ReaderError::MalformedData, reader.read::<BlockHeader>().unwrap_err());
+	}
+
+	#[test]
+	fn test_block_header_streaming_hash() {
+		let block_header = BlockHeader {
+			version: 4,
+			previous_header_hash: [2; 32].into(),
+			merkle_root_hash: [3; 32].into(),
+			final_sapling_root: [4; 32].into(),
+			time: 5,
+			bits: 6.into(),
+			nonce: 7.into(),
+			solution: Default::default(),
+		};
+
+		let raw = serialize(&block_header);
+		let mut hasher = DHash256::new();
+		for chunk in raw.chunks(100) {
+			hasher.write_all(chunk).unwrap();
+		}
+
+		let streaming_hash = hasher.finish();
+		assert_eq!(streaming_hash, dhash256(&raw));
+		assert_eq!(streaming_hash, block_header.hash());
+	}
+
+	#[test]
+	fn test_block_header_target_and_work() {
+		let header = |bits: u32| BlockHeader {
+			version: 4,
+			previous_header_hash: Default::default(),
+			merkle_root_hash: Default::default(),
+			final_sapling_root: Default::default(),
+			time: 0,
+			bits: bits.into(),
+			nonce: Default::default(),
+			solution: Default::default(),
+		};
+
+		// mainnet genesis block
+		assert_eq!(header(0x1f07ffff).target(), Some(U256::from(0x07ffffu64) << 224));
+		assert_eq!(header(0x1f07ffff).work(), Some(U256::from(8192u64)));
+
+		// mainnet block 449120
+		assert_eq!(header(0x1c022fe4).target(), Some(U256::from(0x022fe4u64) << 200));
+		assert_eq!(header(0x1c022fe4).work(), Some(U256::from(502732076842u64)));
+
+		// bitcoin genesis block and regtest
+		assert_eq!(header(0x1d00ffff).work(), Some(U256::from(0x0100010001u64)));
+		assert_eq!(header(0x207fffff).work(), Some(U256::from(2u64)));
+
+		// zero, negative and overflowed targets
+		assert_eq!(header(0x1f000000).target(), None);
+		assert_eq!(header(0x1f000000).work(), None);
+		assert_eq!(header(0x04923456).target(), None);
+		assert_eq!(header(0xff123456).target(), None);
+		assert_eq!(header(0xff123456).work(), None);
+	}
 }
@@ -21,5 +21,21 @@ pub const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000ffff;
 /// otherwise as UNIX timestamp.
 pub const LOCKTIME_THRESHOLD: u32 = 500000000; // Tue Nov  5 00:53:20 1985 UTC
 
+/// Transaction expiry height must be less than this value (TX_EXPIRY_HEIGHT_THRESHOLD in zcashd).
+pub const TX_EXPIRY_HEIGHT_THRESHOLD: u32 = 500_000_000;
+
 /// Number of Satoshis in single coin
 pub const SATOSHIS_IN_COIN: u64 = 100_000_000;
+
+/// Factor, applied to non-witness data when computing block weight. Zcash has no
+/// witness data, so weight of any block is its size, multiplied by this factor.
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+
+/// Maximal size of serialized block (MAX_BLOCK_SIZE in zcashd).
+pub const MAX_BLOCK_SIZE: usize = 2_000_000;
+
+/// Size of the smallest possible serialized transaction (version + empty inputs + empty outputs + lock_time).
+pub const MIN_TRANSACTION_SIZE: usize = 10;
+
+/// Maximal number of transactions in block, which is valid on any network.
+pub const MAX_BLOCK_TRANSACTIONS: usize = MAX_BLOCK_SIZE / MIN_TRANSACTION_SIZE;
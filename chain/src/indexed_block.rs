@@ -1,17 +1,52 @@
-use std::cmp;
+use std::{io, cmp, fmt};
+use std::hash::{Hash, Hasher};
+use std::sync::RwLock;
 use hash::H256;
 use hex::FromHex;
-use ser::{Serializable, serialized_list_size, deserialize};
+use ser::{Serializable, Deserializable, Reader, Error as ReaderError, serialized_list_size, deserialize};
 use block::Block;
 use transaction::Transaction;
 use merkle_root::merkle_root;
 use indexed_header::IndexedBlockHeader;
 use indexed_transaction::IndexedTransaction;
 
-#[derive(Debug, Clone, Deserializable)]
+#[derive(Debug, Clone)]
 pub struct IndexedBlock {
 	pub header: IndexedBlockHeader,
-	pub transactions: Vec<IndexedTransaction>,
+	/// Block transactions. Only modified through `transactions_mut`, which resets cached merkle root.
+	transactions: Vec<IndexedTransaction>,
+	/// Cached merkle root of block transactions.
+	merkle_root: MerkleRootCache,
+}
+
+/// Lazily computed merkle root.
+#[derive(Default)]
+struct MerkleRootCache(RwLock<Option<H256>>);
+
+impl MerkleRootCache {
+	fn get(&self) -> Option<H256> {
+		self.0.read().expect("merkle root cache lock is never poisoned; qed").clone()
+	}
+
+	fn set(&self, merkle_root: H256) {
+		*self.0.write().expect("merkle root cache lock is never poisoned; qed") = Some(merkle_root);
+	}
+
+	fn reset(&mut self) {
+		*self.0.get_mut().expect("merkle root cache lock is never poisoned; qed") = None;
+	}
+}
+
+impl Clone for MerkleRootCache {
+	fn clone(&self) -> Self {
+		MerkleRootCache(RwLock::new(self.get()))
+	}
+}
+
+impl fmt::Debug for MerkleRootCache {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.get().map(|hash| hash.reversed()).fmt(f)
+	}
 }
 
 #[cfg(feature = "test-helpers")]
@@ -20,17 +55,29 @@ impl From<Block> for IndexedBlock {
 		Self::from_raw(block)
 	}
 }
+/// Blocks are identified by their hash: equality and hashing only use the cached header hash,
+/// so that `IndexedBlock` could be used as a `HashSet` element or a `HashMap` key.
 impl cmp::PartialEq for IndexedBlock {
 	fn eq(&self, other: &Self) -> bool {
 		self.header.hash == other.header.hash
 	}
 }
 
+impl cmp::Eq for IndexedBlock {
+}
+
+impl Hash for IndexedBlock {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.header.hash.hash(state)
+	}
+}
+
 impl IndexedBlock {
 	pub fn new(header: IndexedBlockHeader, transactions: Vec<IndexedTransaction>) -> Self {
 		IndexedBlock {
 			header: header,
 			transactions: transactions,
+			merkle_root: MerkleRootCache::default(),
 		}
 	}
 
@@ -49,6 +96,21 @@ impl IndexedBlock {
 		&self.header.hash
 	}
 
+	/// Returns block transactions.
+	pub fn transactions(&self) -> &[IndexedTransaction] {
+		&self.transactions
+	}
+
+	/// Consumes block, returning its transactions.
+	pub fn into_transactions(self) -> Vec<IndexedTransaction> {
+		self.transactions
+	}
+
+	/// Consumes block, returning its header and transactions.
+	pub fn into_parts(self) -> (IndexedBlockHeader, Vec<IndexedTransaction>) {
+		(self.header, self.transactions)
+	}
+
 	pub fn to_raw_block(self) -> Block {
 		Block::new(self.header.raw, self.transactions.into_iter().map(|tx| tx.raw).collect())
 	}
@@ -60,8 +122,21 @@ impl IndexedBlock {
 		header_size + txs_size
 	}
 
+	/// Returns merkle root of block transactions. It is computed once and then cached.
 	pub fn merkle_root(&self) -> H256 {
-		merkle_root(&self.transactions.iter().map(|tx| &tx.hash).collect::<Vec<&H256>>())
+		if let Some(merkle_root) = self.merkle_root.get() {
+			return merkle_root;
+		}
+
+		let merkle_root = merkle_root(&self.transactions.iter().map(|tx| &tx.hash).collect::<Vec<&H256>>());
+		self.merkle_root.set(merkle_root.clone());
+		merkle_root
+	}
+
+	/// Returns mutable reference to block transactions, invalidating cached merkle root.
+	pub fn transactions_mut(&mut self) -> &mut Vec<IndexedTransaction> {
+		self.merkle_root.reset();
+		&mut self.transactions
 	}
 
 	pub fn is_final(&self, height: u32) -> bool {
@@ -69,8 +144,69 @@ impl IndexedBlock {
 	}
 }
 
+impl Deserializable for IndexedBlock {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let header = try!(reader.read());
+		let transactions = try!(reader.read_list());
+		Ok(IndexedBlock::new(header, transactions))
+	}
+}
+
 impl From<&'static str> for IndexedBlock {
 	fn from(s: &'static str) -> Self {
 		deserialize(&s.from_hex::<Vec<u8>>().unwrap() as &[u8]).unwrap()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::collections::HashSet;
+	use hash::H256;
+	use merkle_root::merkle_root;
+	use super::IndexedBlock;
+
+	fn block() -> IndexedBlock {
+		"040000000000000000000000000000000000000000000000000000000000000000000000db4d7a85b768123f1dff1d4c4cece70083b2d27e117b4ac2e31d087988a5eac4000000000000000000000000000000000000000000000000000000000000000090041358ffff071f5712000000000000000000000000000000000000000000000000000000000000fd4005000a889f00854b8665cd555f4656f68179d31ccadc1b1f7fb0952726313b16941da348284d67add4686121d4e3d930160c1348d8191c25f12b267a6a9c131b5031cbf8af1f79c9d513076a216ec87ed045fa966e01214ed83ca02dc1797270a454720d3206ac7d931a0a680c5c5e099057592570ca9bdf6058343958b31901fce1a15a4f38fd347750912e14004c73dfe588b903b6c03166582eeaf30529b14072a7b3079e3a684601b9b3024054201f7440b0ee9eb1a7120ff43f713735494aa27b1f8bab60d7f398bca14f6abb2adbf29b04099121438a7974b078a11635b594e9170f1086140b4173822dd697894483e1c6b4e8b8dcd5cb12ca4903bc61e108871d4d915a9093c18ac9b02b6716ce1013ca2c1174e319c1a570215bc9ab5f7564765f7be20524dc3fdf8aa356fd94d445e05ab165ad8bb4a0db096c097618c81098f91443c719416d39837af6de85015dca0de89462b1d8386758b2cf8a99e00953b308032ae44c35e05eb71842922eb69797f68813b59caf266cb6c213569ae3280505421a7e3a0a37fdf8e2ea354fc5422816655394a9454bac542a9298f176e211020d63dee6852c40de02267e2fc9d5e1ff2ad9309506f02a1a71a0501b16d0d36f70cdfd8de78116c0c506ee0b8ddfdeb561acadf31746b5a9dd32c21930884397fb1682164cb565cc14e089d66635a32618f7eb05fe05082b8a3fae620571660a6b89886eac53dec109d7cbb6930ca698a168f301a950be152da1be2b9e07516995e20baceebecb5579d7cdbc16d09f3a50cb3c7dffe33f26686d4ff3f8946ee6475e98cf7b3cf9062b6966e838f865ff3de5fb064a37a21da7bb8dfd2501a29e184f207caaba364f36f2329a77515dcb710e29ffbf73e2bbd773fab1f9a6b005567affff605c132e4e4dd69f36bd201005458cfbd2c658701eb2a700251cefd886b1e674ae816d3f719bac64be649c172ba27a4fd55947d95d53ba4cbc73de97b8af5ed4840b659370c556e7376457f51e5ebb66018849923db82c1c9a819f173cccdb8f3324b239609a300018d0fb094adf5bd7cbb3834c69e6d0b3798065c525b20f040e965e1a161af78ff7561cd874f5f1b75aa0bc77f720589e1b810f831eac5073e6dd46d00a2793f70f7427f0f798f2f53a67e615e65d356e66fe40609a958a05edb4c175bcc383ea0530e67ddbe479a898943c6e3074c6fcc252d6014de3a3d292b03f0d88d312fe221be7be7e3c59d07fa0f2f4029e364f1f355c5d01fa53770d0cd76d82bf7e60f6903bc1beb772e6fde4a70be51d9c7e03c8d6d8dfb361a234ba47c470fe630820bbd920715621b9fbedb49fcee165ead0875e6c2b1af16f50b5d6140cc981122fcbcf7c5a4e3772b3661b628e08380abc545957e59f634705b1bbde2f0b4e055a5ec5676d859be77e20962b645e051a880fddb0180b4555789e1f9344a436a84dc5579e2553f1e5fb0a599c137be36cabbed0319831fea3fddf94ddc7971e4bcf02cdc93294a9aab3e3b13e3b058235b4f4ec06ba4ceaa49d675b4ba80716f3bc6976b1fbf9c8bf1f3e3a4dc1cd83ef9cf816667fb94f1e923ff63fef072e6a19321e4812f96cb0ffa864da50ad74deb76917a336f31dce03ed5f0303aad5e6a83634f9fcc371096f8288b8f02ddded5ff1bb9d49331e4a84dbe1543164438fde9ad71dab024779dcdde0b6602b5ae0a6265c14b94edd83b37403f4b78fcd2ed555b596402c28ee81d87a909c4e8722b30c71ecdd861b05f61f8b1231795c76adba2fdefa451b283a5d527955b9f3de1b9828e7b2e74123dd47062ddcc09b05e7fa13cb2212a6fdbc65d7e852cec463ec6fd929f5b8483cf3052113b13dac91b69f49d1b7d1aec01c4a68e41ce1570101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff071f0104455a6361736830623963346565663862376363343137656535303031653335303039383462366665613335363833613763616331343161303433633432303634383335643334ffffffff010000000000000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000".into()
+	}
+
+	#[test]
+	fn test_indexed_block_merkle_root_is_cached() {
+		let block = block();
+		let hashes = block.transactions().iter().map(|tx| tx.hash.clone()).collect::<Vec<H256>>();
+		let expected = merkle_root(&hashes);
+
+		assert_eq!(block.merkle_root(), expected);
+		assert_eq!(block.merkle_root(), expected);
+		assert_eq!(block.clone().merkle_root(), expected);
+		assert_eq!(expected, block.header.raw.merkle_root_hash);
+	}
+
+	#[test]
+	fn test_indexed_block_merkle_root_is_invalidated_on_mutation() {
+		let mut block = block();
+		let original = block.merkle_root();
+
+		let tx = block.transactions()[0].clone();
+		block.transactions_mut().push(tx);
+		let hashes = block.transactions().iter().map(|tx| tx.hash.clone()).collect::<Vec<H256>>();
+		assert!(block.merkle_root() != original);
+		assert_eq!(block.merkle_root(), merkle_root(&hashes));
+	}
+
+	#[test]
+	fn test_indexed_block_is_identified_by_hash() {
+		let block = block();
+		let mut same_hash = block.clone();
+		let tx = same_hash.transactions()[0].clone();
+		same_hash.transactions_mut().push(tx);
+
+		let mut other = block.clone();
+		other.header.raw.nonce = 1.into();
+		other.header.hash = other.header.raw.hash();
+
+		let blocks: HashSet<_> = vec![block.clone(), same_hash, other.clone()].into_iter().collect();
+		assert_eq!(blocks.len(), 2);
+		assert!(blocks.contains(&block));
+		assert!(blocks.contains(&other));
+	}
+}
@@ -27,13 +27,13 @@ pub use primitives::{hash, bytes, bigint, compact};
 pub use transaction::{BTC_TX_VERSION, SPROUT_TX_VERSION, OVERWINTER_TX_VERSION, SAPLING_TX_VERSION};
 pub use transaction::{OVERWINTER_TX_VERSION_GROUP_ID, SAPLING_TX_VERSION_GROUP_ID};
 
-pub use block::Block;
+pub use block::{Block, BlockTransactionsReader, MerkleError, decode_coinbase_height};
 pub use block_header::BlockHeader;
 pub use solution::EquihashSolution;
 pub use join_split::{JoinSplit, JoinSplitDescription, JoinSplitProof};
-pub use merkle_root::{merkle_root, merkle_node_hash};
+pub use merkle_root::{merkle_root, merkle_root_with_mutation, merkle_branch, verify_merkle_branch, merkle_node_hash};
 pub use sapling::{Sapling, SaplingSpendDescription, SaplingOutputDescription};
-pub use transaction::{Transaction, TransactionInput, TransactionOutput, OutPoint};
+pub use transaction::{Transaction, TransactionInput, TransactionOutput, TransactionFormat, OutPoint, RelayId};
 
 pub use read_and_hash::{ReadAndHash, HashedData};
 pub use indexed_block::IndexedBlock;
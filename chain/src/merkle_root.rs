@@ -32,6 +32,84 @@ pub fn merkle_root<T>(hashes: &[T]) -> H256 where T: AsRef<H256> {
 	merkle_root(&row)
 }
 
+/// Calculates the root of the merkle tree and checks if the tree is mutated, i.e. if the same
+/// root could be computed from the different list of hashes by duplicating some of them
+/// (CVE-2012-2459). Returns the root and true if the tree is mutated.
+pub fn merkle_root_with_mutation<T>(hashes: &[T]) -> (H256, bool) where T: AsRef<H256> {
+	let mut mutated = false;
+	let mut row: Vec<H256> = hashes.iter().map(|h| h.as_ref().clone()).collect();
+	while row.len() > 1 {
+		// two equal hashes at the same level make the tree ambiguous
+		mutated |= row.chunks(2).any(|pair| pair.len() == 2 && pair[0] == pair[1]);
+
+		if row.len() % 2 == 1 {
+			let last = row[row.len() - 1].clone();
+			row.push(last);
+		}
+
+		row = row.chunks(2).map(|pair| merkle_node_hash(&pair[0], &pair[1])).collect();
+	}
+
+	(row.pop().unwrap_or_default(), mutated)
+}
+
+/// Returns merkle branch (sibling hashes on the path from the leaf to the root) of the hash with given index.
+/// Returns empty branch if index is out of bounds.
+pub fn merkle_branch<T>(hashes: &[T], index: usize) -> Vec<H256> where T: AsRef<H256> {
+	let mut branch = Vec::new();
+	if index >= hashes.len() {
+		return branch;
+	}
+
+	let mut index = index;
+	let mut row: Vec<H256> = hashes.iter().map(|h| h.as_ref().clone()).collect();
+	while row.len() > 1 {
+		// duplicate the last element if len is not even
+		if row.len() % 2 == 1 {
+			let last = row[row.len() - 1].clone();
+			row.push(last);
+		}
+
+		branch.push(row[index ^ 1].clone());
+		row = row.chunks(2).map(|pair| merkle_node_hash(&pair[0], &pair[1])).collect();
+		index /= 2;
+	}
+
+	branch
+}
+
+/// Verifies that the hash with given index is included in the merkle tree of `leaf_count` hashes with given root.
+pub fn verify_merkle_branch(hash: &H256, branch: &[H256], index: usize, leaf_count: usize, root: &H256) -> bool {
+	// index must be in the tree bounds
+	if index >= leaf_count {
+		return false;
+	}
+
+	// branch must contain exactly one sibling for every level of the tree
+	let mut depth = 0;
+	let mut row_len = leaf_count;
+	while row_len > 1 {
+		row_len = (row_len + 1) / 2;
+		depth += 1;
+	}
+	if branch.len() != depth {
+		return false;
+	}
+
+	let mut index = index;
+	let mut node = hash.clone();
+	for sibling in branch {
+		node = if index % 2 == 1 {
+			merkle_node_hash(sibling, &node)
+		} else {
+			merkle_node_hash(&node, sibling)
+		};
+		index /= 2;
+	}
+
+	node == *root
+}
+
 /// Calculate merkle tree node hash
 pub fn merkle_node_hash<T>(left: T, right: T) -> H256 where T: AsRef<H256> {
 	dhash256(&*concat(left, right))
@@ -40,7 +118,7 @@ pub fn merkle_node_hash<T>(left: T, right: T) -> H256 where T: AsRef<H256> {
 #[cfg(test)]
 mod tests {
 	use hash::H256;
-	use super::merkle_root;
+	use super::{merkle_root, merkle_root_with_mutation, merkle_branch, verify_merkle_branch};
 
 	// block 80_000
 	// https://blockchain.info/block/000000000043a8c0fd1d6f726790caa2a406010d19efd2780db27bdbbd93baf6
@@ -55,4 +133,49 @@ mod tests {
 		assert_eq!(result, expected);
 		assert_eq!(result2, expected);
 	}
+
+	#[test]
+	fn test_merkle_root_mutation() {
+		let tx1 = H256::from(1);
+		let tx2 = H256::from(2);
+		let tx3 = H256::from(3);
+
+		let (root, mutated) = merkle_root_with_mutation(&[&tx1, &tx2, &tx3]);
+		assert_eq!(root, merkle_root(&[&tx1, &tx2, &tx3]));
+		assert!(!mutated);
+
+		// duplicating the last hash gives the same root, but the tree is mutated
+		assert_eq!(merkle_root_with_mutation(&[&tx1, &tx2, &tx3, &tx3]), (root, true));
+		assert_eq!(merkle_root_with_mutation(&[&tx1, &tx1]).1, true);
+		assert_eq!(merkle_root_with_mutation(&[&tx1]), (tx1, false));
+	}
+
+	#[test]
+	fn test_merkle_branch() {
+		let hashes: Vec<H256> = (1..6).map(H256::from).collect();
+		let root = merkle_root(&hashes);
+
+		for index in 0..hashes.len() {
+			let branch = merkle_branch(&hashes, index);
+			assert_eq!(branch.len(), 3);
+			assert!(verify_merkle_branch(&hashes[index], &branch, index, hashes.len(), &root));
+			// wrong index
+			assert!(!verify_merkle_branch(&hashes[index], &branch, (index + 1) % hashes.len(), hashes.len(), &root));
+			assert!(!verify_merkle_branch(&hashes[index], &branch, index + 8, hashes.len(), &root));
+			// wrong number of leaves
+			assert!(!verify_merkle_branch(&hashes[index], &branch, index, 9, &root));
+		}
+
+		// duplicated last hash is not in the tree, even though its branch leads to the root
+		let branch = merkle_branch(&hashes, 4);
+		assert!(!verify_merkle_branch(&hashes[4], &branch, 5, hashes.len(), &root));
+
+		// single hash is the root itself
+		assert_eq!(merkle_branch(&hashes[0..1], 0), Vec::<H256>::new());
+		assert!(verify_merkle_branch(&hashes[0], &[], 0, 1, &hashes[0]));
+		assert!(!verify_merkle_branch(&hashes[0], &[], 0, 0, &hashes[0]));
+
+		// index is out of bounds
+		assert_eq!(merkle_branch(&hashes, 5), Vec::<H256>::new());
+	}
 }
@@ -2,11 +2,19 @@ use std::{fmt, io};
 use hex::ToHex;
 use ser::{Error, Serializable, Deserializable, Stream, Reader};
 
-/// Equihash solution size.
+/// Equihash solution size (for the on-chain Equihash(200, 9) parameters). Only solutions of this
+/// size could be deserialized. Solutions for smaller parameters (like Equihash(48, 5)) are shorter
+/// and could only be constructed in memory (i.e. in tests).
 pub const SOLUTION_SIZE: usize = 1344;
 
 #[derive(Clone)]
-pub struct EquihashSolution([u8; SOLUTION_SIZE]);
+pub struct EquihashSolution(Vec<u8>);
+
+impl From<Vec<u8>> for EquihashSolution {
+	fn from(solution: Vec<u8>) -> Self {
+		EquihashSolution(solution)
+	}
+}
 
 impl AsRef<[u8]> for EquihashSolution {
 	fn as_ref(&self) -> &[u8] {
@@ -16,13 +24,13 @@ impl AsRef<[u8]> for EquihashSolution {
 
 impl Default for EquihashSolution {
 	fn default() -> Self {
-		EquihashSolution([0; SOLUTION_SIZE])
+		EquihashSolution(vec![0; SOLUTION_SIZE])
 	}
 }
 
 impl PartialEq<EquihashSolution> for EquihashSolution {
 	fn eq(&self, other: &EquihashSolution) -> bool {
-		self.0.as_ref() == other.0.as_ref()
+		self.0 == other.0
 	}
 }
 
@@ -40,9 +48,6 @@ impl Serializable for EquihashSolution {
 
 impl Deserializable for EquihashSolution {
 	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, Error> where Self: Sized, T: io::Read {
-		let v = reader.read_list_exact(SOLUTION_SIZE)?;
-		let mut sol = [0; SOLUTION_SIZE];
-		sol.copy_from_slice(&v);
-		Ok(EquihashSolution(sol))
+		reader.read_list_exact(SOLUTION_SIZE).map(EquihashSolution)
 	}
 }
@@ -1,18 +1,31 @@
 //! Bitcoin transaction.
 //! https://en.bitcoin.it/wiki/Protocol_documentation#tx
 
+use std::cmp;
 use std::io;
 use heapsize::HeapSizeOf;
 use hex::FromHex;
 use bytes::Bytes;
-use ser::{deserialize, serialize};
-use crypto::dhash256;
+use ser::{deserialize, serialize_to};
+use crypto::{DHash256, dhash256};
 use hash::H256;
 use constants::{SEQUENCE_FINAL, LOCKTIME_THRESHOLD};
-use join_split::{JoinSplit, deserialize_join_split, serialize_join_split};
-use sapling::Sapling;
+use join_split::{JoinSplit, JoinSplitDescription, deserialize_join_split, serialize_join_split};
+use sapling::{Sapling, SaplingSpendDescription, SaplingOutputDescription};
 use ser::{Error, Serializable, Deserializable, Stream, Reader};
 
+/// Wtxid-style transaction identifier. Like the wtxid, it consists of the digest of transaction
+/// effects and the digest of authorizing data. Malleated variants of the same transaction share
+/// the effects digest, but any change of the signature changes the relay id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RelayId {
+	/// Hash of the transaction with all signatures (scriptSigs, JoinSplit signature,
+	/// Sapling spend authorization and binding signatures) stripped.
+	pub effects: H256,
+	/// Hash of all transaction signatures.
+	pub auth: H256,
+}
+
 /// Original bitcoin transaction version.
 pub const BTC_TX_VERSION: i32 = 1;
 /// Sprout-era transaction version wit JS.
@@ -27,6 +40,57 @@ pub const OVERWINTER_TX_VERSION_GROUP_ID: u32 = 0x03C48270;
 /// Sapling version group id.
 pub const SAPLING_TX_VERSION_GROUP_ID: u32 = 0x892F2085;
 
+/// Serialization format of the transaction, selected by its version and version group.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransactionFormat {
+	/// Original bitcoin format (v1): version, inputs, outputs, lock_time.
+	Bitcoin,
+	/// Sprout format (v2+, not overwintered): bitcoin format + JoinSplits with PHGR proofs.
+	Sprout,
+	/// Overwinter format (v3, ZIP 202): sprout format + version group and expiry height.
+	Overwinter,
+	/// Sapling format (v4, ZIP 243): overwinter format + Sapling spends and outputs, JoinSplits with Groth proofs.
+	Sapling,
+}
+
+impl TransactionFormat {
+	/// Selects format of transaction with given (serialized) header fields.
+	pub fn from_header(overwintered: bool, version: i32, version_group_id: u32) -> Result<Self, Error> {
+		match (overwintered, version, version_group_id) {
+			(false, version, _) if version < SPROUT_TX_VERSION => Ok(TransactionFormat::Bitcoin),
+			(false, _, _) => Ok(TransactionFormat::Sprout),
+			(true, OVERWINTER_TX_VERSION, OVERWINTER_TX_VERSION_GROUP_ID) => Ok(TransactionFormat::Overwinter),
+			(true, SAPLING_TX_VERSION, SAPLING_TX_VERSION_GROUP_ID) => Ok(TransactionFormat::Sapling),
+			// NU5 (v5) and unknown future formats are rejected
+			(true, version, version_group_id) => Err(Error::InvalidFormat(format!(
+				"Invalid overwinter transaction version: {}, version group: {}", version, version_group_id))),
+		}
+	}
+
+	/// Returns true if transaction of this format has version group and expiry height fields.
+	pub fn is_overwintered(&self) -> bool {
+		match *self {
+			TransactionFormat::Overwinter | TransactionFormat::Sapling => true,
+			TransactionFormat::Bitcoin | TransactionFormat::Sprout => false,
+		}
+	}
+
+	/// Returns true if transaction of this format has JoinSplits field.
+	pub fn has_join_split(&self) -> bool {
+		*self != TransactionFormat::Bitcoin
+	}
+
+	/// Returns true if transaction of this format has Sapling fields.
+	pub fn has_sapling(&self) -> bool {
+		*self == TransactionFormat::Sapling
+	}
+
+	/// Returns true if JoinSplits of this format are using Groth16 proofs.
+	pub fn uses_groth_proofs(&self) -> bool {
+		*self == TransactionFormat::Sapling
+	}
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Default, Serializable, Deserializable, Hash)]
 pub struct OutPoint {
 	pub hash: H256,
@@ -34,6 +98,13 @@ pub struct OutPoint {
 }
 
 impl OutPoint {
+	pub fn new(hash: H256, index: u32) -> Self {
+		OutPoint {
+			hash: hash,
+			index: index,
+		}
+	}
+
 	pub fn null() -> Self {
 		OutPoint {
 			hash: H256::default(),
@@ -46,6 +117,21 @@ impl OutPoint {
 	}
 }
 
+/// BIP-69 ordering: by previous transaction hash (compared in reversed, i.e. displayed, byte order),
+/// then by output index.
+impl cmp::Ord for OutPoint {
+	fn cmp(&self, other: &Self) -> cmp::Ordering {
+		self.hash.iter().rev().cmp(other.hash.iter().rev())
+			.then_with(|| self.index.cmp(&other.index))
+	}
+}
+
+impl cmp::PartialOrd for OutPoint {
+	fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
 #[derive(Debug, PartialEq, Default, Clone)]
 pub struct TransactionInput {
 	pub previous_output: OutPoint,
@@ -120,6 +206,25 @@ impl HeapSizeOf for Transaction {
 }
 
 impl Transaction {
+	/// Returns serialization format of this transaction, or error if transaction has
+	/// unknown (or unsupported) version and version group combination.
+	pub fn format(&self) -> Result<TransactionFormat, Error> {
+		TransactionFormat::from_header(self.overwintered, self.version, self.version_group_id)
+	}
+
+	/// Returns true if transaction is overwintered, i.e. has version group and expiry height fields.
+	pub fn overwintered(&self) -> bool {
+		self.overwintered
+	}
+
+	/// Returns version group id of overwintered transaction, or None if transaction is not overwintered.
+	pub fn version_group_id(&self) -> Option<u32> {
+		match self.overwintered {
+			true => Some(self.version_group_id),
+			false => None,
+		}
+	}
+
 	/// Returns version as it is serialized (including overwintered flag).
 	pub fn serialized_version(&self) -> u32 {
 		let mut version = self.version as u32;
@@ -134,6 +239,33 @@ impl Transaction {
 		transaction_hash(self)
 	}
 
+	/// Wtxid-style transaction identifier, used for relay tracking.
+	pub fn relay_id(&self) -> RelayId {
+		let mut stripped = self.clone();
+		let mut auth = Stream::default();
+		for input in &mut stripped.inputs {
+			auth.append(&input.script_sig);
+			input.script_sig = Bytes::default();
+		}
+		if let Some(ref mut join_split) = stripped.join_split {
+			auth.append(&join_split.sig);
+			join_split.sig = Default::default();
+		}
+		if let Some(ref mut sapling) = stripped.sapling {
+			for spend in &mut sapling.spends {
+				auth.append_slice(&spend.spend_auth_sig);
+				spend.spend_auth_sig = [0; 64];
+			}
+			auth.append_slice(&sapling.binding_sig);
+			sapling.binding_sig = [0; 64];
+		}
+
+		RelayId {
+			effects: transaction_hash(&stripped),
+			auth: dhash256(&auth.out()),
+		}
+	}
+
 	pub fn inputs(&self) -> &[TransactionInput] {
 		&self.inputs
 	}
@@ -142,6 +274,32 @@ impl Transaction {
 		&self.outputs
 	}
 
+	/// Returns Sprout JoinSplit descriptions of this transaction.
+	pub fn joinsplits(&self) -> &[JoinSplitDescription] {
+		self.join_split.as_ref().map(|join_split| &join_split.descriptions[..]).unwrap_or(&[])
+	}
+
+	/// Returns nullifiers of all Sprout notes, spent by this transaction (two per JoinSplit description).
+	pub fn sprout_nullifiers<'a>(&'a self) -> impl Iterator<Item=&'a [u8; 32]> + 'a {
+		self.joinsplits().iter().flat_map(|description| description.nullifiers.iter())
+	}
+
+	/// Returns Sapling value balance: the net value of Sapling spends minus outputs,
+	/// or None if transaction has no Sapling data.
+	pub fn value_balance(&self) -> Option<i64> {
+		self.sapling.as_ref().map(|sapling| sapling.balancing_value)
+	}
+
+	/// Returns Sapling spend descriptions of this transaction.
+	pub fn sapling_spends(&self) -> &[SaplingSpendDescription] {
+		self.sapling.as_ref().map(|sapling| &sapling.spends[..]).unwrap_or(&[])
+	}
+
+	/// Returns Sapling output descriptions of this transaction.
+	pub fn sapling_outputs(&self) -> &[SaplingOutputDescription] {
+		self.sapling.as_ref().map(|sapling| &sapling.outputs[..]).unwrap_or(&[])
+	}
+
 	pub fn is_empty(&self) -> bool {
 		self.inputs.is_empty() || self.outputs.is_empty()
 	}
@@ -213,8 +371,13 @@ impl Deserializable for TransactionInput {
 	}
 }
 
+/// Transaction is serialized using the format, selected by its header. Fields are never dropped:
+/// if transaction has fields that its format doesn't have (or it has invalid version and version group),
+/// they're still serialized and the result is rejected by the deserializer.
 impl Serializable for Transaction {
 	fn serialize(&self, stream: &mut Stream) {
+		let is_sapling_format = self.format() == Ok(TransactionFormat::Sapling);
+
 		stream.append(&self.serialized_version());
 		if self.overwintered {
 			stream.append(&self.version_group_id);
@@ -228,17 +391,24 @@ impl Serializable for Transaction {
 			stream.append(&self.expiry_height);
 		}
 
-		if let Some(sapling) = self.sapling.as_ref() {
+		// Sapling transaction always has Sapling fields, even if there are no Sapling spends && outputs
+		let empty_sapling = Sapling::default();
+		let sapling = match is_sapling_format {
+			true => Some(self.sapling.as_ref().unwrap_or(&empty_sapling)),
+			false => self.sapling.as_ref(),
+		};
+
+		if let Some(sapling) = sapling {
 			stream.append(&sapling.balancing_value)
 				.append_list(&sapling.spends)
 				.append_list(&sapling.outputs);
 		}
 
-		if self.version >= SPROUT_TX_VERSION {
+		if self.overwintered || self.version >= SPROUT_TX_VERSION || self.join_split.is_some() {
 			serialize_join_split(stream, &self.join_split);
 		}
 
-		if let Some(sapling) = self.sapling.as_ref() {
+		if let Some(sapling) = sapling {
 			if !sapling.spends.is_empty() || !sapling.outputs.is_empty() {
 				stream.append(&sapling.binding_sig);
 			}
@@ -257,8 +427,9 @@ impl Deserializable for Transaction {
 		// overwinter format (ZIP 202):
 		// overwintered, version (3), version group, inputs, outputs, lock_time, expiry height, joint split
 		//
-		// sapling format:
-		// overwintered, version (4), version group, inputs, outputs, lock_time, expiry height, joint split
+		// sapling format (ZIP 243):
+		// overwintered, version (4), version group, inputs, outputs, lock_time, expiry height,
+		// value balance, shielded spends, shielded outputs, joint split, binding sig
 
 		let version: u32 = reader.read()?;
 		let overwintered = (version & 0x80000000) != 0;
@@ -270,25 +441,19 @@ impl Deserializable for Transaction {
 			0
 		};
 
-		// reject overwintered transactions of unknown versions
-		let is_overwinter_tx = overwintered && version == OVERWINTER_TX_VERSION && version_group_id == OVERWINTER_TX_VERSION_GROUP_ID;
-		let is_sapling_tx = overwintered && version == SAPLING_TX_VERSION && version_group_id == SAPLING_TX_VERSION_GROUP_ID;
-		if overwintered && !is_overwinter_tx && !is_sapling_tx {
-			return Err(Error::InvalidFormat(format!("Invalid overwinter transaction version: {}, version group: {}",
-				version, version_group_id)));
-		}
+		let format = TransactionFormat::from_header(overwintered, version, version_group_id)?;
 
 		let inputs: Vec<TransactionInput> = reader.read_list()?;
 		let outputs = reader.read_list()?;
 		let lock_time = reader.read()?;
 
-		let expiry_height = if is_overwinter_tx || is_sapling_tx {
+		let expiry_height = if format.is_overwintered() {
 			reader.read()?
 		} else {
 			0
 		};
 
-		let mut sapling = if is_sapling_tx {
+		let mut sapling = if format.has_sapling() {
 			let balancing_value = reader.read()?;
 			let spends = reader.read_list()?;
 			let outputs = reader.read_list()?;
@@ -302,9 +467,8 @@ impl Deserializable for Transaction {
 			None
 		};
 
-		let join_split = if version >= SPROUT_TX_VERSION {
-			let use_groth = overwintered && version >= SAPLING_TX_VERSION;
-			deserialize_join_split(reader, use_groth)?
+		let join_split = if format.has_join_split() {
+			deserialize_join_split(reader, format.uses_groth_proofs())?
 		} else {
 			None
 		};
@@ -330,15 +494,22 @@ impl Deserializable for Transaction {
 }
 
 pub(crate) fn transaction_hash(transaction: &Transaction) -> H256 {
-	dhash256(&serialize(transaction))
+	let mut hasher = DHash256::new();
+	serialize_to(transaction, &mut hasher);
+	hasher.finish()
 }
 
 #[cfg(test)]
 mod tests {
-	use hex::ToHex;
+	use std::io::Write;
+	use hex::{ToHex, FromHex};
 	use hash::H256;
+	use crypto::{dhash256, DHash256};
 	use ser::{Serializable, serialize};
-	use super::Transaction;
+	use ser::{deserialize, Error};
+	use sapling::Sapling;
+	use super::{Transaction, TransactionInput, TransactionOutput, TransactionFormat, OutPoint, SAPLING_TX_VERSION,
+		SAPLING_TX_VERSION_GROUP_ID, OVERWINTER_TX_VERSION_GROUP_ID};
 
 	// real transaction from Zcash block 30003
 	// https://zcash.blockexplorer.com/api/rawtx/54c8acf69271dad83e9faa34284cda725caa5bea7378db92acf35becd0989463
@@ -351,6 +522,9 @@ mod tests {
 		assert_eq!(t.overwintered, false);
 		assert_eq!(t.version, 1);
 		assert_eq!(t.version_group_id, 0);
+		assert_eq!(t.format(), Ok(TransactionFormat::Bitcoin));
+		assert!(!t.overwintered());
+		assert_eq!(t.version_group_id(), None);
 		assert_eq!(t.lock_time, 29992);
 		assert_eq!(t.expiry_height, 0);
 		assert_eq!(t.inputs.len(), 3);
@@ -372,6 +546,11 @@ mod tests {
 		assert_eq!(tx_output.script_pubkey, "76a9148061115677d41cd5661b86a6f9c288fbeb9d8e1f88ac".into());
 		assert!(t.join_split.is_none());
 		assert!(t.sapling.is_none());
+		assert!(t.joinsplits().is_empty());
+		assert_eq!(t.sprout_nullifiers().count(), 0);
+		assert_eq!(t.value_balance(), None);
+		assert!(t.sapling_spends().is_empty());
+		assert!(t.sapling_outputs().is_empty());
 
 		// serialize && check tx
 		let t: String = serialize(&t).to_hex();
@@ -389,12 +568,47 @@ mod tests {
 		assert_eq!(t.overwintered, false);
 		assert_eq!(t.version, 2);
 		assert_eq!(t.version_group_id, 0);
+		assert_eq!(t.format(), Ok(TransactionFormat::Sprout));
+		assert!(!t.overwintered());
+		assert_eq!(t.version_group_id(), None);
 		assert_eq!(t.lock_time, 0);
 		assert_eq!(t.expiry_height, 0);
 		assert_eq!(t.inputs.len(), 1);
 		assert_eq!(t.outputs.len(), 0);
 		assert!(t.join_split.is_some());
 		assert!(t.sapling.is_none());
+		assert_eq!(t.joinsplits().len(), 1);
+		assert_eq!(t.joinsplits()[0].value_pub_old, 14250000);
+		assert_eq!(t.joinsplits()[0].value_pub_new, 0);
+		assert_eq!(t.sprout_nullifiers().map(|nullifier| H256::from(*nullifier)).collect::<Vec<_>>(), vec![
+			"7ae7c48e86173b231e84fbdcb4d8f569f28f71ebf0f9b5867f9d4c12e031a2ac".into(),
+			"c0108235936d2fa2d2c968654fbea2a89fde8522ec7c227d2ff3c10bff9c1197".into(),
+		]);
+
+		// serialize && check tx
+		let t: String = serialize(&t).to_hex();
+		assert_eq!(t, hex);
+	}
+
+	// real Overwinter transaction from Zcash mainnet (lock time 419060, expiry height 419091)
+	#[test]
+	fn test_overwinter_transaction() {
+		let hex = "030000807082c40301fae7d139e4469fde499b15b8d73a1449fa60002b1939d3aa52bfa35fea2d4b28280000006a4730440220191a63406c15031d2841020a38e99236732a5e7f9901e76719bbaecbce380ab302204cd4b51ed7ac9bf7aa426195beaa8c9bc614b05337a3159d6b538169dbfe82370121021057319f530d97412f40f76c8183109b7948ead50179b9ed3e1e43cdc8dbc5a6feffffff02a9a43500000000001976a914eeaf06a51b139b079c7357ddacc5cf64463ef21388ac6d569202000000001976a914d8bf63402dc79ef4b612a023df412e6c00f646e088acf46406001365060000";
+
+		// deserialize && check tx
+		let t: Transaction = hex.into();
+		assert_eq!(t.format(), Ok(TransactionFormat::Overwinter));
+		assert_eq!(t.overwintered, true);
+		assert_eq!(t.version, 3);
+		assert_eq!(t.version_group_id, 0x03C48270);
+		assert!(t.overwintered());
+		assert_eq!(t.version_group_id(), Some(0x03C48270));
+		assert_eq!(t.lock_time, 419060);
+		assert_eq!(t.expiry_height, 419091);
+		assert_eq!(t.inputs.len(), 1);
+		assert_eq!(t.outputs.len(), 2);
+		assert!(t.join_split.is_none());
+		assert!(t.sapling.is_none());
 
 		// serialize && check tx
 		let t: String = serialize(&t).to_hex();
@@ -412,6 +626,9 @@ mod tests {
 		assert_eq!(t.overwintered, true);
 		assert_eq!(t.version, 4);
 		assert_eq!(t.version_group_id, 0x892F2085);
+		assert_eq!(t.format(), Ok(TransactionFormat::Sapling));
+		assert!(t.overwintered());
+		assert_eq!(t.version_group_id(), Some(0x892F2085));
 		assert_eq!(t.lock_time, 0x86dd1c48);
 		assert_eq!(t.expiry_height, 0x1843ccb3);
 		assert_eq!(t.inputs.len(), 0);
@@ -434,8 +651,23 @@ mod tests {
 		// deserialize && check tx
 		let t: Transaction = hex.into();
 		assert!(t.sapling.is_some());
-		assert_eq!(t.sapling.as_ref().unwrap().spends.len(), 1);
-		assert_eq!(t.sapling.as_ref().unwrap().outputs.len(), 1);
+		assert_eq!(t.value_balance(), Some(10000));
+		assert_eq!(t.sapling_spends().len(), 1);
+		assert_eq!(t.sapling_outputs().len(), 1);
+
+		let spend = &t.sapling_spends()[0];
+		assert_eq!(H256::from(spend.value_commitment), "48b1c0668fce604361fbb1b89bbd76f8fee09b51a9dc0fdfcf6c6720cd596083".into());
+		assert_eq!(H256::from(spend.anchor), "d970234fcc0e9a70fdfed82d32fbb9ca92c9c5c3bad5daad9ac62b5bf4255817".into());
+		assert_eq!(H256::from(spend.nullifier), "ee5bc95a9af453bb9cc7e2c544aa29efa20011a65b624998369c849aa8f0bc83".into());
+		assert_eq!(H256::from(spend.randomized_key), "d60e7902a3cfe6eeaeb8d583a491de5982c5ded29e64cd8f8fac594a5bb4f283".into());
+
+		let output = &t.sapling_outputs()[0];
+		assert_eq!(H256::from(output.value_commitment), "f4c24b0125e4059eec61f63ccbe277363172f2bdee384412ea073c5aca06b94e".into());
+		assert_eq!(H256::from(output.note_commitment), "402ba3a43e15bd9c65bbfb194c561c24a031dec43be95c59eb6b568c176b1038".into());
+
+		// serialize && check tx
+		let t: String = serialize(&t).to_hex();
+		assert_eq!(t, hex);
 	}
 
 	#[test]
@@ -451,4 +683,216 @@ mod tests {
 		let tx: Transaction = raw_tx.into();
 		assert_eq!(tx.serialized_size(), raw_tx.len() / 2);
 	}
+
+	#[test]
+	fn test_large_transaction_streaming_hash() {
+		let mut tx: Transaction = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000".into();
+		tx.outputs.extend((0..10_000).map(|i| TransactionOutput {
+			value: i,
+			script_pubkey: "76a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac".into(),
+		}));
+
+		let raw = serialize(&tx);
+		assert!(raw.len() > 300_000);
+
+		let mut hasher = DHash256::new();
+		for chunk in raw.chunks(4096) {
+			hasher.write_all(chunk).unwrap();
+		}
+
+		let streaming_hash = hasher.finish();
+		assert_eq!(streaming_hash, dhash256(&raw));
+		assert_eq!(streaming_hash, tx.hash());
+	}
+
+	#[test]
+	fn test_relay_id_of_malleated_transaction() {
+		let t1: Transaction = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000".into();
+
+		// the same signature, pushed using OP_PUSHDATA1 instead of direct push
+		let mut t2 = t1.clone();
+		let mut script_sig = vec![0x4c];
+		script_sig.extend_from_slice(&t1.inputs[0].script_sig);
+		t2.inputs[0].script_sig = script_sig.into();
+
+		// signature change changes relay id, but effects of malleated variants are the same
+		assert!(t1.hash() != t2.hash());
+		assert!(t1.relay_id() != t2.relay_id());
+		assert!(t1.relay_id().auth != t2.relay_id().auth);
+		assert_eq!(t1.relay_id().effects, t2.relay_id().effects);
+
+		// changing non-signature data changes relay id
+		let mut t3 = t1.clone();
+		t3.outputs[0].value -= 1;
+		assert!(t1.relay_id() != t3.relay_id());
+		assert!(t1.relay_id().effects != t3.relay_id().effects);
+		assert_eq!(t1.relay_id().auth, t3.relay_id().auth);
+
+		// the same transaction has the same relay id
+		assert_eq!(t1.relay_id(), t1.clone().relay_id());
+
+		// shielded signatures are also covered
+		let t4 = Transaction {
+			overwintered: true,
+			version: SAPLING_TX_VERSION,
+			version_group_id: SAPLING_TX_VERSION_GROUP_ID,
+			sapling: Some(Sapling {
+				spends: vec![Default::default()],
+				..Default::default()
+			}),
+			..t1.clone()
+		};
+		let mut t5 = t4.clone();
+		t5.sapling.as_mut().unwrap().spends[0].spend_auth_sig = [1; 64];
+		assert!(t4.hash() != t5.hash());
+		assert!(t4.relay_id() != t5.relay_id());
+		assert_eq!(t4.relay_id().effects, t5.relay_id().effects);
+	}
+
+	#[test]
+	fn test_transaction_format_is_selected_by_version() {
+		assert_eq!(TransactionFormat::from_header(false, 1, 0), Ok(TransactionFormat::Bitcoin));
+		assert_eq!(TransactionFormat::from_header(false, 2, 0), Ok(TransactionFormat::Sprout));
+		assert_eq!(TransactionFormat::from_header(true, 3, 0x03C48270), Ok(TransactionFormat::Overwinter));
+		assert_eq!(TransactionFormat::from_header(true, 4, 0x892F2085), Ok(TransactionFormat::Sapling));
+		// version group must match version
+		assert!(TransactionFormat::from_header(true, 3, 0x892F2085).is_err());
+		assert!(TransactionFormat::from_header(true, 4, 0x03C48270).is_err());
+		// NU5 transactions are not supported
+		assert!(TransactionFormat::from_header(true, 5, 0x26A7270A).is_err());
+	}
+
+	#[test]
+	fn test_transaction_with_unknown_version_group_is_rejected() {
+		let mut t = Transaction {
+			overwintered: true,
+			version: 3,
+			version_group_id: OVERWINTER_TX_VERSION_GROUP_ID,
+			inputs: vec![TransactionInput::coinbase(vec![1, 2].into())],
+			outputs: vec![TransactionOutput::default()],
+			expiry_height: 100,
+			..Default::default()
+		};
+		assert_eq!(deserialize::<_, Transaction>(&serialize(&t)[..]), Ok(t.clone()));
+
+		// v3 transaction with Sapling version group id
+		t.version_group_id = SAPLING_TX_VERSION_GROUP_ID;
+		assert!(match deserialize::<_, Transaction>(&serialize(&t)[..]) {
+			Err(Error::InvalidFormat(_)) => true,
+			_ => false,
+		});
+
+		// unknown version group id
+		t.version_group_id = 0x01020304;
+		assert!(deserialize::<_, Transaction>(&serialize(&t)[..]).is_err());
+	}
+
+	#[test]
+	fn test_sapling_transaction_without_shielded_data_roundtrip() {
+		let t = Transaction {
+			overwintered: true,
+			version: SAPLING_TX_VERSION,
+			version_group_id: SAPLING_TX_VERSION_GROUP_ID,
+			outputs: vec![TransactionOutput { value: 100, script_pubkey: "51".into() }],
+			expiry_height: 20,
+			..Default::default()
+		};
+
+		// empty Sapling fields are serialized: value balance, no spends, no outputs
+		let serialized = serialize(&t);
+		assert_eq!(serialized.to_hex(), "0400008085202f8900016400000000000000015100000000140000000000000000000000000000");
+
+		let deserialized: Transaction = deserialize(&serialized as &[u8]).unwrap();
+		assert_eq!(deserialized.format(), Ok(TransactionFormat::Sapling));
+		assert_eq!(deserialized.sapling, Some(Default::default()));
+		assert_eq!(serialize(&deserialized), serialized);
+	}
+
+	#[test]
+	fn test_nu5_transaction_is_rejected() {
+		let t = Transaction {
+			overwintered: true,
+			version: 5,
+			version_group_id: 0x26A7270A,
+			inputs: vec![TransactionInput::coinbase(vec![1, 2].into())],
+			outputs: vec![TransactionOutput::default()],
+			expiry_height: 100,
+			..Default::default()
+		};
+		assert!(t.format().is_err());
+
+		// v5 transaction (header, consensus branch id, lock time && expiry height) can't be read
+		let serialized = serialize(&t);
+		assert_eq!(&serialized[..8], &"050000800a27a726".from_hex::<Vec<u8>>().unwrap()[..]);
+		assert!(match deserialize::<_, Transaction>(&serialized[..]) {
+			Err(Error::InvalidFormat(_)) => true,
+			_ => false,
+		});
+
+		let nu5_header = "050000800a27a726b4d0d6c20000000000000000";
+		assert!(match deserialize::<_, Transaction>(&nu5_header.from_hex::<Vec<u8>>().unwrap() as &[u8]) {
+			Err(Error::InvalidFormat(_)) => true,
+			_ => false,
+		});
+	}
+
+	#[test]
+	fn test_sapling_fields_of_overwinter_transaction_are_not_dropped() {
+		let mut t = Transaction {
+			overwintered: true,
+			version: 3,
+			version_group_id: OVERWINTER_TX_VERSION_GROUP_ID,
+			outputs: vec![TransactionOutput { value: 100, script_pubkey: "51".into() }],
+			expiry_height: 20,
+			..Default::default()
+		};
+		let without_sapling = serialize(&t);
+		assert_eq!(deserialize::<_, Transaction>(&without_sapling[..]), Ok(t.clone()));
+
+		t.sapling = Some(Sapling { balancing_value: 10, ..Default::default() });
+		let with_sapling = serialize(&t);
+		assert!(with_sapling != without_sapling);
+		assert!(t.hash() != dhash256(&without_sapling));
+		assert!(deserialize::<_, Transaction>(&with_sapling[..]).is_err());
+	}
+
+	#[test]
+	fn test_outpoint_is_null() {
+		assert!(OutPoint::null().is_null());
+		assert!(!OutPoint::default().is_null());
+		assert!(!OutPoint::new(H256::default(), 0).is_null());
+		assert!(!OutPoint::new(H256::from(1), u32::max_value()).is_null());
+	}
+
+	#[test]
+	fn test_outpoint_bip69_ordering() {
+		let hash1 = H256::from_reversed_str("0e53ec5dfb2cb8a71fec32dc9a634a35b7e24799295ddd5278217822e0b31f57");
+		let hash2 = H256::from_reversed_str("26aa6e6d8b9e49bb0630aac301db6757c02e3619feb4ee0eea81eb1672947024");
+		// hash2 is smaller in the internal byte order, but greater in the displayed byte order
+		assert!(&*hash2 < &*hash1);
+
+		let mut outpoints = vec![
+			OutPoint::new(hash2.clone(), 0),
+			OutPoint::new(hash1.clone(), 1),
+			OutPoint::new(hash1.clone(), 0),
+		];
+		outpoints.sort();
+		assert_eq!(outpoints, vec![
+			OutPoint::new(hash1.clone(), 0),
+			OutPoint::new(hash1, 1),
+			OutPoint::new(hash2, 0),
+		]);
+	}
+
+	#[test]
+	fn test_outpoint_serialization_roundtrip() {
+		let outpoint = OutPoint::new(H256::from(1), 2);
+		let serialized = serialize(&outpoint);
+		assert_eq!(serialized.len(), 36);
+		assert_eq!(serialized, "010000000000000000000000000000000000000000000000000000000000000002000000".into());
+		let deserialized: OutPoint = deserialize(&serialized as &[u8]).unwrap();
+		assert_eq!(deserialized, outpoint);
+		let deserialized: OutPoint = deserialize(&serialize(&OutPoint::null()) as &[u8]).unwrap();
+		assert_eq!(deserialized, OutPoint::null());
+	}
 }
@@ -2,8 +2,11 @@ use std::fmt;
 
 use hex::ToHex;
 
-use pairing::bls12_381::Bls12;
-use bellman::groth16::Proof as BellmanProof;
+use rand::{Rng, Rand, OsRng};
+use pairing::{Engine, Field, PrimeField, CurveAffine, CurveProjective};
+use pairing::bls12_381::{Bls12, Fr, G1};
+use bellman::groth16::{Proof as BellmanProof, verify_proof};
+use Groth16VerifyingKey;
 
 #[derive(Clone)]
 pub struct Proof([u8; 192]);
@@ -55,3 +58,175 @@ impl Proof {
 			.map_err(|_| /* only invalid point data possible, length is always ok */ Error::InvalidData)
 	}
 }
+
+/// Verifies multiple Groth16 proofs, created for the same verifying key, at once.
+///
+/// Instead of checking `e(A, B) = e(alpha, beta) * e(acc, gamma) * e(C, delta)` for every proof,
+/// the random linear combination of all equations is checked, which requires single final
+/// exponentiation. If batch check fails, proofs are verified individually to find invalid one.
+pub struct BatchVerifier<'a, T> {
+	vk: &'a Groth16VerifyingKey,
+	items: Vec<(T, BellmanProof<Bls12>, Vec<Fr>)>,
+}
+
+impl<'a, T> BatchVerifier<'a, T> {
+	pub fn new(vk: &'a Groth16VerifyingKey) -> Self {
+		BatchVerifier {
+			vk: vk,
+			items: Vec::new(),
+		}
+	}
+
+	/// Queue proof for verification. `tag` identifies the proof in case of failure.
+	pub fn queue(&mut self, tag: T, proof: BellmanProof<Bls12>, public_input: Vec<Fr>) {
+		self.items.push((tag, proof, public_input));
+	}
+
+	pub fn len(&self) -> usize {
+		self.items.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.items.is_empty()
+	}
+
+	/// Verify all queued proofs. Returns the smallest tag of invalid proofs (if any).
+	pub fn verify(&self) -> Result<(), &T> where T: Ord {
+		let mut rng = OsRng::new().expect("OS random generator is required for batch verification");
+		if self.verify_batch(&mut rng) {
+			return Ok(());
+		}
+
+		match self.invalid_proofs().into_iter().min() {
+			Some(tag) => Err(tag),
+			// that could only happen if the batch check has failed because of bad random
+			None => Ok(()),
+		}
+	}
+
+	/// Check all proofs at once, using random linear combination of verification equations.
+	pub fn verify_batch<R: Rng>(&self, rng: &mut R) -> bool {
+		if self.items.is_empty() {
+			return true;
+		}
+
+		let vk = &self.vk.1;
+		let mut pairs = Vec::with_capacity(self.items.len() + 2);
+		let mut acc_inputs = G1::zero();
+		let mut acc_c = G1::zero();
+		let mut r_sum = Fr::zero();
+		for &(_, ref proof, ref public_input) in &self.items {
+			if public_input.len() + 1 != vk.ic.len() {
+				return false;
+			}
+
+			let r = Fr::rand(rng);
+
+			let mut acc = vk.ic[0].into_projective();
+			for (input, ic) in public_input.iter().zip(vk.ic.iter().skip(1)) {
+				acc.add_assign(&ic.mul(input.into_repr()));
+			}
+			acc.mul_assign(r.into_repr());
+			acc_inputs.add_assign(&acc);
+
+			acc_c.add_assign(&proof.c.mul(r.into_repr()));
+
+			pairs.push((proof.a.mul(r.into_repr()).into_affine().prepare(), proof.b.prepare()));
+
+			r_sum.add_assign(&r);
+		}
+
+		let mut neg_gamma = vk.gamma_g2;
+		neg_gamma.negate();
+		let mut neg_delta = vk.delta_g2;
+		neg_delta.negate();
+		pairs.push((acc_inputs.into_affine().prepare(), neg_gamma.prepare()));
+		pairs.push((acc_c.into_affine().prepare(), neg_delta.prepare()));
+
+		let pairs_refs: Vec<_> = pairs.iter().map(|&(ref a, ref b)| (a, b)).collect();
+		let lhs = Bls12::final_exponentiation(&Bls12::miller_loop(pairs_refs.iter()));
+		let rhs = Bls12::pairing(vk.alpha_g1, vk.beta_g2).pow(r_sum.into_repr());
+		lhs == Some(rhs)
+	}
+
+	/// Verify every proof individually. Returns tags of all invalid proofs.
+	pub fn invalid_proofs(&self) -> Vec<&T> {
+		self.items.iter()
+			.filter(|&&(_, ref proof, ref public_input)| !verify_proof(&self.vk.0, proof, public_input).unwrap_or(false))
+			.map(|&(ref tag, _, _)| tag)
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use rand::{XorShiftRng, SeedableRng, Rand};
+	use pairing::Field;
+	use pairing::bls12_381::{Bls12, Fr};
+	use bellman::{Circuit, ConstraintSystem, SynthesisError};
+	use bellman::groth16::{generate_random_parameters, create_random_proof};
+	use Groth16VerifyingKey;
+	use super::BatchVerifier;
+
+	/// Proves knowledge of `a` and `b` such that `a * b = c`, where `c` is public.
+	struct MulCircuit {
+		a: Option<Fr>,
+		b: Option<Fr>,
+	}
+
+	impl Circuit<Bls12> for MulCircuit {
+		fn synthesize<CS: ConstraintSystem<Bls12>>(self, cs: &mut CS) -> Result<(), SynthesisError> {
+			let a_value = self.a;
+			let b_value = self.b;
+			let a = cs.alloc(|| "a", || a_value.ok_or(SynthesisError::AssignmentMissing))?;
+			let b = cs.alloc(|| "b", || b_value.ok_or(SynthesisError::AssignmentMissing))?;
+			let c = cs.alloc_input(|| "c", || {
+				let mut c = a_value.ok_or(SynthesisError::AssignmentMissing)?;
+				c.mul_assign(&b_value.ok_or(SynthesisError::AssignmentMissing)?);
+				Ok(c)
+			})?;
+			cs.enforce(|| "a * b = c", |lc| lc + a, |lc| lc + b, |lc| lc + c);
+			Ok(())
+		}
+	}
+
+	fn batch_verifier_with_proofs<'a>(vk: &'a Groth16VerifyingKey, rng: &mut XorShiftRng, params: &::bellman::groth16::Parameters<Bls12>) -> BatchVerifier<'a, usize> {
+		let mut batch = BatchVerifier::new(vk);
+		for i in 0..4 {
+			let a = Fr::rand(rng);
+			let b = Fr::rand(rng);
+			let mut c = a;
+			c.mul_assign(&b);
+			let proof = create_random_proof(MulCircuit { a: Some(a), b: Some(b) }, params, rng).unwrap();
+			batch.queue(i, proof, vec![c]);
+		}
+		batch
+	}
+
+	#[test]
+	fn batch_verification_of_valid_proofs() {
+		let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+		let params = generate_random_parameters::<Bls12, _, _>(MulCircuit { a: None, b: None }, rng).unwrap();
+		let vk: Groth16VerifyingKey = params.vk.clone().into();
+
+		let batch = batch_verifier_with_proofs(&vk, rng, &params);
+		assert!(batch.verify_batch(rng));
+		assert!(batch.invalid_proofs().is_empty());
+		assert_eq!(batch.verify(), Ok(()));
+	}
+
+	#[test]
+	fn batch_verification_of_proofs_with_single_invalid() {
+		let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+		let params = generate_random_parameters::<Bls12, _, _>(MulCircuit { a: None, b: None }, rng).unwrap();
+		let vk: Groth16VerifyingKey = params.vk.clone().into();
+
+		let mut batch = batch_verifier_with_proofs(&vk, rng, &params);
+		// proof #2 is valid, but it is for other public input
+		batch.items[2].2[0].add_assign(&Fr::one());
+
+		assert!(!batch.verify_batch(rng));
+		assert_eq!(batch.invalid_proofs(), vec![&2]);
+		assert_eq!(batch.verify(), Err(&2));
+	}
+}
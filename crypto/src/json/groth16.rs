@@ -1,6 +1,6 @@
 use std::fmt;
 use hex::FromHex;
-use bellman::groth16::{prepare_verifying_key, VerifyingKey as BellmanVerifyingKey};
+use bellman::groth16::VerifyingKey as BellmanVerifyingKey;
 use pairing::{EncodedPoint, bls12_381::{Bls12, G1Uncompressed, G2Uncompressed}};
 use serde::de::{self, Visitor, Deserialize, Deserializer};
 
@@ -11,20 +11,20 @@ use Groth16VerifyingKey;
 pub fn load_sapling_spend_verifying_key() -> Result<Groth16VerifyingKey, String> {
 	let spend_vk_json = include_bytes!("../../../res/sapling-spend-verifying-key.json");
 	let spend_vk = serde_json::from_slice::<VerifyingKey>(&spend_vk_json[..]).unwrap();
-	Ok(Groth16VerifyingKey(prepare_verifying_key(&spend_vk.into())))
+	Ok(BellmanVerifyingKey::<Bls12>::from(spend_vk).into())
 }
 
 /// Load Sapling output verification key.
 pub fn load_sapling_output_verifying_key() -> Result<Groth16VerifyingKey, String> {
 	let output_vk_json = include_bytes!("../../../res/sapling-output-verifying-key.json");
 	let output_vk = serde_json::from_slice::<VerifyingKey>(&output_vk_json[..]).unwrap();
-	Ok(Groth16VerifyingKey(prepare_verifying_key(&output_vk.into())))
+	Ok(BellmanVerifyingKey::<Bls12>::from(output_vk).into())
 }
 
 pub fn load_joinsplit_groth16_verifying_key() -> Result<Groth16VerifyingKey, String> {
 	let output_vk_json = include_bytes!("../../../res/sprout-groth16-key.json");
 	let output_vk = serde_json::from_slice::<VerifyingKey>(&output_vk_json[..]).unwrap();
-	Ok(Groth16VerifyingKey(prepare_verifying_key(&output_vk.into())))
+	Ok(BellmanVerifyingKey::<Bls12>::from(output_vk).into())
 }
 
 type G1 = Point<G1Uncompressed>;
@@ -1,6 +1,7 @@
 extern crate blake2_rfc;
 extern crate crypto as rcrypto;
 extern crate primitives;
+extern crate rand;
 extern crate serde_json;
 extern crate siphasher;
 extern crate bn;
@@ -28,9 +29,10 @@ lazy_static! {
 		{ sapling_crypto::jubjub::JubjubBls12::new() };
 }
 
+use std::io;
 use std::hash::Hasher;
 use rcrypto::sha1::Sha1;
-use rcrypto::sha2::Sha256;
+use rcrypto::sha2::Sha256 as Sha256Hasher;
 use rcrypto::ripemd160::Ripemd160;
 use siphasher::sip::SipHasher24;
 use primitives::hash::{H32, H160, H256};
@@ -44,6 +46,7 @@ pub use pghr13::{VerifyingKey as Pghr13VerifyingKey, Proof as Pghr13Proof, verif
 pub use groth16::{
 	Proof as Groth16Proof,
 	Error as Groth16Error,
+	BatchVerifier as Groth16BatchVerifier,
 };
 
 pub mod curve {
@@ -56,17 +59,27 @@ pub mod curve {
 	}
 }
 
-pub struct Groth16VerifyingKey(pub bellman::groth16::PreparedVerifyingKey<pairing::bls12_381::Bls12>);
+/// Groth16 verifying key. Raw key is required for batch verification.
+pub struct Groth16VerifyingKey(
+	pub bellman::groth16::PreparedVerifyingKey<pairing::bls12_381::Bls12>,
+	pub bellman::groth16::VerifyingKey<pairing::bls12_381::Bls12>,
+);
+
+impl From<bellman::groth16::VerifyingKey<pairing::bls12_381::Bls12>> for Groth16VerifyingKey {
+	fn from(vk: bellman::groth16::VerifyingKey<pairing::bls12_381::Bls12>) -> Self {
+		Groth16VerifyingKey(bellman::groth16::prepare_verifying_key(&vk), vk)
+	}
+}
 
 pub struct DHash160 {
-	sha256: Sha256,
+	sha256: Sha256Hasher,
 	ripemd: Ripemd160,
 }
 
 impl Default for DHash160 {
 	fn default() -> Self {
 		DHash160 {
-			sha256: Sha256::new(),
+			sha256: Sha256Hasher::new(),
 			ripemd: Ripemd160::new(),
 		}
 	}
@@ -104,14 +117,73 @@ impl Digest for DHash160 {
 	}
 }
 
+/// Incremental SHA-256 hasher.
+pub struct Sha256 {
+	hasher: Sha256Hasher,
+}
+
+impl Default for Sha256 {
+	fn default() -> Self {
+		Sha256 {
+			hasher: Sha256Hasher::new(),
+		}
+	}
+}
+
+impl Sha256 {
+	pub fn new() -> Self {
+		Sha256::default()
+	}
+
+	pub fn finish(mut self) -> H256 {
+		let mut result = H256::default();
+		self.result(&mut *result);
+		result
+	}
+}
+
+impl Digest for Sha256 {
+	fn input(&mut self, d: &[u8]) {
+		self.hasher.input(d)
+	}
+
+	fn result(&mut self, out: &mut [u8]) {
+		self.hasher.result(out);
+	}
+
+	fn reset(&mut self) {
+		self.hasher.reset();
+	}
+
+	fn output_bits(&self) -> usize {
+		256
+	}
+
+	fn block_size(&self) -> usize {
+		64
+	}
+}
+
+impl io::Write for Sha256 {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.input(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+/// Incremental double SHA-256 hasher.
 pub struct DHash256 {
-	hasher: Sha256,
+	hasher: Sha256Hasher,
 }
 
 impl Default for DHash256 {
 	fn default() -> Self {
 		DHash256 {
-			hasher: Sha256::new(),
+			hasher: Sha256Hasher::new(),
 		}
 	}
 }
@@ -153,6 +225,17 @@ impl Digest for DHash256 {
 	}
 }
 
+impl io::Write for DHash256 {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		self.input(buf);
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+
 /// RIPEMD160
 #[inline]
 pub fn ripemd160(input: &[u8]) -> H160 {
@@ -176,11 +259,9 @@ pub fn sha1(input: &[u8]) -> H160 {
 /// SHA-256
 #[inline]
 pub fn sha256(input: &[u8]) -> H256 {
-	let mut result = H256::default();
 	let mut hasher = Sha256::new();
 	hasher.input(input);
-	hasher.result(&mut *result);
-	result
+	hasher.finish()
 }
 
 /// SHA-256
@@ -190,16 +271,16 @@ pub fn sha256_compress(left: &[u8], right: &[u8]) -> H256 {
 	assert_eq!(right.len(), 32, "sha-256-compress 2nd argument should be 32-byte length (half-block)");
 
 	let mut result = H256::default();
-	let mut hasher = Sha256::new();
+	let mut hasher = Sha256Hasher::new();
 	hasher.input(left);
 	hasher.input(right);
 	hasher.result_no_padding(&mut *result);
 	result
 }
 
-/// SHA-256 and RIPEMD160
+/// SHA-256 and RIPEMD160. Used to derive P2PKH and P2SH address hashes.
 #[inline]
-pub fn dhash160(input: &[u8]) -> H160 {
+pub fn hash160(input: &[u8]) -> H160 {
 	let mut result = H160::default();
 	let mut hasher = DHash160::new();
 	hasher.input(input);
@@ -308,7 +389,9 @@ pub fn verify_ed25519(msg: &[u8], public_key: &[u8; 32], signature: &[u8; 64]) -
 mod tests {
 	use primitives::bytes::Bytes;
 	use primitives::hash::H256;
-	use super::{ripemd160, sha1, sha256, dhash160, dhash256, siphash24, checksum, sha256_compress, pedersen_hash};
+	use std::io::Write;
+	use super::{ripemd160, sha1, sha256, hash160, dhash256, siphash24, checksum, sha256_compress, pedersen_hash,
+		Sha256, DHash256};
 
 	#[test]
 	fn test_ripemd160() {
@@ -332,14 +415,14 @@ mod tests {
 	}
 
 	#[test]
-	fn test_dhash160() {
+	fn test_hash160() {
 		let expected = "b6a9c8c230722b7c748331a8b450f05566dc7d0f".into();
-		let result = dhash160(b"hello");
+		let result = hash160(b"hello");
 		assert_eq!(result, expected);
 
 		let expected = "865c71bfc7e314709207ab9e7e205c6f8e453d08".into();
 		let bytes: Bytes = "210292be03ed9475445cc24a34a115c641a67e4ff234ccb08cb4c5cea45caa526cb26ead6ead6ead6ead6eadac".into();
-		let result = dhash160(&bytes);
+		let result = hash160(&bytes);
 		assert_eq!(result, expected);
 	}
 
@@ -350,6 +433,26 @@ mod tests {
 		assert_eq!(result, expected);
 	}
 
+	#[test]
+	fn test_streaming_sha256() {
+		let input: Vec<u8> = (0..10_000).map(|i| i as u8).collect();
+		let mut hasher = Sha256::new();
+		for chunk in input.chunks(7) {
+			hasher.write_all(chunk).unwrap();
+		}
+		assert_eq!(hasher.finish(), sha256(&input));
+	}
+
+	#[test]
+	fn test_streaming_dhash256() {
+		let input: Vec<u8> = (0..10_000).map(|i| i as u8).collect();
+		let mut hasher = DHash256::new();
+		for chunk in input.chunks(7) {
+			hasher.write_all(chunk).unwrap();
+		}
+		assert_eq!(hasher.finish(), dhash256(&input));
+	}
+
 	#[test]
 	fn test_siphash24() {
 		let expected = 0x74f839c593dc67fd_u64;
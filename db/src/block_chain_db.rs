@@ -1,8 +1,9 @@
 use std::collections::HashMap;
-use std::fs;
+use std::{cmp, fs, io};
 use std::path::Path;
 use parking_lot::RwLock;
 use hash::H256;
+use primitives::bigint::U256;
 use bytes::Bytes;
 use chain::{
 	IndexedBlock, IndexedBlockHeader, IndexedTransaction,
@@ -19,19 +20,32 @@ use kv::{
 use kv::{
 	COL_COUNT, COL_BLOCK_HASHES, COL_BLOCK_HEADERS, COL_BLOCK_TRANSACTIONS, COL_TRANSACTIONS,
 	COL_TRANSACTIONS_META, COL_BLOCK_NUMBERS, COL_SAPLING_NULLIFIERS, COL_SPROUT_NULLIFIERS,
-	COL_SPROUT_BLOCK_ROOTS, COL_TREE_STATES,
+	COL_SPROUT_BLOCK_ROOTS, COL_TREE_STATES, COL_BLOCK_TIMESTAMPS,
 };
+use migration;
+use utxo_snapshot::{self, UtxoSnapshotRecord};
 use storage::{
 	BlockRef, Error, BlockHeaderProvider, BlockProvider, BlockOrigin, TransactionMeta,
 	TransactionMetaProvider, TransactionProvider, TransactionOutputProvider, BlockChain, Store,
 	SideChainOrigin, ForkChain, Forkable, CanonStore, BestBlock, NullifierTracker,
-	EpochTag, EpochRef, SproutTreeState, SaplingTreeState, TreeStateProvider,
+	EpochTag, EpochRef, SproutTreeState, SaplingTreeState, TreeStateProvider, BlockIndex,
+	ChainIndex, IndexKind, TransactionLocation, SpentInfo,
 };
 
-const KEY_BEST_BLOCK_NUMBER: &'static str = "best_block_number";
-const KEY_BEST_BLOCK_HASH: &'static str = "best_block_hash";
+pub const KEY_BEST_BLOCK_NUMBER: &'static str = "best_block_number";
+pub const KEY_BEST_BLOCK_HASH: &'static str = "best_block_hash";
+const KEY_TRANSACTION_INDEX_NEXT_BLOCK: &'static str = "transaction_index_next_block";
+const KEY_ADDRESS_INDEX_NEXT_BLOCK: &'static str = "address_index_next_block";
+const KEY_SPENT_INDEX_NEXT_BLOCK: &'static str = "spent_index_next_block";
+pub const KEY_UTXO_SNAPSHOT_BLOCK_NUMBER: &'static str = "utxo_snapshot_block_number";
 
 const MAX_FORK_ROUTE_PRESET: usize = 2048;
+/// Canon blocks are indexed by `header.time / BLOCK_TIMESTAMP_BUCKET`.
+pub const BLOCK_TIMESTAMP_BUCKET: u32 = 3600;
+/// Max number of hashes returned by `block_hashes_by_time`.
+pub const MAX_BLOCK_HASHES_BY_TIME: usize = 16 * 1024;
+/// Blocks, which are more than 2 hours ahead of the node time, are rejected by verification.
+const BLOCK_TIMESTAMP_MAX_FUTURE: u32 = 2 * 60 * 60;
 
 pub struct BlockChainDatabase<T> where T: KeyValueDatabase {
 	best_block: RwLock<BestBlock>,
@@ -57,8 +71,9 @@ mod cache {
 	pub const CACHE_TRANSACTION_META: u32 = 20;
 	pub const CACHE_HEADERS: u32 = 15;
 	pub const CACHE_BLOCK_HASHES: u32 = 5;
-	pub const CACHE_BLOCK_TRANSACTIONS: u32 = 10;
+	pub const CACHE_BLOCK_TRANSACTIONS: u32 = 9;
 	pub const CACHE_BLOCK_NUMBERS: u32 = 5;
+	pub const CACHE_BLOCK_TIMESTAMPS: u32 = 1;
 	pub const CACHE_SPROUT_NULLIFIERS: u32 = 5;
 	pub const CACHE_SAPLING_NULLIFIERS: u32 = 5;
 	pub const CACHE_TREE_STATES: u32 = 10;
@@ -77,6 +92,7 @@ mod cache {
 			CACHE_BLOCK_HASHES +
 			CACHE_BLOCK_TRANSACTIONS +
 			CACHE_BLOCK_NUMBERS +
+			CACHE_BLOCK_TIMESTAMPS +
 			CACHE_SPROUT_NULLIFIERS +
 			CACHE_SAPLING_NULLIFIERS +
 			CACHE_TREE_STATES +
@@ -86,7 +102,9 @@ mod cache {
 }
 
 impl BlockChainDatabase<CacheDatabase<AutoFlushingOverlayDatabase<DiskDatabase>>> {
-	pub fn open_at_path<P>(path: P, total_cache: usize) -> Result<Self, Error> where P: AsRef<Path> {
+	/// Opens database at given path. `total_cache` is the size of the backend caches (in MB),
+	/// `read_cache_entries` is the number of entries of every kind, kept in the read cache.
+	pub fn open_at_path<P>(path: P, total_cache: usize, read_cache_entries: usize) -> Result<Self, Error> where P: AsRef<Path> {
 		fs::create_dir_all(path.as_ref()).map_err(|err| Error::DatabaseError(err.to_string()))?;
 		let mut cfg = DatabaseConfig::with_columns(Some(COL_COUNT));
 
@@ -97,6 +115,7 @@ impl BlockChainDatabase<CacheDatabase<AutoFlushingOverlayDatabase<DiskDatabase>>
 		cache::set(&mut cfg, total_cache, COL_BLOCK_HASHES, cache::CACHE_BLOCK_HASHES);
 		cache::set(&mut cfg, total_cache, COL_BLOCK_TRANSACTIONS, cache::CACHE_BLOCK_TRANSACTIONS);
 		cache::set(&mut cfg, total_cache, COL_BLOCK_NUMBERS, cache::CACHE_BLOCK_NUMBERS);
+		cache::set(&mut cfg, total_cache, COL_BLOCK_TIMESTAMPS, cache::CACHE_BLOCK_TIMESTAMPS);
 
 		cache::set(&mut cfg, total_cache, COL_SPROUT_NULLIFIERS, cache::CACHE_SPROUT_NULLIFIERS);
 		cache::set(&mut cfg, total_cache, COL_SAPLING_NULLIFIERS, cache::CACHE_SAPLING_NULLIFIERS);
@@ -108,7 +127,10 @@ impl BlockChainDatabase<CacheDatabase<AutoFlushingOverlayDatabase<DiskDatabase>>
 		cfg.bloom_filters.insert(Some(COL_TRANSACTIONS_META), 32);
 
 		match DiskDatabase::open(cfg, path) {
-			Ok(db) => Ok(Self::open_with_cache(db)),
+			Ok(db) => {
+				migration::migrate(&db, migration::MIGRATIONS, migration::DB_VERSION)?;
+				Ok(Self::open_with_cache(db, read_cache_entries))
+			},
 			Err(err) => Err(Error::DatabaseError(err))
 		}
 	}
@@ -128,8 +150,8 @@ impl BlockChainDatabase<MemoryDatabase> {
 }
 
 impl<T> BlockChainDatabase<CacheDatabase<AutoFlushingOverlayDatabase<T>>> where T: KeyValueDatabase {
-	pub fn open_with_cache(db: T) -> Self {
-		let db = CacheDatabase::new(AutoFlushingOverlayDatabase::new(db, 50));
+	pub fn open_with_cache(db: T, read_cache_entries: usize) -> Self {
+		let db = CacheDatabase::with_capacity(AutoFlushingOverlayDatabase::new(db, 50), read_cache_entries);
 		let best_block = Self::read_best_block(&db).unwrap_or_default();
 		BlockChainDatabase {
 			best_block: RwLock::new(best_block),
@@ -165,6 +187,36 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		self.best_block.read().clone()
 	}
 
+	/// Writes snapshot of the canon chain UTXO set and shielded pools state.
+	pub fn dump_utxo<W>(&self, writer: W) -> Result<(), Error> where W: io::Write {
+		utxo_snapshot::dump_utxo(self, writer)
+	}
+
+	/// Reads and verifies UTXO set snapshot, written by `dump_utxo`, and imports it into the empty database.
+	/// Snapshot blocks (and blocks before them) can't be decanonized after import.
+	pub fn load_utxo<R>(&self, reader: R) -> Result<(), Error> where R: io::Read {
+		let mut best_block = self.best_block.write();
+		if !best_block.hash.is_zero() || self.block_hash(0).is_some() {
+			return Err(Error::InvalidUtxoSnapshot("database is not empty".into()));
+		}
+
+		*best_block = utxo_snapshot::import_utxo(&self.db, reader)?;
+		Ok(())
+	}
+
+	/// Returns number of the best block of the imported UTXO snapshot.
+	pub fn utxo_snapshot_block_number(&self) -> Option<u32> {
+		self.get(Key::Meta(KEY_UTXO_SNAPSHOT_BLOCK_NUMBER))
+			.and_then(Value::as_meta)
+			.map(|number| deserialize(&**number).expect("Inconsistent DB. Invalid UTXO snapshot block number."))
+	}
+
+	/// Returns record of the imported UTXO snapshot. Records are numbered from 0 in the snapshot order.
+	pub fn utxo_snapshot_record(&self, index: u32) -> Option<UtxoSnapshotRecord> {
+		self.get(Key::UtxoSnapshotRecord(index))
+			.and_then(Value::as_utxo_snapshot_record)
+	}
+
 	pub fn fork(&self, side_chain: SideChainOrigin) -> Result<ForkChainDatabase<T>, Error> {
 		let overlay = BlockChainDatabase::open(OverlayDatabase::new(&self.db));
 
@@ -186,8 +238,10 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 
 	pub fn switch_to_fork(&self, fork: ForkChainDatabase<T>) -> Result<(), Error> {
 		let mut best_block = self.best_block.write();
+		// only switch to the new best block when fork changes are persisted
+		fork.blockchain.db.flush().map_err(Error::DatabaseError)?;
 		*best_block = fork.blockchain.best_block.read().clone();
-		fork.blockchain.db.flush().map_err(Error::DatabaseError)
+		Ok(())
 	}
 
 	pub fn block_origin(&self, header: &IndexedBlockHeader) -> Result<BlockOrigin, Error> {
@@ -223,7 +277,7 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 							.collect(),
 						block_number: block_number,
 					};
-					if block_number > best_block.number {
+					if self.has_more_work_than_best(header, block_number, &best_block) {
 						return Ok(BlockOrigin::SideChainBecomingCanonChain(origin))
 					} else {
 						return Ok(BlockOrigin::SideChain(origin))
@@ -241,6 +295,21 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		Err(Error::AncientFork)
 	}
 
+	/// Returns true if side chain, ending with given header, has more work than the best chain.
+	/// Chains with equal work are not switched to, so that the first seen chain stays canon.
+	fn has_more_work_than_best(&self, header: &IndexedBlockHeader, block_number: u32, best_block: &BestBlock) -> bool {
+		let parent_index = self.block_index_or_compute(&header.raw.previous_header_hash);
+		let best_index = self.block_index_or_compute(&best_block.hash);
+		match (parent_index, best_index) {
+			(Some(parent_index), Some(best_index)) => {
+				let block_work = header.raw.work().unwrap_or_else(U256::zero);
+				parent_index.chain_work.overflowing_add(block_work).0 > best_index.chain_work
+			},
+			// index is computed for every stored block => fallback to chain length is never used
+			_ => block_number > best_block.number,
+		}
+	}
+
 	pub fn insert(&self, block: IndexedBlock) -> Result<(), Error> {
 		if self.contains_block(block.hash().clone().into()) {
 			return Ok(())
@@ -265,13 +334,30 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 				.expect(&format!("Corrupted database - no sapling root for block {}", parent_hash))
 		};
 
+		let block_work = block.header.raw.work().unwrap_or_else(U256::zero);
+		let block_index = if parent_hash.is_zero() {
+			BlockIndex {
+				height: 0,
+				chain_work: block_work,
+			}
+		} else {
+			let parent_index = self.block_index_or_compute(&parent_hash)
+				.ok_or_else(|| Error::DatabaseError(format!("Corrupted database - no header of ancestor of block {}", parent_hash)))?;
+			BlockIndex {
+				height: parent_index.height + 1,
+				chain_work: parent_index.chain_work.overflowing_add(block_work).0,
+			}
+		};
+
 		let sapling_tree_root = block.header.raw.final_sapling_root;
+		let (header, transactions) = block.into_parts();
 		let mut update = DBTransaction::new();
-		update.insert(KeyValue::BlockHeader(*block.hash(), block.header.raw));
-		let tx_hashes = block.transactions.iter().map(|tx| tx.hash).collect::<Vec<_>>();
-		update.insert(KeyValue::BlockTransactions(block.header.hash, List::from(tx_hashes)));
+		update.insert(KeyValue::BlockIndex(header.hash, block_index));
+		update.insert(KeyValue::BlockHeader(header.hash, header.raw));
+		let tx_hashes = transactions.iter().map(|tx| tx.hash).collect::<Vec<_>>();
+		update.insert(KeyValue::BlockTransactions(header.hash, List::from(tx_hashes)));
 
-		for tx in block.transactions.into_iter() {
+		for tx in transactions.into_iter() {
 
 			if let Some(ref js) = tx.raw.join_split {
 				for js_descriptor in js.descriptions.iter() {
@@ -297,7 +383,7 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		}
 
 		let sprout_tree_root = sprout_tree_state.root();
-		update.insert(KeyValue::SproutBlockRoot(block.header.hash, sprout_tree_root));
+		update.insert(KeyValue::SproutBlockRoot(header.hash, sprout_tree_root));
 		update.insert(KeyValue::SproutTreeState(sprout_tree_root, sprout_tree_state));
 
 		// TODO: possible optimization is not to store sapling trees until sapling is activated
@@ -306,22 +392,54 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		self.db.write(update).map_err(Error::DatabaseError)
 	}
 
+	/// Returns index of the stored block. Side chain blocks, inserted before block index has been
+	/// introduced, have no index => it is computed from headers, starting from the nearest indexed ancestor.
+	fn block_index_or_compute(&self, hash: &H256) -> Option<BlockIndex> {
+		let mut unindexed_work = Vec::new();
+		let mut hash = hash.clone();
+		let mut index = loop {
+			if let Some(index) = self.block_index(&hash) {
+				break index;
+			}
+
+			let header = self.block_header(hash.into())?;
+			let block_work = header.raw.work().unwrap_or_else(U256::zero);
+			if header.raw.previous_header_hash.is_zero() {
+				break BlockIndex {
+					height: 0,
+					chain_work: block_work,
+				};
+			}
+
+			unindexed_work.push(block_work);
+			hash = header.raw.previous_header_hash;
+		};
+
+		for block_work in unindexed_work.into_iter().rev() {
+			index = BlockIndex {
+				height: index.height + 1,
+				chain_work: index.chain_work.overflowing_add(block_work).0,
+			};
+		}
+
+		Some(index)
+	}
+
 	/// Rollbacks single best block.
 	fn rollback_best(&self) -> Result<H256, Error> {
 		let best_block_hash = self.best_block.read().hash.clone();
-		let tx_to_decanonize = self.block_transaction_hashes(best_block_hash.into());
 		let decanonized_hash = self.decanonize()?;
 		debug_assert_eq!(best_block_hash, decanonized_hash);
 
 		// and now remove decanonized block from database
 		// all code currently works in assumption that origin of all blocks is one of:
 		// {CanonChain, SideChain, SideChainBecomingCanonChain}
+		// transactions are left in the database, because the same transaction could also be
+		// included in some side chain block
 		let mut update = DBTransaction::new();
 		update.delete(Key::BlockHeader(decanonized_hash.clone()));
+		update.delete(Key::BlockIndex(decanonized_hash.clone()));
 		update.delete(Key::BlockTransactions(decanonized_hash.clone()));
-		for tx_hash in tx_to_decanonize {
-			update.delete(Key::Transaction(tx_hash));
-		}
 
 		self.db.write(update).map_err(Error::DatabaseError)?;
 
@@ -371,28 +489,24 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		update.insert(KeyValue::Meta(KEY_BEST_BLOCK_NUMBER, serialize(&new_best_block.number)));
 
 		let mut modified_meta: HashMap<H256, TransactionMeta> = HashMap::new();
-		if let Some(tx) = block.transactions.first() {
+		if let Some(tx) = block.transactions().first() {
 			let meta = TransactionMeta::new_coinbase(new_best_block.number, tx.raw.outputs.len());
 			modified_meta.insert(tx.hash.clone(), meta);
 		}
 
-		for tx in block.transactions.iter().skip(1) {
+		for tx in block.transactions().iter().skip(1) {
 			modified_meta.insert(tx.hash.clone(), TransactionMeta::new(new_best_block.number, tx.raw.outputs.len()));
 
-			if let Some(ref js) = tx.raw.join_split {
-				for js_descriptor in js.descriptions.iter() {
-					for nullifier in &js_descriptor.nullifiers[..] {
-						let nullifier_key = EpochRef::new(
-							EpochTag::Sprout,
-							H256::from(&nullifier[..])
-						);
-						if self.contains_nullifier(nullifier_key) {
-							error!(target: "db", "Duplicate sprout nullifer during canonization: {:?}", nullifier_key);
-							return Err(Error::CannotCanonize);
-						}
-						update.insert(KeyValue::Nullifier(nullifier_key));
-					}
+			for nullifier in tx.raw.sprout_nullifiers() {
+				let nullifier_key = EpochRef::new(
+					EpochTag::Sprout,
+					H256::from(&nullifier[..])
+				);
+				if self.contains_nullifier(nullifier_key) {
+					error!(target: "db", "Duplicate sprout nullifer during canonization: {:?}", nullifier_key);
+					return Err(Error::CannotCanonize);
 				}
+				update.insert(KeyValue::Nullifier(nullifier_key));
 			}
 
 			if let Some(ref sapling) = tx.raw.sapling {
@@ -440,6 +554,26 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 			update.insert(KeyValue::TransactionMeta(hash, meta));
 		}
 
+		// final tree roots of canonized block are now valid anchors
+		let sprout_root = self.sprout_block_root(hash).ok_or_else(|| {
+			error!(target: "db", "No sprout root for block during canonization: {}", hash.reversed());
+			Error::CannotCanonize
+		})?;
+		update.insert(KeyValue::Anchor(EpochRef::new(EpochTag::Sprout, sprout_root)));
+		update.insert(KeyValue::Anchor(EpochRef::new(EpochTag::Sapling, block.header.raw.final_sapling_root)));
+
+		let timestamp_bucket = block.header.raw.time / BLOCK_TIMESTAMP_BUCKET;
+		let mut bucket_hashes = self.block_timestamp_bucket(timestamp_bucket);
+		bucket_hashes.push(hash.clone());
+		update.insert(KeyValue::BlockTimestamps(timestamp_bucket, List::from(bucket_hashes)));
+
+		for index in IndexKind::all().iter().cloned() {
+			if self.index_next_block(index) == Some(new_best_block.number) {
+				Self::update_index(&mut update, index, &block, new_best_block.number, true);
+				update.insert(KeyValue::Meta(index_next_block_key(index), serialize(&(new_best_block.number + 1))));
+			}
+		}
+
 		self.db.write(update).map_err(Error::DatabaseError)?;
 		*best_block = new_best_block;
 		Ok(())
@@ -447,6 +581,11 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 
 	pub fn decanonize(&self) -> Result<H256, Error> {
 		let mut best_block = self.best_block.write();
+		if self.utxo_snapshot_block_number().map_or(false, |number| best_block.number <= number) {
+			error!(target: "db", "Cannot decanonize block of the imported UTXO snapshot: {}", best_block.hash.reversed());
+			return Err(Error::CannotDecanonize);
+		}
+
 		let block = match self.block(best_block.hash.clone().into()) {
 			Some(block) => block,
 			None => {
@@ -476,21 +615,17 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		update.insert(KeyValue::Meta(KEY_BEST_BLOCK_NUMBER, serialize(&new_best_block.number)));
 
 		let mut modified_meta: HashMap<H256, TransactionMeta> = HashMap::new();
-		for tx in block.transactions.iter().skip(1) {
-			if let Some(ref js) = tx.raw.join_split {
-				for js_descriptor in js.descriptions.iter() {
-					for nullifier in &js_descriptor.nullifiers[..] {
-						let nullifier_key = EpochRef::new(
-							EpochTag::Sprout,
-							H256::from(&nullifier[..])
-						);
-						if !self.contains_nullifier(nullifier_key) {
-							error!(target: "db", "cannot decanonize, no sprout nullifier: {:?}", nullifier_key);
-							return Err(Error::CannotDecanonize);
-						}
-						update.delete(Key::Nullifier(nullifier_key));
-					}
+		for tx in block.transactions().iter().skip(1) {
+			for nullifier in tx.raw.sprout_nullifiers() {
+				let nullifier_key = EpochRef::new(
+					EpochTag::Sprout,
+					H256::from(&nullifier[..])
+				);
+				if !self.contains_nullifier(nullifier_key) {
+					error!(target: "db", "cannot decanonize, no sprout nullifier: {:?}", nullifier_key);
+					return Err(Error::CannotDecanonize);
 				}
+				update.delete(Key::Nullifier(nullifier_key));
 			}
 
 			if let Some(ref sapling) = tx.raw.sapling {
@@ -538,7 +673,36 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 			update.insert(KeyValue::TransactionMeta(hash, meta));
 		}
 
-		for tx in block.transactions {
+		// tree roots stay anchors while they're also final roots of the new best block (i.e. when
+		// the decanonized block has no shielded outputs), because tree only grows
+		let sprout_root = self.sprout_block_root(&block_hash);
+		if sprout_root != self.sprout_block_root(&new_best_block.hash) {
+			if let Some(sprout_root) = sprout_root {
+				update.delete(Key::Anchor(EpochRef::new(EpochTag::Sprout, sprout_root)));
+			}
+		}
+		let sapling_root = block.header.raw.final_sapling_root;
+		if Some(sapling_root) != self.sapling_block_root(&new_best_block.hash) {
+			update.delete(Key::Anchor(EpochRef::new(EpochTag::Sapling, sapling_root)));
+		}
+
+		let timestamp_bucket = block.header.raw.time / BLOCK_TIMESTAMP_BUCKET;
+		let mut bucket_hashes = self.block_timestamp_bucket(timestamp_bucket);
+		bucket_hashes.retain(|hash| *hash != block_hash);
+		if bucket_hashes.is_empty() {
+			update.delete(Key::BlockTimestamps(timestamp_bucket));
+		} else {
+			update.insert(KeyValue::BlockTimestamps(timestamp_bucket, List::from(bucket_hashes)));
+		}
+
+		for index in IndexKind::all().iter().cloned() {
+			if self.index_next_block(index) == Some(block_number + 1) {
+				Self::update_index(&mut update, index, &block, block_number, false);
+				update.insert(KeyValue::Meta(index_next_block_key(index), serialize(&block_number)));
+			}
+		}
+
+		for tx in block.into_transactions() {
 			update.delete(Key::TransactionMeta(tx.hash));
 		}
 
@@ -547,6 +711,56 @@ impl<T> BlockChainDatabase<T> where T: KeyValueDatabase {
 		Ok(block_hash)
 	}
 
+	/// Adds index entries of the canon block `block_number` to the `update` (or removes them, if
+	/// block is decanonized). Every entry has its own key, so no index entries are read here.
+	fn update_index(update: &mut DBTransaction, index: IndexKind, block: &IndexedBlock, block_number: u32, insert: bool) {
+		match index {
+			IndexKind::Transaction => for (tx_index, tx) in block.transactions().iter().enumerate() {
+				if insert {
+					update.insert(KeyValue::TransactionIndex(tx.hash.clone(), TransactionLocation {
+						block_hash: block.hash().clone(),
+						index: tx_index as u32,
+					}));
+				} else {
+					update.delete(Key::TransactionIndex(tx.hash.clone()));
+				}
+			},
+			IndexKind::Address => for tx in block.transactions() {
+				for (output_index, output) in tx.raw.outputs.iter().enumerate() {
+					let outpoint = OutPoint {
+						hash: tx.hash.clone(),
+						index: output_index as u32,
+					};
+					if insert {
+						update.insert(KeyValue::AddressIndex(output.script_pubkey.clone(), outpoint));
+					} else {
+						update.delete(Key::AddressIndex(output.script_pubkey.clone(), outpoint));
+					}
+				}
+			},
+			IndexKind::Spent => for tx in block.transactions().iter().skip(1) {
+				for (input_index, input) in tx.raw.inputs.iter().enumerate() {
+					if insert {
+						update.insert(KeyValue::SpentIndex(input.previous_output.clone(), SpentInfo {
+							hash: tx.hash.clone(),
+							input_index: input_index as u32,
+							height: block_number,
+						}));
+					} else {
+						update.delete(Key::SpentIndex(input.previous_output.clone()));
+					}
+				}
+			},
+		}
+	}
+
+	fn block_timestamp_bucket(&self, bucket: u32) -> Vec<H256> {
+		self.get(Key::BlockTimestamps(bucket))
+			.and_then(Value::as_block_timestamps)
+			.map(List::into)
+			.unwrap_or_default()
+	}
+
 	fn get(&self, key: Key) -> Option<Value> {
 		self.db.get(&key).expect("db value to be fine").into_option()
 	}
@@ -585,6 +799,11 @@ impl<T> BlockProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
 			.and_then(Value::as_block_hash)
 	}
 
+	fn block_index(&self, hash: &H256) -> Option<BlockIndex> {
+		self.get(Key::BlockIndex(hash.clone()))
+			.and_then(Value::as_block_index)
+	}
+
 	fn block(&self, block_ref: BlockRef) -> Option<IndexedBlock> {
 		self.resolve_hash(block_ref)
 			.and_then(|block_hash| {
@@ -643,8 +862,11 @@ impl<T> TransactionOutputProvider for BlockChainDatabase<T> where T: KeyValueDat
 	fn transaction_output(&self, prevout: &OutPoint, _transaction_index: usize) -> Option<TransactionOutput> {
 		// return previous transaction outputs only for canon chain transactions
 		self.transaction_meta(&prevout.hash)
-			.and_then(|_| self.transaction(&prevout.hash))
-			.and_then(|tx| tx.raw.outputs.into_iter().nth(prevout.index as usize))
+			.and_then(|_| match self.transaction(&prevout.hash) {
+				Some(tx) => tx.raw.outputs.into_iter().nth(prevout.index as usize),
+				// transactions of the imported UTXO snapshot only have their unspent outputs stored
+				None => self.get(Key::UtxoOutput(prevout.clone())).and_then(Value::as_utxo_output),
+			})
 	}
 
 	fn is_spent(&self, prevout: &OutPoint) -> bool {
@@ -677,6 +899,77 @@ impl<T> TreeStateProvider for BlockChainDatabase<T> where T: KeyValueDatabase {
 		self.block_header(BlockRef::Hash(*block_hash))
 			.map(|header| header.raw.final_sapling_root)
 	}
+
+	fn contains_anchor(&self, anchor: EpochRef) -> bool {
+		self.get(Key::Anchor(anchor)).is_some()
+	}
+}
+
+fn index_next_block_key(index: IndexKind) -> &'static str {
+	match index {
+		IndexKind::Transaction => KEY_TRANSACTION_INDEX_NEXT_BLOCK,
+		IndexKind::Address => KEY_ADDRESS_INDEX_NEXT_BLOCK,
+		IndexKind::Spent => KEY_SPENT_INDEX_NEXT_BLOCK,
+	}
+}
+
+impl<T> ChainIndex for BlockChainDatabase<T> where T: KeyValueDatabase {
+	fn index_next_block(&self, index: IndexKind) -> Option<u32> {
+		self.get(Key::Meta(index_next_block_key(index)))
+			.and_then(Value::as_meta)
+			.map(|next_block| deserialize(&**next_block).expect("Inconsistent DB. Invalid index progress."))
+	}
+
+	fn enable_index(&self, index: IndexKind) -> Result<(), Error> {
+		// prevent concurrent canonization
+		let _best_block = self.best_block.read();
+		if self.index_next_block(index).is_some() {
+			return Ok(());
+		}
+
+		let mut update = DBTransaction::new();
+		update.insert(KeyValue::Meta(index_next_block_key(index), serialize(&0u32)));
+		self.db.write(update).map_err(Error::DatabaseError)
+	}
+
+	fn index_next_blocks(&self, index: IndexKind, max_blocks: u32) -> Result<u32, Error> {
+		// prevent concurrent canonization
+		let _best_block = self.best_block.read();
+		let first_block = self.index_next_block(index).ok_or(Error::IndexDisabled)?;
+
+		let mut next_block = first_block;
+		let mut update = DBTransaction::new();
+		while next_block - first_block < max_blocks {
+			let block = match self.block(BlockRef::Number(next_block)) {
+				Some(block) => block,
+				None => break,
+			};
+
+			Self::update_index(&mut update, index, &block, next_block, true);
+			next_block += 1;
+		}
+
+		if next_block != first_block {
+			update.insert(KeyValue::Meta(index_next_block_key(index), serialize(&next_block)));
+			self.db.write(update).map_err(Error::DatabaseError)?;
+		}
+
+		Ok(next_block)
+	}
+
+	fn transaction_location(&self, hash: &H256) -> Option<TransactionLocation> {
+		self.get(Key::TransactionIndex(hash.clone()))
+			.and_then(Value::as_transaction_location)
+	}
+
+	fn address_outputs(&self, script_pubkey: &[u8]) -> Vec<OutPoint> {
+		self.db.address_outputs(&script_pubkey.into()).expect("db value to be fine")
+	}
+
+	fn spent_info(&self, outpoint: &OutPoint) -> Option<SpentInfo> {
+		self.get(Key::SpentIndex(outpoint.clone()))
+			.and_then(Value::as_spent_info)
+	}
 }
 
 impl<T> BlockChain for BlockChainDatabase<T> where T: KeyValueDatabase {
@@ -728,8 +1021,57 @@ impl<T> Store for BlockChainDatabase<T> where T: KeyValueDatabase {
 		BlockChainDatabase::best_block(self)
 	}
 
+	fn dump_utxo(&self, writer: &mut io::Write) -> Result<(), Error> {
+		BlockChainDatabase::dump_utxo(self, writer)
+	}
+
+	fn load_utxo(&self, reader: &mut io::Read) -> Result<(), Error> {
+		BlockChainDatabase::load_utxo(self, reader)
+	}
+
 	/// get best header
 	fn best_header(&self) -> IndexedBlockHeader {
 		self.block_header(self.best_block().hash.into()).expect("best block header should be in db; qed")
 	}
+
+	fn block_hashes_by_time(&self, low: u32, high: u32) -> Vec<H256> {
+		// canon blocks are never older than genesis block and are not expected to be
+		// far ahead of the best block => do not scan buckets outside of this range
+		let genesis_time = match self.block_header(0.into()) {
+			Some(header) => header.raw.time,
+			None => return Vec::new(),
+		};
+		let best_time = self.best_header().raw.time;
+		let low = cmp::max(low, genesis_time);
+		let high = cmp::min(high, best_time.saturating_add(BLOCK_TIMESTAMP_MAX_FUTURE));
+		if low > high {
+			return Vec::new();
+		}
+
+		let mut blocks = Vec::new();
+		for bucket in low / BLOCK_TIMESTAMP_BUCKET..high / BLOCK_TIMESTAMP_BUCKET + 1 {
+			// buckets are visited in time order => blocks from next buckets are newer than collected
+			if blocks.len() >= MAX_BLOCK_HASHES_BY_TIME {
+				break;
+			}
+
+			for hash in self.block_timestamp_bucket(bucket) {
+				let time = match self.block_header(hash.into()) {
+					Some(header) => header.raw.time,
+					None => continue,
+				};
+				let number = match self.block_number(&hash) {
+					Some(number) => number,
+					None => continue,
+				};
+				if time >= low && time <= high {
+					blocks.push((time, number, hash));
+				}
+			}
+		}
+
+		blocks.sort_by_key(|&(time, number, _)| (time, number));
+		blocks.truncate(MAX_BLOCK_HASHES_BY_TIME);
+		blocks.into_iter().map(|(_, _, hash)| hash).collect()
+	}
 }
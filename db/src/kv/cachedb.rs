@@ -1,20 +1,38 @@
 use lru_cache::LruCache;
 use parking_lot::Mutex;
 use hash::H256;
-use chain::BlockHeader;
+use bytes::Bytes;
+use chain::{BlockHeader, Transaction as ChainTransaction, OutPoint};
+use storage::TransactionMeta;
 use kv::{KeyValueDatabase, KeyState, Operation, KeyValue, Key, Value, Transaction};
 
+/// Default number of entries of every kind, kept in the read cache.
+/// 144 (blocks per day) * 14 (days) + 100 (arbitrary number)
+pub const DEFAULT_READ_CACHE_ENTRIES: usize = 2116;
+
+/// Database with in-memory LRU read cache for block headers and UTXO entries
+/// (transactions and transactions meta).
+///
+/// Cache is updated on every write, so stale entries are never returned.
 pub struct CacheDatabase<T> where T: KeyValueDatabase {
 	db: T,
 	header: Mutex<LruCache<H256, KeyState<BlockHeader>>>,
+	transaction: Mutex<LruCache<H256, KeyState<ChainTransaction>>>,
+	transaction_meta: Mutex<LruCache<H256, KeyState<TransactionMeta>>>,
 }
 
 impl<T> CacheDatabase<T> where T: KeyValueDatabase {
 	pub fn new(db: T) -> Self {
+		CacheDatabase::with_capacity(db, DEFAULT_READ_CACHE_ENTRIES)
+	}
+
+	/// Creates cache database, which keeps at most `capacity` entries of every kind.
+	pub fn with_capacity(db: T, capacity: usize) -> Self {
 		CacheDatabase {
 			db: db,
-			// 144 (blocks per day) * 14 (days) + 100 (arbitrary number)
-			header: Mutex::new(LruCache::new(2116)),
+			header: Mutex::new(LruCache::new(capacity)),
+			transaction: Mutex::new(LruCache::new(capacity)),
+			transaction_meta: Mutex::new(LruCache::new(capacity)),
 		}
 	}
 }
@@ -26,9 +44,21 @@ impl<T> KeyValueDatabase for CacheDatabase<T> where T: KeyValueDatabase {
 				Operation::Insert(KeyValue::BlockHeader(ref hash, ref header)) => {
 					self.header.lock().insert(hash.clone(), KeyState::Insert(header.clone()));
 				},
+				Operation::Insert(KeyValue::Transaction(ref hash, ref transaction)) => {
+					self.transaction.lock().insert(hash.clone(), KeyState::Insert(transaction.clone()));
+				},
+				Operation::Insert(KeyValue::TransactionMeta(ref hash, ref meta)) => {
+					self.transaction_meta.lock().insert(hash.clone(), KeyState::Insert(meta.clone()));
+				},
 				Operation::Delete(Key::BlockHeader(ref hash)) => {
 					self.header.lock().insert(hash.clone(), KeyState::Delete);
 				},
+				Operation::Delete(Key::Transaction(ref hash)) => {
+					self.transaction.lock().insert(hash.clone(), KeyState::Delete);
+				},
+				Operation::Delete(Key::TransactionMeta(ref hash)) => {
+					self.transaction_meta.lock().insert(hash.clone(), KeyState::Delete);
+				},
 				_ => (),
 			}
 		}
@@ -36,12 +66,129 @@ impl<T> KeyValueDatabase for CacheDatabase<T> where T: KeyValueDatabase {
 	}
 
 	fn get(&self, key: &Key) -> Result<KeyState<Value>, String> {
-		if let Key::BlockHeader(ref hash) = *key {
-			let mut header = self.header.lock();
-			if let Some(state) = header.get_mut(hash) {
-				return Ok(state.clone().map(Value::BlockHeader))
-			}
+		match *key {
+			Key::BlockHeader(ref hash) => {
+				if let Some(state) = self.header.lock().get_mut(hash) {
+					return Ok(state.clone().map(Value::BlockHeader))
+				}
+			},
+			Key::Transaction(ref hash) => {
+				if let Some(state) = self.transaction.lock().get_mut(hash) {
+					return Ok(state.clone().map(Value::Transaction))
+				}
+			},
+			Key::TransactionMeta(ref hash) => {
+				if let Some(state) = self.transaction_meta.lock().get_mut(hash) {
+					return Ok(state.clone().map(Value::TransactionMeta))
+				}
+			},
+			_ => (),
+		}
+
+		let state = self.db.get(key)?;
+		match (key, &state) {
+			(&Key::BlockHeader(ref hash), &KeyState::Insert(Value::BlockHeader(ref header))) => {
+				self.header.lock().insert(hash.clone(), KeyState::Insert(header.clone()));
+			},
+			(&Key::Transaction(ref hash), &KeyState::Insert(Value::Transaction(ref transaction))) => {
+				self.transaction.lock().insert(hash.clone(), KeyState::Insert(transaction.clone()));
+			},
+			(&Key::TransactionMeta(ref hash), &KeyState::Insert(Value::TransactionMeta(ref meta))) => {
+				self.transaction_meta.lock().insert(hash.clone(), KeyState::Insert(meta.clone()));
+			},
+			_ => (),
+		}
+		Ok(state)
+	}
+
+	fn address_outputs(&self, script_pubkey: &Bytes) -> Result<Vec<OutPoint>, String> {
+		self.db.address_outputs(script_pubkey)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+	use hash::H256;
+	use bytes::Bytes;
+	use chain::OutPoint;
+	use storage::TransactionMeta;
+	use kv::{KeyValueDatabase, MemoryDatabase, Transaction, KeyValue, Key, KeyState, Value};
+	use super::CacheDatabase;
+
+	/// Memory database, which counts reads.
+	struct CountingDatabase {
+		db: MemoryDatabase,
+		reads: Arc<AtomicUsize>,
+	}
+
+	impl KeyValueDatabase for CountingDatabase {
+		fn write(&self, tx: Transaction) -> Result<(), String> {
+			self.db.write(tx)
 		}
-		self.db.get(key)
+
+		fn get(&self, key: &Key) -> Result<KeyState<Value>, String> {
+			self.reads.fetch_add(1, Ordering::SeqCst);
+			self.db.get(key)
+		}
+
+		fn address_outputs(&self, script_pubkey: &Bytes) -> Result<Vec<OutPoint>, String> {
+			self.db.address_outputs(script_pubkey)
+		}
+	}
+
+	fn cache_database(capacity: usize) -> (CacheDatabase<CountingDatabase>, Arc<AtomicUsize>) {
+		let reads = Arc::new(AtomicUsize::new(0));
+		let db = CountingDatabase {
+			db: MemoryDatabase::default(),
+			reads: reads.clone(),
+		};
+		(CacheDatabase::with_capacity(db, capacity), reads)
+	}
+
+	fn insert_meta(db: &KeyValueDatabase, hash: H256, height: u32) {
+		let mut tx = Transaction::new();
+		tx.insert(KeyValue::TransactionMeta(hash, TransactionMeta::new(height, 1)));
+		db.write(tx).unwrap();
+	}
+
+	fn meta_height(db: &KeyValueDatabase, hash: H256) -> Option<u32> {
+		db.get(&Key::TransactionMeta(hash)).unwrap()
+			.into_option()
+			.and_then(Value::as_transaction_meta)
+			.map(|meta| meta.height())
+	}
+
+	#[test]
+	fn cache_hits_avoid_backend_reads() {
+		let (db, reads) = cache_database(1);
+		insert_meta(&db, H256::from(1), 1);
+		// evicts the first entry from the cache
+		insert_meta(&db, H256::from(2), 2);
+
+		assert_eq!(meta_height(&db, H256::from(2)), Some(2));
+		assert_eq!(reads.load(Ordering::SeqCst), 0);
+
+		assert_eq!(meta_height(&db, H256::from(1)), Some(1));
+		assert_eq!(reads.load(Ordering::SeqCst), 1);
+		assert_eq!(meta_height(&db, H256::from(1)), Some(1));
+		assert_eq!(reads.load(Ordering::SeqCst), 1);
+	}
+
+	#[test]
+	fn writes_invalidate_cached_entries() {
+		let (db, reads) = cache_database(16);
+		insert_meta(&db, H256::from(1), 1);
+		assert_eq!(meta_height(&db, H256::from(1)), Some(1));
+
+		insert_meta(&db, H256::from(1), 2);
+		assert_eq!(meta_height(&db, H256::from(1)), Some(2));
+
+		let mut tx = Transaction::new();
+		tx.delete(Key::TransactionMeta(H256::from(1)));
+		db.write(tx).unwrap();
+		assert_eq!(meta_height(&db, H256::from(1)), None);
+		assert_eq!(reads.load(Ordering::SeqCst), 0);
 	}
 }
@@ -1,7 +1,12 @@
+use bytes::Bytes;
+use chain::OutPoint;
 use kv::{Transaction, KeyState, Key, Value};
 
 pub trait KeyValueDatabase: Send + Sync {
 	fn write(&self, tx: Transaction) -> Result<(), String>;
 
 	fn get(&self, key: &Key) -> Result<KeyState<Value>, String>;
+
+	/// Returns outpoints of all address index entries of given script.
+	fn address_outputs(&self, script_pubkey: &Bytes) -> Result<Vec<OutPoint>, String>;
 }
@@ -3,12 +3,14 @@
 use std::collections::HashMap;
 use std::path::Path;
 use rocksdb::{
-	DB, Writable, WriteBatch, WriteOptions, IteratorMode, DBIterator,
+	DB, Writable, WriteBatch, WriteOptions, IteratorMode, DBIterator, Direction,
 	Options, DBCompactionStyle, BlockBasedOptions, Cache, Column, ReadOptions
 };
 use bytes::Bytes;
+use chain::OutPoint;
+use ser::{serialize, deserialize};
 use kv::{Transaction, RawTransaction, RawOperation, Location, Value, KeyValueDatabase, Key, KeyState, RawKeyValue,
-RawKey};
+RawKey, COL_ADDRESS_INDEX};
 
 const DB_BACKGROUND_FLUSHES: i32 = 2;
 const DB_BACKGROUND_COMPACTIONS: i32 = 2;
@@ -132,6 +134,14 @@ impl KeyValueDatabase for Database {
 			None => Ok(KeyState::Unknown)
 		}
 	}
+
+	fn address_outputs(&self, script_pubkey: &Bytes) -> Result<Vec<OutPoint>, String> {
+		let prefix = serialize(script_pubkey);
+		self.iter_from(COL_ADDRESS_INDEX.into(), &prefix)
+			.take_while(|&(ref key, _)| key.starts_with(&prefix))
+			.map(|(key, _)| deserialize(&key[prefix.len()..]).map_err(|e| format!("{:?}", e)))
+			.collect()
+	}
 }
 
 impl Database {
@@ -207,15 +217,22 @@ impl Database {
 						assert!(cfs.len() == columns as usize);
 						Ok(db)
 					}
-					Err(_) => {
-						// retry and create CFs
-						match DB::open_cf(&opts, &path, &[], &[]) {
-							Ok(mut db) => {
-								cfs = try!(cfnames.iter().enumerate().map(|(i, n)| db.create_cf(n, &cf_options[i])).collect());
-								Ok(db)
-							},
-							err @ Err(_) => err,
+					Err(err) => {
+						// database may have been created by previous version, which had less columns:
+						// retry with less CFs and create missing ones
+						let mut result = Err(err);
+						for existing in (0..cfnames.len()).rev() {
+							if let Ok(mut db) = DB::open_cf(&opts, &path, &cfnames[..existing], &cf_options[..existing]) {
+								cfs = cfnames[..existing].iter().map(|n| db.cf_handle(n)
+									.expect("rocksdb opens a cf_handle for each cfname; qed")).collect();
+								for i in existing..cfnames.len() {
+									cfs.push(try!(db.create_cf(cfnames[i], &cf_options[i])));
+								}
+								result = Ok(db);
+								break;
+							}
 						}
+						result
 					}
 				}
 			},
@@ -292,6 +309,21 @@ impl Database {
 			}
 		}
 	}
+
+	/// Iterates over entries, starting with the first entry whose key is not less than `key`.
+	pub fn iter_from(&self, location: Location, key: &[u8]) -> DatabaseIterator {
+		let DBAndColumns { ref db, ref cfs } = self.db;
+		let mode = IteratorMode::From(key, Direction::Forward);
+		match location {
+			Location::DB => DatabaseIterator {
+				iter: db.iterator_opt(mode, &self.read_opts)
+			},
+			Location::Column(column) => DatabaseIterator {
+				iter: db.iterator_cf_opt(cfs[column as usize], mode, &self.read_opts)
+					.expect("iterator params are valid; qed")
+			}
+		}
+	}
 }
 
 #[cfg(test)]
@@ -299,7 +331,7 @@ mod tests {
 	extern crate tempdir;
 
 	use self::tempdir::TempDir;
-	use kv::{RawTransaction, Location};
+	use kv::{RawTransaction, Location, KeyValue, COL_COUNT};
 	use super::*;
 
 	fn test_db(config: DatabaseConfig) {
@@ -347,4 +379,47 @@ mod tests {
 		let _ = Database::open_default(tempdir.path()).unwrap();
 		test_db(DatabaseConfig::default());
 	}
+
+	#[test]
+	fn missing_columns_are_created_on_open() {
+		let tempdir = TempDir::new("").unwrap();
+		{
+			let db = Database::open(DatabaseConfig::with_columns(Some(2)), tempdir.path()).unwrap();
+			let mut batch = RawTransaction::default();
+			batch.insert_raw(Location::Column(1), b"key", b"cat");
+			db.write(batch).unwrap();
+		}
+
+		let db = Database::open(DatabaseConfig::with_columns(Some(3)), tempdir.path()).unwrap();
+		assert_eq!(&*db.get(&RawKey::new(Location::Column(1), b"key" as &[u8])).unwrap().unwrap(), b"cat");
+
+		let mut batch = RawTransaction::default();
+		batch.insert_raw(Location::Column(2), b"key", b"dog");
+		db.write(batch).unwrap();
+		assert_eq!(&*db.get(&RawKey::new(Location::Column(2), b"key" as &[u8])).unwrap().unwrap(), b"dog");
+	}
+
+	#[test]
+	fn address_outputs_are_listed_by_script_prefix() {
+		let tempdir = TempDir::new("").unwrap();
+		let db = Database::open(DatabaseConfig::with_columns(Some(COL_COUNT)), tempdir.path()).unwrap();
+		let script1: Bytes = vec![1u8].into();
+		// script1 is a prefix of script2
+		let script2: Bytes = vec![1u8, 2].into();
+		let outpoint = |index| OutPoint { hash: 1.into(), index: index };
+
+		let mut tx = Transaction::new();
+		tx.insert(KeyValue::AddressIndex(script1.clone(), outpoint(1)));
+		tx.insert(KeyValue::AddressIndex(script1.clone(), outpoint(0)));
+		tx.insert(KeyValue::AddressIndex(script2.clone(), outpoint(2)));
+		KeyValueDatabase::write(&db, tx).unwrap();
+		assert_eq!(db.address_outputs(&script1).unwrap(), vec![outpoint(0), outpoint(1)]);
+		assert_eq!(db.address_outputs(&script2).unwrap(), vec![outpoint(2)]);
+
+		let mut tx = Transaction::new();
+		tx.delete(Key::AddressIndex(script1.clone(), outpoint(0)));
+		KeyValueDatabase::write(&db, tx).unwrap();
+		assert_eq!(db.address_outputs(&script1).unwrap(), vec![outpoint(1)]);
+		assert!(db.address_outputs(&vec![2u8].into()).unwrap().is_empty());
+	}
 }
@@ -5,9 +5,13 @@ use parking_lot::RwLock;
 use hash::H256;
 use bytes::Bytes;
 use ser::List;
-use chain::{Transaction as ChainTransaction, BlockHeader};
+use chain::{Transaction as ChainTransaction, BlockHeader, OutPoint, TransactionOutput};
 use kv::{Transaction, Key, KeyState, Operation, Value, KeyValueDatabase, KeyValue};
-use storage::{TransactionMeta, EpochTag, EpochRef, SproutTreeState, SaplingTreeState};
+use storage::{
+	TransactionMeta, EpochTag, EpochRef, SproutTreeState, SaplingTreeState, BlockIndex,
+	TransactionLocation, SpentInfo,
+};
+use utxo_snapshot::UtxoSnapshotRecord;
 
 #[derive(Default, Debug)]
 struct InnerDatabase {
@@ -19,11 +23,20 @@ struct InnerDatabase {
 	transaction: HashMap<H256, KeyState<ChainTransaction>>,
 	transaction_meta: HashMap<H256, KeyState<TransactionMeta>>,
 	block_number: HashMap<H256, KeyState<u32>>,
+	block_index: HashMap<H256, KeyState<BlockIndex>>,
+	block_timestamps: HashMap<u32, KeyState<List<H256>>>,
 	configuration: HashMap<&'static str, KeyState<Bytes>>,
 	sprout_nullifiers: HashMap<H256, KeyState<()>>,
 	sapling_nullifiers: HashMap<H256, KeyState<()>>,
+	sprout_anchors: HashMap<H256, KeyState<()>>,
+	sapling_anchors: HashMap<H256, KeyState<()>>,
 	sprout_tree_state: HashMap<H256, KeyState<SproutTreeState>>,
 	sapling_tree_state: HashMap<H256, KeyState<SaplingTreeState>>,
+	utxo_output: HashMap<OutPoint, KeyState<TransactionOutput>>,
+	utxo_snapshot_record: HashMap<u32, KeyState<UtxoSnapshotRecord>>,
+	transaction_index: HashMap<H256, KeyState<TransactionLocation>>,
+	address_index: HashMap<Bytes, HashMap<OutPoint, KeyState<()>>>,
+	spent_index: HashMap<OutPoint, KeyState<SpentInfo>>,
 }
 
 #[derive(Default, Debug)]
@@ -54,6 +67,10 @@ impl MemoryDatabase {
 
 		let block_number = replace(&mut db.block_number, HashMap::default()).into_iter()
 			.flat_map(|(key, state)| state.into_operation(key, KeyValue::BlockNumber, Key::BlockNumber));
+		let block_index = replace(&mut db.block_index, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::BlockIndex, Key::BlockIndex));
+		let block_timestamps = replace(&mut db.block_timestamps, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::BlockTimestamps, Key::BlockTimestamps));
 
 		let configuration = replace(&mut db.configuration, HashMap::default()).into_iter()
 			.flat_map(|(key, state)| state.into_operation(key, KeyValue::Configuration, Key::Configuration));
@@ -74,6 +91,22 @@ impl MemoryDatabase {
 				)
 			);
 
+		let sprout_anchors = replace(&mut db.sprout_anchors, HashMap::default()).into_iter()
+			.flat_map(|(key, state)|
+				state.into_operation(key,
+					|k, _| KeyValue::Anchor(EpochRef::new(EpochTag::Sprout, k)),
+					|h| Key::Anchor(EpochRef::new(EpochTag::Sprout, h))
+				)
+			);
+
+		let sapling_anchors = replace(&mut db.sapling_anchors, HashMap::default()).into_iter()
+			.flat_map(|(key, state)|
+				state.into_operation(key,
+					|k, _| KeyValue::Anchor(EpochRef::new(EpochTag::Sapling, k)),
+					|h| Key::Anchor(EpochRef::new(EpochTag::Sapling, h))
+				)
+			);
+
 		let sprout_tree_state = replace(&mut db.sprout_tree_state, HashMap::default()).into_iter()
 			.flat_map(|(key, state)|
 				state.into_operation(key,
@@ -89,6 +122,24 @@ impl MemoryDatabase {
 					KeyValue::SaplingTreeState,
 					|k| Key::TreeRoot(EpochRef::new(EpochTag::Sapling, k))));
 
+		let utxo_output = replace(&mut db.utxo_output, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::UtxoOutput, Key::UtxoOutput));
+		let utxo_snapshot_record = replace(&mut db.utxo_snapshot_record, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::UtxoSnapshotRecord, Key::UtxoSnapshotRecord));
+
+		let transaction_index = replace(&mut db.transaction_index, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::TransactionIndex, Key::TransactionIndex));
+		let address_index = replace(&mut db.address_index, HashMap::default()).into_iter()
+			.flat_map(|(script, outputs)| outputs.into_iter().map(move |(outpoint, state)| (script.clone(), outpoint, state)))
+			.flat_map(|(script, outpoint, state)|
+				state.into_operation((script, outpoint),
+					|(script, outpoint), _| KeyValue::AddressIndex(script, outpoint),
+					|(script, outpoint)| Key::AddressIndex(script, outpoint)
+				)
+			);
+		let spent_index = replace(&mut db.spent_index, HashMap::default()).into_iter()
+			.flat_map(|(key, state)| state.into_operation(key, KeyValue::SpentIndex, Key::SpentIndex));
+
 		Transaction {
 			operations: meta
 				.chain(block_hash)
@@ -97,17 +148,33 @@ impl MemoryDatabase {
 				.chain(transaction)
 				.chain(transaction_meta)
 				.chain(block_number)
+				.chain(block_index)
+				.chain(block_timestamps)
 				.chain(configuration)
 				.chain(sprout_tree_state)
 				.chain(sapling_tree_state)
 				.chain(sprout_block_root)
 				.chain(sprout_nullifiers)
 				.chain(sapling_nullifiers)
+				.chain(sprout_anchors)
+				.chain(sapling_anchors)
+				.chain(utxo_output)
+				.chain(utxo_snapshot_record)
+				.chain(transaction_index)
+				.chain(address_index)
+				.chain(spent_index)
 				.collect()
 		}
 	}
 }
 
+impl MemoryDatabase {
+	/// Returns states (including deletions) of all address index entries of given script.
+	pub fn address_index_states(&self, script_pubkey: &Bytes) -> HashMap<OutPoint, KeyState<()>> {
+		self.db.read().address_index.get(script_pubkey).cloned().unwrap_or_default()
+	}
+}
+
 impl KeyValueDatabase for MemoryDatabase {
 	fn write(&self, tx: Transaction) -> Result<(), String> {
 		let mut db = self.db.write();
@@ -121,14 +188,25 @@ impl KeyValueDatabase for MemoryDatabase {
 					KeyValue::Transaction(key, value) => { db.transaction.insert(key, KeyState::Insert(value)); },
 					KeyValue::TransactionMeta(key, value) => { db.transaction_meta.insert(key, KeyState::Insert(value)); },
 					KeyValue::BlockNumber(key, value) => { db.block_number.insert(key, KeyState::Insert(value)); },
+					KeyValue::BlockIndex(key, value) => { db.block_index.insert(key, KeyState::Insert(value)); },
+					KeyValue::BlockTimestamps(key, value) => { db.block_timestamps.insert(key, KeyState::Insert(value)); },
 					KeyValue::Configuration(key, value) => { db.configuration.insert(key, KeyState::Insert(value)); },
 					KeyValue::Nullifier(key) => match key.epoch() {
 						EpochTag::Sprout => { db.sprout_nullifiers.insert(*key.hash(), KeyState::Insert(())); },
 						EpochTag::Sapling => { db.sapling_nullifiers.insert(*key.hash(), KeyState::Insert(())); },
 					},
+					KeyValue::Anchor(key) => match key.epoch() {
+						EpochTag::Sprout => { db.sprout_anchors.insert(*key.hash(), KeyState::Insert(())); },
+						EpochTag::Sapling => { db.sapling_anchors.insert(*key.hash(), KeyState::Insert(())); },
+					},
 					KeyValue::SproutTreeState(key, value) => { db.sprout_tree_state.insert(key, KeyState::Insert(value)); },
 					KeyValue::SaplingTreeState(key, value) => { db.sapling_tree_state.insert(key, KeyState::Insert(value)); },
 					KeyValue::SproutBlockRoot(key, value) => { db.sprout_block_root.insert(key, KeyState::Insert(value)); },
+					KeyValue::UtxoOutput(key, value) => { db.utxo_output.insert(key, KeyState::Insert(value)); },
+					KeyValue::UtxoSnapshotRecord(key, value) => { db.utxo_snapshot_record.insert(key, KeyState::Insert(value)); },
+					KeyValue::TransactionIndex(key, value) => { db.transaction_index.insert(key, KeyState::Insert(value)); },
+					KeyValue::AddressIndex(script, outpoint) => { db.address_index.entry(script).or_insert_with(HashMap::default).insert(outpoint, KeyState::Insert(())); },
+					KeyValue::SpentIndex(key, value) => { db.spent_index.insert(key, KeyState::Insert(value)); },
 				},
 				Operation::Delete(delete) => match delete {
 					Key::Meta(key) => { db.meta.insert(key, KeyState::Delete); }
@@ -138,16 +216,27 @@ impl KeyValueDatabase for MemoryDatabase {
 					Key::Transaction(key) => { db.transaction.insert(key, KeyState::Delete); }
 					Key::TransactionMeta(key) => { db.transaction_meta.insert(key, KeyState::Delete); }
 					Key::BlockNumber(key) => { db.block_number.insert(key, KeyState::Delete); }
+					Key::BlockIndex(key) => { db.block_index.insert(key, KeyState::Delete); }
+					Key::BlockTimestamps(key) => { db.block_timestamps.insert(key, KeyState::Delete); }
 					Key::Configuration(key) => { db.configuration.insert(key, KeyState::Delete); }
 					Key::Nullifier(key) => match key.epoch() {
 						EpochTag::Sprout => { db.sprout_nullifiers.insert(*key.hash(), KeyState::Delete); },
 						EpochTag::Sapling => { db.sapling_nullifiers.insert(*key.hash(), KeyState::Delete); },
 					},
+					Key::Anchor(key) => match key.epoch() {
+						EpochTag::Sprout => { db.sprout_anchors.insert(*key.hash(), KeyState::Delete); },
+						EpochTag::Sapling => { db.sapling_anchors.insert(*key.hash(), KeyState::Delete); },
+					},
 					Key::TreeRoot(key) => match key.epoch() {
 						EpochTag::Sprout => { db.sprout_tree_state.insert(*key.hash(), KeyState::Delete); },
 						EpochTag::Sapling => { db.sapling_tree_state.insert(*key.hash(), KeyState::Delete); },
 					},
 					Key::SproutBlockRoot(key) => { db.sprout_block_root.insert(key, KeyState::Delete); },
+					Key::UtxoOutput(key) => { db.utxo_output.insert(key, KeyState::Delete); },
+					Key::UtxoSnapshotRecord(key) => { db.utxo_snapshot_record.insert(key, KeyState::Delete); },
+					Key::TransactionIndex(key) => { db.transaction_index.insert(key, KeyState::Delete); },
+					Key::AddressIndex(script, outpoint) => { db.address_index.entry(script).or_insert_with(HashMap::default).insert(outpoint, KeyState::Delete); },
+					Key::SpentIndex(key) => { db.spent_index.insert(key, KeyState::Delete); },
 				},
 			}
 		}
@@ -164,20 +253,39 @@ impl KeyValueDatabase for MemoryDatabase {
 			Key::Transaction(ref key) => db.transaction.get(key).cloned().unwrap_or_default().map(Value::Transaction),
 			Key::TransactionMeta(ref key) => db.transaction_meta.get(key).cloned().unwrap_or_default().map(Value::TransactionMeta),
 			Key::BlockNumber(ref key) => db.block_number.get(key).cloned().unwrap_or_default().map(Value::BlockNumber),
+			Key::BlockIndex(ref key) => db.block_index.get(key).cloned().unwrap_or_default().map(Value::BlockIndex),
+			Key::BlockTimestamps(ref key) => db.block_timestamps.get(key).cloned().unwrap_or_default().map(Value::BlockTimestamps),
 			Key::Configuration(ref key) => db.configuration.get(key).cloned().unwrap_or_default().map(Value::Configuration),
 			Key::Nullifier(ref key) => match key.epoch() {
 				EpochTag::Sprout => db.sprout_nullifiers.get(key.hash()).cloned().unwrap_or_default().map(|_| Value::Empty),
 				EpochTag::Sapling => db.sapling_nullifiers.get(key.hash()).cloned().unwrap_or_default().map(|_| Value::Empty),
 			},
+			Key::Anchor(ref key) => match key.epoch() {
+				EpochTag::Sprout => db.sprout_anchors.get(key.hash()).cloned().unwrap_or_default().map(|_| Value::Empty),
+				EpochTag::Sapling => db.sapling_anchors.get(key.hash()).cloned().unwrap_or_default().map(|_| Value::Empty),
+			},
 			Key::TreeRoot(ref key) => match key.epoch() {
 				EpochTag::Sprout => db.sprout_tree_state.get(key.hash()).cloned().unwrap_or_default().map(Value::SproutTreeState),
 				EpochTag::Sapling => db.sapling_tree_state.get(key.hash()).cloned().unwrap_or_default().map(Value::SaplingTreeState),
 			},
 			Key::SproutBlockRoot(ref key) => db.sprout_block_root.get(key).cloned().unwrap_or_default().map(Value::SproutTreeRoot),
+			Key::UtxoOutput(ref key) => db.utxo_output.get(key).cloned().unwrap_or_default().map(Value::UtxoOutput),
+			Key::UtxoSnapshotRecord(ref key) => db.utxo_snapshot_record.get(key).cloned().unwrap_or_default().map(Value::UtxoSnapshotRecord),
+			Key::TransactionIndex(ref key) => db.transaction_index.get(key).cloned().unwrap_or_default().map(Value::TransactionIndex),
+			Key::AddressIndex(ref script, ref outpoint) => db.address_index.get(script)
+				.and_then(|outputs| outputs.get(outpoint))
+				.cloned().unwrap_or_default().map(|_| Value::Empty),
+			Key::SpentIndex(ref key) => db.spent_index.get(key).cloned().unwrap_or_default().map(Value::SpentIndex),
 		};
 
 		Ok(result)
 	}
+
+	fn address_outputs(&self, script_pubkey: &Bytes) -> Result<Vec<OutPoint>, String> {
+		Ok(self.address_index_states(script_pubkey).into_iter()
+			.filter_map(|(outpoint, state)| state.into_option().map(|_| outpoint))
+			.collect())
+	}
 }
 
 #[derive(Debug)]
@@ -209,4 +317,8 @@ impl KeyValueDatabase for SharedMemoryDatabase {
 	fn get(&self, key: &Key) -> Result<KeyState<Value>, String> {
 		self.db.get(key)
 	}
+
+	fn address_outputs(&self, script_pubkey: &Bytes) -> Result<Vec<OutPoint>, String> {
+		self.db.address_outputs(script_pubkey)
+	}
 }
@@ -5,7 +5,7 @@ mod memorydb;
 mod overlaydb;
 mod transaction;
 
-pub use self::cachedb::CacheDatabase;
+pub use self::cachedb::{CacheDatabase, DEFAULT_READ_CACHE_ENTRIES};
 pub use self::db::KeyValueDatabase;
 pub use self::diskdb::{Database as DiskDatabase, DatabaseConfig, CompactionProfile};
 pub use self::memorydb::{MemoryDatabase, SharedMemoryDatabase};
@@ -15,5 +15,7 @@ pub use self::transaction::{
 	Key, Value, KeyValue, RawKeyValue, RawKey,
 	COL_COUNT, COL_META, COL_BLOCK_HASHES, COL_BLOCK_HEADERS, COL_BLOCK_TRANSACTIONS,
 	COL_TRANSACTIONS, COL_TRANSACTIONS_META, COL_BLOCK_NUMBERS, COL_SAPLING_NULLIFIERS,
-	COL_SPROUT_NULLIFIERS, COL_TREE_STATES, COL_SPROUT_BLOCK_ROOTS,
+	COL_SPROUT_NULLIFIERS, COL_TREE_STATES, COL_SPROUT_BLOCK_ROOTS, COL_BLOCK_INDEX,
+	COL_ADDRESS_INDEX, COL_SPROUT_ANCHORS, COL_SAPLING_ANCHORS, COL_BLOCK_TIMESTAMPS,
+	COL_UTXO_OUTPUTS, COL_UTXO_SNAPSHOT, COL_TRANSACTION_INDEX, COL_SPENT_INDEX,
 };
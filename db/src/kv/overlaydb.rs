@@ -1,4 +1,6 @@
 use parking_lot::Mutex;
+use bytes::Bytes;
+use chain::OutPoint;
 use kv::{Transaction, Value, KeyValueDatabase, MemoryDatabase, KeyState, Key};
 
 pub struct OverlayDatabase<'a, T> where T: 'a + KeyValueDatabase {
@@ -30,6 +32,10 @@ impl<'a, T> KeyValueDatabase for OverlayDatabase<'a, T> where T: 'a + KeyValueDa
 			exists => Ok(exists)
 		}
 	}
+
+	fn address_outputs(&self, script_pubkey: &Bytes) -> Result<Vec<OutPoint>, String> {
+		overlay_address_outputs(&self.overlay, self.db, script_pubkey)
+	}
 }
 
 pub struct AutoFlushingOverlayDatabase<T> where T: KeyValueDatabase {
@@ -72,6 +78,24 @@ impl<T> KeyValueDatabase for AutoFlushingOverlayDatabase<T> where T: KeyValueDat
 			exists => Ok(exists)
 		}
 	}
+
+	fn address_outputs(&self, script_pubkey: &Bytes) -> Result<Vec<OutPoint>, String> {
+		overlay_address_outputs(&self.overlay, &self.db, script_pubkey)
+	}
+}
+
+/// Returns outputs of the address index entries from the `db`, updated with entries from the `overlay`.
+fn overlay_address_outputs<T>(overlay: &MemoryDatabase, db: &T, script_pubkey: &Bytes) -> Result<Vec<OutPoint>, String>
+	where T: KeyValueDatabase
+{
+	let overlay_states = overlay.address_index_states(script_pubkey);
+	let mut outputs: Vec<OutPoint> = db.address_outputs(script_pubkey)?
+		.into_iter()
+		.filter(|outpoint| !overlay_states.contains_key(outpoint))
+		.collect();
+	outputs.extend(overlay_states.into_iter()
+		.filter_map(|(outpoint, state)| state.into_option().map(|_| outpoint)));
+	Ok(outputs)
 }
 
 impl<T> Drop for AutoFlushingOverlayDatabase<T> where T: KeyValueDatabase {
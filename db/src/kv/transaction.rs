@@ -1,10 +1,14 @@
 use bytes::Bytes;
 use hash::H256;
-use ser::{serialize, List, deserialize};
-use chain::{Transaction as ChainTransaction, BlockHeader};
-use storage::{TransactionMeta, EpochTag, EpochRef, SproutTreeState, SaplingTreeState};
-
-pub const COL_COUNT: u32 = 16;
+use ser::{serialize, List, deserialize, Stream};
+use chain::{Transaction as ChainTransaction, BlockHeader, OutPoint, TransactionOutput};
+use storage::{
+	TransactionMeta, EpochTag, EpochRef, SproutTreeState, SaplingTreeState, BlockIndex,
+	TransactionLocation, SpentInfo,
+};
+use utxo_snapshot::UtxoSnapshotRecord;
+
+pub const COL_COUNT: u32 = 21;
 pub const COL_META: u32 = 0;
 pub const COL_BLOCK_HASHES: u32 = 1;
 pub const COL_BLOCK_HEADERS: u32 = 2;
@@ -17,6 +21,15 @@ pub const COL_SAPLING_NULLIFIERS: u32 = 8;
 pub const COL_SPROUT_BLOCK_ROOTS: u32 = 9;
 pub const COL_TREE_STATES: u32 = 10;
 pub const COL_CONFIGURATION: u32 = 11;
+pub const COL_BLOCK_INDEX: u32 = 12;
+pub const COL_ADDRESS_INDEX: u32 = 13;
+pub const COL_SPROUT_ANCHORS: u32 = 14;
+pub const COL_SAPLING_ANCHORS: u32 = 15;
+pub const COL_BLOCK_TIMESTAMPS: u32 = 16;
+pub const COL_UTXO_OUTPUTS: u32 = 17;
+pub const COL_UTXO_SNAPSHOT: u32 = 18;
+pub const COL_TRANSACTION_INDEX: u32 = 19;
+pub const COL_SPENT_INDEX: u32 = 20;
 
 #[derive(Debug)]
 pub enum Operation {
@@ -33,11 +46,19 @@ pub enum KeyValue {
 	Transaction(H256, ChainTransaction),
 	TransactionMeta(H256, TransactionMeta),
 	BlockNumber(H256, u32),
+	BlockIndex(H256, BlockIndex),
+	BlockTimestamps(u32, List<H256>),
 	Configuration(&'static str, Bytes),
 	Nullifier(EpochRef),
+	Anchor(EpochRef),
 	SproutTreeState(H256, SproutTreeState),
 	SaplingTreeState(H256, SaplingTreeState),
 	SproutBlockRoot(H256, H256),
+	UtxoOutput(OutPoint, TransactionOutput),
+	UtxoSnapshotRecord(u32, UtxoSnapshotRecord),
+	TransactionIndex(H256, TransactionLocation),
+	AddressIndex(Bytes, OutPoint),
+	SpentIndex(OutPoint, SpentInfo),
 }
 
 #[derive(Debug)]
@@ -49,10 +70,18 @@ pub enum Key {
 	Transaction(H256),
 	TransactionMeta(H256),
 	BlockNumber(H256),
+	BlockIndex(H256),
+	BlockTimestamps(u32),
 	Configuration(&'static str),
 	Nullifier(EpochRef),
+	Anchor(EpochRef),
 	TreeRoot(EpochRef),
 	SproutBlockRoot(H256),
+	UtxoOutput(OutPoint),
+	UtxoSnapshotRecord(u32),
+	TransactionIndex(H256),
+	AddressIndex(Bytes, OutPoint),
+	SpentIndex(OutPoint),
 }
 
 #[derive(Debug, Clone)]
@@ -64,11 +93,17 @@ pub enum Value {
 	Transaction(ChainTransaction),
 	TransactionMeta(TransactionMeta),
 	BlockNumber(u32),
+	BlockIndex(BlockIndex),
+	BlockTimestamps(List<H256>),
 	Configuration(Bytes),
 	Empty,
 	SproutTreeState(SproutTreeState),
 	SaplingTreeState(SaplingTreeState),
 	SproutTreeRoot(H256),
+	UtxoOutput(TransactionOutput),
+	UtxoSnapshotRecord(UtxoSnapshotRecord),
+	TransactionIndex(TransactionLocation),
+	SpentIndex(SpentInfo),
 }
 
 impl Value {
@@ -81,13 +116,21 @@ impl Value {
 			Key::Transaction(_) => deserialize(bytes).map(Value::Transaction),
 			Key::TransactionMeta(_) => deserialize(bytes).map(Value::TransactionMeta),
 			Key::BlockNumber(_) => deserialize(bytes).map(Value::BlockNumber),
+			Key::BlockIndex(_) => deserialize(bytes).map(Value::BlockIndex),
+			Key::BlockTimestamps(_) => deserialize(bytes).map(Value::BlockTimestamps),
 			Key::Configuration(_) => deserialize(bytes).map(Value::Configuration),
 			Key::Nullifier(_) => Ok(Value::Empty),
+			Key::Anchor(_) => Ok(Value::Empty),
 			Key::TreeRoot(tag) => match tag.epoch() {
 				EpochTag::Sprout => deserialize(bytes).map(Value::SproutTreeState),
 				EpochTag::Sapling => deserialize(bytes).map(Value::SaplingTreeState),
 			},
 			Key::SproutBlockRoot(_) => deserialize(bytes).map(Value::SproutTreeRoot),
+			Key::UtxoOutput(_) => deserialize(bytes).map(Value::UtxoOutput),
+			Key::UtxoSnapshotRecord(_) => deserialize(bytes).map(Value::UtxoSnapshotRecord),
+			Key::TransactionIndex(_) => deserialize(bytes).map(Value::TransactionIndex),
+			Key::AddressIndex(_, _) => Ok(Value::Empty),
+			Key::SpentIndex(_) => deserialize(bytes).map(Value::SpentIndex),
 		}.map_err(|e| format!("{:?}", e))
 	}
 
@@ -140,6 +183,13 @@ impl Value {
 		}
 	}
 
+	pub fn as_block_index(self) -> Option<BlockIndex> {
+		match self {
+			Value::BlockIndex(index) => Some(index),
+			_ => None,
+		}
+	}
+
 	pub fn as_configuration(self) -> Option<Bytes> {
 		match self {
 			Value::Configuration(bytes) => Some(bytes),
@@ -167,6 +217,41 @@ impl Value {
 			_ => None,
 		}
 	}
+
+	pub fn as_block_timestamps(self) -> Option<List<H256>> {
+		match self {
+			Value::BlockTimestamps(hashes) => Some(hashes),
+			_ => None,
+		}
+	}
+
+	pub fn as_utxo_output(self) -> Option<TransactionOutput> {
+		match self {
+			Value::UtxoOutput(output) => Some(output),
+			_ => None,
+		}
+	}
+
+	pub fn as_utxo_snapshot_record(self) -> Option<UtxoSnapshotRecord> {
+		match self {
+			Value::UtxoSnapshotRecord(record) => Some(record),
+			_ => None,
+		}
+	}
+
+	pub fn as_transaction_location(self) -> Option<TransactionLocation> {
+		match self {
+			Value::TransactionIndex(location) => Some(location),
+			_ => None,
+		}
+	}
+
+	pub fn as_spent_info(self) -> Option<SpentInfo> {
+		match self {
+			Value::SpentIndex(spent_info) => Some(spent_info),
+			_ => None,
+		}
+	}
 }
 
 #[derive(Debug, Clone)]
@@ -272,11 +357,22 @@ impl<'a> From<&'a KeyValue> for RawKeyValue {
 				EpochTag::Sprout => (COL_SPROUT_NULLIFIERS, serialize(key.hash()), Bytes::new()),
 				EpochTag::Sapling => (COL_SAPLING_NULLIFIERS, serialize(key.hash()), Bytes::new()),
 			},
+			KeyValue::Anchor(ref key) => match key.epoch() {
+				EpochTag::Sprout => (COL_SPROUT_ANCHORS, serialize(key.hash()), Bytes::new()),
+				EpochTag::Sapling => (COL_SAPLING_ANCHORS, serialize(key.hash()), Bytes::new()),
+			},
 			KeyValue::BlockNumber(ref key, ref value) => (COL_BLOCK_NUMBERS, serialize(key), serialize(value)),
+			KeyValue::BlockIndex(ref key, ref value) => (COL_BLOCK_INDEX, serialize(key), serialize(value)),
+			KeyValue::BlockTimestamps(ref key, ref value) => (COL_BLOCK_TIMESTAMPS, serialize(key), serialize(value)),
 			KeyValue::SproutTreeState(ref key, ref value) => (COL_TREE_STATES, serialize(key), serialize(value)),
 			KeyValue::SaplingTreeState(ref key, ref value) => (COL_TREE_STATES, serialize(key), serialize(value)),
 			KeyValue::SproutBlockRoot(ref key, ref value) => (COL_SPROUT_BLOCK_ROOTS, serialize(key), serialize(value)),
 			KeyValue::Configuration(ref key, ref value) => (COL_CONFIGURATION, serialize(key), serialize(value)),
+			KeyValue::UtxoOutput(ref key, ref value) => (COL_UTXO_OUTPUTS, serialize(key), serialize(value)),
+			KeyValue::UtxoSnapshotRecord(ref key, ref value) => (COL_UTXO_SNAPSHOT, serialize(key), serialize(value)),
+			KeyValue::TransactionIndex(ref key, ref value) => (COL_TRANSACTION_INDEX, serialize(key), serialize(value)),
+			KeyValue::AddressIndex(ref script, ref outpoint) => (COL_ADDRESS_INDEX, address_index_key(script, outpoint), Bytes::new()),
+			KeyValue::SpentIndex(ref key, ref value) => (COL_SPENT_INDEX, serialize(key), serialize(value)),
 		};
 
 		RawKeyValue {
@@ -314,10 +410,21 @@ impl<'a> From<&'a Key> for RawKey {
 				EpochTag::Sprout => (COL_SPROUT_NULLIFIERS, serialize(key.hash())),
 				EpochTag::Sapling => (COL_SAPLING_NULLIFIERS, serialize(key.hash())),
 			},
+			Key::Anchor(ref key) => match key.epoch() {
+				EpochTag::Sprout => (COL_SPROUT_ANCHORS, serialize(key.hash())),
+				EpochTag::Sapling => (COL_SAPLING_ANCHORS, serialize(key.hash())),
+			},
 			Key::TreeRoot(ref key) => (COL_TREE_STATES, serialize(key.hash())),
 			Key::BlockNumber(ref key) => (COL_BLOCK_NUMBERS, serialize(key)),
+			Key::BlockIndex(ref key) => (COL_BLOCK_INDEX, serialize(key)),
+			Key::BlockTimestamps(ref key) => (COL_BLOCK_TIMESTAMPS, serialize(key)),
 			Key::SproutBlockRoot(ref key) => (COL_SPROUT_BLOCK_ROOTS, serialize(key)),
 			Key::Configuration(ref key) => (COL_CONFIGURATION, serialize(key)),
+			Key::UtxoOutput(ref key) => (COL_UTXO_OUTPUTS, serialize(key)),
+			Key::UtxoSnapshotRecord(ref key) => (COL_UTXO_SNAPSHOT, serialize(key)),
+			Key::TransactionIndex(ref key) => (COL_TRANSACTION_INDEX, serialize(key)),
+			Key::AddressIndex(ref script, ref outpoint) => (COL_ADDRESS_INDEX, address_index_key(script, outpoint)),
+			Key::SpentIndex(ref key) => (COL_SPENT_INDEX, serialize(key)),
 		};
 
 		RawKey {
@@ -327,6 +434,16 @@ impl<'a> From<&'a Key> for RawKey {
 	}
 }
 
+/// Address index key is the serialized script, followed by the serialized outpoint. Script is
+/// prefixed with its length, so all entries of the same script are sharing the same key prefix.
+fn address_index_key(script_pubkey: &Bytes, outpoint: &OutPoint) -> Bytes {
+	let mut stream = Stream::new();
+	stream
+		.append(script_pubkey)
+		.append(outpoint);
+	stream.out()
+}
+
 impl<'a> From<&'a Operation> for RawOperation {
 	fn from(o: &'a Operation) -> Self {
 		match *o {
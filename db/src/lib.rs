@@ -7,12 +7,23 @@ extern crate bit_vec;
 extern crate lru_cache;
 
 extern crate primitives;
+extern crate bitcrypto as crypto;
 extern crate serialization as ser;
 extern crate chain;
 extern crate storage;
 
 pub mod kv;
 mod block_chain_db;
+pub mod migration;
+#[cfg(any(test, feature = "test-helpers"))]
+mod test_chain;
+mod utxo_snapshot;
 
-pub use block_chain_db::{BlockChainDatabase, ForkChainDatabase};
+pub use block_chain_db::{BlockChainDatabase, ForkChainDatabase, BLOCK_TIMESTAMP_BUCKET, MAX_BLOCK_HASHES_BY_TIME};
+#[cfg(any(test, feature = "test-helpers"))]
+pub use test_chain::{TestChain, mine_block};
+pub use utxo_snapshot::{
+	UTXO_SNAPSHOT_MAGIC, UTXO_SNAPSHOT_HEADERS, UTXO_IMPORT_BATCH_SIZE, UtxoEntry, UtxoSnapshot,
+	UtxoSnapshotRecord, dump_utxo, read_utxo,
+};
 pub use primitives::{hash, bytes};
\ No newline at end of file
@@ -0,0 +1,308 @@
+//! Database schema migrations.
+//!
+//! Schema version is stored in the meta column. Every migration is applied in a single
+//! database transaction, together with the version marker update, so the database is
+//! never left in the state where migration is partially applied.
+//!
+//! Migrations, which are touching every canon block, are writing their changes in batches
+//! of `MIGRATION_BATCH_SIZE` operations. Such migrations must be idempotent: version marker
+//! is only updated with the last batch, so interrupted migration is restarted from scratch.
+
+use std::collections::HashMap;
+use ser::{serialize, deserialize, List};
+use hash::H256;
+use primitives::bigint::U256;
+use kv::{KeyValueDatabase, Transaction as DBTransaction, KeyValue, Key, KeyState, Value};
+use storage::{Error, EpochRef, EpochTag, BlockIndex};
+use block_chain_db::{KEY_BEST_BLOCK_NUMBER, BLOCK_TIMESTAMP_BUCKET};
+
+/// Meta key of the database schema version marker.
+pub const KEY_DB_VERSION: &'static str = "db_version";
+/// Schema version that this version of the software expects.
+pub const DB_VERSION: u32 = 5;
+/// Max number of operations that batched migration keeps in memory before writing them to the database.
+pub const MIGRATION_BATCH_SIZE: usize = 16 * 1024;
+/// All known migrations, ordered by version.
+pub const MIGRATIONS: &'static [Migration] = &[
+	Migration {
+		version: 1,
+		description: "introduce database schema version marker",
+		apply: noop_migration,
+	},
+	Migration {
+		version: 2,
+		description: "index commitment tree anchors of canon chain",
+		apply: index_canon_anchors,
+	},
+	Migration {
+		version: 3,
+		description: "index canon blocks by timestamp",
+		apply: index_canon_block_timestamps,
+	},
+	Migration {
+		version: 4,
+		description: "index height and chain work of canon blocks",
+		apply: index_canon_blocks,
+	},
+	Migration {
+		version: 5,
+		description: "move canon blocks timestamp index to dedicated column",
+		apply: index_canon_block_timestamps,
+	},
+];
+
+/// Single migration from `version - 1` to `version`.
+pub struct Migration {
+	/// Schema version that database has after migration is applied.
+	pub version: u32,
+	/// Human-readable description (for logs).
+	pub description: &'static str,
+	/// Reads from database and pushes all required changes to the transaction.
+	pub apply: fn(&KeyValueDatabase, &mut DBTransaction) -> Result<(), String>,
+}
+
+fn noop_migration(_db: &KeyValueDatabase, _tx: &mut DBTransaction) -> Result<(), String> {
+	Ok(())
+}
+
+fn index_canon_anchors(db: &KeyValueDatabase, tx: &mut DBTransaction) -> Result<(), String> {
+	let best_number = match read_best_block_number(db)? {
+		Some(best_number) => best_number,
+		None => return Ok(()),
+	};
+
+	for number in 0..best_number + 1 {
+		let block_hash = read_canon_block_hash(db, number)?;
+		let sprout_root = db.get(&Key::SproutBlockRoot(block_hash))?.into_option().and_then(Value::as_sprout_block_root)
+			.ok_or_else(|| format!("no sprout root for block {}", block_hash.reversed()))?;
+		let header = db.get(&Key::BlockHeader(block_hash))?.into_option().and_then(Value::as_block_header)
+			.ok_or_else(|| format!("no header of block {}", block_hash.reversed()))?;
+
+		tx.insert(KeyValue::Anchor(EpochRef::new(EpochTag::Sprout, sprout_root)));
+		tx.insert(KeyValue::Anchor(EpochRef::new(EpochTag::Sapling, header.final_sapling_root)));
+		flush_batch(db, tx)?;
+	}
+
+	Ok(())
+}
+
+fn index_canon_block_timestamps(db: &KeyValueDatabase, tx: &mut DBTransaction) -> Result<(), String> {
+	let best_number = match read_best_block_number(db)? {
+		Some(best_number) => best_number,
+		None => return Ok(()),
+	};
+
+	let mut buckets: HashMap<u32, Vec<_>> = HashMap::new();
+	for number in 0..best_number + 1 {
+		let block_hash = read_canon_block_hash(db, number)?;
+		let header = db.get(&Key::BlockHeader(block_hash))?.into_option().and_then(Value::as_block_header)
+			.ok_or_else(|| format!("no header of block {}", block_hash.reversed()))?;
+		buckets.entry(header.time / BLOCK_TIMESTAMP_BUCKET).or_insert_with(Vec::new).push(block_hash);
+	}
+
+	for (bucket, hashes) in buckets {
+		tx.insert(KeyValue::BlockTimestamps(bucket, List::from(hashes)));
+		flush_batch(db, tx)?;
+	}
+
+	Ok(())
+}
+
+fn index_canon_blocks(db: &KeyValueDatabase, tx: &mut DBTransaction) -> Result<(), String> {
+	let best_number = match read_best_block_number(db)? {
+		Some(best_number) => best_number,
+		None => return Ok(()),
+	};
+
+	// side chain blocks are indexed on demand, when their child is inserted
+	let mut chain_work = U256::zero();
+	for number in 0..best_number + 1 {
+		let block_hash = read_canon_block_hash(db, number)?;
+		let header = db.get(&Key::BlockHeader(block_hash))?.into_option().and_then(Value::as_block_header)
+			.ok_or_else(|| format!("no header of block {}", block_hash.reversed()))?;
+		chain_work = chain_work.overflowing_add(header.work().unwrap_or_else(U256::zero)).0;
+		tx.insert(KeyValue::BlockIndex(block_hash, BlockIndex {
+			height: number,
+			chain_work: chain_work,
+		}));
+		flush_batch(db, tx)?;
+	}
+
+	Ok(())
+}
+
+/// Writes changes of idempotent migration to the database once batch is full.
+fn flush_batch(db: &KeyValueDatabase, tx: &mut DBTransaction) -> Result<(), String> {
+	if tx.operations.len() < MIGRATION_BATCH_SIZE {
+		return Ok(());
+	}
+
+	db.write(::std::mem::replace(tx, DBTransaction::new()))
+}
+
+fn read_best_block_number(db: &KeyValueDatabase) -> Result<Option<u32>, String> {
+	match db.get(&Key::Meta(KEY_BEST_BLOCK_NUMBER))?.into_option().and_then(Value::as_meta) {
+		Some(best_number) => deserialize(&**best_number).map(Some).map_err(|e| format!("{:?}", e)),
+		None => Ok(None),
+	}
+}
+
+fn read_canon_block_hash(db: &KeyValueDatabase, number: u32) -> Result<H256, String> {
+	db.get(&Key::BlockHash(number))?.into_option().and_then(Value::as_block_hash)
+		.ok_or_else(|| format!("no canon block #{}", number))
+}
+
+/// Reads schema version of the database. Returns `None` if database is empty.
+pub fn read_version(db: &KeyValueDatabase) -> Result<Option<u32>, Error> {
+	let version = db.get(&Key::Meta(KEY_DB_VERSION))
+		.map(KeyState::into_option)
+		.map(|x| x.and_then(Value::as_meta))
+		.map_err(Error::DatabaseError)?;
+	if let Some(version) = version {
+		return deserialize(&**version)
+			.map(Some)
+			.map_err(|e| Error::DatabaseError(format!("Invalid database version marker: {:?}", e)));
+	}
+
+	// databases created before version marker has been introduced have version 0
+	let has_best_block = db.get(&Key::Meta(super::block_chain_db::KEY_BEST_BLOCK_HASH))
+		.map(KeyState::into_option)
+		.map_err(Error::DatabaseError)?
+		.is_some();
+	Ok(if has_best_block { Some(0) } else { None })
+}
+
+/// Applies all migrations that are required to upgrade database to the `expected_version`.
+pub fn migrate(db: &KeyValueDatabase, migrations: &[Migration], expected_version: u32) -> Result<(), Error> {
+	let current_version = match read_version(db)? {
+		Some(current_version) => current_version,
+		None => {
+			// fresh database => no migrations required
+			let mut tx = DBTransaction::new();
+			tx.insert(KeyValue::Meta(KEY_DB_VERSION, serialize(&expected_version)));
+			return db.write(tx).map_err(Error::DatabaseError);
+		},
+	};
+
+	if current_version > expected_version {
+		return Err(Error::UnsupportedDatabaseVersion(current_version, expected_version));
+	}
+
+	for migration in migrations.iter().filter(|m| m.version > current_version && m.version <= expected_version) {
+		info!(target: "db", "Migrating database to version {}: {}", migration.version, migration.description);
+
+		let mut tx = DBTransaction::new();
+		(migration.apply)(db, &mut tx)
+			.map_err(|e| Error::DatabaseError(format!("Migration to version {} has failed: {}", migration.version, e)))?;
+		tx.insert(KeyValue::Meta(KEY_DB_VERSION, serialize(&migration.version)));
+		db.write(tx).map_err(Error::DatabaseError)?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use ser::serialize;
+	use bytes::Bytes;
+	use kv::{KeyValueDatabase, MemoryDatabase, Transaction as DBTransaction, KeyValue, Key, KeyState, Value};
+	use block_chain_db::KEY_BEST_BLOCK_HASH;
+	use storage::Error;
+	use super::{Migration, KEY_DB_VERSION, MIGRATION_BATCH_SIZE, migrate, read_version, noop_migration, flush_batch};
+
+	fn insert_test_key(_db: &KeyValueDatabase, tx: &mut DBTransaction) -> Result<(), String> {
+		tx.insert(KeyValue::Meta("test_key", Bytes::from(vec![42])));
+		Ok(())
+	}
+
+	fn failing_migration(_db: &KeyValueDatabase, tx: &mut DBTransaction) -> Result<(), String> {
+		tx.insert(KeyValue::Meta("test_key", Bytes::from(vec![42])));
+		Err("failed".into())
+	}
+
+	const MIGRATIONS: &'static [Migration] = &[
+		Migration { version: 1, description: "noop", apply: noop_migration },
+		Migration { version: 2, description: "insert test key", apply: insert_test_key },
+	];
+
+	fn database_with_version(version: Option<u32>) -> MemoryDatabase {
+		let db = MemoryDatabase::default();
+		let mut tx = DBTransaction::new();
+		tx.insert(KeyValue::Meta(KEY_BEST_BLOCK_HASH, Bytes::from(vec![0; 32])));
+		if let Some(version) = version {
+			tx.insert(KeyValue::Meta(KEY_DB_VERSION, serialize(&version)));
+		}
+		db.write(tx).unwrap();
+		db
+	}
+
+	fn has_test_key(db: &MemoryDatabase) -> bool {
+		db.get(&Key::Meta("test_key")).map(KeyState::into_option).unwrap().and_then(Value::as_meta).is_some()
+	}
+
+	#[test]
+	fn fresh_database_gets_expected_version() {
+		let db = MemoryDatabase::default();
+		assert_eq!(read_version(&db), Ok(None));
+		assert_eq!(migrate(&db, MIGRATIONS, 2), Ok(()));
+		assert_eq!(read_version(&db), Ok(Some(2)));
+		assert!(!has_test_key(&db));
+	}
+
+	#[test]
+	fn old_database_is_migrated() {
+		let db = database_with_version(Some(1));
+		assert_eq!(read_version(&db), Ok(Some(1)));
+		assert_eq!(migrate(&db, MIGRATIONS, 2), Ok(()));
+		assert_eq!(read_version(&db), Ok(Some(2)));
+		assert!(has_test_key(&db));
+	}
+
+	#[test]
+	fn database_without_version_marker_is_migrated() {
+		let db = database_with_version(None);
+		assert_eq!(read_version(&db), Ok(Some(0)));
+		assert_eq!(migrate(&db, MIGRATIONS, 2), Ok(()));
+		assert_eq!(read_version(&db), Ok(Some(2)));
+		assert!(has_test_key(&db));
+	}
+
+	#[test]
+	fn newer_database_is_rejected() {
+		let db = database_with_version(Some(3));
+		assert_eq!(migrate(&db, MIGRATIONS, 2), Err(Error::UnsupportedDatabaseVersion(3, 2)));
+	}
+
+	#[test]
+	fn failed_migration_is_not_applied() {
+		let migrations = &[
+			Migration { version: 1, description: "noop", apply: noop_migration },
+			Migration { version: 2, description: "fail", apply: failing_migration },
+		];
+
+		let db = database_with_version(Some(1));
+		assert!(migrate(&db, migrations, 2).is_err());
+		assert_eq!(read_version(&db), Ok(Some(1)));
+		assert!(!has_test_key(&db));
+	}
+
+	#[test]
+	fn full_batch_is_flushed() {
+		let db = MemoryDatabase::default();
+		let mut tx = DBTransaction::new();
+		for _ in 0..MIGRATION_BATCH_SIZE - 1 {
+			tx.insert(KeyValue::Meta("test_key", Bytes::from(vec![42])));
+		}
+
+		// batch isn't full yet
+		assert_eq!(flush_batch(&db, &mut tx), Ok(()));
+		assert_eq!(tx.operations.len(), MIGRATION_BATCH_SIZE - 1);
+		assert!(!has_test_key(&db));
+
+		// full batch is written to the database
+		tx.insert(KeyValue::Meta("test_key", Bytes::from(vec![42])));
+		assert_eq!(flush_batch(&db, &mut tx), Ok(()));
+		assert!(tx.operations.is_empty());
+		assert!(has_test_key(&db));
+	}
+}
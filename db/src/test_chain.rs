@@ -0,0 +1,219 @@
+//! In-memory chain harness for deterministic tests.
+
+use std::sync::Arc;
+use primitives::bigint::U256;
+use primitives::compact::Compact;
+use hash::H256;
+use bytes::Bytes;
+use chain::{
+	merkle_root, BlockHeader, EquihashSolution, IndexedBlock, IndexedBlockHeader,
+	IndexedTransaction, Transaction, TransactionInput, TransactionOutput,
+};
+use storage::{
+	BestBlock, BlockOrigin, BlockProvider, BlockChain, ForkChain, Error, SharedStore, SaplingTreeState,
+	TreeStateProvider,
+};
+use kv::MemoryDatabase;
+use BlockChainDatabase;
+
+/// Block version used by all mined blocks.
+const TEST_BLOCK_VERSION: u32 = 4;
+
+/// Chain of blocks backed by the in-memory database.
+///
+/// Blocks are mined with trivial proof of work, so the harness is usable with
+/// the unit-test consensus params. Connecting a block follows the same fork choice
+/// rules as the synchronization code: side chains with more work become canon.
+pub struct TestChain {
+	store: Arc<BlockChainDatabase<MemoryDatabase>>,
+	bits: Compact,
+	time: u32,
+}
+
+impl TestChain {
+	/// Creates new chain, starting with given genesis block.
+	pub fn new(genesis: IndexedBlock) -> Self {
+		let time = genesis.header.raw.time;
+		let bits = genesis.header.raw.bits;
+		TestChain {
+			store: Arc::new(BlockChainDatabase::init_test_chain(vec![genesis])),
+			bits: bits,
+			time: time,
+		}
+	}
+
+	/// Creates new chain, starting with block mined on top of zero hash.
+	pub fn with_bits(bits: Compact) -> Self {
+		let genesis = mine_block(H256::default(), Vec::new(), bits, 0);
+		let mut test_chain = TestChain::new(genesis);
+		test_chain.bits = bits;
+		test_chain
+	}
+
+	/// Returns underlying store.
+	pub fn store(&self) -> SharedStore {
+		self.store.clone()
+	}
+
+	/// Returns underlying database.
+	pub fn database(&self) -> &BlockChainDatabase<MemoryDatabase> {
+		&self.store
+	}
+
+	/// Returns current best block.
+	pub fn best_block(&self) -> BestBlock {
+		self.store.best_block()
+	}
+
+	/// Returns hash of canon block at given height.
+	pub fn block_hash(&self, number: u32) -> Option<H256> {
+		self.store.block_hash(number)
+	}
+
+	/// Mines block with given transactions on top of given parent. Block is not connected.
+	///
+	/// If transactions list is empty, unique coinbase transaction is added to the block.
+	/// Parent block must be already inserted into the database.
+	pub fn mine(&mut self, parent: H256, transactions: Vec<Transaction>) -> IndexedBlock {
+		self.time += 1;
+		let transactions = if transactions.is_empty() {
+			vec![unique_coinbase(self.time)]
+		} else {
+			transactions
+		};
+
+		let final_sapling_root = self.final_sapling_root(&parent, &transactions);
+		mine_block_with_sapling_root(parent, transactions, final_sapling_root, self.bits, self.time)
+	}
+
+	/// Computes sapling root of the block with given transactions, mined on top of given parent.
+	fn final_sapling_root(&self, parent: &H256, transactions: &[Transaction]) -> H256 {
+		let parent_root = self.store.sapling_block_root(parent).unwrap_or_default();
+		let commitments: Vec<H256> = transactions.iter()
+			.filter_map(|tx| tx.sapling.as_ref())
+			.flat_map(|sapling| sapling.outputs.iter().map(|output| H256::from(output.note_commitment)))
+			.collect();
+		if commitments.is_empty() {
+			return parent_root;
+		}
+
+		let mut tree = self.store.sapling_tree_at(&parent_root).unwrap_or_else(SaplingTreeState::new);
+		for commitment in commitments {
+			tree.append(commitment).expect("test chain never fills sapling tree; qed");
+		}
+		tree.root()
+	}
+
+	/// Mines and connects block with given transactions on top of current best block.
+	pub fn mine_and_connect(&mut self, transactions: Vec<Transaction>) -> Result<H256, Error> {
+		let parent = self.best_block().hash;
+		let block = self.mine(parent, transactions);
+		let hash = block.hash().clone();
+		self.connect(block)?;
+		Ok(hash)
+	}
+
+	/// Mines and connects `count` empty blocks on top of given parent. Returns hashes of mined blocks.
+	///
+	/// If parent is not the best block, the mined blocks form a side chain, which
+	/// becomes canon as soon as it has more work than the current canon chain.
+	pub fn mine_on(&mut self, parent: H256, count: usize) -> Result<Vec<H256>, Error> {
+		let mut hashes = Vec::with_capacity(count);
+		let mut parent = parent;
+		for _ in 0..count {
+			let block = self.mine(parent, Vec::new());
+			parent = block.hash().clone();
+			self.connect(block)?;
+			hashes.push(parent.clone());
+		}
+		Ok(hashes)
+	}
+
+	/// Connects block to the chain, reorganizing it if required.
+	pub fn connect(&self, block: IndexedBlock) -> Result<(), Error> {
+		match self.store.block_origin(&block.header)? {
+			BlockOrigin::KnownBlock => Ok(()),
+			BlockOrigin::CanonChain { .. } => {
+				let hash = block.hash().clone();
+				self.store.insert(block)?;
+				self.store.canonize(&hash)
+			},
+			BlockOrigin::SideChain(_) => self.store.insert(block),
+			BlockOrigin::SideChainBecomingCanonChain(origin) => {
+				let hash = block.hash().clone();
+				let fork = self.store.fork(origin)?;
+				fork.store().insert(block)?;
+				fork.store().canonize(&hash)?;
+				self.store.switch_to_fork(fork)
+			},
+		}
+	}
+
+	/// Rollbacks `count` best blocks, making their parent the new best block.
+	pub fn rollback(&self, count: usize) -> Result<H256, Error> {
+		let mut best_hash = self.best_block().hash;
+		for _ in 0..count {
+			best_hash = BlockChain::rollback_best(&*self.store)?;
+		}
+		Ok(best_hash)
+	}
+}
+
+/// Mines block on top of given parent.
+///
+/// Nonce is incremented until block hash satisfies the target, represented by `bits`.
+pub fn mine_block(parent: H256, transactions: Vec<Transaction>, bits: Compact, time: u32) -> IndexedBlock {
+	mine_block_with_sapling_root(parent, transactions, Default::default(), bits, time)
+}
+
+fn mine_block_with_sapling_root(
+	parent: H256,
+	transactions: Vec<Transaction>,
+	final_sapling_root: H256,
+	bits: Compact,
+	time: u32,
+) -> IndexedBlock {
+	let transactions: Vec<IndexedTransaction> = transactions.into_iter().map(IndexedTransaction::from_raw).collect();
+	let merkle_root_hash = merkle_root(&transactions.iter().map(|tx| &tx.hash).collect::<Vec<&H256>>());
+	let target: U256 = bits.into();
+
+	let mut header = BlockHeader {
+		version: TEST_BLOCK_VERSION,
+		previous_header_hash: parent,
+		merkle_root_hash: merkle_root_hash,
+		final_sapling_root: final_sapling_root,
+		time: time,
+		bits: bits,
+		nonce: Default::default(),
+		solution: EquihashSolution::default(),
+	};
+
+	let mut nonce = 0u64;
+	loop {
+		let indexed_header = IndexedBlockHeader::from_raw(header.clone());
+		if U256::from(&*indexed_header.hash.reversed() as &[u8]) <= target {
+			return IndexedBlock::new(indexed_header, transactions);
+		}
+
+		nonce += 1;
+		let mut raw_nonce = [0u8; 32];
+		for i in 0..8 {
+			raw_nonce[i] = (nonce >> (8 * i)) as u8;
+		}
+		header.nonce = raw_nonce.into();
+	}
+}
+
+fn unique_coinbase(seed: u32) -> Transaction {
+	let script_sig: Bytes = vec![4, seed as u8, (seed >> 8) as u8, (seed >> 16) as u8, (seed >> 24) as u8].into();
+
+	Transaction {
+		version: 1,
+		inputs: vec![TransactionInput::coinbase(script_sig)],
+		outputs: vec![TransactionOutput {
+			value: 0,
+			script_pubkey: Bytes::new(),
+		}],
+		..Default::default()
+	}
+}
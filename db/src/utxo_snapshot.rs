@@ -0,0 +1,634 @@
+//! UTXO set snapshot.
+//!
+//! Snapshot starts with `UTXO_SNAPSHOT_MAGIC`, followed by the list of records (every record is
+//! prefixed with its type byte, the list is terminated with `RECORD_END`) and `dhash256` of
+//! everything written before. Records are:
+//! - hashes of all canon blocks, starting from the genesis block;
+//! - headers (with indices) of `UTXO_SNAPSHOT_HEADERS` most recent canon blocks, required to
+//!   verify blocks that follow the snapshot. Header immediately follows hash of its block;
+//! - all distinct Sprout commitment trees of canon blocks (i.e. all valid Sprout anchors) and
+//!   all distinct final Sapling roots of canon blocks (i.e. all valid Sapling anchors);
+//! - Sapling commitment tree of the best block;
+//! - all Sprout and Sapling nullifiers, revealed by canon transactions;
+//! - all unspent outputs. Output follows hash of its block, outputs of the same transaction are
+//!   adjacent and ordered by index.
+//!
+//! Snapshot is imported without full transactions: unspent outputs are stored in the dedicated
+//! column and records, that can't be restored from the database, are kept, so that the snapshot of
+//! the imported database could also be written.
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::mem::replace;
+use chain::{OutPoint, TransactionOutput, BlockHeader, IndexedBlockHeader};
+use crypto::{DHash256, Digest};
+use primitives::hash::{H32, H256};
+use ser::{Serializable, Deserializable, Stream, Reader, Error as ReaderError, List, serialize};
+use storage::{
+	Error, BestBlock, BlockIndex, TransactionMeta, EpochTag, EpochRef, SproutTreeState,
+	SaplingTreeState, BlockProvider, BlockHeaderProvider, TransactionMetaProvider,
+	TransactionOutputProvider, TreeStateProvider,
+};
+use kv::{KeyValueDatabase, Transaction as DBTransaction, KeyValue};
+use block_chain_db::{
+	BlockChainDatabase, KEY_BEST_BLOCK_NUMBER, KEY_BEST_BLOCK_HASH, KEY_UTXO_SNAPSHOT_BLOCK_NUMBER,
+	BLOCK_TIMESTAMP_BUCKET,
+};
+
+/// Magic bytes, starting the UTXO set snapshot.
+pub const UTXO_SNAPSHOT_MAGIC: [u8; 4] = *b"ZUTX";
+/// Number of the most recent canon blocks headers, included in the snapshot. Headers are required
+/// to verify the following blocks (difficulty adjustment, median time past).
+pub const UTXO_SNAPSHOT_HEADERS: u32 = 100;
+/// Max number of operations that snapshot import keeps in memory before writing them to the database.
+pub const UTXO_IMPORT_BATCH_SIZE: usize = 16 * 1024;
+
+const RECORD_END: u8 = 0;
+const RECORD_BLOCK_HASH: u8 = 1;
+const RECORD_BLOCK_HEADER: u8 = 2;
+const RECORD_SPROUT_TREE: u8 = 3;
+const RECORD_SAPLING_ANCHOR: u8 = 4;
+const RECORD_SAPLING_TREE: u8 = 5;
+const RECORD_SPROUT_NULLIFIER: u8 = 6;
+const RECORD_SAPLING_NULLIFIER: u8 = 7;
+const RECORD_OUTPUT: u8 = 8;
+
+/// Unspent transaction output of the UTXO set snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UtxoEntry {
+	/// Reference to the output.
+	pub outpoint: OutPoint,
+	/// The output itself.
+	pub output: TransactionOutput,
+	/// True if output belongs to the coinbase transaction.
+	pub is_coinbase: bool,
+	/// Number of the block, containing output.
+	pub height: u32,
+}
+
+impl Serializable for UtxoEntry {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.outpoint)
+			.append(&self.output)
+			.append(&self.is_coinbase)
+			.append(&self.height);
+	}
+}
+
+impl Deserializable for UtxoEntry {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		Ok(UtxoEntry {
+			outpoint: reader.read()?,
+			output: reader.read()?,
+			is_coinbase: reader.read()?,
+			height: reader.read()?,
+		})
+	}
+}
+
+/// Record of the imported UTXO set snapshot, kept in the database. Blocks of the imported snapshot
+/// have no transactions, so these records are the only source of their shielded state and outputs.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UtxoSnapshotRecord {
+	/// Root of the Sprout commitment tree (the tree itself is stored in the tree states column).
+	SproutTree(H256),
+	/// Final Sapling root of the block.
+	SaplingAnchor(H256),
+	/// Sprout nullifier.
+	SproutNullifier(H256),
+	/// Sapling nullifier.
+	SaplingNullifier(H256),
+	/// Unspent (at the moment of import) output. The output itself is stored in the UTXO column.
+	Output(OutPoint),
+}
+
+impl Serializable for UtxoSnapshotRecord {
+	fn serialize(&self, stream: &mut Stream) {
+		match *self {
+			UtxoSnapshotRecord::SproutTree(ref root) => stream.append(&RECORD_SPROUT_TREE).append(root),
+			UtxoSnapshotRecord::SaplingAnchor(ref root) => stream.append(&RECORD_SAPLING_ANCHOR).append(root),
+			UtxoSnapshotRecord::SproutNullifier(ref nullifier) => stream.append(&RECORD_SPROUT_NULLIFIER).append(nullifier),
+			UtxoSnapshotRecord::SaplingNullifier(ref nullifier) => stream.append(&RECORD_SAPLING_NULLIFIER).append(nullifier),
+			UtxoSnapshotRecord::Output(ref outpoint) => stream.append(&RECORD_OUTPUT).append(outpoint),
+		};
+	}
+}
+
+impl Deserializable for UtxoSnapshotRecord {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		match reader.read()? {
+			RECORD_SPROUT_TREE => Ok(UtxoSnapshotRecord::SproutTree(reader.read()?)),
+			RECORD_SAPLING_ANCHOR => Ok(UtxoSnapshotRecord::SaplingAnchor(reader.read()?)),
+			RECORD_SPROUT_NULLIFIER => Ok(UtxoSnapshotRecord::SproutNullifier(reader.read()?)),
+			RECORD_SAPLING_NULLIFIER => Ok(UtxoSnapshotRecord::SaplingNullifier(reader.read()?)),
+			RECORD_OUTPUT => Ok(UtxoSnapshotRecord::Output(reader.read()?)),
+			_ => Err(ReaderError::MalformedData),
+		}
+	}
+}
+
+/// UTXO set snapshot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UtxoSnapshot {
+	/// Hashes of all canon blocks, starting from the genesis block.
+	pub block_hashes: Vec<H256>,
+	/// Headers of the most recent canon blocks, ending with the best block.
+	pub headers: Vec<(BlockHeader, BlockIndex)>,
+	/// Distinct Sprout commitment trees of canon blocks. The last one is the tree of the best block.
+	pub sprout_trees: Vec<SproutTreeState>,
+	/// Distinct final Sapling roots of canon blocks.
+	pub sapling_anchors: Vec<H256>,
+	/// Sapling commitment tree of the best block.
+	pub sapling_tree: SaplingTreeState,
+	/// Sprout nullifiers, revealed by canon transactions.
+	pub sprout_nullifiers: Vec<H256>,
+	/// Sapling nullifiers, revealed by canon transactions.
+	pub sapling_nullifiers: Vec<H256>,
+	/// All unspent outputs.
+	pub outputs: Vec<UtxoEntry>,
+}
+
+impl UtxoSnapshot {
+	/// Returns best block of the snapshot.
+	pub fn best_block(&self) -> BestBlock {
+		BestBlock {
+			number: self.block_hashes.len() as u32 - 1,
+			hash: self.block_hashes[self.block_hashes.len() - 1].clone(),
+		}
+	}
+}
+
+/// Writer, that also computes hash of all written data.
+struct HashingWriter<W> {
+	writer: W,
+	hasher: DHash256,
+}
+
+impl<W: io::Write> HashingWriter<W> {
+	fn append<T: Serializable>(&mut self, value: &T) -> Result<(), Error> {
+		let bytes = serialize(value);
+		self.hasher.write_all(&bytes).map_err(snapshot_io_error)?;
+		self.writer.write_all(&bytes).map_err(snapshot_io_error)
+	}
+
+	fn append_record<T: Serializable>(&mut self, record: u8, value: &T) -> Result<(), Error> {
+		self.append(&record)?;
+		self.append(value)
+	}
+
+	fn append_block<T>(&mut self, store: &BlockChainDatabase<T>, number: u32, hash: &H256, first_header: u32) -> Result<(), Error> where T: KeyValueDatabase {
+		self.append_record(RECORD_BLOCK_HASH, hash)?;
+		if number < first_header {
+			return Ok(());
+		}
+
+		let header = store.block_header(hash.clone().into())
+			.ok_or_else(|| Error::DatabaseError(format!("Missing header of canon block {}", hash.reversed())))?;
+		let index = store.block_index(hash)
+			.ok_or_else(|| Error::DatabaseError(format!("Missing index of canon block {}", hash.reversed())))?;
+		self.append_record(RECORD_BLOCK_HEADER, &header.raw)?;
+		self.append(&index)
+	}
+}
+
+/// Writes snapshot of the canon chain UTXO set and shielded pools state.
+pub fn dump_utxo<T, W>(store: &BlockChainDatabase<T>, writer: W) -> Result<(), Error> where T: KeyValueDatabase, W: io::Write {
+	let best_block = store.best_block();
+	let mut writer = HashingWriter {
+		writer: writer,
+		hasher: DHash256::new(),
+	};
+
+	writer.append(&H32::from(UTXO_SNAPSHOT_MAGIC))?;
+
+	let first_header = (best_block.number + 1).saturating_sub(UTXO_SNAPSHOT_HEADERS);
+	let mut first_block = 0;
+	let mut sprout_root = None;
+	let mut sapling_root = None;
+	if let Some(snapshot_block) = store.utxo_snapshot_block_number() {
+		// blocks of the imported snapshot have no transactions => their state comes from the snapshot records
+		let mut snapshot_block_hash = H256::default();
+		for number in 0..snapshot_block + 1 {
+			snapshot_block_hash = store.block_hash(number)
+				.ok_or_else(|| Error::DatabaseError(format!("Missing canon block {}", number)))?;
+			writer.append_block(store, number, &snapshot_block_hash, first_header)?;
+		}
+
+		let mut index = 0;
+		while let Some(record) = store.utxo_snapshot_record(index) {
+			match record {
+				UtxoSnapshotRecord::SproutTree(root) => {
+					let sprout_tree = store.sprout_tree_at(&root)
+						.ok_or_else(|| Error::DatabaseError(format!("Missing sprout tree {}", root.reversed())))?;
+					writer.append_record(RECORD_SPROUT_TREE, &sprout_tree)?;
+				},
+				UtxoSnapshotRecord::SaplingAnchor(root) => writer.append_record(RECORD_SAPLING_ANCHOR, &root)?,
+				UtxoSnapshotRecord::SproutNullifier(nullifier) => writer.append_record(RECORD_SPROUT_NULLIFIER, &nullifier)?,
+				UtxoSnapshotRecord::SaplingNullifier(nullifier) => writer.append_record(RECORD_SAPLING_NULLIFIER, &nullifier)?,
+				UtxoSnapshotRecord::Output(outpoint) => {
+					let meta = store.transaction_meta(&outpoint.hash)
+						.ok_or_else(|| Error::DatabaseError(format!("Missing meta of imported transaction {}", outpoint.hash.reversed())))?;
+					if !meta.is_spent(outpoint.index as usize).unwrap_or(true) {
+						let output = store.transaction_output(&outpoint, 0)
+							.ok_or_else(|| Error::DatabaseError(format!("Missing imported output {}:{}", outpoint.hash.reversed(), outpoint.index)))?;
+						writer.append_record(RECORD_OUTPUT, &UtxoEntry {
+							outpoint: outpoint,
+							output: output,
+							is_coinbase: meta.is_coinbase(),
+							height: meta.height(),
+						})?;
+					}
+				},
+			}
+			index += 1;
+		}
+
+		first_block = snapshot_block + 1;
+		sprout_root = store.sprout_block_root(&snapshot_block_hash);
+		sapling_root = store.block_header(snapshot_block_hash.into()).map(|header| header.raw.final_sapling_root);
+	}
+
+	for number in first_block..best_block.number + 1 {
+		let block = store.block(number.into())
+			.ok_or_else(|| Error::DatabaseError(format!("Missing canon block {}", number)))?;
+		writer.append_block(store, number, block.hash(), first_header)?;
+
+		let block_sprout_root = store.sprout_block_root(block.hash());
+		if block_sprout_root != sprout_root {
+			let sprout_tree = block_sprout_root.as_ref().and_then(|root| store.sprout_tree_at(root))
+				.ok_or_else(|| Error::DatabaseError(format!("Missing sprout tree of canon block {}", block.hash().reversed())))?;
+			writer.append_record(RECORD_SPROUT_TREE, &sprout_tree)?;
+			sprout_root = block_sprout_root;
+		}
+
+		if sapling_root.as_ref() != Some(&block.header.raw.final_sapling_root) {
+			writer.append_record(RECORD_SAPLING_ANCHOR, &block.header.raw.final_sapling_root)?;
+			sapling_root = Some(block.header.raw.final_sapling_root.clone());
+		}
+
+		for tx in block.into_transactions() {
+			let meta = match store.transaction_meta(&tx.hash) {
+				Some(meta) => meta,
+				None => return Err(Error::DatabaseError(format!("Missing meta of canon transaction {}", tx.hash.reversed()))),
+			};
+
+			for nullifier in tx.raw.sprout_nullifiers() {
+				writer.append_record(RECORD_SPROUT_NULLIFIER, &H256::from(&nullifier[..]))?;
+			}
+
+			if let Some(ref sapling) = tx.raw.sapling {
+				for spend in &sapling.spends {
+					writer.append_record(RECORD_SAPLING_NULLIFIER, &H256::from(&spend.nullifier[..]))?;
+				}
+			}
+
+			for (index, output) in tx.raw.outputs.into_iter().enumerate() {
+				if meta.is_spent(index).unwrap_or(true) {
+					continue;
+				}
+
+				writer.append_record(RECORD_OUTPUT, &UtxoEntry {
+					outpoint: OutPoint::new(tx.hash.clone(), index as u32),
+					output: output,
+					is_coinbase: meta.is_coinbase(),
+					height: number,
+				})?;
+			}
+		}
+	}
+
+	let sapling_tree = store.sapling_tree_at_block(&best_block.hash)
+		.ok_or_else(|| Error::DatabaseError(format!("Missing sapling tree of canon block {}", best_block.hash.reversed())))?;
+	writer.append_record(RECORD_SAPLING_TREE, &sapling_tree)?;
+	writer.append(&RECORD_END)?;
+
+	let hash = writer.hasher.finish();
+	writer.writer.write_all(&*hash).map_err(snapshot_io_error)?;
+	writer.writer.flush().map_err(snapshot_io_error)
+}
+
+/// Single record of the snapshot.
+enum Record {
+	BlockHash(H256),
+	BlockHeader(BlockHeader, BlockIndex),
+	SproutTree(SproutTreeState),
+	SaplingAnchor(H256),
+	SaplingTree(SaplingTreeState),
+	SproutNullifier(H256),
+	SaplingNullifier(H256),
+	Output(UtxoEntry),
+}
+
+/// Reads snapshot record by record, checking its consistency on the fly.
+struct SnapshotReader<R> {
+	reader: Reader<R>,
+	hasher: DHash256,
+	best_block: Option<BestBlock>,
+	last_header: Option<(u32, H256)>,
+	has_sprout_tree: bool,
+	has_sapling_anchor: bool,
+	has_sapling_tree: bool,
+}
+
+impl<R> SnapshotReader<R> where R: io::Read {
+	fn new(reader: R) -> Result<Self, Error> {
+		let mut reader = SnapshotReader {
+			reader: Reader::from_read(reader),
+			hasher: DHash256::new(),
+			best_block: None,
+			last_header: None,
+			has_sprout_tree: false,
+			has_sapling_anchor: false,
+			has_sapling_tree: false,
+		};
+
+		let magic: H32 = reader.read()?;
+		if magic.take() != UTXO_SNAPSHOT_MAGIC {
+			return Err(invalid_snapshot("invalid magic"));
+		}
+
+		Ok(reader)
+	}
+
+	/// Reads next record. Returns `None` after the last record, once integrity of the whole snapshot is checked.
+	fn next_record(&mut self) -> Result<Option<Record>, Error> {
+		let record = match self.read()? {
+			RECORD_END => return self.finish().map(|_| None),
+			RECORD_BLOCK_HASH => {
+				let hash = self.read()?;
+				let number = self.best_block.as_ref().map_or(0, |best_block| best_block.number + 1);
+				if self.last_header.is_some() && self.last_header.as_ref().map(|h| h.0) != number.checked_sub(1) {
+					return Err(invalid_snapshot(&format!("header of block {} is missing", number - 1)));
+				}
+				self.best_block = Some(BestBlock {
+					number: number,
+					hash: hash,
+				});
+				Record::BlockHash(hash)
+			},
+			RECORD_BLOCK_HEADER => {
+				let header: BlockHeader = self.read()?;
+				let index: BlockIndex = self.read()?;
+				let header_hash = IndexedBlockHeader::from_raw(header.clone()).hash;
+				let number = match self.best_block {
+					Some(ref best_block) if best_block.hash == header_hash && best_block.number == index.height => best_block.number,
+					_ => return Err(invalid_snapshot(&format!("header {} doesn't match its block hash", header_hash.reversed()))),
+				};
+				if let Some((_, ref previous_header_hash)) = self.last_header {
+					if *previous_header_hash != header.previous_header_hash {
+						return Err(invalid_snapshot(&format!("header of block {} is not connected to its parent", number)));
+					}
+				}
+				self.last_header = Some((number, header_hash));
+				Record::BlockHeader(header, index)
+			},
+			RECORD_SPROUT_TREE => {
+				self.has_sprout_tree = true;
+				Record::SproutTree(self.read()?)
+			},
+			RECORD_SAPLING_ANCHOR => {
+				self.has_sapling_anchor = true;
+				Record::SaplingAnchor(self.read()?)
+			},
+			RECORD_SAPLING_TREE => {
+				self.has_sapling_tree = true;
+				Record::SaplingTree(self.read()?)
+			},
+			RECORD_SPROUT_NULLIFIER => Record::SproutNullifier(self.read()?),
+			RECORD_SAPLING_NULLIFIER => Record::SaplingNullifier(self.read()?),
+			RECORD_OUTPUT => {
+				let entry: UtxoEntry = self.read()?;
+				if self.best_block.as_ref().map_or(true, |best_block| entry.height > best_block.number) {
+					return Err(invalid_snapshot("output from unknown block"));
+				}
+				Record::Output(entry)
+			},
+			record => return Err(invalid_snapshot(&format!("unknown record {}", record))),
+		};
+
+		Ok(Some(record))
+	}
+
+	/// Checks that the snapshot is complete and is not corrupted.
+	fn finish(&mut self) -> Result<(), Error> {
+		let hash: H256 = self.reader.read().map_err(snapshot_read_error)?;
+		if hash != replace(&mut self.hasher, DHash256::new()).finish() {
+			return Err(invalid_snapshot("hash mismatch"));
+		}
+
+		let best_block_number = self.best_block.as_ref().map(|best_block| best_block.number);
+		if best_block_number.is_none() || self.last_header.as_ref().map(|h| h.0) != best_block_number {
+			return Err(invalid_snapshot("invalid number of headers"));
+		}
+		if !self.has_sprout_tree || !self.has_sapling_anchor {
+			return Err(invalid_snapshot("missing commitment trees"));
+		}
+		if !self.has_sapling_tree {
+			return Err(invalid_snapshot("missing sapling tree"));
+		}
+
+		Ok(())
+	}
+
+	fn read<T>(&mut self) -> Result<T, Error> where T: Deserializable {
+		let hasher = &mut self.hasher;
+		self.reader.read_with_proxy(|bytes| hasher.input(bytes)).map_err(snapshot_read_error)
+	}
+}
+
+/// Reads UTXO set snapshot, written by `dump_utxo`, checking its integrity and consistency.
+pub fn read_utxo<R: io::Read>(reader: R) -> Result<UtxoSnapshot, Error> {
+	let mut reader = SnapshotReader::new(reader)?;
+	let mut block_hashes = Vec::new();
+	let mut headers = Vec::new();
+	let mut sprout_trees = Vec::new();
+	let mut sapling_anchors = Vec::new();
+	let mut sapling_tree = None;
+	let mut sprout_nullifiers = Vec::new();
+	let mut sapling_nullifiers = Vec::new();
+	let mut outputs = Vec::new();
+	while let Some(record) = reader.next_record()? {
+		match record {
+			Record::BlockHash(hash) => block_hashes.push(hash),
+			Record::BlockHeader(header, index) => headers.push((header, index)),
+			Record::SproutTree(tree) => sprout_trees.push(tree),
+			Record::SaplingAnchor(root) => sapling_anchors.push(root),
+			Record::SaplingTree(tree) => sapling_tree = Some(tree),
+			Record::SproutNullifier(nullifier) => sprout_nullifiers.push(nullifier),
+			Record::SaplingNullifier(nullifier) => sapling_nullifiers.push(nullifier),
+			Record::Output(entry) => outputs.push(entry),
+		}
+	}
+
+	Ok(UtxoSnapshot {
+		block_hashes: block_hashes,
+		headers: headers,
+		sprout_trees: sprout_trees,
+		sapling_anchors: sapling_anchors,
+		sapling_tree: sapling_tree.ok_or_else(|| invalid_snapshot("missing sapling tree"))?,
+		sprout_nullifiers: sprout_nullifiers,
+		sapling_nullifiers: sapling_nullifiers,
+		outputs: outputs,
+	})
+}
+
+/// Outputs of the imported transaction, which records are currently read.
+struct ImportedTransaction {
+	hash: H256,
+	height: u32,
+	is_coinbase: bool,
+	outputs: Vec<u32>,
+}
+
+impl ImportedTransaction {
+	fn meta(&self) -> TransactionMeta {
+		let outputs_count = self.outputs.last().map_or(0, |index| *index as usize + 1);
+		let mut meta = if self.is_coinbase {
+			TransactionMeta::new_coinbase(self.height, outputs_count)
+		} else {
+			TransactionMeta::new(self.height, outputs_count)
+		};
+		// outputs are ordered => every index, which is missing in the snapshot, belongs to spent output
+		let mut unspent = self.outputs.iter().peekable();
+		for index in 0..outputs_count {
+			if unspent.peek() == Some(&&(index as u32)) {
+				unspent.next();
+			} else {
+				meta.denote_used(index);
+			}
+		}
+		meta
+	}
+}
+
+/// Reads UTXO set snapshot, written by `dump_utxo`, and imports it into the empty database.
+/// Returns best block of the imported snapshot.
+///
+/// Snapshot is written in batches of `UTXO_IMPORT_BATCH_SIZE` operations. Best block is only
+/// written with the last batch (after integrity of the whole snapshot is checked), so the
+/// database, where import has failed, has no best block and must be removed before next attempt.
+///
+/// Imported transactions have no full transaction in the database: their unspent outputs are
+/// stored in the UTXO column and spent outputs are marked as spent in the transaction meta.
+/// Blocks of the snapshot are imported without transactions and can't be decanonized.
+pub fn import_utxo<T, R>(db: &T, reader: R) -> Result<BestBlock, Error> where T: KeyValueDatabase, R: io::Read {
+	let mut reader = SnapshotReader::new(reader)?;
+	let mut update = DBTransaction::new();
+	let mut best_block = BestBlock::default();
+	let mut blocks_count = 0u32;
+	let mut timestamp_buckets: HashMap<u32, Vec<H256>> = HashMap::new();
+	let mut best_sprout_root = H256::default();
+	let mut best_sapling_root = H256::default();
+	let mut sapling_tree = None;
+	let mut transaction: Option<ImportedTransaction> = None;
+	let mut records = 0u32;
+	while let Some(record) = reader.next_record()? {
+		let snapshot_record = match record {
+			Record::BlockHash(hash) => {
+				best_block = BestBlock {
+					number: blocks_count,
+					hash: hash,
+				};
+				blocks_count += 1;
+				update.insert(KeyValue::BlockHash(best_block.number, hash));
+				update.insert(KeyValue::BlockNumber(hash, best_block.number));
+				None
+			},
+			Record::BlockHeader(header, index) => {
+				let header = IndexedBlockHeader::from_raw(header);
+				timestamp_buckets.entry(header.raw.time / BLOCK_TIMESTAMP_BUCKET).or_insert_with(Vec::new).push(header.hash.clone());
+				update.insert(KeyValue::BlockIndex(header.hash.clone(), index));
+				update.insert(KeyValue::BlockHeader(header.hash, header.raw));
+				None
+			},
+			Record::SproutTree(sprout_tree) => {
+				best_sprout_root = sprout_tree.root();
+				update.insert(KeyValue::Anchor(EpochRef::new(EpochTag::Sprout, best_sprout_root.clone())));
+				update.insert(KeyValue::SproutTreeState(best_sprout_root.clone(), sprout_tree));
+				Some(UtxoSnapshotRecord::SproutTree(best_sprout_root.clone()))
+			},
+			Record::SaplingAnchor(root) => {
+				best_sapling_root = root.clone();
+				update.insert(KeyValue::Anchor(EpochRef::new(EpochTag::Sapling, root.clone())));
+				Some(UtxoSnapshotRecord::SaplingAnchor(root))
+			},
+			Record::SaplingTree(tree) => {
+				sapling_tree = Some(tree);
+				None
+			},
+			Record::SproutNullifier(nullifier) => {
+				update.insert(KeyValue::Nullifier(EpochRef::new(EpochTag::Sprout, nullifier.clone())));
+				Some(UtxoSnapshotRecord::SproutNullifier(nullifier))
+			},
+			Record::SaplingNullifier(nullifier) => {
+				update.insert(KeyValue::Nullifier(EpochRef::new(EpochTag::Sapling, nullifier.clone())));
+				Some(UtxoSnapshotRecord::SaplingNullifier(nullifier))
+			},
+			Record::Output(entry) => {
+				let is_next_output = match transaction {
+					Some(ref transaction) if transaction.hash == entry.outpoint.hash => {
+						if transaction.outputs.last().map_or(false, |index| *index >= entry.outpoint.index) {
+							return Err(invalid_snapshot("outputs of transaction are not ordered"));
+						}
+						true
+					},
+					_ => false,
+				};
+				if !is_next_output {
+					if let Some(transaction) = transaction.take() {
+						update.insert(KeyValue::TransactionMeta(transaction.hash.clone(), transaction.meta()));
+					}
+					transaction = Some(ImportedTransaction {
+						hash: entry.outpoint.hash.clone(),
+						height: entry.height,
+						is_coinbase: entry.is_coinbase,
+						outputs: Vec::new(),
+					});
+				}
+
+				transaction.as_mut().expect("set above; qed").outputs.push(entry.outpoint.index);
+				update.insert(KeyValue::UtxoOutput(entry.outpoint.clone(), entry.output));
+				Some(UtxoSnapshotRecord::Output(entry.outpoint))
+			},
+		};
+
+		if let Some(snapshot_record) = snapshot_record {
+			update.insert(KeyValue::UtxoSnapshotRecord(records, snapshot_record));
+			records += 1;
+		}
+
+		if update.operations.len() >= UTXO_IMPORT_BATCH_SIZE {
+			db.write(replace(&mut update, DBTransaction::new())).map_err(Error::DatabaseError)?;
+		}
+	}
+
+	if let Some(transaction) = transaction {
+		update.insert(KeyValue::TransactionMeta(transaction.hash.clone(), transaction.meta()));
+	}
+	for (bucket, hashes) in timestamp_buckets {
+		update.insert(KeyValue::BlockTimestamps(bucket, List::from(hashes)));
+	}
+	update.insert(KeyValue::SproutBlockRoot(best_block.hash.clone(), best_sprout_root));
+	// sapling tree is keyed by the final sapling root of the block header (see `BlockChainDatabase::insert`)
+	update.insert(KeyValue::SaplingTreeState(best_sapling_root, sapling_tree.expect("checked by SnapshotReader; qed")));
+
+	update.insert(KeyValue::Meta(KEY_BEST_BLOCK_HASH, serialize(&best_block.hash)));
+	update.insert(KeyValue::Meta(KEY_BEST_BLOCK_NUMBER, serialize(&best_block.number)));
+	update.insert(KeyValue::Meta(KEY_UTXO_SNAPSHOT_BLOCK_NUMBER, serialize(&best_block.number)));
+	db.write(update).map_err(Error::DatabaseError)?;
+
+	Ok(best_block)
+}
+
+fn invalid_snapshot(reason: &str) -> Error {
+	Error::InvalidUtxoSnapshot(reason.into())
+}
+
+fn snapshot_io_error(error: io::Error) -> Error {
+	Error::InvalidUtxoSnapshot(format!("{}", error))
+}
+
+fn snapshot_read_error(error: ReaderError) -> Error {
+	Error::InvalidUtxoSnapshot(format!("{:?}", error))
+}
@@ -1,12 +1,22 @@
 extern crate chain;
+extern crate primitives;
+extern crate serialization as ser;
 extern crate storage;
 extern crate db;
 extern crate test_data;
 
-use chain::IndexedBlock;
-use storage::{ForkChain, BlockProvider, SideChainOrigin};
-use db::BlockChainDatabase;
-use db::kv::{MemoryDatabase, SharedMemoryDatabase};
+use chain::{IndexedBlock, IndexedTransaction, Transaction, OutPoint, Sapling, SaplingSpendDescription, SaplingOutputDescription, JoinSplit, JoinSplitDescription};
+use primitives::compact::Compact;
+use primitives::hash::H256;
+use storage::{
+	ForkChain, BlockProvider, SideChainOrigin, NullifierTracker, EpochTag, EpochRef, ChainIndex, IndexKind,
+	TransactionLocation, SpentInfo, rescan_index, Store, TreeStateProvider, TransactionProvider, TransactionOutputProvider,
+	TransactionMetaProvider, Error as StorageError,
+};
+use db::{BlockChainDatabase, TestChain, UTXO_SNAPSHOT_MAGIC, read_utxo};
+use db::kv::{MemoryDatabase, SharedMemoryDatabase, KeyValueDatabase, Transaction as DBTransaction, Key, KeyValue};
+use db::migration::{migrate, MIGRATIONS, DB_VERSION, KEY_DB_VERSION};
+use ser::serialize;
 
 #[test]
 fn insert_block() {
@@ -75,6 +85,52 @@ fn reopen_db() {
 	}
 }
 
+#[test]
+fn block_index_is_restored_in_database_created_before_it_was_introduced() {
+	let shared_database = SharedMemoryDatabase::default();
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+	let b2: IndexedBlock = test_data::block_h2().into();
+	let b3: IndexedBlock = test_data::block_h3().into();
+
+	let expected_indexes = {
+		let store = BlockChainDatabase::open(shared_database.clone());
+		store.insert(b0.clone()).unwrap();
+		store.insert(b1.clone()).unwrap();
+		store.insert(b2.clone()).unwrap();
+
+		store.canonize(b0.hash()).unwrap();
+		store.canonize(b1.hash()).unwrap();
+
+		vec![
+			store.block_index(b0.hash()).unwrap(),
+			store.block_index(b1.hash()).unwrap(),
+			store.block_index(b2.hash()).unwrap(),
+		]
+	};
+
+	// simulate database of version 3, which has no block index
+	let mut update = DBTransaction::new();
+	update.delete(Key::BlockIndex(b0.hash().clone()));
+	update.delete(Key::BlockIndex(b1.hash().clone()));
+	update.delete(Key::BlockIndex(b2.hash().clone()));
+	update.insert(KeyValue::Meta(KEY_DB_VERSION, serialize(&3u32)));
+	shared_database.write(update).unwrap();
+
+	// migration restores index of canon blocks
+	migrate(&shared_database, MIGRATIONS, DB_VERSION).unwrap();
+	let store = BlockChainDatabase::open(shared_database);
+	assert_eq!(store.block_index(b0.hash()), Some(expected_indexes[0].clone()));
+	assert_eq!(store.block_index(b1.hash()), Some(expected_indexes[1].clone()));
+	assert_eq!(store.block_index(b2.hash()), None);
+
+	// index of non-canon parent is computed from headers
+	store.insert(b3.clone()).unwrap();
+	let b3_index = store.block_index(b3.hash()).unwrap();
+	assert_eq!(b3_index.height, 3);
+	assert_eq!(b3_index.chain_work, expected_indexes[2].chain_work + b3.header.raw.work().unwrap());
+}
+
 #[test]
 fn switch_to_simple_fork() {
 	let store = BlockChainDatabase::open(MemoryDatabase::default());
@@ -128,3 +184,417 @@ fn switch_to_simple_fork() {
 	assert_eq!(store.best_block().hash, store.block_hash(2).unwrap());
 
 }
+
+#[test]
+fn sapling_nullifier_is_spent_after_canonization() {
+	let spent_nullifier = [1u8; 32];
+	let unspent_nullifier = [2u8; 32];
+
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_builder()
+		.transaction().coinbase().build()
+		.with_transaction(test_data::TransactionBuilder::with_sapling(Sapling {
+			spends: vec![SaplingSpendDescription {
+				nullifier: spent_nullifier,
+				..Default::default()
+			}],
+			..Default::default()
+		}).into())
+		.merkled_header().parent(b0.hash().clone()).build()
+		.build()
+		.into();
+
+	let store = BlockChainDatabase::open(MemoryDatabase::default());
+	store.insert(b0.clone()).unwrap();
+	store.insert(b1.clone()).unwrap();
+	store.canonize(b0.hash()).unwrap();
+	assert!(!store.is_nullifier_spent(EpochTag::Sapling, &spent_nullifier.into()));
+
+	store.canonize(b1.hash()).unwrap();
+	assert!(store.is_nullifier_spent(EpochTag::Sapling, &spent_nullifier.into()));
+	assert!(!store.is_nullifier_spent(EpochTag::Sapling, &unspent_nullifier.into()));
+	// pools are disjoint
+	assert!(!store.is_nullifier_spent(EpochTag::Sprout, &spent_nullifier.into()));
+
+	store.decanonize().unwrap();
+	assert!(!store.is_nullifier_spent(EpochTag::Sapling, &spent_nullifier.into()));
+}
+
+#[test]
+fn address_index_is_backfilled_after_enabling() {
+	let store = BlockChainDatabase::init_test_chain(vec![
+		test_data::block_h0().into(),
+		test_data::block_h1().into(),
+	]);
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_h1().into();
+	let b2: IndexedBlock = test_data::block_h2().into();
+	let outpoint = |block: &IndexedBlock| OutPoint { hash: block.transactions()[0].hash.clone(), index: 0 };
+	let script = |block: &IndexedBlock| block.transactions()[0].raw.outputs[0].script_pubkey.clone();
+
+	// index is disabled by default
+	assert_eq!(store.index_next_block(IndexKind::Address), None);
+	assert_eq!(store.index_next_blocks(IndexKind::Address, 1), Err(storage::Error::IndexDisabled));
+	assert!(store.address_outputs(&script(&b0)).is_empty());
+
+	// index the first block, as if rescan has been interrupted
+	store.enable_index(IndexKind::Address).unwrap();
+	assert_eq!(store.index_next_block(IndexKind::Address), Some(0));
+	assert_eq!(store.index_next_blocks(IndexKind::Address, 1), Ok(1));
+	assert!(store.address_outputs(&script(&b0)).contains(&outpoint(&b0)));
+	assert!(!store.address_outputs(&script(&b1)).contains(&outpoint(&b1)));
+
+	// rescan is resumed from the first non-indexed block
+	let mut progress = Vec::new();
+	rescan_index(&store, IndexKind::Address, store.best_block().number, |next_block| progress.push(next_block)).unwrap();
+	assert_eq!(progress, vec![2]);
+	assert_eq!(store.index_next_block(IndexKind::Address), Some(2));
+	assert!(store.address_outputs(&script(&b1)).contains(&outpoint(&b1)));
+	// other indexes are not affected
+	assert_eq!(store.index_next_block(IndexKind::Transaction), None);
+	assert_eq!(store.transaction_location(&b1.transactions()[0].hash), None);
+
+	// index is updated when chain is changed
+	store.insert(b2.clone()).unwrap();
+	store.canonize(b2.hash()).unwrap();
+	assert_eq!(store.index_next_block(IndexKind::Address), Some(3));
+	assert!(store.address_outputs(&script(&b2)).contains(&outpoint(&b2)));
+
+	store.decanonize().unwrap();
+	assert_eq!(store.index_next_block(IndexKind::Address), Some(2));
+	assert!(!store.address_outputs(&script(&b2)).contains(&outpoint(&b2)));
+	assert!(store.address_outputs(&script(&b1)).contains(&outpoint(&b1)));
+}
+
+#[test]
+fn address_index_keeps_every_output_of_the_script() {
+	let script = vec![0x51u8];
+	let b0: IndexedBlock = test_data::block_h0().into();
+	let b1: IndexedBlock = test_data::block_builder()
+		.transaction().coinbase()
+			.output().value(10).script_pubkey_bytes(script.clone().into()).build()
+			.output().value(20).script_pubkey_bytes(script.clone().into()).build()
+			.build()
+		.merkled_header().parent(b0.hash().clone()).build()
+		.build()
+		.into();
+	let b2: IndexedBlock = test_data::block_builder()
+		.transaction().coinbase()
+			.output().value(30).script_pubkey_bytes(script.clone().into()).build()
+			.build()
+		.merkled_header().parent(b1.hash().clone()).build()
+		.build()
+		.into();
+	let outpoint = |block: &IndexedBlock, index| OutPoint { hash: block.transactions()[0].hash.clone(), index: index };
+
+	let store = BlockChainDatabase::init_test_chain(vec![b0, b1.clone(), b2.clone()]);
+	store.enable_index(IndexKind::Address).unwrap();
+	rescan_index(&store, IndexKind::Address, 2, |_| ()).unwrap();
+
+	let mut outputs = store.address_outputs(&script);
+	outputs.sort();
+	let mut expected = vec![outpoint(&b1, 0), outpoint(&b1, 1), outpoint(&b2, 0)];
+	expected.sort();
+	assert_eq!(outputs, expected);
+
+	store.decanonize().unwrap();
+	let mut outputs = store.address_outputs(&script);
+	outputs.sort();
+	expected.retain(|o| *o != outpoint(&b2, 0));
+	assert_eq!(outputs, expected);
+}
+
+#[test]
+fn transaction_and_spent_indexes_follow_canon_chain() {
+	let b0: IndexedBlock = test_data::block_builder()
+		.transaction().coinbase()
+			.output().value(10).build()
+			.build()
+		.merkled_header().build()
+		.build()
+		.into();
+	let b0_coinbase = b0.transactions()[0].hash.clone();
+	let b1: IndexedBlock = test_data::block_builder()
+		.transaction().coinbase().build()
+		.transaction()
+			.input().hash(b0_coinbase.clone()).index(0).build()
+			.output().value(10).build()
+			.build()
+		.merkled_header().parent(b0.hash().clone()).build()
+		.build()
+		.into();
+	let b1_transaction = b1.transactions()[1].hash.clone();
+	let spent_outpoint = OutPoint { hash: b0_coinbase.clone(), index: 0 };
+
+	let store = BlockChainDatabase::init_test_chain(vec![b0.clone()]);
+	store.enable_index(IndexKind::Transaction).unwrap();
+	store.enable_index(IndexKind::Spent).unwrap();
+	rescan_index(&store, IndexKind::Transaction, 0, |_| ()).unwrap();
+	assert_eq!(store.index_next_block(IndexKind::Transaction), Some(1));
+	assert_eq!(store.index_next_block(IndexKind::Spent), Some(0));
+	assert_eq!(store.transaction_location(&b0_coinbase), Some(TransactionLocation {
+		block_hash: b0.hash().clone(),
+		index: 0,
+	}));
+
+	// spent index is not backfilled yet => it is not updated on canonization
+	store.insert(b1.clone()).unwrap();
+	store.canonize(b1.hash()).unwrap();
+	assert_eq!(store.index_next_block(IndexKind::Transaction), Some(2));
+	assert_eq!(store.index_next_block(IndexKind::Spent), Some(0));
+	assert_eq!(store.spent_info(&spent_outpoint), None);
+	assert_eq!(store.transaction_location(&b1_transaction), Some(TransactionLocation {
+		block_hash: b1.hash().clone(),
+		index: 1,
+	}));
+
+	rescan_index(&store, IndexKind::Spent, 1, |_| ()).unwrap();
+	assert_eq!(store.index_next_block(IndexKind::Spent), Some(2));
+	assert_eq!(store.spent_info(&spent_outpoint), Some(SpentInfo {
+		hash: b1_transaction.clone(),
+		input_index: 0,
+		height: 1,
+	}));
+
+	store.decanonize().unwrap();
+	assert_eq!(store.index_next_block(IndexKind::Transaction), Some(1));
+	assert_eq!(store.index_next_block(IndexKind::Spent), Some(1));
+	assert_eq!(store.transaction_location(&b1_transaction), None);
+	assert_eq!(store.spent_info(&spent_outpoint), None);
+	assert!(store.transaction_location(&b0_coinbase).is_some());
+}
+
+#[test]
+fn block_locator_hashes() {
+	let mut test_chain = TestChain::with_bits(Compact::max_value());
+	let genesis = test_chain.best_block().hash;
+	assert_eq!(test_chain.store().block_locator_hashes(), vec![genesis.clone()]);
+
+	let hashes = test_chain.mine_on(genesis.clone(), 30).unwrap();
+	let block_hash = |number: usize| hashes[number - 1].clone();
+
+	// 10 dense hashes: 30, 29, ..., 21, followed by 19, 15, 7 (step doubles) and genesis
+	let mut expected: Vec<_> = (21..31).rev().map(&block_hash).collect();
+	expected.extend(vec![block_hash(19), block_hash(15), block_hash(7), genesis.clone()]);
+	assert_eq!(test_chain.store().block_locator_hashes(), expected);
+
+	// genesis is included exactly once, even if the walk ends at it
+	let mut test_chain = TestChain::with_bits(Compact::max_value());
+	let genesis = test_chain.best_block().hash;
+	let hashes = test_chain.mine_on(genesis.clone(), 5).unwrap();
+	let mut expected: Vec<_> = hashes.into_iter().rev().collect();
+	expected.push(genesis);
+	assert_eq!(test_chain.store().block_locator_hashes(), expected);
+}
+
+#[test]
+fn transaction_block_hash_follows_reorganization() {
+	let mut test_chain = TestChain::with_bits(Compact::max_value());
+	let genesis = test_chain.best_block().hash;
+	let store = test_chain.store();
+	let coinbase = |block_hash: &H256| store.block_transaction_hashes(block_hash.clone().into())[0].clone();
+
+	// canon chain: genesis -> a1 -> a2
+	let canon = test_chain.mine_on(genesis.clone(), 2).unwrap();
+	assert_eq!(store.transaction_block_hash(&coinbase(&canon[1])), Some(canon[1].clone()));
+	assert_eq!(store.transaction_block_hash(&coinbase(&canon[0])), Some(canon[0].clone()));
+
+	// side chain genesis -> b1 -> b2 -> b3 becomes canon
+	let side = test_chain.mine_on(genesis.clone(), 3).unwrap();
+	assert_eq!(test_chain.best_block().hash, side[2]);
+
+	// transactions of orphaned blocks are not indexed anymore
+	assert_eq!(store.transaction_block_hash(&coinbase(&canon[0])), None);
+	assert_eq!(store.transaction_block_hash(&coinbase(&canon[1])), None);
+	for hash in &side {
+		assert_eq!(store.transaction_block_hash(&coinbase(hash)), Some(hash.clone()));
+	}
+	assert_eq!(store.transaction_block_hash(&H256::default()), None);
+}
+
+#[test]
+fn utxo_snapshot_round_trip() {
+	let mut test_chain = TestChain::with_bits(Compact::max_value());
+	let genesis = test_chain.best_block().hash;
+	let store = test_chain.store();
+
+	// a1 reveals nullifiers and appends note commitments
+	let coinbase1: Transaction = test_data::TransactionBuilder::coinbase().add_output(10).add_output(20).into();
+	let shielded = test_data::TransactionBuilder::with_join_split(JoinSplit {
+		descriptions: vec![JoinSplitDescription {
+			nullifiers: [[1; 32], [2; 32]],
+			commitments: [[3; 32], [4; 32]],
+			..Default::default()
+		}],
+		..Default::default()
+	}).set_sapling(Sapling {
+		spends: vec![SaplingSpendDescription { nullifier: [5; 32], ..Default::default() }],
+		outputs: vec![SaplingOutputDescription { note_commitment: [6; 32], ..Default::default() }],
+		..Default::default()
+	}).into();
+	let a1 = test_chain.mine_and_connect(vec![coinbase1.clone(), shielded]).unwrap();
+
+	// a2 spends a1 coinbase output#0
+	let coinbase2: Transaction = test_data::TransactionBuilder::coinbase().add_output(30).into();
+	let spend: Transaction = test_data::TransactionBuilder::with_output(5).add_output(4).add_input(&coinbase1, 0).into();
+	let a2 = test_chain.mine_and_connect(vec![coinbase2, spend.clone()]).unwrap();
+	let coinbase1_hash = IndexedTransaction::from_raw(coinbase1).hash;
+	let spend_hash = IndexedTransaction::from_raw(spend.clone()).hash;
+
+	let mut snapshot = Vec::new();
+	store.dump_utxo(&mut snapshot).unwrap();
+	assert_eq!(&snapshot[..4], &UTXO_SNAPSHOT_MAGIC);
+
+	let parsed = read_utxo(&snapshot[..]).unwrap();
+	assert_eq!(parsed.block_hashes, vec![genesis, a1, a2]);
+	assert_eq!(parsed.outputs.len(), 4);
+
+	// snapshot could only be imported into the empty database
+	assert!(store.load_utxo(&mut &snapshot[..]).is_err());
+	let imported = BlockChainDatabase::open(MemoryDatabase::default());
+	imported.load_utxo(&snapshot[..]).unwrap();
+	assert_eq!(imported.best_block(), store.best_block());
+	assert_eq!(imported.best_header(), store.best_header());
+	assert_eq!(imported.block_hash(0), Some(genesis));
+	assert_eq!(imported.block_number(&a1), Some(1));
+
+	// UTXO set is the same, but transactions themselves are not imported
+	for entry in &parsed.outputs {
+		assert_eq!(imported.transaction_output(&entry.outpoint, 0), Some(entry.output.clone()));
+		assert!(!imported.is_spent(&entry.outpoint));
+		let meta = imported.transaction_meta(&entry.outpoint.hash).unwrap();
+		assert_eq!((meta.height(), meta.is_coinbase()), (entry.height, entry.is_coinbase));
+		assert!(imported.transaction(&entry.outpoint.hash).is_none());
+	}
+	assert!(imported.is_spent(&OutPoint { hash: coinbase1_hash, index: 0 }));
+	assert_eq!(imported.transaction_output(&OutPoint { hash: coinbase1_hash, index: 0 }, 0), None);
+
+	// snapshot of the imported database is the same
+	let mut imported_snapshot = Vec::new();
+	imported.dump_utxo(&mut imported_snapshot).unwrap();
+	assert_eq!(read_utxo(&imported_snapshot[..]).unwrap(), parsed);
+
+	// shielded state is the same
+	assert!(imported.is_nullifier_spent(EpochTag::Sprout, &[1u8; 32].into()));
+	assert!(imported.is_nullifier_spent(EpochTag::Sprout, &[2u8; 32].into()));
+	assert!(imported.is_nullifier_spent(EpochTag::Sapling, &[5u8; 32].into()));
+	for block_hash in &[genesis, a1, a2] {
+		let sprout_root = store.sprout_block_root(block_hash).unwrap();
+		let sapling_root = store.sapling_block_root(block_hash).unwrap();
+		assert!(imported.contains_anchor(EpochRef::new(EpochTag::Sprout, sprout_root)));
+		assert!(imported.contains_anchor(EpochRef::new(EpochTag::Sapling, sapling_root)));
+		assert_eq!(imported.sprout_tree_at(&sprout_root), store.sprout_tree_at(&sprout_root));
+	}
+	assert_eq!(imported.sapling_tree_at_block(&a2), store.sapling_tree_at_block(&a2));
+
+	// next block, spending imported output, is connected to both databases
+	let coinbase3: Transaction = test_data::TransactionBuilder::coinbase().add_output(40).into();
+	let spend2: Transaction = test_data::TransactionBuilder::with_output(3).add_input(&spend, 1).into();
+	let a3 = test_chain.mine(a2, vec![coinbase3, spend2]);
+	let a3_hash = a3.hash().clone();
+	test_chain.connect(a3.clone()).unwrap();
+	imported.insert(a3).unwrap();
+	imported.canonize(&a3_hash).unwrap();
+	assert!(imported.is_spent(&OutPoint { hash: spend_hash.clone(), index: 1 }));
+	assert_eq!(imported.sprout_tree_at_block(&a3_hash), store.sprout_tree_at_block(&a3_hash));
+	assert_eq!(imported.sapling_tree_at_block(&a3_hash), store.sapling_tree_at_block(&a3_hash));
+
+	// snapshot of the imported database includes blocks, connected after import
+	let mut snapshot_a3 = Vec::new();
+	let mut imported_snapshot_a3 = Vec::new();
+	store.dump_utxo(&mut snapshot_a3).unwrap();
+	imported.dump_utxo(&mut imported_snapshot_a3).unwrap();
+	assert_eq!(read_utxo(&imported_snapshot_a3[..]).unwrap(), read_utxo(&snapshot_a3[..]).unwrap());
+
+	// blocks of the snapshot can't be decanonized
+	assert_eq!(imported.decanonize(), Ok(a2));
+	assert!(!imported.is_spent(&OutPoint { hash: spend_hash.clone(), index: 1 }));
+	assert_eq!(imported.decanonize(), Err(StorageError::CannotDecanonize));
+
+	// corrupted snapshot is rejected
+	let mut corrupted = snapshot.clone();
+	let terminator = corrupted.len() - 33;
+	corrupted[terminator] ^= 1;
+	assert!(read_utxo(&corrupted[..]).is_err());
+	corrupted = snapshot.clone();
+	corrupted[0] = b'X';
+	assert!(read_utxo(&corrupted[..]).is_err());
+	assert!(read_utxo(&snapshot[..snapshot.len() - 1]).is_err());
+}
+
+#[test]
+fn shielded_state_is_rolled_back_on_reorganization() {
+	let mut test_chain = TestChain::with_bits(Compact::max_value());
+	let genesis = test_chain.best_block().hash;
+	let store = test_chain.store();
+	let sprout_anchor = |block_hash: &H256| EpochRef::new(EpochTag::Sprout, store.sprout_block_root(block_hash).unwrap());
+	let sapling_anchor = |block_hash: &H256| EpochRef::new(EpochTag::Sapling, store.sapling_block_root(block_hash).unwrap());
+
+	// canon chain: genesis -> a1, where a1 reveals nullifiers and appends note commitments
+	let coinbase = test_data::TransactionBuilder::coinbase().add_output(10).into();
+	let shielded = test_data::TransactionBuilder::with_join_split(JoinSplit {
+		descriptions: vec![JoinSplitDescription {
+			nullifiers: [[1; 32], [2; 32]],
+			commitments: [[3; 32], [4; 32]],
+			..Default::default()
+		}],
+		..Default::default()
+	}).set_sapling(Sapling {
+		spends: vec![SaplingSpendDescription { nullifier: [5; 32], ..Default::default() }],
+		outputs: vec![SaplingOutputDescription { note_commitment: [6; 32], ..Default::default() }],
+		..Default::default()
+	}).into();
+	let a1 = test_chain.mine_and_connect(vec![coinbase, shielded]).unwrap();
+	assert!(store.is_nullifier_spent(EpochTag::Sprout, &[1u8; 32].into()));
+	assert!(store.is_nullifier_spent(EpochTag::Sapling, &[5u8; 32].into()));
+	assert!(sprout_anchor(&a1) != sprout_anchor(&genesis));
+	assert!(sapling_anchor(&a1) != sapling_anchor(&genesis));
+	assert!(store.contains_anchor(sprout_anchor(&a1)));
+	assert!(store.contains_anchor(sapling_anchor(&a1)));
+
+	// side chain genesis -> b1 -> b2 becomes canon
+	let side = test_chain.mine_on(genesis.clone(), 2).unwrap();
+	assert_eq!(test_chain.best_block().hash, side[1]);
+
+	// nullifiers are unspent again, a1 roots are not anchors anymore and trees are back to genesis state
+	assert!(!store.is_nullifier_spent(EpochTag::Sprout, &[1u8; 32].into()));
+	assert!(!store.is_nullifier_spent(EpochTag::Sprout, &[2u8; 32].into()));
+	assert!(!store.is_nullifier_spent(EpochTag::Sapling, &[5u8; 32].into()));
+	assert!(!store.contains_anchor(sprout_anchor(&a1)));
+	assert!(!store.contains_anchor(sapling_anchor(&a1)));
+	assert!(store.contains_anchor(sprout_anchor(&genesis)));
+	assert!(store.contains_anchor(sapling_anchor(&genesis)));
+	assert_eq!(sprout_anchor(&side[1]), sprout_anchor(&genesis));
+	assert_eq!(sapling_anchor(&side[1]), sapling_anchor(&genesis));
+}
+
+#[test]
+fn block_hashes_by_time() {
+	let times = [1_000, 5_000, 4_000, 5_000, 10_000];
+	let mut blocks: Vec<IndexedBlock> = Vec::new();
+	for (nonce, &time) in times.iter().enumerate() {
+		let parent = blocks.last().map(|block| block.hash().clone()).unwrap_or_default();
+		blocks.push(test_data::block_builder()
+			.transaction().coinbase().lock_time(nonce as u32).build()
+			.merkled_header().parent(parent).time(time).build()
+			.build()
+			.into());
+	}
+	let hash = |number: usize| blocks[number].hash().clone();
+	let store = BlockChainDatabase::init_test_chain(blocks.clone());
+
+	// blocks are ordered by time, then by height
+	assert_eq!(store.block_hashes_by_time(4_000, 5_000), vec![hash(2), hash(1), hash(3)]);
+	assert_eq!(store.block_hashes_by_time(0, u32::max_value()), vec![hash(0), hash(2), hash(1), hash(3), hash(4)]);
+	assert_eq!(store.block_hashes_by_time(10_000, 10_000), vec![hash(4)]);
+
+	// no blocks in range
+	assert!(store.block_hashes_by_time(6_000, 9_999).is_empty());
+	assert!(store.block_hashes_by_time(5_000, 4_000).is_empty());
+	assert!(store.block_hashes_by_time(0, 999).is_empty());
+
+	// only canon blocks are returned
+	store.decanonize().unwrap();
+	store.decanonize().unwrap();
+	assert_eq!(store.block_hashes_by_time(0, u32::max_value()), vec![hash(0), hash(2), hash(1)]);
+}
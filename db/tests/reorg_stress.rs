@@ -0,0 +1,411 @@
+//! Randomized connect/disconnect/reorg sequences against the storage layer.
+//!
+//! After every step the state of the database (UTXO set, nullifiers, note commitment tree)
+//! is compared to the state, recomputed from scratch by replaying the active chain.
+
+extern crate chain;
+extern crate primitives;
+extern crate storage;
+extern crate db;
+
+use std::collections::{HashMap, HashSet};
+use chain::{
+	IndexedBlock, OutPoint, Transaction, TransactionInput, TransactionOutput, Sapling,
+	SaplingSpendDescription, SaplingOutputDescription, SAPLING_TX_VERSION, SAPLING_TX_VERSION_GROUP_ID,
+};
+use primitives::bytes::Bytes;
+use primitives::compact::Compact;
+use primitives::hash::H256;
+use storage::{
+	BlockHeaderProvider, BlockProvider, TransactionMetaProvider, TransactionOutputProvider,
+	NullifierTracker, TreeStateProvider, EpochTag, SaplingTreeState,
+};
+use db::{BlockChainDatabase, TestChain};
+
+/// Number of random steps, performed for every seed.
+const STEPS: usize = 60;
+/// Maximal depth of reorganization.
+const MAX_REORG_DEPTH: usize = 4;
+
+/// Deterministic xorshift generator, so that every failure is reproducible with its seed.
+struct Rng(u64);
+
+impl Rng {
+	fn next(&mut self) -> u64 {
+		let mut x = self.0;
+		x ^= x << 13;
+		x ^= x >> 7;
+		x ^= x << 17;
+		self.0 = x;
+		x
+	}
+
+	fn below(&mut self, n: usize) -> usize {
+		(self.next() % n as u64) as usize
+	}
+
+	fn chance(&mut self, one_in: usize) -> bool {
+		self.below(one_in) == 0
+	}
+
+	fn bytes(&mut self) -> [u8; 32] {
+		let mut bytes = [0u8; 32];
+		for chunk in bytes.chunks_mut(8) {
+			let value = self.next();
+			for (i, byte) in chunk.iter_mut().enumerate() {
+				*byte = (value >> (8 * i)) as u8;
+			}
+		}
+		bytes
+	}
+}
+
+/// State of the chain, computed by replaying its blocks.
+struct ChainState {
+	unspent: HashSet<OutPoint>,
+	spent: HashSet<OutPoint>,
+	nullifiers: HashSet<H256>,
+	sapling_tree: SaplingTreeState,
+}
+
+impl ChainState {
+	fn replay(blocks: &[IndexedBlock]) -> Self {
+		let mut state = ChainState {
+			unspent: HashSet::new(),
+			spent: HashSet::new(),
+			nullifiers: HashSet::new(),
+			sapling_tree: SaplingTreeState::new(),
+		};
+		for block in blocks {
+			state.apply(block);
+		}
+		state
+	}
+
+	fn apply(&mut self, block: &IndexedBlock) {
+		for tx in block.transactions() {
+			if !tx.raw.is_coinbase() {
+				for input in &tx.raw.inputs {
+					assert!(self.unspent.remove(&input.previous_output), "replayed chain spends unknown output");
+					self.spent.insert(input.previous_output.clone());
+				}
+			}
+
+			for index in 0..tx.raw.outputs.len() {
+				self.unspent.insert(OutPoint::new(tx.hash.clone(), index as u32));
+			}
+
+			if let Some(ref sapling) = tx.raw.sapling {
+				for spend in &sapling.spends {
+					assert!(self.nullifiers.insert(spend.nullifier.into()), "replayed chain reveals nullifier twice");
+				}
+				for output in &sapling.outputs {
+					self.sapling_tree.append(output.note_commitment.into()).expect("tree is never filled in test; qed");
+				}
+			}
+		}
+	}
+
+	/// Returns true if transaction could be included in the block on top of this state.
+	fn accepts(&self, tx: &Transaction) -> bool {
+		tx.inputs.iter().all(|input| self.unspent.contains(&input.previous_output))
+			&& tx.sapling.as_ref().map_or(true, |sapling| sapling.spends.iter()
+				.all(|spend| !self.nullifiers.contains(&H256::from(spend.nullifier))))
+	}
+}
+
+struct Harness {
+	rng: Rng,
+	chain: TestChain,
+	/// All mined blocks, including side chain and rolled back blocks.
+	blocks: HashMap<H256, IndexedBlock>,
+	/// Tips of side chains, which could be extended later.
+	side_tips: Vec<H256>,
+	/// All transactions ever mined.
+	transactions: HashSet<H256>,
+	/// All nullifiers ever revealed.
+	nullifiers: HashSet<H256>,
+}
+
+impl Harness {
+	fn new(seed: u64) -> Self {
+		let chain = TestChain::with_bits(Compact::max_value());
+		let genesis_hash = chain.best_block().hash;
+		let genesis = chain.database().block(genesis_hash.clone().into()).unwrap();
+
+		let mut harness = Harness {
+			rng: Rng(seed),
+			chain: chain,
+			blocks: HashMap::new(),
+			side_tips: Vec::new(),
+			transactions: HashSet::new(),
+			nullifiers: HashSet::new(),
+		};
+		harness.remember(genesis);
+		harness
+	}
+
+	fn remember(&mut self, block: IndexedBlock) {
+		for tx in block.transactions() {
+			self.transactions.insert(tx.hash.clone());
+			if let Some(ref sapling) = tx.raw.sapling {
+				self.nullifiers.extend(sapling.spends.iter().map(|spend| H256::from(spend.nullifier)));
+			}
+		}
+		self.blocks.insert(block.hash().clone(), block);
+	}
+
+	/// Returns blocks of the branch, ending with given block.
+	fn branch(&self, tip: &H256) -> Vec<IndexedBlock> {
+		let mut branch = Vec::new();
+		let mut hash = tip.clone();
+		while !hash.is_zero() {
+			let block = self.blocks[&hash].clone();
+			hash = block.header.raw.previous_header_hash.clone();
+			branch.push(block);
+		}
+		branch.reverse();
+		branch
+	}
+
+	/// Returns blocks of the canon chain.
+	fn canon(&self) -> Vec<IndexedBlock> {
+		self.branch(&self.chain.best_block().hash)
+	}
+
+	/// Returns true if all blocks of the branch are still in the database.
+	fn is_alive(&self, tip: &H256) -> bool {
+		self.branch(tip).iter().all(|block| self.chain.database().block_header(block.hash().clone().into()).is_some())
+	}
+
+	fn random_transaction(&mut self, state: &ChainState, used: &mut HashSet<OutPoint>) -> Option<Transaction> {
+		let mut candidates: Vec<OutPoint> = state.unspent.iter().filter(|o| !used.contains(*o)).cloned().collect();
+		if candidates.is_empty() {
+			return None;
+		}
+		// HashSet iteration order is random => sort to make the test reproducible
+		candidates.sort();
+
+		let inputs_count = 1 + self.rng.below(2);
+		let mut inputs = Vec::new();
+		for _ in 0..inputs_count {
+			if candidates.is_empty() {
+				break;
+			}
+			let index = self.rng.below(candidates.len());
+			let outpoint = candidates.swap_remove(index);
+			used.insert(outpoint.clone());
+			inputs.push(TransactionInput {
+				previous_output: outpoint,
+				script_sig: Bytes::new(),
+				sequence: 0xffffffff,
+			});
+		}
+
+		let outputs = (0..1 + self.rng.below(2)).map(|_| TransactionOutput {
+			value: self.rng.next() % 1_000,
+			script_pubkey: Bytes::new(),
+		}).collect();
+
+		let sapling = if self.rng.chance(2) {
+			let spends = (0..self.rng.below(2)).map(|_| SaplingSpendDescription {
+				nullifier: self.rng.bytes(),
+				..Default::default()
+			}).collect();
+			let outputs_count = if self.rng.chance(2) { 1 } else { 0 };
+			let outputs = (0..outputs_count).map(|_| SaplingOutputDescription {
+				note_commitment: self.rng.bytes(),
+				..Default::default()
+			}).collect();
+			Some(Sapling {
+				spends: spends,
+				outputs: outputs,
+				..Default::default()
+			})
+		} else {
+			None
+		};
+
+		Some(Transaction {
+			overwintered: true,
+			version: SAPLING_TX_VERSION,
+			version_group_id: SAPLING_TX_VERSION_GROUP_ID,
+			inputs: inputs,
+			outputs: outputs,
+			sapling: sapling,
+			..Default::default()
+		})
+	}
+
+	/// Mines `count` blocks on top of given parent. Transactions from `reusable` are included
+	/// into the blocks if they are still valid.
+	fn mine(&mut self, parent: H256, count: usize, reusable: &[Transaction]) -> H256 {
+		let mut state = ChainState::replay(&self.branch(&parent));
+		let mut parent = parent;
+		for _ in 0..count {
+			let coinbase = Transaction {
+				version: 1,
+				inputs: vec![TransactionInput::coinbase(self.rng.bytes().to_vec().into())],
+				outputs: vec![TransactionOutput { value: 1_000, script_pubkey: Bytes::new() }],
+				..Default::default()
+			};
+
+			let mut transactions = vec![coinbase];
+			let mut used = HashSet::new();
+			for tx in reusable {
+				let conflicts = tx.inputs.iter().any(|input| used.contains(&input.previous_output));
+				if !conflicts && state.accepts(tx) && self.rng.chance(2) {
+					used.extend(tx.inputs.iter().map(|input| input.previous_output.clone()));
+					transactions.push(tx.clone());
+				}
+			}
+			for _ in 0..self.rng.below(3) {
+				if let Some(tx) = self.random_transaction(&state, &mut used) {
+					transactions.push(tx);
+				}
+			}
+
+			// transactions in the block could only spend outputs of previous blocks
+			let block = self.chain.mine(parent, transactions);
+			parent = block.hash().clone();
+			state.apply(&block);
+			self.remember(block.clone());
+			self.chain.connect(block).unwrap();
+		}
+		parent
+	}
+
+	/// Non-coinbase transactions of the canon blocks above given height.
+	fn canon_transactions_above(&self, number: u32) -> Vec<Transaction> {
+		self.canon().into_iter()
+			.skip(number as usize + 1)
+			.flat_map(|block| block.into_transactions().into_iter().skip(1).map(|tx| tx.raw))
+			.collect()
+	}
+
+	fn step(&mut self) {
+		let best = self.chain.best_block();
+		match self.rng.below(9) {
+			// extend canon chain
+			0..=3 => {
+				let count = 1 + self.rng.below(3);
+				self.mine(best.hash, count, &[]);
+			},
+			// reorganize to the longer fork
+			4 | 5 if best.number > 0 => {
+				let depth = 1 + self.rng.below(::std::cmp::min(best.number as usize, MAX_REORG_DEPTH));
+				let ancestor = best.number - depth as u32;
+				let ancestor_hash = self.chain.block_hash(ancestor).unwrap();
+				let reusable = self.canon_transactions_above(ancestor);
+				let count = depth + 1 + self.rng.below(2);
+				self.mine(ancestor_hash, count, &reusable);
+				self.side_tips.push(best.hash);
+			},
+			// create side chain, which is not longer than the canon chain
+			6 if best.number > 0 => {
+				let depth = 1 + self.rng.below(::std::cmp::min(best.number as usize, MAX_REORG_DEPTH));
+				let ancestor = best.number - depth as u32;
+				let ancestor_hash = self.chain.block_hash(ancestor).unwrap();
+				let reusable = self.canon_transactions_above(ancestor);
+				let count = 1 + self.rng.below(depth);
+				let tip = self.mine(ancestor_hash, count, &reusable);
+				self.side_tips.push(tip);
+			},
+			// extend previously abandoned side chain, so that it becomes canon
+			7 => {
+				let tips: Vec<H256> = self.side_tips.iter().filter(|tip| self.is_alive(tip)).cloned().collect();
+				self.side_tips = tips;
+				if self.side_tips.is_empty() {
+					return;
+				}
+
+				let index = self.rng.below(self.side_tips.len());
+				let tip = self.side_tips.swap_remove(index);
+				let tip_number = self.branch(&tip).len() as u32 - 1;
+				if tip_number + MAX_REORG_DEPTH as u32 + 2 < best.number {
+					// too deep reorganization
+					return;
+				}
+				let reusable = self.canon_transactions_above(best.number.saturating_sub(MAX_REORG_DEPTH as u32 + 2));
+				// side chain could be even longer than canon chain, if canon blocks were disconnected
+				let count = ::std::cmp::max(1, (best.number + 1).saturating_sub(tip_number)) as usize + self.rng.below(2);
+				self.mine(tip, count, &reusable);
+				self.side_tips.push(best.hash);
+			},
+			// disconnect best blocks
+			8 if best.number > 2 => {
+				let count = 1 + self.rng.below(2);
+				self.chain.rollback(count).unwrap();
+			},
+			_ => (),
+		}
+	}
+
+	/// Compares database state to the state, recomputed from scratch.
+	fn check(&self) {
+		let db = self.chain.database();
+		let canon = self.canon();
+		let best = self.chain.best_block();
+		assert_eq!(canon.len() as u32, best.number + 1);
+
+		// canon blocks are stored completely
+		for (number, block) in canon.iter().enumerate() {
+			assert_eq!(db.block_hash(number as u32), Some(block.hash().clone()));
+			let stored = db.block(block.hash().clone().into()).expect("canon block is stored");
+			assert_eq!(
+				stored.transactions().iter().map(|tx| tx.hash.clone()).collect::<Vec<_>>(),
+				block.transactions().iter().map(|tx| tx.hash.clone()).collect::<Vec<_>>()
+			);
+		}
+
+		let state = ChainState::replay(&canon);
+		let fresh = BlockChainDatabase::init_test_chain(canon.clone());
+		assert_eq!(db.best_block(), fresh.best_block());
+
+		// UTXO set
+		for tx_hash in &self.transactions {
+			assert_eq!(db.transaction_meta(tx_hash), fresh.transaction_meta(tx_hash));
+		}
+		for outpoint in &state.unspent {
+			assert!(!db.is_spent(outpoint));
+			assert!(db.transaction_output(outpoint, usize::max_value()).is_some());
+		}
+		for outpoint in &state.spent {
+			assert!(db.is_spent(outpoint));
+		}
+
+		// nullifiers
+		for nullifier in &self.nullifiers {
+			let expected = state.nullifiers.contains(nullifier);
+			assert_eq!(db.is_nullifier_spent(EpochTag::Sapling, nullifier), expected);
+			assert_eq!(fresh.is_nullifier_spent(EpochTag::Sapling, nullifier), expected);
+		}
+
+		// note commitment tree
+		assert_eq!(db.sapling_tree_at_block(&best.hash), Some(state.sapling_tree.clone()));
+		assert_eq!(fresh.sapling_tree_at_block(&best.hash), Some(state.sapling_tree));
+	}
+}
+
+fn run_reorg_stress(seed: u64) {
+	let mut harness = Harness::new(seed);
+	for _ in 0..STEPS {
+		harness.step();
+		harness.check();
+	}
+}
+
+#[test]
+fn reorg_stress_seed_1() {
+	run_reorg_stress(1);
+}
+
+#[test]
+fn reorg_stress_seed_2() {
+	run_reorg_stress(0x5eed_5eed);
+}
+
+#[test]
+fn reorg_stress_seed_3() {
+	run_reorg_stress(0xdead_beef_cafe_babe);
+}
@@ -0,0 +1,117 @@
+extern crate primitives;
+extern crate storage;
+extern crate db;
+
+use primitives::compact::Compact;
+use storage::{BlockHeaderProvider, BlockProvider, TransactionMetaProvider};
+use db::TestChain;
+
+#[test]
+fn test_chain_connects_five_blocks() {
+	let mut test_chain = TestChain::with_bits(Compact::max_value());
+	let genesis = test_chain.best_block().hash;
+
+	let hashes = test_chain.mine_on(genesis, 5).unwrap();
+	assert_eq!(5, test_chain.best_block().number);
+	assert_eq!(hashes[4], test_chain.best_block().hash);
+	for (number, hash) in hashes.iter().enumerate() {
+		assert_eq!(Some(hash.clone()), test_chain.block_hash(number as u32 + 1));
+	}
+}
+
+#[test]
+fn test_chain_mines_blocks_satisfying_target() {
+	let bits: Compact = 0x2007ffff.into();
+	let mut test_chain = TestChain::with_bits(bits);
+	let genesis = test_chain.best_block().hash;
+
+	let block = test_chain.mine(genesis, Vec::new());
+	let target: primitives::bigint::U256 = bits.into();
+	assert!(primitives::bigint::U256::from(&*block.hash().reversed() as &[u8]) <= target);
+	assert_eq!(bits, block.header.raw.bits);
+}
+
+#[test]
+fn test_chain_reorganizes_to_longer_fork() {
+	let mut test_chain = TestChain::with_bits(Compact::max_value());
+	let genesis = test_chain.best_block().hash;
+
+	// canon chain: genesis -> a1 -> a2 -> a3 -> a4 -> a5
+	let canon = test_chain.mine_on(genesis.clone(), 5).unwrap();
+	assert_eq!(canon[4], test_chain.best_block().hash);
+
+	// side chain: a2 -> b3 -> b4 -> b5, still shorter than the canon chain
+	let fork = test_chain.mine_on(canon[1].clone(), 3).unwrap();
+	assert_eq!(5, test_chain.best_block().number);
+	assert_eq!(canon[4], test_chain.best_block().hash);
+
+	// b6 makes side chain longer => reorganization
+	let fork_head = test_chain.mine_on(fork[2].clone(), 1).unwrap();
+	assert_eq!(6, test_chain.best_block().number);
+	assert_eq!(fork_head[0], test_chain.best_block().hash);
+	assert_eq!(Some(canon[1].clone()), test_chain.block_hash(2));
+	assert_eq!(Some(fork[0].clone()), test_chain.block_hash(3));
+	assert_eq!(Some(fork[2].clone()), test_chain.block_hash(5));
+
+	// transactions of decanonized blocks are not in the canon chain anymore
+	let decanonized_tx = test_chain.database().block_transaction_hashes(canon[4].clone().into());
+	assert!(test_chain.database().transaction_meta(&decanonized_tx[0]).is_none());
+	assert_eq!(None, test_chain.database().block_number(&canon[4]));
+}
+
+#[test]
+fn test_chain_rollback() {
+	let mut test_chain = TestChain::with_bits(Compact::max_value());
+	let genesis = test_chain.best_block().hash;
+
+	let canon = test_chain.mine_on(genesis, 5).unwrap();
+	assert_eq!(canon[2], test_chain.rollback(2).unwrap());
+	assert_eq!(3, test_chain.best_block().number);
+	assert!(test_chain.database().block(canon[4].clone().into()).is_none());
+}
+
+#[test]
+fn test_chain_stores_block_index() {
+	let mut test_chain = TestChain::with_bits(0x207fffff.into());
+	let genesis = test_chain.best_block().hash;
+
+	// canon chain: genesis -> a1 -> a2 -> a3 -> a4
+	let canon = test_chain.mine_on(genesis.clone(), 4).unwrap();
+	// side chain: genesis -> a1 -> b2
+	let side = test_chain.mine_on(canon[0].clone(), 1).unwrap();
+
+	let mut expected_work = primitives::bigint::U256::zero();
+	for (height, hash) in ::std::iter::once(&genesis).chain(canon.iter()).enumerate() {
+		let header = test_chain.database().block_header(hash.clone().into()).unwrap();
+		expected_work = expected_work + header.raw.work().unwrap();
+
+		let index = test_chain.database().block_index(hash).unwrap();
+		assert_eq!(height as u32, index.height);
+		assert_eq!(expected_work, index.chain_work);
+	}
+
+	let canon_index = test_chain.database().block_index(&canon[1]).unwrap();
+	let side_index = test_chain.database().block_index(&side[0]).unwrap();
+	assert_eq!(canon_index, side_index);
+	assert_eq!(4, test_chain.best_block().number);
+}
+
+#[test]
+fn test_chain_switches_to_side_chain_with_more_work() {
+	// every canon block has work 2
+	let mut test_chain = TestChain::with_bits(0x207fffff.into());
+	let genesis = test_chain.best_block().hash;
+	let canon = test_chain.mine_on(genesis.clone(), 3).unwrap();
+
+	// side chain with the same work does not become canon
+	let side = test_chain.mine_on(canon[0].clone(), 2).unwrap();
+	assert_eq!(canon[2], test_chain.best_block().hash);
+	assert!(test_chain.database().block(side[1].clone().into()).is_some());
+
+	// side block has work 8192 => shorter side chain becomes canon
+	let heavy = db::mine_block(genesis, Vec::new(), 0x1f07ffff.into(), 1000);
+	let heavy_hash = heavy.hash().clone();
+	test_chain.connect(heavy).unwrap();
+	assert_eq!(1, test_chain.best_block().number);
+	assert_eq!(heavy_hash, test_chain.best_block().hash);
+}
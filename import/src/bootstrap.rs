@@ -0,0 +1,255 @@
+use std::{cmp, io, fs, path};
+use std::io::Read;
+use std::collections::VecDeque;
+use hash::H32;
+use ser::{deserialize, Error as ReaderError};
+use chain::IndexedBlock;
+use block::Block;
+
+/// Maximal size of single framed block. Frames with greater length are treated as corrupted.
+const MAX_FRAMED_BLOCK_SIZE: u32 = 32 * 1024 * 1024;
+
+/// Opens bootstrap.dat-style file: sequence of blocks, each prefixed with network magic and block length.
+pub fn open_bootstrap_file<P>(path: P, magic: u32) -> Result<BootstrapFile<io::BufReader<fs::File>>, io::Error> where P: AsRef<path::Path> {
+	trace!("Opening bootstrap file: {:?}", path.as_ref());
+	let file = try!(fs::File::open(path));
+	Ok(read_blocks(io::BufReader::new(file), magic))
+}
+
+/// Reads blocks from the stream of network-magic-prefixed and length-prefixed blocks.
+pub fn read_blocks<R>(reader: R, magic: u32) -> BootstrapFile<R> where R: io::BufRead {
+	BootstrapFile::new(reader, magic)
+}
+
+/// Iterator over blocks of bootstrap.dat-style file.
+///
+/// Corrupted frames are skipped: reading resumes from the next occurence of network magic
+/// after the beginning of corrupted frame. Truncated last frame (or read error) is reported
+/// as a single error, after which iteration stops.
+pub struct BootstrapFile<R> {
+	reader: R,
+	magic: [u8; 4],
+	/// Bytes that have been read from the reader, but must be scanned again.
+	pending: VecDeque<u8>,
+	/// True if error has been reported.
+	failed: bool,
+}
+
+impl<R> BootstrapFile<R> where R: io::BufRead {
+	pub fn new(reader: R, magic: u32) -> Self {
+		BootstrapFile {
+			reader: reader,
+			magic: [magic as u8, (magic >> 8) as u8, (magic >> 16) as u8, (magic >> 24) as u8],
+			pending: VecDeque::new(),
+			failed: false,
+		}
+	}
+
+	/// Reads exactly `len` bytes. Returns less bytes only if end of file has been reached.
+	fn read_bytes(&mut self, len: usize) -> Result<Vec<u8>, io::Error> {
+		let from_pending = cmp::min(len, self.pending.len());
+		let mut bytes: Vec<u8> = self.pending.drain(..from_pending).collect();
+		let remaining = (len - bytes.len()) as u64;
+		try!((&mut self.reader).take(remaining).read_to_end(&mut bytes));
+		Ok(bytes)
+	}
+
+	/// Skips bytes until network magic is found. Returns false if end of file has been reached.
+	fn skip_to_magic(&mut self) -> Result<bool, io::Error> {
+		let magic = self.magic;
+		let mut window = MagicWindow::default();
+		while let Some(byte) = self.pending.pop_front() {
+			if window.push(byte, &magic) {
+				return Ok(true);
+			}
+		}
+
+		loop {
+			let (consumed, found) = {
+				let buffer = match self.reader.fill_buf() {
+					Ok(buffer) => buffer,
+					Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+					Err(err) => return Err(err),
+				};
+
+				if buffer.is_empty() {
+					return Ok(false);
+				}
+
+				match buffer.iter().position(|byte| window.push(*byte, &magic)) {
+					Some(position) => (position + 1, true),
+					None => (buffer.len(), false),
+				}
+			};
+
+			self.reader.consume(consumed);
+			if found {
+				return Ok(true);
+			}
+		}
+	}
+
+	fn read_framed_block(&mut self) -> Result<Option<Block>, ReaderError> {
+		loop {
+			if !try!(self.skip_to_magic()) {
+				return Ok(None);
+			}
+
+			let size_bytes = try!(self.read_bytes(4));
+			if size_bytes.len() != 4 {
+				warn!("Bootstrap file is truncated: expected 4 bytes of block size, got {}", size_bytes.len());
+				return Err(ReaderError::UnexpectedEnd);
+			}
+
+			let block_size = size_bytes[0] as u32 | (size_bytes[1] as u32) << 8
+				| (size_bytes[2] as u32) << 16 | (size_bytes[3] as u32) << 24;
+			if block_size > MAX_FRAMED_BLOCK_SIZE {
+				warn!("Skipping corrupted frame with block size {}", block_size);
+				self.rescan(size_bytes, Vec::new());
+				continue;
+			}
+
+			let block_bytes = try!(self.read_bytes(block_size as usize));
+			if block_bytes.len() != block_size as usize {
+				warn!("Bootstrap file is truncated: expected {} bytes of block, got {}", block_size, block_bytes.len());
+				return Err(ReaderError::UnexpectedEnd);
+			}
+
+			match deserialize::<_, IndexedBlock>(&block_bytes as &[u8]) {
+				Ok(block) => return Ok(Some(Block {
+					magic: H32::from(&self.magic as &[u8]),
+					block_size: block_size,
+					block: block,
+				})),
+				Err(err) => {
+					warn!("Skipping corrupted frame with block of size {}: {:?}", block_size, err);
+					self.rescan(size_bytes, block_bytes);
+				},
+			}
+		}
+	}
+
+	/// Schedules bytes of corrupted frame for rescanning, so that any magic inside is found.
+	fn rescan(&mut self, size_bytes: Vec<u8>, block_bytes: Vec<u8>) {
+		for byte in size_bytes.into_iter().chain(block_bytes).rev() {
+			self.pending.push_front(byte);
+		}
+	}
+}
+
+/// Last 4 bytes, scanned when looking for network magic.
+#[derive(Default)]
+struct MagicWindow {
+	bytes: [u8; 4],
+	len: usize,
+}
+
+impl MagicWindow {
+	/// Pushes next byte to the window. Returns true if last 4 pushed bytes are equal to `magic`.
+	fn push(&mut self, byte: u8, magic: &[u8; 4]) -> bool {
+		self.bytes = [self.bytes[1], self.bytes[2], self.bytes[3], byte];
+		self.len = cmp::min(self.len + 1, 4);
+		self.len == 4 && self.bytes == *magic
+	}
+}
+
+impl<R> Iterator for BootstrapFile<R> where R: io::BufRead {
+	type Item = Result<Block, ReaderError>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.failed {
+			return None;
+		}
+
+		match self.read_framed_block() {
+			Ok(Some(block)) => Some(Ok(block)),
+			Ok(None) => None,
+			Err(err) => {
+				self.failed = true;
+				Some(Err(err))
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use chain::IndexedBlock;
+	use ser::{serialize, Error as ReaderError};
+	use super::{BootstrapFile, read_blocks};
+
+	const MAGIC: u32 = 0x6427e924;
+
+	fn framed(block: &IndexedBlock) -> Vec<u8> {
+		let block_bytes = serialize(&block.clone().to_raw_block());
+		let size = block_bytes.len() as u32;
+		let mut frame = vec![0x24, 0xe9, 0x27, 0x64, size as u8, (size >> 8) as u8, (size >> 16) as u8, (size >> 24) as u8];
+		frame.extend_from_slice(&block_bytes);
+		frame
+	}
+
+	#[test]
+	fn bootstrap_file_reads_framed_blocks() {
+		let b0: IndexedBlock = test_data::block_h0().into();
+		let b1: IndexedBlock = test_data::block_h1().into();
+		let b2: IndexedBlock = test_data::block_h2().into();
+
+		// blocks are stored out of order, with zero-filled gap between frames
+		let mut file = framed(&b0);
+		file.extend_from_slice(&framed(&b2));
+		file.extend_from_slice(&[0u8; 16]);
+		file.extend_from_slice(&framed(&b1));
+
+		let blocks = BootstrapFile::new(&file as &[u8], MAGIC)
+			.map(|block| block.unwrap().block.hash().clone())
+			.collect::<Vec<_>>();
+		assert_eq!(blocks, vec![b0.hash().clone(), b2.hash().clone(), b1.hash().clone()]);
+	}
+
+	#[test]
+	fn bootstrap_file_skips_corrupted_frames() {
+		let b0: IndexedBlock = test_data::block_h0().into();
+		let b1: IndexedBlock = test_data::block_h1().into();
+
+		let mut file = framed(&b0);
+		// frame with truncated block, directly followed by next frame
+		let corrupted = framed(&b1);
+		file.extend_from_slice(&corrupted[..corrupted.len() / 2]);
+		file.extend_from_slice(&framed(&b1));
+		// frame with invalid length
+		file.extend_from_slice(&[0x24, 0xe9, 0x27, 0x64, 0xff, 0xff, 0xff, 0xff]);
+		// trailing garbage
+		file.extend_from_slice(&[1, 2, 3]);
+
+		let blocks = BootstrapFile::new(&file as &[u8], MAGIC)
+			.map(|block| block.unwrap().block.hash().clone())
+			.collect::<Vec<_>>();
+		assert_eq!(blocks, vec![b0.hash().clone(), b1.hash().clone()]);
+	}
+
+	#[test]
+	fn read_blocks_reports_truncated_last_frame() {
+		let b0: IndexedBlock = test_data::block_h0().into();
+		let b1: IndexedBlock = test_data::block_h1().into();
+
+		let mut file = framed(&b0);
+		let truncated = framed(&b1);
+		file.extend_from_slice(&truncated[..truncated.len() - 1]);
+
+		let mut blocks = read_blocks(&file as &[u8], MAGIC);
+		assert_eq!(blocks.next().map(|block| block.unwrap().block.hash().clone()), Some(b0.hash().clone()));
+		assert_eq!(blocks.next().map(|block| block.map(|_| ())), Some(Err(ReaderError::UnexpectedEnd)));
+		assert!(blocks.next().is_none());
+
+		// frame is truncated right after the magic
+		let mut file = framed(&b0);
+		file.extend_from_slice(&[0x24, 0xe9, 0x27, 0x64, 0x01]);
+
+		let mut blocks = read_blocks(&file as &[u8], MAGIC);
+		assert!(blocks.next().unwrap().is_ok());
+		assert_eq!(blocks.next().map(|block| block.map(|_| ())), Some(Err(ReaderError::UnexpectedEnd)));
+		assert!(blocks.next().is_none());
+	}
+}
@@ -8,8 +8,10 @@ extern crate chain;
 
 mod blk;
 mod block;
+mod bootstrap;
 mod fs;
 
 pub use primitives::{hash, bytes};
 
 pub use blk::{open_blk_dir, BlkDir};
+pub use bootstrap::{open_bootstrap_file, read_blocks, BootstrapFile};
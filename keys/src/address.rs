@@ -38,6 +38,18 @@ pub struct Address {
 	pub hash: AddressHash,
 }
 
+impl Address {
+	/// Returns the type of the address.
+	pub fn kind(&self) -> Type {
+		self.kind
+	}
+
+	/// Returns the network of the address.
+	pub fn network(&self) -> Network {
+		self.network
+	}
+}
+
 pub struct AddressDisplayLayout([u8; 26]);
 
 impl Deref for AddressDisplayLayout {
@@ -145,4 +157,22 @@ mod tests {
 
 		assert_eq!(address, "t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe".into());
 	}
+
+	#[test]
+	fn test_address_roundtrip() {
+		let addresses = vec![
+			("t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe", Network::Mainnet, Type::P2PKH, "ff197b14e502ab41f3bc8ccb48c4abac9eab35bc"),
+			("t3Vz22vK5z2LcKEdg16Yv4FFneEL1zg9ojd", Network::Mainnet, Type::P2SH, "7d46a730d31f97b1930d3368a967c309bd4d136a"),
+			("tmYyCAXNkS4ranGYZuj1SJge5dNcMN6xDC7", Network::Testnet, Type::P2PKH, "ff197b14e502ab41f3bc8ccb48c4abac9eab35bc"),
+			("t2UNzUUx8mWBCRYPRezvA363EYXyEpHokyi", Network::Testnet, Type::P2SH, "ef775f1f997f122a062fff1a2d7443abd1f9c642"),
+		];
+
+		for (encoded, network, kind, hash) in addresses {
+			let address: Address = encoded.into();
+			assert_eq!(address.network(), network);
+			assert_eq!(address.kind(), kind);
+			assert_eq!(address.hash, hash.into());
+			assert_eq!(address.to_string(), encoded);
+		}
+	}
 }
@@ -0,0 +1,92 @@
+//! Base64 encoding (RFC 4648), used by signed messages.
+
+use Error;
+
+const CHARSET: &'static [u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const PADDING: u8 = b'=';
+
+fn decode_char(c: u8) -> Option<u32> {
+	CHARSET.iter().position(|x| *x == c).map(|position| position as u32)
+}
+
+/// Encode data using the standard alphabet, with padding.
+pub fn encode(data: &[u8]) -> String {
+	let mut result = Vec::with_capacity((data.len() + 2) / 3 * 4);
+	for chunk in data.chunks(3) {
+		let b0 = chunk[0] as u32;
+		let b1 = chunk.get(1).cloned().unwrap_or(0) as u32;
+		let b2 = chunk.get(2).cloned().unwrap_or(0) as u32;
+		let group = (b0 << 16) | (b1 << 8) | b2;
+
+		result.push(CHARSET[(group >> 18) as usize & 0x3f]);
+		result.push(CHARSET[(group >> 12) as usize & 0x3f]);
+		result.push(if chunk.len() > 1 { CHARSET[(group >> 6) as usize & 0x3f] } else { PADDING });
+		result.push(if chunk.len() > 2 { CHARSET[group as usize & 0x3f] } else { PADDING });
+	}
+
+	String::from_utf8(result).expect("base64 charset is ASCII; qed")
+}
+
+/// Decode padded data, encoded using the standard alphabet.
+pub fn decode(s: &str) -> Result<Vec<u8>, Error> {
+	let s = s.as_bytes();
+	if s.len() % 4 != 0 {
+		return Err(Error::InvalidSignature);
+	}
+
+	let mut result = Vec::with_capacity(s.len() / 4 * 3);
+	for (index, chunk) in s.chunks(4).enumerate() {
+		let is_last = index == s.len() / 4 - 1;
+		let padding = chunk.iter().rev().take_while(|c| **c == PADDING).count();
+		if padding > 2 || (padding != 0 && !is_last) {
+			return Err(Error::InvalidSignature);
+		}
+
+		let mut group = 0u32;
+		for c in &chunk[..4 - padding] {
+			group = (group << 6) | try!(decode_char(*c).ok_or(Error::InvalidSignature));
+		}
+		group <<= 6 * padding as u32;
+
+		result.push((group >> 16) as u8);
+		if padding < 2 {
+			result.push((group >> 8) as u8);
+		}
+		if padding < 1 {
+			result.push(group as u8);
+		}
+	}
+
+	Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{encode, decode};
+
+	#[test]
+	fn test_base64_roundtrip() {
+		let vectors: &[(&[u8], &str)] = &[
+			(b"", ""),
+			(b"f", "Zg=="),
+			(b"fo", "Zm8="),
+			(b"foo", "Zm9v"),
+			(b"foob", "Zm9vYg=="),
+			(b"fooba", "Zm9vYmE="),
+			(b"foobar", "Zm9vYmFy"),
+		];
+
+		for &(data, encoded) in vectors {
+			assert_eq!(encode(data), encoded);
+			assert_eq!(decode(encoded).unwrap(), data.to_vec());
+		}
+	}
+
+	#[test]
+	fn test_base64_errors() {
+		assert!(decode("Zm9").is_err());
+		assert!(decode("Zm9!").is_err());
+		assert!(decode("Z===").is_err());
+		assert!(decode("Zg==Zm9v").is_err());
+	}
+}
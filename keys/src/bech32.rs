@@ -0,0 +1,129 @@
+//! Bech32 encoding (BIP 173), used by Sapling addresses.
+
+use Error;
+
+const CHARSET: &'static [u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+const CHECKSUM_LEN: usize = 6;
+
+fn polymod(values: &[u8]) -> u32 {
+	const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+	let mut checksum = 1u32;
+	for value in values {
+		let top = checksum >> 25;
+		checksum = (checksum & 0x1ffffff) << 5 ^ *value as u32;
+		for (i, generator) in GENERATOR.iter().enumerate() {
+			if (top >> i) & 1 == 1 {
+				checksum ^= *generator;
+			}
+		}
+	}
+	checksum
+}
+
+fn expand_hrp(hrp: &str) -> Vec<u8> {
+	let mut result = Vec::with_capacity(hrp.len() * 2 + 1);
+	result.extend(hrp.bytes().map(|b| b >> 5));
+	result.push(0);
+	result.extend(hrp.bytes().map(|b| b & 0x1f));
+	result
+}
+
+/// Regroups bits of `data` from `from`-bit groups into `to`-bit groups.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, Error> {
+	let mut acc = 0u32;
+	let mut bits = 0u32;
+	let max = (1u32 << to) - 1;
+	let mut result = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+	for value in data {
+		acc = (acc << from) | *value as u32;
+		bits += from;
+		while bits >= to {
+			bits -= to;
+			result.push(((acc >> bits) & max) as u8);
+		}
+	}
+
+	if pad {
+		if bits > 0 {
+			result.push(((acc << (to - bits)) & max) as u8);
+		}
+	} else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+		return Err(Error::InvalidAddress);
+	}
+
+	Ok(result)
+}
+
+/// Encodes `data` with given human-readable part.
+pub fn encode(hrp: &str, data: &[u8]) -> String {
+	let mut values = convert_bits(data, 8, 5, true).expect("padding is allowed; qed");
+	let mut checksum_input = expand_hrp(hrp);
+	checksum_input.extend_from_slice(&values);
+	checksum_input.extend_from_slice(&[0u8; CHECKSUM_LEN]);
+	let checksum = polymod(&checksum_input) ^ 1;
+	values.extend((0..CHECKSUM_LEN).map(|i| ((checksum >> (5 * (5 - i))) & 0x1f) as u8));
+
+	let mut result = String::with_capacity(hrp.len() + 1 + values.len());
+	result.push_str(hrp);
+	result.push('1');
+	result.extend(values.into_iter().map(|value| CHARSET[value as usize] as char));
+	result
+}
+
+/// Decodes Bech32 string into human-readable part and data.
+pub fn decode(s: &str) -> Result<(String, Vec<u8>), Error> {
+	// mixed case strings are invalid
+	if s.to_lowercase() != s && s.to_uppercase() != s {
+		return Err(Error::InvalidAddress);
+	}
+
+	let s = s.to_lowercase();
+	let separator = s.rfind('1').ok_or(Error::InvalidAddress)?;
+	let (hrp, data) = (&s[..separator], &s[separator + 1..]);
+	if hrp.is_empty() || data.len() < CHECKSUM_LEN || hrp.bytes().any(|b| b < 33 || b > 126) {
+		return Err(Error::InvalidAddress);
+	}
+
+	let values = data.bytes()
+		.map(|b| CHARSET.iter().position(|c| *c == b).map(|position| position as u8))
+		.collect::<Option<Vec<u8>>>()
+		.ok_or(Error::InvalidAddress)?;
+
+	let mut checksum_input = expand_hrp(hrp);
+	checksum_input.extend_from_slice(&values);
+	if polymod(&checksum_input) != 1 {
+		return Err(Error::InvalidChecksum);
+	}
+
+	let data = convert_bits(&values[..values.len() - CHECKSUM_LEN], 5, 8, false)?;
+	Ok((hrp.to_owned(), data))
+}
+
+#[cfg(test)]
+mod tests {
+	use Error;
+	use super::{encode, decode};
+
+	#[test]
+	fn test_bech32_decode_bip173_vectors() {
+		assert_eq!(decode("A12UEL5L"), Ok(("a".to_owned(), vec![])));
+		assert_eq!(decode("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw").map(|(hrp, _)| hrp), Ok("abcdef".to_owned()));
+		assert_eq!(decode("split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w").map(|(hrp, _)| hrp), Ok("split".to_owned()));
+		// invalid checksum
+		assert_eq!(decode("A12UEL5A"), Err(Error::InvalidChecksum));
+		// mixed case
+		assert_eq!(decode("A12uEL5L"), Err(Error::InvalidAddress));
+		// empty hrp
+		assert_eq!(decode("1pzry9x0s0muk"), Err(Error::InvalidAddress));
+		// invalid character in data part
+		assert_eq!(decode("x1b4n0q5v"), Err(Error::InvalidAddress));
+	}
+
+	#[test]
+	fn test_bech32_roundtrip() {
+		let data = vec![0, 1, 2, 3, 0xfe, 0xff];
+		let encoded = encode("test", &data);
+		assert_eq!(decode(&encoded), Ok(("test".to_owned(), data)));
+	}
+}
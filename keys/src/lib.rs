@@ -11,23 +11,31 @@ extern crate primitives;
 
 pub mod generator;
 mod address;
+mod base64;
+mod bech32;
 mod display;
 mod keypair;
+mod message;
 mod error;
 mod network;
 mod private;
 mod public;
+mod sapling_address;
 mod signature;
+mod sprout_address;
 
 pub use primitives::{hash, bytes};
 
 pub use address::{Type, Address};
 pub use display::DisplayLayout;
 pub use keypair::KeyPair;
+pub use message::{MESSAGE_MAGIC, message_hash, sign_message, verify_message};
 pub use error::Error;
 pub use private::Private;
 pub use public::Public;
+pub use sapling_address::{SaplingAddress, SAPLING_DIVERSIFIER_SIZE, SAPLING_PK_D_SIZE};
 pub use signature::{Signature, CompactSignature};
+pub use sprout_address::{SproutAddress, SPROUT_PAYING_KEY_SIZE, SPROUT_TRANSMISSION_KEY_SIZE};
 pub use network::Network;
 
 use hash::{H160, H256};
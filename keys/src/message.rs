@@ -0,0 +1,78 @@
+//! Signed messages, compatible with zcashd `signmessage` and `verifymessage`.
+
+use crypto::dhash256;
+use address::Type;
+use {Address, CompactSignature, Error, Message, Private, Public};
+
+/// Prefix of every signed message. Differs from Bitcoin's "Bitcoin Signed Message:\n",
+/// so signatures made with Bitcoin magic are never valid here.
+pub const MESSAGE_MAGIC: &'static str = "Zcash Signed Message:\n";
+
+fn write_compact_size(data: &mut Vec<u8>, size: usize) {
+	match size {
+		0...0xfc => data.push(size as u8),
+		0xfd...0xffff => {
+			data.push(0xfd);
+			data.extend_from_slice(&[size as u8, (size >> 8) as u8]);
+		},
+		_ => {
+			data.push(0xfe);
+			data.extend_from_slice(&[size as u8, (size >> 8) as u8, (size >> 16) as u8, (size >> 24) as u8]);
+		},
+	}
+}
+
+/// Hash of the message, which is actually signed.
+pub fn message_hash(message: &str) -> Message {
+	let mut data = Vec::with_capacity(MESSAGE_MAGIC.len() + message.len() + 10);
+	write_compact_size(&mut data, MESSAGE_MAGIC.len());
+	data.extend_from_slice(MESSAGE_MAGIC.as_bytes());
+	write_compact_size(&mut data, message.len());
+	data.extend_from_slice(message.as_bytes());
+	dhash256(&data)
+}
+
+/// Sign the message with given private key.
+pub fn sign_message(private: &Private, message: &str) -> Result<CompactSignature, Error> {
+	private.sign_compact(&message_hash(message))
+}
+
+/// Check that the message has been signed by the owner of given P2PKH address.
+pub fn verify_message(address: &Address, signature: &CompactSignature, message: &str) -> bool {
+	if address.kind != Type::P2PKH || signature[0] < 27 || signature[0] > 34 {
+		return false;
+	}
+
+	match Public::recover_compact(&message_hash(message), signature) {
+		Ok(public) => public.address_hash() == address.hash,
+		Err(_) => false,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use {Address, CompactSignature, Private};
+	use super::{sign_message, verify_message};
+
+	const PRIVATE: &'static str = "Kwr371tjA9u2rFSMZjTNun2PXXP3WPZu2afRHTcta6KxEUdm1vEw";
+	const ADDRESS: &'static str = "t1ffus9J1vhxvFqLoExGBRPjE7BcJxiSCTC";
+	const MESSAGE: &'static str = "Zcash message signing test";
+	// same as zcashd signmessage output: RFC 6979 nonces make signatures deterministic
+	const SIGNATURE: &'static str = "H2OecXC6ecEikddPOYHlO9TROa8qZzD/prkHleOkxED3au+Uhhjh7RWToCWOMjjyWMa3EV83o6nJz1q74xXKkcY=";
+
+	#[test]
+	fn test_sign_message() {
+		let private: Private = PRIVATE.into();
+		let signature = sign_message(&private, MESSAGE).unwrap();
+		assert_eq!(signature.to_base64(), SIGNATURE);
+	}
+
+	#[test]
+	fn test_verify_message() {
+		let address: Address = ADDRESS.into();
+		let signature = CompactSignature::from_base64(SIGNATURE).unwrap();
+		assert!(verify_message(&address, &signature, MESSAGE));
+		assert!(!verify_message(&address, &signature, "Another message"));
+		assert!(!verify_message(&"t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe".into(), &signature, MESSAGE));
+	}
+}
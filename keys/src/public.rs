@@ -2,7 +2,7 @@ use std::{fmt, ops};
 use secp256k1::key;
 use secp256k1::{Message as SecpMessage, RecoveryId, RecoverableSignature, Error as SecpError, Signature as SecpSignature};
 use hex::ToHex;
-use crypto::dhash160;
+use crypto::hash160;
 use hash::{H264, H520};
 use {AddressHash, Error, CompactSignature, Signature, Message, SECP256K1};
 
@@ -32,7 +32,7 @@ impl Public {
 	}
 
 	pub fn address_hash(&self) -> AddressHash {
-		dhash160(self)
+		hash160(self)
 	}
 
 	pub fn verify(&self, message: &Message, signature: &Signature) -> Result<bool, Error> {
@@ -103,3 +103,42 @@ impl fmt::Display for Public {
 		self.to_hex::<String>().fmt(f)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use hex::FromHex;
+	use network::Network;
+	use {Address, Type};
+	use super::Public;
+
+	fn check_address_hash(public: &str, hash: &str, address: &str) {
+		let public = Public::from_slice(&public.from_hex::<Vec<u8>>().unwrap()).unwrap();
+		let address_hash = public.address_hash();
+		assert_eq!(address_hash, hash.into());
+
+		let derived = Address {
+			kind: Type::P2PKH,
+			network: Network::Mainnet,
+			hash: address_hash,
+		};
+		assert_eq!(derived.to_string(), address);
+	}
+
+	#[test]
+	fn test_compressed_public_address_hash() {
+		check_address_hash(
+			"0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+			"751e76e8199196d454941c45d1b3a323f1433bd6",
+			"t1UYsZVJkLPeMjxEtACvSxfWuNmddpWfxzs",
+		);
+	}
+
+	#[test]
+	fn test_normal_public_address_hash() {
+		check_address_hash(
+			"0479be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798483ada7726a3c4655da4fbfc0e1108a8fd17b448a68554199c47d08ffb10d4b8",
+			"91b24bf9f5288532960ac687abb035127b1d28a5",
+			"t1X9yaRpCHJpWX1HrGUxEu39xyQinmo3Ana",
+		);
+	}
+}
@@ -0,0 +1,130 @@
+use std::fmt;
+use std::str::FromStr;
+use network::Network;
+use {bech32, Error};
+
+/// Size of the diversifier of Sapling payment address.
+pub const SAPLING_DIVERSIFIER_SIZE: usize = 11;
+/// Size of the diversified transmission key of Sapling payment address.
+pub const SAPLING_PK_D_SIZE: usize = 32;
+
+/// Sapling shielded payment address (`zs1...` on mainnet, `ztestsapling1...` on testnet).
+///
+/// Only the encoding is checked here: pk_d isn't checked to be a valid Jubjub point.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SaplingAddress {
+	/// The network of the address.
+	pub network: Network,
+	/// Diversifier.
+	pub diversifier: [u8; SAPLING_DIVERSIFIER_SIZE],
+	/// Diversified transmission key.
+	pub pk_d: [u8; SAPLING_PK_D_SIZE],
+}
+
+impl SaplingAddress {
+	/// Returns human-readable part of Bech32-encoded address for given network.
+	pub fn hrp(network: Network) -> &'static str {
+		match network {
+			Network::Mainnet => "zs",
+			Network::Testnet => "ztestsapling",
+		}
+	}
+}
+
+impl fmt::Display for SaplingAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut payload = Vec::with_capacity(SAPLING_DIVERSIFIER_SIZE + SAPLING_PK_D_SIZE);
+		payload.extend_from_slice(&self.diversifier);
+		payload.extend_from_slice(&self.pk_d);
+		bech32::encode(SaplingAddress::hrp(self.network), &payload).fmt(f)
+	}
+}
+
+impl FromStr for SaplingAddress {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> where Self: Sized {
+		let (hrp, payload) = bech32::decode(s)?;
+		let network = match hrp.as_str() {
+			"zs" => Network::Mainnet,
+			"ztestsapling" => Network::Testnet,
+			_ => return Err(Error::InvalidNetwork),
+		};
+
+		if payload.len() != SAPLING_DIVERSIFIER_SIZE + SAPLING_PK_D_SIZE {
+			return Err(Error::InvalidAddress);
+		}
+
+		let mut diversifier = [0u8; SAPLING_DIVERSIFIER_SIZE];
+		diversifier.copy_from_slice(&payload[..SAPLING_DIVERSIFIER_SIZE]);
+		let mut pk_d = [0u8; SAPLING_PK_D_SIZE];
+		pk_d.copy_from_slice(&payload[SAPLING_DIVERSIFIER_SIZE..]);
+
+		Ok(SaplingAddress {
+			network: network,
+			diversifier: diversifier,
+			pk_d: pk_d,
+		})
+	}
+}
+
+impl From<&'static str> for SaplingAddress {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use hex::FromHex;
+	use network::Network;
+	use {bech32, Error};
+	use super::SaplingAddress;
+
+	fn address(network: Network, diversifier: &str, pk_d: &str) -> SaplingAddress {
+		let mut address = SaplingAddress {
+			network: network,
+			diversifier: Default::default(),
+			pk_d: Default::default(),
+		};
+		address.diversifier.copy_from_slice(&diversifier.from_hex::<Vec<u8>>().unwrap());
+		address.pk_d.copy_from_slice(&pk_d.from_hex::<Vec<u8>>().unwrap());
+		address
+	}
+
+	#[test]
+	fn test_sapling_address_mainnet() {
+		let encoded = "zs1z7rejlpsa98s2rrrfkwmaxu53e4ue0ulcrw0h4x5g8jl04tak0d3mm47vdtahatqrlkngh9slya";
+		let expected = address(Network::Mainnet, "1787997c30e94f050c634d",
+			"9dbe9b948e6bccbf9fc0dcfbd4d441e5f7d57db3db1deebe6357dbf5601fed34");
+
+		assert_eq!(encoded.parse::<SaplingAddress>(), Ok(expected.clone()));
+		assert_eq!(expected.to_string(), encoded);
+	}
+
+	#[test]
+	fn test_sapling_address_testnet() {
+		let encoded = "ztestsapling1wn3tw9w5rs55x5yl586gtk72e8hcfdq8zsnjzcu8p7ghm8lrx54axc74mvm335q7lmy3g0sqje6";
+		let expected = address(Network::Testnet, "74e2b715d41c2943509fa1",
+			"f485dbcac9ef84b40714272163870f917d9fe3352bd363d5db3718d01efec914");
+
+		assert_eq!(encoded.parse::<SaplingAddress>(), Ok(expected.clone()));
+		assert_eq!(expected.to_string(), encoded);
+	}
+
+	#[test]
+	fn test_sapling_address_errors() {
+		let payload = "1787997c30e94f050c634d9dbe9b948e6bccbf9fc0dcfbd4d441e5f7d57db3db1deebe6357dbf5601fed34"
+			.from_hex::<Vec<u8>>().unwrap();
+
+		// valid encoding with wrong human-readable part
+		assert_eq!(bech32::encode("zc", &payload).parse::<SaplingAddress>(), Err(Error::InvalidNetwork));
+		// invalid payload length
+		assert_eq!(bech32::encode("zs", &payload[1..]).parse::<SaplingAddress>(), Err(Error::InvalidAddress));
+		// invalid checksum
+		assert_eq!("zs1z7rejlpsa98s2rrrfkwmaxu53e4ue0ulcrw0h4x5g8jl04tak0d3mm47vdtahatqrlkngh9slyq".parse::<SaplingAddress>(),
+			Err(Error::InvalidChecksum));
+		// transparent address
+		assert!("t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe".parse::<SaplingAddress>().is_err());
+	}
+}
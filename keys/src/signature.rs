@@ -5,7 +5,7 @@
 use std::{fmt, ops, str};
 use hex::{ToHex, FromHex};
 use hash::H520;
-use Error;
+use {base64, Error};
 
 #[derive(PartialEq)]
 pub struct Signature(Vec<u8>);
@@ -109,6 +109,23 @@ impl From<&'static str> for CompactSignature {
 	}
 }
 
+impl CompactSignature {
+	pub fn to_base64(&self) -> String {
+		base64::encode(&*self.0)
+	}
+
+	pub fn from_base64(s: &str) -> Result<Self, Error> {
+		let data = try!(base64::decode(s));
+		if data.len() != 65 {
+			return Err(Error::InvalidSignature);
+		}
+
+		let mut signature = H520::default();
+		signature.copy_from_slice(&data);
+		Ok(CompactSignature(signature))
+	}
+}
+
 impl From<H520> for CompactSignature {
 	fn from(h: H520) -> Self {
 		CompactSignature(h)
@@ -0,0 +1,128 @@
+use std::fmt;
+use std::str::FromStr;
+use base58::{ToBase58, FromBase58};
+use crypto::checksum;
+use network::Network;
+use Error;
+
+/// Size of the paying key of Sprout payment address.
+pub const SPROUT_PAYING_KEY_SIZE: usize = 32;
+/// Size of the transmission key of Sprout payment address.
+pub const SPROUT_TRANSMISSION_KEY_SIZE: usize = 32;
+
+const PREFIX_SIZE: usize = 2;
+const PAYLOAD_SIZE: usize = PREFIX_SIZE + SPROUT_PAYING_KEY_SIZE + SPROUT_TRANSMISSION_KEY_SIZE;
+
+/// Sprout shielded payment address (`zc...` on mainnet, `zt...` on testnet).
+///
+/// Only the encoding is checked here: transmission key isn't checked to be a valid Curve25519 key.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SproutAddress {
+	/// The network of the address.
+	pub network: Network,
+	/// Paying key (a_pk).
+	pub paying_key: [u8; SPROUT_PAYING_KEY_SIZE],
+	/// Transmission key (pk_enc).
+	pub transmission_key: [u8; SPROUT_TRANSMISSION_KEY_SIZE],
+}
+
+impl SproutAddress {
+	/// Returns Base58Check prefix of address for given network.
+	pub fn prefix(network: Network) -> [u8; PREFIX_SIZE] {
+		match network {
+			Network::Mainnet => [0x16, 0x9A],
+			Network::Testnet => [0x16, 0xB6],
+		}
+	}
+}
+
+impl fmt::Display for SproutAddress {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut data = Vec::with_capacity(PAYLOAD_SIZE + 4);
+		data.extend_from_slice(&SproutAddress::prefix(self.network));
+		data.extend_from_slice(&self.paying_key);
+		data.extend_from_slice(&self.transmission_key);
+		let cs = checksum(&data);
+		data.extend_from_slice(&*cs);
+		data.to_base58().fmt(f)
+	}
+}
+
+impl FromStr for SproutAddress {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Error> where Self: Sized {
+		let data = s.from_base58().map_err(|_| Error::InvalidAddress)?;
+		if data.len() != PAYLOAD_SIZE + 4 {
+			return Err(Error::InvalidAddress);
+		}
+
+		let cs = checksum(&data[..PAYLOAD_SIZE]);
+		if &data[PAYLOAD_SIZE..] != &*cs {
+			return Err(Error::InvalidChecksum);
+		}
+
+		let network = match (data[0], data[1]) {
+			(0x16, 0x9A) => Network::Mainnet,
+			(0x16, 0xB6) => Network::Testnet,
+			_ => return Err(Error::InvalidNetwork),
+		};
+
+		let mut paying_key = [0u8; SPROUT_PAYING_KEY_SIZE];
+		paying_key.copy_from_slice(&data[PREFIX_SIZE..PREFIX_SIZE + SPROUT_PAYING_KEY_SIZE]);
+		let mut transmission_key = [0u8; SPROUT_TRANSMISSION_KEY_SIZE];
+		transmission_key.copy_from_slice(&data[PREFIX_SIZE + SPROUT_PAYING_KEY_SIZE..PAYLOAD_SIZE]);
+
+		Ok(SproutAddress {
+			network: network,
+			paying_key: paying_key,
+			transmission_key: transmission_key,
+		})
+	}
+}
+
+impl From<&'static str> for SproutAddress {
+	fn from(s: &'static str) -> Self {
+		s.parse().unwrap()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use base58::{ToBase58, FromBase58};
+	use network::Network;
+	use Error;
+	use super::SproutAddress;
+
+	fn address(network: Network) -> SproutAddress {
+		SproutAddress {
+			network: network,
+			paying_key: [0x11; 32],
+			transmission_key: [0x22; 32],
+		}
+	}
+
+	#[test]
+	fn test_sprout_address_roundtrip() {
+		for &(network, prefix) in &[(Network::Mainnet, "zc"), (Network::Testnet, "zt")] {
+			let encoded = address(network).to_string();
+			assert!(encoded.starts_with(prefix));
+			assert_eq!(encoded.parse::<SproutAddress>(), Ok(address(network)));
+		}
+	}
+
+	#[test]
+	fn test_sprout_address_errors() {
+		let mut data = address(Network::Mainnet).to_string().from_base58().unwrap();
+
+		// invalid length
+		assert_eq!(data[1..].to_base58().parse::<SproutAddress>(), Err(Error::InvalidAddress));
+		// invalid checksum
+		let last = data.len() - 1;
+		data[last] ^= 1;
+		assert_eq!(data.to_base58().parse::<SproutAddress>(), Err(Error::InvalidChecksum));
+		// transparent and sapling addresses
+		assert!("t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe".parse::<SproutAddress>().is_err());
+		assert!("zs1z7rejlpsa98s2rrrfkwmaxu53e4ue0ulcrw0h4x5g8jl04tak0d3mm47vdtahatqrlkngh9slya".parse::<SproutAddress>().is_err());
+	}
+}
@@ -1,10 +1,141 @@
+use std::io;
+use ser::{
+	Serializable, Stream, CompactInteger,
+	Deserializable, Reader, Error as ReaderError,
+};
 use chain::{BlockHeader, ShortTransactionID};
+use network::MAX_BLOCK_TRANSACTIONS;
 use common::PrefilledTransaction;
 
-#[derive(Debug, PartialEq, Serializable, Deserializable)]
+/// Max index of the transaction in the compact block (BIP152).
+pub const COMPACT_BLOCK_MAX_TRANSACTION_INDEX: usize = 0xffff;
+
+#[derive(Debug, PartialEq)]
 pub struct BlockHeaderAndIDs {
 	pub header: BlockHeader,
 	pub nonce: u64,
 	pub short_ids: Vec<ShortTransactionID>,
 	pub prefilled_transactions: Vec<PrefilledTransaction>,
 }
+
+impl Serializable for BlockHeaderAndIDs {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.header)
+			.append(&self.nonce)
+			.append_list(&self.short_ids)
+			.append(&CompactInteger::from(self.prefilled_transactions.len()));
+
+		// indexes of prefilled transactions are differentially encoded
+		let mut next_index = 0;
+		for prefilled_transaction in &self.prefilled_transactions {
+			stream
+				.append(&CompactInteger::from(prefilled_transaction.index - next_index))
+				.append(&prefilled_transaction.transaction);
+			next_index = prefilled_transaction.index + 1;
+		}
+	}
+}
+
+impl Deserializable for BlockHeaderAndIDs {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let header = try!(reader.read());
+		let nonce = try!(reader.read());
+		let short_ids = try!(reader.read_list_max(MAX_BLOCK_TRANSACTIONS));
+
+		let prefilled_transactions_len: CompactInteger = try!(reader.read());
+		let prefilled_transactions_len: usize = prefilled_transactions_len.into();
+		if prefilled_transactions_len > MAX_BLOCK_TRANSACTIONS {
+			return Err(ReaderError::MalformedData);
+		}
+
+		let mut prefilled_transactions = Vec::new();
+		let mut next_index = 0;
+		for _ in 0..prefilled_transactions_len {
+			let index_diff: CompactInteger = try!(reader.read());
+			let index = match next_index.checked_add(usize::from(index_diff)) {
+				Some(index) if index <= COMPACT_BLOCK_MAX_TRANSACTION_INDEX => index,
+				_ => return Err(ReaderError::MalformedData),
+			};
+
+			prefilled_transactions.push(PrefilledTransaction {
+				index: index,
+				transaction: try!(reader.read()),
+			});
+			next_index = index + 1;
+		}
+
+		Ok(BlockHeaderAndIDs {
+			header: header,
+			nonce: nonce,
+			short_ids: short_ids,
+			prefilled_transactions: prefilled_transactions,
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ser::{serialize, deserialize, CompactInteger};
+	use chain::{BlockHeader, Transaction};
+	use common::PrefilledTransaction;
+	use super::BlockHeaderAndIDs;
+
+	fn test_header() -> BlockHeader {
+		BlockHeader {
+			version: 4,
+			previous_header_hash: Default::default(),
+			merkle_root_hash: Default::default(),
+			final_sapling_root: Default::default(),
+			time: 0,
+			bits: 0.into(),
+			nonce: Default::default(),
+			solution: Default::default(),
+		}
+	}
+
+	#[test]
+	fn block_header_and_ids_differentially_encodes_indexes() {
+		let transaction: Transaction = "01000000000000000000".into();
+		let compact = BlockHeaderAndIDs {
+			header: test_header(),
+			nonce: 7,
+			short_ids: vec![1u8.into()],
+			prefilled_transactions: vec![
+				PrefilledTransaction { index: 0, transaction: transaction.clone() },
+				PrefilledTransaction { index: 2, transaction: transaction.clone() },
+			],
+		};
+
+		let serialized = serialize(&compact);
+		// prefilled transactions: count, index 0, transaction, index (2 - 0 - 1), transaction
+		let mut expected_tail = vec![2u8, 0];
+		expected_tail.extend_from_slice(&serialize(&transaction));
+		expected_tail.push(1);
+		expected_tail.extend_from_slice(&serialize(&transaction));
+		assert!(serialized.ends_with(&expected_tail));
+		assert_eq!(deserialize::<_, BlockHeaderAndIDs>(&serialized as &[u8]).unwrap(), compact);
+	}
+
+	#[test]
+	fn block_header_and_ids_rejects_index_diff_overflow() {
+		let transaction: Transaction = "01000000000000000000".into();
+		let compact = BlockHeaderAndIDs {
+			header: test_header(),
+			nonce: 7,
+			short_ids: vec![],
+			prefilled_transactions: vec![
+				PrefilledTransaction { index: 0, transaction: transaction.clone() },
+				PrefilledTransaction { index: 1, transaction: transaction.clone() },
+			],
+		};
+
+		// replace index diff of the second prefilled transaction with u64::MAX
+		let serialized = serialize(&compact);
+		let tail_len = 1 + serialize(&transaction).len();
+		let mut malformed = serialized[..serialized.len() - tail_len].to_vec();
+		malformed.extend_from_slice(&serialize(&CompactInteger::from(u64::max_value())));
+		malformed.extend_from_slice(&serialize(&transaction));
+		assert!(deserialize::<_, BlockHeaderAndIDs>(&malformed as &[u8]).is_err());
+	}
+}
@@ -4,6 +4,7 @@ use ser::{
 	Serializable, Stream, CompactInteger,
 	Deserializable, Reader, Error as ReaderError,
 };
+use common::COMPACT_BLOCK_MAX_TRANSACTION_INDEX;
 
 #[derive(Debug, PartialEq)]
 pub struct BlockTransactionsRequest {
@@ -13,9 +14,15 @@ pub struct BlockTransactionsRequest {
 
 impl Serializable for BlockTransactionsRequest {
 	fn serialize(&self, stream: &mut Stream) {
+		// indexes are differentially encoded
+		let mut next_index = 0;
 		let indexes: Vec<CompactInteger> = self.indexes
 			.iter()
-			.map(|x| (*x).into())
+			.map(|index| {
+				let index_diff = *index - next_index;
+				next_index = *index + 1;
+				index_diff.into()
+			})
 			.collect();
 
 		stream
@@ -27,13 +34,64 @@ impl Serializable for BlockTransactionsRequest {
 impl Deserializable for BlockTransactionsRequest {
 	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
 		let blockhash = try!(reader.read());
-		let indexes: Vec<CompactInteger> = try!(reader.read_list());
+		let index_diffs: Vec<CompactInteger> = try!(reader.read_list());
+
+		let mut next_index = 0;
+		let mut indexes = Vec::with_capacity(index_diffs.len());
+		for index_diff in index_diffs {
+			let index = match next_index.checked_add(usize::from(index_diff)) {
+				Some(index) if index <= COMPACT_BLOCK_MAX_TRANSACTION_INDEX => index,
+				_ => return Err(ReaderError::MalformedData),
+			};
+
+			indexes.push(index);
+			next_index = index + 1;
+		}
 
 		let request = BlockTransactionsRequest {
 			blockhash: blockhash,
-			indexes: indexes.into_iter().map(Into::into).collect(),
+			indexes: indexes,
 		};
 
 		Ok(request)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use ser::{serialize, deserialize, Stream, CompactInteger};
+	use super::BlockTransactionsRequest;
+
+	#[test]
+	fn block_transactions_request_differentially_encodes_indexes() {
+		let request = BlockTransactionsRequest {
+			blockhash: 1u8.into(),
+			indexes: vec![1, 2, 5],
+		};
+
+		let serialized = serialize(&request);
+		assert_eq!(&serialized[32..], &[3u8, 1, 0, 2]);
+		assert_eq!(deserialize::<_, BlockTransactionsRequest>(&serialized as &[u8]).unwrap(), request);
+	}
+
+	#[test]
+	fn block_transactions_request_rejects_index_overflow() {
+		let mut serialized = serialize(&BlockTransactionsRequest {
+			blockhash: 1u8.into(),
+			indexes: vec![0xffff],
+		}).take();
+		// second index is 0xffff + 1
+		serialized[32] = 2;
+		serialized.push(0);
+		assert!(deserialize::<_, BlockTransactionsRequest>(&serialized as &[u8]).is_err());
+	}
+
+	#[test]
+	fn block_transactions_request_rejects_index_diff_overflow() {
+		let mut stream = Stream::default();
+		stream
+			.append(&::hash::H256::from(1u8))
+			.append_list(&vec![CompactInteger::from(1u64), CompactInteger::from(u64::max_value())]);
+		assert!(deserialize::<_, BlockTransactionsRequest>(&stream.out() as &[u8]).is_err());
+	}
+}
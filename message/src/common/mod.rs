@@ -10,7 +10,7 @@ mod prefilled_transaction;
 mod service;
 
 pub use self::address::NetAddress;
-pub use self::block_header_and_ids::BlockHeaderAndIDs;
+pub use self::block_header_and_ids::{BlockHeaderAndIDs, COMPACT_BLOCK_MAX_TRANSACTION_INDEX};
 pub use self::block_transactions::BlockTransactions;
 pub use self::block_transactions_request::BlockTransactionsRequest;
 pub use self::command::Command;
@@ -15,6 +15,8 @@ pub enum Error {
 	InvalidChecksum,
 	/// Invalid version.
 	InvalidVersion,
+	/// Payload is larger than `MAX_PAYLOAD_LEN`.
+	MessageTooLarge,
 }
 
 impl From<ReaderError> for Error {
@@ -37,6 +39,7 @@ impl error::Error for Error {
 			Error::InvalidMagic => "Invalid Network Magic",
 			Error::InvalidChecksum => "Invalid message chacksum",
 			Error::InvalidVersion => "Unsupported protocol version",
+			Error::MessageTooLarge => "Message payload is too large",
 		}
 	}
 }
@@ -16,6 +16,6 @@ mod error;
 pub use primitives::{hash, bytes};
 
 pub use common::{Command, Services};
-pub use message::{Message, MessageHeader, Payload, to_raw_message};
-pub use serialization::{serialize_payload, deserialize_payload};
+pub use message::{Message, MessageHeader, MAX_PAYLOAD_LEN, MESSAGE_HEADER_LEN, Payload, to_raw_message};
+pub use serialization::{serialize_payload, deserialize_payload, deserialize_payload_with_max_len};
 pub use error::{Error, MessageResult};
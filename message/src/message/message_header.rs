@@ -5,6 +5,12 @@ use network::Magic;
 use common::Command;
 use Error;
 
+/// Maximal length of message payload (MAX_PROTOCOL_MESSAGE_LENGTH in zcashd).
+pub const MAX_PAYLOAD_LEN: u32 = 2 * 1024 * 1024;
+
+/// Length of serialized message header.
+pub const MESSAGE_HEADER_LEN: usize = 24;
+
 #[derive(Debug, PartialEq)]
 pub struct MessageHeader {
 	pub magic: Magic,
@@ -26,7 +32,7 @@ impl MessageHeader {
 
 impl MessageHeader {
 	pub fn deserialize(data: &[u8], expected: Magic) -> Result<Self, Error> {
-		if data.len() != 24 {
+		if data.len() != MESSAGE_HEADER_LEN {
 			return Err(Error::Deserialize);
 		}
 
@@ -44,6 +50,11 @@ impl MessageHeader {
 			checksum: try!(reader.read()),
 		};
 
+		// do not even try to read huge payload
+		if header.len > MAX_PAYLOAD_LEN {
+			return Err(Error::MessageTooLarge);
+		}
+
 		Ok(header)
 	}
 }
@@ -63,6 +74,7 @@ mod tests {
 	use bytes::Bytes;
 	use ser::serialize;
 	use network::Network;
+	use Error;
 	use super::MessageHeader;
 
 	#[test]
@@ -90,4 +102,19 @@ mod tests {
 
 		assert_eq!(expected, MessageHeader::deserialize(&raw, Network::Mainnet.magic()).unwrap());
 	}
+
+	#[test]
+	fn test_message_header_deserialization_with_too_large_payload() {
+		// len = MAX_PAYLOAD_LEN
+		let raw: Bytes = "24e927646164647200000000000000000000200000000000".into();
+		assert_eq!(MessageHeader::deserialize(&raw, Network::Mainnet.magic()).unwrap().len, 0x200000);
+
+		// len = MAX_PAYLOAD_LEN + 1
+		let raw: Bytes = "24e927646164647200000000000000000100200000000000".into();
+		assert_eq!(MessageHeader::deserialize(&raw, Network::Mainnet.magic()), Err(Error::MessageTooLarge));
+
+		// len = 0xffffffff
+		let raw: Bytes = "24e92764616464720000000000000000ffffffff00000000".into();
+		assert_eq!(MessageHeader::deserialize(&raw, Network::Mainnet.magic()), Err(Error::MessageTooLarge));
+	}
 }
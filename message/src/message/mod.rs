@@ -3,5 +3,5 @@ mod message_header;
 pub mod payload;
 
 pub use self::message::{Message, to_raw_message};
-pub use self::message_header::MessageHeader;
+pub use self::message_header::{MessageHeader, MAX_PAYLOAD_LEN, MESSAGE_HEADER_LEN};
 pub use self::payload::Payload;
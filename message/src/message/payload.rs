@@ -1,10 +1,15 @@
 use std::io;
 use ser::{Reader, Stream};
 use MessageResult;
+use super::MAX_PAYLOAD_LEN;
 
 pub trait Payload: Send + 'static {
  	fn version() -> u32;
 	fn command() -> &'static str;
+	/// Maximal length of serialized payload.
+	fn max_payload_len() -> usize {
+		MAX_PAYLOAD_LEN as usize
+	}
 	fn deserialize_payload<T>(reader: &mut Reader<T>, version: u32) -> MessageResult<Self> where Self: Sized, T: io::Read;
 	fn serialize_payload(&self, stream: &mut Stream, version: u32) -> MessageResult<()>;
 }
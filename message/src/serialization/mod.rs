@@ -2,4 +2,4 @@ mod stream;
 mod reader;
 
 pub use self::stream::serialize_payload;
-pub use self::reader::deserialize_payload;
+pub use self::reader::{deserialize_payload, deserialize_payload_with_max_len};
@@ -2,6 +2,15 @@ use ser::Reader;
 use {Payload, Error};
 
 pub fn deserialize_payload<T>(buffer: &[u8], version: u32) -> Result<T, Error> where T: Payload {
+	deserialize_payload_with_max_len(buffer, version, T::max_payload_len())
+}
+
+/// Deserializes payload, which is also limited by given (e.g. consensus) maximal length.
+pub fn deserialize_payload_with_max_len<T>(buffer: &[u8], version: u32, max_len: usize) -> Result<T, Error> where T: Payload {
+	if buffer.len() > T::max_payload_len() || buffer.len() > max_len {
+		return Err(Error::MessageTooLarge);
+	}
+
 	let mut reader = PayloadReader::new(buffer, version);
 	let result = try!(reader.read());
 	if !reader.is_finished() {
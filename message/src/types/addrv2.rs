@@ -0,0 +1,322 @@
+use std::{io, net};
+use ser::{
+	Serializable, Stream, CompactInteger,
+	Deserializable, Reader, Error as ReaderError,
+};
+use common::{NetAddress, Port, Services};
+use types::addr::AddressEntry;
+use types::Addr;
+use {Payload, MessageResult};
+
+/// Maximal number of addresses in single `addrv2` message.
+pub const ADDRV2_MAX_ADDRESSES_LEN: usize = 1000;
+/// Maximal length of address in `addrv2` message.
+pub const ADDRV2_MAX_ADDRESS_LEN: usize = 512;
+
+const NETWORK_ID_IPV4: u8 = 0x01;
+const NETWORK_ID_IPV6: u8 = 0x02;
+const NETWORK_ID_TORV2: u8 = 0x03;
+const NETWORK_ID_TORV3: u8 = 0x04;
+const NETWORK_ID_I2P: u8 = 0x05;
+const NETWORK_ID_CJDNS: u8 = 0x06;
+
+/// `addrv2` message (BIP155).
+#[derive(Debug, PartialEq)]
+pub struct AddrV2 {
+	pub addresses: Vec<AddressEntryV2>,
+}
+
+impl AddrV2 {
+	pub fn new(addresses: Vec<AddressEntryV2>) -> Self {
+		AddrV2 {
+			addresses: addresses,
+		}
+	}
+
+	/// Converts to legacy `addr` message for peers that haven't sent us `sendaddrv2`.
+	/// Addresses that can't be represented in legacy format are skipped.
+	pub fn to_legacy(&self) -> Addr {
+		Addr::new(self.addresses.iter().filter_map(AddressEntryV2::to_legacy).collect())
+	}
+}
+
+impl Payload for AddrV2 {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"addrv2"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let addrv2 = AddrV2 {
+			addresses: try!(reader.read_list_max(ADDRV2_MAX_ADDRESSES_LEN)),
+		};
+
+		Ok(addrv2)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream.append_list(&self.addresses);
+		Ok(())
+	}
+}
+
+/// Single entry of `addrv2` message.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AddressEntryV2 {
+	pub timestamp: u32,
+	pub services: Services,
+	pub address: NetworkAddressV2,
+	pub port: Port,
+}
+
+impl AddressEntryV2 {
+	/// Converts to legacy `addr` entry, if address is IPv4 or IPv6 address.
+	pub fn to_legacy(&self) -> Option<AddressEntry> {
+		let address = match self.address {
+			NetworkAddressV2::IPv4(address) => net::IpAddr::V4(address),
+			NetworkAddressV2::IPv6(address) => net::IpAddr::V6(address),
+			_ => return None,
+		};
+
+		Some(AddressEntry {
+			timestamp: self.timestamp,
+			address: NetAddress {
+				services: self.services,
+				address: address.into(),
+				port: self.port,
+			},
+		})
+	}
+}
+
+impl From<AddressEntry> for AddressEntryV2 {
+	fn from(entry: AddressEntry) -> Self {
+		AddressEntryV2 {
+			timestamp: entry.timestamp,
+			services: entry.address.services,
+			address: match net::IpAddr::from(entry.address.address) {
+				net::IpAddr::V4(address) => NetworkAddressV2::IPv4(address),
+				net::IpAddr::V6(address) => NetworkAddressV2::IPv6(address),
+			},
+			port: entry.address.port,
+		}
+	}
+}
+
+impl Serializable for AddressEntryV2 {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.timestamp)
+			.append(&CompactInteger::from(u64::from(self.services)))
+			.append(&self.address)
+			.append(&self.port);
+	}
+}
+
+impl Deserializable for AddressEntryV2 {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let timestamp = try!(reader.read());
+		let services: u64 = try!(reader.read::<CompactInteger>()).into();
+		let entry = AddressEntryV2 {
+			timestamp: timestamp,
+			services: services.into(),
+			address: try!(reader.read()),
+			port: try!(reader.read()),
+		};
+
+		Ok(entry)
+	}
+}
+
+/// Network address, as it is encoded in `addrv2` message.
+#[derive(Debug, PartialEq, Clone)]
+pub enum NetworkAddressV2 {
+	IPv4(net::Ipv4Addr),
+	IPv6(net::Ipv6Addr),
+	TorV2([u8; 10]),
+	TorV3([u8; 32]),
+	I2P([u8; 32]),
+	Cjdns([u8; 16]),
+	/// Address from network we're not aware of. Must be relayed as-is.
+	Unknown(u8, Vec<u8>),
+}
+
+impl NetworkAddressV2 {
+	fn network_id(&self) -> u8 {
+		match *self {
+			NetworkAddressV2::IPv4(_) => NETWORK_ID_IPV4,
+			NetworkAddressV2::IPv6(_) => NETWORK_ID_IPV6,
+			NetworkAddressV2::TorV2(_) => NETWORK_ID_TORV2,
+			NetworkAddressV2::TorV3(_) => NETWORK_ID_TORV3,
+			NetworkAddressV2::I2P(_) => NETWORK_ID_I2P,
+			NetworkAddressV2::Cjdns(_) => NETWORK_ID_CJDNS,
+			NetworkAddressV2::Unknown(network_id, _) => network_id,
+		}
+	}
+
+	fn address_bytes(&self) -> Vec<u8> {
+		match *self {
+			NetworkAddressV2::IPv4(ref address) => address.octets().to_vec(),
+			NetworkAddressV2::IPv6(ref address) => address.octets().to_vec(),
+			NetworkAddressV2::TorV2(ref address) => address.to_vec(),
+			NetworkAddressV2::TorV3(ref address) => address.to_vec(),
+			NetworkAddressV2::I2P(ref address) => address.to_vec(),
+			NetworkAddressV2::Cjdns(ref address) => address.to_vec(),
+			NetworkAddressV2::Unknown(_, ref address) => address.clone(),
+		}
+	}
+}
+
+impl Serializable for NetworkAddressV2 {
+	fn serialize(&self, stream: &mut Stream) {
+		let address = self.address_bytes();
+		stream
+			.append(&self.network_id())
+			.append(&CompactInteger::from(address.len()))
+			.append_slice(&address);
+	}
+}
+
+impl Deserializable for NetworkAddressV2 {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let network_id: u8 = try!(reader.read());
+		let len: usize = try!(reader.read::<CompactInteger>()).into();
+		if len > ADDRV2_MAX_ADDRESS_LEN {
+			return Err(ReaderError::MalformedData);
+		}
+
+		let expected_len = match network_id {
+			NETWORK_ID_IPV4 => Some(4),
+			NETWORK_ID_IPV6 => Some(16),
+			NETWORK_ID_TORV2 => Some(10),
+			NETWORK_ID_TORV3 | NETWORK_ID_I2P => Some(32),
+			NETWORK_ID_CJDNS => Some(16),
+			_ => None,
+		};
+		if expected_len.map(|expected_len| expected_len != len).unwrap_or(false) {
+			return Err(ReaderError::MalformedData);
+		}
+
+		let mut address = vec![0u8; len];
+		try!(reader.read_slice(&mut address));
+
+		Ok(match network_id {
+			NETWORK_ID_IPV4 => NetworkAddressV2::IPv4(net::Ipv4Addr::new(address[0], address[1], address[2], address[3])),
+			NETWORK_ID_IPV6 => {
+				let mut octets = [0u8; 16];
+				octets.copy_from_slice(&address);
+				NetworkAddressV2::IPv6(net::Ipv6Addr::from(octets))
+			},
+			NETWORK_ID_TORV2 => {
+				let mut octets = [0u8; 10];
+				octets.copy_from_slice(&address);
+				NetworkAddressV2::TorV2(octets)
+			},
+			NETWORK_ID_TORV3 => {
+				let mut octets = [0u8; 32];
+				octets.copy_from_slice(&address);
+				NetworkAddressV2::TorV3(octets)
+			},
+			NETWORK_ID_I2P => {
+				let mut octets = [0u8; 32];
+				octets.copy_from_slice(&address);
+				NetworkAddressV2::I2P(octets)
+			},
+			NETWORK_ID_CJDNS => {
+				let mut octets = [0u8; 16];
+				octets.copy_from_slice(&address);
+				NetworkAddressV2::Cjdns(octets)
+			},
+			_ => NetworkAddressV2::Unknown(network_id, address),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net;
+	use bytes::Bytes;
+	use ser::deserialize;
+	use common::Services;
+	use types::Addr;
+	use types::addr::AddressEntry;
+	use {serialize_payload, deserialize_payload};
+	use super::{AddrV2, AddressEntryV2, NetworkAddressV2};
+
+	fn entry(address: NetworkAddressV2) -> AddressEntryV2 {
+		AddressEntryV2 {
+			timestamp: 0x4d1015e2,
+			services: Services::default().with_network(true),
+			address: address,
+			port: 8233.into(),
+		}
+	}
+
+	fn round_trip(address: NetworkAddressV2) {
+		let addrv2 = AddrV2::new(vec![entry(address)]);
+		let serialized = serialize_payload(&addrv2, 0).unwrap();
+		assert_eq!(deserialize_payload::<AddrV2>(serialized.as_ref(), 0).unwrap(), addrv2);
+	}
+
+	#[test]
+	fn test_addrv2_ipv4_serialize() {
+		let expected: Bytes = "01e215104d0101040a0000012029".into();
+		let addrv2 = AddrV2::new(vec![entry(NetworkAddressV2::IPv4(net::Ipv4Addr::new(10, 0, 0, 1)))]);
+		assert_eq!(serialize_payload(&addrv2, 0).unwrap(), expected);
+		assert_eq!(deserialize_payload::<AddrV2>(expected.as_ref(), 0).unwrap(), addrv2);
+	}
+
+	#[test]
+	fn test_addrv2_ipv6_round_trip() {
+		round_trip(NetworkAddressV2::IPv6("2001:db8::1".parse().unwrap()));
+	}
+
+	#[test]
+	fn test_addrv2_torv2_round_trip() {
+		round_trip(NetworkAddressV2::TorV2([0x11; 10]));
+	}
+
+	#[test]
+	fn test_addrv2_torv3_round_trip() {
+		round_trip(NetworkAddressV2::TorV3([0x22; 32]));
+	}
+
+	#[test]
+	fn test_addrv2_i2p_round_trip() {
+		round_trip(NetworkAddressV2::I2P([0x33; 32]));
+	}
+
+	#[test]
+	fn test_addrv2_cjdns_round_trip() {
+		round_trip(NetworkAddressV2::Cjdns([0xfc; 16]));
+	}
+
+	#[test]
+	fn test_addrv2_unknown_network_round_trip() {
+		round_trip(NetworkAddressV2::Unknown(0x42, vec![1, 2, 3]));
+	}
+
+	#[test]
+	fn test_addrv2_rejects_invalid_address_length() {
+		let raw: Bytes = "0203000000000000000000000000000000000000".into();
+		assert!(deserialize::<_, NetworkAddressV2>(raw.as_ref()).is_err());
+	}
+
+	#[test]
+	fn test_addrv2_to_legacy() {
+		let legacy_entry = || AddressEntry {
+			timestamp: 0x4d1015e2,
+			address: "01000000000000000000000000000000000000000a0000012029".into(),
+		};
+		let addrv2 = AddrV2::new(vec![
+			entry(NetworkAddressV2::IPv4(net::Ipv4Addr::new(10, 0, 0, 1))),
+			entry(NetworkAddressV2::TorV3([0x22; 32])),
+		]);
+
+		assert_eq!(AddressEntryV2::from(legacy_entry()), addrv2.addresses[0]);
+		assert_eq!(addrv2.to_legacy(), Addr::new(vec![legacy_entry()]));
+	}
+}
@@ -0,0 +1,59 @@
+use std::io;
+use bytes::Bytes;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+/// Deprecated alert message. Alerts are parsed, so that older peers, still sending them,
+/// are not treated as misbehaving, but their contents is never processed.
+#[derive(Debug, PartialEq)]
+pub struct Alert {
+	/// Serialized alert.
+	pub payload: Bytes,
+	/// Signature of the alert.
+	pub signature: Bytes,
+}
+
+impl Payload for Alert {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"alert"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let alert = Alert {
+			payload: try!(reader.read()),
+			signature: try!(reader.read()),
+		};
+
+		Ok(alert)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream
+			.append(&self.payload)
+			.append(&self.signature);
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use bytes::Bytes;
+	use {serialize_payload, deserialize_payload};
+	use super::Alert;
+
+	#[test]
+	fn test_alert_roundtrip() {
+		let raw: Bytes = "0568656c6c6f03736967".into();
+		let expected = Alert {
+			payload: "68656c6c6f".into(),
+			signature: "736967".into(),
+		};
+
+		assert_eq!(deserialize_payload::<Alert>(&raw, 0).unwrap(), expected);
+		assert_eq!(serialize_payload(&expected, 0).unwrap(), raw);
+	}
+}
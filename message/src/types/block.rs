@@ -1,6 +1,7 @@
 use std::io;
 use ser::{Stream, Reader};
 use chain::Block as ChainBlock;
+use network::{MAX_BLOCK_SIZE, MAX_BLOCK_TRANSACTIONS};
 use {Payload, MessageResult};
 
 #[derive(Debug, PartialEq)]
@@ -25,9 +26,15 @@ impl Payload for Block {
 		"block"
 	}
 
+	fn max_payload_len() -> usize {
+		MAX_BLOCK_SIZE
+	}
+
 	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let header = try!(reader.read());
+		let transactions = try!(reader.read_list_max(MAX_BLOCK_TRANSACTIONS));
 		let tx = Block {
-			block: try!(reader.read()),
+			block: ChainBlock::new(header, transactions),
 		};
 
 		Ok(tx)
@@ -38,3 +45,82 @@ impl Payload for Block {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use bytes::Bytes;
+	use ser::{serialize, Stream, CompactInteger};
+	use chain::{Block as ChainBlock, BlockHeader, Transaction, TransactionOutput};
+	use network::{MAX_BLOCK_SIZE, MAX_BLOCK_TRANSACTIONS};
+	use {deserialize_payload, deserialize_payload_with_max_len, Error};
+	use super::Block;
+
+	fn header() -> BlockHeader {
+		BlockHeader {
+			version: 4,
+			previous_header_hash: Default::default(),
+			merkle_root_hash: Default::default(),
+			final_sapling_root: Default::default(),
+			time: 0,
+			bits: 0.into(),
+			nonce: Default::default(),
+			solution: Default::default(),
+		}
+	}
+
+	/// Returns serialized block with single transaction, padded to given size.
+	fn raw_block_of_size(size: usize) -> Bytes {
+		let block_with_script = |script_len: usize| ChainBlock::new(header(), vec![Transaction {
+			version: 1,
+			outputs: vec![TransactionOutput {
+				value: 0,
+				script_pubkey: vec![0u8; script_len].into(),
+			}],
+			..Default::default()
+		}]);
+
+		// script length prefix is 5 bytes long for every script above 0xffff bytes
+		let base_script_len = 0x10000;
+		let base_size = serialize(&block_with_script(base_script_len)).len();
+		serialize(&block_with_script(base_script_len + size - base_size))
+	}
+
+	#[test]
+	fn test_block_at_size_limit_is_accepted() {
+		let raw = raw_block_of_size(MAX_BLOCK_SIZE);
+		assert_eq!(raw.len(), MAX_BLOCK_SIZE);
+		assert!(deserialize_payload::<Block>(&raw, 0).is_ok());
+	}
+
+	#[test]
+	fn test_block_over_size_limit_is_rejected() {
+		let raw = raw_block_of_size(MAX_BLOCK_SIZE + 1);
+		assert_eq!(raw.len(), MAX_BLOCK_SIZE + 1);
+		assert_eq!(deserialize_payload::<Block>(&raw, 0), Err(Error::MessageTooLarge));
+	}
+
+	#[test]
+	fn test_block_over_consensus_size_limit_is_rejected() {
+		let raw = raw_block_of_size(MAX_BLOCK_SIZE - 1000);
+		assert!(deserialize_payload_with_max_len::<Block>(&raw, 0, MAX_BLOCK_SIZE - 1000).is_ok());
+		assert_eq!(deserialize_payload_with_max_len::<Block>(&raw, 0, MAX_BLOCK_SIZE - 1001), Err(Error::MessageTooLarge));
+	}
+
+	#[test]
+	fn test_block_over_transactions_limit_is_rejected() {
+		let mut stream = Stream::new();
+		stream
+			.append(&header())
+			.append(&CompactInteger::from(MAX_BLOCK_TRANSACTIONS + 1));
+		let raw = stream.out();
+		assert_eq!(deserialize_payload::<Block>(&raw, 0), Err(Error::Deserialize));
+
+		// the same block is malformed if it announces allowed number of transactions
+		let mut stream = Stream::new();
+		stream
+			.append(&header())
+			.append(&CompactInteger::from(MAX_BLOCK_TRANSACTIONS));
+		let raw = stream.out();
+		assert_eq!(deserialize_payload::<Block>(&raw, 0), Err(Error::Deserialize));
+	}
+}
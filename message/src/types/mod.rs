@@ -1,6 +1,9 @@
 pub mod addr;
+pub mod addrv2;
+mod alert;
 mod block;
 mod blocktxn;
+mod compactblock;
 mod feefilter;
 mod filteradd;
 mod filterclear;
@@ -18,14 +21,19 @@ mod notfound;
 mod ping;
 mod pong;
 pub mod reject;
+mod sendaddrv2;
+mod sendcompact;
 mod sendheaders;
 mod tx;
 mod verack;
 pub mod version;
 
 pub use self::addr::Addr;
+pub use self::addrv2::{AddrV2, ADDRV2_MAX_ADDRESSES_LEN};
+pub use self::alert::Alert;
 pub use self::block::Block;
 pub use self::blocktxn::BlockTxn;
+pub use self::compactblock::CompactBlock;
 pub use self::feefilter::FeeFilter;
 pub use self::filterload::{FilterLoad, FILTERLOAD_MAX_FILTER_LEN, FILTERLOAD_MAX_HASH_FUNCS};
 pub use self::filterload::FilterFlags;
@@ -44,6 +52,8 @@ pub use self::notfound::NotFound;
 pub use self::ping::Ping;
 pub use self::pong::Pong;
 pub use self::reject::Reject;
+pub use self::sendaddrv2::{SendAddrV2, ADDRV2_PROTOCOL_VERSION};
+pub use self::sendcompact::{SendCompact, SENDCOMPACT_VERSION};
 pub use self::sendheaders::SendHeaders;
 pub use self::tx::Tx;
 pub use self::verack::Verack;
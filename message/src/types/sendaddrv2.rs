@@ -0,0 +1,50 @@
+use std::io;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+/// Minimal Zcash protocol version (NU5, zcashd 5.0.0) of peers, supporting `addrv2` messages (BIP155).
+/// Bitcoin protocol version 70016 can't be used here: every Zcash peer (170xxx) would pass the check.
+pub const ADDRV2_PROTOCOL_VERSION: u32 = 170_100;
+
+/// Signals that node prefers to receive `addrv2` messages instead of `addr` (BIP155).
+/// Must be sent before `verack`. Only sent to (and accepted from) peers with protocol
+/// version `ADDRV2_PROTOCOL_VERSION`+.
+#[derive(Debug, PartialEq)]
+pub struct SendAddrV2;
+
+impl Payload for SendAddrV2 {
+	fn version() -> u32 {
+		0
+	}
+
+	fn command() -> &'static str {
+		"sendaddrv2"
+	}
+
+	fn deserialize_payload<T>(_reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		Ok(SendAddrV2)
+	}
+
+	fn serialize_payload(&self, _stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use network::Network;
+	use {Message, Payload, deserialize_payload};
+	use super::{SendAddrV2, ADDRV2_PROTOCOL_VERSION};
+
+	#[test]
+	fn test_sendaddrv2_message_round_trip() {
+		let magic = Network::Mainnet.magic();
+		let message = Message::new(magic, ADDRV2_PROTOCOL_VERSION, &SendAddrV2).unwrap();
+		let bytes: &[u8] = message.as_ref();
+		// header only: magic + command + zero length + checksum of empty payload
+		assert_eq!(bytes.len(), 24);
+		assert_eq!(&bytes[4..14], b"sendaddrv2");
+		assert_eq!(deserialize_payload::<SendAddrV2>(&[], ADDRV2_PROTOCOL_VERSION).unwrap(), SendAddrV2);
+		assert_eq!(SendAddrV2::command(), "sendaddrv2");
+	}
+}
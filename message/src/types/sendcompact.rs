@@ -0,0 +1,40 @@
+use std::io;
+use ser::{Stream, Reader};
+use {Payload, MessageResult};
+
+/// Compact blocks (BIP152) version, supported by this node.
+pub const SENDCOMPACT_VERSION: u64 = 1;
+
+#[derive(Debug, PartialEq)]
+pub struct SendCompact {
+	/// Should peer announce new blocks using `cmpctblock` messages?
+	pub announce: bool,
+	/// Version of compact blocks protocol.
+	pub version: u64,
+}
+
+impl Payload for SendCompact {
+	fn version() -> u32 {
+		70014
+	}
+
+	fn command() -> &'static str {
+		"sendcmpct"
+	}
+
+	fn deserialize_payload<T>(reader: &mut Reader<T>, _version: u32) -> MessageResult<Self> where T: io::Read {
+		let send_compact = SendCompact {
+			announce: try!(reader.read()),
+			version: try!(reader.read()),
+		};
+
+		Ok(send_compact)
+	}
+
+	fn serialize_payload(&self, stream: &mut Stream, _version: u32) -> MessageResult<()> {
+		stream
+			.append(&self.announce)
+			.append(&self.version);
+		Ok(())
+	}
+}
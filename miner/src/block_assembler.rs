@@ -7,8 +7,10 @@ use keys::Address;
 use storage::{SharedStore, TransactionOutputProvider, SaplingTreeState};
 use script::Builder;
 use network::ConsensusParams;
-use memory_pool::{MemoryPool, OrderingStrategy, Entry};
-use verification::{work_required, transaction_sigops};
+use memory_pool::{MemoryPool, Entry};
+use coinbase::{coinbase_script_sig, MAX_COINBASE_SCRIPT_SIG_LEN};
+use spent_output_cache::SpentOutputCache;
+use verification::{work_required, transaction_sigops, founders_reward_output};
 
 const BLOCK_VERSION: u32 = 4;
 const BLOCK_HEADER_SIZE: u32 = 4 + 32 + 32 + 32 + 4 + 4 + 32 + 1344;
@@ -127,6 +129,10 @@ pub struct BlockAssembler<'a> {
 	pub max_block_size: u32,
 	/// Maximal # of sigops in the block.
 	pub max_block_sigops: u32,
+	/// Miner tag, appended to the coinbase scriptSig after the block height.
+	pub coinbase_tag: &'a [u8],
+	/// Cache of outputs, spent by memory pool transactions.
+	pub output_cache: Option<&'a SpentOutputCache>,
 }
 
 /// Iterator iterating over mempool transactions and yielding only those which fit the block
@@ -214,10 +220,12 @@ impl<'a, T> Iterator for FittingTransactionsIterator<'a, T> where T: Iterator<It
 			// both next checks could be checked above, but then it will break finishing
 			// check if transaction is still not finalized in this block
 			if !entry.transaction.is_final_in_block(self.block_height, self.block_time) {
+				self.ignored.insert(entry.hash.clone());
 				continue;
 			}
 			// check if any parent transaction has been ignored
 			if !self.ignored.is_empty() && entry.transaction.inputs.iter().any(|input| self.ignored.contains(&input.previous_output.hash)) {
+				self.ignored.insert(entry.hash.clone());
 				continue;
 			}
 
@@ -236,7 +244,9 @@ impl<'a, T> Iterator for FittingTransactionsIterator<'a, T> where T: Iterator<It
 					self.previous_entries.push(entry);
 					return Some(entry);
 				},
-				NextStep::Ignore => (),
+				NextStep::Ignore => {
+					self.ignored.insert(entry.hash.clone());
+				},
 				NextStep::FinishAndIgnore => {
 					self.ignored.insert(entry.hash.clone());
 					self.finished = true;
@@ -267,15 +277,24 @@ impl<'a> BlockAssembler<'a> {
 		let mut miner_reward = consensus.miner_reward(height);
 		let mut transactions = Vec::new();
 
-		let mempool_iter = mempool.iter(OrderingStrategy::ByTransactionScore);
+		// transactions are selected by ancestor fee rate, ancestors are always selected before descendants
+		let mempool_iter = mempool.select_package_entries(self.max_block_size as usize, self.max_block_sigops as usize).into_iter();
 		let mut sapling_tree = if previous_header_hash.is_zero() {
 			SaplingTreeState::new()
 		} else {
 			store.as_tree_state_provider().sapling_tree_at_block(&previous_header_hash)
 				.ok_or_else(|| format!("Sapling commitment tree for block {} is not found", previous_header_hash.reversed()))?
 		};
+		let cached_output_provider;
+		let output_provider = match self.output_cache {
+			Some(output_cache) => {
+				cached_output_provider = output_cache.provider(store.as_transaction_output_provider(), &previous_header_hash);
+				&cached_output_provider as &TransactionOutputProvider
+			},
+			None => store.as_transaction_output_provider(),
+		};
 		let tx_iter = FittingTransactionsIterator::new(
-			store.as_transaction_output_provider(),
+			output_provider,
 			mempool_iter,
 			self.max_block_size,
 			self.max_block_sigops,
@@ -300,15 +319,18 @@ impl<'a> BlockAssembler<'a> {
 		}
 
 		// prepare coinbase transaction
+		let script_sig = coinbase_script_sig(height, self.coinbase_tag);
+		if script_sig.len() > MAX_COINBASE_SCRIPT_SIG_LEN {
+			return Err(format!("Coinbase scriptSig of {} bytes exceeds maximal length of {} bytes",
+				script_sig.len(), MAX_COINBASE_SCRIPT_SIG_LEN));
+		}
+
 		let mut coinbase_tx = Transaction {
 			overwintered: true,
 			version: SAPLING_TX_VERSION,
 			version_group_id: SAPLING_TX_VERSION_GROUP_ID,
 			inputs: vec![
-				TransactionInput::coinbase(Builder::default()
-					.push_i64(height.into())
-					.into_script()
-					.into())
+				TransactionInput::coinbase(script_sig.to_bytes())
 			],
 			outputs: vec![
 				TransactionOutput {
@@ -323,10 +345,10 @@ impl<'a> BlockAssembler<'a> {
 		};
 
 		// insert founder reward if required
-		if let Some(founder_address) = consensus.founder_address(height) {
+		if let Some((script_pubkey, value)) = founders_reward_output(height, consensus) {
 			coinbase_tx.outputs.push(TransactionOutput {
-				value: consensus.founder_reward(height),
-				script_pubkey: Builder::build_p2sh(&founder_address.hash).into(),
+				value: value,
+				script_pubkey: script_pubkey.into(),
 			});
 		}
 
@@ -358,6 +380,8 @@ mod tests {
 	use memory_pool::MemoryPool;
 	use fee::{FeeCalculator, NonZeroFeeCalculator};
 	use self::test_data::{ChainBuilder, TransactionBuilder};
+	use script::Script;
+	use coinbase::{decode_coinbase_height, MAX_COINBASE_SCRIPT_SIG_LEN};
 	use super::{BlockAssembler, SizePolicy, NextStep, BlockTemplate};
 
 	#[test]
@@ -423,6 +447,8 @@ mod tests {
 				miner_address: &"t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe".into(),
 				max_block_size: 0xffffffff,
 				max_block_sigops: 0xffffffff,
+				coinbase_tag: &[],
+				output_cache: None,
 			}.create_new_block(&storage, &pool, 0, &consensus).unwrap(), hash0, hash1)
 		}
 
@@ -434,6 +460,71 @@ mod tests {
 		assert_eq!(block.transactions[1].hash, hash1);
 	}
 
+	#[test]
+	fn block_assembler_selects_prioritised_transaction_first() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_default_input(0).set_output(10).store(chain)	// transaction0: low fee
+			.set_default_input(1).set_output(50).store(chain);					// transaction1: high fee
+		let hash0 = chain.at(0).hash();
+		let hash1 = chain.at(1).hash();
+
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+
+		let storage: SharedStore = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		let assembler = BlockAssembler {
+			miner_address: &"t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe".into(),
+			max_block_size: 0xffffffff,
+			max_block_sigops: 0xffffffff,
+			coinbase_tag: &[],
+			output_cache: None,
+		};
+
+		let block = assembler.create_new_block(&storage, &pool, 0, &consensus).unwrap();
+		assert_eq!(block.transactions[0].hash, hash1);
+		assert_eq!(block.transactions[1].hash, hash0);
+
+		pool.prioritise_transaction(&hash0, 100);
+		let block = assembler.create_new_block(&storage, &pool, 0, &consensus).unwrap();
+		assert_eq!(block.transactions[0].hash, hash0);
+		assert_eq!(block.transactions[1].hash, hash1);
+	}
+
+	#[test]
+	fn block_assembler_breaks_fee_rate_ties_by_hash() {
+		let chain = &mut ChainBuilder::new();
+		for i in 0..5 {
+			// same size && same fee => same fee rate
+			TransactionBuilder::with_default_input(i as u32).set_output(10).store(chain);
+		}
+		let mut expected_hashes: Vec<H256> = (0..5).map(|i| chain.at(i).hash()).collect();
+		expected_hashes.sort();
+
+		let storage: SharedStore = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		let assembler = BlockAssembler {
+			miner_address: &"t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe".into(),
+			max_block_size: 0xffffffff,
+			max_block_sigops: 0xffffffff,
+			coinbase_tag: &[],
+			output_cache: None,
+		};
+
+		// the same transactions, inserted in different orders, are selected in the same order
+		for insertion_order in &[[0, 1, 2, 3, 4], [4, 3, 2, 1, 0], [2, 4, 0, 3, 1]] {
+			let mut pool = MemoryPool::new();
+			for i in insertion_order.iter() {
+				pool.insert_verified(chain.at(*i).into(), &NonZeroFeeCalculator);
+			}
+
+			let block = assembler.create_new_block(&storage, &pool, 0, &consensus).unwrap();
+			let selected_hashes: Vec<H256> = block.transactions.iter().map(|tx| tx.hash.clone()).collect();
+			assert_eq!(selected_hashes, expected_hashes);
+		}
+	}
+
 	#[test]
 	fn block_assembler_miner_fee() {
 		let input_tx = test_data::block_h1().transactions[0].clone();
@@ -450,10 +541,35 @@ mod tests {
 		let block = BlockAssembler {
 			max_block_size: 0xffffffff,
 			max_block_sigops: 0xffffffff,
+			coinbase_tag: &[],
+			output_cache: None,
 			miner_address: &"t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe".into(),
 		}.create_new_block(&storage, &pool, 0, &consensus).unwrap();
 
 		let expected_coinbase_value = consensus.block_reward(2) + expected_tx0_fee;
 		assert_eq!(block.coinbase_tx.raw.total_spends(), expected_coinbase_value);
 	}
+
+	#[test]
+	fn block_assembler_coinbase_tag() {
+		let storage: SharedStore = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let pool = MemoryPool::new();
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		let too_long_tag = [0x20u8; MAX_COINBASE_SCRIPT_SIG_LEN];
+		let mut assembler = BlockAssembler {
+			miner_address: &"t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe".into(),
+			max_block_size: 0xffffffff,
+			max_block_sigops: 0xffffffff,
+			coinbase_tag: b"/pzec/",
+			output_cache: None,
+		};
+
+		let block = assembler.create_new_block(&storage, &pool, 0, &consensus).unwrap();
+		let script_sig: Script = block.coinbase_tx.raw.inputs[0].script_sig.clone().into();
+		assert_eq!(decode_coinbase_height(&script_sig), Some(1));
+		assert!(script_sig.ends_with(b"/pzec/"));
+
+		assembler.coinbase_tag = &too_long_tag;
+		assert!(assembler.create_new_block(&storage, &pool, 0, &consensus).is_err());
+	}
 }
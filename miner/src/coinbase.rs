@@ -0,0 +1,108 @@
+use script::{Builder, Script};
+
+pub use chain::decode_coinbase_height;
+
+/// Maximal length of the coinbase scriptSig (consensus rule)
+pub const MAX_COINBASE_SCRIPT_SIG_LEN: usize = 100;
+
+/// Encode block height as minimal push of script number (BIP34).
+/// Coinbase scriptSig of every block must start with this prefix.
+pub fn encode_coinbase_height(height: u32) -> Script {
+	Builder::default()
+		.push_i64(height.into())
+		.into_script()
+}
+
+/// Build coinbase scriptSig: BIP34 height, followed by the push of the miner tag (if any).
+pub fn coinbase_script_sig(height: u32, tag: &[u8]) -> Script {
+	let builder = Builder::default().push_i64(height.into());
+	match tag.is_empty() {
+		true => builder.into_script(),
+		false => builder.push_data(tag).into_script(),
+	}
+}
+
+/// Check that coinbase scriptSig with given miner tag fits the length limit at any height.
+pub fn check_coinbase_tag(tag: &[u8]) -> Result<(), String> {
+	let script_sig_len = coinbase_script_sig(u32::max_value(), tag).len();
+	if script_sig_len > MAX_COINBASE_SCRIPT_SIG_LEN {
+		return Err(format!("coinbase tag of {} bytes is too long: coinbase scriptSig would take {} bytes, while maximum is {}",
+			tag.len(), script_sig_len, MAX_COINBASE_SCRIPT_SIG_LEN));
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use script::{Builder, Opcode};
+	use super::{encode_coinbase_height, decode_coinbase_height, coinbase_script_sig, check_coinbase_tag,
+		MAX_COINBASE_SCRIPT_SIG_LEN};
+
+	#[test]
+	fn coinbase_height_round_trip() {
+		let heights = vec![0, 1, 2, 16, 17, 127, 128, 255, 256, 32767, 32768, 65535, 65536,
+			8388607, 8388608, 0x7fffffff, 0x80000000, u32::max_value()];
+		for height in heights {
+			let script = encode_coinbase_height(height);
+			assert_eq!(decode_coinbase_height(&script), Some(height), "height {}", height);
+		}
+	}
+
+	#[test]
+	fn coinbase_height_encoding() {
+		assert_eq!(encode_coinbase_height(1).to_vec(), vec![Opcode::OP_1 as u8]);
+		assert_eq!(encode_coinbase_height(16).to_vec(), vec![Opcode::OP_16 as u8]);
+		assert_eq!(encode_coinbase_height(127).to_vec(), vec![0x01, 0x7f]);
+		assert_eq!(encode_coinbase_height(128).to_vec(), vec![0x02, 0x80, 0x00]);
+		assert_eq!(encode_coinbase_height(65535).to_vec(), vec![0x03, 0xff, 0xff, 0x00]);
+	}
+
+	#[test]
+	fn coinbase_height_matches_verifier_prefix() {
+		// verification checks that coinbase scriptSig starts with the same push
+		for height in vec![1, 16, 127, 128, 65535] {
+			let prefix = Builder::default().push_i64(height as i64).into_script();
+			assert_eq!(encode_coinbase_height(height), prefix);
+		}
+	}
+
+	#[test]
+	fn coinbase_height_decode_with_extra_data() {
+		let script = Builder::default()
+			.push_i64(500000)
+			.push_data(b"extra nonce")
+			.into_script();
+		assert_eq!(decode_coinbase_height(&script), Some(500000));
+	}
+
+	#[test]
+	fn coinbase_height_decode_non_minimal() {
+		// 1 encoded as non-minimal push
+		let script = Builder::default().push_data(&[1, 0]).into_script();
+		assert_eq!(decode_coinbase_height(&script), None);
+		// negative number
+		let script = Builder::default().push_opcode(Opcode::OP_1NEGATE).into_script();
+		assert_eq!(decode_coinbase_height(&script), None);
+		// 16 must be encoded as OP_16
+		let script = Builder::default().push_data(&[16]).into_script();
+		assert_eq!(decode_coinbase_height(&script), None);
+	}
+
+	#[test]
+	fn coinbase_script_sig_with_tag() {
+		let script = coinbase_script_sig(500000, b"/pzec/");
+		assert_eq!(decode_coinbase_height(&script), Some(500000));
+		assert!(script.ends_with(b"/pzec/"));
+		assert_eq!(coinbase_script_sig(500000, b""), encode_coinbase_height(500000));
+	}
+
+	#[test]
+	fn coinbase_tag_length_limit() {
+		// 6 bytes of height push + 2 bytes of OP_PUSHDATA1 prefix + 92 bytes of tag
+		assert_eq!(check_coinbase_tag(&[0x20; 92]), Ok(()));
+		assert_eq!(coinbase_script_sig(u32::max_value(), &[0x20; 92]).len(), MAX_COINBASE_SCRIPT_SIG_LEN);
+		assert!(check_coinbase_tag(&[0x20; 93]).is_err());
+		assert!(check_coinbase_tag(&[0x20; 200]).is_err());
+	}
+}
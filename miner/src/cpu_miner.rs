@@ -0,0 +1,92 @@
+use byteorder::{WriteBytesExt, LittleEndian};
+use primitives::hash::H256;
+use chain::{merkle_root, BlockHeader, IndexedBlock, IndexedBlockHeader};
+use verification::{solve_equihash, is_valid_proof_of_work_hash};
+use block_assembler::BlockTemplate;
+
+/// Mine block from the template, using Equihash(n, k) solver. Nonces are tried sequentially,
+/// starting from zero. Returns None if neither of first `max_nonces` nonces has produced
+/// block with valid proof of work.
+///
+/// Solver is slow, so this is only suitable for testing networks (i.e. for regtest).
+pub fn mine_block(template: BlockTemplate, equihash_params: (u32, u32), max_nonces: u32) -> Option<IndexedBlock> {
+	let mut transactions = Vec::with_capacity(template.transactions.len() + 1);
+	transactions.push(template.coinbase_tx);
+	transactions.extend(template.transactions);
+
+	let merkle_root_hash = {
+		let hashes: Vec<&H256> = transactions.iter().map(|tx| &tx.hash).collect();
+		merkle_root(&hashes)
+	};
+
+	let mut header = BlockHeader {
+		version: template.version,
+		previous_header_hash: template.previous_header_hash,
+		merkle_root_hash: merkle_root_hash,
+		final_sapling_root: template.final_sapling_root_hash,
+		time: template.time,
+		bits: template.bits,
+		nonce: H256::default(),
+		solution: Default::default(),
+	};
+
+	let (n, k) = equihash_params;
+	for nonce in 0..max_nonces {
+		(&mut header.nonce[..4]).write_u32::<LittleEndian>(nonce).expect("writing to the 32-bytes buffer never fails; qed");
+
+		let input = header.equihash_input();
+		let solution = match solve_equihash(&input[..input.len() - 32], &header.nonce, n, k) {
+			Some(solution) => solution,
+			None => continue,
+		};
+
+		header.solution = solution.into();
+		let header = IndexedBlockHeader::from_raw(header.clone());
+		if is_valid_proof_of_work_hash(header.raw.bits, &header.hash) {
+			return Some(IndexedBlock::new(header, transactions));
+		}
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use primitives::hash::H256;
+	use chain::IndexedTransaction;
+	use verification::BlockHeaderEquihash;
+	use block_assembler::BlockTemplate;
+	use super::mine_block;
+
+	fn template(bits: u32) -> BlockTemplate {
+		let coinbase = IndexedTransaction::from_raw(test_data::genesis().transactions[0].clone());
+		BlockTemplate {
+			version: 4,
+			previous_header_hash: H256::from(1),
+			final_sapling_root_hash: H256::from(2),
+			time: 1_000_000,
+			bits: bits.into(),
+			height: 1,
+			transactions: Vec::new(),
+			coinbase_tx: coinbase,
+			size_limit: 2_000_000,
+			sigop_limit: 20_000,
+		}
+	}
+
+	#[test]
+	fn mine_block_works() {
+		let block = mine_block(template(0x207fffff), (48, 5), 32).unwrap();
+		assert!(block.header.raw.verify_equihash(48, 5));
+		assert_eq!(block.header.raw.merkle_root_hash, block.merkle_root());
+		assert_eq!(block.transactions().len(), 1);
+	}
+
+	#[test]
+	fn mine_block_gives_up_after_max_nonces() {
+		// target is 1 => it is almost impossible to find a valid block
+		assert!(mine_block(template(0x01010000), (48, 5), 2).is_none());
+	}
+}
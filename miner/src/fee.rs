@@ -41,8 +41,16 @@ pub fn transaction_fee(store: &TransactionOutputProvider, tx: &Transaction) -> u
 	checked_transaction_fee(store, ::std::usize::MAX, tx).unwrap_or(0)
 }
 
+/// Compute fee rate (in zatoshis per 1000 bytes) of given (memory pool) transaction.
+///
+/// This is the same unit, which is used by `feefilter` messages.
 pub fn transaction_fee_rate(store: &TransactionOutputProvider, tx: &Transaction) -> u64 {
-	transaction_fee(store, tx) / tx.serialized_size() as u64
+	fee_rate(transaction_fee(store, tx), tx.serialized_size())
+}
+
+/// Compute fee rate (in zatoshis per 1000 bytes) of the transaction with given fee and size.
+pub fn fee_rate(fee: u64, size: usize) -> u64 {
+	fee.saturating_mul(1000) / size as u64
 }
 
 #[cfg(test)]
@@ -50,7 +58,7 @@ mod tests {
 	use std::sync::Arc;
 	use storage::AsSubstore;
 	use db::BlockChainDatabase;
-	use super::transaction_fee_rate;
+	use super::{transaction_fee_rate, fee_rate};
 
 	#[test]
 	fn transaction_fee_rate_works() {
@@ -75,6 +83,13 @@ mod tests {
 		let store = db.as_transaction_output_provider();
 
 		assert_eq!(transaction_fee_rate(store, &tx0), 0);
-		assert_eq!(transaction_fee_rate(store, &tx2), 4_901);
+		assert_eq!(transaction_fee_rate(store, &tx2), 4_901_960);
+	}
+
+	#[test]
+	fn fee_rate_is_per_kilobyte() {
+		assert_eq!(fee_rate(0, 250), 0);
+		assert_eq!(fee_rate(250, 250), 1_000);
+		assert_eq!(fee_rate(2_500, 250), 10_000);
 	}
 }
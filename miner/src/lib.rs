@@ -13,13 +13,27 @@ extern crate serialization as ser;
 extern crate verification;
 
 mod block_assembler;
+mod coinbase;
+#[cfg(feature = "solver")]
+mod cpu_miner;
 mod fee;
 mod memory_pool;
+mod priority_estimator;
+mod spent_output_cache;
 
 pub use block_assembler::{BlockAssembler, BlockTemplate};
-pub use memory_pool::{MemoryPool, HashedOutPoint, Information as MemoryPoolInformation,
-	OrderingStrategy as MemoryPoolOrderingStrategy, DoubleSpendCheckResult, NonFinalDoubleSpendSet};
-pub use fee::{FeeCalculator, transaction_fee, transaction_fee_rate};
+pub use coinbase::{encode_coinbase_height, decode_coinbase_height, coinbase_script_sig, check_coinbase_tag,
+	MAX_COINBASE_SCRIPT_SIG_LEN};
+pub use memory_pool::{MemoryPool, Information as MemoryPoolInformation,
+	OrderingStrategy as MemoryPoolOrderingStrategy, DoubleSpendCheckResult, NonFinalDoubleSpendSet,
+	MAX_BIP125_RBF_SEQUENCE};
+pub use verification::RbfError;
+pub use fee::{FeeCalculator, fee_rate, transaction_fee, transaction_fee_rate};
+pub use spent_output_cache::{SpentOutputCache, CachedTransactionOutputProvider};
+pub use priority_estimator::{PriorityEstimator, coin_age_priority, INSUFFICIENT_PRIORITY_DATA,
+	MAX_PRIORITY_CONFIRMATIONS};
 
+#[cfg(feature = "solver")]
+pub use cpu_miner::mine_block;
 #[cfg(feature = "test-helpers")]
 pub use fee::NonZeroFeeCalculator;
\ No newline at end of file
@@ -13,11 +13,13 @@ use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::collections::BTreeSet;
+use std::collections::BinaryHeap;
 use std::collections::VecDeque;
-use std::hash::{Hash, Hasher};
 use ser::{Serializable, serialize};
 use heapsize::HeapSizeOf;
 use fee::MemoryPoolFeeCalculator;
+use priority_estimator::PriorityEstimator;
+use verification::{transaction_sigops, RbfError};
 
 /// Transactions ordering strategy
 #[cfg_attr(feature="cargo-clippy", allow(enum_variant_names))]
@@ -45,6 +47,12 @@ pub struct Information {
 pub struct MemoryPool {
 	/// Transactions storage
 	storage: Storage,
+	/// Virtual fee deltas, set by `prioritisetransaction`. Kept until transaction is mined or dropped.
+	fee_deltas: HashMap<H256, i64>,
+	/// Height of the best block, known to the memory pool.
+	best_block_height: u32,
+	/// Estimates priority of transactions, confirmed within given number of blocks.
+	priority_estimator: PriorityEstimator,
 }
 
 /// Single entry
@@ -60,6 +68,8 @@ pub struct Entry {
 	pub size: usize,
 	/// Throughout index of this transaction in memory pool (non persistent)
 	pub storage_index: u64,
+	/// Height of the best block at the moment when transaction has entered memory pool
+	pub height: u32,
 	/// Transaction fee (stored for efficiency)
 	pub miner_fee: u64,
 	/// Virtual transaction fee (a way to prioritize/penalize transaction)
@@ -82,7 +92,7 @@ struct Storage {
 	/// By-hash storage
 	by_hash: HashMap<H256, Entry>,
 	/// Transactions by previous output
-	by_previous_output: HashMap<HashedOutPoint, H256>,
+	by_previous_output: HashMap<OutPoint, H256>,
 	/// References storage
 	references: ReferenceStorage,
 }
@@ -141,10 +151,26 @@ struct ByPackageScoreOrderedEntry {
 	package_miner_virtual_fee: i64,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct HashedOutPoint {
-	/// Transaction output point
-	out_point: OutPoint,
+/// In-pool ancestor package of the transaction: the transaction itself and all its not-yet-selected
+/// in-pool ancestors (used when selecting transactions for block template).
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AncestorPackage {
+	/// miner_fee + miner_virtual_fee + Sum(miner_fee + miner_virtual_fee) for all not-yet-selected ancestors
+	fee: i64,
+	/// size + Sum(size) for all not-yet-selected ancestors
+	size: usize,
+	/// sigops + Sum(sigops) for all not-yet-selected ancestors
+	sigops: usize,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+struct ByAncestorScoreOrderedEntry {
+	/// Transaction hash
+	hash: H256,
+	/// Fee of the ancestor package at the moment when entry has been created
+	package_fee: i64,
+	/// Size of the ancestor package at the moment when entry has been created
+	package_size: usize,
 }
 
 /// Result of checking double spend with
@@ -152,36 +178,32 @@ pub struct HashedOutPoint {
 pub enum DoubleSpendCheckResult {
 	/// No double spend
 	NoDoubleSpend,
-	/// Input {self.1, self.2} of new transaction is already spent in previous final memory-pool transaction {self.0}
-	DoubleSpend(H256, H256, u32),
+	/// Input {self.1} of new transaction is already spent in previous final memory-pool transaction {self.0}
+	DoubleSpend(H256, OutPoint),
 	/// Some inputs of new transaction are already spent by non-final memory-pool transactions
 	NonFinalDoubleSpend(NonFinalDoubleSpendSet),
+	/// Some inputs of new transaction are already spent by final memory-pool transactions, which are signaling
+	/// replaceability. New transaction must be inserted using `MemoryPool::accept_replacement` (BIP125)
+	ReplaceableDoubleSpend(NonFinalDoubleSpendSet),
 }
 
 /// Set of transaction outputs, which can be replaced if newer transaction
-/// replaces non-final transaction in memory pool
+/// replaces non-final (or replaceable) transaction in memory pool
 #[derive(Debug, PartialEq)]
 pub struct NonFinalDoubleSpendSet {
 	/// Double-spend outputs (outputs of newer transaction, which are also spent by nonfinal transactions of mempool)
-	pub double_spends: HashSet<HashedOutPoint>,
+	pub double_spends: HashSet<OutPoint>,
 	/// Outputs which also will be removed from memory pool in case of newer transaction insertion
 	/// (i.e. outputs of nonfinal transactions && their descendants)
-	pub dependent_spends: HashSet<HashedOutPoint>,
+	pub dependent_spends: HashSet<OutPoint>,
 }
 
-impl From<OutPoint> for HashedOutPoint {
-	fn from(out_point: OutPoint) -> Self {
-		HashedOutPoint {
-			out_point: out_point,
-		}
-	}
-}
+/// Max input sequence number that signals transaction replaceability (BIP125).
+pub const MAX_BIP125_RBF_SEQUENCE: u32 = 0xfffffffd;
 
-impl Hash for HashedOutPoint {
-	fn hash<H>(&self, state: &mut H) where H: Hasher {
-		state.write(&serialize(&self.out_point));
-		state.finish();
-	}
+/// Returns true if transaction signals replaceability (BIP125)
+fn signals_replaceability(transaction: &Transaction) -> bool {
+	transaction.inputs.iter().any(|input| input.sequence <= MAX_BIP125_RBF_SEQUENCE)
 }
 
 impl<'a> From<&'a Entry> for ByTimestampOrderedEntry {
@@ -248,6 +270,8 @@ impl Ord for ByTransactionScoreOrderedEntry {
 			return order
 		}
 
+		// equal fee rates are ordered by transaction hash, so that the order (and block template
+		// transactions selection) only depends on memory pool contents, not on insertion order
 		self.hash.cmp(&other.hash)
 	}
 }
@@ -272,6 +296,37 @@ impl Ord for ByPackageScoreOrderedEntry {
 	}
 }
 
+impl ByAncestorScoreOrderedEntry {
+	fn new(entry: &Entry, package: &AncestorPackage) -> Self {
+		ByAncestorScoreOrderedEntry {
+			hash: entry.hash.clone(),
+			package_fee: package.fee,
+			package_size: package.size,
+		}
+	}
+}
+
+impl PartialOrd for ByAncestorScoreOrderedEntry {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for ByAncestorScoreOrderedEntry {
+	fn cmp(&self, other: &Self) -> Ordering {
+		// greater ancestor score means earlier selection
+		let left = self.package_fee * (other.package_size as i64);
+		let right = other.package_fee * (self.package_size as i64);
+		let order = left.cmp(&right);
+		if order != Ordering::Equal {
+			return order
+		}
+
+		// equal fee rates are ordered by transaction hash (lesser hash means earlier selection)
+		other.hash.cmp(&self.hash)
+	}
+}
+
 impl HeapSizeOf for Entry {
 	fn heap_size_of_children(&self) -> usize {
 		self.transaction.heap_size_of_children() + self.ancestors.heap_size_of_children()
@@ -331,7 +386,7 @@ impl Storage {
 
 		// remember that all inputs of this transaction are spent
 		for input in &entry.transaction.inputs {
-			let previous_tx = self.by_previous_output.insert(input.previous_output.clone().into(), entry.hash.clone());
+			let previous_tx = self.by_previous_output.insert(input.previous_output.clone(), entry.hash.clone());
 			assert_eq!(previous_tx, None); // transaction must be verified before => no double spend
 		}
 
@@ -348,7 +403,7 @@ impl Storage {
 	}
 
 	pub fn is_output_spent(&self, prevout: &OutPoint) -> bool {
-		self.by_previous_output.contains_key(&prevout.clone().into())
+		self.by_previous_output.contains_key(prevout)
 	}
 
 	pub fn set_virtual_fee(&mut self, h: &H256, virtual_fee: i64) {
@@ -412,7 +467,7 @@ impl Storage {
 
 				// forget that all inputs of this transaction are spent
 				for input in &entry.transaction.inputs {
-					let spent_in_tx = self.by_previous_output.remove(&input.previous_output.clone().into())
+					let spent_in_tx = self.by_previous_output.remove(&input.previous_output)
 						.expect("by_spent_output is filled for each incoming transaction inputs; so the drained value should exist; qed");
 					assert_eq!(&spent_in_tx, h);
 				}
@@ -425,31 +480,35 @@ impl Storage {
 	}
 
 	pub fn check_double_spend(&self, transaction: &Transaction) -> DoubleSpendCheckResult {
-		let mut double_spends: HashSet<HashedOutPoint> = HashSet::new();
-		let mut dependent_spends: HashSet<HashedOutPoint> = HashSet::new();
+		let mut double_spends: HashSet<OutPoint> = HashSet::new();
+		let mut dependent_spends: HashSet<OutPoint> = HashSet::new();
+		let mut is_replacement = false;
 
 		for input in &transaction.inputs {
 			// find transaction that spends the same output
-			let prevout: HashedOutPoint = input.previous_output.clone().into();
+			let prevout = input.previous_output.clone();
 			if let Some(entry_hash) = self.by_previous_output.get(&prevout).cloned() {
 				// check if this is final transaction. If so, that's a potential double-spend error
+				// (unless it signals replaceability)
 				let entry = self.by_hash.get(&entry_hash).expect("checked that it exists line above; qed");
 				if entry.transaction.is_final() {
-					return DoubleSpendCheckResult::DoubleSpend(entry_hash,	 prevout.out_point.hash, prevout.out_point.index);
+					if !signals_replaceability(&entry.transaction) {
+						return DoubleSpendCheckResult::DoubleSpend(entry_hash, prevout);
+					}
+					is_replacement = true;
 				}
 				// else remember this double spend
 				double_spends.insert(prevout.clone());
 				// and 'virtually' remove entry && all descendants from mempool
-				let mut queue: VecDeque<HashedOutPoint> = VecDeque::new();
+				let mut queue: VecDeque<OutPoint> = VecDeque::new();
 				queue.push_back(prevout);
 				while let Some(dependent_prevout) = queue.pop_front() {
 					// if the same output is already spent with another in-pool transaction
 					if let Some(dependent_entry_hash) = self.by_previous_output.get(&dependent_prevout).cloned() {
 						let dependent_entry = self.by_hash.get(&dependent_entry_hash).expect("checked that it exists line above; qed");
-						let dependent_outputs: Vec<_> = dependent_entry.transaction.outputs.iter().enumerate().map(|(idx, _)| OutPoint {
-							hash: dependent_entry_hash.clone(),
-							index: idx as u32,
-						}.into()).collect();
+						let dependent_outputs: Vec<_> = dependent_entry.transaction.outputs.iter().enumerate()
+							.map(|(idx, _)| OutPoint::new(dependent_entry_hash.clone(), idx as u32))
+							.collect();
 						dependent_spends.extend(dependent_outputs.clone());
 						queue.extend(dependent_outputs);
 					}
@@ -458,12 +517,17 @@ impl Storage {
 		}
 
 		if double_spends.is_empty() {
-			DoubleSpendCheckResult::NoDoubleSpend
+			return DoubleSpendCheckResult::NoDoubleSpend;
+		}
+
+		let double_spend_set = NonFinalDoubleSpendSet {
+			double_spends: double_spends,
+			dependent_spends: dependent_spends,
+		};
+		if is_replacement {
+			DoubleSpendCheckResult::ReplaceableDoubleSpend(double_spend_set)
 		} else {
-			DoubleSpendCheckResult::NonFinalDoubleSpend(NonFinalDoubleSpendSet {
-				double_spends: double_spends,
-				dependent_spends: dependent_spends,
-			})
+			DoubleSpendCheckResult::NonFinalDoubleSpend(double_spend_set)
 		}
 	}
 
@@ -473,7 +537,7 @@ impl Storage {
 		queue.push_back(prevout.clone());
 
 		while let Some(prevout) = queue.pop_front() {
-			if let Some(entry_hash) = self.by_previous_output.get(&prevout.clone().into()).cloned() {
+			if let Some(entry_hash) = self.by_previous_output.get(&prevout).cloned() {
 				let entry = self.remove_by_hash(&entry_hash).expect("checked that it exists line above; qed");
 				queue.extend(entry.transaction.outputs.iter().enumerate().map(|(idx, _)| OutPoint {
 					hash: entry_hash.clone(),
@@ -486,6 +550,53 @@ impl Storage {
 		Some(removed)
 	}
 
+	/// Returns hashes of in-pool transactions spending the same outputs as given transaction.
+	pub fn conflicts(&self, transaction: &Transaction) -> HashSet<H256> {
+		transaction.inputs.iter()
+			.filter_map(|input| self.by_previous_output.get(&input.previous_output).cloned())
+			.collect()
+	}
+
+	/// Returns hashes of given in-pool transactions and all their in-pool descendants.
+	pub fn with_descendants(&self, hashes: &HashSet<H256>) -> HashSet<H256> {
+		let mut result = HashSet::new();
+		let mut queue: VecDeque<H256> = hashes.iter().cloned().collect();
+		while let Some(hash) = queue.pop_front() {
+			if result.contains(&hash) || !self.by_hash.contains_key(&hash) {
+				continue;
+			}
+
+			if let Some(descendants) = self.references.by_input.get(&hash) {
+				queue.extend(descendants.iter().cloned());
+			}
+			result.insert(hash);
+		}
+
+		result
+	}
+
+	pub fn remove_expired(&mut self, height: u32) -> Vec<H256> {
+		let expired: Vec<H256> = self.by_hash.values()
+			.filter(|entry| entry.transaction.expiry_height != 0 && entry.transaction.expiry_height <= height)
+			.map(|entry| entry.hash.clone())
+			.collect();
+
+		let mut removed = Vec::new();
+		for hash in expired {
+			// expired transaction could already be removed as a descendant of other expired transaction
+			if let Some(entry) = self.remove_by_hash(&hash) {
+				// descendants of expired transaction are spending missing outputs => remove them too
+				for idx in 0..entry.transaction.outputs.len() {
+					let descendants = self.remove_by_prevout(&OutPoint::new(hash.clone(), idx as u32));
+					removed.extend(descendants.into_iter().flat_map(|d| d.into_iter()).map(|tx| tx.hash));
+				}
+				removed.push(hash);
+			}
+		}
+
+		removed
+	}
+
 	pub fn remove_by_parent_hash(&mut self, h: &H256) -> Option<Vec<IndexedTransaction>> {
 		// this code will run only when ancestor transaction is inserted
 		// in memory pool after its descendants
@@ -639,6 +750,9 @@ impl Default for MemoryPool {
 	fn default() -> Self {
 		MemoryPool {
 			storage: Storage::new(),
+			fee_deltas: HashMap::new(),
+			best_block_height: 0,
+			priority_estimator: PriorityEstimator::new(),
 		}
 	}
 }
@@ -664,14 +778,67 @@ impl MemoryPool {
 		}
 	}
 
+	/// Insert verified transaction, which replaces conflicting (i.e. spending the same outputs)
+	/// memory pool transactions (BIP125). All final conflicting transactions must signal replaceability
+	/// and replacement must pay greater fee than all replaced transactions (including descendants
+	/// of conflicting transactions) and greater fee rate than every conflicting transaction.
+	/// Returns hashes of replaced transactions.
+	pub fn accept_replacement<FC: MemoryPoolFeeCalculator>(&mut self, t: IndexedTransaction, fc: &FC) -> Result<Vec<H256>, RbfError> {
+		let replaced = {
+			let conflicts = self.storage.conflicts(&t.raw);
+			let replaced = self.storage.with_descendants(&conflicts);
+			// compare modified fees, so that prioritised transactions are (not) replaced accordingly
+			let fee = fc.calculate(self, &t.raw) as i64 + self.fee_delta(&t.hash);
+			let size = self.get_transaction_size(&t.raw);
+
+			if let Some(input) = t.raw.inputs.iter().find(|input| replaced.contains(&input.previous_output.hash)) {
+				return Err(RbfError::SpendsConflictingTransaction(input.previous_output.hash.clone()));
+			}
+
+			for conflict_hash in &conflicts {
+				let conflict = self.storage.get_by_hash(conflict_hash)
+					.expect("conflicts are read from storage; qed");
+				if conflict.transaction.is_final() && !signals_replaceability(&conflict.transaction) {
+					return Err(RbfError::NotReplaceable(conflict_hash.clone()));
+				}
+				let conflict_fee = conflict.miner_fee as i64 + conflict.miner_virtual_fee;
+				if fee as i128 * conflict.size as i128 <= conflict_fee as i128 * size as i128 {
+					return Err(RbfError::InsufficientFeeRate(conflict_hash.clone()));
+				}
+			}
+
+			let replaced_fee = replaced.iter()
+				.filter_map(|hash| self.storage.get_by_hash(hash))
+				.map(|entry| entry.miner_fee as i64 + entry.miner_virtual_fee)
+				.sum::<i64>();
+			if !conflicts.is_empty() && fee <= replaced_fee {
+				return Err(RbfError::InsufficientFee(fee, replaced_fee));
+			}
+
+			replaced
+		};
+
+		let mut removed = Vec::new();
+		for input in &t.raw.inputs {
+			if let Some(transactions) = self.remove_by_prevout(&input.previous_output) {
+				removed.extend(transactions.into_iter().map(|tx| tx.hash));
+			}
+		}
+		debug_assert_eq!(removed.len(), replaced.len());
+
+		self.insert_verified(t, fc);
+		Ok(removed)
+	}
+
 	/// Iterator over memory pool transactions according to specified strategy
 	pub fn iter(&self, strategy: OrderingStrategy) -> MemoryPoolIterator {
 		MemoryPoolIterator::new(self, strategy)
 	}
 
-	/// Removes single transaction by its hash.
+	/// Removes single transaction by its hash (i.e. when it is mined).
 	/// All descendants remain in the pool.
 	pub fn remove_by_hash(&mut self, h: &H256) -> Option<IndexedTransaction> {
+		self.fee_deltas.remove(h);
 		self.storage.remove_by_hash(h)
 			.map(|entry| IndexedTransaction::new(entry.hash, entry.transaction))
 	}
@@ -683,7 +850,24 @@ impl MemoryPool {
 
 	/// Removes transaction (and all its descendants) which has spent given output
 	pub fn remove_by_prevout(&mut self, prevout: &OutPoint) -> Option<Vec<IndexedTransaction>> {
-		self.storage.remove_by_prevout(prevout)
+		let removed = self.storage.remove_by_prevout(prevout);
+		if let Some(ref removed) = removed {
+			for transaction in removed {
+				self.fee_deltas.remove(&transaction.hash);
+			}
+		}
+		removed
+	}
+
+	/// Removes all transactions that are expired at given height (i.e. their non-zero expiry
+	/// height is less than or equal to the `height`), and all their descendants.
+	/// Returns hashes of removed transactions.
+	pub fn remove_expired(&mut self, height: u32) -> Vec<H256> {
+		let removed = self.storage.remove_expired(height);
+		for hash in &removed {
+			self.fee_deltas.remove(hash);
+		}
+		removed
 	}
 
 	/// Reads single transaction by its hash.
@@ -716,11 +900,149 @@ impl MemoryPool {
 		self.storage.remove_n_with_strategy(n, strategy)
 	}
 
+	/// Selects transactions for the block template, using ancestor fee rate (child-pays-for-parent).
+	/// Every transaction is selected together with all its not-yet-selected in-pool ancestors, so that
+	/// a low-fee parent could be mined because of the high-fee child. Packages are selected while
+	/// they fit into `max_size` bytes and `max_sigops` (legacy) signature operations.
+	/// Ancestors are always returned before descendant transactions.
+	pub fn select_packages(&self, max_size: usize, max_sigops: usize) -> Vec<IndexedTransaction> {
+		self.select_package_entries(max_size, max_sigops)
+			.into_iter()
+			.map(|entry| IndexedTransaction::new(entry.hash.clone(), entry.transaction.clone()))
+			.collect()
+	}
+
+	/// Same as `select_packages`, but returns memory pool entries.
+	///
+	/// Ancestor packages are computed once. When package is selected, packages of all its
+	/// in-pool descendants are updated (like the modified set of Bitcoin Core miner), so every
+	/// selection step only costs the number of descendants of selected transactions.
+	pub fn select_package_entries(&self, max_size: usize, max_sigops: usize) -> Vec<&Entry> {
+		let sigops: HashMap<H256, usize> = self.storage.by_hash.values()
+			.map(|entry| (entry.hash.clone(), transaction_sigops(&entry.transaction, self, false)))
+			.collect();
+
+		let mut packages: HashMap<H256, AncestorPackage> = HashMap::with_capacity(self.storage.by_hash.len());
+		let mut descendants: HashMap<H256, Vec<H256>> = HashMap::new();
+		for entry in self.storage.by_hash.values() {
+			let mut package = AncestorPackage {
+				fee: entry.miner_fee as i64 + entry.miner_virtual_fee,
+				size: entry.size,
+				sigops: sigops[&entry.hash],
+			};
+			for ancestor in entry.ancestors.iter().filter_map(|hash| self.storage.by_hash.get(hash)) {
+				package.fee += ancestor.miner_fee as i64 + ancestor.miner_virtual_fee;
+				package.size += ancestor.size;
+				package.sigops += sigops[&ancestor.hash];
+				descendants.entry(ancestor.hash.clone()).or_insert_with(Vec::new).push(entry.hash.clone());
+			}
+			packages.insert(entry.hash.clone(), package);
+		}
+
+		// every entry has single actual score in the queue. When package is updated, new score is
+		// pushed to the queue, making the previous one outdated
+		let mut queue: BinaryHeap<ByAncestorScoreOrderedEntry> = self.storage.by_hash.values()
+			.map(|entry| ByAncestorScoreOrderedEntry::new(entry, &packages[&entry.hash]))
+			.collect();
+		let mut selected: HashSet<H256> = HashSet::new();
+		let mut result = Vec::new();
+		let mut size = 0;
+		let mut sigops_count = 0;
+		while let Some(score) = queue.pop() {
+			let package = packages[&score.hash];
+			if selected.contains(&score.hash) || package.fee != score.package_fee || package.size != score.package_size {
+				continue;
+			}
+
+			if size + package.size > max_size || sigops_count + package.sigops > max_sigops {
+				continue;
+			}
+
+			// every in-pool ancestor has less in-pool ancestors than its descendant
+			let entry = &self.storage.by_hash[&score.hash];
+			let mut package_entries: Vec<&Entry> = entry.ancestors.iter()
+				.filter(|hash| !selected.contains(*hash))
+				.filter_map(|hash| self.storage.by_hash.get(hash))
+				.chain(::std::iter::once(entry))
+				.collect();
+			package_entries.sort_by_key(|e| e.ancestors.len());
+			for e in &package_entries {
+				selected.insert(e.hash.clone());
+			}
+
+			// remove selected transactions from ancestor packages of their descendants
+			for e in &package_entries {
+				let e_fee = e.miner_fee as i64 + e.miner_virtual_fee;
+				let e_sigops = sigops[&e.hash];
+				for descendant in descendants.get(&e.hash).into_iter().flat_map(|d| d.iter()) {
+					if selected.contains(descendant) {
+						continue;
+					}
+
+					let descendant_package = packages.get_mut(descendant).expect("descendants are in pool; qed");
+					descendant_package.fee -= e_fee;
+					descendant_package.size -= e.size;
+					descendant_package.sigops -= e_sigops;
+					queue.push(ByAncestorScoreOrderedEntry::new(&self.storage.by_hash[descendant], descendant_package));
+				}
+			}
+
+			size += package.size;
+			sigops_count += package.sigops;
+			result.extend(package_entries);
+		}
+
+		result
+	}
+
 	/// Set miner virtual fee for transaction
 	pub fn set_virtual_fee(&mut self, h: &H256, virtual_fee: i64) {
 		self.storage.set_virtual_fee(h, virtual_fee)
 	}
 
+	/// Adds virtual fee delta to the transaction, so that it is treated as if it has paid more (or less).
+	/// Delta is remembered even if transaction is not (yet) in the pool, until it is mined or dropped.
+	pub fn prioritise_transaction(&mut self, h: &H256, fee_delta: i64) {
+		let virtual_fee = {
+			let virtual_fee = self.fee_deltas.entry(h.clone()).or_insert(0);
+			*virtual_fee = virtual_fee.saturating_add(fee_delta);
+			*virtual_fee
+		};
+		if virtual_fee == 0 {
+			self.fee_deltas.remove(h);
+		}
+
+		self.storage.set_virtual_fee(h, virtual_fee);
+	}
+
+	/// Sets height of the best block. Transactions, inserted after this call, are considered
+	/// to enter memory pool at this height.
+	pub fn set_best_block_height(&mut self, height: u32) {
+		self.best_block_height = height;
+	}
+
+	/// Updates priority estimator with transactions of the new best block. `priorities` are coin-age
+	/// priorities of block transactions. Transactions, that have never entered memory pool, are ignored,
+	/// so this must be called before confirmed transactions are removed from the pool.
+	pub fn process_block_priorities(&mut self, height: u32, priorities: &[(H256, f64)]) {
+		let confirmed: Vec<_> = priorities.iter()
+			.filter_map(|&(ref hash, priority)| self.storage.get_by_hash(hash)
+				.map(|entry| (priority, height.saturating_sub(entry.height))))
+			.collect();
+		self.priority_estimator.process_block(&confirmed);
+	}
+
+	/// Returns the lowest coin-age priority, transactions with which are likely to be confirmed
+	/// within `target` blocks (`INSUFFICIENT_PRIORITY_DATA` if there's not enough data).
+	pub fn estimate_priority(&self, target: u32) -> f64 {
+		self.priority_estimator.estimate_priority(target)
+	}
+
+	/// Returns virtual fee delta of the transaction.
+	pub fn fee_delta(&self, h: &H256) -> i64 {
+		self.fee_deltas.get(h).cloned().unwrap_or(0)
+	}
+
 	/// Get transaction by hash
 	pub fn get(&self, hash: &H256) -> Option<&Transaction> {
 		self.storage.get_by_hash(hash).map(|entry| &entry.transaction)
@@ -756,6 +1078,7 @@ impl MemoryPool {
 		let size = self.get_transaction_size(&t.raw);
 		let storage_index = self.get_storage_index();
 		let miner_fee = fc.calculate(self, &t.raw);
+		let miner_virtual_fee = self.fee_delta(&t.hash);
 
 		// do not accept any transactions that have negative OR zero fee
 		if miner_fee == 0 {
@@ -767,13 +1090,14 @@ impl MemoryPool {
 			hash: t.hash,
 			ancestors: ancestors,
 			storage_index: storage_index,
+			height: self.best_block_height,
 			size: size,
 			miner_fee: miner_fee,
-			miner_virtual_fee: 0,
+			miner_virtual_fee: miner_virtual_fee,
 			// following fields are also updated when inserted to storage
 			package_size: size,
 			package_miner_fee: miner_fee,
-			package_miner_virtual_fee: 0,
+			package_miner_virtual_fee: miner_virtual_fee,
 		})
 	}
 
@@ -875,10 +1199,14 @@ impl<'a> Iterator for MemoryPoolIterator<'a> {
 pub mod tests {
 	extern crate test_data;
 
-	use chain::{Transaction, OutPoint};
+	use chain::{Transaction, OutPoint, IndexedTransaction};
 	use heapsize::HeapSizeOf;
-	use fee::NonZeroFeeCalculator;
-	use super::{MemoryPool, OrderingStrategy, DoubleSpendCheckResult};
+	use ser::Serializable;
+	use std::collections::HashSet;
+	use primitives::hash::H256;
+	use fee::{NonZeroFeeCalculator, MemoryPoolFeeCalculator};
+	use priority_estimator::INSUFFICIENT_PRIORITY_DATA;
+	use super::{MemoryPool, OrderingStrategy, DoubleSpendCheckResult, RbfError, MAX_BIP125_RBF_SEQUENCE};
 	use self::test_data::{ChainBuilder, TransactionBuilder};
 
 	fn to_memory_pool(chain: &mut ChainBuilder) -> MemoryPool {
@@ -1114,6 +1442,205 @@ pub mod tests {
 		assert_eq!(transactions[3], chain.at(1).into());
 	}
 
+	#[test]
+	fn test_memory_pool_prioritise_transaction() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10).store(chain)
+			.set_output(40).store(chain)
+			.set_output(30).store(chain);
+
+		// delta is remembered before transaction is inserted to the pool
+		let mut pool = MemoryPool::new();
+		pool.prioritise_transaction(&chain.hash(0), 50);
+		pool.prioritise_transaction(&chain.hash(0), 50);
+		assert_eq!(pool.fee_delta(&chain.hash(0)), 100);
+		pool.insert_verified(chain.at(0).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(1).into(), &NonZeroFeeCalculator);
+		pool.insert_verified(chain.at(2).into(), &NonZeroFeeCalculator);
+		assert_eq!(pool.read_with_strategy(OrderingStrategy::ByTransactionScore), Some(chain.hash(0)));
+
+		// delta is applied to in-pool transaction
+		pool.prioritise_transaction(&chain.hash(2), 200);
+		assert_eq!(pool.read_with_strategy(OrderingStrategy::ByTransactionScore), Some(chain.hash(2)));
+
+		// delta is forgotten when transaction is mined
+		pool.remove_by_hash(&chain.hash(2));
+		assert_eq!(pool.fee_delta(&chain.hash(2)), 0);
+		assert_eq!(pool.fee_delta(&chain.hash(0)), 100);
+	}
+
+	#[test]
+	fn test_memory_pool_estimate_priority() {
+		let mut pool = MemoryPool::new();
+		assert_eq!(pool.estimate_priority(10), INSUFFICIENT_PRIORITY_DATA);
+
+		// transactions enter memory pool at height 10
+		pool.set_best_block_height(10);
+		let transactions: Vec<IndexedTransaction> = (0..100)
+			.map(|index| TransactionBuilder::with_output(index + 1).into())
+			.collect();
+		for transaction in &transactions {
+			pool.insert_verified(transaction.clone(), &NonZeroFeeCalculator);
+		}
+
+		// ...and are confirmed 10 blocks later
+		let priorities: Vec<_> = transactions.iter().map(|tx| (tx.hash.clone(), 1e8)).collect();
+		pool.process_block_priorities(20, &priorities);
+
+		assert_eq!(pool.estimate_priority(5), INSUFFICIENT_PRIORITY_DATA);
+		let priority = pool.estimate_priority(10);
+		assert!(priority > 9e7 && priority < 1.1e8);
+	}
+
+	struct OutputsSumFeeCalculator;
+
+	impl MemoryPoolFeeCalculator for OutputsSumFeeCalculator {
+		fn calculate(&self, _: &MemoryPool, tx: &Transaction) -> u64 {
+			tx.outputs.iter().map(|output| output.value).sum()
+		}
+	}
+
+	fn replaceable_tx(output: u64) -> Transaction {
+		let mut tx: Transaction = TransactionBuilder::with_default_input(0).set_output(output).into();
+		tx.inputs[0].sequence = MAX_BIP125_RBF_SEQUENCE;
+		tx
+	}
+
+	#[test]
+	fn test_memory_pool_accept_replacement() {
+		let original = replaceable_tx(10);
+		let child: Transaction = TransactionBuilder::with_input(&original, 0).set_output(20).into();
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(original.clone().into(), &OutputsSumFeeCalculator);
+		pool.insert_verified(child.clone().into(), &OutputsSumFeeCalculator);
+
+		// replacement pays more than both original transaction and its descendant
+		let replacement = replaceable_tx(100);
+		match pool.check_double_spend(&replacement) {
+			DoubleSpendCheckResult::ReplaceableDoubleSpend(set) => assert_eq!(set.dependent_spends.len(), 2),
+			_ => panic!("unexpected"),
+		}
+		let replaced: HashSet<H256> = pool.accept_replacement(replacement.clone().into(), &OutputsSumFeeCalculator)
+			.unwrap().into_iter().collect();
+		let expected: HashSet<H256> = vec![original.hash(), child.hash()].into_iter().collect();
+		assert_eq!(replaced, expected);
+		assert_eq!(pool.get_transactions_ids(), vec![replacement.hash()]);
+
+		// transaction without conflicts is simply inserted
+		let independent: Transaction = TransactionBuilder::with_default_input(1).set_output(10).into();
+		assert_eq!(pool.accept_replacement(independent.clone().into(), &OutputsSumFeeCalculator), Ok(vec![]));
+		assert!(pool.contains(&independent.hash()));
+	}
+
+	#[test]
+	fn test_memory_pool_accept_replacement_rejected() {
+		let original = replaceable_tx(10);
+		let child: Transaction = TransactionBuilder::with_input(&original, 0).set_output(20).into();
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(original.clone().into(), &OutputsSumFeeCalculator);
+		pool.insert_verified(child.clone().into(), &OutputsSumFeeCalculator);
+
+		// replacement has better fee rate than original transaction, but pays less than original + child
+		assert_eq!(pool.accept_replacement(replaceable_tx(25).into(), &OutputsSumFeeCalculator),
+			Err(RbfError::InsufficientFee(25, 30)));
+		// replacement has worse fee rate than original transaction
+		assert_eq!(pool.accept_replacement(replaceable_tx(5).into(), &OutputsSumFeeCalculator),
+			Err(RbfError::InsufficientFeeRate(original.hash())));
+		// replacement spends output of replaced transaction
+		let spending_child: Transaction = TransactionBuilder::with_default_input(0).add_input(&original, 1).set_output(100).into();
+		assert_eq!(pool.accept_replacement(spending_child.into(), &OutputsSumFeeCalculator),
+			Err(RbfError::SpendsConflictingTransaction(original.hash())));
+		assert_eq!(pool.information().transactions_count, 2);
+
+		// final transaction can't be replaced
+		let final_tx: Transaction = TransactionBuilder::with_default_input(1).set_output(10).into();
+		pool.insert_verified(final_tx.clone().into(), &OutputsSumFeeCalculator);
+		let replacement: Transaction = TransactionBuilder::with_default_input(1).set_output(100).into();
+		assert_eq!(pool.accept_replacement(replacement.into(), &OutputsSumFeeCalculator),
+			Err(RbfError::NotReplaceable(final_tx.hash())));
+	}
+
+	#[test]
+	fn test_memory_pool_accept_replacement_uses_modified_fees() {
+		let original = replaceable_tx(10);
+		let replacement = replaceable_tx(25);
+		let mut pool = MemoryPool::new();
+		pool.insert_verified(original.clone().into(), &OutputsSumFeeCalculator);
+
+		// prioritised original transaction pays more than replacement
+		pool.prioritise_transaction(&original.hash(), 20);
+		assert_eq!(pool.accept_replacement(replacement.clone().into(), &OutputsSumFeeCalculator),
+			Err(RbfError::InsufficientFeeRate(original.hash())));
+
+		// prioritised replacement pays more than prioritised original transaction
+		pool.prioritise_transaction(&replacement.hash(), 10);
+		assert_eq!(pool.accept_replacement(replacement.clone().into(), &OutputsSumFeeCalculator),
+			Ok(vec![original.hash()]));
+	}
+
+	#[test]
+	fn test_memory_pool_remove_expired() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_output(10).set_expiry_height(100).store(chain)	// transaction0
+			.into_input(0).set_output(20).store(chain)							// transaction0 -> transaction1
+			.reset().set_output(30).store(chain);								// transaction2
+		let mut pool = to_memory_pool(chain);
+		assert_eq!(pool.information().transactions_count, 3);
+
+		// nothing is expired before height 100
+		assert_eq!(pool.remove_expired(99), vec![]);
+		assert_eq!(pool.information().transactions_count, 3);
+
+		// transaction0 is expired at height 100 => it is removed with its descendant transaction1
+		let removed: HashSet<H256> = pool.remove_expired(100).into_iter().collect();
+		let expected: HashSet<H256> = vec![chain.hash(0), chain.hash(1)].into_iter().collect();
+		assert_eq!(removed, expected);
+
+		// transaction2 with zero expiry height stays in the pool
+		assert_eq!(pool.get_transactions_ids(), vec![chain.hash(2)]);
+		assert_eq!(pool.remove_expired(::std::u32::MAX), vec![]);
+	}
+
+	#[test]
+	fn test_memory_pool_select_packages() {
+		let chain = &mut ChainBuilder::new();
+		// all transactions of same size
+		TransactionBuilder::with_default_input(0).set_output(10).store(chain)	// transaction0
+			.into_input(0).set_output(1000).store(chain)						// transaction0 -> transaction1
+			.set_default_input(1).set_output(500).store(chain);				// transaction2
+		let pool = to_memory_pool(chain);
+		let size = chain.at(0).serialized_size();
+
+		// low-fee transaction0 is selected before transaction2, because high-fee transaction1 pays for it
+		let expected: Vec<IndexedTransaction> = vec![chain.at(0).into(), chain.at(1).into(), chain.at(2).into()];
+		assert_eq!(pool.select_packages(3 * size, 1000), expected);
+
+		// transaction2 doesn't fit after the { transaction0, transaction1 } package
+		let expected: Vec<IndexedTransaction> = vec![chain.at(0).into(), chain.at(1).into()];
+		assert_eq!(pool.select_packages(2 * size, 1000), expected);
+
+		// { transaction0, transaction1 } package doesn't fit, transaction0 alone has the lowest fee rate
+		let expected: Vec<IndexedTransaction> = vec![chain.at(2).into()];
+		assert_eq!(pool.select_packages(size, 1000), expected);
+	}
+
+	#[test]
+	fn test_memory_pool_select_packages_updates_packages_of_descendants() {
+		let chain = &mut ChainBuilder::new();
+		TransactionBuilder::with_default_input(0).set_output(10).add_output(10).store(chain)	// transaction0
+			.into_input(0).set_output(1000).store(chain)									// transaction0 -> transaction1
+			.set_input(&chain.at(0), 1).set_output(500).store(chain);						// transaction0 -> transaction2
+		TransactionBuilder::with_default_input(1).set_output(100).store(chain);				// transaction3
+		let mut pool = to_memory_pool(chain);
+		pool.prioritise_transaction(&chain.hash(0), -100_000_000);
+		pool.prioritise_transaction(&chain.hash(1), 1_000_000_000);
+
+		// transaction1 pays for transaction0 => after { transaction0, transaction1 } package is selected,
+		// package of transaction2 only includes transaction2 itself, which has better fee rate than transaction3
+		let expected: Vec<IndexedTransaction> = vec![chain.at(0).into(), chain.at(1).into(), chain.at(2).into(), chain.at(3).into()];
+		assert_eq!(pool.select_packages(1_000_000, 1000), expected);
+	}
+
 	#[test]
 	fn test_memory_pool_package_score_ordering_strategy() {
 		let chain = &mut ChainBuilder::new();
@@ -1330,25 +1857,24 @@ pub mod tests {
 		match pool.check_double_spend(&chain.at(3)) {
 			DoubleSpendCheckResult::NonFinalDoubleSpend(set) => {
 				assert_eq!(set.double_spends.len(), 1);
-				assert!(set.double_spends.contains(&chain.at(1).inputs[0].previous_output.clone().into()));
+				assert!(set.double_spends.contains(&chain.at(1).inputs[0].previous_output));
 				assert_eq!(set.dependent_spends.len(), 2);
 				assert!(set.dependent_spends.contains(&OutPoint {
 					hash: chain.at(1).hash(),
 					index: 0,
-				}.into()));
+				}));
 				assert!(set.dependent_spends.contains(&OutPoint {
 					hash: chain.at(2).hash(),
 					index: 0,
-				}.into()));
+				}));
 			},
 			_ => panic!("unexpected"),
 		}
 		// when output is spent by final transaction
 		match pool.check_double_spend(&chain.at(5)) {
-			DoubleSpendCheckResult::DoubleSpend(inpool_hash, prev_hash, prev_index) => {
+			DoubleSpendCheckResult::DoubleSpend(inpool_hash, prevout) => {
 				assert_eq!(inpool_hash, chain.at(4).hash());
-				assert_eq!(prev_hash, chain.at(0).hash());
-				assert_eq!(prev_index, 1);
+				assert_eq!(prevout, OutPoint::new(chain.at(0).hash(), 1));
 			},
 			_ => panic!("unexpected"),
 		}
@@ -1374,20 +1900,20 @@ pub mod tests {
 		match pool.check_double_spend(&chain.at(2)) {
 			DoubleSpendCheckResult::NonFinalDoubleSpend(set) => {
 				assert_eq!(set.double_spends.len(), 1);
-				assert!(set.double_spends.contains(&chain.at(1).inputs[0].previous_output.clone().into()));
+				assert!(set.double_spends.contains(&chain.at(1).inputs[0].previous_output));
 				assert_eq!(set.dependent_spends.len(), 3);
 				assert!(set.dependent_spends.contains(&OutPoint {
 					hash: chain.at(1).hash(),
 					index: 0,
-				}.into()));
+				}));
 				assert!(set.dependent_spends.contains(&OutPoint {
 					hash: chain.at(1).hash(),
 					index: 1,
-				}.into()));
+				}));
 				assert!(set.dependent_spends.contains(&OutPoint {
 					hash: chain.at(1).hash(),
 					index: 2,
-				}.into()));
+				}));
 			},
 			_ => panic!("unexpected"),
 		}
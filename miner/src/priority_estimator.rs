@@ -0,0 +1,187 @@
+//! Coin-age priority estimator
+//!
+//! Tracks priorities of transactions at the moment they're confirmed, grouping them into
+//! exponentially spaced priority buckets. For every bucket it keeps (exponentially decaying) number
+//! of transactions which were confirmed within given number of blocks. This data is then used to find
+//! the lowest priority, at which (legacy, free) transaction is likely to be confirmed within given
+//! number of blocks.
+
+/// Returned by `PriorityEstimator::estimate_priority` when there's not enough data to make an estimate.
+pub const INSUFFICIENT_PRIORITY_DATA: f64 = -1.0;
+
+/// Maximal confirmation target that is tracked by the estimator.
+pub const MAX_PRIORITY_CONFIRMATIONS: u32 = 25;
+
+/// Lower boundary of the first priority bucket.
+const MIN_BUCKET_PRIORITY: f64 = 10.0;
+/// Upper boundary of the last finite priority bucket.
+const MAX_BUCKET_PRIORITY: f64 = 1e16;
+/// Ratio between boundaries of adjacent buckets.
+const BUCKET_SPACING: f64 = 2.0;
+/// Every block, historical data is multiplied by this factor.
+const DECAY: f64 = 0.998;
+/// Minimal share of transactions in the bucket range, confirmed within target blocks.
+const MIN_SUCCESS_RATE: f64 = 0.95;
+/// Minimal (decayed) number of transactions in the bucket range required to make estimate.
+const SUFFICIENT_DATA_POINTS: f64 = 100.0;
+
+/// Computes coin-age priority of transaction: sum(input value * input age) / transaction size.
+pub fn coin_age_priority(inputs: &[(u64, u32)], transaction_size: usize) -> f64 {
+	if transaction_size == 0 {
+		return 0.0;
+	}
+
+	let coin_age = inputs.iter().fold(0f64, |acc, &(value, age)| acc + value as f64 * age as f64);
+	coin_age / transaction_size as f64
+}
+
+/// Priority estimator.
+#[derive(Debug)]
+pub struct PriorityEstimator {
+	/// Upper boundaries of priority buckets.
+	buckets: Vec<f64>,
+	/// Decayed number of transactions in every bucket.
+	transactions: Vec<f64>,
+	/// Decayed sum of priorities of transactions in every bucket.
+	priorities: Vec<f64>,
+	/// confirmed[target - 1][bucket] is a decayed number of transactions from bucket, confirmed within target blocks.
+	confirmed: Vec<Vec<f64>>,
+}
+
+impl Default for PriorityEstimator {
+	fn default() -> Self {
+		PriorityEstimator::new()
+	}
+}
+
+impl PriorityEstimator {
+	/// Creates estimator without any historical data.
+	pub fn new() -> Self {
+		let mut buckets = Vec::new();
+		let mut boundary = MIN_BUCKET_PRIORITY;
+		while boundary <= MAX_BUCKET_PRIORITY {
+			buckets.push(boundary);
+			boundary *= BUCKET_SPACING;
+		}
+		buckets.push(::std::f64::INFINITY);
+
+		let buckets_count = buckets.len();
+		PriorityEstimator {
+			buckets: buckets,
+			transactions: vec![0f64; buckets_count],
+			priorities: vec![0f64; buckets_count],
+			confirmed: vec![vec![0f64; buckets_count]; MAX_PRIORITY_CONFIRMATIONS as usize],
+		}
+	}
+
+	/// Processes new block. Every confirmed transaction is passed as its priority and
+	/// the number of blocks it has waited for confirmation (at least 1).
+	pub fn process_block(&mut self, confirmed: &[(f64, u32)]) {
+		self.decay();
+		for &(priority, blocks_to_confirm) in confirmed {
+			self.record(priority, blocks_to_confirm);
+		}
+	}
+
+	/// Returns the lowest priority, transactions with which are likely to be confirmed within
+	/// `target` blocks. Returns `INSUFFICIENT_PRIORITY_DATA` if there's not enough data for the estimate.
+	pub fn estimate_priority(&self, target: u32) -> f64 {
+		if target == 0 || target > MAX_PRIORITY_CONFIRMATIONS {
+			return INSUFFICIENT_PRIORITY_DATA;
+		}
+
+		let confirmed = &self.confirmed[target as usize - 1];
+		let mut estimate = INSUFFICIENT_PRIORITY_DATA;
+		let (mut range_transactions, mut range_priorities, mut range_confirmed) = (0f64, 0f64, 0f64);
+
+		// start from the highest priorities and go down while transactions are still confirmed in time
+		for bucket in (0..self.buckets.len()).rev() {
+			range_transactions += self.transactions[bucket];
+			range_priorities += self.priorities[bucket];
+			range_confirmed += confirmed[bucket];
+
+			if range_transactions < SUFFICIENT_DATA_POINTS {
+				continue;
+			}
+
+			if range_confirmed / range_transactions < MIN_SUCCESS_RATE {
+				break;
+			}
+
+			estimate = range_priorities / range_transactions;
+			range_transactions = 0f64;
+			range_priorities = 0f64;
+			range_confirmed = 0f64;
+		}
+
+		estimate
+	}
+
+	fn decay(&mut self) {
+		for value in self.transactions.iter_mut().chain(self.priorities.iter_mut()) {
+			*value *= DECAY;
+		}
+		for value in self.confirmed.iter_mut().flat_map(|confirmed| confirmed.iter_mut()) {
+			*value *= DECAY;
+		}
+	}
+
+	fn record(&mut self, priority: f64, blocks_to_confirm: u32) {
+		let bucket = self.buckets.iter().position(|boundary| priority <= *boundary)
+			.expect("last bucket boundary is infinity; qed");
+		self.transactions[bucket] += 1f64;
+		self.priorities[bucket] += priority;
+
+		let blocks_to_confirm = ::std::cmp::max(blocks_to_confirm, 1);
+		for target in blocks_to_confirm..MAX_PRIORITY_CONFIRMATIONS + 1 {
+			self.confirmed[target as usize - 1][bucket] += 1f64;
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{PriorityEstimator, coin_age_priority, INSUFFICIENT_PRIORITY_DATA, MAX_PRIORITY_CONFIRMATIONS};
+
+	#[test]
+	fn coin_age_priority_works() {
+		assert_eq!(coin_age_priority(&[], 100), 0.0);
+		assert_eq!(coin_age_priority(&[(100_000_000, 10)], 0), 0.0);
+		assert_eq!(coin_age_priority(&[(100_000_000, 10), (50_000_000, 2)], 250), 4_400_000.0);
+	}
+
+	#[test]
+	fn priority_estimator_insufficient_data() {
+		let mut estimator = PriorityEstimator::new();
+		assert_eq!(estimator.estimate_priority(1), INSUFFICIENT_PRIORITY_DATA);
+
+		// not enough transactions to make estimate
+		estimator.process_block(&[(1e8, 1); 10]);
+		assert_eq!(estimator.estimate_priority(1), INSUFFICIENT_PRIORITY_DATA);
+
+		// unsupported targets
+		estimator.process_block(&[(1e8, 1); 200]);
+		assert_eq!(estimator.estimate_priority(0), INSUFFICIENT_PRIORITY_DATA);
+		assert_eq!(estimator.estimate_priority(MAX_PRIORITY_CONFIRMATIONS + 1), INSUFFICIENT_PRIORITY_DATA);
+	}
+
+	#[test]
+	fn priority_estimator_synthetic_data() {
+		let mut estimator = PriorityEstimator::new();
+		// high priority transactions are confirmed in the next block
+		// low priority transactions are confirmed after 10 blocks
+		for _ in 0..10 {
+			estimator.process_block(&[(1e8, 1); 30]);
+			estimator.process_block(&[(100.0, 10); 30]);
+		}
+
+		let next_block_priority = estimator.estimate_priority(1);
+		assert!(next_block_priority > 9e7 && next_block_priority < 1.1e8);
+
+		let ten_blocks_priority = estimator.estimate_priority(10);
+		assert!(ten_blocks_priority > 90.0 && ten_blocks_priority < 110.0);
+
+		// no transactions have been confirmed within 5 blocks at low priority
+		assert_eq!(estimator.estimate_priority(5), next_block_priority);
+	}
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use chain::{OutPoint, TransactionOutput};
+use primitives::hash::H256;
+use storage::TransactionOutputProvider;
+
+/// Cache of outputs, spent by memory pool transactions.
+///
+/// Outputs are resolved (from the UTXO set and memory pool) only once and then reused when
+/// transactions fees and sigops are recomputed during block template construction.
+/// Cache is bound to the best block it has been filled at => it is cleared when new block is connected.
+#[derive(Debug, Default)]
+pub struct SpentOutputCache {
+	inner: Mutex<SpentOutputCacheInner>,
+}
+
+#[derive(Debug, Default)]
+struct SpentOutputCacheInner {
+	/// Best block hash at the time when outputs were cached.
+	best_block: H256,
+	/// Cached outputs.
+	outputs: HashMap<OutPoint, TransactionOutput>,
+}
+
+/// Transaction output provider, which reads outputs from the cache, falling back to the backend.
+pub struct CachedTransactionOutputProvider<'a> {
+	backend: &'a TransactionOutputProvider,
+	cache: &'a SpentOutputCache,
+	backend_lookups: AtomicUsize,
+}
+
+impl SpentOutputCache {
+	/// Creates empty cache.
+	pub fn new() -> Self {
+		SpentOutputCache::default()
+	}
+
+	/// Returns output provider that is using this cache on top of the backend.
+	/// If best block has changed since the cache has been filled, cache is cleared.
+	pub fn provider<'a>(&'a self, backend: &'a TransactionOutputProvider, best_block: &H256) -> CachedTransactionOutputProvider<'a> {
+		{
+			let mut inner = self.inner.lock().expect("cache lock is never poisoned");
+			if inner.best_block != *best_block {
+				inner.best_block = best_block.clone();
+				inner.outputs.clear();
+			}
+		}
+
+		CachedTransactionOutputProvider {
+			backend: backend,
+			cache: self,
+			backend_lookups: AtomicUsize::new(0),
+		}
+	}
+
+	/// Returns number of cached outputs.
+	pub fn len(&self) -> usize {
+		self.inner.lock().expect("cache lock is never poisoned").outputs.len()
+	}
+
+	/// Returns true if cache is empty.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+}
+
+impl<'a> CachedTransactionOutputProvider<'a> {
+	/// Returns number of outputs, read from the backend by this provider.
+	pub fn backend_lookups(&self) -> usize {
+		self.backend_lookups.load(Ordering::Relaxed)
+	}
+}
+
+impl<'a> TransactionOutputProvider for CachedTransactionOutputProvider<'a> {
+	fn transaction_output(&self, outpoint: &OutPoint, transaction_index: usize) -> Option<TransactionOutput> {
+		if let Some(output) = self.cache.inner.lock().expect("cache lock is never poisoned").outputs.get(outpoint) {
+			return Some(output.clone());
+		}
+
+		self.backend_lookups.fetch_add(1, Ordering::Relaxed);
+		let output = self.backend.transaction_output(outpoint, transaction_index);
+		if let Some(ref output) = output {
+			self.cache.inner.lock().expect("cache lock is never poisoned").outputs.insert(outpoint.clone(), output.clone());
+		}
+		output
+	}
+
+	fn is_spent(&self, outpoint: &OutPoint) -> bool {
+		// spent status could change at any time => never cache it
+		self.backend.is_spent(outpoint)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use std::sync::Arc;
+	use db::BlockChainDatabase;
+	use chain::Transaction;
+	use primitives::hash::H256;
+	use storage::AsSubstore;
+	use fee::transaction_fee;
+	use super::SpentOutputCache;
+
+	#[test]
+	fn spent_output_cache_fee_computation() {
+		let b0 = test_data::block_builder().header().nonce(1.into()).build()
+			.transaction()
+				.output().value(1_000_000).build()
+				.output().value(2_000_000).build()
+				.build()
+			.build();
+		let tx0_hash = b0.transactions[0].hash();
+		let tx1: Transaction = test_data::TransactionBuilder::with_output(2_500_000)
+			.add_input(&b0.transactions[0], 0)
+			.add_input(&b0.transactions[0], 1)
+			.into();
+
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![b0.into()]));
+		let store = db.as_transaction_output_provider();
+		let expected_fee = transaction_fee(store, &tx1);
+		assert_eq!(expected_fee, 500_000);
+
+		// first computation fills the cache
+		let cache = SpentOutputCache::new();
+		let provider = cache.provider(store, &tx0_hash);
+		assert_eq!(transaction_fee(&provider, &tx1), expected_fee);
+		assert_eq!(provider.backend_lookups(), 2);
+		assert_eq!(cache.len(), 2);
+
+		// next computations are served from the cache
+		assert_eq!(transaction_fee(&provider, &tx1), expected_fee);
+		let provider = cache.provider(store, &tx0_hash);
+		assert_eq!(transaction_fee(&provider, &tx1), expected_fee);
+		assert_eq!(provider.backend_lookups(), 0);
+
+		// new block is connected => cache is cleared
+		let provider = cache.provider(store, &H256::from(1));
+		assert!(cache.is_empty());
+		assert_eq!(transaction_fee(&provider, &tx1), expected_fee);
+		assert_eq!(provider.backend_lookups(), 2);
+	}
+}
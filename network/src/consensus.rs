@@ -10,6 +10,91 @@ lazy_static! {
 		.expect("hardcoded value should load without errors");
 }
 
+pub use chain::constants::{MAX_BLOCK_SIZE, MIN_TRANSACTION_SIZE, MAX_BLOCK_TRANSACTIONS};
+
+/// Ratio of pre-Blossom to post-Blossom target block spacing (ZIP-208).
+pub const BLOSSOM_POW_TARGET_SPACING_RATIO: u32 = 2;
+
+/// Network upgrade, changing consensus rules at given activation height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetworkUpgrade {
+	/// Overwinter (ZIP-201, ZIP-202, ZIP-203, ZIP-143).
+	Overwinter,
+	/// Sapling (ZIP-205, ZIP-243).
+	Sapling,
+	/// Blossom (ZIP-206, ZIP-208).
+	Blossom,
+	/// Heartwood (ZIP-213, ZIP-221).
+	Heartwood,
+	/// Canopy (ZIP-207, ZIP-211, ZIP-212, ZIP-214, ZIP-215).
+	Canopy,
+}
+
+/// All network upgrades, in activation order.
+pub const NETWORK_UPGRADES: [NetworkUpgrade; 5] = [
+	NetworkUpgrade::Overwinter,
+	NetworkUpgrade::Sapling,
+	NetworkUpgrade::Blossom,
+	NetworkUpgrade::Heartwood,
+	NetworkUpgrade::Canopy,
+];
+
+impl NetworkUpgrade {
+	/// Consensus branch id of the network upgrade (ZIP-200).
+	pub fn branch_id(&self) -> u32 {
+		match *self {
+			NetworkUpgrade::Overwinter => 0x5ba81b19,
+			NetworkUpgrade::Sapling => 0x76b809bb,
+			NetworkUpgrade::Blossom => 0x2bb40e60,
+			NetworkUpgrade::Heartwood => 0xf5b9230b,
+			NetworkUpgrade::Canopy => 0xe9ff75a6,
+		}
+	}
+
+	/// Network upgrade with given consensus branch id.
+	pub fn from_branch_id(branch_id: u32) -> Option<NetworkUpgrade> {
+		NETWORK_UPGRADES.iter().cloned().find(|upgrade| upgrade.branch_id() == branch_id)
+	}
+}
+
+/// Funding stream, receiving fraction of block subsidy after Canopy activation (ZIP-207, ZIP-214).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FundingStream {
+	/// Electric Coin Company.
+	ElectricCoinCompany,
+	/// Zcash Foundation.
+	ZcashFoundation,
+	/// Major Grants.
+	MajorGrants,
+}
+
+/// All funding streams.
+pub const FUNDING_STREAMS: [FundingStream; 3] = [
+	FundingStream::ElectricCoinCompany,
+	FundingStream::ZcashFoundation,
+	FundingStream::MajorGrants,
+];
+
+impl FundingStream {
+	/// Name of the funding stream recipient.
+	pub fn recipient(&self) -> &'static str {
+		match *self {
+			FundingStream::ElectricCoinCompany => "Electric Coin Company",
+			FundingStream::ZcashFoundation => "Zcash Foundation",
+			FundingStream::MajorGrants => "Major Grants",
+		}
+	}
+
+	/// Percents of block subsidy, going to this funding stream.
+	pub fn percents(&self) -> u64 {
+		match *self {
+			FundingStream::ElectricCoinCompany => 7,
+			FundingStream::ZcashFoundation => 5,
+			FundingStream::MajorGrants => 8,
+		}
+	}
+}
+
 #[derive(Debug, Clone)]
 /// Parameters that influence chain consensus.
 pub struct ConsensusParams {
@@ -40,6 +125,18 @@ pub struct ConsensusParams {
 	/// Height of Sapling activation.
 	/// Details: https://zcash.readthedocs.io/en/latest/rtd_pages/nu_dev_guide.html#sapling
 	pub sapling_height: u32,
+	/// Height of Blossom activation.
+	/// Details: https://zips.z.cash/zip-0206
+	pub blossom_height: u32,
+	/// Height of Heartwood activation.
+	/// Details: https://zips.z.cash/zip-0250
+	pub heartwood_height: u32,
+	/// Height of Canopy activation.
+	/// Details: https://zips.z.cash/zip-0251
+	pub canopy_height: u32,
+	/// Whether transparent coinbase outputs could only be spent to the shielded pools.
+	/// Details: https://zips.z.cash/protocol/protocol.pdf#txnencodingandconsensus
+	pub coinbase_must_be_shielded: bool,
 
 	/// Interval (in blocks) to calculate average work.
 	pub pow_averaging_window: u32,
@@ -69,6 +166,9 @@ pub struct ConsensusParams {
 	/// https://z.cash/support/faq/#founders-reward
 	pub founders_addresses: Vec<Address>,
 
+	/// Maximal size of serialized block.
+	pub max_block_size: usize,
+
 	/// Equihash (N, K) parameters.
 	pub equihash_params: Option<(u32, u32)>,
 
@@ -151,6 +251,10 @@ impl ConsensusParams {
 
 				overwinter_height: 347500,
 				sapling_height: 419200,
+				blossom_height: 653600,
+				heartwood_height: 903000,
+				canopy_height: 1046400,
+				coinbase_must_be_shielded: true,
 
 				pow_averaging_window: 17,
 				pow_max_adjust_down: 32,
@@ -211,6 +315,8 @@ impl ConsensusParams {
 					"t3Pcm737EsVkGTbhsu2NekKtJeG92mvYyoN".into(),
 				],
 
+				max_block_size: MAX_BLOCK_SIZE,
+
 				equihash_params: Some((200, 9)),
 
 				joinsplit_verification_key: mainnet_pghr_verification_key(),
@@ -231,6 +337,10 @@ impl ConsensusParams {
 
 				overwinter_height: 207500,
 				sapling_height: 280000,
+				blossom_height: 584000,
+				heartwood_height: 903800,
+				canopy_height: 1028500,
+				coinbase_must_be_shielded: true,
 
 				pow_averaging_window: 17,
 				pow_max_adjust_down: 32,
@@ -291,6 +401,8 @@ impl ConsensusParams {
 					"t2UV2wr1PTaUiybpkV3FdSdGxUJeZdZztyt".into(),
 				],
 
+				max_block_size: MAX_BLOCK_SIZE,
+
 				equihash_params: Some((200, 9)),
 
 				joinsplit_verification_key: testnet_pghr_verification_key(),
@@ -310,8 +422,12 @@ impl ConsensusParams {
 				miner_confirmation_window: 144,
 				csv_deployment: None,
 
-				overwinter_height: ::std::u32::MAX,
-				sapling_height: ::std::u32::MAX,
+				overwinter_height: 1,
+				sapling_height: 1,
+				blossom_height: 1,
+				heartwood_height: 1,
+				canopy_height: 1,
+				coinbase_must_be_shielded: false,
 
 				pow_averaging_window: 17,
 				pow_max_adjust_down: 0,
@@ -325,6 +441,8 @@ impl ConsensusParams {
 					"t2FwcEhFdNXuFMv1tcYwaBJtYVtMj8b1uTg".into(),
 				],
 
+				max_block_size: MAX_BLOCK_SIZE,
+
 				equihash_params: Some((200, 9)),
 
 				joinsplit_verification_key: regtest_pghr_verification_key(),
@@ -345,6 +463,10 @@ impl ConsensusParams {
 
 				overwinter_height: ::std::u32::MAX,
 				sapling_height: ::std::u32::MAX,
+				blossom_height: ::std::u32::MAX,
+				heartwood_height: ::std::u32::MAX,
+				canopy_height: ::std::u32::MAX,
+				coinbase_must_be_shielded: false,
 
 				pow_averaging_window: 17,
 				pow_max_adjust_down: 0,
@@ -358,6 +480,8 @@ impl ConsensusParams {
 					"t2FwcEhFdNXuFMv1tcYwaBJtYVtMj8b1uTg".into(),
 				],
 
+				max_block_size: MAX_BLOCK_SIZE,
+
 				equihash_params: None,
 
 				joinsplit_verification_key: unitest_pghr_verification_key(),
@@ -390,7 +514,7 @@ impl ConsensusParams {
 	}
 
 	pub fn max_block_size(&self) -> usize {
-		2_000_000
+		self.max_block_size
 	}
 
 	pub fn max_block_sigops(&self) -> usize {
@@ -406,7 +530,7 @@ impl ConsensusParams {
 	}
 
 	pub fn max_transaction_size(&self, height: u32) -> usize {
-		if height >= self.sapling_height {
+		if self.is_sapling_active(height) {
 			2_000_000
 		} else {
 			100_000
@@ -417,12 +541,89 @@ impl ConsensusParams {
 		500_000_000
 	}
 
+	/// Height at which given network upgrade activates. None if upgrade is never activated on this network.
+	pub fn activation_height(&self, upgrade: NetworkUpgrade) -> Option<u32> {
+		let height = match upgrade {
+			NetworkUpgrade::Overwinter => self.overwinter_height,
+			NetworkUpgrade::Sapling => self.sapling_height,
+			NetworkUpgrade::Blossom => self.blossom_height,
+			NetworkUpgrade::Heartwood => self.heartwood_height,
+			NetworkUpgrade::Canopy => self.canopy_height,
+		};
+
+		match height {
+			::std::u32::MAX => None,
+			height => Some(height),
+		}
+	}
+
+	/// Override activation height of given network upgrade.
+	pub fn set_activation_height(&mut self, upgrade: NetworkUpgrade, height: u32) {
+		match upgrade {
+			NetworkUpgrade::Overwinter => self.overwinter_height = height,
+			NetworkUpgrade::Sapling => self.sapling_height = height,
+			NetworkUpgrade::Blossom => self.blossom_height = height,
+			NetworkUpgrade::Heartwood => self.heartwood_height = height,
+			NetworkUpgrade::Canopy => self.canopy_height = height,
+		}
+	}
+
+	/// Is given network upgrade active at given height?
+	pub fn is_active(&self, upgrade: NetworkUpgrade, height: u32) -> bool {
+		self.activation_height(upgrade).map(|activation_height| height >= activation_height).unwrap_or(false)
+	}
+
 	pub fn is_overwinter_active(&self, height: u32) -> bool {
-		height >= self.overwinter_height
+		self.is_active(NetworkUpgrade::Overwinter, height)
 	}
 
 	pub fn is_sapling_active(&self, height: u32) -> bool {
-		height >= self.sapling_height
+		self.is_active(NetworkUpgrade::Sapling, height)
+	}
+
+	pub fn is_canopy_active(&self, height: u32) -> bool {
+		self.is_active(NetworkUpgrade::Canopy, height)
+	}
+
+	pub fn is_blossom_active(&self, height: u32) -> bool {
+		self.is_active(NetworkUpgrade::Blossom, height)
+	}
+
+	pub fn is_heartwood_active(&self, height: u32) -> bool {
+		self.is_active(NetworkUpgrade::Heartwood, height)
+	}
+
+	/// Is Blossom activated before the first (pre-Blossom) subsidy halving?
+	fn is_blossom_before_first_halving(&self) -> bool {
+		self.blossom_height < self.subsidy_slow_start_interval / 2 + self.subsidy_halving_interval
+	}
+
+	/// Number of blocks between subsidy halvings after Blossom activation.
+	pub fn post_blossom_subsidy_halving_interval(&self) -> u32 {
+		self.subsidy_halving_interval * BLOSSOM_POW_TARGET_SPACING_RATIO
+	}
+
+	/// Number of subsidy halvings that have happened at given height (ZIP-208).
+	pub fn subsidy_halvings(&self, height: u32) -> u32 {
+		let slow_start_shift = self.subsidy_slow_start_interval / 2;
+		if !self.is_blossom_active(height) {
+			return height.saturating_sub(slow_start_shift) / self.subsidy_halving_interval;
+		}
+
+		let scaled_halvings = self.blossom_height.saturating_sub(slow_start_shift) as u64
+			* BLOSSOM_POW_TARGET_SPACING_RATIO as u64
+			+ (height - self.blossom_height) as u64;
+		(scaled_halvings / self.post_blossom_subsidy_halving_interval() as u64) as u32
+	}
+
+	/// Height of the first subsidy halving.
+	pub fn first_halving_height(&self) -> u32 {
+		let pre_blossom_height = self.subsidy_slow_start_interval / 2 + self.subsidy_halving_interval;
+		if !self.is_blossom_before_first_halving() {
+			return pre_blossom_height;
+		}
+
+		self.blossom_height + (pre_blossom_height - self.blossom_height) * BLOSSOM_POW_TARGET_SPACING_RATIO
 	}
 
 	/// Block subsidy (total block reward).
@@ -435,11 +636,15 @@ impl ConsensusParams {
 			reward /= self.subsidy_slow_start_interval as u64;
 			reward *= height as u64 + 1;
 		} else {
-			let halvings = (height - self.subsidy_slow_start_interval / 2) / self.subsidy_halving_interval;
+			let halvings = self.subsidy_halvings(height);
 			if halvings >= 64 {
 				return 0;
 			}
 
+			if self.is_blossom_active(height) {
+				reward /= BLOSSOM_POW_TARGET_SPACING_RATIO as u64;
+			}
+
 			reward >>= halvings as u64;
 		}
 
@@ -460,32 +665,55 @@ impl ConsensusParams {
 		self.block_reward(height) / 5
 	}
 
+	/// Height at which funding streams end (second halving).
+	pub fn funding_streams_end_height(&self) -> u32 {
+		if self.is_blossom_before_first_halving() {
+			self.first_halving_height() + self.post_blossom_subsidy_halving_interval()
+		} else {
+			self.first_halving_height() + self.subsidy_halving_interval
+		}
+	}
+
+	/// Funding streams and their values at given height.
+	pub fn funding_streams(&self, height: u32) -> Vec<(FundingStream, u64)> {
+		if !self.is_canopy_active(height) || height >= self.funding_streams_end_height() {
+			return Vec::new();
+		}
+
+		let block_reward = self.block_reward(height);
+		FUNDING_STREAMS.iter()
+			.map(|stream| (*stream, block_reward * stream.percents() / 100))
+			.collect()
+	}
+
 	/// Address (transparent) where founders reward goes at given height.
 	pub fn founder_address(&self, height: u32) -> Option<Address> {
-		let last_founder_reward_block_height = self.subsidy_halving_interval + self.subsidy_slow_start_interval / 2 - 1;
-		if height == 0 || height > last_founder_reward_block_height {
+		if height == 0 || height >= self.first_halving_height() {
 			return None;
 		}
 
+		// address change interval is computed using pre-Blossom heights, so post-Blossom height is adjusted (ZIP-208)
+		let pre_blossom_last_founder_reward_block_height = self.subsidy_halving_interval + self.subsidy_slow_start_interval / 2 - 1;
+		let adjusted_height = if self.is_blossom_active(height) {
+			self.blossom_height + (height - self.blossom_height) / BLOSSOM_POW_TARGET_SPACING_RATIO
+		} else {
+			height
+		};
+
 		let founders_len = self.founders_addresses.len() as u32;
-		let address_change_interval = (last_founder_reward_block_height + founders_len) / founders_len;
-		let address_index = height / address_change_interval;
+		let address_change_interval = (pre_blossom_last_founder_reward_block_height + founders_len) / founders_len;
+		let address_index = adjusted_height / address_change_interval;
 		Some(self.founders_addresses[address_index as usize].clone())
 	}
 
-	pub fn consensus_branch_id(&self, height: u32) -> u32 {
-		// sapling upgrade
-		if height >= self.sapling_height {
-			return 0x76b809bb;
-		}
-
-		// overwinter upgrade
-		if height >= self.overwinter_height {
-			return 0x5ba81b19;
-		}
-
-		// sprout
-		0
+	/// Consensus branch id of the most recent network upgrade, active at given height.
+	/// Branch id of Sprout (no upgrades are active) is zero.
+	pub fn branch_id(&self, height: u32) -> u32 {
+		NETWORK_UPGRADES.iter()
+			.rev()
+			.find(|upgrade| self.is_active(**upgrade, height))
+			.map(|upgrade| upgrade.branch_id())
+			.unwrap_or(0)
 	}
 }
 
@@ -502,8 +730,81 @@ mod tests {
 		assert_eq!(consensus.block_reward(1_000_000), 625_000_000);
 		assert_eq!(consensus.block_reward(2_000_000), 312_500_000);
 		assert_eq!(consensus.block_reward(3_000_000), 156_250_000);
-		assert_eq!(consensus.block_reward(4_000_000), 78_125_000);
-		assert_eq!(consensus.block_reward(20_000_000), 149);
-		assert_eq!(consensus.block_reward(30_000_000), 0);
+		assert_eq!(consensus.block_reward(4_000_000), 156_250_000);
+		assert_eq!(consensus.block_reward(20_000_000), 152_587);
+		assert_eq!(consensus.block_reward(30_000_000), 2_384);
+		assert_eq!(consensus.block_reward(consensus.blossom_height - 1), 1_250_000_000);
+		assert_eq!(consensus.block_reward(consensus.blossom_height), 625_000_000);
+		assert_eq!(consensus.first_halving_height(), 1_046_400);
+		assert_eq!(consensus.block_reward(1_046_399), 625_000_000);
+		assert_eq!(consensus.block_reward(1_046_400), 312_500_000);
+		assert_eq!(consensus.funding_streams_end_height(), 2_726_400);
+	}
+
+	#[test]
+	fn funding_streams() {
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		assert_eq!(consensus.funding_streams(consensus.canopy_height - 1), vec![]);
+		assert_eq!(consensus.funding_streams(consensus.canopy_height), vec![
+			(FundingStream::ElectricCoinCompany, 21_875_000),
+			(FundingStream::ZcashFoundation, 15_625_000),
+			(FundingStream::MajorGrants, 25_000_000),
+		]);
+		assert_eq!(consensus.funding_streams(consensus.funding_streams_end_height()), vec![]);
+	}
+
+	#[test]
+	fn network_upgrades_activation() {
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		assert_eq!(consensus.activation_height(NetworkUpgrade::Sapling), Some(419200));
+		assert!(!consensus.is_active(NetworkUpgrade::Sapling, 419199));
+		assert!(consensus.is_active(NetworkUpgrade::Sapling, 419200));
+		assert!(consensus.is_active(NetworkUpgrade::Overwinter, 419200));
+		assert!(!consensus.is_active(NetworkUpgrade::Blossom, 419200));
+
+		let consensus = ConsensusParams::new(Network::Testnet);
+		assert_eq!(consensus.activation_height(NetworkUpgrade::Sapling), Some(280000));
+		assert_eq!(consensus.activation_height(NetworkUpgrade::Heartwood), Some(903800));
+
+		let mut consensus = ConsensusParams::new(Network::Regtest);
+		for upgrade in NETWORK_UPGRADES.iter() {
+			assert!(!consensus.is_active(*upgrade, 0));
+			assert!(consensus.is_active(*upgrade, 1));
+		}
+		consensus.set_activation_height(NetworkUpgrade::Canopy, 100);
+		assert!(!consensus.is_active(NetworkUpgrade::Canopy, 99));
+		assert!(consensus.is_active(NetworkUpgrade::Canopy, 100));
+
+		let consensus = ConsensusParams::new(Network::Unitest);
+		assert_eq!(consensus.activation_height(NetworkUpgrade::Overwinter), None);
+		assert!(!consensus.is_active(NetworkUpgrade::Overwinter, ::std::u32::MAX));
+		assert!(!consensus.is_overwinter_active(::std::u32::MAX));
+		assert!(!consensus.is_canopy_active(::std::u32::MAX));
+
+		assert_eq!(NetworkUpgrade::from_branch_id(0x76b809bb), Some(NetworkUpgrade::Sapling));
+		assert_eq!(NetworkUpgrade::from_branch_id(0), None);
+	}
+
+	#[test]
+	fn branch_id() {
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		assert_eq!(consensus.branch_id(0), 0);
+		assert_eq!(consensus.branch_id(347499), 0);
+		assert_eq!(consensus.branch_id(347500), 0x5ba81b19);
+		assert_eq!(consensus.branch_id(419199), 0x5ba81b19);
+		assert_eq!(consensus.branch_id(419200), 0x76b809bb);
+		assert_eq!(consensus.branch_id(653599), 0x76b809bb);
+		assert_eq!(consensus.branch_id(653600), 0x2bb40e60);
+		assert_eq!(consensus.branch_id(903000), 0xf5b9230b);
+		assert_eq!(consensus.branch_id(1046399), 0xf5b9230b);
+		assert_eq!(consensus.branch_id(1046400), 0xe9ff75a6);
+		assert_eq!(consensus.branch_id(::std::u32::MAX), 0xe9ff75a6);
+
+		let consensus = ConsensusParams::new(Network::Regtest);
+		assert_eq!(consensus.branch_id(0), 0);
+		assert_eq!(consensus.branch_id(1), 0xe9ff75a6);
+
+		let consensus = ConsensusParams::new(Network::Unitest);
+		assert_eq!(consensus.branch_id(::std::u32::MAX), 0);
 	}
 }
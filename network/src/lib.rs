@@ -14,6 +14,6 @@ mod network;
 
 pub use primitives::{hash, compact};
 
-pub use consensus::ConsensusParams;
+pub use consensus::{ConsensusParams, FundingStream, FUNDING_STREAMS, NetworkUpgrade, NETWORK_UPGRADES, BLOSSOM_POW_TARGET_SPACING_RATIO, MAX_BLOCK_SIZE, MIN_TRANSACTION_SIZE, MAX_BLOCK_TRANSACTIONS};
 pub use deployments::Deployment;
 pub use network::{Magic, Network};
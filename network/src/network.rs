@@ -53,6 +53,17 @@ impl Network {
 		}
 	}
 
+	/// Returns network name, as it is reported by RPC.
+	pub fn name(&self) -> &'static str {
+		match *self {
+			Network::Mainnet => "main",
+			Network::Testnet => "test",
+			Network::Regtest => "regtest",
+			Network::Unitest => "unitest",
+			Network::Other(_) => "other",
+		}
+	}
+
 	pub fn port(&self) -> u16 {
 		match *self {
 			Network::Mainnet | Network::Other(_) => 8233,
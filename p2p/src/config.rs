@@ -1,7 +1,7 @@
 use std::{net, path};
 use message::common::Services;
 use net::Config as NetConfig;
-use util::InternetProtocol;
+use util::{InternetProtocol, SubnetPermissions};
 
 #[derive(Debug, Clone)]
 pub struct Config {
@@ -11,9 +11,15 @@ pub struct Config {
 	pub inbound_connections: u32,
 	/// Number of outbound connections.
 	pub outbound_connections: u32,
+	/// Number of outbound block-relay-only connections. Transactions and addresses are not exchanged over these connections.
+	pub block_relay_only_connections: u32,
+	/// Number of inbound peers from the least represented network groups, protected from eviction.
+	pub protect_by_netgroup: usize,
+	/// Number of inbound peers, which have most recently sent us a new block, protected from eviction.
+	pub protect_by_last_block: usize,
 	/// Configuration for every connection.
 	pub connection: NetConfig,
-	/// Connect only to these nodes.
+	/// Connect only to these nodes. When not empty, peer discovery and inbound connections are disabled.
 	pub peers: Vec<net::SocketAddr>,
 	/// Connect to these nodes to retrieve peer addresses, and disconnect.
 	pub seeds: Vec<String>,
@@ -23,4 +29,13 @@ pub struct Config {
 	pub preferable_services: Services,
 	/// Internet protocol.
 	pub internet_protocol: InternetProtocol,
+	/// Permissions, granted to trusted nodes.
+	pub permissions: Vec<SubnetPermissions>,
+}
+
+impl Config {
+	/// Returns true if node should only connect to explicitly configured peers.
+	pub fn is_connect_only(&self) -> bool {
+		!self.peers.is_empty()
+	}
 }
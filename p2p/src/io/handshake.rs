@@ -1,18 +1,21 @@
-use std::{io, cmp};
+use std::{io, cmp, mem};
 use futures::{Future, Poll, Async};
 use tokio_io::{AsyncRead, AsyncWrite};
-use message::{Message, MessageResult, Error};
-use message::types::{Version, Verack};
+use message::{Message, MessageResult, Error, Payload, Command, MESSAGE_HEADER_LEN};
+use message::types::{Version, Verack, SendAddrV2, ADDRV2_PROTOCOL_VERSION};
 use network::Magic;
-use io::{write_message, WriteMessage, ReadMessage, read_message};
+use io::{write_message, WriteMessage, ReadMessage, read_message, ReadHeader, read_header, ReadPayload, read_payload};
 
 pub fn handshake<A>(a: A, magic: Magic, version: Version, min_version: u32) -> Handshake<A> where A: AsyncWrite + AsyncRead {
+	let mut traffic = HandshakeTraffic::default();
 	Handshake {
 		version: version.version(),
 		nonce: version.nonce(),
-		state: HandshakeState::SendVersion(write_message(a, version_message(magic, version))),
+		state: HandshakeState::SendVersion(write_message(a, noted(&mut traffic.sent, version_message(magic, version)))),
 		magic: magic,
 		min_version: min_version,
+		addrv2: false,
+		traffic: traffic,
 	}
 }
 
@@ -26,6 +29,7 @@ pub fn accept_handshake<A>(a: A, magic: Magic, version: Version, min_version: u3
 		},
 		magic: magic,
 		min_version: min_version,
+		traffic: HandshakeTraffic::default(),
 	}
 }
 
@@ -33,10 +37,32 @@ pub fn negotiate_version(local: u32, other: u32) -> u32 {
 	cmp::min(local, other)
 }
 
+/// True if peer understands `sendaddrv2` message. We always understand it, whatever protocol version
+/// we are announcing, so only peer version is checked.
+fn supports_addrv2(peer_version: &Version) -> bool {
+	peer_version.version() >= ADDRV2_PROTOCOL_VERSION
+}
+
+/// Commands and sizes (header included) of messages, exchanged during handshake.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct HandshakeTraffic {
+	pub sent: Vec<(Command, usize)>,
+	pub received: Vec<(Command, usize)>,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct HandshakeResult {
 	pub version: Version,
 	pub negotiated_version: u32,
+	/// True if peer has sent `sendaddrv2` message before `verack`.
+	pub addrv2: bool,
+	pub traffic: HandshakeTraffic,
+}
+
+/// Remembers command and size of the message, which is about to be sent.
+fn noted<T>(sent: &mut Vec<(Command, usize)>, message: Message<T>) -> Message<T> where T: Payload {
+	sent.push((T::command().into(), message.len()));
+	message
 }
 
 fn version_message(magic: Magic, version: Version) -> Message<Version> {
@@ -47,16 +73,32 @@ fn verack_message(magic: Magic) -> Message<Verack> {
 	Message::new(magic, 0, &Verack).expect("verack message should always be serialized correctly")
 }
 
+fn sendaddrv2_message(magic: Magic) -> Message<SendAddrV2> {
+	Message::new(magic, SendAddrV2::version(), &SendAddrV2).expect("sendaddrv2 message should always be serialized correctly")
+}
+
 enum HandshakeState<A> {
 	SendVersion(WriteMessage<Version, A>),
 	ReceiveVersion(ReadMessage<Version, A>),
+	SendSendAddrV2 {
+		version: Option<Version>,
+		future: WriteMessage<SendAddrV2, A>,
+	},
 	SendVerack {
 		version: Option<Version>,
 		future: WriteMessage<Verack, A>,
 	},
+	ReceiveVerackHeader {
+		version: Option<Version>,
+		future: ReadHeader<A>,
+	},
+	ReceiveSendAddrV2 {
+		version: Option<Version>,
+		future: ReadPayload<SendAddrV2, A>,
+	},
 	ReceiveVerack {
 		version: Option<Version>,
-		future: ReadMessage<Verack, A>,
+		future: ReadPayload<Verack, A>,
 	},
 }
 
@@ -69,6 +111,10 @@ enum AcceptHandshakeState<A> {
 		version: Option<Version>,
 		future: WriteMessage<Version, A>,
 	},
+	SendSendAddrV2 {
+		version: Option<Version>,
+		future: WriteMessage<SendAddrV2, A>,
+	},
 	SendVerack {
 		version: Option<Version>,
 		future: WriteMessage<Verack, A>,
@@ -81,6 +127,8 @@ pub struct Handshake<A> {
 	version: u32,
 	nonce: Option<u64>,
 	min_version: u32,
+	addrv2: bool,
+	traffic: HandshakeTraffic,
 }
 
 pub struct AcceptHandshake<A> {
@@ -89,6 +137,7 @@ pub struct AcceptHandshake<A> {
 	version: u32,
 	nonce: Option<u64>,
 	min_version: u32,
+	traffic: HandshakeTraffic,
 }
 
 impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
@@ -108,6 +157,7 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 						Ok(version) => version,
 						Err(err) => return Ok((stream, Err(err.into())).into()),
 					};
+					self.traffic.received.push((Version::command().into(), future.message_len()));
 
 					if version.version() < self.min_version {
 						return Ok((stream, Err(Error::InvalidVersion)).into());
@@ -118,27 +168,84 @@ impl<A> Future for Handshake<A> where A: AsyncRead + AsyncWrite {
 						}
 					}
 
+					if supports_addrv2(&version) {
+						HandshakeState::SendSendAddrV2 {
+							version: Some(version),
+							future: write_message(stream, noted(&mut self.traffic.sent, sendaddrv2_message(self.magic))),
+						}
+					} else {
+						HandshakeState::SendVerack {
+							version: Some(version),
+							future: write_message(stream, noted(&mut self.traffic.sent, verack_message(self.magic))),
+						}
+					}
+				},
+				HandshakeState::SendSendAddrV2 { ref mut version, ref mut future } => {
+					let (stream, _) = try_ready!(future.poll());
 					HandshakeState::SendVerack {
-						version: Some(version),
-						future: write_message(stream, verack_message(self.magic)),
+						version: version.take(),
+						future: write_message(stream, noted(&mut self.traffic.sent, verack_message(self.magic))),
 					}
 				},
 				HandshakeState::SendVerack { ref mut version, ref mut future } => {
 					let (stream, _) = try_ready!(future.poll());
 					let version = version.take().expect("verack must be preceded by version");
 
-					HandshakeState::ReceiveVerack {
+					HandshakeState::ReceiveVerackHeader {
 						version: Some(version),
-						future: read_message(stream, self.magic, 0),
+						future: read_header(stream, self.magic),
+					}
+				},
+				HandshakeState::ReceiveVerackHeader { ref mut version, ref mut future } => {
+					let (stream, header) = try_ready!(future.poll());
+					let header = match header {
+						Ok(header) => header,
+						Err(err) => return Ok((stream, Err(err)).into()),
+					};
+					self.traffic.received.push((header.command.clone(), MESSAGE_HEADER_LEN + header.len as usize));
+
+					// peer may send `sendaddrv2` message (once) before `verack`
+					let version = version.take();
+					let addrv2_allowed = !self.addrv2 && version.as_ref().map_or(false, supports_addrv2);
+					if header.command == SendAddrV2::command() && addrv2_allowed {
+						HandshakeState::ReceiveSendAddrV2 {
+							version: version,
+							future: read_payload(stream, SendAddrV2::version(), header.len as usize, header.checksum),
+						}
+					} else if header.command == Verack::command() {
+						HandshakeState::ReceiveVerack {
+							version: version,
+							future: read_payload(stream, 0, header.len as usize, header.checksum),
+						}
+					} else {
+						return Ok((stream, Err(Error::InvalidCommand)).into());
+					}
+				},
+				HandshakeState::ReceiveSendAddrV2 { ref mut version, ref mut future } => {
+					let (stream, sendaddrv2) = try_ready!(future.poll());
+					if let Err(err) = sendaddrv2 {
+						return Ok((stream, Err(err)).into());
+					}
+
+					self.addrv2 = true;
+					HandshakeState::ReceiveVerackHeader {
+						version: version.take(),
+						future: read_header(stream, self.magic),
 					}
 				},
 				HandshakeState::ReceiveVerack { ref mut version, ref mut future } => {
-					let (stream, _verack) = try_ready!(future.poll());
+					let (stream, verack) = try_ready!(future.poll());
+					if let Err(err) = verack {
+						return Ok((stream, Err(err)).into());
+					}
+
 					let version = version.take().expect("verack must be preceded by version");
 
 					let result = HandshakeResult {
 						negotiated_version: negotiate_version(self.version, version.version()),
 						version: version,
+						addrv2: self.addrv2,
+						traffic: mem::replace(&mut self.traffic, HandshakeTraffic::default()),
 					};
 
 					return Ok(Async::Ready((stream, Ok(result))));
@@ -162,6 +269,7 @@ impl<A> Future for AcceptHandshake<A> where A: AsyncRead + AsyncWrite {
 						Ok(version) => version,
 						Err(err) => return Ok((stream, Err(err.into())).into()),
 					};
+					self.traffic.received.push((Version::command().into(), future.message_len()));
 
 					if version.version() < self.min_version {
 						return Ok((stream, Err(Error::InvalidVersion)).into());
@@ -175,14 +283,29 @@ impl<A> Future for AcceptHandshake<A> where A: AsyncRead + AsyncWrite {
 					let local_version = local_version.take().expect("local version must be set");
 					AcceptHandshakeState::SendVersion {
 						version: Some(version),
-						future: write_message(stream, version_message(self.magic, local_version)),
+						future: write_message(stream, noted(&mut self.traffic.sent, version_message(self.magic, local_version))),
 					}
 				},
 				AcceptHandshakeState::SendVersion { ref mut version, ref mut future } => {
+					let (stream, _) = try_ready!(future.poll());
+					let version = version.take().expect("version must be received before it is sent");
+					if supports_addrv2(&version) {
+						AcceptHandshakeState::SendSendAddrV2 {
+							version: Some(version),
+							future: write_message(stream, noted(&mut self.traffic.sent, sendaddrv2_message(self.magic))),
+						}
+					} else {
+						AcceptHandshakeState::SendVerack {
+							version: Some(version),
+							future: write_message(stream, noted(&mut self.traffic.sent, verack_message(self.magic))),
+						}
+					}
+				},
+				AcceptHandshakeState::SendSendAddrV2 { ref mut version, ref mut future } => {
 					let (stream, _) = try_ready!(future.poll());
 					AcceptHandshakeState::SendVerack {
 						version: version.take(),
-						future: write_message(stream, verack_message(self.magic)),
+						future: write_message(stream, noted(&mut self.traffic.sent, verack_message(self.magic))),
 					}
 				},
 				AcceptHandshakeState::SendVerack { ref mut version, ref mut future } => {
@@ -190,9 +313,12 @@ impl<A> Future for AcceptHandshake<A> where A: AsyncRead + AsyncWrite {
 
 					let version = version.take().expect("verack must be preceded by version");
 
+					// peer's `sendaddrv2` (if any) is received after handshake is completed
 					let result = HandshakeResult {
 						negotiated_version: negotiate_version(self.version, version.version()),
 						version: version,
+						addrv2: false,
+						traffic: mem::replace(&mut self.traffic, HandshakeTraffic::default()),
 					};
 
 					return Ok(Async::Ready((stream, Ok(result))));
@@ -211,10 +337,10 @@ mod tests {
 	use bytes::Bytes;
 	use ser::Stream;
 	use network::Network;
-	use message::{Message, Error};
-	use message::types::Verack;
+	use message::{Message, Error, Payload};
+	use message::types::{Verack, SendAddrV2, ADDRV2_PROTOCOL_VERSION};
 	use message::types::version::{Version, V0, V106, V70001};
-	use super::{handshake, accept_handshake, HandshakeResult};
+	use super::{handshake, accept_handshake, HandshakeResult, HandshakeTraffic};
 
 	pub struct TestIo {
 		read: io::Cursor<Bytes>,
@@ -284,6 +410,20 @@ mod tests {
 		})
 	}
 
+	fn message_len<T>(version: u32, payload: &T) -> usize where T: Payload {
+		Message::new(Network::Mainnet.magic(), version, payload).unwrap().len()
+	}
+
+	fn with_protocol_version(version: Version, protocol_version: u32) -> Version {
+		match version {
+			Version::V70001(mut v0, v106, v70001) => {
+				v0.version = protocol_version;
+				Version::V70001(v0, v106, v70001)
+			},
+			_ => unreachable!("test versions are V70001"),
+		}
+	}
+
 	#[test]
 	fn test_handshake() {
 		let magic = Network::Mainnet.magic();
@@ -295,9 +435,15 @@ mod tests {
 		remote_stream.append_slice(Message::new(magic, version, &remote_version).unwrap().as_ref());
 		remote_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
 
+		let expected_traffic = HandshakeTraffic {
+			sent: vec![("version".into(), message_len(version, &local_version)), ("verack".into(), message_len(version, &Verack))],
+			received: vec![("version".into(), message_len(version, &remote_version)), ("verack".into(), message_len(version, &Verack))],
+		};
 		let expected = HandshakeResult {
 			version: remote_version,
 			negotiated_version: 70001,
+			addrv2: false,
+			traffic: expected_traffic,
 		};
 
 		let mut expected_stream = Stream::new();
@@ -314,6 +460,95 @@ mod tests {
 		assert_eq!(hs.1.unwrap(), expected);
 	}
 
+	#[test]
+	fn test_handshake_with_sendaddrv2() {
+		let magic = Network::Mainnet.magic();
+		let version = ADDRV2_PROTOCOL_VERSION;
+		let local_version = with_protocol_version(local_version(), version);
+		let remote_version = with_protocol_version(remote_version(), version);
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, version, &remote_version).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, version, &SendAddrV2).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
+
+		let expected_traffic = HandshakeTraffic {
+			sent: vec![
+				("version".into(), message_len(version, &local_version)),
+				("sendaddrv2".into(), message_len(version, &SendAddrV2)),
+				("verack".into(), message_len(version, &Verack)),
+			],
+			received: vec![
+				("version".into(), message_len(version, &remote_version)),
+				("sendaddrv2".into(), message_len(version, &SendAddrV2)),
+				("verack".into(), message_len(version, &Verack)),
+			],
+		};
+		let expected = HandshakeResult {
+			version: remote_version,
+			negotiated_version: ADDRV2_PROTOCOL_VERSION,
+			addrv2: true,
+			traffic: expected_traffic,
+		};
+
+		let mut expected_stream = Stream::new();
+		expected_stream.append_slice(Message::new(magic, version, &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, version, &SendAddrV2).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
+
+		let test_io = TestIo {
+			read: io::Cursor::new(remote_stream.out()),
+			write: Bytes::default(),
+		};
+
+		let hs = handshake(test_io, magic, local_version, 0).wait().unwrap();
+		assert_eq!(hs.0.write, expected_stream.out());
+		assert_eq!(hs.1.unwrap(), expected);
+	}
+
+	#[test]
+	fn test_handshake_rejects_sendaddrv2_from_old_peer() {
+		let magic = Network::Mainnet.magic();
+		let version = ADDRV2_PROTOCOL_VERSION;
+		let local_version = with_protocol_version(local_version(), version);
+		let remote_version = remote_version();
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, 70012, &remote_version).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, version, &SendAddrV2).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, 70012, &Verack).unwrap().as_ref());
+
+		let test_io = TestIo {
+			read: io::Cursor::new(remote_stream.out()),
+			write: Bytes::default(),
+		};
+
+		let hs = handshake(test_io, magic, local_version, 0).wait().unwrap();
+		assert_eq!(hs.1.unwrap_err(), Error::InvalidCommand);
+	}
+
+	#[test]
+	fn test_handshake_with_unexpected_message() {
+		let magic = Network::Mainnet.magic();
+		let version = ADDRV2_PROTOCOL_VERSION;
+		let local_version = with_protocol_version(local_version(), version);
+		let remote_version = with_protocol_version(remote_version(), version);
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, version, &remote_version).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, version, &SendAddrV2).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, version, &SendAddrV2).unwrap().as_ref());
+		remote_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
+
+		let test_io = TestIo {
+			read: io::Cursor::new(remote_stream.out()),
+			write: Bytes::default(),
+		};
+
+		let hs = handshake(test_io, magic, local_version, 0).wait().unwrap();
+		assert_eq!(hs.1.unwrap_err(), Error::InvalidCommand);
+	}
+
 	#[test]
 	fn test_accept_handshake() {
 		let magic = Network::Mainnet.magic();
@@ -329,13 +564,59 @@ mod tests {
 			write: Bytes::default(),
 		};
 
+		let expected_traffic = HandshakeTraffic {
+			sent: vec![("version".into(), message_len(version, &local_version)), ("verack".into(), message_len(version, &Verack))],
+			received: vec![("version".into(), message_len(version, &remote_version))],
+		};
 		let expected = HandshakeResult {
 			version: remote_version,
 			negotiated_version: 70001,
+			addrv2: false,
+			traffic: expected_traffic,
+		};
+
+		let mut expected_stream = Stream::new();
+		expected_stream.append_slice(Message::new(magic, version, &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
+
+		let hs = accept_handshake(test_io, magic, local_version, 0).wait().unwrap();
+		assert_eq!(hs.0.write, expected_stream.out());
+		assert_eq!(hs.1.unwrap(), expected);
+	}
+
+	#[test]
+	fn test_accept_handshake_with_sendaddrv2() {
+		let magic = Network::Mainnet.magic();
+		let version = ADDRV2_PROTOCOL_VERSION;
+		let local_version = with_protocol_version(local_version(), version);
+		let remote_version = with_protocol_version(remote_version(), version);
+
+		let mut remote_stream = Stream::new();
+		remote_stream.append_slice(Message::new(magic, version, &remote_version).unwrap().as_ref());
+
+		let test_io = TestIo {
+			read: io::Cursor::new(remote_stream.out()),
+			write: Bytes::default(),
+		};
+
+		let expected_traffic = HandshakeTraffic {
+			sent: vec![
+				("version".into(), message_len(version, &local_version)),
+				("sendaddrv2".into(), message_len(version, &SendAddrV2)),
+				("verack".into(), message_len(version, &Verack)),
+			],
+			received: vec![("version".into(), message_len(version, &remote_version))],
+		};
+		let expected = HandshakeResult {
+			version: remote_version,
+			negotiated_version: ADDRV2_PROTOCOL_VERSION,
+			addrv2: false,
+			traffic: expected_traffic,
 		};
 
 		let mut expected_stream = Stream::new();
 		expected_stream.append_slice(Message::new(magic, version, &local_version).unwrap().as_ref());
+		expected_stream.append_slice(Message::new(magic, version, &SendAddrV2).unwrap().as_ref());
 		expected_stream.append_slice(Message::new(magic, version, &Verack).unwrap().as_ref());
 
 		let hs = accept_handshake(test_io, magic, local_version, 0).wait().unwrap();
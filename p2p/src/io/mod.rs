@@ -9,7 +9,7 @@ mod write_message;
 
 pub use self::deadline::{deadline, Deadline, DeadlineStatus};
 pub use self::handshake::{
-	handshake, accept_handshake, Handshake, AcceptHandshake, HandshakeResult
+	handshake, accept_handshake, Handshake, AcceptHandshake, HandshakeResult, HandshakeTraffic
 };
 pub use self::read_header::{read_header, ReadHeader};
 pub use self::read_payload::{read_payload, ReadPayload};
@@ -90,4 +90,19 @@ mod tests {
 		let raw: Bytes = "24e9276470696e6700000000000000000800000083c01c765845303b6da97786".into();
 		assert_eq!(read_any_message(raw.as_ref(), Network::Mainnet.magic()).wait().unwrap(), Err(Error::InvalidChecksum));
 	}
+
+	#[test]
+	fn test_read_any_message_with_too_large_payload() {
+		// header claims 16MB payload, but only few bytes follow => must fail before trying to read the payload
+		let raw: Bytes = "24e9276470696e670000000000000000000000015845303b6da97786".into();
+		assert_eq!(read_any_message(raw.as_ref(), Network::Mainnet.magic()).wait().unwrap(), Err(Error::MessageTooLarge));
+	}
+
+	#[test]
+	fn test_read_any_message_with_deprecated_alert() {
+		// deprecated alert message must be read without errors, so that peer is not disconnected
+		let raw: Bytes = "24e92764616c657274000000000000000a00000022a1eb850568656c6c6f03736967".into();
+		let expected = ("alert".into(), "0568656c6c6f03736967".into());
+		assert_eq!(read_any_message(raw.as_ref(), Network::Mainnet.magic()).wait().unwrap(), Ok(expected));
+	}
 }
@@ -61,4 +61,10 @@ mod tests {
 		let raw: Bytes = "24e927646164647200000000000000001f000000ed5239".into();
 		assert!(read_header(raw.as_ref(), Network::Mainnet.magic()).wait().is_err());
 	}
+
+	#[test]
+	fn test_read_header_with_too_large_payload() {
+		let raw: Bytes = "24e9276461646472000000000000000000000001ed52399b".into();
+		assert_eq!(read_header(raw.as_ref(), Network::Mainnet.magic()).wait().unwrap().1, Err(Error::MessageTooLarge));
+	}
 }
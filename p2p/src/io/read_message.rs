@@ -3,7 +3,7 @@ use std::marker::PhantomData;
 use futures::{Poll, Future, Async};
 use tokio_io::AsyncRead;
 use network::Magic;
-use message::{MessageResult, Error, Payload};
+use message::{MessageResult, Error, Payload, MESSAGE_HEADER_LEN};
 use io::{read_header, ReadHeader, read_payload, ReadPayload};
 
 pub fn read_message<M, A>(a: A, magic: Magic, version: u32) -> ReadMessage<M, A>
@@ -13,6 +13,7 @@ pub fn read_message<M, A>(a: A, magic: Magic, version: u32) -> ReadMessage<M, A>
 			version: version,
 			future: read_header(a, magic),
 		},
+		message_len: 0,
 		message_type: PhantomData,
 	}
 }
@@ -29,9 +30,17 @@ enum ReadMessageState<M, A> {
 
 pub struct ReadMessage<M, A> {
 	state: ReadMessageState<M, A>,
+	message_len: usize,
 	message_type: PhantomData<M>,
 }
 
+impl<M, A> ReadMessage<M, A> {
+	/// Size of the message (header included). Known once the header is read.
+	pub fn message_len(&self) -> usize {
+		self.message_len
+	}
+}
+
 impl<M, A> Future for ReadMessage<M, A> where A: AsyncRead, M: Payload {
 	type Item = (A, MessageResult<M>);
 	type Error = io::Error;
@@ -49,6 +58,7 @@ impl<M, A> Future for ReadMessage<M, A> where A: AsyncRead, M: Payload {
 					if header.command != M::command() {
 						return Ok((read, Err(Error::InvalidCommand)).into());
 					}
+					self.message_len = MESSAGE_HEADER_LEN + header.len as usize;
 					let future = read_payload(
 						read, version, header.len as usize, header.checksum,
 					);
@@ -30,10 +30,10 @@ mod p2p;
 pub use primitives::{hash, bytes};
 
 pub use config::Config;
-pub use net::Config as NetConfig;
+pub use net::{Config as NetConfig, NetTotals, TimeoutProfile, MAX_TIMEOUT_SCALE, PROTECTED_BY_NETGROUP, PROTECTED_BY_LAST_BLOCK};
 pub use p2p::{P2P, Context};
 pub use event_loop::{event_loop, forever};
-pub use util::{NodeTableError, PeerId, PeerInfo, InternetProtocol, Direction};
+pub use util::{NodeTableError, PeerId, PeerInfo, InternetProtocol, Direction, PeerPermissions, Subnet, SubnetPermissions};
 pub use protocol::{
 	InboundSyncConnection, InboundSyncConnectionRef,
 	InboundSyncConnectionState, InboundSyncConnectionStateRef,
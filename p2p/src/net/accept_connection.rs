@@ -1,5 +1,4 @@
 use std::{net, io};
-use std::time::Duration;
 use futures::{Future, Poll};
 use tokio_core::reactor::Handle;
 use tokio_core::net::TcpStream;
@@ -15,7 +14,7 @@ pub fn accept_connection(stream: TcpStream, handle: &Handle, config: &Config, ad
 		address: address,
 	};
 
-	deadline(Duration::new(5, 0), handle, accept).expect("Failed to create timeout")
+	deadline(config.timeouts.handshake, handle, accept).expect("Failed to create timeout")
 }
 
 pub struct AcceptConnection {
@@ -41,6 +40,8 @@ impl Future for AcceptConnection {
 			version_message: result.version, 
 			magic: self.magic,
 			address: self.address,
+			addrv2: result.addrv2,
+			handshake_traffic: result.traffic,
 		};
 		Ok(Ok(connection).into())
 	}
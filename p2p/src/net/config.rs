@@ -1,4 +1,6 @@
+use std::cmp;
 use std::net::SocketAddr;
+use std::time::Duration;
 use network::Magic;
 use message::common::{Services, NetAddress};
 use message::types::version::{Version, V0, V106, V70001};
@@ -15,6 +17,60 @@ pub struct Config {
 	pub user_agent: String,
 	pub start_height: i32,
 	pub relay: bool,
+	pub timeouts: TimeoutProfile,
+	/// Maximal size of serialized block, accepted from peers (consensus rule).
+	pub max_block_size: usize,
+}
+
+/// Maximal factor, network timeouts could be multiplied by.
+pub const MAX_TIMEOUT_SCALE: u32 = 100;
+
+/// Network timeouts profile.
+///
+/// Default timeouts are tuned for low-latency networks. For high-latency environments
+/// (e.g. Tor-only nodes), the scaled profile should be used to avoid spurious disconnects.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeoutProfile {
+	/// Time to establish connection and complete the handshake.
+	pub handshake: Duration,
+	/// Time to wait for pong response to our ping request.
+	pub ping_response: Duration,
+	/// Time to wait for requested blocks from the new peer before penalizing it.
+	pub block_request: Duration,
+	/// Time to wait for requested blocks from the trusted peer before penalizing it.
+	pub trusted_block_request: Duration,
+	/// Time to wait for requested headers from the new peer before penalizing it.
+	pub headers_request: Duration,
+	/// Time to wait for requested headers from the trusted peer before penalizing it.
+	pub trusted_headers_request: Duration,
+}
+
+impl Default for TimeoutProfile {
+	fn default() -> Self {
+		TimeoutProfile {
+			handshake: Duration::from_secs(5),
+			ping_response: Duration::from_secs(60),
+			block_request: Duration::from_secs(5),
+			trusted_block_request: Duration::from_secs(20),
+			headers_request: Duration::from_secs(5),
+			trusted_headers_request: Duration::from_secs(20),
+		}
+	}
+}
+
+impl TimeoutProfile {
+	/// Returns profile with all timeouts multiplied by given factor (at most `MAX_TIMEOUT_SCALE`).
+	pub fn scaled(&self, factor: u32) -> Self {
+		let factor = cmp::min(factor, MAX_TIMEOUT_SCALE);
+		TimeoutProfile {
+			handshake: self.handshake * factor,
+			ping_response: self.ping_response * factor,
+			block_request: self.block_request * factor,
+			trusted_block_request: self.trusted_block_request * factor,
+			headers_request: self.headers_request * factor,
+			trusted_headers_request: self.trusted_headers_request * factor,
+		}
+	}
 }
 
 impl Config {
@@ -42,3 +98,22 @@ impl Config {
 		})
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+	use super::{TimeoutProfile, MAX_TIMEOUT_SCALE};
+
+	#[test]
+	fn timeout_profile_scaled() {
+		let profile = TimeoutProfile::default().scaled(3);
+		assert_eq!(profile.handshake, Duration::from_secs(15));
+		assert_eq!(profile.ping_response, Duration::from_secs(180));
+		assert_eq!(profile.block_request, Duration::from_secs(15));
+		assert_eq!(profile.trusted_block_request, Duration::from_secs(60));
+		assert_eq!(profile.headers_request, Duration::from_secs(15));
+		assert_eq!(profile.trusted_headers_request, Duration::from_secs(60));
+		assert_eq!(TimeoutProfile::default().scaled(1), TimeoutProfile::default());
+		assert_eq!(TimeoutProfile::default().scaled(u32::max_value()), TimeoutProfile::default().scaled(MAX_TIMEOUT_SCALE));
+	}
+}
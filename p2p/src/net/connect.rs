@@ -1,5 +1,4 @@
 use std::io;
-use std::time::Duration;
 use std::net::SocketAddr;
 use futures::{Future, Poll, Async};
 use tokio_core::reactor::Handle;
@@ -21,7 +20,7 @@ pub fn connect(address: &SocketAddr, handle: &Handle, config: &Config) -> Deadli
 		protocol_minimum: config.protocol_minimum,
 	};
 
-	deadline(Duration::new(5, 0), handle, connect).expect("Failed to create timeout")
+	deadline(config.timeouts.handshake, handle, connect).expect("Failed to create timeout")
 }
 
 enum ConnectState {
@@ -65,6 +64,8 @@ impl Future for Connect {
 					version_message: result.version,
 					magic: self.magic,
 					address: self.address,
+					addrv2: result.addrv2,
+					handshake_traffic: result.traffic,
 				};
 				(ConnectState::Connected, Async::Ready(Ok(connection)))
 			},
@@ -2,7 +2,7 @@ use std::net;
 use network::Magic;
 use message::common::Services;
 use message::types;
-use io::SharedTcpStream;
+use io::{SharedTcpStream, HandshakeTraffic};
 
 pub struct Connection {
 	pub stream: SharedTcpStream,
@@ -11,4 +11,6 @@ pub struct Connection {
 	pub magic: Magic,
 	pub services: Services,
 	pub address: net::SocketAddr,
+	pub addrv2: bool,
+	pub handshake_traffic: HandshakeTraffic,
 }
@@ -1,5 +1,5 @@
 use std::cmp;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
 /// Counts number of open inbound and outbound connections.
 pub struct ConnectionCounter {
@@ -7,22 +7,41 @@ pub struct ConnectionCounter {
 	current_inbound_connections: AtomicUsize,
 	/// Current number of outbound connections.
 	current_outbound_connections: AtomicUsize,
+	/// Current number of outbound block-relay-only connections.
+	current_block_relay_only_connections: AtomicUsize,
 	/// Maximum number of inbound connections.
 	max_inbound_connections: u32,
 	/// Maximum number of outbound connections.
 	max_outbound_connections: u32,
+	/// Maximum number of outbound block-relay-only connections.
+	max_block_relay_only_connections: u32,
+	/// Is networking enabled?
+	network_active: AtomicBool,
 }
 
 impl ConnectionCounter {
-	pub fn new(max_inbound_connections: u32, max_outbound_connections: u32) -> Self {
+	pub fn new(max_inbound_connections: u32, max_outbound_connections: u32, max_block_relay_only_connections: u32) -> Self {
 		ConnectionCounter {
 			current_inbound_connections: AtomicUsize::new(0),
 			current_outbound_connections: AtomicUsize::new(0),
+			current_block_relay_only_connections: AtomicUsize::new(0),
 			max_inbound_connections: max_inbound_connections,
 			max_outbound_connections: max_outbound_connections,
+			max_block_relay_only_connections: max_block_relay_only_connections,
+			network_active: AtomicBool::new(true),
 		}
 	}
 
+	/// Returns true if networking is enabled.
+	pub fn is_network_active(&self) -> bool {
+		self.network_active.load(Ordering::Acquire)
+	}
+
+	/// Enables or disables networking. No new connections are needed while networking is disabled.
+	pub fn set_network_active(&self, active: bool) {
+		self.network_active.store(active, Ordering::Release);
+	}
+
 	/// Increases inbound connections counter by 1.
 	pub fn note_new_inbound_connection(&self) {
 		self.current_inbound_connections.fetch_add(1, Ordering::AcqRel);
@@ -45,18 +64,47 @@ impl ConnectionCounter {
 		self.current_outbound_connections.fetch_sub(1, Ordering::AcqRel);
 	}
 
+	/// Increases outbound block-relay-only connections counter by 1.
+	pub fn note_new_block_relay_only_connection(&self) {
+		self.current_block_relay_only_connections.fetch_add(1, Ordering::AcqRel);
+	}
+
+	/// Decreases outbound block-relay-only connections counter by 1.
+	/// If it underflows, it means, that there is a logic error.
+	pub fn note_close_block_relay_only_connection(&self) {
+		self.current_block_relay_only_connections.fetch_sub(1, Ordering::AcqRel);
+	}
+
 	/// Returns number of inbound connections needed to reach the maximum
 	pub fn inbound_connections_needed(&self) -> u32 {
+		if !self.is_network_active() {
+			return 0;
+		}
+
 		let ic = self.inbound_connections();
 		ic.1 - cmp::min(ic.0, ic.1)
 	}
 
 	/// Returns number of inbound connections needed to reach the maximum
 	pub fn outbound_connections_needed(&self) -> u32 {
+		if !self.is_network_active() {
+			return 0;
+		}
+
 		let oc = self.outbound_connections();
 		oc.1 - cmp::min(oc.0, oc.1)
 	}
 
+	/// Returns number of outbound block-relay-only connections needed to reach the maximum
+	pub fn block_relay_only_connections_needed(&self) -> u32 {
+		if !self.is_network_active() {
+			return 0;
+		}
+
+		let bc = self.block_relay_only_connections();
+		bc.1 - cmp::min(bc.0, bc.1)
+	}
+
 	/// Returns a pair of unsigned integers where first element is current number of connections and the second is max.
 	pub fn inbound_connections(&self) -> (u32, u32) {
 		let current = self.current_inbound_connections.load(Ordering::Acquire) as u32;
@@ -68,6 +116,12 @@ impl ConnectionCounter {
 		let current = self.current_outbound_connections.load(Ordering::Acquire) as u32;
 		(current, self.max_outbound_connections)
 	}
+
+	/// Returns a pair of unsigned integers where first element is current number of connections and the second is max.
+	pub fn block_relay_only_connections(&self) -> (u32, u32) {
+		let current = self.current_block_relay_only_connections.load(Ordering::Acquire) as u32;
+		(current, self.max_block_relay_only_connections)
+	}
 }
 
 #[cfg(test)]
@@ -76,7 +130,7 @@ mod tests {
 
 	#[test]
 	fn test_inbound_connection_counter() {
-		let cc = ConnectionCounter::new(5, 10);
+		let cc = ConnectionCounter::new(5, 10, 2);
 		assert_eq!(cc.inbound_connections_needed(), 5);
 		assert_eq!(cc.inbound_connections(), (0, 5));
 		cc.note_new_inbound_connection();
@@ -98,7 +152,7 @@ mod tests {
 
 	#[test]
 	fn test_outbound_connection_counter() {
-		let cc = ConnectionCounter::new(0, 4);
+		let cc = ConnectionCounter::new(0, 4, 2);
 		assert_eq!(cc.outbound_connections_needed(), 4);
 		assert_eq!(cc.outbound_connections(), (0, 4));
 		cc.note_new_outbound_connection();
@@ -109,4 +163,38 @@ mod tests {
 		assert_eq!(cc.outbound_connections_needed(), 3);
 		assert_eq!(cc.outbound_connections(), (1, 4));
 	}
+
+	#[test]
+	fn test_block_relay_only_connection_counter() {
+		let cc = ConnectionCounter::new(0, 4, 2);
+		assert_eq!(cc.block_relay_only_connections_needed(), 2);
+		cc.note_new_outbound_connection();
+		cc.note_new_block_relay_only_connection();
+		assert_eq!(cc.outbound_connections_needed(), 3);
+		assert_eq!(cc.block_relay_only_connections_needed(), 1);
+		assert_eq!(cc.block_relay_only_connections(), (1, 2));
+		cc.note_close_block_relay_only_connection();
+		assert_eq!(cc.outbound_connections_needed(), 3);
+		assert_eq!(cc.block_relay_only_connections_needed(), 2);
+	}
+
+	#[test]
+	fn test_connection_counter_network_inactive() {
+		let cc = ConnectionCounter::new(5, 4, 2);
+		cc.note_new_outbound_connection();
+		assert!(cc.is_network_active());
+		assert_eq!(cc.inbound_connections_needed(), 5);
+		assert_eq!(cc.outbound_connections_needed(), 3);
+		cc.set_network_active(false);
+		assert!(!cc.is_network_active());
+		assert_eq!(cc.inbound_connections_needed(), 0);
+		assert_eq!(cc.outbound_connections_needed(), 0);
+		assert_eq!(cc.block_relay_only_connections_needed(), 0);
+		cc.note_close_outbound_connection();
+		assert_eq!(cc.outbound_connections_needed(), 0);
+		assert_eq!(cc.outbound_connections(), (0, 4));
+		cc.set_network_active(true);
+		assert_eq!(cc.inbound_connections_needed(), 5);
+		assert_eq!(cc.outbound_connections_needed(), 4);
+	}
 }
@@ -47,7 +47,7 @@ impl Connections {
 
 	/// Stores new channel.
 	/// Returns a shared pointer to it.
-	pub fn store<T>(&self, context: Arc<Context>, connection: Connection, direction: Direction) -> Arc<Channel> where T: SessionFactory {
+	pub fn store<T>(&self, context: Arc<Context>, connection: Connection, direction: Direction, block_relay_only: bool) -> Arc<Channel> where T: SessionFactory {
 		let id = self.peer_counter.fetch_add(1, Ordering::AcqRel);
 
 		let peer_info = PeerInfo {
@@ -58,6 +58,9 @@ impl Connections {
 			version: connection.version,
 			version_message: connection.version_message,
 			magic: connection.magic,
+			permissions: context.peer_permissions(&connection.address),
+			addrv2: connection.addrv2,
+			block_relay_only: block_relay_only,
 		};
 
 		let session = T::new_session(context, peer_info.clone(), SYNCHRONOUS_RESPONSES);
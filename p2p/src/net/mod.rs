@@ -6,14 +6,16 @@ mod connection;
 mod connection_counter;
 mod connections;
 mod peer_context;
+mod peer_manager;
 mod stats;
 
 pub use self::accept_connection::{AcceptConnection, accept_connection};
 pub use self::channel::Channel;
-pub use self::config::Config;
+pub use self::config::{Config, TimeoutProfile, MAX_TIMEOUT_SCALE};
 pub use self::connect::{Connect, connect};
 pub use self::connection::Connection;
 pub use self::connection_counter::ConnectionCounter;
 pub use self::connections::Connections;
 pub use self::peer_context::PeerContext;
-pub use self::stats::PeerStats;
+pub use self::peer_manager::{PeerManager, EvictionCandidate, netgroup, PROTECTED_BY_NETGROUP, PROTECTED_BY_LAST_BLOCK};
+pub use self::stats::{PeerStats, NetTotals};
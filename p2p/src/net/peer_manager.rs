@@ -0,0 +1,145 @@
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use util::PeerId;
+
+/// Number of peers from the least represented network groups, protected from eviction.
+pub const PROTECTED_BY_NETGROUP: usize = 4;
+/// Number of peers, which have most recently sent us a new block, protected from eviction.
+pub const PROTECTED_BY_LAST_BLOCK: usize = 4;
+
+/// Inbound connection, which could be evicted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvictionCandidate {
+	/// Peer id. Ids are assigned incrementally, so smaller id means longer connection.
+	pub id: PeerId,
+	/// Time of the last new block, received from the peer (0 if none).
+	pub last_block: u32,
+	/// Network group of the peer address.
+	pub netgroup: Vec<u8>,
+}
+
+/// Selects inbound connection to evict when all inbound slots are taken.
+#[derive(Debug, Clone)]
+pub struct PeerManager {
+	/// Number of peers from the least represented network groups, protected from eviction.
+	pub protect_by_netgroup: usize,
+	/// Number of peers, which have most recently sent us a new block, protected from eviction.
+	pub protect_by_last_block: usize,
+}
+
+impl Default for PeerManager {
+	fn default() -> Self {
+		PeerManager {
+			protect_by_netgroup: PROTECTED_BY_NETGROUP,
+			protect_by_last_block: PROTECTED_BY_LAST_BLOCK,
+		}
+	}
+}
+
+impl PeerManager {
+	/// Selects inbound connection to evict. Candidates are protected in the following order:
+	/// 1) longest connected peer from each of the least represented network groups;
+	/// 2) peers, which have most recently sent us a block;
+	/// 3) longest connected half of remaining peers.
+	/// The youngest connection from the most represented network group is selected from the rest.
+	pub fn evict_inbound(&self, mut candidates: Vec<EvictionCandidate>) -> Option<PeerId> {
+		// protect peers from the least represented network groups
+		let mut netgroup_sizes = HashMap::new();
+		for candidate in &candidates {
+			*netgroup_sizes.entry(candidate.netgroup.clone()).or_insert(0usize) += 1;
+		}
+		candidates.sort_by_key(|candidate| (netgroup_sizes[&candidate.netgroup], candidate.netgroup.clone(), candidate.id));
+		let mut protected_netgroups = HashSet::new();
+		let protect_by_netgroup = self.protect_by_netgroup;
+		candidates.retain(|candidate| {
+			if protected_netgroups.len() < protect_by_netgroup && !protected_netgroups.contains(&candidate.netgroup) {
+				protected_netgroups.insert(candidate.netgroup.clone());
+				return false;
+			}
+			true
+		});
+
+		// protect peers which are providing us with new blocks
+		candidates.sort_by_key(|candidate| cmp::Reverse(candidate.last_block));
+		let protect_by_last_block = candidates.iter()
+			.take(self.protect_by_last_block)
+			.take_while(|candidate| candidate.last_block != 0)
+			.count();
+		candidates.drain(..protect_by_last_block);
+
+		// protect longest connected half of remaining peers
+		candidates.sort_by_key(|candidate| candidate.id);
+		let protect_by_uptime = candidates.len() / 2;
+		candidates.drain(..protect_by_uptime);
+
+		// evict youngest peer from the most represented network group
+		let mut netgroups = HashMap::new();
+		for candidate in candidates {
+			netgroups.entry(candidate.netgroup).or_insert_with(Vec::new).push(candidate.id);
+		}
+
+		netgroups.values()
+			.map(|ids| (ids.len(), ids.iter().max().expect("netgroup is created with at least one peer; qed")))
+			.max()
+			.map(|(_, id)| *id)
+	}
+}
+
+/// Returns network group of the address: /16 subnet for IPv4 and /32 subnet for IPv6 addresses.
+pub fn netgroup(address: &IpAddr) -> Vec<u8> {
+	match *address {
+		IpAddr::V4(ref address) => address.octets()[..2].to_vec(),
+		IpAddr::V6(ref address) => match address.to_ipv4() {
+			Some(ref address) if address.octets() != [0, 0, 0, 1] => address.octets()[..2].to_vec(),
+			_ => address.octets()[..4].to_vec(),
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{PeerManager, EvictionCandidate, netgroup};
+
+	fn candidate(id: usize, last_block: u32, netgroup: u8) -> EvictionCandidate {
+		EvictionCandidate {
+			id: id,
+			last_block: last_block,
+			netgroup: vec![netgroup, netgroup],
+		}
+	}
+
+	#[test]
+	fn evict_inbound_selects_unprotected_least_useful_peer() {
+		let peer_manager = PeerManager::default();
+		assert_eq!(peer_manager.evict_inbound(vec![]), None);
+
+		// peers 0, 1, 2 are protected by network group
+		// peers 7, 8 are protected by recently received blocks
+		// peers 3, 4 are protected by connection time
+		let mut candidates = vec![candidate(0, 0, 1), candidate(1, 0, 2)];
+		candidates.extend((2..10).map(|id| candidate(id, 0, 3)));
+		candidates[7].last_block = 100;
+		candidates[8].last_block = 200;
+		assert_eq!(peer_manager.evict_inbound(candidates.clone()), Some(9));
+
+		// once youngest peer sends us a block, it is protected
+		candidates[9].last_block = 300;
+		assert_eq!(peer_manager.evict_inbound(candidates.clone()), Some(6));
+
+		// peer from the most represented network group is evicted, even if it is not the youngest one
+		let candidates = vec![candidate(0, 0, 4), candidate(1, 0, 3), candidate(2, 0, 3), candidate(3, 0, 3), candidate(4, 0, 4)];
+		let peer_manager = PeerManager {
+			protect_by_netgroup: 0,
+			protect_by_last_block: 0,
+		};
+		assert_eq!(peer_manager.evict_inbound(candidates), Some(3));
+	}
+
+	#[test]
+	fn test_netgroup() {
+		assert_eq!(netgroup(&"10.1.2.3".parse().unwrap()), vec![10, 1]);
+		assert_eq!(netgroup(&"::ffff:10.1.2.3".parse().unwrap()), vec![10, 1]);
+		assert_eq!(netgroup(&"2001:db8::1".parse().unwrap()), vec![0x20, 0x01, 0x0d, 0xb8]);
+	}
+}
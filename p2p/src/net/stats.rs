@@ -47,6 +47,8 @@ pub enum Flow { Receive, Send }
 pub struct PeerStats<T: Interval = RealInterval> {
 	pub last_send: u32,
 	pub last_recv: u32,
+	/// Time of the last new block, received from the peer and accepted by sync (0 if none).
+	pub last_block: u32,
 
 	pub total_send: u64,
 	pub total_recv: u64,
@@ -128,6 +130,10 @@ impl<I: Interval> PeerStats<I> {
 		}
 	}
 
+	pub fn report_new_block(&mut self) {
+		self.last_block = ::time::get_time().sec as u32;
+	}
+
 	pub fn avg<T>(&self, dir: Flow, cmd: T) -> u64
 		where T: Into<Command>
 	{
@@ -138,10 +144,32 @@ impl<I: Interval> PeerStats<I> {
 	}
 }
 
+/// Traffic totals of all connections, including already closed ones.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct NetTotals {
+	pub total_send: u64,
+	pub total_recv: u64,
+
+	pub send_by_command: HashMap<Command, u64>,
+	pub recv_by_command: HashMap<Command, u64>,
+}
+
+impl NetTotals {
+	pub fn report_send(&mut self, command: Command, bytes: usize) {
+		self.total_send += bytes as u64;
+		*self.send_by_command.entry(command).or_insert(0) += bytes as u64;
+	}
+
+	pub fn report_recv(&mut self, command: Command, bytes: usize) {
+		self.total_recv += bytes as u64;
+		*self.recv_by_command.entry(command).or_insert(0) += bytes as u64;
+	}
+}
+
 #[cfg(test)]
 mod tests {
 
-	use super::{RunningAverage, PeerStats, Flow};
+	use super::{RunningAverage, PeerStats, Flow, NetTotals};
 	use util::interval::{FixedIntervalSpawner, RealInterval};
 
 	#[test]
@@ -189,4 +217,34 @@ mod tests {
 
 		assert_eq!(stats.avg(Flow::Receive, "inv"), 2500);
 	}
+
+	#[test]
+	fn last_block() {
+		let mut stats = PeerStats::<RealInterval>::default();
+		stats.report_recv("inv".into(), 100);
+		assert_eq!(stats.last_block, 0);
+
+		// block message alone doesn't update last block time: block could be old or invalid
+		stats.report_recv("block".into(), 1000);
+		assert_eq!(stats.last_block, 0);
+
+		stats.report_new_block();
+		assert!(stats.last_block != 0);
+	}
+
+	#[test]
+	fn net_totals() {
+		let mut totals = NetTotals::default();
+		totals.report_send("inv".into(), 200);
+		totals.report_send("getdata".into(), 100);
+		totals.report_send("inv".into(), 300);
+		totals.report_recv("block".into(), 2000);
+
+		assert_eq!(totals.total_send, 600);
+		assert_eq!(totals.total_recv, 2000);
+		assert_eq!(totals.send_by_command[&"inv".into()], 500);
+		assert_eq!(totals.send_by_command[&"getdata".into()], 100);
+		assert_eq!(totals.recv_by_command[&"block".into()], 2000);
+		assert!(totals.recv_by_command.get(&"inv".into()).is_none());
+	}
 }
@@ -1,7 +1,8 @@
 use std::{io, net, error, time};
 use std::sync::Arc;
+use std::collections::HashSet;
 use std::net::SocketAddr;
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use futures::{Future, finished, failed};
 use futures::stream::Stream;
 use futures_cpupool::{CpuPool, Builder as CpuPoolBuilder};
@@ -10,12 +11,12 @@ use tokio_core::net::{TcpListener, TcpStream};
 use tokio_core::reactor::{Handle, Remote, Timeout, Interval};
 use abstract_ns::Resolver;
 use ns_dns_tokio::DnsResolver;
-use message::{Payload, MessageResult, Message};
+use message::{Command, Payload, MessageResult, Message, MessageHeader, MESSAGE_HEADER_LEN};
 use message::common::Services;
 use message::types::addr::AddressEntry;
-use net::{connect, Connections, Channel, Config as NetConfig, accept_connection, ConnectionCounter};
-use util::{NodeTable, Node, NodeTableError, Direction};
-use session::{SessionFactory, SeednodeSessionFactory, NormalSessionFactory};
+use net::{connect, Connection, Connections, Channel, Config as NetConfig, accept_connection, ConnectionCounter, NetTotals, TimeoutProfile, PeerManager, EvictionCandidate, netgroup};
+use util::{NodeTable, Node, NodeTableError, Direction, PeerInfo, PeerPermissions, ConnectBackoff, BanList, peer_permissions};
+use session::{SessionFactory, SeednodeSessionFactory, NormalSessionFactory, BlockRelayOnlySessionFactory};
 use {Config, PeerId};
 use protocol::{LocalSyncNodeRef, InboundSyncConnectionRef, OutboundSyncConnectionRef};
 use io::DeadlineStatus;
@@ -28,8 +29,16 @@ pub struct Context {
 	connections: Connections,
 	/// Connection counter.
 	connection_counter: ConnectionCounter,
+	/// Selects inbound connections to evict.
+	peer_manager: PeerManager,
+	/// Traffic totals.
+	net_totals: Mutex<NetTotals>,
 	/// Node Table.
 	node_table: RwLock<NodeTable>,
+	/// Reconnection delays for explicitly configured peers.
+	connect_backoff: Mutex<ConnectBackoff>,
+	/// Nodes, banned for misbehavior.
+	ban_list: Mutex<BanList>,
 	/// Thread pool handle.
 	pool: CpuPool,
 	/// Remote event loop handle.
@@ -43,10 +52,23 @@ pub struct Context {
 impl Context {
 	/// Creates new context with reference to local sync node, thread pool and event loop.
 	pub fn new(local_sync_node: LocalSyncNodeRef, pool_handle: CpuPool, remote: Remote, config: Config) -> Result<Self, Box<error::Error>> {
+		// in connect-only mode we never accept inbound connections and keep connections to all configured peers
+		let (inbound_connections, outbound_connections, block_relay_only_connections) = match config.is_connect_only() {
+			true => (0, ::std::cmp::max(config.outbound_connections, config.peers.len() as u32), 0),
+			false => (config.inbound_connections, config.outbound_connections, config.block_relay_only_connections),
+		};
+
 		let context = Context {
 			connections: Default::default(),
-			connection_counter: ConnectionCounter::new(config.inbound_connections, config.outbound_connections),
+			connection_counter: ConnectionCounter::new(inbound_connections, outbound_connections, block_relay_only_connections),
+			peer_manager: PeerManager {
+				protect_by_netgroup: config.protect_by_netgroup,
+				protect_by_last_block: config.protect_by_last_block,
+			},
+			net_totals: Mutex::default(),
 			node_table: RwLock::new(try!(NodeTable::from_file(config.preferable_services, &config.node_table_path))),
+			connect_backoff: Mutex::default(),
+			ban_list: Mutex::default(),
 			pool: pool_handle,
 			remote: remote,
 			local_sync_node: local_sync_node,
@@ -90,10 +112,34 @@ impl Context {
 		self.node_table.write().insert_many(nodes);
 	}
 
-	/// Penalize node.
-	pub fn penalize_node(&self, addr: &SocketAddr) {
-		trace!("Penalizing node {}", addr);
+	/// Penalize node and refuse its inbound connections for some time.
+	pub fn ban_node(&self, addr: &SocketAddr) {
+		if self.peer_permissions(addr).noban {
+			trace!("Not banning node {}, because it has noban permission", addr);
+			return;
+		}
+
+		trace!("Banning node {}", addr);
 		self.node_table.write().note_failure(addr);
+		self.ban_list.lock().ban(addr.ip(), time::Instant::now());
+	}
+
+	/// Penalize node that has failed to serve us (e.g. stalled the synchronization), without banning it.
+	pub fn note_failure(&self, addr: &SocketAddr) {
+		self.node_table.write().note_failure(addr);
+	}
+
+	/// Returns true if node is banned.
+	pub fn is_banned(&self, addr: &SocketAddr) -> bool {
+		self.ban_list.lock().is_banned(&addr.ip(), time::Instant::now())
+	}
+
+	/// Penalize node that we have failed to connect to.
+	fn note_outbound_failure(&self, addr: &SocketAddr) {
+		self.node_table.write().note_failure(addr);
+		if self.config.peers.contains(addr) {
+			self.connect_backoff.lock().note_failure(*addr, time::Instant::now());
+		}
 	}
 
 	/// Adds node to table.
@@ -109,7 +155,7 @@ impl Context {
 	}
 
 	/// Every 10 seconds check if we have reached maximum number of outbound connections.
-	/// If not, connect to best peers.
+	/// If not, connect to best peers (or to configured peers only, if in connect-only mode).
 	pub fn autoconnect(context: Arc<Context>, handle: &Handle) {
 		let c = context.clone();
 		// every 10 seconds connect to new peers (if needed)
@@ -118,24 +164,35 @@ impl Context {
 				// print traces
 				let ic = context.connection_counter.inbound_connections();
 				let oc = context.connection_counter.outbound_connections();
+				let bc = context.connection_counter.block_relay_only_connections();
 				info!("Inbound connections: ({}/{})", ic.0, ic.1);
 				info!("Outbound connections: ({}/{})", oc.0, oc.1);
+				info!("Block-relay-only connections: ({}/{})", bc.0, bc.1);
 
 				for channel in context.connections.channels().values() {
 					channel.session().maintain();
 				}
 
 				let needed = context.connection_counter.outbound_connections_needed() as usize;
-				if needed != 0 {
+				let block_relay_only_needed = context.connection_counter.block_relay_only_connections_needed() as usize;
+				if needed + block_relay_only_needed != 0 {
 					let used_addresses = context.connections.addresses();
-					let peers = context.node_table.read().nodes_with_services(&Services::default(), context.config.internet_protocol, &used_addresses, needed);
-					let addresses = peers.into_iter()
-						.map(|peer| peer.address())
-						.collect::<Vec<_>>();
+					let addresses = outbound_connection_candidates(
+						&context.config,
+						&*context.node_table.read(),
+						&mut *context.connect_backoff.lock(),
+						&used_addresses,
+						needed + block_relay_only_needed,
+						time::Instant::now(),
+					);
 
 					trace!("Creating {} more outbound connections", addresses.len());
-					for address in addresses {
-						Context::connect::<NormalSessionFactory>(context.clone(), address);
+					for (index, address) in addresses.into_iter().enumerate() {
+						// full-relay slots are filled first
+						match index < needed {
+							true => Context::connect::<NormalSessionFactory>(context.clone(), address),
+							false => Context::connect_block_relay_only(context.clone(), address),
+						}
 					}
 				}
 
@@ -151,16 +208,23 @@ impl Context {
 	}
 
 	/// Connect to socket using given context and handle.
-	fn connect_future<T>(context: Arc<Context>, socket: net::SocketAddr, handle: &Handle, config: &NetConfig) -> BoxedEmptyFuture where T: SessionFactory {
+	fn connect_future<T>(context: Arc<Context>, socket: net::SocketAddr, handle: &Handle, config: &NetConfig, block_relay_only: bool) -> BoxedEmptyFuture where T: SessionFactory {
 		trace!("Trying to connect to: {}", socket);
 		let connection = connect(&socket, handle, config);
 		Box::new(connection.then(move |result| {
 			match result {
+				Ok(DeadlineStatus::Meet(Ok(ref connection))) if !context.connection_counter.is_network_active() => {
+					// network has been disabled during handshake
+					trace!("Dropping connection with {}: network is disabled", connection.address);
+					context.note_close_outbound_connection(block_relay_only);
+					Box::new(finished(Ok(())))
+				},
 				Ok(DeadlineStatus::Meet(Ok(connection))) => {
 					// successful handshake
 					trace!("Connected to {}", connection.address);
 					context.node_table.write().insert(connection.address, connection.services);
-					let channel = context.connections.store::<T>(context.clone(), connection, Direction::Outbound);
+					context.connect_backoff.lock().note_success(&socket);
+					let channel = Context::store_connection::<T>(&context, connection, Direction::Outbound, block_relay_only);
 
 					// initialize session and then start reading messages
 					channel.session().initialize();
@@ -170,23 +234,23 @@ impl Context {
 					// protocol error
 					trace!("Handshake with {} failed with: {}", socket, err);
 					// TODO: close socket
-					context.node_table.write().note_failure(&socket);
-					context.connection_counter.note_close_outbound_connection();
+					context.note_outbound_failure(&socket);
+					context.note_close_outbound_connection(block_relay_only);
 					Box::new(finished(Ok(())))
 				},
 				Ok(DeadlineStatus::Timeout) => {
 					// connection time out
 					trace!("Handshake with {} timed out", socket);
 					// TODO: close socket
-					context.node_table.write().note_failure(&socket);
-					context.connection_counter.note_close_outbound_connection();
+					context.note_outbound_failure(&socket);
+					context.note_close_outbound_connection(block_relay_only);
 					Box::new(finished(Ok(())))
 				},
 				Err(_) => {
 					// network error
 					trace!("Unable to connect to {}", socket);
-					context.node_table.write().note_failure(&socket);
-					context.connection_counter.note_close_outbound_connection();
+					context.note_outbound_failure(&socket);
+					context.note_close_outbound_connection(block_relay_only);
 					Box::new(finished(Ok(())))
 				}
 			}
@@ -196,13 +260,64 @@ impl Context {
 
 	/// Connect to socket using given context.
 	pub fn connect<T>(context: Arc<Context>, socket: net::SocketAddr) where T: SessionFactory {
-		context.connection_counter.note_new_outbound_connection();
+		Context::open_outbound_connection::<T>(context, socket, false)
+	}
+
+	/// Open block-relay-only connection to socket using given context.
+	pub fn connect_block_relay_only(context: Arc<Context>, socket: net::SocketAddr) {
+		Context::open_outbound_connection::<BlockRelayOnlySessionFactory>(context, socket, true)
+	}
+
+	fn open_outbound_connection<T>(context: Arc<Context>, socket: net::SocketAddr, block_relay_only: bool) where T: SessionFactory {
+		if !context.connection_counter.is_network_active() {
+			trace!("Not connecting to {}: network is disabled", socket);
+			return;
+		}
+
+		match block_relay_only {
+			true => context.connection_counter.note_new_block_relay_only_connection(),
+			false => context.connection_counter.note_new_outbound_connection(),
+		}
+
 		context.remote.clone().spawn(move |handle| {
-			let config = context.config.clone();
-			context.pool.clone().spawn(Context::connect_future::<T>(context, socket, handle, &config.connection))
+			let mut config = context.config.connection.clone();
+			// ask peer to not announce transactions over block-relay-only connection
+			if block_relay_only {
+				config.relay = false;
+			}
+			context.pool.clone().spawn(Context::connect_future::<T>(context, socket, handle, &config, block_relay_only))
 		})
 	}
 
+	/// Decreases counter of outbound connections of given type.
+	fn note_close_outbound_connection(&self, block_relay_only: bool) {
+		match block_relay_only {
+			true => self.connection_counter.note_close_block_relay_only_connection(),
+			false => self.connection_counter.note_close_outbound_connection(),
+		}
+	}
+
+	/// Decreases counter of connections, matching given peer.
+	fn note_close_connection(&self, info: &PeerInfo) {
+		match info.direction {
+			Direction::Inbound => self.connection_counter.note_close_inbound_connection(),
+			Direction::Outbound => self.note_close_outbound_connection(info.block_relay_only),
+		}
+	}
+
+	/// Stores connection with completed handshake. Messages, exchanged during handshake, are accounted.
+	fn store_connection<T>(context: &Arc<Context>, connection: Connection, direction: Direction, block_relay_only: bool) -> Arc<Channel> where T: SessionFactory {
+		let traffic = connection.handshake_traffic.clone();
+		let channel = context.connections.store::<T>(context.clone(), connection, direction, block_relay_only);
+		for (command, bytes) in traffic.sent {
+			context.report_send_to(&channel, command, bytes);
+		}
+		for (command, bytes) in traffic.received {
+			context.report_recv_from(&channel, command, bytes);
+		}
+		channel
+	}
+
 	pub fn connect_normal(context: Arc<Context>, socket: net::SocketAddr) {
 		Self::connect::<NormalSessionFactory>(context, socket)
 	}
@@ -210,11 +325,17 @@ impl Context {
 	pub fn accept_connection_future(context: Arc<Context>, stream: TcpStream, socket: net::SocketAddr, handle: &Handle, config: NetConfig) -> BoxedEmptyFuture {
 		Box::new(accept_connection(stream, handle, &config, socket).then(move |result| {
 			match result {
+				Ok(DeadlineStatus::Meet(Ok(ref connection))) if !context.connection_counter.is_network_active() => {
+					// network has been disabled during handshake
+					trace!("Dropping connection with {}: network is disabled", connection.address);
+					context.connection_counter.note_close_inbound_connection();
+					Box::new(finished(Ok(())))
+				},
 				Ok(DeadlineStatus::Meet(Ok(connection))) => {
 					// successful handshake
 					trace!("Accepted connection from {}", connection.address);
 					context.node_table.write().insert(connection.address, connection.services);
-					let channel = context.connections.store::<NormalSessionFactory>(context.clone(), connection, Direction::Inbound);
+					let channel = Context::store_connection::<NormalSessionFactory>(&context, connection, Direction::Inbound, false);
 
 					// initialize session and then start reading messages
 					channel.session().initialize();
@@ -264,7 +385,10 @@ impl Context {
 				// because we acquire atomic value twice,
 				// it may happen that accept slightly more connections than we need
 				// we don't mind
-				if context.connection_counter.inbound_connections_needed() > 0 {
+				if context.is_banned(&socket) {
+					trace!("Refusing connection from banned node {}", socket);
+					let _ = stream.shutdown(net::Shutdown::Both);
+				} else if context.connection_counter.inbound_connections_needed() > 0 || context.evict_inbound() {
 					Context::accept_connection(context.clone(), stream, socket, config.clone());
 				} else {
 					// ignore result
@@ -320,7 +444,7 @@ impl Context {
 			Some(channel) => {
 				let info = channel.peer_info();
 				let message = Message::new(info.magic, info.version, payload).expect("failed to create outgoing message");
-				channel.session().stats().lock().report_send(T::command().into(), message.len());
+				context.report_send_to(&channel, T::command().into(), message.len());
 				Context::send(context, channel, message)
 			},
 			None => {
@@ -333,7 +457,16 @@ impl Context {
 
 	pub fn send_message_to_peer<T>(context: Arc<Context>, peer: PeerId, message: T) -> IoFuture<()> where T: AsRef<[u8]> + Send + 'static {
 		match context.connections.channel(peer) {
-			Some(channel) => Context::send(context, channel, message),
+			Some(channel) => {
+				// message is already serialized => command is read from its header
+				let command = message.as_ref().get(..MESSAGE_HEADER_LEN)
+					.and_then(|header| MessageHeader::deserialize(header, channel.peer_info().magic).ok())
+					.map(|header| header.command);
+				if let Some(command) = command {
+					context.report_send_to(&channel, command, message.as_ref().len());
+				}
+				Context::send(context, channel, message)
+			},
 			None => {
 				// peer no longer exists.
 				// TODO: should we return error here?
@@ -368,10 +501,7 @@ impl Context {
 			channel.session().on_close();
 			trace!("Disconnecting from {}", info.address);
 			channel.shutdown();
-			match info.direction {
-				Direction::Inbound => self.connection_counter.note_close_inbound_connection(),
-				Direction::Outbound => self.connection_counter.note_close_outbound_connection(),
-			}
+			self.note_close_connection(&info);
 		}
 	}
 
@@ -383,21 +513,114 @@ impl Context {
 			trace!("Disconnecting from {} caused by {}", info.address, error.description());
 			channel.shutdown();
 			self.node_table.write().note_failure(&info.address);
-			match info.direction {
-				Direction::Inbound => self.connection_counter.note_close_inbound_connection(),
-				Direction::Outbound => self.connection_counter.note_close_outbound_connection(),
+			self.note_close_connection(&info);
+		}
+	}
+
+	/// Closes inbound connection, selected by the eviction heuristic.
+	/// Returns true if some connection has been closed.
+	pub fn evict_inbound(&self) -> bool {
+		// never make room for inbound connections that we are not going to accept anyway
+		if !self.connection_counter.is_network_active() || self.connection_counter.inbound_connections().1 == 0 {
+			return false;
+		}
+
+		let candidates = self.connections.channels().into_iter()
+			.map(|(id, channel)| (id, channel.peer_info(), channel))
+			.filter(|&(_, ref info, _)| info.direction == Direction::Inbound && !info.permissions.noban)
+			.map(|(id, info, channel)| EvictionCandidate {
+				id: id,
+				last_block: channel.session().stats().lock().last_block,
+				netgroup: netgroup(&info.address.ip()),
+			})
+			.collect();
+
+		match self.peer_manager.evict_inbound(candidates) {
+			Some(id) => {
+				trace!("Evicting inbound peer#{}", id);
+				self.close_channel(id);
+				true
+			},
+			None => false,
+		}
+	}
+
+	/// Returns true if networking is enabled.
+	pub fn is_network_active(&self) -> bool {
+		self.connection_counter.is_network_active()
+	}
+
+	/// Enables or disables networking. When disabled, all peers are disconnected and no new connections are made.
+	pub fn set_network_active(&self, active: bool) {
+		info!("Setting network active: {}", active);
+		self.connection_counter.set_network_active(active);
+		if !active {
+			for id in self.connections.channels().keys() {
+				self.close_channel(*id);
 			}
 		}
 	}
 
-	pub fn create_sync_session(&self, start_height: i32, services: Services, outbound_connection: OutboundSyncConnectionRef) -> InboundSyncConnectionRef {
-		self.local_sync_node.create_sync_session(start_height, services, outbound_connection)
+	/// Returns permissions, granted to the node with given address.
+	pub fn peer_permissions(&self, addr: &SocketAddr) -> PeerPermissions {
+		peer_permissions(&self.config.permissions, &addr.ip())
+	}
+
+	pub fn create_sync_session(&self, start_height: i32, services: Services, permissions: PeerPermissions, outbound_connection: OutboundSyncConnectionRef) -> InboundSyncConnectionRef {
+		self.local_sync_node.create_sync_session(start_height, services, permissions, outbound_connection)
+	}
+
+	/// Accounts bytes, sent to some peer.
+	pub fn report_send(&self, command: Command, bytes: usize) {
+		self.net_totals.lock().report_send(command, bytes);
+	}
+
+	/// Accounts bytes, received from some peer.
+	pub fn report_recv(&self, command: Command, bytes: usize) {
+		self.net_totals.lock().report_recv(command, bytes);
+	}
+
+	/// Accounts bytes, sent to given peer, both in peer stats and in totals.
+	fn report_send_to(&self, channel: &Channel, command: Command, bytes: usize) {
+		channel.session().stats().lock().report_send(command.clone(), bytes);
+		self.report_send(command, bytes);
+	}
+
+	/// Accounts bytes, received from given peer, both in peer stats and in totals.
+	fn report_recv_from(&self, channel: &Channel, command: Command, bytes: usize) {
+		channel.session().stats().lock().report_recv(command.clone(), bytes);
+		self.report_recv(command, bytes);
+	}
+
+	/// Returns traffic totals of all connections.
+	pub fn net_totals(&self) -> NetTotals {
+		self.net_totals.lock().clone()
 	}
 
 	pub fn connections(&self) -> &Connections {
 		&self.connections
 	}
 
+	/// Returns protocol version, advertised to peers.
+	pub fn protocol_version(&self) -> u32 {
+		self.config.connection.protocol_version
+	}
+
+	/// Returns user agent, advertised to peers.
+	pub fn user_agent(&self) -> &str {
+		&self.config.connection.user_agent
+	}
+
+	/// Returns network timeouts profile.
+	pub fn timeouts(&self) -> &TimeoutProfile {
+		&self.config.connection.timeouts
+	}
+
+	/// Returns maximal size of serialized block, accepted from peers.
+	pub fn max_block_size(&self) -> usize {
+		self.config.connection.max_block_size
+	}
+
 	pub fn nodes(&self) -> Vec<Node> {
 		self.node_table.read().nodes()
 	}
@@ -447,8 +670,12 @@ impl P2P {
 	}
 
 	pub fn run(&self) -> Result<(), Box<error::Error>> {
-		for peer in &self.config.peers {
-			self.connect::<NormalSessionFactory>(*peer);
+		// configured peers are dialed (and redialed) by autoconnect, starting immediately
+		Context::autoconnect(self.context.clone(), &self.event_loop_handle);
+
+		if self.config.is_connect_only() {
+			info!("Connect-only mode: peer discovery and inbound connections are disabled");
+			return Ok(());
 		}
 
 		let resolver = try!(DnsResolver::system_config(&self.event_loop_handle));
@@ -456,7 +683,6 @@ impl P2P {
 			self.connect_to_seednode(&resolver, seed);
 		}
 
-		Context::autoconnect(self.context.clone(), &self.event_loop_handle);
 		try!(self.listen());
 		Ok(())
 	}
@@ -500,3 +726,298 @@ impl P2P {
 		&self.context
 	}
 }
+
+/// Selects addresses to open new outbound connections to.
+/// In connect-only mode only configured peers are selected, respecting their reconnection delays.
+/// Otherwise best nodes from the node table are selected.
+fn outbound_connection_candidates(config: &Config, node_table: &NodeTable, backoff: &mut ConnectBackoff, used_addresses: &HashSet<SocketAddr>, needed: usize, now: time::Instant) -> Vec<SocketAddr> {
+	if config.is_connect_only() {
+		let addresses = config.peers.iter()
+			.filter(|peer| !used_addresses.contains(peer) && backoff.is_ready(peer, now))
+			.take(needed)
+			.cloned()
+			.collect::<Vec<_>>();
+		for address in &addresses {
+			backoff.note_attempt(*address, now);
+		}
+		return addresses;
+	}
+
+	node_table.nodes_with_services(&Services::default(), config.internet_protocol, used_addresses, needed)
+		.into_iter()
+		.map(|peer| peer.address())
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use std::{env, fs, process};
+	use std::collections::HashSet;
+	use std::net::{SocketAddr, TcpListener as StdTcpListener, TcpStream as StdTcpStream};
+	use std::sync::Arc;
+	use std::time::{Duration, Instant};
+	use futures_cpupool::CpuPool;
+	use tokio_core::net::TcpStream;
+	use tokio_core::reactor::Core;
+	use bytes::Bytes;
+	use message::{Message, serialize_payload};
+	use network::MAX_BLOCK_SIZE;
+	use message::common::Services;
+	use io::HandshakeTraffic;
+	use net::{Config as NetConfig, Connection, PeerContext};
+	use message::types;
+	use protocol::{LocalSyncNode, InboundSyncConnection, InboundSyncConnectionRef, OutboundSyncConnectionRef,
+		InboundSyncConnectionState, InboundSyncConnectionStateRef};
+	use session::{Session, SessionFactory, NormalSessionFactory};
+	use util::{NodeTable, ConnectBackoff, InternetProtocol, PeerInfo, PeerPermissions, Direction};
+	use Config;
+	use super::{Context, outbound_connection_candidates};
+
+	struct DummyLocalSyncNode;
+
+	/// Creates sessions without protocols => received messages are only accounted.
+	struct SilentSessionFactory;
+
+	impl SessionFactory for SilentSessionFactory {
+		fn new_session(context: Arc<Context>, info: PeerInfo, synchronous: bool) -> Session {
+			Session::new(Arc::new(PeerContext::new(context, info, synchronous)), vec![])
+		}
+	}
+
+	impl LocalSyncNode for DummyLocalSyncNode {
+		fn create_sync_session(&self, _height: i32, _services: Services, _permissions: PeerPermissions, _outbound: OutboundSyncConnectionRef) -> InboundSyncConnectionRef {
+			Box::new(DummyInboundSyncConnection)
+		}
+	}
+
+	/// Sync session, which must not receive any messages.
+	struct DummyInboundSyncConnection;
+
+	impl InboundSyncConnectionState for DummyInboundSyncConnection {
+		fn synchronizing(&self) -> bool { false }
+	}
+
+	impl InboundSyncConnection for DummyInboundSyncConnection {
+		fn sync_state(&self) -> InboundSyncConnectionStateRef { Arc::new(DummyInboundSyncConnection) }
+		fn start_sync_session(&self, _peer_name: String, _version: types::Version) {}
+		fn close_session(&self) {}
+		fn on_inventory(&self, _message: types::Inv) { unreachable!() }
+		fn on_getdata(&self, _message: types::GetData) { unreachable!() }
+		fn on_getblocks(&self, _message: types::GetBlocks) { unreachable!() }
+		fn on_getheaders(&self, _message: types::GetHeaders, _id: u32) { unreachable!() }
+		fn on_transaction(&self, _message: types::Tx) { unreachable!() }
+		fn on_block(&self, _message: types::Block) { unreachable!() }
+		fn on_headers(&self, _message: types::Headers) { unreachable!() }
+		fn on_mempool(&self, _message: types::MemPool) { unreachable!() }
+		fn on_filterload(&self, _message: types::FilterLoad) { unreachable!() }
+		fn on_filteradd(&self, _message: types::FilterAdd) { unreachable!() }
+		fn on_filterclear(&self, _message: types::FilterClear) { unreachable!() }
+		fn on_merkleblock(&self, _message: types::MerkleBlock) { unreachable!() }
+		fn on_sendheaders(&self, _message: types::SendHeaders) { unreachable!() }
+		fn on_feefilter(&self, _message: types::FeeFilter) { unreachable!() }
+		fn on_send_compact(&self, _message: types::SendCompact) { unreachable!() }
+		fn on_compact_block(&self, _message: types::CompactBlock) { unreachable!() }
+		fn on_get_block_txn(&self, _message: types::GetBlockTxn) { unreachable!() }
+		fn on_block_txn(&self, _message: types::BlockTxn) { unreachable!() }
+		fn on_notfound(&self, _message: types::NotFound) { unreachable!() }
+	}
+
+	fn config(peers: Vec<SocketAddr>) -> Config {
+		Config {
+			threads: 1,
+			inbound_connections: 10,
+			outbound_connections: 10,
+			block_relay_only_connections: 2,
+			protect_by_netgroup: 4,
+			protect_by_last_block: 4,
+			connection: NetConfig {
+				protocol_version: 0,
+				protocol_minimum: 0,
+				magic: 0,
+				local_address: "127.0.0.1:8233".parse().unwrap(),
+				services: Services::default(),
+				user_agent: "test".into(),
+				start_height: 0,
+				relay: false,
+				timeouts: Default::default(),
+				max_block_size: MAX_BLOCK_SIZE,
+			},
+			peers: peers,
+			seeds: vec!["seed.example.com".into()],
+			node_table_path: "".into(),
+			preferable_services: Services::default(),
+			internet_protocol: InternetProtocol::default(),
+			permissions: Vec::new(),
+		}
+	}
+
+	fn context(core: &Core, name: &str) -> Arc<Context> {
+		let mut config = config(vec![]);
+		config.node_table_path = env::temp_dir().join(format!("pzec-p2p-{}-{}.csv", name, process::id()));
+		let context = Context::new(Box::new(DummyLocalSyncNode), CpuPool::new(1), core.remote(), config).unwrap();
+		Arc::new(context)
+	}
+
+	fn peer_info(id: usize) -> PeerInfo {
+		PeerInfo {
+			id: id,
+			address: "127.0.0.1:18233".parse().unwrap(),
+			user_agent: "test".into(),
+			direction: Direction::Inbound,
+			version: 0,
+			version_message: types::Version::default(),
+			magic: 0,
+			permissions: PeerPermissions::default(),
+			addrv2: false,
+			block_relay_only: false,
+		}
+	}
+
+	fn node_table() -> NodeTable {
+		let mut node_table = NodeTable::default();
+		node_table.insert("10.0.0.1:8233".parse().unwrap(), Services::default());
+		node_table.insert("10.0.0.2:8233".parse().unwrap(), Services::default());
+		node_table
+	}
+
+	#[test]
+	fn outbound_candidates_are_discovered_from_node_table() {
+		let candidates = outbound_connection_candidates(&config(vec![]), &node_table(), &mut ConnectBackoff::default(), &HashSet::new(), 10, Instant::now());
+		assert_eq!(candidates.len(), 2);
+	}
+
+	#[test]
+	fn connect_only_mode_never_dials_discovered_nodes() {
+		let peer: SocketAddr = "127.0.0.1:18233".parse().unwrap();
+		let config = config(vec![peer]);
+		let node_table = node_table();
+		let mut backoff = ConnectBackoff::default();
+		let now = Instant::now();
+		assert!(config.is_connect_only());
+
+		// only configured peer is dialed
+		assert_eq!(outbound_connection_candidates(&config, &node_table, &mut backoff, &HashSet::new(), 10, now), vec![peer]);
+
+		// configured peer is unreachable => nothing is dialed until reconnect delay passes
+		backoff.note_failure(peer, now);
+		assert_eq!(outbound_connection_candidates(&config, &node_table, &mut backoff, &HashSet::new(), 10, now), vec![]);
+		assert_eq!(outbound_connection_candidates(&config, &node_table, &mut backoff, &HashSet::new(), 10, now + Duration::from_secs(30)), vec![peer]);
+
+		// configured peer is connected => nothing is dialed
+		let used_addresses = vec![peer].into_iter().collect();
+		assert_eq!(outbound_connection_candidates(&config, &node_table, &mut backoff, &used_addresses, 10, now + Duration::from_secs(3600)), vec![]);
+	}
+
+	#[test]
+	fn no_outbound_connections_are_opened_while_network_is_inactive() {
+		let core = Core::new().unwrap();
+		let context = context(&core, "network-inactive");
+		let peer: SocketAddr = "127.0.0.1:18233".parse().unwrap();
+
+		context.set_network_active(false);
+		assert!(!context.is_network_active());
+		Context::connect::<NormalSessionFactory>(context.clone(), peer);
+		assert_eq!(context.connection_counter.outbound_connections().0, 0);
+
+		context.set_network_active(true);
+		assert!(context.is_network_active());
+		Context::connect::<NormalSessionFactory>(context.clone(), peer);
+		assert_eq!(context.connection_counter.outbound_connections().0, 1);
+
+		let _ = fs::remove_file(&context.config.node_table_path);
+	}
+
+	#[test]
+	fn net_totals_include_traffic_of_closed_sessions() {
+		let core = Core::new().unwrap();
+		let context = context(&core, "net-totals");
+
+		{
+			let session = Session::new(Arc::new(PeerContext::new(context.clone(), peer_info(1), true)), vec![]);
+			session.on_message("inv".into(), Bytes::new_with_len(100)).unwrap();
+		}
+		let session = Session::new(Arc::new(PeerContext::new(context.clone(), peer_info(2), true)), vec![]);
+		session.on_message("inv".into(), Bytes::new_with_len(50)).unwrap();
+		session.on_message("block".into(), Bytes::new_with_len(1000)).unwrap();
+
+		// received messages are accounted together with 24-bytes headers
+		let totals = context.net_totals();
+		assert_eq!(totals.total_recv, 1222);
+		assert_eq!(totals.recv_by_command[&"inv".into()], 198);
+		assert_eq!(totals.recv_by_command[&"block".into()], 1024);
+
+		let _ = fs::remove_file(&context.config.node_table_path);
+	}
+
+	#[test]
+	fn net_totals_account_sent_and_received_messages_equally() {
+		let core = Core::new().unwrap();
+		let context = context(&core, "net-totals-symmetric");
+
+		// connection over real socket, which has just completed the handshake
+		let listener = StdTcpListener::bind("127.0.0.1:0").unwrap();
+		let address = listener.local_addr().unwrap();
+		let stream = TcpStream::from_stream(StdTcpStream::connect(address).unwrap(), &core.handle()).unwrap();
+		let _remote = listener.accept().unwrap();
+
+		let version_len = Message::new(0, 0, &types::Version::default()).unwrap().len();
+		let verack_len = Message::new(0, 0, &types::Verack).unwrap().len();
+		let connection = Connection {
+			stream: stream.into(),
+			version: 0,
+			version_message: types::Version::default(),
+			magic: 0,
+			services: Services::default(),
+			address: address,
+			addrv2: false,
+			handshake_traffic: HandshakeTraffic {
+				sent: vec![("version".into(), version_len), ("verack".into(), verack_len)],
+				received: vec![("version".into(), version_len), ("verack".into(), verack_len)],
+			},
+		};
+		let channel = Context::store_connection::<SilentSessionFactory>(&context, connection, Direction::Inbound, false);
+		let peer = channel.peer_info().id;
+
+		// handshake is accounted in both directions
+		let totals = context.net_totals();
+		assert_eq!(totals.total_send, (version_len + verack_len) as u64);
+		assert_eq!(totals.total_recv, (version_len + verack_len) as u64);
+
+		// ping is sent twice: as payload and as already serialized message (as responses are sent)
+		let ping = types::Ping::new(1);
+		let ping_len = Message::new(0, 0, &ping).unwrap().len();
+		let _ = Context::send_to_peer(context.clone(), peer, &ping);
+		let _ = Context::send_message_to_peer(context.clone(), peer, Message::new(0, 0, &ping).unwrap());
+		// ... and received once
+		channel.session().on_message("ping".into(), serialize_payload(&ping, 0).unwrap()).unwrap();
+
+		let totals = context.net_totals();
+		assert_eq!(totals.total_send, (version_len + verack_len + 2 * ping_len) as u64);
+		assert_eq!(totals.total_recv, (version_len + verack_len + ping_len) as u64);
+		assert_eq!(totals.send_by_command[&"ping".into()], 2 * ping_len as u64);
+		assert_eq!(totals.recv_by_command[&"ping".into()], ping_len as u64);
+
+		// peer stats are the same, as there's the only peer
+		let stats = channel.session().stats().lock();
+		assert_eq!(stats.total_send, totals.total_send);
+		assert_eq!(stats.total_recv, totals.total_recv);
+
+		let _ = fs::remove_file(&context.config.node_table_path);
+	}
+
+	#[test]
+	fn deprecated_alert_does_not_penalize_peer() {
+		let core = Core::new().unwrap();
+		let context = context(&core, "alert");
+		let info = peer_info(1);
+		let address = info.address;
+		let session = NormalSessionFactory::new_session(context.clone(), info, true);
+
+		// both well-formed and malformed alerts are ignored
+		assert!(session.on_message("alert".into(), "0568656c6c6f03736967".into()).is_ok());
+		assert!(session.on_message("alert".into(), "ff".into()).is_ok());
+		assert!(!context.is_banned(&address));
+
+		let _ = fs::remove_file(&context.config.node_table_path);
+	}
+}
@@ -2,7 +2,9 @@ use std::sync::Arc;
 use std::time::Duration;
 use bytes::Bytes;
 use message::{Error, Command, deserialize_payload, Payload};
-use message::types::{GetAddr, Addr};
+use message::types::{GetAddr, Addr, AddrV2, SendAddrV2};
+use message::types::addr::AddressEntry;
+use message::types::addrv2::AddressEntryV2;
 use protocol::Protocol;
 use net::PeerContext;
 use util::Direction;
@@ -12,13 +14,28 @@ pub struct AddrProtocol {
 	context: Arc<PeerContext>,
 	/// True if this is a connection to the seednode && we should disconnect after receiving addr message
 	is_seed_node_connection: bool,
+	/// True if peer wants to receive `addrv2` messages instead of `addr`
+	addrv2: bool,
 }
 
 impl AddrProtocol {
 	pub fn new(context: Arc<PeerContext>, is_seed_node_connection: bool) -> Self {
+		let addrv2 = context.info().addrv2;
 		AddrProtocol {
 			context: context,
 			is_seed_node_connection: is_seed_node_connection,
+			addrv2: addrv2,
+		}
+	}
+
+	fn on_addresses(&self, addresses: Vec<AddressEntry>) {
+		let nodes_len = addresses.len();
+		self.context.global().update_node_table(addresses);
+		// seednodes are currently responding with two addr messages:
+		// 1) addr message with single address - seednode itself
+		// 2) addr message with 1000 addresses (seednode node_table contents)
+		if self.is_seed_node_connection && nodes_len > 1 {
+			self.context.close();
 		}
 	}
 }
@@ -35,26 +52,34 @@ impl Protocol for AddrProtocol {
 		// meanwhile seednodes, surprisingly, send addr message even before they are asked for it
 		if command == &GetAddr::command() {
 			let _: GetAddr = try!(deserialize_payload(payload, self.context.info().version));
-			let entries = self.context.global().node_table_entries().into_iter().map(Into::into).collect();
-			let addr = Addr::new(entries);
-			self.context.send_response_inline(&addr);
+			let entries: Vec<AddressEntry> = self.context.global().node_table_entries().into_iter().map(Into::into).collect();
+			if self.addrv2 {
+				let addrv2 = AddrV2::new(entries.into_iter().map(Into::into).collect());
+				self.context.send_response_inline(&addrv2);
+			} else {
+				let addr = Addr::new(entries);
+				self.context.send_response_inline(&addr);
+			}
 		} else if command == &Addr::command() {
 			let addr: Addr = try!(deserialize_payload(payload, self.context.info().version));
 			match addr {
 				Addr::V0(_) => {
 					unreachable!("This version of protocol is not supported!");
 				},
-				Addr::V31402(addr) => {
-					let nodes_len = addr.addresses.len();
-					self.context.global().update_node_table(addr.addresses);
-					// seednodes are currently responding with two addr messages:
-					// 1) addr message with single address - seednode itself
-					// 2) addr message with 1000 addresses (seednode node_table contents)
-					if self.is_seed_node_connection && nodes_len > 1 {
-						self.context.close();
-					}
-				},
+				Addr::V31402(addr) => self.on_addresses(addr.addresses),
+			}
+		} else if command == &AddrV2::command() {
+			let addrv2: AddrV2 = try!(deserialize_payload(payload, self.context.info().version));
+			let addresses: Vec<_> = addrv2.addresses.iter().filter_map(AddressEntryV2::to_legacy).collect();
+			// node table only holds IP addresses => onion (TorV3) and other non-IP addresses are rejected
+			let rejected = addrv2.addresses.len() - addresses.len();
+			if rejected != 0 {
+				trace!("Rejecting {} non-IP (TorV3, I2P, ...) addresses from peer {}", rejected, self.context.info().id);
 			}
+			self.on_addresses(addresses);
+		} else if command == &SendAddrV2::command() {
+			let _: SendAddrV2 = try!(deserialize_payload(payload, self.context.info().version));
+			self.addrv2 = true;
 		}
 		Ok(())
 	}
@@ -80,7 +105,7 @@ impl Protocol for SeednodeProtocol {
 	fn on_message(&mut self, command: &Command, _payload: &Bytes) -> Result<(), Error> {
 		// Seednodes send addr message more than once with different addresses.
 		// We can't disconnect after first read. Let's delay it by 60 seconds.
-		if !self.disconnecting && command == &Addr::command() {
+		if !self.disconnecting && (command == &Addr::command() || command == &AddrV2::command()) {
 			self.disconnecting = true;
 			let context = self.context.global().clone();
 			let peer = self.context.info().id;
@@ -10,15 +10,13 @@ use util::nonce::{NonceGenerator, RandomNonce};
 
 /// Time that must pass since last message from this peer, before we send ping request
 const PING_INTERVAL_S: f64 = 60f64;
-/// If peer has not responded to our ping request with pong during this interval => close connection
-const MAX_PING_RESPONSE_TIME_S: f64 = 60f64;
 
 /// Ping state
 #[derive(Debug, Copy, Clone, PartialEq)]
 enum State {
 	/// Peer is sending us messages && we wait for `PING_INTERVAL_S` to pass before sending ping request
 	WaitingTimeout(f64),
-	/// Ping message is sent to the peer && we are waiting for pong response for `MAX_PING_RESPONSE_TIME_S`
+	/// Ping message is sent to the peer && we are waiting for pong response for `max_ping_response_time_s`
 	WaitingPong(f64),
 }
 
@@ -31,15 +29,19 @@ pub struct PingProtocol<T = RandomNonce, C = PeerContext> {
 	state: State,
 	/// Last nonce sent in the ping message.
 	last_ping_nonce: Option<u64>,
+	/// If peer has not responded to our ping request with pong during this interval => close connection
+	max_ping_response_time_s: f64,
 }
 
 impl PingProtocol {
 	pub fn new(context: Arc<PeerContext>) -> Self {
+		let ping_response = context.global().timeouts().ping_response;
 		PingProtocol {
 			context: context,
 			nonce_generator: RandomNonce::default(),
 			state: State::WaitingTimeout(time::precise_time_s()),
 			last_ping_nonce: None,
+			max_ping_response_time_s: ping_response.as_secs() as f64 + ping_response.subsec_nanos() as f64 / 1_000_000_000f64,
 		}
 	}
 }
@@ -64,8 +66,8 @@ impl Protocol for PingProtocol {
 				}
 			},
 			State::WaitingPong(time) => {
-				// if no new messages from peer for last MAX_PING_RESPONSE_TIME_S => disconnect
-				if now - time > MAX_PING_RESPONSE_TIME_S {
+				// if no new messages from peer for last max_ping_response_time_s => disconnect
+				if now - time > self.max_ping_response_time_s {
 					trace!("closing connection to peer {}: no messages for last {} seconds", self.context.info().id, now - time);
 					self.context.close();
 				}
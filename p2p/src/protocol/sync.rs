@@ -1,8 +1,10 @@
 use std::sync::Arc;
 use bytes::Bytes;
-use message::{Command, Error, Payload, Services, types, deserialize_payload};
+use message::{Command, Error, Payload, Services, types, deserialize_payload, deserialize_payload_with_max_len};
+use message::common::InventoryType;
 use protocol::Protocol;
 use net::PeerContext;
+use util::PeerPermissions;
 
 pub type InboundSyncConnectionRef = Box<InboundSyncConnection>;
 pub type OutboundSyncConnectionRef = Arc<OutboundSyncConnection>;
@@ -10,7 +12,7 @@ pub type LocalSyncNodeRef = Box<LocalSyncNode>;
 pub type InboundSyncConnectionStateRef = Arc<InboundSyncConnectionState>;
 
 pub trait LocalSyncNode : Send + Sync {
-	fn create_sync_session(&self, height: i32, services: Services, outbound: OutboundSyncConnectionRef) -> InboundSyncConnectionRef;
+	fn create_sync_session(&self, height: i32, services: Services, permissions: PeerPermissions, outbound: OutboundSyncConnectionRef) -> InboundSyncConnectionRef;
 }
 
 pub trait InboundSyncConnectionState: Send + Sync {
@@ -35,6 +37,10 @@ pub trait InboundSyncConnection : Send + Sync {
 	fn on_merkleblock(&self, message: types::MerkleBlock);
 	fn on_sendheaders(&self, message: types::SendHeaders);
 	fn on_feefilter(&self, message: types::FeeFilter);
+	fn on_send_compact(&self, message: types::SendCompact);
+	fn on_compact_block(&self, message: types::CompactBlock);
+	fn on_get_block_txn(&self, message: types::GetBlockTxn);
+	fn on_block_txn(&self, message: types::BlockTxn);
 	fn on_notfound(&self, message: types::NotFound);
 }
 
@@ -54,9 +60,18 @@ pub trait OutboundSyncConnection : Send + Sync {
 	fn send_merkleblock(&self, message: &types::MerkleBlock);
 	fn send_sendheaders(&self, message: &types::SendHeaders);
 	fn send_feefilter(&self, message: &types::FeeFilter);
+	fn send_send_compact(&self, message: &types::SendCompact);
+	fn send_compact_block(&self, message: &types::CompactBlock);
+	fn send_get_block_txn(&self, message: &types::GetBlockTxn);
+	fn send_block_txn(&self, message: &types::BlockTxn);
 	fn send_notfound(&self, message: &types::NotFound);
 	fn ignored(&self, id: u32);
+	/// Closes connection to the peer, which fails to serve us. Peer isn't banned.
 	fn close(&self);
+	/// Bans misbehaving peer for a limited time and closes connection to it.
+	fn ban(&self);
+	/// Called when peer has provided us with the new block, which has been accepted by sync.
+	fn report_new_block(&self);
 }
 
 struct OutboundSync {
@@ -73,6 +88,18 @@ impl OutboundSync {
 
 impl OutboundSyncConnection for OutboundSync {
 	fn send_inventory(&self, message: &types::Inv) {
+		if self.context.info().block_relay_only {
+			// transactions are never announced over block-relay-only connections
+			let inventory = message.inventory.iter()
+				.filter(|item| item.inv_type != InventoryType::MessageTx)
+				.cloned()
+				.collect::<Vec<_>>();
+			if !inventory.is_empty() {
+				self.context.send_request(&types::Inv::with_inventory(inventory));
+			}
+			return;
+		}
+
 		self.context.send_request(message);
 	}
 
@@ -89,6 +116,10 @@ impl OutboundSyncConnection for OutboundSync {
 	}
 
 	fn send_transaction(&self, message: &types::Tx) {
+		if self.context.info().block_relay_only {
+			return;
+		}
+
 		self.context.send_request(message);
 	}
 
@@ -132,6 +163,22 @@ impl OutboundSyncConnection for OutboundSync {
 		self.context.send_request(message);
 	}
 
+	fn send_send_compact(&self, message: &types::SendCompact) {
+		self.context.send_request(message);
+	}
+
+	fn send_compact_block(&self, message: &types::CompactBlock) {
+		self.context.send_request(message);
+	}
+
+	fn send_get_block_txn(&self, message: &types::GetBlockTxn) {
+		self.context.send_request(message);
+	}
+
+	fn send_block_txn(&self, message: &types::BlockTxn) {
+		self.context.send_request(message);
+	}
+
 	fn send_notfound(&self, message: &types::NotFound) {
 		self.context.send_request(message);
 	}
@@ -141,9 +188,18 @@ impl OutboundSyncConnection for OutboundSync {
 	}
 
 	fn close(&self) {
-		self.context.global().penalize_node(&self.context.info().address);
+		self.context.global().note_failure(&self.context.info().address);
 		self.context.close()
 	}
+
+	fn ban(&self) {
+		self.context.global().ban_node(&self.context.info().address);
+		self.context.close()
+	}
+
+	fn report_new_block(&self) {
+		self.context.stats().lock().report_new_block();
+	}
 }
 
 pub struct SyncProtocol {
@@ -155,7 +211,8 @@ pub struct SyncProtocol {
 impl SyncProtocol {
 	pub fn new(context: Arc<PeerContext>) -> Self {
 		let outbound_connection = Arc::new(OutboundSync::new(context.clone()));
-		let inbound_connection = context.global().create_sync_session(0, context.info().version_message.services(), outbound_connection);
+		let version_message = &context.info().version_message;
+		let inbound_connection = context.global().create_sync_session(version_message.start_height().unwrap_or(0), version_message.services(), context.info().permissions, outbound_connection);
 		let state = inbound_connection.sync_state();
 		SyncProtocol {
 			inbound_connection: inbound_connection,
@@ -176,6 +233,7 @@ impl Protocol for SyncProtocol {
 
 	fn on_message(&mut self, command: &Command, payload: &Bytes) -> Result<(), Error> {
 		let version = self.context.info().version;
+		let permissions = self.context.info().permissions;
 		if command == &types::Inv::command() {
 			// we are synchronizing => we ask only for blocks with known headers => there are no useful blocks hashes for us
 			// we are synchronizing => we ignore all transactions until it is completed => there are no useful transactions hashes for us
@@ -187,7 +245,7 @@ impl Protocol for SyncProtocol {
 			self.inbound_connection.on_inventory(message);
 		}
 		else if command == &types::GetData::command() {
-			if self.state.synchronizing() {
+			if self.state.synchronizing() && !permissions.download {
 				return Ok(());
 			}
 
@@ -195,7 +253,7 @@ impl Protocol for SyncProtocol {
 			self.inbound_connection.on_getdata(message);
 		}
 		else if command == &types::GetBlocks::command() {
-			if self.state.synchronizing() {
+			if self.state.synchronizing() && !permissions.download {
 				return Ok(());
 			}
 
@@ -203,7 +261,7 @@ impl Protocol for SyncProtocol {
 			self.inbound_connection.on_getblocks(message);
 		}
 		else if command == &types::GetHeaders::command() {
-			if self.state.synchronizing() {
+			if self.state.synchronizing() && !permissions.download {
 				return Ok(());
 			}
 
@@ -224,11 +282,11 @@ impl Protocol for SyncProtocol {
 			self.inbound_connection.on_transaction(message);
 		}
 		else if command == &types::Block::command() {
-			let message: types::Block = try!(deserialize_payload(payload, version));
+			let message: types::Block = try!(deserialize_payload_with_max_len(payload, version, self.context.global().max_block_size()));
 			self.inbound_connection.on_block(message);
 		}
 		else if command == &types::MemPool::command() {
-			if self.state.synchronizing() {
+			if self.state.synchronizing() && !permissions.mempool {
 				return Ok(());
 			}
 
@@ -263,10 +321,33 @@ impl Protocol for SyncProtocol {
 			let message: types::FeeFilter = try!(deserialize_payload(payload, version));
 			self.inbound_connection.on_feefilter(message);
 		}
+		else if command == &types::SendCompact::command() {
+			let message: types::SendCompact = try!(deserialize_payload(payload, version));
+			self.inbound_connection.on_send_compact(message);
+		}
+		else if command == &types::CompactBlock::command() {
+			let message: types::CompactBlock = try!(deserialize_payload(payload, version));
+			self.inbound_connection.on_compact_block(message);
+		}
+		else if command == &types::GetBlockTxn::command() {
+			if self.state.synchronizing() && !permissions.download {
+				return Ok(());
+			}
+
+			let message: types::GetBlockTxn = try!(deserialize_payload(payload, version));
+			self.inbound_connection.on_get_block_txn(message);
+		}
+		else if command == &types::BlockTxn::command() {
+			let message: types::BlockTxn = try!(deserialize_payload(payload, version));
+			self.inbound_connection.on_block_txn(message);
+		}
 		else if command == &types::NotFound::command() {
 			let message: types::NotFound = try!(deserialize_payload(payload, version));
 			self.inbound_connection.on_notfound(message);
 		}
+		else if command == &types::Alert::command() {
+			on_alert(payload, version);
+		}
 		Ok(())
 	}
 
@@ -274,3 +355,12 @@ impl Protocol for SyncProtocol {
 		self.inbound_connection.close_session()
 	}
 }
+
+/// Alert system is deprecated. Alerts are parsed and discarded, even if they are malformed,
+/// to remain compatible with older peers.
+fn on_alert(payload: &Bytes, version: u32) {
+	match deserialize_payload::<types::Alert>(payload, version) {
+		Ok(_) => trace!("Ignoring deprecated alert message"),
+		Err(err) => trace!("Ignoring malformed deprecated alert message: {}", err),
+	}
+}
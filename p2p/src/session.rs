@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use parking_lot::Mutex;
 use bytes::Bytes;
-use message::{Command, Error};
+use message::{Command, Error, MESSAGE_HEADER_LEN};
 use p2p::Context;
 use net::{PeerContext, PeerStats};
 use protocol::{Protocol, PingProtocol, SyncProtocol, AddrProtocol, SeednodeProtocol};
@@ -35,6 +35,17 @@ impl SessionFactory for NormalSessionFactory {
 	}
 }
 
+pub struct BlockRelayOnlySessionFactory;
+
+impl SessionFactory for BlockRelayOnlySessionFactory {
+	fn new_session(context: Arc<Context>, info: PeerInfo, synchronous: bool) -> Session {
+		let peer_context = Arc::new(PeerContext::new(context, info, synchronous));
+		let ping = PingProtocol::new(peer_context.clone()).boxed();
+		let sync = SyncProtocol::new(peer_context.clone()).boxed();
+		Session::new(peer_context, vec![ping, sync])
+	}
+}
+
 pub struct Session {
 	peer_context: Arc<PeerContext>,
 	protocols: Mutex<Vec<Box<Protocol>>>,
@@ -61,7 +72,10 @@ impl Session {
 	}
 
 	pub fn on_message(&self, command: Command, payload: Bytes) -> Result<(), Error> {
-		self.stats().lock().report_recv(command.clone(), payload.len());
+		// header is accounted as well, as it is for sent messages
+		let message_len = MESSAGE_HEADER_LEN + payload.len();
+		self.stats().lock().report_recv(command.clone(), message_len);
+		self.peer_context.global().report_recv(command.clone(), message_len);
 
 		self.protocols.lock()
 			.iter_mut()
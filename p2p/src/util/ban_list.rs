@@ -0,0 +1,55 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Duration of the ban for misbehaving nodes.
+const BAN_TIME_SECS: u64 = 24 * 60 * 60;
+
+/// Tracks nodes that have been banned for misbehavior.
+#[derive(Debug, Default)]
+pub struct BanList {
+	/// Banned addresses, mapped to ban expiration time.
+	banned: HashMap<IpAddr, Instant>,
+}
+
+impl BanList {
+	/// Bans address for `BAN_TIME_SECS`.
+	pub fn ban(&mut self, ip: IpAddr, now: Instant) {
+		self.banned.insert(ip, now + Duration::from_secs(BAN_TIME_SECS));
+	}
+
+	/// Returns true if address is currently banned. Expired bans are forgotten.
+	pub fn is_banned(&mut self, ip: &IpAddr, now: Instant) -> bool {
+		match self.banned.get(ip).cloned() {
+			Some(until) if until > now => true,
+			Some(_) => {
+				self.banned.remove(ip);
+				false
+			},
+			None => false,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::IpAddr;
+	use std::time::{Duration, Instant};
+	use super::{BanList, BAN_TIME_SECS};
+
+	#[test]
+	fn ban_list_bans_for_limited_time() {
+		let ip: IpAddr = "127.0.0.1".parse().unwrap();
+		let other_ip: IpAddr = "127.0.0.2".parse().unwrap();
+		let now = Instant::now();
+		let mut ban_list = BanList::default();
+		assert!(!ban_list.is_banned(&ip, now));
+
+		ban_list.ban(ip, now);
+		assert!(ban_list.is_banned(&ip, now));
+		assert!(!ban_list.is_banned(&other_ip, now));
+		assert!(ban_list.is_banned(&ip, now + Duration::from_secs(BAN_TIME_SECS - 1)));
+		assert!(!ban_list.is_banned(&ip, now + Duration::from_secs(BAN_TIME_SECS)));
+		assert!(ban_list.banned.is_empty());
+	}
+}
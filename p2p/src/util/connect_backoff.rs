@@ -0,0 +1,97 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// Delay before the first reconnection attempt.
+const BASE_RECONNECT_DELAY_SECS: u64 = 15;
+/// Maximal delay between reconnection attempts.
+const MAX_RECONNECT_DELAY_SECS: u64 = 10 * 60;
+
+#[derive(Debug, Clone, Copy)]
+struct BackoffState {
+	/// Number of consecutive failed attempts.
+	failures: u32,
+	/// Do not retry before this moment.
+	next_attempt: Instant,
+}
+
+/// Tracks exponential reconnection delays for explicitly configured peers.
+#[derive(Debug, Default)]
+pub struct ConnectBackoff {
+	peers: HashMap<SocketAddr, BackoffState>,
+}
+
+impl ConnectBackoff {
+	/// Returns true if it is time to (re)connect to the peer.
+	pub fn is_ready(&self, addr: &SocketAddr, now: Instant) -> bool {
+		self.peers.get(addr)
+			.map(|state| state.next_attempt <= now)
+			.unwrap_or(true)
+	}
+
+	/// Notes that connection attempt has been started.
+	/// Peer won't be dialed again until attempt either completes, or delay is passed.
+	pub fn note_attempt(&mut self, addr: SocketAddr, now: Instant) {
+		let failures = self.peers.get(&addr).map(|state| state.failures).unwrap_or(0);
+		self.peers.insert(addr, BackoffState {
+			failures: failures,
+			next_attempt: now + reconnect_delay(failures),
+		});
+	}
+
+	/// Notes that connection attempt has failed.
+	pub fn note_failure(&mut self, addr: SocketAddr, now: Instant) {
+		let failures = self.peers.get(&addr).map(|state| state.failures).unwrap_or(0).saturating_add(1);
+		self.peers.insert(addr, BackoffState {
+			failures: failures,
+			next_attempt: now + reconnect_delay(failures),
+		});
+	}
+
+	/// Notes that connection has been established.
+	pub fn note_success(&mut self, addr: &SocketAddr) {
+		self.peers.remove(addr);
+	}
+}
+
+/// Delay before the next attempt after given number of consecutive failures.
+fn reconnect_delay(failures: u32) -> Duration {
+	let multiplier = 1u64.checked_shl(failures).unwrap_or(u64::max_value());
+	Duration::from_secs(cmp::min(BASE_RECONNECT_DELAY_SECS.saturating_mul(multiplier), MAX_RECONNECT_DELAY_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::net::SocketAddr;
+	use std::time::{Duration, Instant};
+	use super::{ConnectBackoff, reconnect_delay};
+
+	#[test]
+	fn reconnect_delay_grows_exponentially_up_to_limit() {
+		assert_eq!(reconnect_delay(0), Duration::from_secs(15));
+		assert_eq!(reconnect_delay(1), Duration::from_secs(30));
+		assert_eq!(reconnect_delay(2), Duration::from_secs(60));
+		assert_eq!(reconnect_delay(10), Duration::from_secs(600));
+		assert_eq!(reconnect_delay(100), Duration::from_secs(600));
+	}
+
+	#[test]
+	fn connect_backoff_delays_failed_peer() {
+		let addr: SocketAddr = "127.0.0.1:8233".parse().unwrap();
+		let now = Instant::now();
+		let mut backoff = ConnectBackoff::default();
+		assert!(backoff.is_ready(&addr, now));
+
+		backoff.note_attempt(addr, now);
+		assert!(!backoff.is_ready(&addr, now));
+		assert!(backoff.is_ready(&addr, now + Duration::from_secs(15)));
+
+		backoff.note_failure(addr, now);
+		assert!(!backoff.is_ready(&addr, now + Duration::from_secs(15)));
+		assert!(backoff.is_ready(&addr, now + Duration::from_secs(30)));
+
+		backoff.note_success(&addr);
+		assert!(backoff.is_ready(&addr, now));
+	}
+}
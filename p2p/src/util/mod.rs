@@ -1,14 +1,20 @@
 pub mod nonce;
 pub mod time;
 pub mod interval;
+mod ban_list;
+mod connect_backoff;
 mod internet_protocol;
 mod node_table;
 mod peer;
+mod peer_permissions;
 mod response_queue;
 mod synchronizer;
 
+pub use self::ban_list::BanList;
+pub use self::connect_backoff::ConnectBackoff;
 pub use self::internet_protocol::InternetProtocol;
 pub use self::node_table::{NodeTable, NodeTableError, Node};
 pub use self::peer::{PeerId, PeerInfo, Direction};
+pub use self::peer_permissions::{PeerPermissions, Subnet, SubnetPermissions, peer_permissions};
 pub use self::response_queue::{ResponseQueue, Responses};
 pub use self::synchronizer::{Synchronizer, ConfigurableSynchronizer};
@@ -1,6 +1,7 @@
 use std::net::SocketAddr;
 use message::types;
 use network::Magic;
+use super::PeerPermissions;
 
 pub type PeerId = usize;
 
@@ -19,5 +20,10 @@ pub struct PeerInfo {
 	pub version: u32,
 	pub version_message: types::Version,
 	pub magic: Magic,
+	pub permissions: PeerPermissions,
+	/// True if peer has asked us to send `addrv2` messages instead of `addr`.
+	pub addrv2: bool,
+	/// True if this is outbound block-relay-only connection.
+	pub block_relay_only: bool,
 }
 
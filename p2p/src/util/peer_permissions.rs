@@ -0,0 +1,204 @@
+use std::{str, net};
+
+/// Permissions, granted to the trusted peer.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct PeerPermissions {
+	/// Peer is never banned or disconnected because of misbehavior.
+	pub noban: bool,
+	/// Loose transactions are accepted from the peer, even in blocks-only mode.
+	pub relay: bool,
+	/// Peer is allowed to request memory pool contents while we are synchronizing.
+	pub mempool: bool,
+	/// Peer is allowed to download blocks and headers while we are synchronizing.
+	pub download: bool,
+}
+
+impl PeerPermissions {
+	/// All permissions.
+	pub fn all() -> Self {
+		PeerPermissions {
+			noban: true,
+			relay: true,
+			mempool: true,
+			download: true,
+		}
+	}
+
+	/// Returns permissions, granted by either of `self` or `other`.
+	pub fn union(&self, other: &PeerPermissions) -> Self {
+		PeerPermissions {
+			noban: self.noban || other.noban,
+			relay: self.relay || other.relay,
+			mempool: self.mempool || other.mempool,
+			download: self.download || other.download,
+		}
+	}
+}
+
+impl str::FromStr for PeerPermissions {
+	type Err = &'static str;
+
+	/// Parses comma-separated list of permissions.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut permissions = PeerPermissions::default();
+		for flag in s.split(',') {
+			match flag {
+				"noban" => permissions.noban = true,
+				"relay" => permissions.relay = true,
+				"mempool" => permissions.mempool = true,
+				"download" => permissions.download = true,
+				"all" => permissions = PeerPermissions::all(),
+				_ => return Err("Invalid peer permission"),
+			}
+		}
+		Ok(permissions)
+	}
+}
+
+/// Range of IP addresses.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Subnet {
+	address: net::IpAddr,
+	prefix_len: u8,
+}
+
+impl Subnet {
+	/// Returns true if address belongs to this subnet.
+	pub fn contains(&self, addr: &net::IpAddr) -> bool {
+		match (self.address, *addr) {
+			(net::IpAddr::V4(subnet), net::IpAddr::V4(addr)) =>
+				prefix_matches(&subnet.octets(), &addr.octets(), self.prefix_len),
+			(net::IpAddr::V6(subnet), net::IpAddr::V6(addr)) =>
+				prefix_matches(&subnet.octets(), &addr.octets(), self.prefix_len),
+			_ => false,
+		}
+	}
+}
+
+fn prefix_matches(subnet: &[u8], addr: &[u8], prefix_len: u8) -> bool {
+	let full_bytes = prefix_len as usize / 8;
+	let rest_bits = prefix_len % 8;
+	if subnet[..full_bytes] != addr[..full_bytes] {
+		return false;
+	}
+
+	if rest_bits == 0 {
+		return true;
+	}
+
+	let mask = 0xffu8 << (8 - rest_bits);
+	subnet[full_bytes] & mask == addr[full_bytes] & mask
+}
+
+impl str::FromStr for Subnet {
+	type Err = &'static str;
+
+	/// Parses either single address, or subnet in CIDR notation.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let mut parts = s.splitn(2, '/');
+		let address: net::IpAddr = parts.next()
+			.and_then(|address| address.parse().ok())
+			.ok_or("Invalid subnet address")?;
+		let max_prefix_len = match address {
+			net::IpAddr::V4(_) => 32,
+			net::IpAddr::V6(_) => 128,
+		};
+		let prefix_len = match parts.next() {
+			Some(prefix_len) => prefix_len.parse().map_err(|_| "Invalid subnet prefix length")?,
+			None => max_prefix_len,
+		};
+		if prefix_len > max_prefix_len {
+			return Err("Invalid subnet prefix length");
+		}
+
+		Ok(Subnet {
+			address: address,
+			prefix_len: prefix_len,
+		})
+	}
+}
+
+/// Permissions, granted to all peers from the subnet.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SubnetPermissions {
+	pub subnet: Subnet,
+	pub permissions: PeerPermissions,
+}
+
+impl str::FromStr for SubnetPermissions {
+	type Err = &'static str;
+
+	/// Parses `permissions@subnet`. If permissions are omitted, all permissions are granted.
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let (permissions, subnet) = match s.find('@') {
+			Some(pos) => (s[..pos].parse()?, s[pos + 1..].parse()?),
+			None => (PeerPermissions::all(), s.parse()?),
+		};
+
+		Ok(SubnetPermissions {
+			subnet: subnet,
+			permissions: permissions,
+		})
+	}
+}
+
+/// Returns permissions, granted to the peer with given address.
+pub fn peer_permissions(entries: &[SubnetPermissions], addr: &net::IpAddr) -> PeerPermissions {
+	entries.iter()
+		.filter(|entry| entry.subnet.contains(addr))
+		.fold(PeerPermissions::default(), |permissions, entry| permissions.union(&entry.permissions))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{PeerPermissions, Subnet, SubnetPermissions, peer_permissions};
+
+	#[test]
+	fn test_parsing_peer_permissions() {
+		assert_eq!("noban,relay".parse::<PeerPermissions>().unwrap(), PeerPermissions {
+			noban: true,
+			relay: true,
+			mempool: false,
+			download: false,
+		});
+		assert_eq!("all".parse::<PeerPermissions>().unwrap(), PeerPermissions::all());
+		assert!("noban,unknown".parse::<PeerPermissions>().is_err());
+	}
+
+	#[test]
+	fn test_subnet_contains() {
+		let subnet: Subnet = "192.168.1.0/24".parse().unwrap();
+		assert!(subnet.contains(&"192.168.1.77".parse().unwrap()));
+		assert!(!subnet.contains(&"192.168.2.77".parse().unwrap()));
+		assert!(!subnet.contains(&"::1".parse().unwrap()));
+
+		let subnet: Subnet = "10.0.0.0/12".parse().unwrap();
+		assert!(subnet.contains(&"10.15.255.255".parse().unwrap()));
+		assert!(!subnet.contains(&"10.16.0.0".parse().unwrap()));
+
+		let subnet: Subnet = "127.0.0.1".parse().unwrap();
+		assert!(subnet.contains(&"127.0.0.1".parse().unwrap()));
+		assert!(!subnet.contains(&"127.0.0.2".parse().unwrap()));
+
+		assert!("127.0.0.1/33".parse::<Subnet>().is_err());
+		assert!("localhost".parse::<Subnet>().is_err());
+	}
+
+	#[test]
+	fn test_peer_permissions_lookup() {
+		let entries: Vec<SubnetPermissions> = vec![
+			"noban@10.0.0.0/8".parse().unwrap(),
+			"relay,mempool@10.1.0.0/16".parse().unwrap(),
+			"127.0.0.1".parse().unwrap(),
+		];
+
+		assert_eq!(peer_permissions(&entries, &"10.1.2.3".parse().unwrap()), PeerPermissions {
+			noban: true,
+			relay: true,
+			mempool: true,
+			download: false,
+		});
+		assert_eq!(peer_permissions(&entries, &"127.0.0.1".parse().unwrap()), PeerPermissions::all());
+		assert_eq!(peer_permissions(&entries, &"8.8.8.8".parse().unwrap()), PeerPermissions::default());
+	}
+}
@@ -1,3 +1,4 @@
+use std::path::Path;
 use clap::ArgMatches;
 use sync::{create_sync_blocks_writer, Error};
 use config::Config;
@@ -7,13 +8,19 @@ pub fn import(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
 	try!(init_db(&cfg));
 
 	let blk_path = matches.value_of("PATH").expect("PATH is required in cli.yml; qed");
-	let blk_dir = ::import::open_blk_dir(blk_path)
-		.map_err(|err| format!("Failed to open import directory: {}", err))?;
+	let blocks: Box<Iterator<Item = _>> = if Path::new(blk_path).is_file() {
+		Box::new(::import::open_bootstrap_file(blk_path, cfg.network.magic())
+			.map_err(|err| format!("Failed to open bootstrap file: {}", err))?)
+	} else {
+		Box::new(::import::open_blk_dir(blk_path)
+			.map_err(|err| format!("Failed to open import directory: {}", err))?)
+	};
 
 	let mut writer = create_sync_blocks_writer(cfg.db, cfg.consensus, cfg.verification_params);
 	let mut counter = 0;
+	let mut skipped = 0;
 	let mut previous_hash = None;
-	for blk in blk_dir {
+	for blk in blocks {
 		// TODO: verify magic!
 		let blk = blk.map_err(|err| format!("Cannot read block: {:?}. Previous block: {:?}", err, previous_hash))?;
 		let blk_hash = blk.block.hash().reversed();
@@ -25,13 +32,18 @@ pub fn import(cfg: Config, matches: &ArgMatches) -> Result<(), String> {
 				}
 			}
 			Err(Error::TooManyOrphanBlocks) => return Err("Too many orphan (unordered) blocks".into()),
+			// invalid block (and all its descendants) is skipped, so that the rest of file is imported
+			Err(Error::Verification(err)) => {
+				warn!(target: "sync", "Skipping invalid block while appending block {}: {}", blk_hash, err);
+				skipped += 1;
+			},
 			Err(err) => return Err(format!("Cannot append block: {:?}. Block: {}", err, blk_hash)),
 		}
 
 		previous_hash = Some(blk_hash);
 	}
 
-	info!("Finished import of {} blocks", counter);
+	info!("Finished import of {} blocks ({} invalid blocks skipped)", counter, skipped);
 
 	Ok(())
 }
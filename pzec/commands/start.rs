@@ -5,7 +5,7 @@ use std::sync::mpsc::{channel, Sender, Receiver};
 use std::sync::atomic::{AtomicBool, Ordering};
 use sync::{create_sync_peers, create_local_sync_node, create_sync_connection_factory, SyncListener};
 use primitives::hash::H256;
-use util::{init_db, node_table_path};
+use util::{init_db, init_indexes, node_table_path};
 use {config, p2p, ZCASH_PROTOCOL_VERSION, ZCASH_PROTOCOL_MINIMUM};
 use super::super::rpc;
 
@@ -85,6 +85,7 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 	let mut el = p2p::event_loop();
 
 	init_db(&cfg)?;
+	init_indexes(&cfg)?;
 
 	let nodes_path = node_table_path(&cfg);
 
@@ -92,6 +93,9 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 		threads: cfg.p2p_threads,
 		inbound_connections: cfg.inbound_connections,
 		outbound_connections: cfg.outbound_connections,
+		block_relay_only_connections: cfg.block_relay_only_connections,
+		protect_by_netgroup: cfg.protect_by_netgroup,
+		protect_by_last_block: cfg.protect_by_last_block,
 		connection: p2p::NetConfig {
 			protocol_version: ZCASH_PROTOCOL_VERSION,
 			protocol_minimum: ZCASH_PROTOCOL_MINIMUM,
@@ -100,17 +104,20 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 			services: cfg.services,
 			user_agent: cfg.user_agent,
 			start_height: 0,
-			relay: true,
+			relay: !cfg.blocks_only,
+			timeouts: cfg.timeouts.clone(),
+			max_block_size: cfg.consensus.max_block_size(),
 		},
-		peers: cfg.connect.map_or_else(|| vec![], |x| vec![x]),
+		peers: cfg.connect,
 		seeds: cfg.seednodes,
 		node_table_path: nodes_path,
 		preferable_services: cfg.services,
 		internet_protocol: cfg.internet_protocol,
+		permissions: cfg.permissions,
 	};
 
 	let sync_peers = create_sync_peers();
-	let local_sync_node = create_local_sync_node(cfg.consensus.clone(), cfg.db.clone(), sync_peers.clone(), cfg.verification_params);
+	let local_sync_node = create_local_sync_node(cfg.consensus.clone(), cfg.db.clone(), sync_peers.clone(), cfg.verification_params, cfg.blocks_only, cfg.timeouts, cfg.sync_mode);
 	let sync_connection_factory = create_sync_connection_factory(sync_peers.clone(), local_sync_node.clone());
 
 	if let Some(block_notify_command) = cfg.block_notify_command {
@@ -124,6 +131,8 @@ pub fn start(cfg: config::Config) -> Result<(), String> {
 		local_sync_node: local_sync_node,
 		p2p_context: p2p.context().clone(),
 		miner_address: cfg.miner_address,
+		coinbase_tag: cfg.coinbase_tag,
+		tx_expiry_delta: cfg.tx_expiry_delta,
 	};
 	let _rpc_server = try!(rpc::new_http(cfg.rpc_config, rpc_deps));
 
@@ -1,17 +1,24 @@
 use std::net;
+use std::sync::Arc;
+use std::time::Duration;
 use clap;
 use storage;
+use db::kv::DEFAULT_READ_CACHE_ENTRIES;
 use keys::Address;
+use chain::constants::TX_EXPIRY_HEIGHT_THRESHOLD;
 use message::Services;
-use network::{Network, ConsensusParams};
-use p2p::InternetProtocol;
+use network::{Network, ConsensusParams, NetworkUpgrade};
+use p2p::{InternetProtocol, SubnetPermissions, TimeoutProfile, MAX_TIMEOUT_SCALE, PROTECTED_BY_NETGROUP, PROTECTED_BY_LAST_BLOCK};
 use seednodes::{zcash_seednodes, zcash_testnet_seednodes};
 use rpc_apis::ApiSet;
 use {USER_AGENT, REGTEST_USER_AGENT};
 use primitives::hash::H256;
+use primitives::bytes::Bytes;
 use rpc::HttpConfiguration as RpcHttpConfig;
-use verification::VerificationLevel;
-use sync::VerificationParameters;
+use ethcore_rpc::v1::DEFAULT_TX_EXPIRY_DELTA;
+use verification::{VerificationLevel, ScriptVerificationPool};
+use miner::check_coinbase_tag;
+use sync::{VerificationParameters, SyncMode};
 use util::open_db;
 
 pub struct Config {
@@ -19,14 +26,18 @@ pub struct Config {
 	pub consensus: ConsensusParams,
 	pub services: Services,
 	pub port: u16,
-	pub connect: Option<net::SocketAddr>,
+	pub connect: Vec<net::SocketAddr>,
 	pub host: net::IpAddr,
 	pub seednodes: Vec<String>,
 	pub quiet: bool,
 	pub inbound_connections: u32,
 	pub outbound_connections: u32,
+	pub block_relay_only_connections: u32,
+	pub protect_by_netgroup: usize,
+	pub protect_by_last_block: usize,
 	pub p2p_threads: usize,
 	pub db_cache: usize,
+	pub db_read_cache: usize,
 	pub data_dir: Option<String>,
 	pub user_agent: String,
 	pub internet_protocol: InternetProtocol,
@@ -35,6 +46,13 @@ pub struct Config {
 	pub verification_params: VerificationParameters,
 	pub db: storage::SharedStore,
 	pub miner_address: Option<Address>,
+	pub coinbase_tag: Bytes,
+	pub blocks_only: bool,
+	pub sync_mode: SyncMode,
+	pub indexes: Vec<storage::IndexKind>,
+	pub permissions: Vec<SubnetPermissions>,
+	pub tx_expiry_delta: u32,
+	pub timeouts: TimeoutProfile,
 }
 
 pub const DEFAULT_DB_CACHE: usize = 512;
@@ -45,12 +63,17 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		None => DEFAULT_DB_CACHE,
 	};
 
+	let db_read_cache = match matches.value_of("db-read-cache") {
+		Some(s) => s.parse().map_err(|_| "Invalid read cache size - should be number of entries".to_owned())?,
+		None => DEFAULT_READ_CACHE_ENTRIES,
+	};
+
 	let data_dir = match matches.value_of("data-dir") {
 		Some(s) => Some(s.parse().map_err(|_| "Invalid data-dir".to_owned())?),
 		None => None,
 	};
 
-	let db = open_db(&data_dir, db_cache);
+	let db = open_db(&data_dir, db_cache, db_read_cache);
 
 	let quiet = matches.is_present("quiet");
 	let network = match (matches.is_present("testnet"), matches.is_present("regtest")) {
@@ -60,11 +83,46 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		(true, true) => return Err("Only one testnet option can be used".into()),
 	};
 
-	let consensus = ConsensusParams::new(network);
+	let mut consensus = ConsensusParams::new(network);
+	if let Some(values) = matches.values_of("nuparams") {
+		if network != Network::Regtest {
+			return Err("nuparams can only be used with regtest".into());
+		}
+
+		for value in values {
+			let (upgrade, height) = parse_nuparams(value)?;
+			consensus.set_activation_height(upgrade, height);
+		}
+	}
 
-	let (in_connections, out_connections) = match network {
-		Network::Testnet | Network::Mainnet | Network::Other(_) => (10, 10),
-		Network::Regtest | Network::Unitest => (1, 0),
+	let (in_connections, out_connections, block_relay_only_connections) = match network {
+		Network::Testnet | Network::Mainnet | Network::Other(_) => (10, 10, 2),
+		Network::Regtest | Network::Unitest => (1, 0, 0),
+	};
+
+	let in_connections = match matches.value_of("inbound-connections") {
+		Some(s) => s.parse().map_err(|_| "Invalid inbound-connections - should be number of connections".to_owned())?,
+		None => in_connections,
+	};
+
+	let out_connections = match matches.value_of("outbound-connections") {
+		Some(s) => s.parse().map_err(|_| "Invalid outbound-connections - should be number of connections".to_owned())?,
+		None => out_connections,
+	};
+
+	let block_relay_only_connections = match matches.value_of("block-relay-only-connections") {
+		Some(s) => s.parse().map_err(|_| "Invalid block-relay-only-connections - should be number of connections".to_owned())?,
+		None => block_relay_only_connections,
+	};
+
+	let protect_by_netgroup = match matches.value_of("protect-by-netgroup") {
+		Some(s) => s.parse().map_err(|_| "Invalid protect-by-netgroup - should be number of peers".to_owned())?,
+		None => PROTECTED_BY_NETGROUP,
+	};
+
+	let protect_by_last_block = match matches.value_of("protect-by-last-block") {
+		Some(s) => s.parse().map_err(|_| "Invalid protect-by-last-block - should be number of peers".to_owned())?,
+		None => PROTECTED_BY_LAST_BLOCK,
 	};
 
 	let p2p_threads = match network {
@@ -84,14 +142,16 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		None => network.port(),
 	};
 
-	let connect = match matches.value_of("connect") {
-		Some(s) => Some(match s.parse::<net::SocketAddr>() {
-			Err(_) => s.parse::<net::IpAddr>()
-				.map(|ip| net::SocketAddr::new(ip, network.port()))
-				.map_err(|_| "Invalid connect".to_owned()),
-			Ok(a) => Ok(a),
-		}?),
-		None => None,
+	let connect = match matches.values_of("connect") {
+		Some(values) => values
+			.map(|s| match s.parse::<net::SocketAddr>() {
+				Err(_) => s.parse::<net::IpAddr>()
+					.map(|ip| net::SocketAddr::new(ip, network.port()))
+					.map_err(|_| "Invalid connect".to_owned()),
+				Ok(a) => Ok(a),
+			})
+			.collect::<Result<Vec<_>, _>>()?,
+		None => Vec::new(),
 	};
 
 	let seednodes: Vec<String> = match matches.value_of("seednode") {
@@ -103,6 +163,27 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		},
 	};
 
+	let blocks_only = matches.is_present("blocksonly");
+	let sync_mode = match matches.is_present("headers-first") {
+		true => SyncMode::HeadersFirst,
+		false => SyncMode::Interleaved,
+	};
+	let indexes = vec![
+		("txindex", storage::IndexKind::Transaction),
+		("addressindex", storage::IndexKind::Address),
+		("spentindex", storage::IndexKind::Spent),
+	].into_iter()
+		.filter(|&(flag, _)| matches.is_present(flag))
+		.map(|(_, index)| index)
+		.collect();
+
+	let permissions = match matches.values_of("whitelist") {
+		Some(values) => values
+			.map(|s| s.parse::<SubnetPermissions>().map_err(|err| format!("Invalid whitelist entry {}: {}", s, err)))
+			.collect::<Result<Vec<_>, _>>()?,
+		None => Vec::new(),
+	};
+
 	let only_net = match matches.value_of("only-net") {
 		Some(s) => s.parse()?,
 		None => InternetProtocol::default(),
@@ -141,11 +222,52 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		_ => network.default_verification_edge(),
 	};
 
+	let transaction_time_budget = match matches.value_of("tx-verification-timeout") {
+		Some(s) => Some(Duration::from_millis(s.parse().map_err(|_| "Invalid tx-verification-timeout - should be number of milliseconds".to_owned())?)),
+		None => None,
+	};
+
+	let sapling_anchor_window = match matches.value_of("sapling-anchor-window") {
+		Some(s) => Some(s.parse().map_err(|_| "Invalid sapling-anchor-window - should be number of blocks".to_owned())?),
+		None => None,
+	};
+
+	// the same pool is shared by all block verifiers
+	let script_verification_pool = match matches.value_of("verification-threads") {
+		Some(s) => match s.parse() {
+			Ok(0) | Err(_) => return Err("Invalid verification-threads - should be positive number".to_owned()),
+			Ok(threads) => Some(Arc::new(ScriptVerificationPool::new(threads))),
+		},
+		None => None,
+	};
+
 	let miner_address = match matches.value_of("miner-address") {
 		Some(s) => Some(s.parse().map_err(|_| "Invalid miner-address command".to_owned())?),
 		None => None,
 	};
 
+	let coinbase_tag: Bytes = match matches.value_of("coinbase-tag") {
+		Some(s) => s.as_bytes().to_vec().into(),
+		None => Bytes::default(),
+	};
+	check_coinbase_tag(&coinbase_tag).map_err(|e| format!("Invalid coinbase-tag: {}", e))?;
+
+	let tx_expiry_delta = match matches.value_of("tx-expiry-delta") {
+		Some(s) => s.parse().map_err(|_| "Invalid tx-expiry-delta - should be number of blocks".to_owned())?,
+		None => DEFAULT_TX_EXPIRY_DELTA,
+	};
+	if tx_expiry_delta >= TX_EXPIRY_HEIGHT_THRESHOLD {
+		return Err(format!("Invalid tx-expiry-delta - should be less than {}", TX_EXPIRY_HEIGHT_THRESHOLD));
+	}
+
+	let timeouts = match matches.value_of("timeout-scale") {
+		Some(s) => match s.parse() {
+			Ok(factor) if factor > 0 && factor <= MAX_TIMEOUT_SCALE => TimeoutProfile::default().scaled(factor),
+			_ => return Err(format!("Invalid timeout-scale - should be number in range [1; {}]", MAX_TIMEOUT_SCALE)),
+		},
+		None => TimeoutProfile::default(),
+	};
+
 	let config = Config {
 		quiet: quiet,
 		network: network,
@@ -157,8 +279,12 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		seednodes: seednodes,
 		inbound_connections: in_connections,
 		outbound_connections: out_connections,
+		block_relay_only_connections: block_relay_only_connections,
+		protect_by_netgroup: protect_by_netgroup,
+		protect_by_last_block: protect_by_last_block,
 		p2p_threads: p2p_threads,
 		db_cache: db_cache,
+		db_read_cache: db_read_cache,
 		data_dir: data_dir,
 		user_agent: user_agent,
 		internet_protocol: only_net,
@@ -167,14 +293,34 @@ pub fn parse(matches: &clap::ArgMatches) -> Result<Config, String> {
 		verification_params: VerificationParameters {
 			verification_level: verification_level,
 			verification_edge: verification_edge,
+			transaction_time_budget: transaction_time_budget,
+			sapling_anchor_window: sapling_anchor_window,
+			script_verification_pool: script_verification_pool,
 		},
 		db: db,
 		miner_address: miner_address,
+		coinbase_tag: coinbase_tag,
+		blocks_only: blocks_only,
+		sync_mode: sync_mode,
+		indexes: indexes,
+		permissions: permissions,
+		tx_expiry_delta: tx_expiry_delta,
+		timeouts: timeouts,
 	};
 
 	Ok(config)
 }
 
+fn parse_nuparams(value: &str) -> Result<(NetworkUpgrade, u32), String> {
+	let invalid = || format!("Invalid nuparams {} - should be BRANCH_ID:HEIGHT", value);
+	let mut parts = value.splitn(2, ':');
+	let branch_id = parts.next().and_then(|branch_id| u32::from_str_radix(branch_id, 16).ok()).ok_or_else(invalid)?;
+	let height = parts.next().and_then(|height| height.parse().ok()).ok_or_else(invalid)?;
+	let upgrade = NetworkUpgrade::from_branch_id(branch_id)
+		.ok_or_else(|| format!("Invalid nuparams {} - unknown consensus branch id", value))?;
+	Ok((upgrade, height))
+}
+
 fn parse_rpc_config(network: Network, matches: &clap::ArgMatches) -> Result<RpcHttpConfig, String> {
 	let mut config = RpcHttpConfig::with_port(network.rpc_port());
 	config.enabled = !matches.is_present("no-jsonrpc");
@@ -183,7 +329,9 @@ fn parse_rpc_config(network: Network, matches: &clap::ArgMatches) -> Result<RpcH
 	}
 
 	if let Some(apis) = matches.value_of("jsonrpc-apis") {
-		config.apis = ApiSet::List(vec![apis.parse().map_err(|_| "Invalid APIs".to_owned())?].into_iter().collect());
+		config.apis = ApiSet::List(apis.split(',')
+			.map(|api| api.trim().parse().map_err(|_| "Invalid APIs".to_owned()))
+			.collect::<Result<_, _>>()?);
 	}
 	if let Some(port) = matches.value_of("jsonrpc-port") {
 		config.port = port.parse().map_err(|_| "Invalid JSON RPC port".to_owned())?;
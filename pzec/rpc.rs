@@ -5,6 +5,7 @@ use ethcore_rpc::{Server, start_http, MetaIoHandler, Compatibility};
 use network::ConsensusParams;
 use std::io;
 use keys::Address;
+use primitives::bytes::Bytes;
 use sync;
 use storage;
 use p2p;
@@ -15,6 +16,8 @@ pub struct Dependencies {
 	pub storage: storage::SharedStore,
 	pub p2p_context: Arc<p2p::Context>,
 	pub miner_address: Option<Address>,
+	pub coinbase_tag: Bytes,
+	pub tx_expiry_delta: u32,
 }
 
 #[derive(Debug, PartialEq)]
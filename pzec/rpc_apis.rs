@@ -13,6 +13,10 @@ pub enum Api {
 	BlockChain,
 	/// Network
 	Network,
+	/// Utility methods
+	Util,
+	/// Message signing methods (private key is passed over RPC => not enabled by default)
+	Sign,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -22,7 +26,7 @@ pub enum ApiSet {
 
 impl Default for ApiSet {
 	fn default() -> Self {
-		ApiSet::List(vec![Api::Raw, Api::Miner, Api::BlockChain, Api::Network].into_iter().collect())
+		ApiSet::List(vec![Api::Raw, Api::Miner, Api::BlockChain, Api::Network, Api::Util].into_iter().collect())
 	}
 }
 
@@ -35,6 +39,8 @@ impl FromStr for Api {
 			"miner" => Ok(Api::Miner),	
 			"blockchain" => Ok(Api::BlockChain),
 			"network" => Ok(Api::Network),
+			"util" => Ok(Api::Util),
+			"sign" => Ok(Api::Sign),
 			api => Err(format!("Unknown api: {}", api)),
 		}
 	}
@@ -53,10 +59,12 @@ pub fn setup_rpc(mut handler: MetaIoHandler<()>, apis: ApiSet, deps: Dependencie
 
 	for api in apis.list_apis() {
 		match api {
-			Api::Raw => handler.extend_with(RawClient::new(RawClientCore::new(deps.local_sync_node.clone())).to_delegate()),
-			Api::Miner => handler.extend_with(MinerClient::new(MinerClientCore::new(deps.local_sync_node.clone(), deps.miner_address.clone())).to_delegate()),
+			Api::Raw => handler.extend_with(RawClient::new(RawClientCore::new(deps.consensus.network, deps.local_sync_node.clone(), deps.storage.clone(), deps.tx_expiry_delta)).to_delegate()),
+			Api::Miner => handler.extend_with(MinerClient::new(MinerClientCore::new(deps.local_sync_node.clone(), deps.miner_address.clone(), deps.coinbase_tag.clone())).to_delegate()),
 			Api::BlockChain => handler.extend_with(BlockChainClient::new(BlockChainClientCore::new(deps.consensus.clone(), deps.storage.clone())).to_delegate()),
-			Api::Network => handler.extend_with(NetworkClient::new(NetworkClientCore::new(deps.p2p_context.clone())).to_delegate()),
+			Api::Network => handler.extend_with(NetworkClient::new(NetworkClientCore::new(deps.p2p_context.clone(), deps.local_sync_node.clone(), deps.consensus.clone(), deps.storage.clone())).to_delegate()),
+			Api::Util => handler.extend_with(UtilClient::new(deps.consensus.network).to_delegate()),
+			Api::Sign => handler.extend_with(SignClient::new(deps.consensus.network).to_delegate()),
 		}
 	}
 
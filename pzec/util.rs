@@ -6,12 +6,12 @@ use {storage, APP_INFO};
 use db;
 use config::Config;
 
-pub fn open_db(data_dir: &Option<String>, db_cache: usize) -> storage::SharedStore {
+pub fn open_db(data_dir: &Option<String>, db_cache: usize, db_read_cache: usize) -> storage::SharedStore {
 	let db_path = match *data_dir {
 		Some(ref data_dir) => custom_path(&data_dir, "db"),
 		None => app_dir(AppDataType::UserData, &APP_INFO, "db").expect("Failed to get app dir"),
 	};
-	Arc::new(db::BlockChainDatabase::open_at_path(db_path, db_cache).expect("Failed to open database"))
+	Arc::new(db::BlockChainDatabase::open_at_path(db_path, db_cache, db_read_cache).expect("Failed to open database"))
 }
 
 pub fn node_table_path(cfg: &Config) -> PathBuf {
@@ -38,6 +38,28 @@ pub fn init_db(cfg: &Config) -> Result<(), String> {
 	}
 }
 
+pub fn init_indexes(cfg: &Config) -> Result<(), String> {
+	for index in &cfg.indexes {
+		init_index(cfg, *index)?;
+	}
+	Ok(())
+}
+
+fn init_index(cfg: &Config, index: storage::IndexKind) -> Result<(), String> {
+	cfg.db.enable_index(index)?;
+	let best_block_number = cfg.db.best_block().number;
+	let next_block = cfg.db.index_next_block(index).unwrap_or_default();
+	if next_block > best_block_number {
+		return Ok(());
+	}
+
+	info!(target: "pzec", "Rescanning blocks {}..{} to backfill {} index", next_block, best_block_number, index.name());
+	storage::rescan_index(cfg.db.as_chain_index(), index, best_block_number, |next_block| {
+		info!(target: "pzec", "{} index: {}/{} blocks indexed", index.name(), next_block, best_block_number + 1);
+	})?;
+	Ok(())
+}
+
 fn custom_path(data_dir: &str, sub_dir: &str) -> PathBuf {
 	let mut path = PathBuf::from(data_dir);
 	path.push(sub_dir);
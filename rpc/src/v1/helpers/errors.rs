@@ -4,6 +4,7 @@ mod codes {
 	// NOTE [ToDr] Codes from [-32099, -32000]
 	pub const UNKNOWN: i64 = -32000;
 	pub const EXECUTION_ERROR: i64 = -32015;
+	pub const ADDRESS_INDEX_DISABLED: i64 = -32095;
 	pub const TRANSACTION_NOT_FOUND: i64 = -32096;
 	pub const TRANSACTION_OUTPUT_NOT_FOUND: i64 = -32097;
 	pub const TRANSACTION_OF_SIDE_BRANCH: i64 = -32098;
@@ -83,6 +84,14 @@ pub fn transaction_of_side_branch<T: fmt::Debug>(data: T) -> Error {
 	}
 }
 
+pub fn address_index_disabled() -> Error {
+	Error {
+		code: ErrorCode::ServerError(codes::ADDRESS_INDEX_DISABLED),
+		message: "Address index is disabled".into(),
+		data: None,
+	}
+}
+
 pub fn node_already_added() -> Error {
 	Error {
 		code: ErrorCode::ServerError(codes::NODE_ALREADY_ADDED),
@@ -1,16 +1,23 @@
+use std::collections::HashSet;
 use v1::traits::BlockChain;
-use v1::types::{BlockRef, GetBlockResponse, VerboseBlock, RawBlock};
+use v1::types::{BlockRef, BlockHeader, GetBlockResponse, VerboseBlock, RawBlock};
+use v1::types::{GetBlockHeaderResponse, VerboseBlockHeader, RawBlockHeader};
 use v1::types::{GetTxOutResponse, TransactionOutputScript};
 use v1::types::GetTxOutSetInfoResponse;
-use v1::types::H256;
+use v1::types::{GetTreeStateResponse, TreeState, TreeStateCommitments};
+use v1::types::{H256, ShieldedPool};
+use v1::types::{GetBlockSubsidyResponse, FundingStreamSubsidy};
+use v1::types::UnspentOutput;
 use keys::{self, Address};
 use v1::helpers::errors::{block_not_found, block_at_height_not_found, transaction_not_found,
-	transaction_output_not_found, transaction_of_side_branch, invalid_params};
+	transaction_output_not_found, transaction_of_side_branch, invalid_params, address_index_disabled};
 use jsonrpc_core::Error;
-use storage;
-use global_script::Script;
+use storage::{self, SproutTreeState, SaplingTreeState, EpochTag, NullifierTracker, IndexKind};
+use global_script::{Script, Builder as ScriptBuilder};
 use chain::OutPoint;
+use chain::constants::SATOSHIS_IN_COIN;
 use verification;
+use verification::constants::COINBASE_MATURITY;
 use ser::serialize;
 use network::{Network, ConsensusParams};
 use primitives::hash::H256 as GlobalH256;
@@ -23,10 +30,17 @@ pub trait BlockChainClientCoreApi: Send + Sync + 'static {
 	fn best_block_hash(&self) -> GlobalH256;
 	fn block_count(&self) -> u32;
 	fn block_hash(&self, height: u32) -> Option<GlobalH256>;
+	fn block_hashes(&self, high: u32, low: u32) -> Vec<GlobalH256>;
 	fn difficulty(&self) -> f64;
 	fn raw_block(&self, hash: GlobalH256) -> Option<RawBlock>;
 	fn verbose_block(&self, hash: GlobalH256) -> Option<VerboseBlock>;
+	fn raw_block_header(&self, hash: GlobalH256) -> Option<RawBlockHeader>;
+	fn verbose_block_header(&self, hash: GlobalH256) -> Option<VerboseBlockHeader>;
 	fn verbose_transaction_out(&self, prev_out: OutPoint) -> Result<GetTxOutResponse, Error>;
+	fn tree_state(&self, hash: GlobalH256) -> Option<GetTreeStateResponse>;
+	fn is_nullifier_spent(&self, pool: ShieldedPool, nullifier: GlobalH256) -> bool;
+	fn block_subsidy(&self, height: u32) -> GetBlockSubsidyResponse;
+	fn unspent_outputs(&self, addresses: Vec<Address>, include_immature_coinbase: bool) -> Result<Vec<UnspentOutput>, Error>;
 }
 
 pub struct BlockChainClientCore {
@@ -56,6 +70,10 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 		self.storage.block_hash(height)
 	}
 
+	fn block_hashes(&self, high: u32, low: u32) -> Vec<GlobalH256> {
+		self.storage.block_hashes_by_time(low, high)
+	}
+
 	fn difficulty(&self) -> f64 {
 		let best_block = self.storage.best_block();
 		let now = ::time::get_time().sec as u32;
@@ -88,20 +106,37 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 				let block_size = block.size();
 
 				VerboseBlock {
+					header: BlockHeader::from(&block.header),
 					confirmations: confirmations,
 					size: block_size as u32,
 					height: height,
 					difficulty: block.header.raw.bits.to_f64(self.consensus.network.max_bits().into()),
-					previousblockhash: Some(block.header.raw.previous_header_hash.clone().into()),
 					nextblockhash: height.and_then(|h| self.storage.block_hash(h + 1).map(|h| h.into())),
-					bits: block.header.raw.bits.into(),
-					hash: block.hash().clone().into(),
-					merkleroot: block.header.raw.merkle_root_hash.clone().into(),
-					finalsaplingroot: block.header.raw.final_sapling_root.into(),
-					nonce: block.header.raw.nonce.clone().into(),
-					time: block.header.raw.time,
-					tx: block.transactions.into_iter().map(|t| t.hash.into()).collect(),
-					version: block.header.raw.version,
+					tx: block.transactions().iter().map(|t| t.hash.clone().into()).collect(),
+				}
+			})
+	}
+
+	fn raw_block_header(&self, hash: GlobalH256) -> Option<RawBlockHeader> {
+		self.storage.block_header(hash.into())
+			.map(|header| serialize(&header.raw).into())
+	}
+
+	fn verbose_block_header(&self, hash: GlobalH256) -> Option<VerboseBlockHeader> {
+		self.storage.block_header(hash.into())
+			.map(|header| {
+				let height = self.storage.block_number(&header.hash);
+				let confirmations = match height {
+					Some(block_number) => (self.storage.best_block().number - block_number + 1) as i64,
+					None => -1,
+				};
+
+				VerboseBlockHeader {
+					header: BlockHeader::from(&header),
+					confirmations: confirmations,
+					height: height,
+					difficulty: header.raw.bits.to_f64(self.consensus.network.max_bits().into()),
+					nextblockhash: height.and_then(|h| self.storage.block_hash(h + 1).map(|h| h.into())),
 				}
 			})
 	}
@@ -164,6 +199,134 @@ impl BlockChainClientCoreApi for BlockChainClientCore {
 			coinbase: transaction.raw.is_coinbase(),
 		})
 	}
+
+	fn tree_state(&self, hash: GlobalH256) -> Option<GetTreeStateResponse> {
+		let header = self.storage.block_header(hash.into())?;
+		let height = self.storage.block_number(&hash)?;
+		let tree_state_provider = self.storage.as_tree_state_provider();
+
+		let sprout_tree = tree_state_provider.sprout_tree_at_block(&hash)
+			.unwrap_or_else(SproutTreeState::new);
+		// before Sapling activation, header's final sapling root field is reserved
+		let sapling_tree = if self.consensus.is_sapling_active(height) {
+			tree_state_provider.sapling_tree_at_block(&hash)
+				.unwrap_or_else(SaplingTreeState::new)
+		} else {
+			SaplingTreeState::new()
+		};
+
+		Some(GetTreeStateResponse {
+			hash: hash.into(),
+			height: height,
+			time: header.raw.time,
+			sprout: TreeState {
+				commitments: TreeStateCommitments {
+					final_root: sprout_tree.root().into(),
+					final_state: serialize(&sprout_tree).into(),
+				},
+			},
+			sapling: TreeState {
+				commitments: TreeStateCommitments {
+					final_root: sapling_tree.root().into(),
+					final_state: serialize(&sapling_tree).into(),
+				},
+			},
+		})
+	}
+
+	fn is_nullifier_spent(&self, pool: ShieldedPool, nullifier: GlobalH256) -> bool {
+		let pool = match pool {
+			ShieldedPool::Sprout => EpochTag::Sprout,
+			// there could be no spent sapling notes before Sapling activation
+			ShieldedPool::Sapling if !self.consensus.is_sapling_active(self.storage.best_block().number) => return false,
+			ShieldedPool::Sapling => EpochTag::Sapling,
+		};
+
+		self.storage.is_nullifier_spent(pool, &nullifier)
+	}
+
+	fn block_subsidy(&self, height: u32) -> GetBlockSubsidyResponse {
+		let total = self.consensus.block_reward(height);
+		let founders = match self.consensus.founder_address(height) {
+			Some(_) => self.consensus.founder_reward(height),
+			None => 0,
+		};
+		let funding_streams = self.consensus.funding_streams(height);
+		let miner = total - founders - funding_streams.iter().map(|&(_, value)| value).sum::<u64>();
+
+		GetBlockSubsidyResponse {
+			total: zatoshis_to_zec(total),
+			miner: zatoshis_to_zec(miner),
+			founders: zatoshis_to_zec(founders),
+			fundingstreams: funding_streams.into_iter()
+				.map(|(stream, value)| FundingStreamSubsidy {
+					recipient: stream.recipient().into(),
+					value: zatoshis_to_zec(value),
+					value_zat: value,
+				})
+				.collect(),
+		}
+	}
+
+	fn unspent_outputs(&self, addresses: Vec<Address>, include_immature_coinbase: bool) -> Result<Vec<UnspentOutput>, Error> {
+		let chain_index = self.storage.as_chain_index();
+		if chain_index.index_next_block(IndexKind::Address).is_none() {
+			return Err(address_index_disabled());
+		}
+
+		let best_block = self.storage.best_block();
+		let mut scripts = HashSet::new();
+		let mut unspent_outputs = Vec::new();
+		for address in addresses {
+			let script_pubkey = match address.kind {
+				keys::Type::P2PKH => ScriptBuilder::build_p2pkh(&address.hash),
+				keys::Type::P2SH => ScriptBuilder::build_p2sh(&address.hash),
+			}.to_bytes();
+			if !scripts.insert(script_pubkey.clone()) {
+				// the same address is requested twice
+				continue;
+			}
+
+			// index holds all canon outputs, paying to the script => filter out spent ones
+			for outpoint in chain_index.address_outputs(&script_pubkey) {
+				let meta = match self.storage.transaction_meta(&outpoint.hash) {
+					Some(meta) => meta,
+					// this is possible during reorgs
+					None => continue,
+				};
+				if meta.height() > best_block.number || meta.is_spent(outpoint.index as usize) != Some(false) {
+					continue;
+				}
+
+				// coinbase output could only be spent by block at height >= coinbase height + COINBASE_MATURITY
+				let is_immature = meta.is_coinbase() && best_block.number + 1 < meta.height() + COINBASE_MATURITY;
+				if is_immature && !include_immature_coinbase {
+					continue;
+				}
+
+				let transaction = match self.storage.transaction(&outpoint.hash) {
+					Some(transaction) => transaction,
+					None => continue,
+				};
+				let ref output = transaction.raw.outputs[outpoint.index as usize];
+				unspent_outputs.push(UnspentOutput {
+					txid: outpoint.hash.into(),
+					vout: outpoint.index,
+					address: address.clone(),
+					script: output.script_pubkey.clone().into(),
+					amount: zatoshis_to_zec(output.value),
+					confirmations: best_block.number - meta.height() + 1,
+					generated: meta.is_coinbase(),
+				});
+			}
+		}
+
+		Ok(unspent_outputs)
+	}
+}
+
+fn zatoshis_to_zec(value: u64) -> f64 {
+	value as f64 / SATOSHIS_IN_COIN as f64
 }
 
 impl<T> BlockChainClient<T> where T: BlockChainClientCoreApi {
@@ -189,6 +352,10 @@ impl<T> BlockChain for BlockChainClient<T> where T: BlockChainClientCoreApi {
 			.ok_or(block_at_height_not_found(height))
 	}
 
+	fn block_hashes(&self, high: u32, low: u32) -> Result<Vec<H256>, Error> {
+		Ok(self.core.block_hashes(high, low).into_iter().map(|h| h.reversed().into()).collect())
+	}
+
 	fn difficulty(&self) -> Result<f64, Error> {
 		Ok(self.core.difficulty())
 	}
@@ -214,11 +381,11 @@ impl<T> BlockChain for BlockChainClient<T> where T: BlockChainClientCoreApi {
 			None | Some(1) => {
 				let verbose_block = self.core.verbose_block(global_hash);
 				if let Some(mut verbose_block) = verbose_block {
-					verbose_block.previousblockhash = verbose_block.previousblockhash.map(|h| h.reversed());
+					verbose_block.header.previousblockhash = verbose_block.header.previousblockhash.map(|h| h.reversed());
 					verbose_block.nextblockhash = verbose_block.nextblockhash.map(|h| h.reversed());
-					verbose_block.hash = verbose_block.hash.reversed();
-					verbose_block.merkleroot = verbose_block.merkleroot.reversed();
-					verbose_block.finalsaplingroot = verbose_block.finalsaplingroot.reversed();
+					verbose_block.header.hash = verbose_block.header.hash.reversed();
+					verbose_block.header.merkleroot = verbose_block.header.merkleroot.reversed();
+					verbose_block.header.finalsaplingroot = verbose_block.header.finalsaplingroot.reversed();
 					verbose_block.tx = verbose_block.tx.into_iter().map(|h| h.reversed()).collect();
 					Some(GetBlockResponse::Verbose(verbose_block))
 				} else {
@@ -232,6 +399,28 @@ impl<T> BlockChain for BlockChainClient<T> where T: BlockChainClientCoreApi {
 		}
 	}
 
+	fn block_header(&self, hash: H256, verbose: Option<bool>) -> Result<GetBlockHeaderResponse, Error> {
+		let global_hash: GlobalH256 = hash.into();
+		let global_hash = global_hash.reversed();
+
+		if verbose.unwrap_or(true) {
+			self.core.verbose_block_header(global_hash)
+				.map(|mut verbose_header| {
+					verbose_header.header.previousblockhash = verbose_header.header.previousblockhash.map(|h| h.reversed());
+					verbose_header.nextblockhash = verbose_header.nextblockhash.map(|h| h.reversed());
+					verbose_header.header.hash = verbose_header.header.hash.reversed();
+					verbose_header.header.merkleroot = verbose_header.header.merkleroot.reversed();
+					verbose_header.header.finalsaplingroot = verbose_header.header.finalsaplingroot.reversed();
+					GetBlockHeaderResponse::Verbose(verbose_header)
+				})
+				.ok_or(block_not_found(global_hash.reversed()))
+		} else {
+			self.core.raw_block_header(global_hash)
+				.map(GetBlockHeaderResponse::Raw)
+				.ok_or(block_not_found(global_hash.reversed()))
+		}
+	}
+
 	fn transaction_out(&self, transaction_hash: H256, out_index: u32, _include_mempool: Option<bool>) -> Result<GetTxOutResponse, Error> {
 		// TODO: include_mempool
 		let transaction_hash: GlobalH256 = transaction_hash.into();
@@ -245,6 +434,47 @@ impl<T> BlockChain for BlockChainClient<T> where T: BlockChainClientCoreApi {
 	fn transaction_out_set_info(&self) -> Result<GetTxOutSetInfoResponse, Error> {
 		rpc_unimplemented!()
 	}
+
+	fn tree_state(&self, block: BlockRef) -> Result<GetTreeStateResponse, Error> {
+		let global_hash = match block {
+			BlockRef::Number(number) => self.core
+				.block_hash(number)
+				.ok_or(block_at_height_not_found(number))?,
+			BlockRef::Hash(hash) => {
+				let h: GlobalH256 = hash.into();
+				h.reversed()
+			},
+		};
+
+		self.core.tree_state(global_hash)
+			.map(|mut response| {
+				response.hash = response.hash.reversed();
+				response.sprout.commitments.final_root = response.sprout.commitments.final_root.reversed();
+				response.sapling.commitments.final_root = response.sapling.commitments.final_root.reversed();
+				response
+			})
+			.ok_or(block_not_found(global_hash.reversed()))
+	}
+
+	fn is_nullifier_spent(&self, pool: ShieldedPool, nullifier: H256) -> Result<bool, Error> {
+		let nullifier: GlobalH256 = nullifier.into();
+		Ok(self.core.is_nullifier_spent(pool, nullifier.reversed()))
+	}
+
+	fn block_subsidy(&self, height: u32) -> Result<GetBlockSubsidyResponse, Error> {
+		Ok(self.core.block_subsidy(height))
+	}
+
+	fn unspent_outputs(&self, addresses: Vec<String>, include_immature_coinbase: Option<bool>) -> Result<Vec<UnspentOutput>, Error> {
+		let addresses = addresses.into_iter()
+			.map(|address| address.parse().map_err(|_| invalid_params("addresses", address)))
+			.collect::<Result<Vec<Address>, _>>()?;
+		self.core.unspent_outputs(addresses, include_immature_coinbase.unwrap_or(true))
+			.map(|outputs| outputs.into_iter().map(|mut output| {
+				output.txid = output.txid.reversed();
+				output
+			}).collect())
+	}
 }
 
 #[cfg(test)]
@@ -264,7 +494,8 @@ pub mod tests {
 	use v1::types::Bytes;
 	use v1::types::H256;
 	use v1::types::ScriptType;
-	use chain::OutPoint;
+	use chain::{OutPoint, Transaction, Sapling, SaplingSpendDescription, SaplingOutputDescription, IndexedBlock};
+	use storage::{ChainIndex, IndexKind};
 	use network::Network;
 	use super::*;
 
@@ -286,6 +517,10 @@ pub mod tests {
 			Some(test_data::genesis().hash())
 		}
 
+		fn block_hashes(&self, _high: u32, _low: u32) -> Vec<GlobalH256> {
+			vec![test_data::genesis().hash(), test_data::block_h1().hash()]
+		}
+
 		fn difficulty(&self) -> f64 {
 			1f64
 		}
@@ -300,20 +535,47 @@ pub mod tests {
 			// https://blockchain.info/ru/block/000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd
 			// https://webbtc.com/block/000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd.json
 			Some(VerboseBlock {
-				hash: "bddd99ccfda39da1b108ce1a5d70038d0a967bacb68b6b63065f626a00000000".into(),
+				header: BlockHeader {
+					hash: "bddd99ccfda39da1b108ce1a5d70038d0a967bacb68b6b63065f626a00000000".into(),
+					version: 1,
+					merkleroot: "d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9b".into(),
+					finalsaplingroot: "a5556cd346010000000000000000000000000000000000000000000000000002".into(),
+					time: 1231469744,
+					nonce: 42.into(),
+					solution: "0102".into(),
+					bits: 486604799,
+					previousblockhash: Some("4860eb18bf1b1620e37e9490fc8a427514416fd75159ab86688e9a8300000000".into()),
+				},
 				confirmations: 1, // h2
 				size: 215,
 				height: Some(2),
-				version: 1,
-				merkleroot: "d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9b".into(),
 				tx: vec!["d5fdcc541e25de1c7a5addedf24858b8bb665c9f36ef744ee42c316022c90f9b".into()],
-				time: 1231469744,
-				nonce: 42.into(),
-				bits: 486604799,
 				difficulty: 1.0,
-				previousblockhash: Some("4860eb18bf1b1620e37e9490fc8a427514416fd75159ab86688e9a8300000000".into()),
 				nextblockhash: None,
-				finalsaplingroot: "a5556cd346010000000000000000000000000000000000000000000000000002".into(),
+			})
+		}
+
+		fn raw_block_header(&self, _hash: GlobalH256) -> Option<RawBlockHeader> {
+			Some(serialize(&test_data::block_h1().block_header).into())
+		}
+
+		fn verbose_block_header(&self, _hash: GlobalH256) -> Option<VerboseBlockHeader> {
+			Some(VerboseBlockHeader {
+				header: BlockHeader {
+					hash: "8392336da29773c56b1649ab555156ceb7e700ad7c230ea7a4571c7e22bc0700".into(),
+					version: 4,
+					merkleroot: "0946edb9c083c9942d92305444527765fad789c438c717783276a9f7fbf61b85".into(),
+					finalsaplingroot: "0000000000000000000000000000000000000000000000000000000000000000".into(),
+					time: 1477671596,
+					nonce: 42.into(),
+					solution: "0102".into(),
+					bits: 520617983,
+					previousblockhash: Some("08ce3d9731b000c08338455c8a4a6bd05da16e26b11daa1b917184ece80f0400".into()),
+				},
+				confirmations: 2,
+				height: Some(1),
+				difficulty: 1.0,
+				nextblockhash: Some("ed73e297d7c51cb8dc53fc2213d7e2e3f116eb4f26434496fc1926906ca20200".into()),
 			})
 		}
 
@@ -333,6 +595,55 @@ pub mod tests {
 				coinbase: false,
 			})
 		}
+
+		fn tree_state(&self, _hash: GlobalH256) -> Option<GetTreeStateResponse> {
+			Some(GetTreeStateResponse {
+				hash: "8392336da29773c56b1649ab555156ceb7e700ad7c230ea7a4571c7e22bc0700".into(),
+				height: 1,
+				time: 1477671596,
+				sprout: TreeState {
+					commitments: TreeStateCommitments {
+						final_root: H256::from(0x01),
+						final_state: Bytes::new(vec![0, 0, 0]),
+					},
+				},
+				sapling: TreeState {
+					commitments: TreeStateCommitments {
+						final_root: H256::from(0x02),
+						final_state: Bytes::new(vec![0, 0, 0]),
+					},
+				},
+			})
+		}
+
+		fn is_nullifier_spent(&self, _pool: ShieldedPool, _nullifier: GlobalH256) -> bool {
+			true
+		}
+
+		fn block_subsidy(&self, _height: u32) -> GetBlockSubsidyResponse {
+			GetBlockSubsidyResponse {
+				total: 6.25,
+				miner: 5.8125,
+				founders: 0.0,
+				fundingstreams: vec![FundingStreamSubsidy {
+					recipient: "Electric Coin Company".into(),
+					value: 0.4375,
+					value_zat: 43_750_000,
+				}],
+			}
+		}
+
+		fn unspent_outputs(&self, _addresses: Vec<Address>, _include_immature_coinbase: bool) -> Result<Vec<UnspentOutput>, Error> {
+			Ok(vec![UnspentOutput {
+				txid: H256::from(0x56),
+				vout: 1,
+				address: "t1KstPVzcNEK4ZeauQ6cogoqxQBMDSiRnGr".into(),
+				script: Bytes::new(vec![1, 2, 3, 4]),
+				amount: 0.0005,
+				confirmations: 77,
+				generated: true,
+			}])
+		}
 	}
 
 	impl BlockChainClientCoreApi for ErrorBlockChainClientCore {
@@ -348,6 +659,10 @@ pub mod tests {
 			None
 		}
 
+		fn block_hashes(&self, _high: u32, _low: u32) -> Vec<GlobalH256> {
+			vec![]
+		}
+
 		fn difficulty(&self) -> f64 {
 			1f64
 		}
@@ -360,9 +675,38 @@ pub mod tests {
 			None
 		}
 
+		fn raw_block_header(&self, _hash: GlobalH256) -> Option<RawBlockHeader> {
+			None
+		}
+
+		fn verbose_block_header(&self, _hash: GlobalH256) -> Option<VerboseBlockHeader> {
+			None
+		}
+
 		fn verbose_transaction_out(&self, prev_out: OutPoint) -> Result<GetTxOutResponse, Error> {
 			Err(block_not_found(prev_out.hash))
 		}
+
+		fn tree_state(&self, _hash: GlobalH256) -> Option<GetTreeStateResponse> {
+			None
+		}
+
+		fn is_nullifier_spent(&self, _pool: ShieldedPool, _nullifier: GlobalH256) -> bool {
+			false
+		}
+
+		fn block_subsidy(&self, _height: u32) -> GetBlockSubsidyResponse {
+			GetBlockSubsidyResponse {
+				total: 0.0,
+				miner: 0.0,
+				founders: 0.0,
+				fundingstreams: vec![],
+			}
+		}
+
+		fn unspent_outputs(&self, _addresses: Vec<Address>, _include_immature_coinbase: bool) -> Result<Vec<UnspentOutput>, Error> {
+			Err(address_index_disabled())
+		}
 	}
 
 	#[test]
@@ -433,6 +777,59 @@ pub mod tests {
 		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block at given height is not found","data":"0"},"id":1}"#);
 	}
 
+	#[test]
+	fn block_hashes_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(
+			vec![
+				test_data::genesis().into(),
+				test_data::block_h1().into(),
+				test_data::block_h2().into(),
+			]
+		));
+
+		let core = BlockChainClientCore::new(ConsensusParams::new(Network::Mainnet), storage);
+
+		// h1 time is 1477671596, h2 time is 1477671626
+		assert_eq!(core.block_hashes(1477671626, 1477671596), vec![test_data::block_h1().hash(), test_data::block_h2().hash()]);
+		assert_eq!(core.block_hashes(1477671625, 0), vec![test_data::genesis().hash(), test_data::block_h1().hash()]);
+		assert_eq!(core.block_hashes(u32::max_value(), 1477671627), vec![]);
+		assert_eq!(core.block_hashes(1477671595, 1477671000), vec![]);
+	}
+
+	#[test]
+	fn block_hashes_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockhashes",
+				"params": [1477671596, 0],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":["00040fe8ec8471911baa1db1266ea15dd06b4a8a5c453883c000b031973dce08","0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283"],"id":1}"#);
+	}
+
+	#[test]
+	fn block_hashes_empty() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockhashes",
+				"params": [1477671596, 0],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":[],"id":1}"#);
+	}
+
 	#[test]
 	fn difficulty_success() {
 		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
@@ -466,40 +863,46 @@ pub mod tests {
 		// https://zcash.blockexplorer.com/block/0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283
 		let verbose_block = core.verbose_block("8392336da29773c56b1649ab555156ceb7e700ad7c230ea7a4571c7e22bc0700".into());
 		assert_eq!(verbose_block, Some(VerboseBlock {
-			hash: "8392336da29773c56b1649ab555156ceb7e700ad7c230ea7a4571c7e22bc0700".into(),
+			header: BlockHeader {
+				hash: "8392336da29773c56b1649ab555156ceb7e700ad7c230ea7a4571c7e22bc0700".into(),
+				version: 4,
+				merkleroot: "0946edb9c083c9942d92305444527765fad789c438c717783276a9f7fbf61b85".into(),
+				finalsaplingroot: "0000000000000000000000000000000000000000000000000000000000000000".into(),
+				time: 1477671596,
+				nonce: "7534e8cf161ff2e49d54bdb3bfbcde8cdbf2fc5963c9ec7d86aed4a67e975790".into(),
+				solution: test_data::block_h1().block_header.solution.as_ref().to_vec().into(),
+				bits: 520617983,
+				previousblockhash: Some("08ce3d9731b000c08338455c8a4a6bd05da16e26b11daa1b917184ece80f0400".into()),
+			},
 			confirmations: 2, // h1 + h2
 			size: 1617,
 			height: Some(1),
-			version: 4,
-			merkleroot: "0946edb9c083c9942d92305444527765fad789c438c717783276a9f7fbf61b85".into(),
 			tx: vec!["0946edb9c083c9942d92305444527765fad789c438c717783276a9f7fbf61b85".into()],
-			time: 1477671596,
-			nonce: "7534e8cf161ff2e49d54bdb3bfbcde8cdbf2fc5963c9ec7d86aed4a67e975790".into(),
-			bits: 520617983,
 			difficulty: 1.0,
-			previousblockhash: Some("08ce3d9731b000c08338455c8a4a6bd05da16e26b11daa1b917184ece80f0400".into()),
 			nextblockhash: Some("ed73e297d7c51cb8dc53fc2213d7e2e3f116eb4f26434496fc1926906ca20200".into()),
-			finalsaplingroot: "0000000000000000000000000000000000000000000000000000000000000000".into(),
 		}));
 
 		// get info on block #2:
 		// https://zcash.blockexplorer.com/block/0002a26c902619fc964443264feb16f1e3e2d71322fc53dcb81cc5d797e273ed
 		let verbose_block = core.verbose_block("ed73e297d7c51cb8dc53fc2213d7e2e3f116eb4f26434496fc1926906ca20200".into());
 		assert_eq!(verbose_block, Some(VerboseBlock {
-			hash: "ed73e297d7c51cb8dc53fc2213d7e2e3f116eb4f26434496fc1926906ca20200".into(),
+			header: BlockHeader {
+				hash: "ed73e297d7c51cb8dc53fc2213d7e2e3f116eb4f26434496fc1926906ca20200".into(),
+				version: 4,
+				merkleroot: "f4b084a7c2fc5a5aa2985f2bcb1d4a9a65562a589d628b0d869c5f1c8dd07489".into(),
+				finalsaplingroot: "0000000000000000000000000000000000000000000000000000000000000000".into(),
+				time: 1477671626,
+				nonce: "a5556cd346010000000000000000000000000000000000000000000000000002".into(),
+				solution: test_data::block_h2().block_header.solution.as_ref().to_vec().into(),
+				bits: 520617983,
+				previousblockhash: Some("8392336da29773c56b1649ab555156ceb7e700ad7c230ea7a4571c7e22bc0700".into()),
+			},
 			confirmations: 1, // h2
 			size: 1617,
 			height: Some(2),
-			version: 4,
-			merkleroot: "f4b084a7c2fc5a5aa2985f2bcb1d4a9a65562a589d628b0d869c5f1c8dd07489".into(),
 			tx: vec!["f4b084a7c2fc5a5aa2985f2bcb1d4a9a65562a589d628b0d869c5f1c8dd07489".into()],
-			time: 1477671626,
-			nonce: "a5556cd346010000000000000000000000000000000000000000000000000002".into(),
-			bits: 520617983,
 			difficulty: 1.0,
-			previousblockhash: Some("8392336da29773c56b1649ab555156ceb7e700ad7c230ea7a4571c7e22bc0700".into()),
 			nextblockhash: None,
-			finalsaplingroot: "0000000000000000000000000000000000000000000000000000000000000000".into(),
 		}));
 	}
 
@@ -544,7 +947,7 @@ pub mod tests {
 		let mut handler = IoHandler::new();
 		handler.extend_with(client.to_delegate());
 
-		let expected = r#"{"jsonrpc":"2.0","result":{"bits":486604799,"confirmations":1,"difficulty":1.0,"finalsaplingroot":"02000000000000000000000000000000000000000000000000000146d36c55a5","hash":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd","height":2,"merkleroot":"9b0fc92260312ce44e74ef369f5c66bbb85848f2eddd5a7a1cde251e54ccfdd5","nextblockhash":null,"nonce":"2a00000000000000000000000000000000000000000000000000000000000000","previousblockhash":"00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048","size":215,"time":1231469744,"tx":["9b0fc92260312ce44e74ef369f5c66bbb85848f2eddd5a7a1cde251e54ccfdd5"],"version":1},"id":1}"#;
+		let expected = r#"{"jsonrpc":"2.0","result":{"bits":486604799,"confirmations":1,"difficulty":1.0,"finalsaplingroot":"02000000000000000000000000000000000000000000000000000146d36c55a5","hash":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd","height":2,"merkleroot":"9b0fc92260312ce44e74ef369f5c66bbb85848f2eddd5a7a1cde251e54ccfdd5","nextblockhash":null,"nonce":"2a00000000000000000000000000000000000000000000000000000000000000","previousblockhash":"00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048","size":215,"solution":"0102","time":1231469744,"tx":["9b0fc92260312ce44e74ef369f5c66bbb85848f2eddd5a7a1cde251e54ccfdd5"],"version":1},"id":1}"#;
 
 		let sample = handler.handle_request_sync(&(r#"
 			{
@@ -584,6 +987,100 @@ pub mod tests {
 		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"000000006a625f06636b8bb6ac7b960a8d03705d1ace08b1a19da3fdcc99ddbd"},"id":1}"#);
 	}
 
+	#[test]
+	fn block_header_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(
+			vec![
+				test_data::genesis().into(),
+				test_data::block_h1().into(),
+				test_data::block_h2().into(),
+			]
+		));
+
+		let core = BlockChainClientCore::new(ConsensusParams::new(Network::Mainnet), storage);
+
+		// header of block #1:
+		// https://zcash.blockexplorer.com/block/0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283
+		let hash: GlobalH256 = "8392336da29773c56b1649ab555156ceb7e700ad7c230ea7a4571c7e22bc0700".into();
+		assert_eq!(core.verbose_block_header(hash.clone()), Some(VerboseBlockHeader {
+			header: BlockHeader {
+				hash: "8392336da29773c56b1649ab555156ceb7e700ad7c230ea7a4571c7e22bc0700".into(),
+				version: 4,
+				merkleroot: "0946edb9c083c9942d92305444527765fad789c438c717783276a9f7fbf61b85".into(),
+				finalsaplingroot: "0000000000000000000000000000000000000000000000000000000000000000".into(),
+				time: 1477671596,
+				nonce: "7534e8cf161ff2e49d54bdb3bfbcde8cdbf2fc5963c9ec7d86aed4a67e975790".into(),
+				solution: test_data::block_h1().block_header.solution.as_ref().to_vec().into(),
+				bits: 520617983,
+				previousblockhash: Some("08ce3d9731b000c08338455c8a4a6bd05da16e26b11daa1b917184ece80f0400".into()),
+			},
+			confirmations: 2, // h1 + h2
+			height: Some(1),
+			difficulty: 1.0,
+			nextblockhash: Some("ed73e297d7c51cb8dc53fc2213d7e2e3f116eb4f26434496fc1926906ca20200".into()),
+		}));
+		assert_eq!(core.raw_block_header(hash), Some(serialize(&test_data::block_h1().block_header).into()));
+
+		// unknown block
+		assert_eq!(core.verbose_block_header(GlobalH256::from(1)), None);
+		assert_eq!(core.raw_block_header(GlobalH256::from(1)), None);
+	}
+
+	#[test]
+	fn block_header_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let expected = r#"{"jsonrpc":"2.0","result":{"bits":520617983,"confirmations":2,"difficulty":1.0,"finalsaplingroot":"0000000000000000000000000000000000000000000000000000000000000000","hash":"0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283","height":1,"merkleroot":"851bf6fbf7a976327817c738c489d7fa657752445430922d94c983c0b9ed4609","nextblockhash":"0002a26c902619fc964443264feb16f1e3e2d71322fc53dcb81cc5d797e273ed","nonce":"2a00000000000000000000000000000000000000000000000000000000000000","previousblockhash":"00040fe8ec8471911baa1db1266ea15dd06b4a8a5c453883c000b031973dce08","solution":"0102","time":1477671596,"version":4},"id":1}"#;
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockheader",
+				"params": ["0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283", true],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, expected);
+
+		// verbose is the default
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockheader",
+				"params": ["0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283"],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, expected);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockheader",
+				"params": ["0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283", false],
+				"id": 1
+			}"#)).unwrap();
+		let raw_header: Bytes = serialize(&test_data::block_h1().block_header).into();
+		assert_eq!(sample, format!(r#"{{"jsonrpc":"2.0","result":{},"id":1}}"#, ::serde_json::to_string(&raw_header).unwrap()));
+	}
+
+	#[test]
+	fn block_header_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblockheader",
+				"params": ["0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283"},"id":1}"#);
+	}
+
 	#[test]
 	fn verbose_transaction_out_contents() {
 		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into(), test_data::block_h1().into()]));
@@ -644,4 +1141,305 @@ pub mod tests {
 
 		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"3ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a"},"id":1}"#);
 	}
+
+	#[test]
+	fn tree_state_contents() {
+		let note_commitment = GlobalH256::from_reversed_str("bab6e8992959caf0ca94847c36b4e648a7f88a9b9c6a62ea387cf1fb9badfd62");
+		let mut expected_sapling_tree = SaplingTreeState::new();
+		expected_sapling_tree.append(note_commitment).unwrap();
+
+		let sapling_tx: Transaction = test_data::TransactionBuilder::with_sapling(Sapling {
+			outputs: vec![SaplingOutputDescription {
+				note_commitment: note_commitment.take(),
+				..Default::default()
+			}],
+			..Default::default()
+		}).into();
+
+		let genesis = test_data::genesis();
+		let b1 = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(10).build()
+				.build()
+			.with_transaction(sapling_tx)
+			.merkled_header()
+				.parent(genesis.hash())
+				.final_sapling_root(expected_sapling_tree.root())
+				.build()
+			.build();
+		let b1_hash = b1.hash();
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.clone().into(), b1.clone().into()]));
+
+		// when Sapling is active, returned anchor is the block's final sapling root
+		let mut consensus = ConsensusParams::new(Network::Mainnet);
+		consensus.sapling_height = 1;
+		let core = BlockChainClientCore::new(consensus, storage.clone());
+		let tree_state = core.tree_state(b1_hash).unwrap();
+		assert_eq!(tree_state.height, 1);
+		assert_eq!(tree_state.time, b1.block_header.time);
+		assert_eq!(tree_state.sapling.commitments.final_root, H256::from(b1.block_header.final_sapling_root));
+		assert_eq!(tree_state.sapling.commitments.final_state, Bytes::from(serialize(&expected_sapling_tree)));
+		assert_eq!(tree_state.sprout.commitments.final_root, H256::from(SproutTreeState::new().root()));
+
+		// when Sapling isn't active yet, sapling tree is empty
+		let core = BlockChainClientCore::new(ConsensusParams::new(Network::Mainnet), storage);
+		let tree_state = core.tree_state(b1_hash).unwrap();
+		assert_eq!(tree_state.sapling.commitments.final_root, H256::from(SaplingTreeState::new().root()));
+		assert_eq!(tree_state.sapling.commitments.final_state, Bytes::from(serialize(&SaplingTreeState::new())));
+
+		// when block is unknown
+		assert_eq!(core.tree_state(GlobalH256::from(1)), None);
+	}
+
+	#[test]
+	fn tree_state_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "z_gettreestate",
+				"params": ["0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"hash":"0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283","height":1,"sapling":{"commitments":{"finalRoot":"0000000000000000000000000000000000000000000000000000000000000002","finalState":"000000"}},"sprout":{"commitments":{"finalRoot":"0000000000000000000000000000000000000000000000000000000000000001","finalState":"000000"}},"time":1477671596},"id":1}"#);
+	}
+
+	#[test]
+	fn tree_state_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "z_gettreestate",
+				"params": ["0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32099,"message":"Block with given hash is not found","data":"0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283"},"id":1}"#);
+	}
+
+	#[test]
+	fn is_nullifier_spent_contents() {
+		let spent_nullifier = GlobalH256::from(1);
+		let sapling_tx: Transaction = test_data::TransactionBuilder::with_sapling(Sapling {
+			spends: vec![SaplingSpendDescription {
+				nullifier: spent_nullifier.clone().take(),
+				..Default::default()
+			}],
+			..Default::default()
+		}).into();
+
+		let genesis = test_data::genesis();
+		let b1 = test_data::block_builder()
+			.transaction().coinbase().build()
+			.with_transaction(sapling_tx)
+			.merkled_header().parent(genesis.hash()).build()
+			.build();
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.into(), b1.into()]));
+
+		let mut consensus = ConsensusParams::new(Network::Mainnet);
+		consensus.sapling_height = 1;
+		let core = BlockChainClientCore::new(consensus, storage.clone());
+		assert!(core.is_nullifier_spent(ShieldedPool::Sapling, spent_nullifier.clone()));
+		assert!(!core.is_nullifier_spent(ShieldedPool::Sapling, GlobalH256::from(2)));
+		assert!(!core.is_nullifier_spent(ShieldedPool::Sprout, spent_nullifier.clone()));
+
+		// when Sapling pool doesn't exist at the current height
+		let core = BlockChainClientCore::new(ConsensusParams::new(Network::Mainnet), storage);
+		assert!(!core.is_nullifier_spent(ShieldedPool::Sapling, spent_nullifier));
+	}
+
+	#[test]
+	fn is_nullifier_spent_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "z_isnullifierspent",
+				"params": ["sapling", "0101010101010101010101010101010101010101010101010101010101010101"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "z_isnullifierspent",
+				"params": ["sprout", "0101010101010101010101010101010101010101010101010101010101010101"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":false,"id":1}"#);
+	}
+
+	#[test]
+	fn block_subsidy_contents() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let core = BlockChainClientCore::new(ConsensusParams::new(Network::Mainnet), storage);
+
+		// slow start: founders reward is paid
+		assert_eq!(core.block_subsidy(1), GetBlockSubsidyResponse {
+			total: 0.000625,
+			miner: 0.0005,
+			founders: 0.000125,
+			fundingstreams: vec![],
+		});
+
+		// after Canopy (which is also the first halving): funding streams are paid
+		assert_eq!(core.block_subsidy(1_046_400), GetBlockSubsidyResponse {
+			total: 3.125,
+			miner: 2.5,
+			founders: 0.0,
+			fundingstreams: vec![
+				FundingStreamSubsidy { recipient: "Electric Coin Company".into(), value: 0.21875, value_zat: 21_875_000 },
+				FundingStreamSubsidy { recipient: "Zcash Foundation".into(), value: 0.15625, value_zat: 15_625_000 },
+				FundingStreamSubsidy { recipient: "Major Grants".into(), value: 0.25, value_zat: 25_000_000 },
+			],
+		});
+
+		// after funding streams end: only (halved) miner subsidy is paid
+		assert_eq!(core.block_subsidy(30_000_000), GetBlockSubsidyResponse {
+			total: 0.00002384,
+			miner: 0.00002384,
+			founders: 0.0,
+			fundingstreams: vec![],
+		});
+	}
+
+	#[test]
+	fn block_subsidy_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getblocksubsidy",
+				"params": [1046400],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"founders":0.0,"fundingstreams":[{"recipient":"Electric Coin Company","value":0.4375,"valueZat":43750000}],"miner":5.8125,"total":6.25},"id":1}"#);
+	}
+
+	#[test]
+	fn unspent_outputs_contents() {
+		let address = |hash: u8| Address {
+			kind: keys::Type::P2PKH,
+			network: keys::Network::Mainnet,
+			hash: hash.into(),
+		};
+		let script = |address: &Address| ScriptBuilder::build_p2pkh(&address.hash).to_bytes();
+		let (a, b, c) = (address(1), address(2), address(3));
+
+		let genesis = test_data::genesis();
+		let b1: IndexedBlock = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(10).script_pubkey_bytes(script(&a)).build()
+				.output().value(20).script_pubkey_bytes(script(&b)).build()
+				.build()
+			.merkled_header().parent(genesis.hash()).build()
+			.build()
+			.into();
+		let b1_coinbase = b1.transactions()[0].hash.clone();
+		let b2: IndexedBlock = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(30).script_pubkey_bytes(script(&c)).build()
+				.build()
+			.transaction()
+				.input().hash(b1_coinbase.clone()).index(1).build()
+				.output().value(5).script_pubkey_bytes(script(&a)).build()
+				.build()
+			.merkled_header().parent(b1.hash().clone()).build()
+			.build()
+			.into();
+		let b2_transaction = b2.transactions()[1].hash.clone();
+
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.into(), b1, b2]));
+		let core = BlockChainClientCore::new(ConsensusParams::new(Network::Mainnet), storage.clone());
+
+		// address index is required
+		assert_eq!(core.unspent_outputs(vec![a.clone()], true), Err(address_index_disabled()));
+
+		storage.enable_index(IndexKind::Address).unwrap();
+		storage::rescan_index(&*storage, IndexKind::Address, 2, |_| ()).unwrap();
+
+		let mature_output = UnspentOutput {
+			txid: b2_transaction.into(),
+			vout: 0,
+			address: a.clone(),
+			script: script(&a).into(),
+			amount: zatoshis_to_zec(5),
+			confirmations: 1,
+			generated: false,
+		};
+		let immature_coinbase_output = UnspentOutput {
+			txid: b1_coinbase.into(),
+			vout: 0,
+			address: a.clone(),
+			script: script(&a).into(),
+			amount: zatoshis_to_zec(10),
+			confirmations: 2,
+			generated: true,
+		};
+
+		// output, paying to b, is spent
+		assert_eq!(core.unspent_outputs(vec![a.clone(), b.clone(), a.clone()], true),
+			Ok(vec![immature_coinbase_output, mature_output.clone()]));
+		assert_eq!(core.unspent_outputs(vec![a.clone(), b.clone()], false), Ok(vec![mature_output]));
+		assert_eq!(core.unspent_outputs(vec![b.clone(), c.clone()], false), Ok(vec![]));
+	}
+
+	#[test]
+	fn unspent_outputs_success() {
+		let client = BlockChainClient::new(SuccessBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "listunspent",
+				"params": [["t1KstPVzcNEK4ZeauQ6cogoqxQBMDSiRnGr"]],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":[{"address":"t1KstPVzcNEK4ZeauQ6cogoqxQBMDSiRnGr","amount":0.0005,"confirmations":77,"generated":true,"scriptPubKey":"01020304","txid":"0000000000000000000000000000000000000000000000000000000000000056","vout":1}],"id":1}"#);
+	}
+
+	#[test]
+	fn unspent_outputs_error() {
+		let client = BlockChainClient::new(ErrorBlockChainClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "listunspent",
+				"params": [["t1KstPVzcNEK4ZeauQ6cogoqxQBMDSiRnGr"], false],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32095,"message":"Address index is disabled"},"id":1}"#);
+	}
 }
@@ -1,7 +1,9 @@
 use v1::helpers::errors::execution;
 use v1::traits::Miner;
-use v1::types::{BlockTemplate, BlockTemplateRequest};
+use v1::types::{BlockTemplate, BlockTemplateRequest, H256};
 use jsonrpc_core::Error;
+use primitives::hash::H256 as GlobalH256;
+use primitives::bytes::Bytes;
 use keys::Address;
 use sync;
 use miner;
@@ -12,18 +14,23 @@ pub struct MinerClient<T: MinerClientCoreApi> {
 
 pub trait MinerClientCoreApi: Send + Sync + 'static {
 	fn get_block_template(&self) -> Result<miner::BlockTemplate, String>;
+	fn prioritise_transaction(&self, hash: GlobalH256, fee_delta: i64);
+	fn estimate_priority(&self, target: u32) -> f64;
+	fn generate(&self, blocks: u32) -> Result<Vec<GlobalH256>, String>;
 }
 
 pub struct MinerClientCore {
 	local_sync_node: sync::LocalNodeRef,
 	miner_address: Option<Address>,
+	coinbase_tag: Bytes,
 }
 
 impl MinerClientCore {
-	pub fn new(local_sync_node: sync::LocalNodeRef, miner_address: Option<Address>) -> Self {
+	pub fn new(local_sync_node: sync::LocalNodeRef, miner_address: Option<Address>, coinbase_tag: Bytes) -> Self {
 		MinerClientCore {
 			local_sync_node: local_sync_node,
 			miner_address: miner_address,
+			coinbase_tag: coinbase_tag,
 		}
 	}
 }
@@ -32,7 +39,21 @@ impl MinerClientCoreApi for MinerClientCore {
 	fn get_block_template(&self) -> Result<miner::BlockTemplate, String> {
 		self.miner_address.as_ref()
 			.ok_or_else(|| "miner address not set".into())
-			.and_then(|miner_address| self.local_sync_node.get_block_template(miner_address))
+			.and_then(|miner_address| self.local_sync_node.get_block_template(miner_address, &self.coinbase_tag))
+	}
+
+	fn prioritise_transaction(&self, hash: GlobalH256, fee_delta: i64) {
+		self.local_sync_node.prioritise_transaction(&hash, fee_delta)
+	}
+
+	fn estimate_priority(&self, target: u32) -> f64 {
+		self.local_sync_node.estimate_priority(target)
+	}
+
+	fn generate(&self, blocks: u32) -> Result<Vec<GlobalH256>, String> {
+		self.miner_address.as_ref()
+			.ok_or_else(|| "miner address not set".into())
+			.and_then(|miner_address| self.local_sync_node.generate(miner_address, &self.coinbase_tag, blocks))
 	}
 }
 
@@ -50,12 +71,28 @@ impl<T> Miner for MinerClient<T> where T: MinerClientCoreApi {
 			.map(Into::into)
 			.map_err(|err| execution(&err))
 	}
+
+	fn prioritise_transaction(&self, hash: H256, fee_delta: i64) -> Result<bool, Error> {
+		self.core.prioritise_transaction(Into::<GlobalH256>::into(hash).reversed(), fee_delta);
+		Ok(true)
+	}
+
+	fn estimate_priority(&self, target: u32) -> Result<f64, Error> {
+		Ok(self.core.estimate_priority(target))
+	}
+
+	fn generate(&self, blocks: u32) -> Result<Vec<H256>, Error> {
+		self.core.generate(blocks)
+			.map(|hashes| hashes.into_iter().map(|hash| hash.reversed().into()).collect())
+			.map_err(|err| execution(&err))
+	}
 }
 
 #[cfg(test)]
 pub mod tests {
 	use jsonrpc_core::IoHandler;
 	use v1::traits::Miner;
+	use std::sync::{Arc, Mutex};
 	use primitives::hash::H256;
 	use chain;
 	use miner;
@@ -82,6 +119,43 @@ pub mod tests {
 				sigop_limit: 88,
 			})
 		}
+
+		fn prioritise_transaction(&self, _hash: H256, _fee_delta: i64) {
+		}
+
+		fn estimate_priority(&self, target: u32) -> f64 {
+			match target {
+				1 => 5_000_000.0,
+				_ => miner::INSUFFICIENT_PRIORITY_DATA,
+			}
+		}
+
+		fn generate(&self, blocks: u32) -> Result<Vec<H256>, String> {
+			Ok((0..blocks).map(|index| H256::from(index as u8 + 1)).collect())
+		}
+	}
+
+	#[derive(Default)]
+	struct PrioritiseMinerClientCore {
+		prioritised: Arc<Mutex<Vec<(H256, i64)>>>,
+	}
+
+	impl MinerClientCoreApi for PrioritiseMinerClientCore {
+		fn get_block_template(&self) -> Result<miner::BlockTemplate, String> {
+			Err("not implemented".into())
+		}
+
+		fn prioritise_transaction(&self, hash: H256, fee_delta: i64) {
+			self.prioritised.lock().unwrap().push((hash, fee_delta));
+		}
+
+		fn estimate_priority(&self, _target: u32) -> f64 {
+			miner::INSUFFICIENT_PRIORITY_DATA
+		}
+
+		fn generate(&self, _blocks: u32) -> Result<Vec<H256>, String> {
+			Err("not implemented".into())
+		}
 	}
 
 	#[test]
@@ -102,4 +176,87 @@ pub mod tests {
 		// but client expects reverse hash
 		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":{"bits":44,"coinbasetxn":{"data":"00000000000000000000","depends":null,"fee":null,"hash":null,"required":false,"sigops":null},"curtime":33,"finalsaplingroothash":"0000000000000000000000000000000000000000000000000000000000000000","height":55,"mintime":null,"mutable":null,"noncerange":null,"previousblockhash":"0000000000000000000000000000000000000000000000000000000000000001","sigoplimit":88,"sizelimit":77,"target":"0000000000000000000000000000000000000000000000000000000000000000","transactions":[{"data":"00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000","depends":null,"fee":null,"hash":null,"required":false,"sigops":null}],"version":777},"id":1}"#);
 	}
+
+	#[test]
+	fn prioritisetransaction_accepted() {
+		let core = PrioritiseMinerClientCore::default();
+		let prioritised = core.prioritised.clone();
+		let client = MinerClient::new(core);
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "prioritisetransaction",
+				"params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", -10000],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+		assert_eq!(*prioritised.lock().unwrap(), vec![
+			(H256::from_reversed_str("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"), -10000),
+		]);
+	}
+
+	#[test]
+	fn estimatepriority_accepted() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "estimatepriority",
+				"params": [1],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":5000000.0,"id":1}"#);
+
+		// not enough data
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "estimatepriority",
+				"params": [2],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":-1.0,"id":1}"#);
+	}
+
+	#[test]
+	fn generate_accepted() {
+		let client = MinerClient::new(SuccessMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "generate",
+				"params": [2],
+				"id": 1
+			}"#)).unwrap();
+
+		// direct hashes are 0100..00 && 0200..00, but client expects reverse hashes
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":["0000000000000000000000000000000000000000000000000000000000000001","0000000000000000000000000000000000000000000000000000000000000002"],"id":1}"#);
+	}
+
+	#[test]
+	fn generate_rejected() {
+		let client = MinerClient::new(PrioritiseMinerClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "generate",
+				"params": [1],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"not implemented\""},"id":1}"#);
+	}
 }
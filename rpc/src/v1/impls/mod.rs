@@ -2,8 +2,12 @@ mod blockchain;
 mod miner;
 mod raw;
 mod network;
+mod util;
+mod sign;
 
 pub use self::blockchain::{BlockChainClient, BlockChainClientCore};
 pub use self::miner::{MinerClient, MinerClientCore};
-pub use self::raw::{RawClient, RawClientCore};
+pub use self::raw::{RawClient, RawClientCore, DEFAULT_TX_EXPIRY_DELTA};
 pub use self::network::{NetworkClient, NetworkClientCore};
+pub use self::util::UtilClient;
+pub use self::sign::SignClient;
@@ -1,10 +1,13 @@
 use std::sync::Arc;
 use std::net::{SocketAddr, IpAddr};
 use v1::traits::Network as NetworkRpc;
-use v1::types::{AddNodeOperation, NodeInfo};
+use v1::types::{AddNodeOperation, NodeInfo, NetTotals, SyncStatus, GetInfoResponse, BlockFailureCategory};
 use jsonrpc_core::Error;
 use v1::helpers::errors;
+use network::ConsensusParams;
+use storage;
 use p2p;
+use sync;
 
 pub trait NetworkApi : Send + Sync + 'static {
 	fn add_node(&self, socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError>;
@@ -13,6 +16,11 @@ pub trait NetworkApi : Send + Sync + 'static {
 	fn node_info(&self, node_addr: IpAddr) -> Result<NodeInfo, p2p::NodeTableError>;
 	fn nodes_info(&self) -> Vec<NodeInfo>;
 	fn connection_count(&self) -> usize;
+	fn set_network_active(&self, active: bool);
+	fn sync_status(&self) -> SyncStatus;
+	fn net_totals(&self) -> p2p::NetTotals;
+	fn info(&self) -> GetInfoResponse;
+	fn reconsider_failed_blocks(&self, category: sync::BlockFailureCategory) -> usize;
 }
 
 impl<T> NetworkRpc for NetworkClient<T> where T: NetworkApi {
@@ -54,6 +62,35 @@ impl<T> NetworkRpc for NetworkClient<T> where T: NetworkApi {
 	fn connection_count(&self) -> Result<usize, Error> {
 		Ok(self.api.connection_count())
 	}
+
+	fn set_network_active(&self, active: bool) -> Result<bool, Error> {
+		self.api.set_network_active(active);
+		Ok(active)
+	}
+
+	fn sync_status(&self) -> Result<SyncStatus, Error> {
+		Ok(self.api.sync_status())
+	}
+
+	fn net_totals(&self) -> Result<NetTotals, Error> {
+		let totals = self.api.net_totals();
+		let now = ::time::get_time();
+		Ok(NetTotals {
+			totalbytesrecv: totals.total_recv,
+			totalbytessent: totals.total_send,
+			timemillis: now.sec as u64 * 1000 + now.nsec as u64 / 1_000_000,
+			bytesrecv_per_msg: totals.recv_by_command.into_iter().map(|(command, bytes)| (command.to_string(), bytes)).collect(),
+			bytessent_per_msg: totals.send_by_command.into_iter().map(|(command, bytes)| (command.to_string(), bytes)).collect(),
+		})
+	}
+
+	fn info(&self) -> Result<GetInfoResponse, Error> {
+		Ok(self.api.info())
+	}
+
+	fn reconsider_failed_blocks(&self, category: BlockFailureCategory) -> Result<usize, Error> {
+		Ok(self.api.reconsider_failed_blocks(category.into()))
+	}
 }
 
 pub struct NetworkClient<T: NetworkApi> {
@@ -70,11 +107,19 @@ impl<T> NetworkClient<T> where T: NetworkApi {
 
 pub struct NetworkClientCore {
 	p2p: Arc<p2p::Context>,
+	local_sync_node: sync::LocalNodeRef,
+	consensus: ConsensusParams,
+	storage: storage::SharedStore,
 }
 
 impl NetworkClientCore {
-	pub fn new(p2p: Arc<p2p::Context>) -> Self {
-		NetworkClientCore { p2p: p2p }
+	pub fn new(p2p: Arc<p2p::Context>, local_sync_node: sync::LocalNodeRef, consensus: ConsensusParams, storage: storage::SharedStore) -> Self {
+		NetworkClientCore {
+			p2p: p2p,
+			local_sync_node: local_sync_node,
+			consensus: consensus,
+			storage: storage,
+		}
 	}
 }
 
@@ -129,4 +174,342 @@ impl NetworkApi for NetworkClientCore {
 	fn connection_count(&self) -> usize {
 		self.p2p.connections().count()
 	}
+
+	fn set_network_active(&self, active: bool) {
+		self.p2p.set_network_active(active)
+	}
+
+	fn sync_status(&self) -> SyncStatus {
+		SyncStatus {
+			synchronizing: self.local_sync_node.is_synchronizing(),
+			blocks: self.local_sync_node.best_block_number(),
+			estimatedheight: self.local_sync_node.estimated_network_height(),
+		}
+	}
+
+	fn net_totals(&self) -> p2p::NetTotals {
+		self.p2p.net_totals()
+	}
+
+	fn info(&self) -> GetInfoResponse {
+		let best_block = self.storage.best_block();
+		let difficulty = self.storage.block_header(best_block.hash.into())
+			.map(|header| header.raw.bits.to_f64(self.consensus.network.max_bits().into()))
+			.unwrap_or_default();
+		// enabled index is being rebuilt until it reaches the best block
+		let chain_index = self.storage.as_chain_index();
+		let reindexing = storage::IndexKind::all().iter()
+			.filter_map(|index| chain_index.index_next_block(*index))
+			.any(|next_block| next_block <= best_block.number);
+
+		GetInfoResponse {
+			version: self.p2p.user_agent().to_owned(),
+			protocolversion: self.p2p.protocol_version(),
+			blocks: self.local_sync_node.best_block_number(),
+			connections: self.p2p.connections().count(),
+			difficulty: difficulty,
+			network: self.consensus.network.name().to_owned(),
+			synchronizing: self.local_sync_node.is_synchronizing(),
+			reindexing: reindexing,
+		}
+	}
+
+	fn reconsider_failed_blocks(&self, category: sync::BlockFailureCategory) -> usize {
+		self.local_sync_node.reconsider_failed_blocks(category)
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	extern crate test_data;
+
+	use std::{env, process};
+	use std::net::{SocketAddr, IpAddr};
+	use std::sync::{Arc, Mutex};
+	use jsonrpc_core::IoHandler;
+	use serde_json;
+	use tokio_core::reactor::Core;
+	use db::BlockChainDatabase;
+	use network::{ConsensusParams, Network as NetworkKind};
+	use storage::ChainIndex;
+	use verification::VerificationLevel;
+	use v1::traits::Network;
+	use v1::types::{NodeInfo, SyncStatus, GetInfoResponse};
+	use p2p;
+	use super::*;
+
+	#[derive(Default)]
+	struct NetworkState {
+		active: bool,
+		connections: Vec<SocketAddr>,
+		totals: p2p::NetTotals,
+		reconsidered: Vec<sync::BlockFailureCategory>,
+	}
+
+	#[derive(Default)]
+	struct TestNetworkClientCore {
+		state: Mutex<NetworkState>,
+	}
+
+	impl TestNetworkClientCore {
+		fn with_connections(connections: Vec<SocketAddr>) -> Self {
+			TestNetworkClientCore {
+				state: Mutex::new(NetworkState {
+					active: true,
+					connections: connections,
+					totals: Default::default(),
+					reconsidered: Vec::new(),
+				}),
+			}
+		}
+	}
+
+	impl NetworkApi for TestNetworkClientCore {
+		fn add_node(&self, _socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError> {
+			Ok(())
+		}
+
+		fn remove_node(&self, _socket_addr: SocketAddr) -> Result<(), p2p::NodeTableError> {
+			Ok(())
+		}
+
+		fn connect(&self, socket_addr: SocketAddr) {
+			let mut state = self.state.lock().unwrap();
+			if state.active {
+				state.connections.push(socket_addr);
+				// simulate version handshake with the mock peer
+				state.totals.report_send("version".into(), 110);
+				state.totals.report_recv("version".into(), 105);
+				state.totals.report_send("verack".into(), 24);
+				state.totals.report_recv("verack".into(), 24);
+			}
+		}
+
+		fn node_info(&self, _node_addr: IpAddr) -> Result<NodeInfo, p2p::NodeTableError> {
+			Err(p2p::NodeTableError::NoAddressInTable)
+		}
+
+		fn nodes_info(&self) -> Vec<NodeInfo> {
+			Vec::new()
+		}
+
+		fn connection_count(&self) -> usize {
+			self.state.lock().unwrap().connections.len()
+		}
+
+		fn set_network_active(&self, active: bool) {
+			let mut state = self.state.lock().unwrap();
+			state.active = active;
+			if !active {
+				state.connections.clear();
+			}
+		}
+
+		fn sync_status(&self) -> SyncStatus {
+			SyncStatus::default()
+		}
+
+		fn net_totals(&self) -> p2p::NetTotals {
+			self.state.lock().unwrap().totals.clone()
+		}
+
+		fn info(&self) -> GetInfoResponse {
+			GetInfoResponse {
+				version: "pzec".into(),
+				protocolversion: 170007,
+				blocks: 419200,
+				connections: self.connection_count(),
+				difficulty: 2.5,
+				network: "main".into(),
+				synchronizing: true,
+				reindexing: false,
+			}
+		}
+
+		fn reconsider_failed_blocks(&self, category: sync::BlockFailureCategory) -> usize {
+			let mut state = self.state.lock().unwrap();
+			state.reconsidered.push(category);
+			state.reconsidered.len()
+		}
+	}
+
+	/// Creates network client core, backed by the real (but not running) p2p context && sync node.
+	fn real_network_client_core(name: &str, storage: storage::SharedStore) -> (Core, NetworkClientCore) {
+		let event_loop = Core::new().unwrap();
+		let consensus = ConsensusParams::new(NetworkKind::Unitest);
+		let sync_peers = sync::create_sync_peers();
+		let verification_params = sync::VerificationParameters {
+			verification_level: VerificationLevel::FULL,
+			verification_edge: 0u8.into(),
+			transaction_time_budget: None,
+			sapling_anchor_window: None,
+			script_verification_pool: None,
+		};
+		let local_sync_node = sync::create_local_sync_node(consensus.clone(), storage.clone(), sync_peers.clone(),
+			verification_params, false, Default::default(), sync::SyncMode::Interleaved);
+		let p2p_config = p2p::Config {
+			threads: 1,
+			inbound_connections: 10,
+			outbound_connections: 10,
+			block_relay_only_connections: 0,
+			protect_by_netgroup: p2p::PROTECTED_BY_NETGROUP,
+			protect_by_last_block: p2p::PROTECTED_BY_LAST_BLOCK,
+			connection: p2p::NetConfig {
+				protocol_version: 170_007,
+				protocol_minimum: 170_002,
+				magic: consensus.magic(),
+				local_address: "127.0.0.1:8233".parse().unwrap(),
+				services: Default::default(),
+				user_agent: "pzec-test".into(),
+				start_height: 0,
+				relay: true,
+				timeouts: Default::default(),
+				max_block_size: consensus.max_block_size(),
+			},
+			peers: Vec::new(),
+			seeds: Vec::new(),
+			node_table_path: env::temp_dir().join(format!("pzec-rpc-{}-{}.csv", name, process::id())),
+			preferable_services: Default::default(),
+			internet_protocol: Default::default(),
+			permissions: Vec::new(),
+		};
+		let sync_connection_factory = sync::create_sync_connection_factory(sync_peers, local_sync_node.clone());
+		let p2p = p2p::P2P::new(p2p_config, sync_connection_factory, event_loop.handle()).unwrap();
+		let core = NetworkClientCore::new(p2p.context().clone(), local_sync_node, consensus, storage);
+		(event_loop, core)
+	}
+
+	fn request(handler: &IoHandler, method: &str, params: &str) -> String {
+		handler.handle_request_sync(&format!(r#"{{"jsonrpc": "2.0", "method": "{}", "params": {}, "id": 1}}"#, method, params)).unwrap()
+	}
+
+	#[test]
+	fn set_network_active_toggles_networking() {
+		let client = NetworkClient::new(TestNetworkClientCore::with_connections(vec![
+			"127.0.0.1:8233".parse().unwrap(),
+			"127.0.0.2:8233".parse().unwrap(),
+		]));
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		assert_eq!(&request(&handler, "getconnectioncount", "[]"), r#"{"jsonrpc":"2.0","result":2,"id":1}"#);
+
+		// all connections are dropped && no new connections are made
+		assert_eq!(&request(&handler, "setnetworkactive", "[false]"), r#"{"jsonrpc":"2.0","result":false,"id":1}"#);
+		assert_eq!(&request(&handler, "getconnectioncount", "[]"), r#"{"jsonrpc":"2.0","result":0,"id":1}"#);
+		assert_eq!(&request(&handler, "addnode", r#"["127.0.0.3:8233", "onetry"]"#), r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+		assert_eq!(&request(&handler, "getconnectioncount", "[]"), r#"{"jsonrpc":"2.0","result":0,"id":1}"#);
+
+		// connections are made again
+		assert_eq!(&request(&handler, "setnetworkactive", "[true]"), r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+		assert_eq!(&request(&handler, "addnode", r#"["127.0.0.3:8233", "onetry"]"#), r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+		assert_eq!(&request(&handler, "getconnectioncount", "[]"), r#"{"jsonrpc":"2.0","result":1,"id":1}"#);
+	}
+
+	#[test]
+	fn set_network_active_switches_p2p_context() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let (_event_loop, core) = real_network_client_core("setnetworkactive", storage);
+		let p2p = core.p2p.clone();
+		let mut handler = IoHandler::new();
+		handler.extend_with(NetworkClient::new(core).to_delegate());
+		assert!(p2p.is_network_active());
+
+		// networking is disabled => no new connections are made
+		assert_eq!(&request(&handler, "setnetworkactive", "[false]"), r#"{"jsonrpc":"2.0","result":false,"id":1}"#);
+		assert!(!p2p.is_network_active());
+		assert_eq!(&request(&handler, "addnode", r#"["127.0.0.3:8233", "onetry"]"#), r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+		assert_eq!(&request(&handler, "getconnectioncount", "[]"), r#"{"jsonrpc":"2.0","result":0,"id":1}"#);
+		assert_eq!(p2p.connections().count(), 0);
+
+		// networking is enabled again
+		assert_eq!(&request(&handler, "setnetworkactive", "[true]"), r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+		assert!(p2p.is_network_active());
+	}
+
+	#[test]
+	fn set_network_active_requires_bool() {
+		let client = NetworkClient::new(TestNetworkClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = request(&handler, "setnetworkactive", r#"["yes"]"#);
+		assert!(sample.contains("error"));
+	}
+
+	#[test]
+	fn net_totals_increase_after_exchanging_messages() {
+		let client = NetworkClient::new(TestNetworkClientCore::with_connections(Vec::new()));
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let totals: serde_json::Value = serde_json::from_str(&request(&handler, "getnettotals", "[]")).unwrap();
+		assert_eq!(totals["result"]["totalbytessent"], 0);
+		assert_eq!(totals["result"]["totalbytesrecv"], 0);
+		assert!(totals["result"]["timemillis"].as_u64().unwrap() > 0);
+
+		assert_eq!(&request(&handler, "addnode", r#"["127.0.0.3:8233", "onetry"]"#), r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+
+		let totals: serde_json::Value = serde_json::from_str(&request(&handler, "getnettotals", "[]")).unwrap();
+		assert_eq!(totals["result"]["totalbytessent"], 134);
+		assert_eq!(totals["result"]["totalbytesrecv"], 129);
+		assert_eq!(totals["result"]["bytessent_per_msg"]["version"], 110);
+		assert_eq!(totals["result"]["bytesrecv_per_msg"]["verack"], 24);
+	}
+
+	#[test]
+	fn getinfo_reflects_node_state() {
+		let client = NetworkClient::new(TestNetworkClientCore::with_connections(vec![
+			"127.0.0.1:8233".parse().unwrap(),
+		]));
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		assert_eq!(&request(&handler, "getinfo", "[]"), r#"{"jsonrpc":"2.0","result":{"version":"pzec","protocolversion":170007,"blocks":419200,"connections":1,"difficulty":2.5,"network":"main","synchronizing":true,"reindexing":false},"id":1}"#);
+
+		// connection count is updated
+		assert_eq!(&request(&handler, "addnode", r#"["127.0.0.2:8233", "onetry"]"#), r#"{"jsonrpc":"2.0","result":null,"id":1}"#);
+		let info: serde_json::Value = serde_json::from_str(&request(&handler, "getinfo", "[]")).unwrap();
+		assert_eq!(info["result"]["connections"], 2);
+	}
+
+	#[test]
+	fn getinfo_reflects_real_node_state() {
+		let genesis = test_data::genesis();
+		let blocks = test_data::build_n_empty_blocks_from(2, 1, &genesis.block_header);
+		let difficulty = blocks[1].block_header.bits.to_f64(NetworkKind::Unitest.max_bits().into());
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.into(), blocks[0].clone().into(), blocks[1].clone().into()]));
+		let (_event_loop, core) = real_network_client_core("getinfo", storage.clone());
+		let mut handler = IoHandler::new();
+		handler.extend_with(NetworkClient::new(core).to_delegate());
+
+		let info: serde_json::Value = serde_json::from_str(&request(&handler, "getinfo", "[]")).unwrap();
+		assert_eq!(info["result"]["version"], "pzec-test");
+		assert_eq!(info["result"]["protocolversion"], 170_007);
+		assert_eq!(info["result"]["blocks"], 2);
+		assert_eq!(info["result"]["connections"], 0);
+		assert_eq!(info["result"]["difficulty"], difficulty);
+		assert_eq!(info["result"]["network"], "unitest");
+		assert_eq!(info["result"]["synchronizing"], false);
+		assert_eq!(info["result"]["reindexing"], false);
+
+		// address index is enabled, but not yet rebuilt
+		storage.enable_index(storage::IndexKind::Address).unwrap();
+		let info: serde_json::Value = serde_json::from_str(&request(&handler, "getinfo", "[]")).unwrap();
+		assert_eq!(info["result"]["reindexing"], true);
+
+		storage::rescan_index(&*storage, storage::IndexKind::Address, 2, |_| ()).unwrap();
+		let info: serde_json::Value = serde_json::from_str(&request(&handler, "getinfo", "[]")).unwrap();
+		assert_eq!(info["result"]["reindexing"], false);
+	}
+
+	#[test]
+	fn reconsider_failed_blocks_parses_category() {
+		let client = NetworkClient::new(TestNetworkClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		assert_eq!(&request(&handler, "reconsiderfailedblocks", r#"["scripts"]"#), r#"{"jsonrpc":"2.0","result":1,"id":1}"#);
+		assert!(request(&handler, "reconsiderfailedblocks", r#"["consensus"]"#).contains("error"));
+	}
 }
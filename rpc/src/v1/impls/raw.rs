@@ -1,20 +1,23 @@
 use jsonrpc_core::Error;
 use ser::{Reader, serialize, deserialize};
 use v1::traits::Raw;
-use v1::types::{RawTransaction, TransactionInput, TransactionOutput, TransactionOutputs, Transaction, GetRawTransactionResponse};
+use v1::types::{RawTransaction, TransactionInput, TransactionOutput, TransactionOutputs, Transaction, GetRawTransactionResponse,
+	TestMempoolAcceptResponse};
 use v1::types::H256;
-use v1::helpers::errors::{execution, invalid_params};
+use v1::helpers::errors::{execution, invalid_params, block_not_found, transaction_not_found};
 use chain::{
 	SAPLING_TX_VERSION, SAPLING_TX_VERSION_GROUP_ID,
 	Transaction as GlobalTransaction, IndexedTransaction as GlobalIndexedTransaction,
 };
 use primitives::bytes::Bytes as GlobalBytes;
 use primitives::hash::H256 as GlobalH256;
+use network::Network;
+use storage;
 use sync;
 
 /// Default expiry height delta (best blocks number + height in blocks) for transactions
-/// created by `createrawtransaction` RPC.
-const DEFAULT_TX_EXPIRY_DELTA: u32 = 20;
+/// created by `createrawtransaction` RPC. Delta of 0 disables expiry.
+pub const DEFAULT_TX_EXPIRY_DELTA: u32 = 20;
 
 pub struct RawClient<T: RawClientCoreApi> {
 	core: T,
@@ -22,6 +25,7 @@ pub struct RawClient<T: RawClientCoreApi> {
 
 pub trait RawClientCoreApi: Send + Sync + 'static {
 	fn accept_transaction(&self, transaction: GlobalTransaction) -> Result<GlobalH256, String>;
+	fn test_transactions(&self, transactions: Vec<GlobalTransaction>) -> Vec<Result<GlobalH256, String>>;
 	fn create_raw_transaction(
 		&self,
 		inputs: Vec<TransactionInput>,
@@ -29,21 +33,91 @@ pub trait RawClientCoreApi: Send + Sync + 'static {
 		lock_time: Option<u32>,
 		expiry_height: Option<u32>,
 	) -> Result<GlobalTransaction, String>;
+	fn transaction(&self, hash: GlobalH256, block_hash: Option<GlobalH256>) -> Result<GlobalTransaction, Error>;
+	fn decode_transaction(&self, transaction: GlobalTransaction) -> Transaction;
+	fn verbose_transaction(&self, hash: GlobalH256, block_hash: Option<GlobalH256>) -> Result<Transaction, Error>;
 }
 
 pub struct RawClientCore {
+	network: keys::Network,
 	local_sync_node: sync::LocalNodeRef,
+	storage: storage::SharedStore,
+	tx_expiry_delta: u32,
 }
 
 impl RawClientCore {
-	pub fn new(local_sync_node: sync::LocalNodeRef) -> Self {
+	pub fn new(network: Network, local_sync_node: sync::LocalNodeRef, storage: storage::SharedStore, tx_expiry_delta: u32) -> Self {
 		RawClientCore {
+			network: match network {
+				Network::Mainnet => keys::Network::Mainnet,
+				// there's no correct choices for Regtests && Other networks
+				// => let's just use Testnet addresses
+				_ => keys::Network::Testnet,
+			},
 			local_sync_node: local_sync_node,
+			storage: storage,
+			tx_expiry_delta: tx_expiry_delta,
 		}
 	}
 
+	/// Finds transaction in the storage. When block hash is provided, transaction is only
+	/// searched in this block => transactions index is not used.
+	pub fn do_transaction(
+		storage: &storage::Store,
+		hash: GlobalH256,
+		block_hash: Option<GlobalH256>,
+	) -> Result<GlobalTransaction, Error> {
+		match block_hash {
+			Some(block_hash) => {
+				let block = storage.block(block_hash.clone().into())
+					.ok_or_else(|| block_not_found(block_hash.reversed()))?;
+				block.into_transactions().into_iter()
+					.find(|transaction| transaction.hash == hash)
+					.map(|transaction| transaction.raw)
+					.ok_or_else(|| transaction_not_found(format!(
+						"transaction {} is not found in block {}",
+						hash.to_reversed_str(),
+						block_hash.to_reversed_str(),
+					)))
+			},
+			None => storage.transaction(&hash)
+				.map(|transaction| transaction.raw)
+				.ok_or_else(|| transaction_not_found(hash.reversed())),
+		}
+	}
+
+	/// Finds transaction in the storage and fills block-related fields of verbose transaction.
+	/// Transactions of blocks that are not in the main chain have zero confirmations.
+	pub fn do_verbose_transaction(
+		storage: &storage::Store,
+		network: keys::Network,
+		hash: GlobalH256,
+		block_hash: Option<GlobalH256>,
+	) -> Result<Transaction, Error> {
+		let transaction = RawClientCore::do_transaction(storage, hash.clone(), block_hash.clone())?;
+		let mut verbose_transaction = Transaction::from_raw(transaction, network);
+
+		let block_hash = block_hash.or_else(|| storage.transaction_block_hash(&hash));
+		let block_header = block_hash.and_then(|block_hash| storage.block_header(block_hash.into()));
+		if let Some(block_header) = block_header {
+			let best_block = storage.best_block();
+			let confirmations = match storage.block_number(&block_header.hash) {
+				Some(block_number) if block_number <= best_block.number => best_block.number - block_number + 1,
+				_ => 0,
+			};
+
+			verbose_transaction.blockhash = Some(block_header.hash.reversed().into());
+			verbose_transaction.confirmations = Some(confirmations);
+			verbose_transaction.time = Some(block_header.raw.time);
+			verbose_transaction.blocktime = Some(block_header.raw.time);
+		}
+
+		Ok(verbose_transaction)
+	}
+
 	pub fn do_create_raw_transaction(
 		best_block_number: u32,
+		tx_expiry_delta: u32,
 		inputs: Vec<TransactionInput>,
 		outputs: TransactionOutputs,
 		lock_time: Option<u32>,
@@ -59,9 +133,16 @@ impl RawClientCore {
 		let lock_time = lock_time.unwrap_or_default();
 		let default_sequence = if lock_time != 0 { chain::constants::SEQUENCE_FINAL - 1 } else { chain::constants::SEQUENCE_FINAL };
 
-		// by default we're creating transactions that are expired in DEFAULT_TX_EXPIRY_DELTA blocks
-		let expiry_height = expiry_height
-			.unwrap_or_else(|| best_block_number + DEFAULT_TX_EXPIRY_DELTA);
+		// by default we're creating transactions that are expired in tx_expiry_delta blocks
+		let expiry_height = match (expiry_height, tx_expiry_delta) {
+			(Some(expiry_height), _) => expiry_height,
+			(None, 0) => 0,
+			(None, delta) => best_block_number.checked_add(delta)
+				.ok_or_else(|| "Expiry height is out of range".to_owned())?,
+		};
+		if expiry_height >= chain::constants::TX_EXPIRY_HEIGHT_THRESHOLD {
+			return Err(format!("Expiry height must be less than {}", chain::constants::TX_EXPIRY_HEIGHT_THRESHOLD));
+		}
 
 		// prepare inputs
 		let inputs: Vec<_> = inputs.into_iter()
@@ -122,6 +203,10 @@ impl RawClientCoreApi for RawClientCore {
 		self.local_sync_node.accept_transaction(GlobalIndexedTransaction::from_raw(transaction))
 	}
 
+	fn test_transactions(&self, transactions: Vec<GlobalTransaction>) -> Vec<Result<GlobalH256, String>> {
+		self.local_sync_node.test_transactions(transactions.into_iter().map(GlobalIndexedTransaction::from_raw).collect())
+	}
+
 	fn create_raw_transaction(
 		&self,
 		inputs: Vec<TransactionInput>,
@@ -131,12 +216,25 @@ impl RawClientCoreApi for RawClientCore {
 	) -> Result<GlobalTransaction, String> {
 		RawClientCore::do_create_raw_transaction(
 			self.local_sync_node.best_block_number(),
+			self.tx_expiry_delta,
 			inputs,
 			outputs,
 			lock_time,
 			expiry_height,
 		)
 	}
+
+	fn transaction(&self, hash: GlobalH256, block_hash: Option<GlobalH256>) -> Result<GlobalTransaction, Error> {
+		RawClientCore::do_transaction(self.storage.as_store(), hash, block_hash)
+	}
+
+	fn decode_transaction(&self, transaction: GlobalTransaction) -> Transaction {
+		Transaction::from_raw(transaction, self.network)
+	}
+
+	fn verbose_transaction(&self, hash: GlobalH256, block_hash: Option<GlobalH256>) -> Result<Transaction, Error> {
+		RawClientCore::do_verbose_transaction(self.storage.as_store(), self.network, hash, block_hash)
+	}
 }
 
 impl<T> RawClient<T> where T: RawClientCoreApi {
@@ -156,6 +254,27 @@ impl<T> Raw for RawClient<T> where T: RawClientCoreApi {
 			.map_err(|e| execution(e))
 	}
 
+	fn test_mempool_accept(&self, raw_transactions: Vec<RawTransaction>) -> Result<Vec<TestMempoolAcceptResponse>, Error> {
+		let transactions: Vec<GlobalTransaction> = raw_transactions.into_iter()
+			.map(|raw_transaction| {
+				let raw_transaction_data: Vec<u8> = raw_transaction.into();
+				deserialize(Reader::new(&raw_transaction_data)).map_err(|e| invalid_params("tx", e))
+			})
+			.collect::<Result<_, _>>()?;
+		let hashes: Vec<GlobalH256> = transactions.iter()
+			.map(|transaction| GlobalIndexedTransaction::from_raw(transaction.clone()).hash)
+			.collect();
+
+		Ok(hashes.into_iter()
+			.zip(self.core.test_transactions(transactions))
+			.map(|(hash, result)| TestMempoolAcceptResponse {
+				txid: hash.reversed().into(),
+				allowed: result.is_ok(),
+				reject_reason: result.err(),
+			})
+			.collect())
+	}
+
 	fn create_raw_transaction(
 		&self,
 		inputs: Vec<TransactionInput>,
@@ -176,22 +295,38 @@ impl<T> Raw for RawClient<T> where T: RawClientCoreApi {
 		Ok(transaction.into())
 	}
 
-	fn decode_raw_transaction(&self, _transaction: RawTransaction) -> Result<Transaction, Error> {
-		rpc_unimplemented!()
+	fn decode_raw_transaction(&self, raw_transaction: RawTransaction) -> Result<Transaction, Error> {
+		let raw_transaction_data: Vec<u8> = raw_transaction.into();
+		let transaction = try!(deserialize(Reader::new(&raw_transaction_data)).map_err(|e| invalid_params("tx", e)));
+		Ok(self.core.decode_transaction(transaction))
 	}
 
-	fn get_raw_transaction(&self, _hash: H256, _verbose: Option<bool>) -> Result<GetRawTransactionResponse, Error> {
-		rpc_unimplemented!()
+	fn get_raw_transaction(&self, hash: H256, verbose: Option<bool>, block_hash: Option<H256>) -> Result<GetRawTransactionResponse, Error> {
+		let hash: GlobalH256 = hash.into();
+		let block_hash = block_hash.map(|block_hash| Into::<GlobalH256>::into(block_hash).reversed());
+		if verbose.unwrap_or_default() {
+			return self.core.verbose_transaction(hash.reversed(), block_hash)
+				.map(GetRawTransactionResponse::Verbose);
+		}
+
+		let transaction = self.core.transaction(hash.reversed(), block_hash)?;
+		Ok(GetRawTransactionResponse::Raw(serialize(&transaction).into()))
 	}
 }
 
 #[cfg(test)]
 pub mod tests {
+	extern crate test_data;
+
+	use std::sync::Arc;
 	use jsonrpc_core::IoHandler;
+	use serde_json;
 	use chain::Transaction;
+	use db::BlockChainDatabase;
 	use primitives::hash::H256 as GlobalH256;
 	use v1::traits::Raw;
 	use v1::types::{TransactionInput, TransactionOutputs};
+	use v1::types::Transaction as VerboseTransaction;
 	use super::*;
 
 	#[derive(Default)]
@@ -204,6 +339,20 @@ pub mod tests {
 			Ok(transaction.hash())
 		}
 
+		fn test_transactions(&self, transactions: Vec<Transaction>) -> Vec<Result<GlobalH256, String>> {
+			// every transaction, spending already spent output, is rejected
+			let mut spent = Vec::new();
+			transactions.into_iter()
+				.map(|transaction| {
+					if transaction.inputs.iter().any(|input| spent.contains(&input.previous_output)) {
+						return Err("UsingSpentOutput".to_owned());
+					}
+					spent.extend(transaction.inputs.iter().map(|input| input.previous_output.clone()));
+					Ok(transaction.hash())
+				})
+				.collect()
+		}
+
 		fn create_raw_transaction(
 			&self,
 			_inputs: Vec<TransactionInput>,
@@ -213,6 +362,16 @@ pub mod tests {
 		) -> Result<Transaction, String> {
 			Ok("0100000001ad9d38823d95f31dc6c0cb0724c11a3cf5a466ca4147254a10cd94aade6eb5b3230000006b483045022100b7683165c3ecd57b0c44bf6a0fb258dc08c328458321c8fadc2b9348d4e66bd502204fd164c58d1a949a4d39bb380f8f05c9f6b3e9417f06bf72e5c068428ca3578601210391c35ac5ee7cf82c5015229dcff89507f83f9b8c952b8fecfa469066c1cb44ccffffffff0170f30500000000001976a914801da3cb2ed9e44540f4b982bde07cd3fbae264288ac00000000".into())
 		}
+		fn transaction(&self, _hash: GlobalH256, _block_hash: Option<GlobalH256>) -> Result<Transaction, Error> {
+			Ok("00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000".into())
+		}
+		fn decode_transaction(&self, transaction: Transaction) -> VerboseTransaction {
+			VerboseTransaction::from_raw(transaction, keys::Network::Mainnet)
+		}
+		fn verbose_transaction(&self, hash: GlobalH256, block_hash: Option<GlobalH256>) -> Result<VerboseTransaction, Error> {
+			let transaction = self.transaction(hash, block_hash)?;
+			Ok(self.decode_transaction(transaction))
+		}
 	}
 
 	impl RawClientCoreApi for ErrorRawClientCore {
@@ -220,6 +379,10 @@ pub mod tests {
 			Err("error".to_owned())
 		}
 
+		fn test_transactions(&self, transactions: Vec<Transaction>) -> Vec<Result<GlobalH256, String>> {
+			transactions.into_iter().map(|_| Err("error".to_owned())).collect()
+		}
+
 		fn create_raw_transaction(
 			&self,
 			_inputs: Vec<TransactionInput>,
@@ -229,6 +392,15 @@ pub mod tests {
 		) -> Result<Transaction, String> {
 			Err("error".to_owned())
 		}
+		fn transaction(&self, hash: GlobalH256, _block_hash: Option<GlobalH256>) -> Result<Transaction, Error> {
+			Err(transaction_not_found(hash))
+		}
+		fn decode_transaction(&self, transaction: Transaction) -> VerboseTransaction {
+			VerboseTransaction::from_raw(transaction, keys::Network::Mainnet)
+		}
+		fn verbose_transaction(&self, hash: GlobalH256, _block_hash: Option<GlobalH256>) -> Result<VerboseTransaction, Error> {
+			Err(transaction_not_found(hash))
+		}
 	}
 
 	#[test]
@@ -269,6 +441,64 @@ pub mod tests {
 		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"error\""},"id":1}"#, &sample);
 	}
 
+	#[test]
+	fn testmempoolaccept_allowed() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "testmempoolaccept",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":[{"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","allowed":true}],"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn testmempoolaccept_double_spend_rejected() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		// the same transaction twice => the second one spends the same output
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "testmempoolaccept",
+				"params": [[
+					"00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000",
+					"00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"
+				]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":[{"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","allowed":true},{"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","allowed":false,"reject-reason":"UsingSpentOutput"}],"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn testmempoolaccept_rejected() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "testmempoolaccept",
+				"params": [["00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000"]],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":[{"txid":"34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107","allowed":false,"reject-reason":"error"}],"id":1}"#, &sample);
+	}
+
 	#[test]
 	fn createrawtransaction_success() {
 		let client = RawClient::new(SuccessRawClientCore::default());
@@ -322,4 +552,249 @@ pub mod tests {
 
 		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32015,"message":"Execution error.","data":"\"error\""},"id":1}"#, &sample);
 	}
+
+	fn create_transaction(best_block_number: u32, tx_expiry_delta: u32, expiry_height: Option<u32>) -> Result<Transaction, String> {
+		let inputs = vec![TransactionInput {
+			txid: H256::from(1),
+			vout: 0,
+			sequence: None,
+		}];
+		let outputs = TransactionOutputs { outputs: Vec::new() };
+		RawClientCore::do_create_raw_transaction(best_block_number, tx_expiry_delta, inputs, outputs, None, expiry_height)
+	}
+
+	#[test]
+	fn created_transaction_expires_after_expiry_delta() {
+		assert_eq!(create_transaction(1000, DEFAULT_TX_EXPIRY_DELTA, None).unwrap().expiry_height, 1020);
+		assert_eq!(create_transaction(1000, 5, None).unwrap().expiry_height, 1005);
+	}
+
+	#[test]
+	fn created_transaction_never_expires_with_zero_expiry_delta() {
+		assert_eq!(create_transaction(1000, 0, None).unwrap().expiry_height, 0);
+	}
+
+	#[test]
+	fn created_transaction_uses_explicit_expiry_height() {
+		assert_eq!(create_transaction(1000, 5, Some(1500)).unwrap().expiry_height, 1500);
+		assert_eq!(create_transaction(1000, 0, Some(1500)).unwrap().expiry_height, 1500);
+	}
+
+	#[test]
+	fn created_transaction_expiry_height_is_below_threshold() {
+		let threshold = chain::constants::TX_EXPIRY_HEIGHT_THRESHOLD;
+		assert_eq!(create_transaction(threshold - 21, DEFAULT_TX_EXPIRY_DELTA, None).unwrap().expiry_height, threshold - 1);
+		assert!(create_transaction(threshold - 20, DEFAULT_TX_EXPIRY_DELTA, None).is_err());
+		assert!(create_transaction(1000, 0, Some(threshold)).is_err());
+		assert!(create_transaction(::std::u32::MAX, DEFAULT_TX_EXPIRY_DELTA, None).is_err());
+	}
+
+	#[test]
+	fn getrawtransaction_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getrawtransaction",
+				"params": ["34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107", false, "0007bc227e1c57a4a70e237cad00e7b7ce565155ab49166bc57397a26d339283"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","result":"00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000","id":1}"#, &sample);
+	}
+
+	#[test]
+	fn getrawtransaction_verbose_success() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getrawtransaction",
+				"params": ["34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107", true],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		let sample: serde_json::Value = serde_json::from_str(&sample).unwrap();
+		assert_eq!(sample["result"]["hex"], "00000000013ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a0000000000000000000101000000000000000000000000");
+		assert_eq!(sample["result"]["txid"], "34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107");
+		assert_eq!(sample["result"]["vin"][0]["txid"], "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b");
+	}
+
+	#[test]
+	fn decoderawtransaction_transparent_transaction() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "decoderawtransaction",
+				"params": ["0100000001ad9d38823d95f31dc6c0cb0724c11a3cf5a466ca4147254a10cd94aade6eb5b3230000006b483045022100b7683165c3ecd57b0c44bf6a0fb258dc08c328458321c8fadc2b9348d4e66bd502204fd164c58d1a949a4d39bb380f8f05c9f6b3e9417f06bf72e5c068428ca3578601210391c35ac5ee7cf82c5015229dcff89507f83f9b8c952b8fecfa469066c1cb44ccffffffff0170f30500000000001976a914801da3cb2ed9e44540f4b982bde07cd3fbae264288ac00000000"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		let sample: serde_json::Value = serde_json::from_str(&sample).unwrap();
+		let transaction = &sample["result"];
+		assert_eq!(transaction["txid"], "4dbbc65cf8eff9a04752bf493232e0b82488308f72f2afb497f36bbddada500c");
+		assert_eq!(transaction["size"], 192);
+		assert_eq!(transaction["overwintered"], false);
+		assert_eq!(transaction["version"], 1);
+		assert!(transaction.get("versiongroupid").is_none());
+		assert!(transaction.get("blockhash").is_none());
+		assert_eq!(transaction["vin"][0]["txid"], "b3b56edeaa94cd104a254741ca66a4f53c1ac12407cbc0c61df3953d82389dad");
+		assert_eq!(transaction["vin"][0]["vout"], 35);
+		assert_eq!(transaction["vin"][0]["sequence"], 0xffffffffu32);
+		assert_eq!(transaction["vout"][0]["value"], 0.0039);
+		assert_eq!(transaction["vout"][0]["n"], 0);
+		assert_eq!(transaction["vout"][0]["scriptPubKey"]["hex"], "76a914801da3cb2ed9e44540f4b982bde07cd3fbae264288ac");
+		assert_eq!(transaction["vout"][0]["scriptPubKey"]["type"], "pubkeyhash");
+		assert_eq!(transaction["vout"][0]["scriptPubKey"]["reqSigs"], 1);
+		assert_eq!(transaction["vout"][0]["scriptPubKey"]["addresses"][0], "t1VZ21JPVLBgPosECLK4btNBuYGknxMYXB1");
+		assert_eq!(transaction["vjoinsplit"].as_array().unwrap().len(), 0);
+		assert_eq!(transaction["vShieldedSpend"].as_array().unwrap().len(), 0);
+	}
+
+	#[test]
+	fn decoderawtransaction_sapling_transaction() {
+		// real Sapling transaction from Zcash mainnet (the same as in chain::transaction tests)
+		let raw: Transaction = "0400008085202f8900000000000072da060010270000000000000148b1c0668fce604361fbb1b89bbd76f8fee09b51a9dc0fdfcf6c6720cd596083d970234fcc0e9a70fdfed82d32fbb9ca92c9c5c3bad5daad9ac62b5bf4255817ee5bc95a9af453bb9cc7e2c544aa29efa20011a65b624998369c849aa8f0bc83d60e7902a3cfe6eeaeb8d583a491de5982c5ded29e64cd8f8fac594a5bb4f2838e6c30876e36a18d8d935238815c8d9205a4f1f523ff76b51f614bff1064d1c5fa0a27ec0c43c8a6c2714e7234d32e9a8934a3e9c0f74f1fdac2ddf6be3b13bc933b0478cae556a2d387cc23b05e8b0bd53d9e838ad2d2cb31daccefe256087511b044dfae665f0af0fa968edeea4cbb437a8099724159471adf7946eec434cccc1129f4d1e31d7f3f8be524226c65f28897d3604c14efb64bea6a889b2705617432927229dfa382e78c0ace31cc158fbf3ec1597242955e45af1ee5cfaffd789cc80dc53d6b18d42033ec2c327170e2811fe8ec00feadeb1033eb48ab24a6dce2480ad428be57c4619466fc3181ece69b914fed30566ff853250ef19ef7370601f4c24b0125e4059eec61f63ccbe277363172f2bdee384412ea073c5aca06b94e402ba3a43e15bd9c65bbfb194c561c24a031dec43be95c59eb6b568c176b1038d5b7b057dc032488335284adebfb6607e6a995b7fa418f13c8a61b343e5df44faa1050d9d76550748d9efebe01da97ade5937afd5f007ed26e0af03f283611655e91bc6a4857f66a57a1584ff687c4baf725f4a1b32fae53a3e6e8b98bca319bb1badb704c9c1a04f401f33d813d605eef6943c2c52dbc85ab7081d1f8f69d3202aae281bf42336a949a12a7dbbd22abdd6e92996282ebd69033c22cb0539d97f83636d6a8232209a7411e8b03bef180d83e608563ea2d0becff56dc996c2049df054961bfb21b7cbef5049a7dacc18f2c977aa1b2d48291abc19c3c8ea25d2e61901048354b17ce952f6f2248cf3a0eb54c19b507b41d7281c3d227e2b142ff695d8b925a4bb942ed9492a73a17468a8332a367fd16295420bdca6c04d380271f40440709998fce3a3af3e1e505f5402e5dd464dd179cb0eede3d494a95b84d2fb2eb5abb425cf2c712af999c65259c4782a5ec97388324c67738908a5ba43b6db62a10f50cddf9b5039123437c74165921ac8cf4f13292a216baef9d00bd544106b52755986c98a462ade1149f69367e926d88eb92798c0e56cd19a1bcf264fd93293033b758da65c7901eb5b4a17ee265a3312dbc477868da0057e1b3cbf47726dead6ecfcc8e1044c6f311ff0fc83192dc2f75a89626ba33364dac747b63ff3c8337e00332c8783ba9c8dc13cdf0750d7adc3926fbe1279017d50adba35c38c5b810f73abe5d759cd7fb650f6b0a1f78dc1f62fd017090ff4de4cf54c883752ddda68083d4617ed2c38bab8da313965dd3f7b755aec23a2d9e2965d08d2134827a72ffb3bd65b1fd5410da105bfba7a74ddff0928a654aca1ee211ac9dce8019ddcbb52263ce44b2544a314355c1e8c8543f3ed3e883e7a7a8f9e3c7c11f41ab9069854fb21e9b3660a860df19d289d54b29d82522b32d187cde6261eb0a429c3994dff6f37b9ab9102281223e3cd584790a909e05ba0ea1a2d9aef8e571986e98e09312dccaf8e739d718a1edd217dc4c8a5c8a650015405b592a7c674a451d7d1686c7ea6d93e74a8fe4ade12b679ac780457f08a79bfbf96dcf7eefe9a39b99f1ae39d2c5f86aadf156b7d5ce4b2733f307cfe1e1ff6de0ff2006d9cba535b0c40dfb7a98399cdff8e681fc38c7b9aa94ee5eb89432e28d94ee27f238776ba964a87caf58eddbb64771e64de094305a8eb848d2d9ad6373903687d22170f48f1ae8d714514034ee2733857af4747312bb006e6ce3918ede8c730bacc7821b81c1b93bb50b219e79e8e0d74531ed18c1145632d9847d38783b49141ac5353aaa7d125fb2934e681467e16b28090978e74e0b".into();
+		let transaction = SuccessRawClientCore::default().decode_transaction(raw);
+
+		assert_eq!(transaction.overwintered, true);
+		assert_eq!(transaction.version, 4);
+		assert_eq!(transaction.versiongroupid, Some(0x892f2085));
+		assert_eq!(transaction.value_balance, Some(0.0001));
+		assert_eq!(transaction.shielded_spends.len(), 1);
+		assert_eq!(transaction.shielded_spends[0].cv, H256::from("48b1c0668fce604361fbb1b89bbd76f8fee09b51a9dc0fdfcf6c6720cd596083"));
+		assert_eq!(transaction.shielded_spends[0].nullifier, H256::from("ee5bc95a9af453bb9cc7e2c544aa29efa20011a65b624998369c849aa8f0bc83"));
+		assert_eq!(transaction.shielded_outputs.len(), 1);
+		assert_eq!(transaction.shielded_outputs[0].cmu, H256::from("402ba3a43e15bd9c65bbfb194c561c24a031dec43be95c59eb6b568c176b1038"));
+		assert!(transaction.binding_sig.is_some());
+		assert!(transaction.vjoinsplit.is_empty());
+	}
+
+	#[test]
+	fn decoderawtransaction_sprout_transaction() {
+		// real Sprout transaction from Zcash mainnet (the same as in chain::transaction tests)
+		let raw: Transaction = "02000000010a141a3f21ed57fa8449ceac0b11909f1b5560f06b772753ca008d49675d45310000000048473044022041aaea8391c0182bf71bd974662e99534d99849b167062f7e8372c4f1a16c2d50220291b2ca6ae7616cd1f1bfddcda5ef2f53d78c2e153d3a8db571885f9adb5f05401ffffffff0000000000011070d900000000000000000000000000d7c612c817793191a1e68652121876d6b3bde40f4fa52bc314145ce6e5cdd2597ae7c48e86173b231e84fbdcb4d8f569f28f71ebf0f9b5867f9d4c12e031a2acc0108235936d2fa2d2c968654fbea2a89fde8522ec7c227d2ff3c10bff9c1197d8a290cca91f23792df8e56aed6c142eaa322e66360b5c49132b940689fb2bc5e77f7877bba6d2c4425d9861515cbe8a5c87dfd7cf159e9d4ac9ff63c096fbcd91d2a459877b1ed40748e2f020cdc678cf576a62c63138d820aba3df4074014bb1624b703774e138c706ba394698fd33c58424bb1a8d22be0d7bc8fe58d369e89836fe673c246d8d0cb1d7e1cc94acfa5b8d76010db8d53a36a3f0e33f0ccbc0f861b5e3d0a92e1c05c6bca775ba7389f6444f0e6cbd34141953220718594664022cbbb59465c880f50d42d0d49d6422197b5f823c2b3ffdb341869b98ed2eb2fd031b271702bda61ff885788363a7cf980a134c09a24c9911dc94cbe970bd613b700b0891fe8b8b05d9d2e7e51df9d6959bdf0a3f2310164afb197a229486a0e8e3808d76c75662b568839ebac7fbf740db9d576523282e6cdd1adf8b0f9c183ae95b0301fa1146d35af869cc47c51cfd827b7efceeca3c55884f54a68e38ee7682b5d102131b9b1198ed371e7e3da9f5a8b9ad394ab5a29f67a1d9b6ca1b8449862c69a5022e5d671e6989d33c182e0a6bbbe4a9da491dbd93ca3c01490c8f74a780479c7c031fb473670cacde779713dcd8cbdad802b8d418e007335919837becf46a3b1d0e02120af9d926bed2b28ed8a2b8307b3da2a171b3ee1bc1e6196773b570407df6b43b51b52c43f834ee0854577cd3a57f8fc23b02a3845cc1f0f42410f363d862e436bf06dbc5f94eddd3b83cdf47cf0acbd7750dff5cba86ea6f1f46a5013e0dc76715d7230e44a038a527cb9033f3eeaeac661264dc6a384788a7cd8aed59589bca6205fe1bd683fa392e7a3c6cc364bba36ad75ee9babf90f7b94071953df95effc0b1c3f542913ed1eb68e15534f9ceb7777c946edf55f129df128c3f767d8d60c4aa0c5e61d00f8e495e78334e2a9feddd9302e9880cb6174d201c89a1d6bc6e83a80cbf80ab3959dcc6cdd12e3d2f6f14d226e6948954f05544941d16ed1d498532722fa39bb985c3224915dd42d70be61217fdcb4aa023251af38b5576ff9eb865a471f2cb2dbc674e401d18014e6119464768778ddcd00907f20279bdecda3880fbbb4d00bb6c5aa3e06113a2f12fcc298f34ccb6bc2c2887b0b064f3bc2e2b507d31e022e65800dd7d30f25266914646bfc07c1eafbbf1e1163c439774b47e8e844799bc8fd06db050f97f5c74ca833e81bcdcf9d864be5746f965ef41838a3535666df867ef79e07068dc7ef809fb0e08e1629bab3215fe36d0f0e0f8c6bb319f93a0f408ff4abbd88c21afaec2e7720674eaceb27efb9144f619bad6f033cbefcebfbe66cabe8286f2ff97b91f4aeef5cbd99a9b862cb904dc085d96238caaad259280ff35caa211e00324f51ff03b6a1cd159cd501faef780ef7f25a98cdcd05ef67596d58d4aea1f9f3e95aae44fd4d4ea679c5e393d4670fb35bf12d036ea731bdfad297303239251a91f9a900e06987eb8e9f5bb1fb847f5ae47e6724ddeb5a3ac01b706a02e494c5547ce338302b4906cf2c91d59a87324322763a12e13a512ace3afb897510ad9ec95aa14ca568a9962da64e5bc7fd15b3e103ab461ee7db3fc9da0a523fc403c11254cd567ca48c8dac5e5b54953e5c754e31def90fff6c56d589a5c4b9a710ccb43cd24988b2fb9336b5508aa553cfdbd1f32dfb4ff16eae066b5fb244bc9058a91898c4ae893eaf0006dae1185c7f553e6e09d12a0a2a9c181c5e4d87c8895b74b0e23a8dc87faf5d6acd5e98cb1df5585f026ae94b77db0e95c5fe22692bd2e70e8e87d07d92b98cdfcc5367e52014163a6e4511d482816259215ee7df246e493523ee51617c318e1a9825f82e73e640fbc2d25c12ce5a07875d489db6a111afdc87061047077030d32de45cd4e575c02a60c4048560bd02cf9203426f589f429b413390ace832b3ddd3dd371750d94f9c34f60a0f1b621b445525d2190a185feaab9e56a079c46236161559713d585a07e94f2316a92fffa7838f1aea39d7846638d16f9b4d1a7dc053e0ddc6620f30e3e798eba900fd25c10c5d6672c9ed7d4d2fa80c0f0137ff24933c37fcd91b19bc7cdd828f7f3f1df0e45cafca795d847e83bca8baa321006581b024306e24c4c2294c0f41b932c1e9f7602f377e8484c7eeb184fab1f747b1dff5b6e2e89f1e5c4232b5a0a41ed6a3775f8942217078b7e035747891cabd2099bfcbf6a8d4680f51265d9e7d05794514f02470e0eb003ad1222cd4fe8bcd077310c5aff274b19608c31f77453d01c9aa9c21a8d9b71de44386aee2145648f7ead471cabed297b8610bba370baa42603f21f5f4640e5bc1a0402d40394e176a0db8cedb33a9d84c48b58d3851617046511946a3700aabe8f69cdb0469ee67776480be090cad2c7adc0bf59551ef6f1ac3119e5c29ab3b82dd945dab00dc4a91d3826c4e488047a4f3ab2d57c0abe1ee7aba304784e7ad211c32c4058fca7b1db2e282132e5ccafe79fc51ab37334f03715f4ad8735b6e03f01".into();
+		let transaction = SuccessRawClientCore::default().decode_transaction(raw);
+
+		assert_eq!(transaction.overwintered, false);
+		assert_eq!(transaction.versiongroupid, None);
+		assert_eq!(transaction.expiryheight, None);
+		assert_eq!(transaction.vin.len(), 1);
+		assert_eq!(transaction.vjoinsplit.len(), 1);
+		assert_eq!(transaction.vjoinsplit[0].vpub_old, 0.1425);
+		assert_eq!(transaction.vjoinsplit[0].vpub_new, 0.0);
+		assert_eq!(transaction.vjoinsplit[0].nullifiers, vec![
+			H256::from("7ae7c48e86173b231e84fbdcb4d8f569f28f71ebf0f9b5867f9d4c12e031a2ac"),
+			H256::from("c0108235936d2fa2d2c968654fbea2a89fde8522ec7c227d2ff3c10bff9c1197"),
+		]);
+		assert!(transaction.join_split_pub_key.is_some());
+		assert!(transaction.join_split_sig.is_some());
+		assert!(transaction.shielded_spends.is_empty());
+		assert_eq!(transaction.value_balance, None);
+	}
+
+	#[test]
+	fn decoderawtransaction_error() {
+		let client = RawClient::new(SuccessRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "decoderawtransaction",
+				"params": ["0100"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		let sample: serde_json::Value = serde_json::from_str(&sample).unwrap();
+		assert_eq!(sample["error"]["code"], -32602);
+	}
+
+	#[test]
+	fn getrawtransaction_error() {
+		let client = RawClient::new(ErrorRawClientCore::default());
+		let mut handler = IoHandler::new();
+		handler.extend_with(client.to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "getrawtransaction",
+				"params": ["34acbc23f60064e0a6e45d9633f5efa5eb068188ff231050fec535d0ccef9107"],
+				"id": 1
+			}"#)
+		).unwrap();
+
+		assert_eq!(r#"{"jsonrpc":"2.0","error":{"code":-32096,"message":"Transaction with given hash is not found","data":"0791efccd035c5fe501023ff888106eba5eff533965de4a6e06400f623bcac34"},"id":1}"#, &sample);
+	}
+
+	#[test]
+	fn transaction_is_found_in_given_block() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(
+			vec![
+				test_data::genesis().into(),
+				test_data::block_h1().into(),
+			]
+		));
+
+		let block1 = test_data::block_h1();
+		let block1_hash = block1.hash();
+		let tx1 = block1.transactions[0].clone();
+		let tx1_hash = tx1.hash();
+
+		// transaction is in the block
+		assert_eq!(RawClientCore::do_transaction(&*storage, tx1_hash.clone(), Some(block1_hash.clone())), Ok(tx1.clone()));
+		// block hash isn't provided => transaction is searched in the whole chain
+		assert_eq!(RawClientCore::do_transaction(&*storage, tx1_hash.clone(), None), Ok(tx1));
+		// transaction isn't in the block
+		assert_eq!(
+			RawClientCore::do_transaction(&*storage, tx1_hash.clone(), Some(test_data::genesis().hash())).unwrap_err().message,
+			"Transaction with given hash is not found",
+		);
+		// block is unknown
+		assert_eq!(
+			RawClientCore::do_transaction(&*storage, tx1_hash, Some(test_data::block_h2().hash())).unwrap_err().message,
+			"Block with given hash is not found",
+		);
+	}
+
+	#[test]
+	fn verbose_transaction_has_block_info() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(
+			vec![
+				test_data::genesis().into(),
+				test_data::block_h1().into(),
+				test_data::block_h2().into(),
+			]
+		));
+
+		let block1 = test_data::block_h1();
+		let tx1_hash = block1.transactions[0].hash();
+
+		let transaction = RawClientCore::do_verbose_transaction(&*storage, keys::Network::Mainnet, tx1_hash.clone(), None).unwrap();
+		assert_eq!(transaction.txid, H256::from(tx1_hash.reversed()));
+		assert_eq!(transaction.blockhash, Some(H256::from(block1.hash().reversed())));
+		assert_eq!(transaction.confirmations, Some(2));
+		assert_eq!(transaction.time, Some(block1.block_header.time));
+		assert_eq!(transaction.blocktime, Some(block1.block_header.time));
+
+		// the same info when block is given explicitly
+		let transaction_in_block = RawClientCore::do_verbose_transaction(&*storage, keys::Network::Mainnet, tx1_hash, Some(block1.hash())).unwrap();
+		assert_eq!(transaction_in_block, transaction);
+	}
 }
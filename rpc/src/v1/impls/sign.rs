@@ -0,0 +1,76 @@
+use jsonrpc_core::Error;
+use keys::{self, Private};
+use network::Network;
+use v1::helpers::errors::{execution, invalid_params};
+use v1::traits::Sign;
+
+pub struct SignClient {
+	network: keys::Network,
+}
+
+impl SignClient {
+	pub fn new(network: Network) -> Self {
+		SignClient {
+			network: match network {
+				Network::Mainnet => keys::Network::Mainnet,
+				// there's no correct choices for Regtests && Other networks
+				// => let's just use Testnet keys
+				_ => keys::Network::Testnet,
+			},
+		}
+	}
+}
+
+impl Sign for SignClient {
+	fn sign_message(&self, private: String, message: String) -> Result<String, Error> {
+		let private: Private = private.parse()
+			.map_err(|_| invalid_params("privkey", "Invalid private key"))?;
+		if private.network != self.network {
+			return Err(invalid_params("privkey", "Private key is for another network"));
+		}
+
+		keys::sign_message(&private, &message)
+			.map(|signature| signature.to_base64())
+			.map_err(execution)
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use jsonrpc_core::IoHandler;
+	use network::Network;
+	use v1::traits::Sign;
+	use super::*;
+
+	#[test]
+	fn sign_message() {
+		let mut handler = IoHandler::new();
+		handler.extend_with(SignClient::new(Network::Mainnet).to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "signmessagewithprivkey",
+				"params": ["Kwr371tjA9u2rFSMZjTNun2PXXP3WPZu2afRHTcta6KxEUdm1vEw", "Zcash message signing test"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":"H2OecXC6ecEikddPOYHlO9TROa8qZzD/prkHleOkxED3au+Uhhjh7RWToCWOMjjyWMa3EV83o6nJz1q74xXKkcY=","id":1}"#);
+	}
+
+	#[test]
+	fn sign_message_rejects_key_of_other_network() {
+		let mut handler = IoHandler::new();
+		handler.extend_with(SignClient::new(Network::Testnet).to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "signmessagewithprivkey",
+				"params": ["Kwr371tjA9u2rFSMZjTNun2PXXP3WPZu2afRHTcta6KxEUdm1vEw", "Zcash message signing test"],
+				"id": 1
+			}"#)).unwrap();
+
+		assert!(sample.contains("Private key is for another network"));
+	}
+}
@@ -0,0 +1,156 @@
+use jsonrpc_core::Error;
+use keys::{self, Address, CompactSignature, SaplingAddress, SproutAddress};
+use network::Network;
+use primitives::hash::H256 as GlobalH256;
+use v1::helpers::errors::invalid_params;
+use v1::traits::Util;
+use v1::types::{ValidateAddressResponse, ValidatedAddressType};
+
+pub struct UtilClient {
+	network: keys::Network,
+}
+
+impl UtilClient {
+	pub fn new(network: Network) -> Self {
+		UtilClient {
+			network: match network {
+				Network::Mainnet => keys::Network::Mainnet,
+				// there's no correct choices for Regtests && Other networks
+				// => let's just use Testnet addresses
+				_ => keys::Network::Testnet,
+			},
+		}
+	}
+}
+
+impl Util for UtilClient {
+	fn validate_address(&self, address: String) -> Result<ValidateAddressResponse, Error> {
+		if let Ok(parsed) = address.parse::<Address>() {
+			if parsed.network != self.network {
+				return Ok(ValidateAddressResponse::default());
+			}
+
+			return Ok(ValidateAddressResponse {
+				isvalid: true,
+				address_type: Some(match parsed.kind {
+					keys::Type::P2PKH => ValidatedAddressType::P2pkh,
+					keys::Type::P2SH => ValidatedAddressType::P2sh,
+				}),
+				address: Some(address),
+				..Default::default()
+			});
+		}
+
+		if let Ok(parsed) = address.parse::<SaplingAddress>() {
+			if parsed.network != self.network {
+				return Ok(ValidateAddressResponse::default());
+			}
+
+			return Ok(ValidateAddressResponse {
+				isvalid: true,
+				address: Some(address),
+				address_type: Some(ValidatedAddressType::Sapling),
+				diversifier: Some(parsed.diversifier.to_vec().into()),
+				diversifiedtransmissionkey: Some(GlobalH256::from(parsed.pk_d).reversed().into()),
+				..Default::default()
+			});
+		}
+
+		if let Ok(parsed) = address.parse::<SproutAddress>() {
+			if parsed.network != self.network {
+				return Ok(ValidateAddressResponse::default());
+			}
+
+			return Ok(ValidateAddressResponse {
+				isvalid: true,
+				address: Some(address),
+				address_type: Some(ValidatedAddressType::Sprout),
+				payingkey: Some(GlobalH256::from(parsed.paying_key).reversed().into()),
+				transmissionkey: Some(GlobalH256::from(parsed.transmission_key).reversed().into()),
+				..Default::default()
+			});
+		}
+
+		Ok(ValidateAddressResponse::default())
+	}
+
+	fn verify_message(&self, address: String, signature: String, message: String) -> Result<bool, Error> {
+		let address: Address = address.parse()
+			.map_err(|_| invalid_params("address", "Invalid address"))?;
+		if address.network != self.network {
+			return Err(invalid_params("address", "Address is for another network"));
+		}
+		if address.kind != keys::Type::P2PKH {
+			return Err(invalid_params("address", "Address does not refer to key"));
+		}
+
+		let signature = CompactSignature::from_base64(&signature)
+			.map_err(|_| invalid_params("signature", "Malformed base64 encoding"))?;
+		Ok(keys::verify_message(&address, &signature, &message))
+	}
+}
+
+#[cfg(test)]
+pub mod tests {
+	use jsonrpc_core::IoHandler;
+	use network::Network;
+	use v1::traits::Util;
+	use super::*;
+
+	fn validate_address(network: Network, address: &str) -> String {
+		let mut handler = IoHandler::new();
+		handler.extend_with(UtilClient::new(network).to_delegate());
+
+		handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "z_validateaddress",
+				"params": [""#.to_owned() + address + r#""],
+				"id": 1
+			}"#)).unwrap()
+	}
+
+	#[test]
+	fn validate_transparent_address() {
+		assert_eq!(&validate_address(Network::Mainnet, "t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe"),
+			r#"{"jsonrpc":"2.0","result":{"address":"t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe","isvalid":true,"type":"p2pkh"},"id":1}"#);
+	}
+
+	#[test]
+	fn validate_sapling_address() {
+		assert_eq!(&validate_address(Network::Mainnet, "zs1z7rejlpsa98s2rrrfkwmaxu53e4ue0ulcrw0h4x5g8jl04tak0d3mm47vdtahatqrlkngh9slya"),
+			r#"{"jsonrpc":"2.0","result":{"address":"zs1z7rejlpsa98s2rrrfkwmaxu53e4ue0ulcrw0h4x5g8jl04tak0d3mm47vdtahatqrlkngh9slya","diversifiedtransmissionkey":"34ed1f60f5db5763beee1ddbb37dd5f7e541d4d4fbdcc09fbfcc6b8e949bbe9d","diversifier":"1787997c30e94f050c634d","isvalid":true,"type":"sapling"},"id":1}"#);
+	}
+
+	#[test]
+	fn verify_message() {
+		let mut handler = IoHandler::new();
+		handler.extend_with(UtilClient::new(Network::Mainnet).to_delegate());
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "verifymessage",
+				"params": ["t1ffus9J1vhxvFqLoExGBRPjE7BcJxiSCTC", "H2OecXC6ecEikddPOYHlO9TROa8qZzD/prkHleOkxED3au+Uhhjh7RWToCWOMjjyWMa3EV83o6nJz1q74xXKkcY=", "Zcash message signing test"],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":true,"id":1}"#);
+
+		let sample = handler.handle_request_sync(&(r#"
+			{
+				"jsonrpc": "2.0",
+				"method": "verifymessage",
+				"params": ["t1ffus9J1vhxvFqLoExGBRPjE7BcJxiSCTC", "H2OecXC6ecEikddPOYHlO9TROa8qZzD/prkHleOkxED3au+Uhhjh7RWToCWOMjjyWMa3EV83o6nJz1q74xXKkcY=", "Bitcoin message signing test"],
+				"id": 1
+			}"#)).unwrap();
+		assert_eq!(&sample, r#"{"jsonrpc":"2.0","result":false,"id":1}"#);
+	}
+
+	#[test]
+	fn validate_invalid_address() {
+		assert_eq!(&validate_address(Network::Mainnet, "garbage"),
+			r#"{"jsonrpc":"2.0","result":{"isvalid":false},"id":1}"#);
+		assert_eq!(&validate_address(Network::Mainnet, "ztestsapling1wn3tw9w5rs55x5yl586gtk72e8hcfdq8zsnjzcu8p7ghm8lrx54axc74mvm335q7lmy3g0sqje6"),
+			r#"{"jsonrpc":"2.0","result":{"isvalid":false},"id":1}"#);
+	}
+}
@@ -8,7 +8,11 @@ pub use self::traits::Raw;
 pub use self::traits::Miner;
 pub use self::traits::BlockChain;
 pub use self::traits::Network;
-pub use self::impls::{RawClient, RawClientCore};
+pub use self::traits::Util;
+pub use self::traits::Sign;
+pub use self::impls::{RawClient, RawClientCore, DEFAULT_TX_EXPIRY_DELTA};
 pub use self::impls::{MinerClient, MinerClientCore};
 pub use self::impls::{BlockChainClient, BlockChainClientCore};
 pub use self::impls::{NetworkClient, NetworkClientCore};
+pub use self::impls::UtilClient;
+pub use self::impls::SignClient;
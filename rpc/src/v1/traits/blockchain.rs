@@ -1,10 +1,14 @@
 use jsonrpc_derive::rpc;
 use jsonrpc_core::Error;
 
-use v1::types::{BlockRef, H256};
+use v1::types::{BlockRef, H256, ShieldedPool};
 use v1::types::GetBlockResponse;
+use v1::types::GetBlockHeaderResponse;
 use v1::types::GetTxOutResponse;
 use v1::types::GetTxOutSetInfoResponse;
+use v1::types::GetTreeStateResponse;
+use v1::types::GetBlockSubsidyResponse;
+use v1::types::UnspentOutput;
 
 /// Parity-bitcoin blockchain data interface.
 #[rpc]
@@ -21,6 +25,10 @@ pub trait BlockChain {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockhash", "params": [0], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getblockhash")]
 	fn block_hash(&self, u32) -> Result<H256, Error>;
+	/// Get hashes of main chain blocks with timestamps in given range (high, low), inclusive, ordered by timestamp.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockhashes", "params": [1477671626, 1477671596], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getblockhashes")]
+	fn block_hashes(&self, u32, u32) -> Result<Vec<H256>, Error>;
 	/// Get proof-of-work difficulty for the next block as a multiple of the minimum difficulty
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getdifficulty", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getdifficulty")]
@@ -30,6 +38,11 @@ pub trait BlockChain {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblock", "params": ["0002a26c902619fc964443264feb16f1e3e2d71322fc53dcb81cc5d797e273ed"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getblock")]
 	fn block(&self, BlockRef, Option<u8>) -> Result<GetBlockResponse, Error>;
+	/// Get information on given block header.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockheader", "params": ["0002a26c902619fc964443264feb16f1e3e2d71322fc53dcb81cc5d797e273ed"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblockheader", "params": ["0002a26c902619fc964443264feb16f1e3e2d71322fc53dcb81cc5d797e273ed", false], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getblockheader")]
+	fn block_header(&self, H256, Option<bool>) -> Result<GetBlockHeaderResponse, Error>;
 	/// Get details about an unspent transaction output.
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "gettxout", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", 0], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "gettxout")]
@@ -38,4 +51,23 @@ pub trait BlockChain {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "gettxoutsetinfo", "params": [], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "gettxoutsetinfo")]
 	fn transaction_out_set_info(&self) -> Result<GetTxOutSetInfoResponse, Error>;
+	/// Get Sprout and Sapling note commitment trees state at given block.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "z_gettreestate", "params": ["0002a26c902619fc964443264feb16f1e3e2d71322fc53dcb81cc5d797e273ed"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "z_gettreestate", "params": ["419200"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "z_gettreestate")]
+	fn tree_state(&self, BlockRef) -> Result<GetTreeStateResponse, Error>;
+	/// Check whether nullifier of given shielded pool is revealed in the main chain (i.e. the note is spent).
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "z_isnullifierspent", "params": ["sapling", "0101010101010101010101010101010101010101010101010101010101010101"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "z_isnullifierspent")]
+	fn is_nullifier_spent(&self, ShieldedPool, H256) -> Result<bool, Error>;
+	/// Get block subsidy and its distribution between miner, founders and funding streams at given height.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblocksubsidy", "params": [1046400], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getblocksubsidy")]
+	fn block_subsidy(&self, u32) -> Result<GetBlockSubsidyResponse, Error>;
+	/// Get unspent outputs of the main chain, paying to given transparent addresses. Requires address index.
+	/// Immature coinbase outputs are included, unless second parameter is false.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "listunspent", "params": [["t1KstPVzcNEK4ZeauQ6cogoqxQBMDSiRnGr"]], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "listunspent", "params": [["t1KstPVzcNEK4ZeauQ6cogoqxQBMDSiRnGr"], false], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "listunspent")]
+	fn unspent_outputs(&self, Vec<String>, Option<bool>) -> Result<Vec<UnspentOutput>, Error>;
 }
@@ -1,6 +1,6 @@
 use jsonrpc_core::Error;
 
-use v1::types::{BlockTemplate, BlockTemplateRequest};
+use v1::types::{BlockTemplate, BlockTemplateRequest, H256};
 
 /// Parity-bitcoin miner data interface.
 #[rpc]
@@ -9,4 +9,21 @@ pub trait Miner {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "getblocktemplate", "params": [{"capabilities": ["coinbasetxn", "workid", "coinbase/append"]}], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getblocktemplate")]
 	fn get_block_template(&self, BlockTemplateRequest) -> Result<BlockTemplate, Error>;
+
+	/// Apply fee delta to the memory pool transaction, so that it is treated as if it has paid more (or less) when mining.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "prioritisetransaction", "params": ["4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b", 10000], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "prioritisetransaction")]
+	fn prioritise_transaction(&self, H256, i64) -> Result<bool, Error>;
+
+	/// Estimate the coin-age priority, with which (free) transaction is likely to be confirmed within given number of blocks.
+	/// Returns -1 if there's not enough data to make an estimate.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "estimatepriority", "params": [6], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "estimatepriority")]
+	fn estimate_priority(&self, u32) -> Result<f64, Error>;
+
+	/// Mine given number of blocks immediately (regtest only, requires node built with `solver` feature).
+	/// Returns hashes of generated blocks.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "generate", "params": [1], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "generate")]
+	fn generate(&self, u32) -> Result<Vec<H256>, Error>;
 }
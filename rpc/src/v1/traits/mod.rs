@@ -2,8 +2,12 @@ mod blockchain;
 mod miner;
 mod raw;
 mod network;
+mod util;
+mod sign;
 
 pub use self::blockchain::BlockChain;
 pub use self::miner::Miner;
 pub use self::raw::Raw;
 pub use self::network::Network;
+pub use self::util::Util;
+pub use self::sign::Sign;
@@ -1,5 +1,5 @@
 use jsonrpc_core::Error;
-use v1::types::{AddNodeOperation, NodeInfo};
+use v1::types::{AddNodeOperation, NodeInfo, NetTotals, SyncStatus, GetInfoResponse, BlockFailureCategory};
 
 /// Parity-bitcoin network interface
 #[rpc]
@@ -19,4 +19,25 @@ pub trait Network {
 	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getconnectioncount", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
 	#[rpc(name = "getconnectioncount")]
 	fn connection_count(&self) -> Result<usize, Error>;
+	/// Disable/enable all p2p network activity
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "setnetworkactive", "params": [false] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "setnetworkactive")]
+	fn set_network_active(&self, bool) -> Result<bool, Error>;
+	/// Query synchronization status
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getsyncstatus", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getsyncstatus")]
+	fn sync_status(&self) -> Result<SyncStatus, Error>;
+	/// Query network traffic totals
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getnettotals", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getnettotals")]
+	fn net_totals(&self) -> Result<NetTotals, Error>;
+	/// Query aggregated node status
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "getinfo", "params": [] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "getinfo")]
+	fn info(&self) -> Result<GetInfoResponse, Error>;
+	/// Forget about blocks that have failed given category of checks ("header", "scripts", "transactions" or "other"),
+	/// so that they're downloaded and verified again. Returns number of reconsidered blocks.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "id":"1", "method": "reconsiderfailedblocks", "params": ["scripts"] }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "reconsiderfailedblocks")]
+	fn reconsider_failed_blocks(&self, BlockFailureCategory) -> Result<usize, Error>;
 }
@@ -0,0 +1,11 @@
+use jsonrpc_core::Error;
+
+/// Parity-zcash message signing interface.
+/// Private keys are passed over RPC, so it is not enabled by default.
+#[rpc]
+pub trait Sign {
+	/// Sign the message with given private key. There's no wallet, so the key itself is passed.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "signmessagewithprivkey", "params": ["Kwr371tjA9u2rFSMZjTNun2PXXP3WPZu2afRHTcta6KxEUdm1vEw", "my message"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "signmessagewithprivkey")]
+	fn sign_message(&self, String, String) -> Result<String, Error>;
+}
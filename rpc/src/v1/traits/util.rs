@@ -0,0 +1,15 @@
+use jsonrpc_core::Error;
+use v1::types::ValidateAddressResponse;
+
+/// Parity-zcash utility interface.
+#[rpc]
+pub trait Util {
+	/// Check whether given transparent or shielded address is valid.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "z_validateaddress", "params": ["zs1z7rejlpsa98s2rrrfkwmaxu53e4ue0ulcrw0h4x5g8jl04tak0d3mm47vdtahatqrlkngh9slya"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "z_validateaddress")]
+	fn validate_address(&self, String) -> Result<ValidateAddressResponse, Error>;
+	/// Verify base64-encoded message signature, made by the owner of given transparent address.
+	/// @curl-example: curl --data-binary '{"jsonrpc": "2.0", "method": "verifymessage", "params": ["t1ffus9J1vhxvFqLoExGBRPjE7BcJxiSCTC", "H2OecXC6ecEikddPOYHlO9TROa8qZzD/prkHleOkxED3au+Uhhjh7RWToCWOMjjyWMa3EV83o6nJz1q74xXKkcY=", "my message"], "id":1 }' -H 'content-type: application/json' http://127.0.0.1:8332/
+	#[rpc(name = "verifymessage")]
+	fn verify_message(&self, String, String, String) -> Result<bool, Error>;
+}
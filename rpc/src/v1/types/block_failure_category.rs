@@ -0,0 +1,67 @@
+use std::fmt;
+use serde::{Deserialize, Deserializer};
+use serde::de::Unexpected;
+use sync;
+
+/// Category of the check, that failed blocks have failed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlockFailureCategory {
+	Header,
+	Scripts,
+	Transactions,
+	Other,
+}
+
+impl From<BlockFailureCategory> for sync::BlockFailureCategory {
+	fn from(category: BlockFailureCategory) -> Self {
+		match category {
+			BlockFailureCategory::Header => sync::BlockFailureCategory::Header,
+			BlockFailureCategory::Scripts => sync::BlockFailureCategory::Scripts,
+			BlockFailureCategory::Transactions => sync::BlockFailureCategory::Transactions,
+			BlockFailureCategory::Other => sync::BlockFailureCategory::Other,
+		}
+	}
+}
+
+impl<'a> Deserialize<'a> for BlockFailureCategory {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a> {
+		use serde::de::Visitor;
+
+		struct DummyVisitor;
+
+		impl<'b> Visitor<'b> for DummyVisitor {
+			type Value = BlockFailureCategory;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("a block failure category")
+			}
+
+			fn visit_str<E>(self, value: &str) -> Result<BlockFailureCategory, E> where E: ::serde::de::Error {
+				match value {
+					"header" => Ok(BlockFailureCategory::Header),
+					"scripts" => Ok(BlockFailureCategory::Scripts),
+					"transactions" => Ok(BlockFailureCategory::Transactions),
+					"other" => Ok(BlockFailureCategory::Other),
+					_ => Err(E::invalid_value(Unexpected::Str(value), &self)),
+				}
+			}
+		}
+
+		deserializer.deserialize_identifier(DummyVisitor)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::BlockFailureCategory;
+
+	#[test]
+	fn block_failure_category_deserialize() {
+		assert_eq!(serde_json::from_str::<BlockFailureCategory>(r#""header""#).unwrap(), BlockFailureCategory::Header);
+		assert_eq!(serde_json::from_str::<BlockFailureCategory>(r#""scripts""#).unwrap(), BlockFailureCategory::Scripts);
+		assert_eq!(serde_json::from_str::<BlockFailureCategory>(r#""transactions""#).unwrap(), BlockFailureCategory::Transactions);
+		assert_eq!(serde_json::from_str::<BlockFailureCategory>(r#""other""#).unwrap(), BlockFailureCategory::Other);
+		assert!(serde_json::from_str::<BlockFailureCategory>(r#""consensus""#).is_err());
+	}
+}
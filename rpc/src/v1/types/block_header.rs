@@ -0,0 +1,81 @@
+use chain;
+use super::bytes::Bytes;
+use super::hash::H256;
+
+/// Block header, as it is represented in all RPC responses
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct BlockHeader {
+	/// Block hash
+	pub hash: H256,
+	/// Block version
+	pub version: u32,
+	/// Merkle root of this block
+	pub merkleroot: H256,
+	/// The root of the Sapling commitment tree after applying this block.
+	pub finalsaplingroot: H256,
+	/// Block time in seconds since epoch (Jan 1 1970 GMT)
+	pub time: u32,
+	/// Block nonce
+	pub nonce: H256,
+	/// Equihash solution
+	pub solution: Bytes,
+	/// Block nbits
+	pub bits: u32,
+	/// Hash of previous block
+	pub previousblockhash: Option<H256>,
+}
+
+impl<'a> From<&'a chain::IndexedBlockHeader> for BlockHeader {
+	fn from(header: &'a chain::IndexedBlockHeader) -> Self {
+		BlockHeader {
+			hash: header.hash.clone().into(),
+			version: header.raw.version,
+			merkleroot: header.raw.merkle_root_hash.clone().into(),
+			finalsaplingroot: header.raw.final_sapling_root.clone().into(),
+			time: header.raw.time,
+			nonce: header.raw.nonce.clone().into(),
+			solution: Bytes::new(header.raw.solution.as_ref().to_vec()),
+			bits: header.raw.bits.into(),
+			previousblockhash: Some(header.raw.previous_header_hash.clone().into()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use serde_json;
+	use serde_json::Value;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn block_header_serialize() {
+		let header = BlockHeader {
+			hash: H256::from(1),
+			version: 4,
+			merkleroot: H256::from(2),
+			finalsaplingroot: H256::from(3),
+			time: 111,
+			nonce: H256::from(5),
+			solution: "0102".into(),
+			bits: 13513,
+			previousblockhash: Some(H256::from(4)),
+		};
+		assert_eq!(serde_json::to_string(&header).unwrap(), r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","version":4,"merkleroot":"0200000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0300000000000000000000000000000000000000000000000000000000000000","time":111,"nonce":"0500000000000000000000000000000000000000000000000000000000000000","solution":"0102","bits":13513,"previousblockhash":"0400000000000000000000000000000000000000000000000000000000000000"}"#);
+	}
+
+	#[test]
+	fn block_header_schema() {
+		let header: chain::IndexedBlockHeader = test_data::genesis().block_header.into();
+		let json = serde_json::to_value(&BlockHeader::from(&header)).unwrap();
+		let fields = match json {
+			Value::Object(map) => map.keys().cloned().collect::<Vec<_>>(),
+			_ => panic!("block header is serialized as object"),
+		};
+		let mut expected = vec!["hash", "version", "merkleroot", "finalsaplingroot", "time", "nonce", "solution", "bits", "previousblockhash"];
+		expected.sort();
+		assert_eq!(fields, expected);
+	}
+}
@@ -0,0 +1,76 @@
+use serde::{Serialize, Serializer};
+use super::bytes::Bytes;
+use super::hash::H256;
+use super::block_header::BlockHeader;
+
+/// Hex-encoded block header
+pub type RawBlockHeader = Bytes;
+
+/// Response to getblockheader RPC request
+#[derive(Debug)]
+pub enum GetBlockHeaderResponse {
+	/// When asking for short response
+	Raw(RawBlockHeader),
+	/// When asking for verbose response
+	Verbose(VerboseBlockHeader),
+}
+
+/// Verbose block header information
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct VerboseBlockHeader {
+	/// Block header
+	#[serde(flatten)]
+	pub header: BlockHeader,
+	/// Number of confirmations. -1 if block is on the side chain
+	pub confirmations: i64,
+	/// Block height
+	pub height: Option<u32>,
+	/// Block difficulty
+	pub difficulty: f64,
+	/// Hash of next block
+	pub nextblockhash: Option<H256>,
+}
+
+impl Serialize for GetBlockHeaderResponse {
+	fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error> where S: Serializer {
+		match *self {
+			GetBlockHeaderResponse::Raw(ref raw_header) => raw_header.serialize(serializer),
+			GetBlockHeaderResponse::Verbose(ref verbose_header) => verbose_header.serialize(serializer),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn verbose_block_header_serialize() {
+		let header = VerboseBlockHeader {
+			header: BlockHeader {
+				hash: H256::from(1),
+				version: 4,
+				merkleroot: H256::from(2),
+				finalsaplingroot: H256::from(3),
+				time: 111,
+				nonce: H256::from(5),
+				solution: "0102".into(),
+				bits: 13513,
+				previousblockhash: Some(H256::from(4)),
+			},
+			confirmations: -1,
+			height: None,
+			difficulty: 1.5,
+			nextblockhash: None,
+		};
+		assert_eq!(serde_json::to_string(&header).unwrap(), r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","version":4,"merkleroot":"0200000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0300000000000000000000000000000000000000000000000000000000000000","time":111,"nonce":"0500000000000000000000000000000000000000000000000000000000000000","solution":"0102","bits":13513,"previousblockhash":"0400000000000000000000000000000000000000000000000000000000000000","confirmations":-1,"height":null,"difficulty":1.5,"nextblockhash":null}"#);
+	}
+
+	#[test]
+	fn raw_block_header_serialize() {
+		let response = GetBlockHeaderResponse::Raw("0102".into());
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#""0102""#);
+	}
+}
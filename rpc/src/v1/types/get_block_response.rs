@@ -1,6 +1,7 @@
 use serde::{Serialize, Serializer};
 use super::hash::H256;
 use super::block::RawBlock;
+use super::block_header::BlockHeader;
 
 /// Response to getblock RPC request
 #[derive(Debug)]
@@ -14,8 +15,9 @@ pub enum GetBlockResponse {
 /// Verbose block information
 #[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct VerboseBlock {
-	/// Block hash
-	pub hash: H256,
+	/// Block header
+	#[serde(flatten)]
+	pub header: BlockHeader,
 	/// Number of confirmations. -1 if block is on the side chain
 	pub confirmations: i64,
 	/// Block size
@@ -23,24 +25,10 @@ pub struct VerboseBlock {
 	/// Block height
 	/// TODO: bitcoind always returns value, but we hold this value for main chain blocks only
 	pub height: Option<u32>,
-	/// Block version
-	pub version: u32,
-	/// Merkle root of this block
-	pub merkleroot: H256,
-	/// The root of the Sapling commitment tree after applying this block.
-	pub finalsaplingroot: H256,
 	/// Transactions ids
 	pub tx: Vec<H256>,
-	/// Block time in seconds since epoch (Jan 1 1970 GMT)
-	pub time: u32,
-	/// Block nonce
-	pub nonce: H256,
-	/// Block nbits
-	pub bits: u32,
 	/// Block difficulty
 	pub difficulty: f64,
-	/// Hash of previous block
-	pub previousblockhash: Option<H256>,
 	/// Hash of next block
 	pub nextblockhash: Option<H256>,
 }
@@ -64,52 +52,64 @@ mod tests {
 	#[test]
 	fn verbose_block_serialize() {
 		let block = VerboseBlock::default();
-		assert_eq!(serde_json::to_string(&block).unwrap(), r#"{"hash":"0000000000000000000000000000000000000000000000000000000000000000","confirmations":0,"size":0,"height":null,"version":0,"merkleroot":"0000000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0000000000000000000000000000000000000000000000000000000000000000","tx":[],"time":0,"nonce":"0000000000000000000000000000000000000000000000000000000000000000","bits":0,"difficulty":0.0,"previousblockhash":null,"nextblockhash":null}"#);
+		assert_eq!(serde_json::to_string(&block).unwrap(), r#"{"hash":"0000000000000000000000000000000000000000000000000000000000000000","version":0,"merkleroot":"0000000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0000000000000000000000000000000000000000000000000000000000000000","time":0,"nonce":"0000000000000000000000000000000000000000000000000000000000000000","solution":"","bits":0,"previousblockhash":null,"confirmations":0,"size":0,"height":null,"tx":[],"difficulty":0.0,"nextblockhash":null}"#);
 
 		let block = VerboseBlock {
-			hash: H256::from(1),
+			header: BlockHeader {
+				hash: H256::from(1),
+				version: 1,
+				merkleroot: H256::from(2),
+				finalsaplingroot: H256::from(3),
+				time: 111,
+				nonce: 124.into(),
+				solution: "0102".into(),
+				bits: 13513,
+				previousblockhash: Some(H256::from(4)),
+			},
 			confirmations: -1,
 			size: 500000,
 			height: Some(3513513),
-			version: 1,
-			merkleroot: H256::from(2),
 			tx: vec![H256::from(3), H256::from(4)],
-			time: 111,
-			nonce: 124.into(),
-			bits: 13513,
 			difficulty: 555.555,
-			previousblockhash: Some(H256::from(4)),
 			nextblockhash: Some(H256::from(5)),
-			finalsaplingroot: H256::from(3),
 		};
-		assert_eq!(serde_json::to_string(&block).unwrap(), r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","confirmations":-1,"size":500000,"height":3513513,"version":1,"merkleroot":"0200000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0300000000000000000000000000000000000000000000000000000000000000","tx":["0300000000000000000000000000000000000000000000000000000000000000","0400000000000000000000000000000000000000000000000000000000000000"],"time":111,"nonce":"7c00000000000000000000000000000000000000000000000000000000000000","bits":13513,"difficulty":555.555,"previousblockhash":"0400000000000000000000000000000000000000000000000000000000000000","nextblockhash":"0500000000000000000000000000000000000000000000000000000000000000"}"#);
+		assert_eq!(serde_json::to_string(&block).unwrap(), r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","version":1,"merkleroot":"0200000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0300000000000000000000000000000000000000000000000000000000000000","time":111,"nonce":"7c00000000000000000000000000000000000000000000000000000000000000","solution":"0102","bits":13513,"previousblockhash":"0400000000000000000000000000000000000000000000000000000000000000","confirmations":-1,"size":500000,"height":3513513,"tx":["0300000000000000000000000000000000000000000000000000000000000000","0400000000000000000000000000000000000000000000000000000000000000"],"difficulty":555.555,"nextblockhash":"0500000000000000000000000000000000000000000000000000000000000000"}"#);
 	}
 
 	#[test]
 	fn verbose_block_deserialize() {
-		let block = VerboseBlock::default();
+		let block = VerboseBlock {
+			header: BlockHeader {
+				solution: "00".into(),
+				..Default::default()
+			},
+			..Default::default()
+		};
 		assert_eq!(
-			serde_json::from_str::<VerboseBlock>(r#"{"hash":"0000000000000000000000000000000000000000000000000000000000000000","confirmations":0,"size":0,"height":null,"version":0,"merkleroot":"0000000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0000000000000000000000000000000000000000000000000000000000000000","tx":[],"time":0,"nonce":"0000000000000000000000000000000000000000000000000000000000000000","bits":0,"difficulty":0.0,"previousblockhash":null,"nextblockhash":null}"#).unwrap(),
+			serde_json::from_str::<VerboseBlock>(r#"{"hash":"0000000000000000000000000000000000000000000000000000000000000000","version":0,"merkleroot":"0000000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0000000000000000000000000000000000000000000000000000000000000000","time":0,"nonce":"0000000000000000000000000000000000000000000000000000000000000000","solution":"00","bits":0,"previousblockhash":null,"confirmations":0,"size":0,"height":null,"tx":[],"difficulty":0.0,"nextblockhash":null}"#).unwrap(),
 			block);
 
 		let block = VerboseBlock {
-			hash: H256::from(1),
+			header: BlockHeader {
+				hash: H256::from(1),
+				version: 1,
+				merkleroot: H256::from(2),
+				finalsaplingroot: H256::from(3),
+				time: 111,
+				nonce: 124.into(),
+				solution: "0102".into(),
+				bits: 13513,
+				previousblockhash: Some(H256::from(4)),
+			},
 			confirmations: -1,
 			size: 500000,
 			height: Some(3513513),
-			version: 1,
-			merkleroot: H256::from(2),
 			tx: vec![H256::from(3), H256::from(4)],
-			time: 111,
-			nonce: 124.into(),
-			bits: 13513,
 			difficulty: 555.555,
-			previousblockhash: Some(H256::from(4)),
 			nextblockhash: Some(H256::from(5)),
-			finalsaplingroot: H256::from(3),
 		};
 		assert_eq!(
-			serde_json::from_str::<VerboseBlock>(r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","confirmations":-1,"size":500000,"height":3513513,"version":1,"merkleroot":"0200000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0300000000000000000000000000000000000000000000000000000000000000","tx":["0300000000000000000000000000000000000000000000000000000000000000","0400000000000000000000000000000000000000000000000000000000000000"],"time":111,"nonce":"7c00000000000000000000000000000000000000000000000000000000000000","bits":13513,"difficulty":555.555,"previousblockhash":"0400000000000000000000000000000000000000000000000000000000000000","nextblockhash":"0500000000000000000000000000000000000000000000000000000000000000"}"#).unwrap(),
+			serde_json::from_str::<VerboseBlock>(r#"{"hash":"0100000000000000000000000000000000000000000000000000000000000000","version":1,"merkleroot":"0200000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0300000000000000000000000000000000000000000000000000000000000000","time":111,"nonce":"7c00000000000000000000000000000000000000000000000000000000000000","solution":"0102","bits":13513,"previousblockhash":"0400000000000000000000000000000000000000000000000000000000000000","confirmations":-1,"size":500000,"height":3513513,"tx":["0300000000000000000000000000000000000000000000000000000000000000","0400000000000000000000000000000000000000000000000000000000000000"],"difficulty":555.555,"nextblockhash":"0500000000000000000000000000000000000000000000000000000000000000"}"#).unwrap(),
 			block);
 	}
 
@@ -123,6 +123,6 @@ mod tests {
 	fn get_block_response_verbose_serialize() {
 		let block = VerboseBlock::default();
 		let verbose_response = GetBlockResponse::Verbose(block);
-		assert_eq!(serde_json::to_string(&verbose_response).unwrap(), r#"{"hash":"0000000000000000000000000000000000000000000000000000000000000000","confirmations":0,"size":0,"height":null,"version":0,"merkleroot":"0000000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0000000000000000000000000000000000000000000000000000000000000000","tx":[],"time":0,"nonce":"0000000000000000000000000000000000000000000000000000000000000000","bits":0,"difficulty":0.0,"previousblockhash":null,"nextblockhash":null}"#);
+		assert_eq!(serde_json::to_string(&verbose_response).unwrap(), r#"{"hash":"0000000000000000000000000000000000000000000000000000000000000000","version":0,"merkleroot":"0000000000000000000000000000000000000000000000000000000000000000","finalsaplingroot":"0000000000000000000000000000000000000000000000000000000000000000","time":0,"nonce":"0000000000000000000000000000000000000000000000000000000000000000","solution":"","bits":0,"previousblockhash":null,"confirmations":0,"size":0,"height":null,"tx":[],"difficulty":0.0,"nextblockhash":null}"#);
 	}
 }
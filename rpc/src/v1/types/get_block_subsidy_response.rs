@@ -0,0 +1,24 @@
+/// getblocksubsidy response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetBlockSubsidyResponse {
+	/// Total block subsidy (in ZEC)
+	pub total: f64,
+	/// Miner portion of the block subsidy (in ZEC)
+	pub miner: f64,
+	/// Founders reward portion of the block subsidy (in ZEC)
+	pub founders: f64,
+	/// Funding streams portions of the block subsidy
+	pub fundingstreams: Vec<FundingStreamSubsidy>,
+}
+
+/// Single funding stream portion of the block subsidy
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct FundingStreamSubsidy {
+	/// Funding stream recipient
+	pub recipient: String,
+	/// Funding stream value (in ZEC)
+	pub value: f64,
+	/// Funding stream value (in zatoshis)
+	#[serde(rename = "valueZat")]
+	pub value_zat: u64,
+}
@@ -0,0 +1,41 @@
+/// Aggregated node status
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct GetInfoResponse {
+	/// Node version (user agent)
+	pub version: String,
+	/// Protocol version, advertised to peers
+	pub protocolversion: u32,
+	/// Height of the best block in the storage
+	pub blocks: u32,
+	/// Number of connected peers
+	pub connections: usize,
+	/// Difficulty of the best block
+	pub difficulty: f64,
+	/// Network name: main, test or regtest
+	pub network: String,
+	/// Is synchronization (initial block download) in progress?
+	pub synchronizing: bool,
+	/// Are some of enabled chain indexes (txindex, addressindex, spentindex) still being rebuilt?
+	pub reindexing: bool,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::GetInfoResponse;
+
+	#[test]
+	fn get_info_response_serialize() {
+		let info = GetInfoResponse {
+			version: "pzec".into(),
+			protocolversion: 170007,
+			blocks: 100,
+			connections: 8,
+			difficulty: 1.5,
+			network: "main".into(),
+			synchronizing: true,
+			reindexing: false,
+		};
+		assert_eq!(serde_json::to_string(&info).unwrap(), r#"{"version":"pzec","protocolversion":170007,"blocks":100,"connections":8,"difficulty":1.5,"network":"main","synchronizing":true,"reindexing":false}"#);
+	}
+}
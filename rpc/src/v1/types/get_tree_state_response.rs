@@ -0,0 +1,65 @@
+use super::bytes::Bytes;
+use super::hash::H256;
+
+/// z_gettreestate response
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct GetTreeStateResponse {
+	/// Hash of the block
+	pub hash: H256,
+	/// Height of the block
+	pub height: u32,
+	/// Block time
+	pub time: u32,
+	/// Sprout note commitment tree state at the block
+	pub sprout: TreeState,
+	/// Sapling note commitment tree state at the block
+	pub sapling: TreeState,
+}
+
+/// Note commitment tree state of the single shielded pool
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TreeState {
+	/// Commitments tree
+	pub commitments: TreeStateCommitments,
+}
+
+/// Note commitment tree root and frontier
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TreeStateCommitments {
+	/// Root of the tree (anchor)
+	#[serde(rename = "finalRoot")]
+	pub final_root: H256,
+	/// Serialized frontier of the tree
+	#[serde(rename = "finalState")]
+	pub final_state: Bytes,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::bytes::Bytes;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn tree_state_response_serialize() {
+		let tree_state = GetTreeStateResponse {
+			hash: H256::from(0x56),
+			height: 419200,
+			time: 1540779337,
+			sprout: TreeState {
+				commitments: TreeStateCommitments {
+					final_root: H256::from(0x01),
+					final_state: Bytes::new(vec![1, 2]),
+				},
+			},
+			sapling: TreeState {
+				commitments: TreeStateCommitments {
+					final_root: H256::from(0x02),
+					final_state: Bytes::new(vec![0, 0]),
+				},
+			},
+		};
+		assert_eq!(serde_json::to_string(&tree_state).unwrap(), r#"{"hash":"0000000000000000000000000000000000000000000000000000000000000056","height":419200,"time":1540779337,"sprout":{"commitments":{"finalRoot":"0000000000000000000000000000000000000000000000000000000000000001","finalState":"0102"}},"sapling":{"commitments":{"finalRoot":"0000000000000000000000000000000000000000000000000000000000000002","finalState":"0000"}}}"#);
+	}
+}
@@ -1,29 +1,54 @@
 pub mod address;
 mod block;
+mod block_failure_category;
+mod block_header;
 mod block_template;
 mod block_template_request;
 mod bytes;
+mod get_block_header_response;
 mod get_block_response;
+mod get_block_subsidy_response;
+mod get_info_response;
 mod get_tx_out_response;
 mod get_tx_out_set_info_response;
+mod get_tree_state_response;
 mod hash;
+mod net_totals;
 mod script;
+mod shielded_pool;
+mod sync_status;
+mod test_mempool_accept_response;
 mod transaction;
 mod uint;
+mod unspent_output;
+mod validate_address_response;
 mod nodes;
 
 pub use self::block::{BlockRef, RawBlock};
+pub use self::block_failure_category::BlockFailureCategory;
+pub use self::block_header::BlockHeader;
 pub use self::block_template::{BlockTemplate, BlockTemplateTransaction};
 pub use self::block_template_request::{BlockTemplateRequest, BlockTemplateRequestMode};
 pub use self::bytes::Bytes;
+pub use self::get_block_header_response::{GetBlockHeaderResponse, VerboseBlockHeader, RawBlockHeader};
 pub use self::get_block_response::{GetBlockResponse, VerboseBlock};
+pub use self::get_block_subsidy_response::{GetBlockSubsidyResponse, FundingStreamSubsidy};
+pub use self::get_info_response::GetInfoResponse;
 pub use self::get_tx_out_response::GetTxOutResponse;
 pub use self::get_tx_out_set_info_response::GetTxOutSetInfoResponse;
+pub use self::get_tree_state_response::{GetTreeStateResponse, TreeState, TreeStateCommitments};
 pub use self::hash::{H160, H256};
+pub use self::net_totals::NetTotals;
 pub use self::script::ScriptType;
+pub use self::shielded_pool::ShieldedPool;
+pub use self::sync_status::SyncStatus;
+pub use self::test_mempool_accept_response::TestMempoolAcceptResponse;
 pub use self::transaction::{RawTransaction, Transaction, TransactionInput, TransactionOutput,
 	TransactionOutputWithAddress, TransactionOutputWithScriptData, TransactionInputScript,
 	TransactionOutputScript, SignedTransactionInput, GetRawTransactionResponse,
-	SignedTransactionOutput, TransactionOutputs};
+	SignedTransactionOutput, TransactionOutputs, TransactionJoinSplit, TransactionShieldedSpend,
+	TransactionShieldedOutput};
 pub use self::uint::U256;
+pub use self::unspent_output::UnspentOutput;
+pub use self::validate_address_response::{ValidateAddressResponse, ValidatedAddressType};
 pub use self::nodes::{AddNodeOperation, NodeInfo};
@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+
+/// Network traffic totals
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct NetTotals {
+	/// Total bytes received
+	pub totalbytesrecv: u64,
+	/// Total bytes sent
+	pub totalbytessent: u64,
+	/// Current UNIX time in milliseconds
+	pub timemillis: u64,
+	/// Total bytes received, grouped by message type
+	pub bytesrecv_per_msg: BTreeMap<String, u64>,
+	/// Total bytes sent, grouped by message type
+	pub bytessent_per_msg: BTreeMap<String, u64>,
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeMap;
+	use serde_json;
+	use super::NetTotals;
+
+	#[test]
+	fn net_totals_serialize() {
+		let mut bytesrecv_per_msg = BTreeMap::new();
+		bytesrecv_per_msg.insert("block".to_owned(), 1000);
+		let mut bytessent_per_msg = BTreeMap::new();
+		bytessent_per_msg.insert("getdata".to_owned(), 30);
+		bytessent_per_msg.insert("inv".to_owned(), 70);
+		let totals = NetTotals {
+			totalbytesrecv: 1000,
+			totalbytessent: 100,
+			timemillis: 1500000000000,
+			bytesrecv_per_msg: bytesrecv_per_msg,
+			bytessent_per_msg: bytessent_per_msg,
+		};
+		assert_eq!(serde_json::to_string(&totals).unwrap(), r#"{"totalbytesrecv":1000,"totalbytessent":100,"timemillis":1500000000000,"bytesrecv_per_msg":{"block":1000},"bytessent_per_msg":{"getdata":30,"inv":70}}"#);
+	}
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+use serde::{Deserialize, Deserializer};
+use serde::de::Unexpected;
+
+/// Shielded value pool.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShieldedPool {
+	Sprout,
+	Sapling,
+}
+
+impl<'a> Deserialize<'a> for ShieldedPool {
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'a> {
+		use serde::de::Visitor;
+
+		struct DummyVisitor;
+
+		impl<'b> Visitor<'b> for DummyVisitor {
+			type Value = ShieldedPool;
+
+			fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+				formatter.write_str("a shielded pool name")
+			}
+
+			fn visit_str<E>(self, value: &str) -> Result<ShieldedPool, E> where E: ::serde::de::Error {
+				match value {
+					"sprout" => Ok(ShieldedPool::Sprout),
+					"sapling" => Ok(ShieldedPool::Sapling),
+					_ => Err(E::invalid_value(Unexpected::Str(value), &self)),
+				}
+			}
+		}
+
+		deserializer.deserialize_identifier(DummyVisitor)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::ShieldedPool;
+
+	#[test]
+	fn shielded_pool_deserialize() {
+		assert_eq!(serde_json::from_str::<ShieldedPool>(r#""sprout""#).unwrap(), ShieldedPool::Sprout);
+		assert_eq!(serde_json::from_str::<ShieldedPool>(r#""sapling""#).unwrap(), ShieldedPool::Sapling);
+		assert!(serde_json::from_str::<ShieldedPool>(r#""orchard""#).is_err());
+	}
+}
@@ -0,0 +1,29 @@
+/// Synchronization status
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct SyncStatus {
+	/// Is synchronization in progress?
+	pub synchronizing: bool,
+	/// Height of the best block in the storage
+	pub blocks: u32,
+	/// Best block height of the network, estimated from heights advertised by peers
+	pub estimatedheight: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::SyncStatus;
+
+	#[test]
+	fn sync_status_serialize() {
+		let status = SyncStatus {
+			synchronizing: true,
+			blocks: 100,
+			estimatedheight: Some(200),
+		};
+		assert_eq!(serde_json::to_string(&status).unwrap(), r#"{"synchronizing":true,"blocks":100,"estimatedheight":200}"#);
+
+		let status = SyncStatus::default();
+		assert_eq!(serde_json::to_string(&status).unwrap(), r#"{"synchronizing":false,"blocks":0,"estimatedheight":null}"#);
+	}
+}
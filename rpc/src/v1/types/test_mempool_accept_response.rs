@@ -0,0 +1,37 @@
+use super::hash::H256;
+
+/// testmempoolaccept response (for single transaction)
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TestMempoolAcceptResponse {
+	/// Transaction hash
+	pub txid: H256,
+	/// Would transaction be accepted to the memory pool?
+	pub allowed: bool,
+	/// Rejection reason (only when transaction isn't allowed)
+	#[serde(rename = "reject-reason", skip_serializing_if = "Option::is_none")]
+	pub reject_reason: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::hash::H256;
+	use super::TestMempoolAcceptResponse;
+
+	#[test]
+	fn test_mempool_accept_response_serialize() {
+		let response = TestMempoolAcceptResponse {
+			txid: H256::from(1),
+			allowed: true,
+			reject_reason: None,
+		};
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"txid":"0100000000000000000000000000000000000000000000000000000000000000","allowed":true}"#);
+
+		let response = TestMempoolAcceptResponse {
+			txid: H256::from(1),
+			allowed: false,
+			reject_reason: Some("UsingSpentOutput".into()),
+		};
+		assert_eq!(serde_json::to_string(&response).unwrap(), r#"{"txid":"0100000000000000000000000000000000000000000000000000000000000000","allowed":false,"reject-reason":"UsingSpentOutput"}"#);
+	}
+}
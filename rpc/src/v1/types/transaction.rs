@@ -1,7 +1,11 @@
 use std::fmt;
 use serde::{Serialize, Serializer, Deserialize, Deserializer};
 use serde::ser::SerializeMap;
-use keys::Address;
+use keys::{self, Address};
+use chain;
+use chain::constants::SATOSHIS_IN_COIN;
+use global_script::Script;
+use ser::serialize;
 use v1::types;
 use super::bytes::Bytes;
 use super::hash::H256;
@@ -105,6 +109,71 @@ pub struct SignedTransactionOutput {
 	pub script: TransactionOutputScript,
 }
 
+/// JoinSplit description
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TransactionJoinSplit {
+	/// Public value that the JoinSplit transfer removes from the transparent value pool
+	pub vpub_old: f64,
+	/// Public value that the JoinSplit transfer inserts into the transparent value pool
+	pub vpub_new: f64,
+	/// Merkle root of the Sprout note commitment tree
+	pub anchor: H256,
+	/// Nullifiers of the input notes
+	pub nullifiers: Vec<H256>,
+	/// Note commitments of the output notes
+	pub commitments: Vec<H256>,
+	/// Ephemeral key
+	#[serde(rename = "onetimePubKey")]
+	pub onetime_pub_key: H256,
+	/// Random seed
+	#[serde(rename = "randomSeed")]
+	pub random_seed: H256,
+	/// Input notes MACs
+	pub macs: Vec<H256>,
+	/// Zero-knowledge proof
+	pub proof: Bytes,
+	/// Encrypted output notes
+	pub ciphertexts: Vec<Bytes>,
+}
+
+/// Sapling spend description
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TransactionShieldedSpend {
+	/// Value commitment to the input note
+	pub cv: H256,
+	/// Merkle root of the Sapling note commitment tree
+	pub anchor: H256,
+	/// Nullifier of the input note
+	pub nullifier: H256,
+	/// Randomized public key for spend_auth_sig
+	pub rk: H256,
+	/// Zero-knowledge proof
+	pub proof: Bytes,
+	/// Spend authorization signature
+	#[serde(rename = "spendAuthSig")]
+	pub spend_auth_sig: Bytes,
+}
+
+/// Sapling output description
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct TransactionShieldedOutput {
+	/// Value commitment to the output note
+	pub cv: H256,
+	/// Note commitment of the output note
+	pub cmu: H256,
+	/// Ephemeral public key
+	#[serde(rename = "ephemeralKey")]
+	pub ephemeral_key: H256,
+	/// Encrypted note plaintext
+	#[serde(rename = "encCiphertext")]
+	pub enc_ciphertext: Bytes,
+	/// Encrypted outgoing cipher key
+	#[serde(rename = "outCiphertext")]
+	pub out_ciphertext: Bytes,
+	/// Zero-knowledge proof
+	pub proof: Bytes,
+}
+
 /// Transaction
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct Transaction {
@@ -116,22 +185,54 @@ pub struct Transaction {
 	pub hash: H256,
 	/// The serialized transaction size
 	pub size: usize,
+	/// Is this an overwintered transaction
+	pub overwintered: bool,
 	/// The version
 	pub version: i32,
+	/// The version group id (overwintered transactions only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub versiongroupid: Option<u32>,
 	/// The lock time
 	pub locktime: i32,
+	/// The block height after which transaction expires (overwintered transactions only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub expiryheight: Option<u32>,
 	/// Transaction inputs
 	pub vin: Vec<SignedTransactionInput>,
 	/// Transaction outputs
 	pub vout: Vec<SignedTransactionOutput>,
-	/// Hash of the block this transaction is included in
-	pub blockhash: H256,
-	/// Number of confirmations of this transaction
-	pub confirmations: u32,
-	/// The transaction time in seconds since epoch (Jan 1 1970 GMT)
-	pub time: u32,
-	/// The block time in seconds since epoch (Jan 1 1970 GMT)
-	pub blocktime: u32,
+	/// JoinSplit descriptions
+	pub vjoinsplit: Vec<TransactionJoinSplit>,
+	/// JoinSplit public key (transactions with JoinSplits only)
+	#[serde(rename = "joinSplitPubKey", skip_serializing_if = "Option::is_none")]
+	pub join_split_pub_key: Option<H256>,
+	/// JoinSplit signature (transactions with JoinSplits only)
+	#[serde(rename = "joinSplitSig", skip_serializing_if = "Option::is_none")]
+	pub join_split_sig: Option<Bytes>,
+	/// Net value of Sapling spends minus outputs, in ZEC (Sapling transactions only)
+	#[serde(rename = "valueBalance", skip_serializing_if = "Option::is_none")]
+	pub value_balance: Option<f64>,
+	/// Sapling spend descriptions
+	#[serde(rename = "vShieldedSpend")]
+	pub shielded_spends: Vec<TransactionShieldedSpend>,
+	/// Sapling output descriptions
+	#[serde(rename = "vShieldedOutput")]
+	pub shielded_outputs: Vec<TransactionShieldedOutput>,
+	/// Sapling binding signature (Sapling transactions only)
+	#[serde(rename = "bindingSig", skip_serializing_if = "Option::is_none")]
+	pub binding_sig: Option<Bytes>,
+	/// Hash of the block this transaction is included in (mined transactions only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub blockhash: Option<H256>,
+	/// Number of confirmations of this transaction (mined transactions only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub confirmations: Option<u32>,
+	/// The transaction time in seconds since epoch (Jan 1 1970 GMT) (mined transactions only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub time: Option<u32>,
+	/// The block time in seconds since epoch (Jan 1 1970 GMT) (mined transactions only)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub blocktime: Option<u32>,
 }
 
 /// Return value of `getrawtransaction` method
@@ -152,6 +253,138 @@ impl Serialize for GetRawTransactionResponse {
 	}
 }
 
+impl Transaction {
+	/// Creates verbose transaction from the raw one. Hashes are reversed (as every other
+	/// hash in RPC) and block-related fields are left empty.
+	pub fn from_raw(transaction: chain::Transaction, network: keys::Network) -> Self {
+		let raw = serialize(&transaction);
+		let hash: H256 = transaction.hash().reversed().into();
+
+		let vin = transaction.inputs.iter()
+			.map(|input| {
+				let script: Script = input.script_sig.clone().into();
+				SignedTransactionInput {
+					txid: input.previous_output.hash.reversed().into(),
+					vout: input.previous_output.index,
+					script_sig: TransactionInputScript {
+						asm: format!("{}", script),
+						hex: input.script_sig.clone().into(),
+					},
+					sequence: input.sequence,
+				}
+			})
+			.collect();
+
+		let vout = transaction.outputs.iter()
+			.enumerate()
+			.map(|(n, output)| {
+				let script: Script = output.script_pubkey.clone().into();
+				let script_addresses = script.extract_destinations().unwrap_or(vec![]);
+				SignedTransactionOutput {
+					value: output.value as f64 / SATOSHIS_IN_COIN as f64,
+					n: n as u32,
+					script: TransactionOutputScript {
+						asm: format!("{}", script),
+						hex: output.script_pubkey.clone().into(),
+						req_sigs: script.num_signatures_required() as u32,
+						script_type: script.script_type().into(),
+						addresses: script_addresses.into_iter().map(|a| Address {
+							network: network,
+							hash: a.hash,
+							kind: a.kind,
+						}).collect(),
+					},
+				}
+			})
+			.collect();
+
+		let join_split = transaction.join_split.as_ref();
+		let sapling = transaction.sapling.as_ref();
+
+		Transaction {
+			size: raw.len(),
+			hex: raw.into(),
+			txid: hash.clone(),
+			hash: hash,
+			overwintered: transaction.overwintered,
+			version: transaction.version,
+			versiongroupid: if transaction.overwintered { Some(transaction.version_group_id) } else { None },
+			locktime: transaction.lock_time as i32,
+			expiryheight: if transaction.overwintered { Some(transaction.expiry_height) } else { None },
+			vin: vin,
+			vout: vout,
+			vjoinsplit: join_split
+				.map(|join_split| join_split.descriptions.iter().map(Into::into).collect())
+				.unwrap_or_default(),
+			join_split_pub_key: join_split.map(|join_split| join_split.pubkey.clone().into()),
+			join_split_sig: join_split.map(|join_split| Bytes::new(join_split.sig.to_vec())),
+			value_balance: sapling.map(|sapling| sapling.balancing_value as f64 / SATOSHIS_IN_COIN as f64),
+			shielded_spends: sapling
+				.map(|sapling| sapling.spends.iter().map(Into::into).collect())
+				.unwrap_or_default(),
+			shielded_outputs: sapling
+				.map(|sapling| sapling.outputs.iter().map(Into::into).collect())
+				.unwrap_or_default(),
+			binding_sig: sapling.map(|sapling| Bytes::new(sapling.binding_sig.to_vec())),
+			blockhash: None,
+			confirmations: None,
+			time: None,
+			blocktime: None,
+		}
+	}
+}
+
+impl<'a> From<&'a chain::JoinSplitDescription> for TransactionJoinSplit {
+	fn from(description: &'a chain::JoinSplitDescription) -> Self {
+		let proof = match description.zkproof {
+			chain::JoinSplitProof::PHGR(ref proof) => proof.to_vec(),
+			chain::JoinSplitProof::Groth(ref proof) => {
+				let proof: &[u8; 192] = proof.into();
+				proof.to_vec()
+			},
+		};
+
+		TransactionJoinSplit {
+			vpub_old: description.value_pub_old as f64 / SATOSHIS_IN_COIN as f64,
+			vpub_new: description.value_pub_new as f64 / SATOSHIS_IN_COIN as f64,
+			anchor: description.anchor.into(),
+			nullifiers: description.nullifiers.iter().cloned().map(Into::into).collect(),
+			commitments: description.commitments.iter().cloned().map(Into::into).collect(),
+			onetime_pub_key: description.ephemeral_key.into(),
+			random_seed: description.random_seed.into(),
+			macs: description.macs.iter().cloned().map(Into::into).collect(),
+			proof: Bytes::new(proof),
+			ciphertexts: description.ciphertexts.iter().map(|c| Bytes::new(c.to_vec())).collect(),
+		}
+	}
+}
+
+impl<'a> From<&'a chain::SaplingSpendDescription> for TransactionShieldedSpend {
+	fn from(spend: &'a chain::SaplingSpendDescription) -> Self {
+		TransactionShieldedSpend {
+			cv: spend.value_commitment.into(),
+			anchor: spend.anchor.into(),
+			nullifier: spend.nullifier.into(),
+			rk: spend.randomized_key.into(),
+			proof: Bytes::new(spend.zkproof.to_vec()),
+			spend_auth_sig: Bytes::new(spend.spend_auth_sig.to_vec()),
+		}
+	}
+}
+
+impl<'a> From<&'a chain::SaplingOutputDescription> for TransactionShieldedOutput {
+	fn from(output: &'a chain::SaplingOutputDescription) -> Self {
+		TransactionShieldedOutput {
+			cv: output.value_commitment.into(),
+			cmu: output.note_commitment.into(),
+			ephemeral_key: output.ephemeral_key.into(),
+			enc_ciphertext: Bytes::new(output.enc_cipher_text.to_vec()),
+			out_ciphertext: Bytes::new(output.out_cipher_text.to_vec()),
+			proof: Bytes::new(output.zkproof.to_vec()),
+		}
+	}
+}
+
 impl TransactionOutputs {
 	pub fn len(&self) -> usize {
 		self.outputs.len()
@@ -414,16 +647,26 @@ mod tests {
 			txid: H256::from(4),
 			hash: H256::from(5),
 			size: 33,
+			overwintered: false,
 			version: 55,
+			versiongroupid: None,
 			locktime: 66,
+			expiryheight: None,
 			vin: vec![],
 			vout: vec![],
-			blockhash: H256::from(6),
-			confirmations: 77,
-			time: 88,
-			blocktime: 99,
+			vjoinsplit: vec![],
+			join_split_pub_key: None,
+			join_split_sig: None,
+			value_balance: None,
+			shielded_spends: vec![],
+			shielded_outputs: vec![],
+			binding_sig: None,
+			blockhash: Some(H256::from(6)),
+			confirmations: Some(77),
+			time: Some(88),
+			blocktime: Some(99),
 		};
-		assert_eq!(serde_json::to_string(&tx).unwrap(), r#"{"hex":"deadbeef","txid":"0400000000000000000000000000000000000000000000000000000000000000","hash":"0500000000000000000000000000000000000000000000000000000000000000","size":33,"version":55,"locktime":66,"vin":[],"vout":[],"blockhash":"0600000000000000000000000000000000000000000000000000000000000000","confirmations":77,"time":88,"blocktime":99}"#);
+		assert_eq!(serde_json::to_string(&tx).unwrap(), r#"{"hex":"deadbeef","txid":"0400000000000000000000000000000000000000000000000000000000000000","hash":"0500000000000000000000000000000000000000000000000000000000000000","size":33,"overwintered":false,"version":55,"locktime":66,"vin":[],"vout":[],"vjoinsplit":[],"vShieldedSpend":[],"vShieldedOutput":[],"blockhash":"0600000000000000000000000000000000000000000000000000000000000000","confirmations":77,"time":88,"blocktime":99}"#);
 	}
 
 	#[test]
@@ -433,17 +676,96 @@ mod tests {
 			txid: H256::from(4),
 			hash: H256::from(5),
 			size: 33,
+			overwintered: false,
 			version: 55,
+			versiongroupid: None,
 			locktime: 66,
+			expiryheight: None,
 			vin: vec![],
 			vout: vec![],
-			blockhash: H256::from(6),
-			confirmations: 77,
-			time: 88,
-			blocktime: 99,
+			vjoinsplit: vec![],
+			join_split_pub_key: None,
+			join_split_sig: None,
+			value_balance: None,
+			shielded_spends: vec![],
+			shielded_outputs: vec![],
+			binding_sig: None,
+			blockhash: Some(H256::from(6)),
+			confirmations: Some(77),
+			time: Some(88),
+			blocktime: Some(99),
 		};
 		assert_eq!(
-			serde_json::from_str::<Transaction>(r#"{"hex":"deadbeef","txid":"0400000000000000000000000000000000000000000000000000000000000000","hash":"0500000000000000000000000000000000000000000000000000000000000000","size":33,"version":55,"locktime":66,"vin":[],"vout":[],"blockhash":"0600000000000000000000000000000000000000000000000000000000000000","confirmations":77,"time":88,"blocktime":99}"#).unwrap(),
+			serde_json::from_str::<Transaction>(r#"{"hex":"deadbeef","txid":"0400000000000000000000000000000000000000000000000000000000000000","hash":"0500000000000000000000000000000000000000000000000000000000000000","size":33,"overwintered":false,"version":55,"locktime":66,"vin":[],"vout":[],"vjoinsplit":[],"vShieldedSpend":[],"vShieldedOutput":[],"blockhash":"0600000000000000000000000000000000000000000000000000000000000000","confirmations":77,"time":88,"blocktime":99}"#).unwrap(),
 			tx);
 	}
+
+	fn json_fields<T: ::serde::Serialize>(value: &T) -> Vec<String> {
+		match serde_json::to_value(value).unwrap() {
+			serde_json::Value::Object(map) => map.keys().cloned().collect(),
+			_ => panic!("value is serialized as object"),
+		}
+	}
+
+	fn sorted(mut fields: Vec<&str>) -> Vec<&str> {
+		fields.sort();
+		fields
+	}
+
+	#[test]
+	fn transaction_schema() {
+		let spend = chain::SaplingSpendDescription {
+			value_commitment: [1; 32],
+			anchor: [2; 32],
+			nullifier: [3; 32],
+			randomized_key: [4; 32],
+			zkproof: [5; 192],
+			spend_auth_sig: [6; 64],
+		};
+		let output = chain::SaplingOutputDescription {
+			value_commitment: [1; 32],
+			note_commitment: [2; 32],
+			ephemeral_key: [3; 32],
+			enc_cipher_text: [4; 580],
+			out_cipher_text: [5; 80],
+			zkproof: [6; 192],
+		};
+		let join_split = chain::JoinSplitDescription::default();
+
+		let tx = Transaction {
+			hex: "DEADBEEF".into(),
+			txid: H256::from(4),
+			hash: H256::from(5),
+			size: 33,
+			overwintered: true,
+			version: 4,
+			versiongroupid: Some(0x892f2085),
+			locktime: 0,
+			expiryheight: Some(100),
+			vin: vec![],
+			vout: vec![],
+			vjoinsplit: vec![TransactionJoinSplit::from(&join_split)],
+			join_split_pub_key: Some(H256::from(7)),
+			join_split_sig: Some("00".into()),
+			value_balance: Some(0.5),
+			shielded_spends: vec![TransactionShieldedSpend::from(&spend)],
+			shielded_outputs: vec![TransactionShieldedOutput::from(&output)],
+			binding_sig: Some("00".into()),
+			blockhash: Some(H256::from(6)),
+			confirmations: Some(77),
+			time: Some(88),
+			blocktime: Some(99),
+		};
+
+		assert_eq!(json_fields(&tx), sorted(vec!["hex", "txid", "hash", "size", "overwintered", "version", "versiongroupid",
+			"locktime", "expiryheight", "vin", "vout", "vjoinsplit", "joinSplitPubKey", "joinSplitSig", "valueBalance",
+			"vShieldedSpend", "vShieldedOutput", "bindingSig", "blockhash", "confirmations", "time", "blocktime"]));
+		assert_eq!(json_fields(&tx.vjoinsplit[0]), sorted(vec!["vpub_old", "vpub_new", "anchor", "nullifiers", "commitments",
+			"onetimePubKey", "randomSeed", "macs", "proof", "ciphertexts"]));
+		assert_eq!(json_fields(&tx.shielded_spends[0]), sorted(vec!["cv", "anchor", "nullifier", "rk", "proof", "spendAuthSig"]));
+		assert_eq!(json_fields(&tx.shielded_outputs[0]), sorted(vec!["cv", "cmu", "ephemeralKey", "encCiphertext", "outCiphertext", "proof"]));
+
+		assert_eq!(tx.shielded_spends[0].nullifier, H256::from([3; 32]));
+		assert_eq!(tx.shielded_outputs[0].enc_ciphertext.len(), 580);
+	}
 }
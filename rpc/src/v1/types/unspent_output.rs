@@ -0,0 +1,63 @@
+use keys::Address;
+use v1::types;
+use super::bytes::Bytes;
+use super::hash::H256;
+
+/// listunspent response item
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct UnspentOutput {
+	/// Hash of the transaction, containing this output
+	pub txid: H256,
+	/// Index of this output in the transaction
+	pub vout: u32,
+	/// Address this output is paying to
+	#[serde(with = "types::address")]
+	pub address: Address,
+	/// Output script
+	#[serde(rename = "scriptPubKey")]
+	pub script: Bytes,
+	/// Output value in ZEC
+	pub amount: f64,
+	/// Number of confirmations of the transaction
+	pub confirmations: u32,
+	/// Is this an output of coinbase transaction?
+	pub generated: bool,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::super::bytes::Bytes;
+	use super::super::hash::H256;
+	use super::*;
+
+	#[test]
+	fn unspent_output_serialize() {
+		let output = UnspentOutput {
+			txid: H256::from(0x56),
+			vout: 1,
+			address: "t1KstPVzcNEK4ZeauQ6cogoqxQBMDSiRnGr".into(),
+			script: Bytes::new(vec![1, 2, 3, 4]),
+			amount: 0.0005,
+			confirmations: 77,
+			generated: true,
+		};
+		assert_eq!(serde_json::to_string(&output).unwrap(), r#"{"txid":"5600000000000000000000000000000000000000000000000000000000000000","vout":1,"address":"t1KstPVzcNEK4ZeauQ6cogoqxQBMDSiRnGr","scriptPubKey":"01020304","amount":0.0005,"confirmations":77,"generated":true}"#);
+	}
+
+	#[test]
+	fn unspent_output_deserialize() {
+		let output = UnspentOutput {
+			txid: H256::from(0x56),
+			vout: 1,
+			address: "t1KstPVzcNEK4ZeauQ6cogoqxQBMDSiRnGr".into(),
+			script: Bytes::new(vec![1, 2, 3, 4]),
+			amount: 0.0005,
+			confirmations: 77,
+			generated: true,
+		};
+		assert_eq!(
+			serde_json::from_str::<UnspentOutput>(r#"{"txid":"5600000000000000000000000000000000000000000000000000000000000000","vout":1,"address":"t1KstPVzcNEK4ZeauQ6cogoqxQBMDSiRnGr","scriptPubKey":"01020304","amount":0.0005,"confirmations":77,"generated":true}"#).unwrap(),
+			output);
+	}
+}
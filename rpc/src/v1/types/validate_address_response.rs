@@ -0,0 +1,59 @@
+use super::bytes::Bytes;
+use super::hash::H256;
+
+/// Kind of the address, validated by z_validateaddress
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidatedAddressType {
+	/// Sprout shielded address
+	Sprout,
+	/// Sapling shielded address
+	Sapling,
+	/// Transparent pay-to-pubkey-hash address
+	P2pkh,
+	/// Transparent pay-to-script-hash address
+	P2sh,
+}
+
+/// Response to z_validateaddress RPC request
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct ValidateAddressResponse {
+	/// Is the address valid for this network?
+	pub isvalid: bool,
+	/// The validated address
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub address: Option<String>,
+	/// Kind of the address
+	#[serde(rename = "type")]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub address_type: Option<ValidatedAddressType>,
+	/// Sprout paying key
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub payingkey: Option<H256>,
+	/// Sprout transmission key
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub transmissionkey: Option<H256>,
+	/// Sapling diversifier
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub diversifier: Option<Bytes>,
+	/// Sapling diversified transmission key
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub diversifiedtransmissionkey: Option<H256>,
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json;
+	use super::*;
+
+	#[test]
+	fn validate_address_response_serialize() {
+		assert_eq!(serde_json::to_string(&ValidateAddressResponse::default()).unwrap(), r#"{"isvalid":false}"#);
+		assert_eq!(serde_json::to_string(&ValidateAddressResponse {
+			isvalid: true,
+			address: Some("t3Vz22vK5z2LcKEdg16Yv4FFneEL1zg9ojd".into()),
+			address_type: Some(ValidatedAddressType::P2sh),
+			..Default::default()
+		}).unwrap(), r#"{"isvalid":true,"address":"t3Vz22vK5z2LcKEdg16Yv4FFneEL1zg9ojd","type":"p2sh"}"#);
+	}
+}
@@ -2,7 +2,7 @@ use std::{cmp, mem};
 use bytes::Bytes;
 use keys::{Signature, Public};
 use chain::constants::SEQUENCE_LOCKTIME_DISABLE_FLAG;
-use crypto::{sha1, sha256, dhash160, dhash256, ripemd160};
+use crypto::{sha1, sha256, hash160, dhash256, ripemd160};
 use sign::Sighash;
 use {
 	script, Script, Num, VerificationFlags, Opcode, Error, SignatureChecker, Stack
@@ -754,7 +754,7 @@ pub fn eval_script(
 				stack.push(v.to_vec().into());
 			},
 			Opcode::OP_HASH160 => {
-				let v = dhash160(&stack.pop()?);
+				let v = hash160(&stack.pop()?);
 				stack.push(v.to_vec().into());
 			},
 			Opcode::OP_HASH256 => {
@@ -873,9 +873,10 @@ pub fn eval_script(
 mod tests {
 	use bytes::Bytes;
 	use chain::Transaction;
+	use chain::constants::{SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_TYPE_FLAG, LOCKTIME_THRESHOLD};
 	use {
 		Opcode, Script, VerificationFlags, Builder, Error, Num, TransactionInputSigner,
-		NoopSignatureChecker, TransactionSignatureChecker, Stack
+		NoopSignatureChecker, TransactionSignatureChecker, Stack, UnsignedTransactionInput,
 	};
 	use super::{eval_script, verify_script, is_public_key};
 
@@ -1818,7 +1819,7 @@ mod tests {
 		let tx: Transaction = "0100000001484d40d45b9ea0d652fca8258ab7caa42541eb52975857f96fb50cd732c8b481000000008a47304402202cb265bf10707bf49346c3515dd3d16fc454618c58ec0a0ff448a676c54ff71302206c6624d762a1fcef4618284ead8f08678ac05b13c84235f1654e6ad168233e8201410414e301b2328f17442c0b8310d787bf3d8a404cfbd0704f135b6ad4b2d3ee751310f981926e53a6e8c39bd7d3fefd576c543cce493cbac06388f2651d1aacbfcdffffffff0162640100000000001976a914c8e90996c7c6080ee06284600c684ed904d14c5c88ac00000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let mut checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: signer.into(),
 			input_index: 0,
 			input_amount: 0,
 			consensus_branch_id: 0,
@@ -1837,7 +1838,7 @@ mod tests {
 		let tx: Transaction = "01000000013dcd7d87904c9cb7f4b79f36b5a03f96e2e729284c09856238d5353e1182b00200000000fd5e0100483045022100deeb1f13b5927b5e32d877f3c42a4b028e2e0ce5010fdb4e7f7b5e2921c1dcd2022068631cb285e8c1be9f061d2968a18c3163b780656f30a049effee640e80d9bff01483045022100ee80e164622c64507d243bd949217d666d8b16486e153ac6a1f8e04c351b71a502203691bef46236ca2b4f5e60a82a853a33d6712d6a1e7bf9a65e575aeb7328db8c014cc9524104a882d414e478039cd5b52a92ffb13dd5e6bd4515497439dffd691a0f12af9575fa349b5694ed3155b136f09e63975a1700c9f4d4df849323dac06cf3bd6458cd41046ce31db9bdd543e72fe3039a1f1c047dab87037c36a669ff90e28da1848f640de68c2fe913d363a51154a0c62d7adea1b822d05035077418267b1a1379790187410411ffd36c70776538d079fbae117dc38effafb33304af83ce4894589747aee1ef992f63280567f52f5ba870678b4ab4ff6c8ea600bd217870a8b4f1f09f3a8e8353aeffffffff0130d90000000000001976a914569076ba39fc4ff6a2291d9ea9196d8c08f9c7ab88ac00000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let mut checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: signer.into(),
 			input_index: 0,
 			input_amount: 0,
 			consensus_branch_id: 0,
@@ -1856,7 +1857,7 @@ mod tests {
 		let tx: Transaction = "010000000173805864da01f15093f7837607ab8be7c3705e29a9d4a12c9116d709f8911e590100000049483045022052ffc1929a2d8bd365c6a2a4e3421711b4b1e1b8781698ca9075807b4227abcb0221009984107ddb9e3813782b095d0d84361ed4c76e5edaf6561d252ae162c2341cfb01ffffffff0200e1f50500000000434104baa9d36653155627c740b3409a734d4eaf5dcca9fb4f736622ee18efcf0aec2b758b2ec40db18fbae708f691edb2d4a2a3775eb413d16e2e3c0f8d4c69119fd1ac009ce4a60000000043410411db93e1dcdb8a016b49840f8c53bc1eb68a382e97b1482ecad7b148a6909a5cb2e0eaddfb84ccf9744464f82e160bfa9b8b64f9d4c03f999b8643f656b412a3ac00000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let mut checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: signer.into(),
 			input_index: 0,
 			input_amount: 0,
 			consensus_branch_id: 0,
@@ -1875,7 +1876,7 @@ mod tests {
 		let tx: Transaction = "01000000012316aac445c13ff31af5f3d1e2cebcada83e54ba10d15e01f49ec28bddc285aa000000008e4b3048022200002b83d59c1d23c08efd82ee0662fec23309c3adbcbd1f0b8695378db4b14e736602220000334a96676e58b1bb01784cb7c556dd8ce1c220171904da22e18fe1e7d1510db5014104d0fe07ff74c9ef5b00fed1104fad43ecf72dbab9e60733e4f56eacf24b20cf3b8cd945bcabcc73ba0158bf9ce769d43e94bd58c5c7e331a188922b3fe9ca1f5affffffff01c0c62d00000000001976a9147a2a3b481ca80c4ba7939c54d9278e50189d94f988ac00000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let mut checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: signer.into(),
 			input_index: 0,
 			input_amount: 0,
 			consensus_branch_id: 0,
@@ -1894,7 +1895,7 @@ mod tests {
 		let tx: Transaction = "01000000010c0e314bd7bb14721b3cfd8e487cd6866173354f87ca2cf4d13c8d3feb4301a6000000004a483045022100d92e4b61452d91a473a43cde4b469a472467c0ba0cbd5ebba0834e4f4762810402204802b76b7783db57ac1f61d2992799810e173e91055938750815b6d8a675902e014fffffffff0140548900000000001976a914a86e8ee2a05a44613904e18132e49b2448adc4e688ac00000000".into();
 		let signer: TransactionInputSigner = tx.into();
 		let mut checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: signer.into(),
 			input_index: 0,
 			input_amount: 0,
 			consensus_branch_id: 0,
@@ -1940,4 +1941,107 @@ mod tests {
 		let result = Ok(true);
 		basic_test(&script, result, vec![vec![1].into()].into());
 	}
+
+	fn lock_time_test(opcode: Opcode, version: i32, tx_lock_time: u32, tx_sequence: u32, script_lock_time: i64) -> Result<bool, Error> {
+		let signer = TransactionInputSigner {
+			overwintered: false,
+			version: version,
+			version_group_id: 0,
+			inputs: vec![UnsignedTransactionInput {
+				previous_output: Default::default(),
+				sequence: tx_sequence,
+			}],
+			outputs: vec![],
+			lock_time: tx_lock_time,
+			expiry_height: 0,
+			join_split: None,
+			sapling: None,
+		};
+		let mut checker = TransactionSignatureChecker {
+			signer: signer.into(),
+			input_index: 0,
+			input_amount: 0,
+			consensus_branch_id: 0,
+			cache: Default::default(),
+		};
+		let script = Builder::default()
+			.push_num(script_lock_time.into())
+			.push_opcode(opcode)
+			.into_script();
+		let flags = VerificationFlags::default()
+			.verify_locktime(true)
+			.verify_checksequence(true);
+		eval_script(&mut Stack::new(), &script, &flags, &mut checker)
+	}
+
+	#[test]
+	fn test_checklocktimeverify_height() {
+		let cltv = |tx_lock_time, tx_sequence, script_lock_time|
+			lock_time_test(Opcode::OP_CHECKLOCKTIMEVERIFY, 1, tx_lock_time, tx_sequence, script_lock_time);
+
+		assert_eq!(cltv(100, 0, 99), Ok(true));
+		assert_eq!(cltv(100, 0, 100), Ok(true));
+		assert_eq!(cltv(100, 0, 101), Err(Error::UnsatisfiedLocktime));
+		assert_eq!(cltv(100, 0, -1), Err(Error::NegativeLocktime));
+		// lock time is ignored if input is final
+		assert_eq!(cltv(100, SEQUENCE_FINAL, 100), Err(Error::UnsatisfiedLocktime));
+		// height can't be compared to time
+		let last_height = LOCKTIME_THRESHOLD - 1;
+		assert_eq!(cltv(last_height, 0, last_height as i64), Ok(true));
+		assert_eq!(cltv(last_height, 0, LOCKTIME_THRESHOLD as i64), Err(Error::UnsatisfiedLocktime));
+	}
+
+	#[test]
+	fn test_checklocktimeverify_time() {
+		let cltv = |tx_lock_time, tx_sequence, script_lock_time|
+			lock_time_test(Opcode::OP_CHECKLOCKTIMEVERIFY, 1, tx_lock_time, tx_sequence, script_lock_time);
+
+		let time = LOCKTIME_THRESHOLD + 100;
+		assert_eq!(cltv(time, 0, LOCKTIME_THRESHOLD as i64), Ok(true));
+		assert_eq!(cltv(time, 0, time as i64 - 1), Ok(true));
+		assert_eq!(cltv(time, 0, time as i64), Ok(true));
+		assert_eq!(cltv(time, 0, time as i64 + 1), Err(Error::UnsatisfiedLocktime));
+		assert_eq!(cltv(time, SEQUENCE_FINAL, time as i64), Err(Error::UnsatisfiedLocktime));
+		// time can't be compared to height
+		assert_eq!(cltv(time, 0, 100), Err(Error::UnsatisfiedLocktime));
+		// 5-byte numbers are allowed, but they're always greater than u32 lock time
+		assert_eq!(cltv(::std::u32::MAX, 0, ::std::u32::MAX as i64), Ok(true));
+		assert_eq!(cltv(::std::u32::MAX, 0, ::std::u32::MAX as i64 + 1), Err(Error::UnsatisfiedLocktime));
+	}
+
+	#[test]
+	fn test_checksequenceverify_height() {
+		let csv = |version, tx_sequence, script_sequence|
+			lock_time_test(Opcode::OP_CHECKSEQUENCEVERIFY, version, 0, tx_sequence, script_sequence);
+
+		assert_eq!(csv(2, 10, 9), Ok(true));
+		assert_eq!(csv(2, 10, 10), Ok(true));
+		assert_eq!(csv(2, 10, 11), Err(Error::UnsatisfiedLocktime));
+		assert_eq!(csv(2, 10, -1), Err(Error::NegativeLocktime));
+		// relative lock times are only enforced for version 2+ transactions
+		assert_eq!(csv(1, 10, 10), Err(Error::UnsatisfiedLocktime));
+		// relative lock time of input is disabled
+		assert_eq!(csv(2, SEQUENCE_LOCKTIME_DISABLE_FLAG | 10, 10), Err(Error::UnsatisfiedLocktime));
+		// relative lock time of script is disabled => CHECKSEQUENCEVERIFY behaves as NOP
+		assert_eq!(csv(1, 0, SEQUENCE_LOCKTIME_DISABLE_FLAG as i64 | 11), Ok(true));
+		// bits, which have no consensus-enforced meaning, are ignored
+		assert_eq!(csv(2, 10 | (1 << 16), 10 | (1 << 17)), Ok(true));
+		// height can't be compared to time
+		assert_eq!(csv(2, 10, SEQUENCE_LOCKTIME_TYPE_FLAG as i64 | 10), Err(Error::UnsatisfiedLocktime));
+	}
+
+	#[test]
+	fn test_checksequenceverify_time() {
+		let csv = |version, tx_sequence, script_sequence|
+			lock_time_test(Opcode::OP_CHECKSEQUENCEVERIFY, version, 0, tx_sequence, script_sequence);
+
+		let sequence = SEQUENCE_LOCKTIME_TYPE_FLAG | 10;
+		assert_eq!(csv(2, sequence, SEQUENCE_LOCKTIME_TYPE_FLAG as i64), Ok(true));
+		assert_eq!(csv(2, sequence, sequence as i64 - 1), Ok(true));
+		assert_eq!(csv(2, sequence, sequence as i64), Ok(true));
+		assert_eq!(csv(2, sequence, sequence as i64 + 1), Err(Error::UnsatisfiedLocktime));
+		assert_eq!(csv(1, sequence, sequence as i64), Err(Error::UnsatisfiedLocktime));
+		// time can't be compared to height
+		assert_eq!(csv(2, sequence, 10), Err(Error::UnsatisfiedLocktime));
+	}
 }
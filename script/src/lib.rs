@@ -31,7 +31,7 @@ pub use self::interpreter::{eval_script, verify_script};
 pub use self::opcode::Opcode;
 pub use self::num::Num;
 pub use self::script::{Script, ScriptType, ScriptAddress};
-pub use self::sign::{SighashBase, SighashCache, TransactionInputSigner, UnsignedTransactionInput};
+pub use self::sign::{SighashBase, SighashCache, TransactionInputSigner, UnsignedTransactionInput, signature_hash_sapling};
 pub use self::stack::Stack;
 pub use self::verify::{SignatureChecker, NoopSignatureChecker, TransactionSignatureChecker};
 
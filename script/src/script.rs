@@ -2,6 +2,7 @@
 
 use std::{fmt, ops};
 use bytes::Bytes;
+use crypto::hash160;
 use keys::{self, AddressHash, Public};
 use {Opcode, Error};
 
@@ -102,6 +103,11 @@ impl Script {
 		self.data.len() == 0
 	}
 
+	/// Hash of the script, used in P2SH output, redeeming this script.
+	pub fn script_hash(&self) -> AddressHash {
+		hash160(&self.data)
+	}
+
 	/// Extra-fast test for pay-to-public-key-hash (P2PKH) scripts.
 	pub fn is_pay_to_public_key_hash(&self) -> bool {
 		self.data.len() == 25 &&
@@ -491,6 +497,16 @@ mod tests {
 	use super::{Script, ScriptType, ScriptAddress, MAX_SCRIPT_ELEMENT_SIZE};
 	use keys::{Address, Public};
 
+	#[test]
+	fn test_script_hash() {
+		// 1-of-1 multisig redeem script
+		let script: Script = "51210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f8179851ae".into();
+		assert_eq!(script.script_hash(), "83eebb7d79aa1d388e3b0ac65b98ac580c4da01a".into());
+
+		let address: Address = "t3WbDSRcGGtYfk4vkcxfCEXbDFCpVZxhxKh".into();
+		assert_eq!(script.script_hash(), address.hash);
+	}
+
 	#[test]
 	fn test_is_pay_to_script_hash() {
 		let script: Script = "a9143b80842f4ea32806ce5e723a255ddd6490cfd28d87".into();
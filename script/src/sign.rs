@@ -24,7 +24,7 @@ impl From<SighashBase> for u32 {
 }
 
 /// Signature portions cache.
-#[derive(Debug, Default, PartialEq)]
+#[derive(Debug, Default, PartialEq, Clone)]
 pub struct SighashCache {
 	pub hash_prevouts: Option<H256>,
 	pub hash_sequence: Option<H256>,
@@ -148,6 +148,27 @@ impl From<Transaction> for TransactionInputSigner {
 }
 
 impl TransactionInputSigner {
+	/// Precomputes signature portions, which are shared by signatures of all transaction inputs.
+	pub fn sighash_cache(&self) -> SighashCache {
+		let signature_version = self.signature_version();
+		if signature_version == SignatureVersion::Sprout {
+			return SighashCache::default();
+		}
+
+		let empty = SighashCache::default();
+		let sighash = Sighash::new(SighashBase::All, false, false);
+		let sapling = signature_version == SignatureVersion::Sapling;
+		let cached = |(hash, cache): (H256, bool)| if cache { Some(hash) } else { None };
+		SighashCache {
+			hash_prevouts: cached(compute_hash_prevouts(&empty, sighash, &self.inputs)),
+			hash_sequence: cached(compute_hash_sequence(&empty, sighash, &self.inputs)),
+			hash_outputs: cached(compute_hash_outputs(&empty, sighash, None, &self.outputs)),
+			hash_join_split: cached(compute_hash_join_split(&empty, self.join_split.as_ref())),
+			hash_sapling_spends: cached(compute_hash_sapling_spends(&empty, sapling, self.sapling.as_ref())),
+			hash_sapling_outputs: cached(compute_hash_sapling_outputs(&empty, sapling, self.sapling.as_ref())),
+		}
+	}
+
 	/// Pass None as input_index to compute transparent input signature
 	pub fn signature_hash(
 		&self,
@@ -341,6 +362,29 @@ impl TransactionInputSigner {
 	}
 }
 
+/// Computes ZIP-243 signature hash of the Sapling (v4) transaction. Pass None as `input_index`
+/// to compute the hash, which is signed by JoinSplit and Sapling signatures.
+pub fn signature_hash_sapling(
+	tx: &Transaction,
+	input_index: Option<usize>,
+	script_code: &Script,
+	amount: u64,
+	sighashtype: u32,
+	consensus_branch_id: u32,
+) -> H256 {
+	let signer: TransactionInputSigner = tx.clone().into();
+	signer.signature_hash_post_overwinter(
+		&mut Default::default(),
+		input_index,
+		amount,
+		script_code,
+		sighashtype,
+		Sighash::from_u32(sighashtype),
+		consensus_branch_id,
+		true,
+	)
+}
+
 fn compute_hash_prevouts(
 	cache: &SighashCache,
 	sighash: Sighash,
@@ -483,7 +527,8 @@ mod tests {
 	use chain::{OutPoint, TransactionOutput, Transaction};
 	use script::Script;
 	use ser::deserialize;
-	use super::{Sighash, UnsignedTransactionInput, TransactionInputSigner, SighashBase};
+	use super::{Sighash, SighashCache, UnsignedTransactionInput, TransactionInputSigner, SighashBase, SignatureVersion,
+		signature_hash_sapling};
 	use {verify_script, VerificationFlags, TransactionSignatureChecker};
 
 	#[test]
@@ -590,6 +635,79 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn test_signature_hash_sapling() {
+		let tests = include_bytes!("../data/sighash_tests.json");
+		let tests: Vec<Value> = from_slice(tests).unwrap();
+		let mut checked_sighashes = Vec::new();
+		for test in tests.iter().skip(1) {
+			let tx: Transaction = deserialize(&test[0].as_str().unwrap().from_hex::<Vec<u8>>().unwrap() as &[u8]).unwrap();
+			let signer: TransactionInputSigner = tx.clone().into();
+			if signer.signature_version() != SignatureVersion::Sapling {
+				continue;
+			}
+
+			let script: Script = Script::new(test[1].as_str().unwrap().parse().unwrap());
+			let input_index = test[2].as_u64().unwrap();
+			let input_index = if input_index == ::std::u64::MAX { None } else { Some(input_index as usize) };
+			let hash_type = test[3].as_i64().unwrap() as u32;
+			let consensus_branch_id = test[4].as_u64().unwrap() as u32;
+			let expected: H256 = test[5].as_str().unwrap().parse().unwrap();
+
+			let hash = signature_hash_sapling(&tx, input_index, &script, 0, hash_type, consensus_branch_id);
+			assert_eq!(hash, expected.reversed());
+
+			let sighash = Sighash::from_u32(hash_type);
+			if !checked_sighashes.contains(&sighash) {
+				checked_sighashes.push(sighash);
+			}
+		}
+
+		// official vectors have no SIGHASH_SINGLE | SIGHASH_ANYONECANPAY cases for Sapling transactions
+		// => these are computed by independent ZIP-243 implementation (verified against all vectors above)
+		let tx: Transaction = deserialize(&tests[24][0].as_str().unwrap().from_hex::<Vec<u8>>().unwrap() as &[u8]).unwrap();
+		let script: Script = "76a914df3bd30160e6c6145baaf2c88a8844c13a00d1d588ac".into();
+		let sapling_single_anyone_can_pay: [(usize, &str); 2] = [
+			(1, "efb74e8e7b00c281f77cd928b994d78377876e6b39126cc88e6a372ca2e0f883"),
+			(2, "59dd3dec15c67960a8c0ee7809baa661ba47b504b1c6b72c1d7a36498685f140"),
+		];
+		for &(input_index, expected) in &sapling_single_anyone_can_pay {
+			let expected: H256 = expected.parse().unwrap();
+			let hash = signature_hash_sapling(&tx, Some(input_index), &script, 50000, 0x83, 0x76b809bb);
+			assert_eq!(hash, expected.reversed());
+		}
+		checked_sighashes.push(Sighash::new(SighashBase::Single, true, false));
+
+		// make sure that every sighash flags combination is covered by test vectors
+		for base in &[SighashBase::All, SighashBase::None, SighashBase::Single] {
+			for anyone_can_pay in &[false, true] {
+				assert!(checked_sighashes.contains(&Sighash::new(*base, *anyone_can_pay, false)));
+			}
+		}
+	}
+
+	#[test]
+	fn test_precomputed_sighash_cache() {
+		let tests = include_bytes!("../data/sighash_tests.json");
+		let tests: Vec<Value> = from_slice(tests).unwrap();
+		for test in tests.iter().skip(1) {
+			let tx: Transaction = deserialize(&test[0].as_str().unwrap().from_hex::<Vec<u8>>().unwrap() as &[u8]).unwrap();
+			let signer: TransactionInputSigner = tx.into();
+			let script: Script = Script::new(test[1].as_str().unwrap().parse().unwrap());
+			let input_index = test[2].as_u64().unwrap();
+			let input_index = if input_index == ::std::u64::MAX { None } else { Some(input_index as usize) };
+			let hash_type = test[3].as_i64().unwrap() as u32;
+			let consensus_branch_id = test[4].as_u64().unwrap() as u32;
+
+			let mut precomputed_cache = signer.sighash_cache();
+			let mut empty_cache = SighashCache::default();
+			assert_eq!(
+				signer.signature_hash(&mut precomputed_cache, input_index, 0, &script, hash_type, consensus_branch_id),
+				signer.signature_hash(&mut empty_cache, input_index, 0, &script, hash_type, consensus_branch_id)
+			);
+		}
+	}
+
 	#[test]
 	fn test_sighash_cache_works_correctly() {
 		let test_cases: Vec<(Transaction, Transaction, usize)> = vec![
@@ -623,7 +741,7 @@ mod tests {
 			let consensus_branch_id = 0x76b809bb; // all test cases are for sapling era
 			let signer: TransactionInputSigner = spend_tx.clone().into();
 			let mut checker = TransactionSignatureChecker {
-				signer,
+				signer: signer.into(),
 				input_index,
 				input_amount: donor_tx.outputs[output_index].value,
 				consensus_branch_id,
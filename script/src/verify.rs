@@ -1,3 +1,4 @@
+use std::sync::Arc;
 use keys::{Public, Signature, Message};
 use chain::constants::{
 	SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG,
@@ -47,9 +48,9 @@ impl SignatureChecker for NoopSignatureChecker {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TransactionSignatureChecker {
-	pub signer: TransactionInputSigner,
+	pub signer: Arc<TransactionInputSigner>,
 	pub input_index: usize,
 	pub input_amount: u64,
 	pub consensus_branch_id: u32,
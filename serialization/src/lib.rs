@@ -16,5 +16,5 @@ pub use reader::{
 	Reader, Deserializable, deserialize, deserialize_iterator, ReadIterator, Error,
 };
 pub use stream::{
-	Stream, Serializable, serialize, serialize_list, serialized_list_size,
+	Stream, Serializable, serialize, serialize_to, serialize_list, serialized_list_size,
 };
@@ -1,6 +1,10 @@
 use std::{io, marker};
 use compact_integer::CompactInteger;
 
+/// Maximal number of list items, space for which is allocated before items are read.
+/// Declared list length is untrusted => the rest of the list grows as items are actually read.
+const MAX_LIST_PREALLOCATION: usize = 1024;
+
 pub fn deserialize<R, T>(buffer: R) -> Result<T, Error> where R: io::Read, T: Deserializable {
 	let mut reader = Reader::from_read(buffer);
 	let result = try!(reader.read());
@@ -129,7 +133,7 @@ impl<R> Reader<R> where R: io::Read {
 			return Err(Error::MalformedData);
 		}
 
-		let mut result = Vec::with_capacity(len);
+		let mut result = Vec::with_capacity(::std::cmp::min(len, MAX_LIST_PREALLOCATION));
 
 		for _ in 0..len {
 			result.push(try!(self.read()));
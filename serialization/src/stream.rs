@@ -10,6 +10,11 @@ pub fn serialize<T>(t: &T) -> Bytes where T: Serializable {
 	stream.out()
 }
 
+/// Serializes the struct directly into the writer, without intermediate buffer.
+pub fn serialize_to<T>(t: &T, writer: &mut io::Write) where T: Serializable {
+	Stream::with_writer(writer).append(t);
+}
+
 pub fn serialize_list<T, K>(t: &[K]) -> Bytes where T: Serializable, K: Borrow<T> {
 	let mut stream = Stream::new();
 	stream.append_list(t);
@@ -33,15 +38,35 @@ pub trait Serializable {
 }
 
 /// Stream used for serialization of Bitcoin structures
-#[derive(Default)]
-pub struct Stream {
-	buffer: Vec<u8>,
+pub struct Stream<'a> {
+	output: Output<'a>,
+}
+
+enum Output<'a> {
+	/// Serialized data is collected in the buffer.
+	Buffer(Vec<u8>),
+	/// Serialized data is passed to the writer. Writer must never fail.
+	Writer(&'a mut io::Write),
+}
+
+impl<'a> Default for Stream<'a> {
+	fn default() -> Self {
+		Stream::new()
+	}
 }
 
-impl Stream {
+impl<'a> Stream<'a> {
 	/// New stream
 	pub fn new() -> Self {
-		Stream { buffer: Vec::new() }
+		Stream { output: Output::Buffer(Vec::new()) }
+	}
+
+	/// New stream, which passes serialized data to the writer.
+	///
+	/// Private, so that owned writer-backed stream (the only kind of stream, `out` could be called on)
+	/// never leaves this module: `Serializable` implementations only get `&mut Stream`.
+	fn with_writer(writer: &'a mut io::Write) -> Self {
+		Stream { output: Output::Writer(writer) }
 	}
 
 	/// Serializes the struct and appends it to the end of stream.
@@ -52,8 +77,10 @@ impl Stream {
 
 	/// Appends raw bytes to the end of the stream.
 	pub fn append_slice(&mut self, bytes: &[u8]) -> &mut Self {
-		// discard error for now, since we write to simple vector
-		self.buffer.write(bytes).unwrap();
+		match self.output {
+			Output::Buffer(ref mut buffer) => buffer.extend_from_slice(bytes),
+			Output::Writer(ref mut writer) => writer.write_all(bytes).expect("stream writer never fails; qed"),
+		}
 		self
 	}
 
@@ -68,18 +95,27 @@ impl Stream {
 
 	/// Full stream.
 	pub fn out(self) -> Bytes {
-		self.buffer.into()
+		match self.output {
+			Output::Buffer(buffer) => buffer.into(),
+			Output::Writer(_) => unreachable!("writer-backed stream is only created in serialize_to, which never calls out; qed"),
+		}
 	}
 }
 
-impl Write for Stream {
+impl<'a> Write for Stream<'a> {
 	#[inline]
 	fn write(&mut self, buf: &[u8]) -> Result<usize, io::Error> {
-		self.buffer.write(buf)
+		match self.output {
+			Output::Buffer(ref mut buffer) => buffer.write(buf),
+			Output::Writer(ref mut writer) => writer.write(buf),
+		}
 	}
 
 	#[inline]
 	fn flush(&mut self) -> Result<(), io::Error> {
-		self.buffer.flush()
+		match self.output {
+			Output::Buffer(ref mut buffer) => buffer.flush(),
+			Output::Writer(ref mut writer) => writer.flush(),
+		}
 	}
 }
@@ -25,11 +25,11 @@ fn is_spent(transactions: &[IndexedTransaction], prevout: &OutPoint) -> bool {
 
 impl TransactionOutputProvider for IndexedBlock {
 	fn transaction_output(&self, outpoint: &OutPoint, transaction_index: usize) -> Option<TransactionOutput> {
-		let take = cmp::min(transaction_index, self.transactions.len());
-		transaction_output(&self.transactions[..take], outpoint)
+		let take = cmp::min(transaction_index, self.transactions().len());
+		transaction_output(&self.transactions()[..take], outpoint)
 	}
 
 	fn is_spent(&self, outpoint: &OutPoint) -> bool {
-		is_spent(&self.transactions, outpoint)
+		is_spent(self.transactions(), outpoint)
 	}
 }
@@ -0,0 +1,56 @@
+//! Block index entry
+
+use std::io;
+use primitives::bigint::U256;
+use ser::{Serializable, Deserializable, Error as ReaderError, Stream, Reader};
+
+/// Information about the block, which is computed once, when block is inserted into the database.
+/// It is stored for both canon && side chain blocks.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockIndex {
+	/// Height of the block (genesis block has zero height)
+	pub height: u32,
+	/// Total work of the chain, ending with this block
+	pub chain_work: U256,
+}
+
+impl Serializable for BlockIndex {
+	fn serialize(&self, stream: &mut Stream) {
+		let mut chain_work = [0u8; 32];
+		self.chain_work.to_little_endian(&mut chain_work);
+		stream
+			.append(&self.height)
+			.append_slice(&chain_work);
+	}
+}
+
+impl Deserializable for BlockIndex {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		let height = reader.read()?;
+		let mut chain_work = [0u8; 32];
+		reader.read_slice(&mut chain_work)?;
+		chain_work.reverse();
+
+		Ok(BlockIndex {
+			height: height,
+			chain_work: U256::from(&chain_work[..]),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use ser::{serialize, deserialize};
+	use super::BlockIndex;
+
+	#[test]
+	fn block_index_serde() {
+		let index = BlockIndex {
+			height: 100,
+			chain_work: 0x0100010001u64.into(),
+		};
+		let serialized = serialize(&index);
+		assert_eq!(serialized.len(), 36);
+		assert_eq!(deserialize::<_, BlockIndex>(&*serialized).unwrap(), index);
+	}
+}
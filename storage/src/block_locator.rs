@@ -0,0 +1,32 @@
+use hash::H256;
+use BlockProvider;
+
+/// Number of block locator hashes, taken with step 1, before the step starts doubling.
+pub const BLOCK_LOCATOR_DENSE_HASHES: usize = 10;
+
+/// Appends block locator hashes of canon blocks to `hashes`, walking back from the block at
+/// given height, using given initial step, as described in protocol documentation:
+/// https://en.bitcoin.it/wiki/Protocol_documentation#getblocks
+/// The step starts doubling after `BLOCK_LOCATOR_DENSE_HASHES` hashes are collected.
+/// Genesis block hash is always included last.
+pub fn append_block_locator_hashes(store: &BlockProvider, mut height: u32, mut step: u32, hashes: &mut Vec<H256>) {
+	loop {
+		match store.block_hash(height) {
+			Some(block_hash) => hashes.push(block_hash),
+			None => return,
+		}
+
+		if hashes.len() >= BLOCK_LOCATOR_DENSE_HASHES {
+			step <<= 1;
+		}
+		if height < step {
+			// always include genesis hash
+			if height != 0 {
+				hashes.extend(store.block_hash(0));
+			}
+
+			break;
+		}
+		height -= step;
+	}
+}
@@ -1,7 +1,7 @@
 use hash::H256;
 use bytes::Bytes;
 use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
-use {BlockRef};
+use {BlockRef, BlockIndex};
 
 pub trait BlockHeaderProvider {
 	/// resolves header bytes by block reference (number/hash)
@@ -18,6 +18,9 @@ pub trait BlockProvider: BlockHeaderProvider {
 	/// resolves hash by block number
 	fn block_hash(&self, number: u32) -> Option<H256>;
 
+	/// resolves height and total chain work by block hash (for both canon and side chain blocks)
+	fn block_index(&self, hash: &H256) -> Option<BlockIndex>;
+
 	/// resolves deserialized block body by block reference (number/hash)
 	fn block(&self, block_ref: BlockRef) -> Option<IndexedBlock>;
 
@@ -0,0 +1,166 @@
+//! Optional indexes of the canon chain
+
+use std::io;
+use hash::H256;
+use chain::OutPoint;
+use ser::{Serializable, Deserializable, Error as ReaderError, Stream, Reader};
+use Error;
+
+/// Number of blocks, indexed within single database transaction during rescan.
+pub const INDEX_RESCAN_BATCH: u32 = 1_000;
+
+/// Optional index of the canon chain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexKind {
+	/// Index of canon transactions by their hash.
+	Transaction,
+	/// Index of canon transaction outputs by their `script_pubkey`.
+	Address,
+	/// Index of spent canon transaction outputs.
+	Spent,
+}
+
+impl IndexKind {
+	/// All known indexes.
+	pub fn all() -> [IndexKind; 3] {
+		[IndexKind::Transaction, IndexKind::Address, IndexKind::Spent]
+	}
+
+	/// Index name (for logs).
+	pub fn name(&self) -> &'static str {
+		match *self {
+			IndexKind::Transaction => "transaction",
+			IndexKind::Address => "address",
+			IndexKind::Spent => "spent",
+		}
+	}
+}
+
+/// Location of the canon transaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionLocation {
+	/// Hash of the block, containing transaction
+	pub block_hash: H256,
+	/// Index of the transaction within the block
+	pub index: u32,
+}
+
+impl Serializable for TransactionLocation {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.block_hash)
+			.append(&self.index);
+	}
+}
+
+impl Deserializable for TransactionLocation {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		Ok(TransactionLocation {
+			block_hash: reader.read()?,
+			index: reader.read()?,
+		})
+	}
+}
+
+/// Input of the canon transaction, spending the output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpentInfo {
+	/// Hash of the spending transaction
+	pub hash: H256,
+	/// Index of the spending input
+	pub input_index: u32,
+	/// Height of the block, containing spending transaction
+	pub height: u32,
+}
+
+impl Serializable for SpentInfo {
+	fn serialize(&self, stream: &mut Stream) {
+		stream
+			.append(&self.hash)
+			.append(&self.input_index)
+			.append(&self.height);
+	}
+}
+
+impl Deserializable for SpentInfo {
+	fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, ReaderError> where T: io::Read {
+		Ok(SpentInfo {
+			hash: reader.read()?,
+			input_index: reader.read()?,
+			height: reader.read()?,
+		})
+	}
+}
+
+/// Optional indexes of the canon chain.
+///
+/// Indexes are disabled by default. Once enabled, index must be backfilled with `rescan_index`,
+/// after which it is kept up to date when blocks are canonized/decanonized.
+pub trait ChainIndex : Sync {
+	/// Returns number of the first canon block that is not yet indexed, or None if index is disabled.
+	fn index_next_block(&self, index: IndexKind) -> Option<u32>;
+
+	/// Enables index. Does nothing if index is already enabled.
+	fn enable_index(&self, index: IndexKind) -> Result<(), Error>;
+
+	/// Indexes at most `max_blocks` canon blocks, starting from the first non-indexed block.
+	/// Indexed blocks and index progress are written atomically, so that indexing could be resumed
+	/// after restart. Returns number of the first non-indexed block.
+	fn index_next_blocks(&self, index: IndexKind, max_blocks: u32) -> Result<u32, Error>;
+
+	/// Returns location of the indexed transaction.
+	fn transaction_location(&self, hash: &H256) -> Option<TransactionLocation>;
+
+	/// Returns all indexed outputs, paying to given script.
+	fn address_outputs(&self, script_pubkey: &[u8]) -> Vec<OutPoint>;
+
+	/// Returns input, which has spent the indexed output.
+	fn spent_info(&self, outpoint: &OutPoint) -> Option<SpentInfo>;
+}
+
+/// Backfills index up to the best block `best_block_number`, starting from the first
+/// non-indexed block. `on_progress` is called with the number of indexed blocks after every batch.
+pub fn rescan_index<F>(chain_index: &ChainIndex, index: IndexKind, best_block_number: u32, mut on_progress: F) -> Result<(), Error>
+	where F: FnMut(u32)
+{
+	let mut next_block = match chain_index.index_next_block(index) {
+		Some(next_block) => next_block,
+		None => return Ok(()),
+	};
+
+	while next_block <= best_block_number {
+		let indexed_block = chain_index.index_next_blocks(index, INDEX_RESCAN_BATCH)?;
+		if indexed_block == next_block {
+			// no more canon blocks to index
+			break;
+		}
+
+		next_block = indexed_block;
+		on_progress(next_block);
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use hash::H256;
+	use ser::{serialize, deserialize};
+	use super::{TransactionLocation, SpentInfo};
+
+	#[test]
+	fn index_entries_serde() {
+		let location = TransactionLocation {
+			block_hash: H256::from(1),
+			index: 2,
+		};
+		assert_eq!(deserialize::<_, TransactionLocation>(&*serialize(&location)).unwrap(), location);
+
+		let spent_info = SpentInfo {
+			hash: H256::from(3),
+			input_index: 4,
+			height: 5,
+		};
+		assert_eq!(deserialize::<_, SpentInfo>(&*serialize(&spent_info)).unwrap(), spent_info);
+	}
+}
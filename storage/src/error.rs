@@ -15,6 +15,15 @@ pub enum Error {
 	/// Invalid block
 	#[display(fmt = "Cannot decanonize block (invalid database state)")]
 	CannotDecanonize,
+	/// Database has been created by newer version of the software
+	#[display(fmt = "Database version {} is newer than supported version {}", _0, _1)]
+	UnsupportedDatabaseVersion(u32, u32),
+	/// Optional index is not enabled
+	#[display(fmt = "Index is disabled")]
+	IndexDisabled,
+	/// UTXO set snapshot is malformed
+	#[display(fmt = "Invalid UTXO snapshot: {}", _0)]
+	InvalidUtxoSnapshot(String),
 }
 
 impl From<Error> for String {
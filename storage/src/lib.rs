@@ -16,10 +16,13 @@ mod best_block;
 mod block_ancestors;
 mod block_chain;
 mod block_impls;
+mod block_index;
 mod block_iterator;
+mod block_locator;
 mod block_origin;
 mod block_provider;
 mod block_ref;
+mod chain_index;
 mod duplex_store;
 mod error;
 mod store;
@@ -34,10 +37,13 @@ pub use primitives::{hash, bytes};
 pub use best_block::BestBlock;
 pub use block_ancestors::BlockAncestors;
 pub use block_chain::{BlockChain, ForkChain, Forkable};
+pub use block_index::BlockIndex;
 pub use block_iterator::BlockIterator;
+pub use block_locator::{append_block_locator_hashes, BLOCK_LOCATOR_DENSE_HASHES};
 pub use block_origin::{BlockOrigin, SideChainOrigin};
 pub use block_provider::{BlockHeaderProvider, BlockProvider};
 pub use block_ref::BlockRef;
+pub use chain_index::{ChainIndex, IndexKind, TransactionLocation, SpentInfo, rescan_index, INDEX_RESCAN_BATCH};
 pub use duplex_store::{DuplexTransactionOutputProvider, NoopStore};
 pub use error::Error;
 pub use store::{AsSubstore, Store, SharedStore, CanonStore};
@@ -1,6 +1,13 @@
-use EpochRef;
+use hash::H256;
+use {EpochRef, EpochTag};
 
 /// Trait to query existing nullifier.
 pub trait NullifierTracker : Sync {
 	fn contains_nullifier(&self, nullifier: EpochRef) -> bool;
+
+	/// Returns true if nullifier of given shielded pool is revealed in the canon chain,
+	/// i.e. if the note it corresponds to has been spent.
+	fn is_nullifier_spent(&self, pool: EpochTag, nullifier: &H256) -> bool {
+		self.contains_nullifier(EpochRef::new(pool, *nullifier))
+	}
 }
@@ -1,8 +1,11 @@
+use std::io;
 use std::sync::Arc;
 use chain::IndexedBlockHeader;
+use hash::H256;
+use block_locator::append_block_locator_hashes;
 use {
 	BestBlock, BlockProvider, BlockHeaderProvider, TransactionProvider, TransactionMetaProvider,
-	TransactionOutputProvider, BlockChain, Forkable, NullifierTracker, TreeStateProvider,
+	TransactionOutputProvider, BlockChain, Forkable, NullifierTracker, TreeStateProvider, ChainIndex, Error,
 };
 
 pub trait CanonStore: Store + Forkable {
@@ -16,6 +19,33 @@ pub trait Store: AsSubstore {
 
 	/// get best header
 	fn best_header(&self) -> IndexedBlockHeader;
+
+	/// get hashes of canon blocks with `low <= header.time <= high`, ordered by time
+	/// (blocks with equal time are ordered by height); implementations may limit number of returned hashes
+	fn block_hashes_by_time(&self, low: u32, high: u32) -> Vec<H256>;
+
+	/// write snapshot of the canon chain UTXO set and shielded pools state
+	fn dump_utxo(&self, writer: &mut io::Write) -> Result<(), Error>;
+
+	/// import snapshot, written by `dump_utxo`, into the empty store
+	fn load_utxo(&self, reader: &mut io::Read) -> Result<(), Error>;
+
+	/// get block locator hashes: exponentially spaced hashes of canon blocks, starting
+	/// with the best block and ending with the genesis block
+	fn block_locator_hashes(&self) -> Vec<H256> {
+		let mut hashes = Vec::new();
+		append_block_locator_hashes(self.as_block_provider(), self.best_block().number, 1, &mut hashes);
+		hashes
+	}
+
+	/// get hash of the canon block, containing given transaction
+	///
+	/// transaction meta is written when block is canonized and deleted when it is decanonized,
+	/// so it is used as the transactions index
+	fn transaction_block_hash(&self, hash: &H256) -> Option<H256> {
+		self.transaction_meta(hash)
+			.and_then(|meta| self.block_hash(meta.height()))
+	}
 }
 
 /// Allows casting Arc<Store> to reference to any substore type
@@ -25,7 +55,8 @@ pub trait AsSubstore:
 	TransactionProvider +
 	TransactionMetaProvider +
 	TransactionOutputProvider +
-	NullifierTracker
+	NullifierTracker +
+	ChainIndex
 {
 	fn as_block_provider(&self) -> &BlockProvider;
 
@@ -40,6 +71,8 @@ pub trait AsSubstore:
 	fn as_nullifier_tracker(&self) -> &NullifierTracker;
 
 	fn as_tree_state_provider(&self) -> &TreeStateProvider;
+
+	fn as_chain_index(&self) -> &ChainIndex;
 }
 
 impl<T> AsSubstore for T
@@ -49,7 +82,8 @@ impl<T> AsSubstore for T
 		TransactionMetaProvider +
 		TransactionOutputProvider +
 		NullifierTracker +
-		TreeStateProvider
+		TreeStateProvider +
+		ChainIndex
 {
 	fn as_block_provider(&self) -> &BlockProvider {
 		&*self
@@ -78,6 +112,10 @@ impl<T> AsSubstore for T
 	fn as_tree_state_provider(&self) -> &TreeStateProvider {
 		&*self
 	}
+
+	fn as_chain_index(&self) -> &ChainIndex {
+		&*self
+	}
 }
 
 pub type SharedStore = Arc<CanonStore + Send + Sync>;
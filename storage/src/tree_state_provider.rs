@@ -1,5 +1,5 @@
 use hash::H256;
-use {SproutTreeState, SaplingTreeState};
+use {SproutTreeState, SaplingTreeState, EpochRef};
 
 pub trait TreeStateProvider : Send + Sync {
 	fn sprout_tree_at(&self, root: &H256) -> Option<SproutTreeState>;
@@ -10,6 +10,10 @@ pub trait TreeStateProvider : Send + Sync {
 
 	fn sapling_block_root(&self, block_hash: &H256) -> Option<H256>;
 
+	/// Returns true if given root is the final commitment tree root of some canon chain block,
+	/// i.e. if it could be used as an anchor by the next block transactions.
+	fn contains_anchor(&self, anchor: EpochRef) -> bool;
+
 	fn sprout_tree_at_block(&self, block_hash: &H256) -> Option<SproutTreeState> {
 		self.sprout_block_root(block_hash).and_then(|h| self.sprout_tree_at(&h))
 	}
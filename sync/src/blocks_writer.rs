@@ -14,6 +14,7 @@ use synchronization_verifier::{
 };
 use types::{PeerIndex, StorageRef};
 use utils::OrphanBlocksPool;
+use verification::{Error as VerificationError, TransactionError};
 use VerificationParameters;
 
 /// Maximum number of orphaned in-memory blocks
@@ -127,8 +128,8 @@ impl BlockVerificationSink for BlocksWriterSink {
 		None
 	}
 
-	fn on_block_verification_error(&self, err: &str, _hash: &H256) {
-		self.data.lock().err = Some(Error::Verification(err.into()));
+	fn on_block_verification_error(&self, err: &VerificationError, _hash: &H256) {
+		self.data.lock().err = Some(Error::Verification(format!("{:?}", err)));
 	}
 }
 
@@ -137,7 +138,7 @@ impl TransactionVerificationSink for BlocksWriterSink {
 		unreachable!("not intended to verify transactions")
 	}
 
-	fn on_transaction_verification_error(&self, _err: &str, _hash: &H256) {
+	fn on_transaction_verification_error(&self, _err: &TransactionError, _hash: &H256) {
 		unreachable!("not intended to verify transactions")
 	}
 }
@@ -155,10 +156,14 @@ impl HeadersVerificationSink for BlocksWriterSink {
 #[cfg(test)]
 mod tests {
 	extern crate test_data;
+	extern crate import;
 
+	use std::{env, fs, process};
 	use std::sync::Arc;
+	use chain::Block;
 	use db::{BlockChainDatabase};
 	use network::{ConsensusParams, Network};
+	use ser::{serialize, Stream};
 	use verification::VerificationLevel;
 	use super::super::Error;
 	use super::{BlocksWriter, MAX_ORPHANED_BLOCKS};
@@ -168,6 +173,9 @@ mod tests {
 		VerificationParameters {
 			verification_level: VerificationLevel::FULL,
 			verification_edge: 0u8.into(),
+			transaction_time_budget: None,
+			sapling_anchor_window: None,
+			script_verification_pool: None,
 		}
 	}
 
@@ -231,9 +239,54 @@ mod tests {
 		let mut blocks_target = BlocksWriter::new(db.clone(), ConsensusParams::new(Network::Testnet), VerificationParameters {
 			verification_level: VerificationLevel::NO_VERIFICATION,
 			verification_edge: 0u8.into(),
+			transaction_time_budget: None,
+			sapling_anchor_window: None,
+			script_verification_pool: None,
 		});
 		assert_eq!(blocks_target.append_block(b1.into()), Ok(()));
 		assert_eq!(blocks_target.append_block(b2.into()), Ok(()));
 		assert_eq!(blocks_target.append_block(b3.into()), Ok(()));
 	}
+
+	#[test]
+	fn blocks_writer_imports_bootstrap_file() {
+		let magic = Network::Mainnet.magic();
+		let framed = |block: Block| {
+			let block = serialize(&block);
+			let mut stream = Stream::default();
+			stream.append(&magic).append(&(block.len() as u32)).append_slice(&block);
+			stream.out()
+		};
+
+		// blocks are stored out of order, invalid block and garbage are stored between valid blocks
+		let invalid_block = test_data::block_builder()
+			.header().parent(test_data::genesis().hash()).build()
+			.build();
+		let mut file = Vec::new();
+		file.extend_from_slice(&framed(test_data::block_h2()));
+		file.extend_from_slice(&[0xff; 16]);
+		file.extend_from_slice(&framed(invalid_block));
+		file.extend_from_slice(&framed(test_data::block_h1()));
+		file.extend_from_slice(&framed(test_data::block_h3()));
+
+		let path = env::temp_dir().join(format!("pzec-bootstrap-{}.dat", process::id()));
+		fs::write(&path, &file).unwrap();
+		let blocks = import::open_bootstrap_file(&path, magic).unwrap();
+
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let mut blocks_target = BlocksWriter::new(db.clone(), ConsensusParams::new(Network::Mainnet), default_verification_params());
+		let mut skipped = 0;
+		for block in blocks {
+			match blocks_target.append_block(block.unwrap().block) {
+				Ok(_) => (),
+				Err(Error::Verification(_)) => skipped += 1,
+				Err(err) => panic!("unexpected error: {:?}", err),
+			}
+		}
+		fs::remove_file(&path).unwrap();
+
+		assert_eq!(skipped, 1);
+		assert_eq!(db.best_block().number, 3);
+		assert_eq!(db.best_block().hash, test_data::block_h3().hash());
+	}
 }
@@ -83,6 +83,7 @@ impl InboundSyncConnection for InboundConnection {
 	fn on_transaction(&self, message: types::Tx) {
 		let tx = IndexedTransaction::from_raw(message.transaction);
 		self.peers.hash_known_as(self.peer_index, tx.hash.clone(), KnownHashType::Transaction);
+		self.peers.hash_known_as(self.peer_index, tx.raw.relay_id().effects, KnownHashType::RelayId);
 		self.node.on_transaction(self.peer_index, tx);
 	}
 
@@ -152,6 +153,22 @@ impl InboundSyncConnection for InboundConnection {
 		self.node.on_feefilter(self.peer_index, message);
 	}
 
+	fn on_send_compact(&self, message: types::SendCompact) {
+		self.node.on_send_compact(self.peer_index, message);
+	}
+
+	fn on_compact_block(&self, message: types::CompactBlock) {
+		self.node.on_compact_block(self.peer_index, message);
+	}
+
+	fn on_get_block_txn(&self, message: types::GetBlockTxn) {
+		self.node.on_get_block_txn(self.peer_index, message);
+	}
+
+	fn on_block_txn(&self, message: types::BlockTxn) {
+		self.node.on_block_txn(self.peer_index, message);
+	}
+
 	fn on_notfound(&self, message: types::NotFound) {
 		self.node.on_notfound(self.peer_index, message);
 	}
@@ -194,8 +211,14 @@ pub mod tests {
 		fn send_merkleblock(&self, _message: &types::MerkleBlock) { *self.messages.lock().entry("merkleblock".to_owned()).or_insert(0) += 1; }
 		fn send_sendheaders(&self, _message: &types::SendHeaders) { *self.messages.lock().entry("sendheaders".to_owned()).or_insert(0) += 1; }
 		fn send_feefilter(&self, _message: &types::FeeFilter) { *self.messages.lock().entry("feefilter".to_owned()).or_insert(0) += 1; }
+		fn send_send_compact(&self, _message: &types::SendCompact) { *self.messages.lock().entry("sendcmpct".to_owned()).or_insert(0) += 1; }
+		fn send_compact_block(&self, _message: &types::CompactBlock) { *self.messages.lock().entry("cmpctblock".to_owned()).or_insert(0) += 1; }
+		fn send_get_block_txn(&self, _message: &types::GetBlockTxn) { *self.messages.lock().entry("getblocktxn".to_owned()).or_insert(0) += 1; }
+		fn send_block_txn(&self, _message: &types::BlockTxn) { *self.messages.lock().entry("blocktxn".to_owned()).or_insert(0) += 1; }
 		fn send_notfound(&self, _message: &types::NotFound) { *self.messages.lock().entry("notfound".to_owned()).or_insert(0) += 1; }
 		fn ignored(&self, _id: RequestId) {}
-		fn close(&self) {}
+		fn close(&self) { *self.messages.lock().entry("close".to_owned()).or_insert(0) += 1; }
+		fn ban(&self) { *self.messages.lock().entry("ban".to_owned()).or_insert(0) += 1; }
+		fn report_new_block(&self) { *self.messages.lock().entry("newblock".to_owned()).or_insert(0) += 1; }
 	}
 }
@@ -1,5 +1,5 @@
 use std::sync::atomic::{AtomicUsize, Ordering};
-use p2p::{LocalSyncNode, LocalSyncNodeRef, OutboundSyncConnectionRef, InboundSyncConnectionRef};
+use p2p::{LocalSyncNode, LocalSyncNodeRef, OutboundSyncConnectionRef, InboundSyncConnectionRef, PeerPermissions};
 use message::Services;
 use inbound_connection::InboundConnection;
 use types::{PeersRef, LocalNodeRef};
@@ -31,11 +31,12 @@ impl InboundConnectionFactory {
 }
 
 impl LocalSyncNode for InboundConnectionFactory {
-	fn create_sync_session(&self, _best_block_height: i32, services: Services, outbound_connection: OutboundSyncConnectionRef) -> InboundSyncConnectionRef {
+	fn create_sync_session(&self, _best_block_height: i32, services: Services, permissions: PeerPermissions, outbound_connection: OutboundSyncConnectionRef) -> InboundSyncConnectionRef {
 		let peer_index = self.counter.fetch_add(1, Ordering::SeqCst) + 1;
 		trace!(target: "sync", "Creating new sync session with peer#{}", peer_index);
 		// remember outbound connection
 		self.peers.insert(peer_index, services, outbound_connection);
+		self.peers.set_permissions(peer_index, permissions);
 		// create new inbound connection
 		InboundConnection::new(peer_index, self.peers.clone(), self.node.clone()).boxed()
 	}
@@ -38,10 +38,13 @@ mod synchronization_verifier;
 mod types;
 mod utils;
 
+pub use synchronization_chain::BlockFailureCategory;
+pub use synchronization_client_core::SyncMode;
 pub use types::LocalNodeRef;
 pub use types::PeersRef;
 
 use std::sync::Arc;
+use std::time::Duration;
 use parking_lot::RwLock;
 use network::{Network, ConsensusParams};
 use primitives::hash::H256;
@@ -66,6 +69,15 @@ pub struct VerificationParameters {
 	/// Blocks verification edge: all blocks before this are validated using verification_level.
 	/// All blocks after this (inclusive) are validated using VerificationLevel::Full level.
 	pub verification_edge: H256,
+	/// Maximal time, spent on verification of single memory pool transaction. This is a relay
+	/// policy - block transactions are always verified completely.
+	pub transaction_time_budget: Option<Duration>,
+	/// Only relay memory pool transactions, which are spending sapling anchors of this number of most recent blocks.
+	/// This is a relay policy - blocks with older (but known) anchors are still accepted.
+	pub sapling_anchor_window: Option<u32>,
+	/// Pool of workers, evaluating scripts of block transactions. The same pool is shared by all
+	/// verifiers. Global pool is used if `None`.
+	pub script_verification_pool: Option<Arc<verification::ScriptVerificationPool>>,
 }
 
 /// Synchronization events listener
@@ -74,6 +86,10 @@ pub trait SyncListener: Send + 'static {
 	fn synchronization_state_switched(&self, is_synchronizing: bool);
 	/// Called when new best storage block is inserted
 	fn best_storage_block_inserted(&self, block_hash: &H256);
+	/// Called when memory pool transactions (and their descendants) are replaced by the new transaction
+	fn transactions_replaced(&self, _replaced: &[H256], _replacement: &H256) {}
+	/// Called when memory pool transactions (and their descendants) are removed, because they have expired
+	fn transactions_expired(&self, _expired: &[H256]) {}
 }
 
 /// Create blocks writer.
@@ -89,7 +105,7 @@ pub fn create_sync_peers() -> PeersRef {
 }
 
 /// Creates local sync node for given `db`
-pub fn create_local_sync_node(consensus: ConsensusParams, db: storage::SharedStore, peers: PeersRef, verification_params: VerificationParameters) -> LocalNodeRef {
+pub fn create_local_sync_node(consensus: ConsensusParams, db: storage::SharedStore, peers: PeersRef, verification_params: VerificationParameters, blocks_only: bool, timeouts: p2p::TimeoutProfile, sync_mode: SyncMode) -> LocalNodeRef {
 	use miner::MemoryPool;
 	use synchronization_chain::Chain as SyncChain;
 	use synchronization_executor::LocalSynchronizationTaskExecutor as SyncExecutor;
@@ -105,14 +121,25 @@ pub fn create_local_sync_node(consensus: ConsensusParams, db: storage::SharedSto
 	let sync_client_config = SynchronizationConfig {
 		// during regtests, peer is providing us with bad blocks => we shouldn't close connection because of this
 		close_connection_on_bad_block: network != Network::Regtest,
+		blocks_only: blocks_only,
+		timeouts: timeouts,
+		sync_mode: sync_mode,
+		consensus: consensus.clone(),
 	};
 
 	let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
 	let sync_state = SynchronizationStateRef::new(SynchronizationState::with_storage(db.clone()));
 	let sync_chain = SyncChain::new(db.clone(), memory_pool.clone());
 
-	let light_chain_verifier = Arc::new(ChainVerifier::new(db.clone(), consensus.clone()));
-	let heavy_chain_verifier = Arc::new(ChainVerifier::new(db.clone(), consensus.clone()));
+	let sapling_anchor_window = verification_params.sapling_anchor_window;
+	let light_chain_verifier = Arc::new(ChainVerifier::new(db.clone(), consensus.clone())
+		.with_transaction_time_budget(verification_params.transaction_time_budget)
+		.with_sapling_anchor_window(verification_params.sapling_anchor_window)
+		.with_script_verification_pool(verification_params.script_verification_pool.clone()));
+	let heavy_chain_verifier = Arc::new(ChainVerifier::new(db.clone(), consensus.clone())
+		.with_transaction_time_budget(verification_params.transaction_time_budget)
+		.with_sapling_anchor_window(verification_params.sapling_anchor_window)
+		.with_script_verification_pool(verification_params.script_verification_pool.clone()));
 	let sync_executor = SyncExecutor::new(peers.clone());
 	let sync_server = Arc::new(ServerImpl::new(peers.clone(), db.clone(), memory_pool.clone(), sync_executor.clone()));
 	let sync_client_core = SynchronizationClientCore::new(sync_client_config, sync_state.clone(), peers.clone(), sync_executor.clone(), sync_chain);
@@ -134,7 +161,8 @@ pub fn create_local_sync_node(consensus: ConsensusParams, db: storage::SharedSto
 		verification_params,
 	);
 	let sync_client = SynchronizationClient::new(sync_state.clone(), sync_client_core, light_verifier, heavy_verifier);
-	Arc::new(SyncNode::new(consensus, db, memory_pool, peers, sync_state, sync_client, sync_server))
+	Arc::new(SyncNode::new(consensus, db, memory_pool, peers, sync_state, sync_client, sync_server)
+		.with_sapling_anchor_window(sapling_anchor_window))
 }
 
 /// Create inbound synchronization connections factory for given local sync node.
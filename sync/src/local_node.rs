@@ -1,20 +1,40 @@
+use std::collections::HashSet;
 use std::sync::Arc;
 use parking_lot::{Mutex, Condvar};
 use time;
 use futures::{lazy, finished};
+#[cfg(feature = "solver")]
+use futures::Future;
+#[cfg(feature = "solver")]
+use futures::sync::oneshot;
 use chain::{IndexedTransaction, IndexedBlock, IndexedBlockHeader};
 use keys::Address;
-use message::types;
-use miner::BlockAssembler;
+use message::{types, Payload};
+use miner::{BlockAssembler, SpentOutputCache};
 use network::ConsensusParams;
+use synchronization_chain::BlockFailureCategory;
 use synchronization_client::{Client};
 use synchronization_server::{Server, ServerTask};
 use synchronization_verifier::{TransactionVerificationSink};
+use verification::{BackwardsCompatibleChainVerifier as ChainVerifier, TransactionError};
 use primitives::hash::H256;
 use miner::BlockTemplate;
+#[cfg(feature = "solver")]
+use miner::mine_block;
+#[cfg(feature = "solver")]
+use network::Network;
 use synchronization_peers::{TransactionAnnouncementType, BlockAnnouncementType};
+#[cfg(feature = "solver")]
+use types::LOCAL_PEER_INDEX;
 use types::{PeerIndex, RequestId, StorageRef, MemoryPoolRef, PeersRef,
 	ClientRef, ServerRef, SynchronizationStateRef, SyncListenerRef, BlockHeight};
+use utils::{MemoryPoolTransactionOutputProvider, PackageTransactionOutputProvider};
+
+/// Max number of peers, which are asked to announce new blocks using `cmpctblock` messages (BIP152).
+const MAX_HIGH_BANDWIDTH_COMPACT_PEERS: usize = 3;
+/// Max number of nonces to try when generating single block.
+#[cfg(feature = "solver")]
+const MAX_GENERATE_NONCES: u32 = 1024;
 
 /// Local synchronization node
 pub struct LocalNode<U: Server, V: Client> {
@@ -32,6 +52,12 @@ pub struct LocalNode<U: Server, V: Client> {
 	client: ClientRef<V>,
 	/// Synchronization server
 	server: ServerRef<U>,
+	/// Sapling anchors window, used when testing memory pool transactions.
+	sapling_anchor_window: Option<u32>,
+	/// Cache of outputs, spent by memory pool transactions. Reused by block templates built on the same block.
+	output_cache: SpentOutputCache,
+	/// Peers, which are announcing new blocks using `cmpctblock` messages.
+	high_bandwidth_compact_peers: Mutex<HashSet<PeerIndex>>,
 }
 
 /// Transaction accept verification sink
@@ -58,9 +84,18 @@ impl<U, V> LocalNode<U, V> where U: Server, V: Client {
 			state: state,
 			client: client,
 			server: server,
+			sapling_anchor_window: None,
+			output_cache: SpentOutputCache::new(),
+			high_bandwidth_compact_peers: Mutex::new(HashSet::new()),
 		}
 	}
 
+	/// Only accept transactions, which are spending sapling anchors of `window` most recent blocks.
+	pub fn with_sapling_anchor_window(mut self, window: Option<u32>) -> Self {
+		self.sapling_anchor_window = window;
+		self
+	}
+
 	/// Return shared reference to synchronization state.
 	pub fn sync_state(&self) -> SynchronizationStateRef {
 		self.state.clone()
@@ -75,6 +110,37 @@ impl<U, V> LocalNode<U, V> where U: Server, V: Client {
 			self.peers.set_transaction_announcement_type(peer_index, TransactionAnnouncementType::DoNotAnnounce);
 		}
 
+		if let Some(connection) = self.peers.connection(peer_index) {
+			// ask peer to announce new blocks using `headers` message
+			if version.version() >= types::SendHeaders::version() {
+				connection.send_sendheaders(&types::SendHeaders);
+			}
+
+			// `feefilter` isn't sent: memory pool accepts transactions regardless of their fee rate,
+			// so there are no announcements we would like peer to suppress
+
+			// tell peer that we're able to process compact blocks
+			// (first peers are also asked to announce new blocks using `cmpctblock` messages)
+			if version.version() >= types::SendCompact::version() {
+				let announce = {
+					let mut high_bandwidth_compact_peers = self.high_bandwidth_compact_peers.lock();
+					high_bandwidth_compact_peers.len() < MAX_HIGH_BANDWIDTH_COMPACT_PEERS
+						&& high_bandwidth_compact_peers.insert(peer_index)
+				};
+				connection.send_send_compact(&types::SendCompact {
+					announce: announce,
+					version: types::SENDCOMPACT_VERSION,
+				});
+			}
+		}
+
+		// remember advertised height to estimate network best height
+		if let Some(start_height) = version.start_height() {
+			if start_height >= 0 {
+				self.state.on_peer_connected(peer_index, start_height as BlockHeight);
+			}
+		}
+
 		// start synchronization session with peer
 		self.client.on_connect(peer_index);
 	}
@@ -84,6 +150,8 @@ impl<U, V> LocalNode<U, V> where U: Server, V: Client {
 		trace!(target: "sync", "Stopping sync session with peer#{}", peer_index);
 
 		// stop synchronization session with peer
+		self.high_bandwidth_compact_peers.lock().remove(&peer_index);
+		self.state.on_peer_disconnected(peer_index);
 		self.client.on_disconnect(peer_index);
 	}
 
@@ -181,6 +249,30 @@ impl<U, V> LocalNode<U, V> where U: Server, V: Client {
 		self.peers.set_block_announcement_type(peer_index, BlockAnnouncementType::SendHeaders);
 	}
 
+	/// When peer asks us to announce new blocks using compact blocks
+	pub fn on_send_compact(&self, peer_index: PeerIndex, _message: types::SendCompact) {
+		trace!(target: "sync", "Got `sendcmpct` message from peer#{}", peer_index);
+		// we never announce blocks using `cmpctblock` messages => ignore
+	}
+
+	/// When compact block is received
+	pub fn on_compact_block(&self, peer_index: PeerIndex, message: types::CompactBlock) {
+		trace!(target: "sync", "Got `cmpctblock` message from peer#{}", peer_index);
+		self.client.on_compact_block(peer_index, message);
+	}
+
+	/// When peer is requesting for transactions of the compact block
+	pub fn on_get_block_txn(&self, peer_index: PeerIndex, message: types::GetBlockTxn) {
+		trace!(target: "sync", "Got `getblocktxn` message from peer#{}", peer_index);
+		self.server.execute(ServerTask::GetBlockTxn(peer_index, message));
+	}
+
+	/// When missing transactions of the compact block are received
+	pub fn on_block_txn(&self, peer_index: PeerIndex, message: types::BlockTxn) {
+		trace!(target: "sync", "Got `blocktxn` message from peer#{}", peer_index);
+		self.client.on_block_txn(peer_index, message);
+	}
+
 	/// When peer sends us a merkle block
 	pub fn on_merkleblock(&self, peer_index: PeerIndex, _message: types::MerkleBlock) {
 		trace!(target: "sync", "Got `merkleblock` message from peer#{}", peer_index);
@@ -200,28 +292,136 @@ impl<U, V> LocalNode<U, V> where U: Server, V: Client {
 		sink_data.wait()
 	}
 
+	/// Verify transactions as if they were accepted to the memory pool, without actually accepting them.
+	/// Every transaction is verified as if all previous accepted transactions of the same package are in the memory pool.
+	pub fn test_transactions(&self, transactions: Vec<IndexedTransaction>) -> Vec<Result<H256, String>> {
+		let verifier = ChainVerifier::new(self.storage.clone(), self.consensus.clone())
+			.with_sapling_anchor_window(self.sapling_anchor_window);
+		let height = self.storage.best_block().number + 1;
+		let time = time::get_time().sec as u32;
+
+		let mut package: Vec<IndexedTransaction> = Vec::with_capacity(transactions.len());
+		let mut results = Vec::with_capacity(transactions.len());
+		for transaction in transactions {
+			let result = self.test_transaction(&verifier, &package, height, time, &transaction);
+			if result.is_ok() {
+				package.push(transaction);
+			}
+			results.push(result);
+		}
+		results
+	}
+
+	/// Verify single transaction of the package
+	fn test_transaction(&self, verifier: &ChainVerifier, package: &[IndexedTransaction], height: BlockHeight, time: u32, transaction: &IndexedTransaction) -> Result<H256, String> {
+		if self.memory_pool.read().contains(&transaction.hash) || package.iter().any(|tx| tx.hash == transaction.hash) {
+			return Err("Transaction is already in memory pool".into());
+		}
+
+		// output provider must check previous outputs in storage, memory pool && package
+		let mempool_provider = MemoryPoolTransactionOutputProvider::for_transaction(self.storage.clone(), &self.memory_pool, &transaction.raw)
+			.map_err(|e| format!("{:?}", e))?;
+		let tx_output_provider = PackageTransactionOutputProvider::new(&mempool_provider, package);
+		verifier.verify_mempool_transaction(self.storage.as_block_header_provider(), &tx_output_provider, height, time, transaction)
+			.map(|_| transaction.hash.clone())
+			.map_err(|e| format!("{:?}", e))
+	}
+
 	/// Get block template for mining
-	pub fn get_block_template(&self, miner_address: &Address) -> Result<BlockTemplate, String> {
+	pub fn get_block_template(&self, miner_address: &Address, coinbase_tag: &[u8]) -> Result<BlockTemplate, String> {
 		let max_block_size = self.consensus.max_block_size();
 		let max_block_sigops = self.consensus.max_block_sigops();
 		let block_assembler = BlockAssembler {
 			miner_address: miner_address,
 			max_block_size: max_block_size as u32,
 			max_block_sigops: max_block_sigops as u32,
+			coinbase_tag: coinbase_tag,
+			output_cache: Some(&self.output_cache),
 		};
 		let memory_pool = &*self.memory_pool.read();
 		block_assembler.create_new_block(&self.storage, memory_pool, time::get_time().sec as u32, &self.consensus)
 	}
 
+	/// Mine given number of blocks on top of the best block and import them. Only allowed on regtest.
+	/// Returns hashes of mined blocks.
+	#[cfg(feature = "solver")]
+	pub fn generate(&self, miner_address: &Address, coinbase_tag: &[u8], blocks: u32) -> Result<Vec<H256>, String> {
+		let equihash_params = match (self.consensus.network, self.consensus.equihash_params) {
+			(Network::Regtest, Some(equihash_params)) => equihash_params,
+			_ => return Err("Blocks could only be generated on regtest".into()),
+		};
+		if self.state.synchronizing() {
+			return Err("Cannot generate blocks while synchronizing".into());
+		}
+
+		let mut hashes = Vec::with_capacity(blocks as usize);
+		for _ in 0..blocks {
+			let template = self.get_block_template(miner_address, coinbase_tag)?;
+			let block = mine_block(template, equihash_params, MAX_GENERATE_NONCES)
+				.ok_or_else(|| "Failed to find block with valid proof of work".to_owned())?;
+			let hash = block.header.hash.clone();
+
+			// import block as if it was received from the (virtual) local peer and wait until it is verified
+			let (verified_sender, verified_receiver) = oneshot::channel();
+			self.client.on_block(LOCAL_PEER_INDEX, block);
+			self.client.after_peer_nearly_blocks_verified(LOCAL_PEER_INDEX, Box::new(lazy(move || {
+				let _ = verified_sender.send(());
+				finished::<(), ()>(())
+			})));
+			let _ = verified_receiver.wait();
+
+			if self.storage.block_number(&hash).is_none() {
+				return Err(format!("Generated block {} has been rejected", hash.to_reversed_str()));
+			}
+
+			hashes.push(hash);
+		}
+
+		Ok(hashes)
+	}
+
+	/// Mine given number of blocks on top of the best block and import them.
+	/// Always fails, because node is built without Equihash solver.
+	#[cfg(not(feature = "solver"))]
+	pub fn generate(&self, _miner_address: &Address, _coinbase_tag: &[u8], _blocks: u32) -> Result<Vec<H256>, String> {
+		Err("Node is built without `solver` feature".into())
+	}
+
+	/// Apply virtual fee delta to the memory pool transaction, changing its mining priority.
+	pub fn prioritise_transaction(&self, hash: &H256, fee_delta: i64) {
+		self.memory_pool.write().prioritise_transaction(hash, fee_delta)
+	}
+
+	/// Estimate coin-age priority, with which transaction is likely to be confirmed within `target` blocks.
+	pub fn estimate_priority(&self, target: u32) -> f64 {
+		self.memory_pool.read().estimate_priority(target)
+	}
+
 	/// Get best synced (and stored) block number.
 	pub fn best_block_number(&self) -> BlockHeight {
 		self.state.best_storage_block_height()
 	}
 
+	/// Is synchronization in progress?
+	pub fn is_synchronizing(&self) -> bool {
+		self.state.synchronizing()
+	}
+
+	/// Get best block number of the network, estimated from heights advertised by peers.
+	pub fn estimated_network_height(&self) -> Option<BlockHeight> {
+		self.state.estimated_network_height()
+	}
+
 	/// Install synchronization events listener
 	pub fn install_sync_listener(&self, listener: SyncListenerRef) {
 		self.client.install_sync_listener(listener);
 	}
+
+	/// Forget about blocks that have failed given category of checks (e.g. because of a bug
+	/// that is now fixed), so that they're verified again. Returns number of reconsidered blocks.
+	pub fn reconsider_failed_blocks(&self, category: BlockFailureCategory) -> usize {
+		self.client.reconsider_failed_blocks(category)
+	}
 }
 
 impl TransactionAcceptSink {
@@ -254,8 +454,8 @@ impl TransactionVerificationSink for TransactionAcceptSink {
 		self.data.waiter.notify_all();
 	}
 
-	fn on_transaction_verification_error(&self, err: &str, _hash: &H256) {
-		*self.data.result.lock() = Some(Err(err.to_owned()));
+	fn on_transaction_verification_error(&self, err: &TransactionError, _hash: &H256) {
+		*self.data.result.lock() = Some(Err(format!("{:?}", err)));
 		self.data.waiter.notify_all();
 	}
 }
@@ -269,21 +469,23 @@ pub mod tests {
 	use synchronization_executor::Task;
 	use synchronization_executor::tests::DummyTaskExecutor;
 	use synchronization_client::SynchronizationClient;
-	use synchronization_client_core::{Config, SynchronizationClientCore, CoreVerificationSink};
+	use synchronization_client_core::{Config, SyncMode, SynchronizationClientCore, CoreVerificationSink};
 	use synchronization_chain::Chain;
-	use message::types;
+	use message::{types, Services};
 	use message::common::{InventoryVector, InventoryType};
 	use network::{ConsensusParams, Network};
 	use chain::Transaction;
 	use db::{BlockChainDatabase};
-	use miner::MemoryPool;
+	use miner::{MemoryPool, NonZeroFeeCalculator};
+	use verification::TransactionError;
 	use super::LocalNode;
 	use synchronization_server::ServerTask;
 	use synchronization_server::tests::DummyServer;
 	use synchronization_verifier::tests::DummyVerifier;
 	use primitives::bytes::Bytes;
 	use std::iter::repeat;
-	use synchronization_peers::PeersImpl;
+	use synchronization_peers::{PeersImpl, PeersContainer};
+	use inbound_connection::tests::DummyOutboundSyncConnection;
 	use utils::SynchronizationState;
 	use types::SynchronizationStateRef;
 
@@ -310,7 +512,7 @@ pub mod tests {
 		let sync_peers = Arc::new(PeersImpl::default());
 		let executor = DummyTaskExecutor::new();
 		let server = Arc::new(DummyServer::new());
-		let config = Config { close_connection_on_bad_block: true };
+		let config = Config { close_connection_on_bad_block: true, blocks_only: false, timeouts: Default::default(), sync_mode: SyncMode::Interleaved, consensus: ConsensusParams::new(Network::Unitest) };
 		let client_core = SynchronizationClientCore::new(config, sync_state.clone(), sync_peers.clone(), executor.clone(), chain);
 		let mut light_verifier = DummyVerifier::default();
 		light_verifier.set_sink(Arc::new(CoreVerificationSink::new(client_core.clone())));
@@ -344,6 +546,58 @@ pub mod tests {
 		assert_eq!(tasks, vec![ServerTask::GetData(peer_index, types::GetData::with_inventory(inventory))]);
 	}
 
+	#[test]
+	fn local_node_sends_sendheaders_on_connect() {
+		let (_, _, local_node) = create_local_node(None);
+
+		let connection = DummyOutboundSyncConnection::new();
+		local_node.peers.insert(0, Services::default(), connection.clone());
+		local_node.on_connect(0, "test".into(), types::Version::V0(types::version::V0 {
+			version: 170_002,
+			..Default::default()
+		}));
+		assert_eq!(connection.messages.lock().get("sendheaders"), Some(&1));
+	}
+
+	#[test]
+	fn local_node_does_not_send_feefilter_on_connect() {
+		let (_, _, local_node) = create_local_node(None);
+
+		// even if peer supports `feefilter` message, there's no fee rate to filter on
+		let connection = DummyOutboundSyncConnection::new();
+		local_node.peers.insert(0, Services::default(), connection.clone());
+		local_node.on_connect(0, "test".into(), types::Version::V0(types::version::V0 {
+			version: 170_002,
+			..Default::default()
+		}));
+		assert_eq!(connection.messages.lock().get("feefilter"), None);
+	}
+
+	#[test]
+	fn local_node_sends_sendcmpct_on_connect() {
+		let (_, _, local_node) = create_local_node(None);
+
+		// peer doesn't support compact blocks
+		let connection = DummyOutboundSyncConnection::new();
+		local_node.peers.insert(0, Services::default(), connection.clone());
+		local_node.on_connect(0, "test".into(), types::Version::default());
+		assert_eq!(connection.messages.lock().get("sendcmpct"), None);
+
+		// peer supports compact blocks
+		let connection = DummyOutboundSyncConnection::new();
+		local_node.peers.insert(1, Services::default(), connection.clone());
+		local_node.on_connect(1, "test".into(), types::Version::V0(types::version::V0 {
+			version: 170_002,
+			..Default::default()
+		}));
+		assert_eq!(connection.messages.lock().get("sendcmpct"), Some(&1));
+		assert!(local_node.high_bandwidth_compact_peers.lock().contains(&1));
+
+		// peer is forgotten on disconnect
+		local_node.on_disconnect(1);
+		assert!(local_node.high_bandwidth_compact_peers.lock().is_empty());
+	}
+
 	#[test]
 	fn local_node_accepts_local_transaction() {
 		let (executor, _, local_node) = create_local_node(None);
@@ -370,7 +624,7 @@ pub mod tests {
 
 		// simulate transaction verification fail
 		let mut verifier = DummyVerifier::default();
-		verifier.error_when_verifying(transaction_hash.clone(), "simulated");
+		verifier.error_when_verifying_transaction(transaction_hash.clone(), TransactionError::Misplaced);
 
 		let (executor, _, local_node) = create_local_node(Some(verifier));
 
@@ -378,8 +632,46 @@ pub mod tests {
 		executor.take_tasks();
 
 		let result = local_node.accept_transaction(transaction.into());
-		assert_eq!(result, Err("simulated".to_owned()));
+		assert_eq!(result, Err(format!("{:?}", TransactionError::Misplaced)));
 
 		assert_eq!(executor.take_tasks(), vec![]);
 	}
+
+	#[test]
+	fn local_node_test_transactions_rejects_double_spend() {
+		let (_, _, local_node) = create_local_node(None);
+
+		let genesis = test_data::genesis();
+		let transaction: Transaction = test_data::TransactionBuilder::with_output(1).add_input(&genesis.transactions[0], 0).into();
+		let double_spend: Transaction = test_data::TransactionBuilder::with_output(2).add_input(&genesis.transactions[0], 0).into();
+		local_node.memory_pool.write().insert_verified(transaction.clone().into(), &NonZeroFeeCalculator);
+
+		let results = local_node.test_transactions(vec![transaction.into(), double_spend.clone().into()]);
+		assert_eq!(results, vec![
+			Err("Transaction is already in memory pool".to_owned()),
+			Err(format!("{:?}", TransactionError::UsingSpentOutput(genesis.transactions[0].hash(), 0))),
+		]);
+
+		// dry run doesn't change memory pool
+		assert!(!local_node.memory_pool.read().contains(&double_spend.hash()));
+	}
+
+	#[cfg(feature = "solver")]
+	#[test]
+	fn local_node_generates_blocks_on_regtest_only() {
+		let (_, _, mut local_node) = create_local_node(None);
+		let miner_address = "t1h8SqgtM3QM5e2M8EzhhT1yL2PXXtA6oqe".into();
+		assert!(local_node.generate(&miner_address, &[], 1).is_err());
+
+		// small Equihash parameters are used to keep the test fast
+		let mut consensus = ConsensusParams::new(Network::Regtest);
+		consensus.equihash_params = Some((48, 5));
+		local_node.consensus = consensus;
+
+		let hashes = local_node.generate(&miner_address, &[], 2).unwrap();
+		assert_eq!(hashes.len(), 2);
+		assert_eq!(local_node.storage.block_number(&hashes[0]), Some(1));
+		assert_eq!(local_node.storage.block_number(&hashes[1]), Some(2));
+		assert_eq!(local_node.best_block_number(), 2);
+	}
 }
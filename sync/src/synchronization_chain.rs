@@ -1,13 +1,16 @@
-use std::collections::{VecDeque, HashSet};
+use std::collections::{VecDeque, HashMap, HashSet};
 use std::fmt;
 use linked_hash_map::LinkedHashMap;
 use chain::{IndexedBlockHeader, IndexedBlock, IndexedTransaction, OutPoint, TransactionOutput};
 use storage;
-use miner::{MemoryPoolOrderingStrategy, MemoryPoolInformation, FeeCalculator};
+use miner::{MemoryPoolOrderingStrategy, MemoryPoolInformation, FeeCalculator, DoubleSpendCheckResult, RbfError,
+	coin_age_priority};
 use primitives::bytes::Bytes;
 use primitives::hash::H256;
-use utils::{BestHeadersChain, BestHeadersChainInformation, HashQueueChain, HashPosition};
+use ser::Serializable;
+use utils::{BestHeadersChain, BestHeadersChainInformation, ForkRoute, HashQueueChain, HashPosition};
 use types::{BlockHeight, StorageRef, MemoryPoolRef};
+use verification::{Error as VerificationError, TransactionError};
 
 /// Index of 'verifying' queue
 const VERIFYING_QUEUE: usize = 0;
@@ -25,6 +28,8 @@ pub struct BlockInsertionResult {
 	pub canonized_blocks_hashes: Vec<H256>,
 	/// Transaction to 'reverify'. Order matters
 	pub transactions_to_reverify: Vec<IndexedTransaction>,
+	/// Hashes of memory pool transactions, which have expired at the new best block
+	pub expired_transactions_hashes: Vec<H256>,
 }
 
 impl fmt::Debug for BlockInsertionResult {
@@ -32,6 +37,7 @@ impl fmt::Debug for BlockInsertionResult {
 		f.debug_struct("BlockInsertionResult")
 			.field("canonized_blocks_hashes", &self.canonized_blocks_hashes.iter().map(H256::reversed).collect::<Vec<_>>())
 			.field("transactions_to_reverify", &self.transactions_to_reverify)
+			.field("expired_transactions_hashes", &self.expired_transactions_hashes.iter().map(H256::reversed).collect::<Vec<_>>())
 			.finish()
 	}
 }
@@ -42,6 +48,7 @@ impl BlockInsertionResult {
 		BlockInsertionResult {
 			canonized_blocks_hashes: canonized_blocks_hashes,
 			transactions_to_reverify: Vec::new(),
+			expired_transactions_hashes: Vec::new(),
 		}
 	}
 }
@@ -65,6 +72,38 @@ pub enum BlockState {
 	DeadEnd,
 }
 
+/// Category of the check, the dead-end block has failed
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum BlockFailureCategory {
+	/// Block header is invalid (proof-of-work, difficulty, timestamp, version, ...)
+	Header,
+	/// One of block transactions has invalid signature script
+	Scripts,
+	/// One of block transactions is invalid
+	Transactions,
+	/// Any other block check has failed
+	Other,
+}
+
+impl BlockFailureCategory {
+	/// Get category of failed check from block verification error
+	pub fn from_verification_error(err: &VerificationError) -> Self {
+		match *err {
+			VerificationError::Pow
+				| VerificationError::FuturisticTimestamp
+				| VerificationError::Timestamp
+				| VerificationError::Difficulty { .. }
+				| VerificationError::InvalidTarget(_)
+				| VerificationError::TargetAboveLimit { .. }
+				| VerificationError::InvalidEquihashSolution
+				| VerificationError::InvalidVersion => BlockFailureCategory::Header,
+			VerificationError::Transaction(_, TransactionError::Signature(_, _)) => BlockFailureCategory::Scripts,
+			VerificationError::Transaction(_, _) => BlockFailureCategory::Transactions,
+			_ => BlockFailureCategory::Other,
+		}
+	}
+}
+
 /// Transactions synchronization state
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum TransactionState {
@@ -102,8 +141,6 @@ pub struct Information {
 /// 3) all blocks currently requested from peers
 /// 4) all blocks currently scheduled for requesting [newest blocks]
 pub struct Chain {
-	/// Genesis block hash (stored for optimizations)
-	genesis_block_hash: H256,
 	/// Best storage block (stored for optimizations)
 	best_storage_block: storage::BestBlock,
 	/// Local blocks storage
@@ -118,8 +155,8 @@ pub struct Chain {
 	verifying_transactions: LinkedHashMap<H256, IndexedTransaction>,
 	/// Transactions memory pool
 	memory_pool: MemoryPoolRef,
-	/// Blocks that have been marked as dead-ends
-	dead_end_blocks: HashSet<H256>,
+	/// Blocks that have been marked as dead-ends + category of failed check
+	dead_end_blocks: HashMap<H256, BlockFailureCategory>,
 }
 
 impl BlockState {
@@ -146,13 +183,12 @@ impl Chain {
 	/// Create new `Chain` with given storage
 	pub fn new(storage: StorageRef, memory_pool: MemoryPoolRef) -> Self {
 		// we only work with storages with genesis block
-		let genesis_block_hash = storage.block_hash(0)
-			.expect("storage with genesis block is required");
+		assert!(storage.block_hash(0).is_some(), "storage with genesis block is required");
 		let best_storage_block = storage.best_block();
 		let best_storage_block_hash = best_storage_block.hash.clone();
+		memory_pool.write().set_best_block_height(best_storage_block.number);
 
 		Chain {
-			genesis_block_hash: genesis_block_hash,
 			best_storage_block: best_storage_block,
 			storage: storage,
 			verifying_headers: HashSet::new(),
@@ -160,7 +196,7 @@ impl Chain {
 			headers_chain: BestHeadersChain::new(best_storage_block_hash),
 			verifying_transactions: LinkedHashMap::new(),
 			memory_pool: memory_pool,
-			dead_end_blocks: HashSet::new(),
+			dead_end_blocks: HashMap::new(),
 		}
 	}
 
@@ -279,7 +315,7 @@ impl Chain {
 			Some(queue_index) => BlockState::from_queue_index(queue_index),
 			None => if self.storage.contains_block(storage::BlockRef::Hash(hash.clone())) {
 				BlockState::Stored
-			} else if self.dead_end_blocks.contains(hash) {
+			} else if self.dead_end_blocks.contains_key(hash) {
 				BlockState::DeadEnd
 			} else {
 				BlockState::Unknown
@@ -320,10 +356,66 @@ impl Chain {
 
 	/// Schedule blocks hashes for requesting
 	pub fn schedule_blocks_headers(&mut self, headers: Vec<IndexedBlockHeader>) {
+		// blocks, headers of which do not fit into the headers chain, are not scheduled:
+		// they are requested again when the chain is drained
+		let headers: Vec<_> = headers.into_iter().take(self.headers_chain.capacity()).collect();
 		self.hash_chain.push_back_n_at(SCHEDULED_QUEUE, headers.iter().map(|h| h.hash.clone()).collect());
 		self.headers_chain.insert_n(headers);
 	}
 
+	/// Schedule blocks hashes for requesting, selecting the headers chain with the most work.
+	///
+	/// Headers, which are not extending the best headers chain, are remembered as side headers.
+	/// Once the side chain has more work than the best headers chain, blocks of the best chain
+	/// are unscheduled and blocks of the side chain are scheduled instead. This only happens
+	/// while blocks of the best chain are not yet requested.
+	pub fn schedule_best_blocks_headers(&mut self, headers: Vec<IndexedBlockHeader>) {
+		// headers are extending best headers chain || fork from the block that is deep in the storage
+		// (the storage is selecting the best chain in the latter case)
+		let parent_hash = headers[0].raw.previous_header_hash.clone();
+		if parent_hash == self.headers_chain.best_block_hash() || self.headers_chain.work(&parent_hash).is_none() {
+			self.schedule_blocks_headers(headers);
+			return;
+		}
+
+		let mut side_best_hash = None;
+		for header in headers {
+			let hash = header.hash.clone();
+			if !self.headers_chain.insert_side(header) {
+				break;
+			}
+			side_best_hash = Some(hash);
+		}
+
+		let side_best_hash = match side_best_hash {
+			Some(side_best_hash) => side_best_hash,
+			None => return,
+		};
+		let side_best_work = self.headers_chain.work(&side_best_hash).expect("side header has been inserted; qed");
+		if side_best_work <= self.headers_chain.best_work() {
+			return;
+		}
+
+		let route: ForkRoute = match self.headers_chain.fork_route(&side_best_hash) {
+			Some(route) => route,
+			None => return,
+		};
+		if route.decanonized.iter().any(|hash| self.block_state(hash) != BlockState::Scheduled) {
+			return;
+		}
+
+		for hash in &route.decanonized {
+			self.forget_block_with_state_leave_header(hash, BlockState::Scheduled);
+		}
+		self.headers_chain.reorganize(&route);
+		self.hash_chain.push_back_n_at(SCHEDULED_QUEUE, route.canonized);
+	}
+
+	/// Returns true if the header (either of the best headers chain, or of the side chain) is known
+	pub fn is_known_header(&self, hash: &H256) -> bool {
+		self.headers_chain.contains(hash)
+	}
+
 	/// Moves n blocks from scheduled queue to requested queue
 	pub fn request_blocks_hashes(&mut self, n: BlockHeight) -> Vec<H256> {
 		let scheduled = self.hash_chain.pop_front_n_at(SCHEDULED_QUEUE, n);
@@ -354,8 +446,21 @@ impl Chain {
 	}
 
 	/// Mark this block as dead end, so these tasks won't be synchronized
-	pub fn mark_dead_end_block(&mut self, hash: &H256) {
-		self.dead_end_blocks.insert(hash.clone());
+	pub fn mark_dead_end_block(&mut self, hash: &H256, category: BlockFailureCategory) {
+		self.dead_end_blocks.insert(hash.clone(), category);
+	}
+
+	/// Forget all dead-end blocks, that have failed given category of checks, so that these
+	/// blocks could be synchronized && verified again. Returns hashes of forgotten blocks.
+	pub fn reconsider_dead_end_blocks(&mut self, category: BlockFailureCategory) -> Vec<H256> {
+		let reconsidered: Vec<_> = self.dead_end_blocks.iter()
+			.filter(|&(_, c)| *c == category)
+			.map(|(hash, _)| hash.clone())
+			.collect();
+		for hash in &reconsidered {
+			self.dead_end_blocks.remove(hash);
+		}
+		reconsidered
 	}
 
 	/// Insert new best block to storage
@@ -370,6 +475,10 @@ impl Chain {
 			},
 			// case 1: block has been added to the main branch
 			storage::BlockOrigin::CanonChain { .. } => {
+				// priorities are computed before block outputs are inserted to the storage
+				let block_height = self.best_storage_block.number + 1;
+				let priorities = self.memory_pool_transactions_priorities(&block, block_height);
+
 				self.storage.insert(block.clone())?;
 				self.storage.canonize(block.hash())?;
 
@@ -387,18 +496,27 @@ impl Chain {
 				// => delete accepted transactions from verification queue and from the memory pool
 				// + also remove transactions which spent outputs which have been spent by transactions from the block
 				let mut memory_pool = self.memory_pool.write();
-				for tx in &block.transactions {
+				memory_pool.process_block_priorities(block_height, &priorities);
+				memory_pool.set_best_block_height(block_height);
+				for tx in block.transactions() {
 					memory_pool.remove_by_hash(&tx.hash);
 					self.verifying_transactions.remove(&tx.hash);
 					for tx_input in &tx.raw.inputs {
 						memory_pool.remove_by_prevout(&tx_input.previous_output);
 					}
 				}
+				// remove transactions which can't be mined after this block, because they are expired
+				let expired = memory_pool.remove_expired(self.best_storage_block.number);
+				if !expired.is_empty() {
+					trace!(target: "sync", "insert_best_block, expired transactions: {:?}",
+						expired.iter().map(|hash| hash.reversed()).collect::<Vec<H256>>());
+				}
 				// no transactions to reverify, because we have just appended new transactions to the blockchain
 
 				Ok(BlockInsertionResult {
 					canonized_blocks_hashes: vec![block.hash().clone()],
 					transactions_to_reverify: Vec::new(),
+					expired_transactions_hashes: expired,
 				})
 			},
 			// case 2: block has been added to the side branch with reorganization to this branch
@@ -418,13 +536,14 @@ impl Chain {
 				// all transactions from this block were accepted
 				// + all transactions from previous blocks of this fork were accepted
 				// => delete accepted transactions from verification queue and from the memory pool
-				let this_block_transactions_hashes = block.transactions.iter().map(|tx| tx.hash.clone()).collect::<Vec<_>>();
+				let this_block_transactions_hashes = block.transactions().iter().map(|tx| tx.hash.clone()).collect::<Vec<_>>();
 				let mut canonized_blocks_hashes = origin.canonized_route.clone();
 				let new_main_blocks_transactions_hashes = origin.canonized_route.into_iter()
 					.flat_map(|block_hash| self.storage.block_transaction_hashes(block_hash.into()))
 					.collect::<Vec<_>>();
 
 				let mut memory_pool = self.memory_pool.write();
+				memory_pool.set_best_block_height(self.best_storage_block.number);
 				for transaction_accepted in this_block_transactions_hashes.into_iter().chain(new_main_blocks_transactions_hashes.into_iter()) {
 					memory_pool.remove_by_hash(&transaction_accepted);
 					self.verifying_transactions.remove(&transaction_accepted);
@@ -462,6 +581,8 @@ impl Chain {
 						.chain(memory_pool_transactions.into_iter())
 						.chain(verifying_transactions.into_iter())
 						.collect(),
+					// expired transactions are rejected when memory pool transactions are reverified
+					expired_transactions_hashes: Vec::new(),
 				};
 
 				trace!(target: "sync", "result: {:?}", result);
@@ -622,17 +743,49 @@ impl Chain {
 				.map(|tx| IndexedTransaction::new(hash.clone(), tx)))
 	}
 
-	/// Insert transaction to memory pool
-	pub fn insert_verified_transaction(&mut self, transaction: IndexedTransaction) {
+	/// Insert transaction to memory pool.
+	/// Returns hashes of memory pool transactions, replaced by this transaction.
+	pub fn insert_verified_transaction(&mut self, transaction: IndexedTransaction) -> Result<Vec<H256>, RbfError> {
+		let mut memory_pool = self.memory_pool.write();
+		let fee_calculator = FeeCalculator(self.storage.as_transaction_output_provider());
+
 		// we have verified transaction, but possibly this transaction replaces
-		// existing transaction from memory pool
+		// replaceable final transactions from memory pool
+		// => BIP125 rules must be satisfied
+		if let DoubleSpendCheckResult::ReplaceableDoubleSpend(_) = memory_pool.check_double_spend(&transaction.raw) {
+			return memory_pool.accept_replacement(transaction, &fee_calculator);
+		}
+
+		// else it could replace non-final transactions from memory pool
 		// => remove previous transactions before
-		let mut memory_pool = self.memory_pool.write();
+		let mut replaced = Vec::new();
 		for input in &transaction.raw.inputs {
-			memory_pool.remove_by_prevout(&input.previous_output);
+			if let Some(removed) = memory_pool.remove_by_prevout(&input.previous_output) {
+				replaced.extend(removed.into_iter().map(|tx| tx.hash));
+			}
 		}
 		// now insert transaction itself
-		memory_pool.insert_verified(transaction, &FeeCalculator(self.storage.as_transaction_output_provider()));
+		memory_pool.insert_verified(transaction, &fee_calculator);
+		Ok(replaced)
+	}
+
+	/// Coin-age priorities of block transactions, which are in the memory pool. Inputs, that are
+	/// not in the storage (i.e. spending outputs of the same block), have zero age.
+	fn memory_pool_transactions_priorities(&self, block: &IndexedBlock, height: BlockHeight) -> Vec<(H256, f64)> {
+		let memory_pool = self.memory_pool.read();
+		let output_provider = self.storage.as_transaction_output_provider();
+		let meta_provider = self.storage.as_transaction_meta_provider();
+		block.transactions().iter()
+			.filter(|tx| memory_pool.contains(&tx.hash))
+			.map(|tx| {
+				let inputs: Vec<(u64, u32)> = tx.raw.inputs.iter()
+					.filter_map(|input| meta_provider.transaction_meta(&input.previous_output.hash)
+						.and_then(|meta| output_provider.transaction_output(&input.previous_output, usize::max_value())
+							.map(|output| (output.value, height.saturating_sub(meta.height())))))
+					.collect();
+				(tx.hash.clone(), coin_age_priority(&inputs, tx.raw.serialized_size()))
+			})
+			.collect()
 	}
 
 	/// Calculate block locator hashes for hash queue
@@ -648,7 +801,7 @@ impl Chain {
 			let block_hash = self.hash_chain[index].clone();
 			hashes.push(block_hash);
 
-			if hashes.len() >= 10 {
+			if hashes.len() >= storage::BLOCK_LOCATOR_DENSE_HASHES {
 				step <<= 1;
 			}
 			if index < step {
@@ -659,25 +812,8 @@ impl Chain {
 	}
 
 	/// Calculate block locator hashes for storage
-	fn block_locator_hashes_for_storage(&self, mut index: BlockHeight, mut step: BlockHeight, hashes: &mut Vec<H256>) {
-		loop {
-			let block_hash = self.storage.block_hash(index)
-				.expect("private function; index calculated in `block_locator_hashes`; qed");
-			hashes.push(block_hash);
-
-			if hashes.len() >= 10 {
-				step <<= 1;
-			}
-			if index < step {
-				// always include genesis hash
-				if index != 0 {
-					hashes.push(self.genesis_block_hash.clone())
-				}
-
-				break;
-			}
-			index -= step;
-		}
+	fn block_locator_hashes_for_storage(&self, index: BlockHeight, step: BlockHeight, hashes: &mut Vec<H256>) {
+		storage::append_block_locator_hashes(self.storage.as_block_provider(), index, step, hashes);
 	}
 }
 
@@ -770,8 +906,11 @@ mod tests {
 	use chain::{Transaction, IndexedBlockHeader};
 	use db::BlockChainDatabase;
 	use miner::MemoryPool;
+	use primitives::compact::Compact;
 	use primitives::hash::H256;
-	use super::{Chain, BlockState, TransactionState, BlockInsertionResult};
+	use script::Error as ScriptError;
+	use verification::{Error as VerificationError, TransactionError};
+	use super::{Chain, BlockState, BlockFailureCategory, TransactionState, BlockInsertionResult};
 	use utils::HashPosition;
 
 	#[test]
@@ -844,6 +983,36 @@ mod tests {
 		assert_eq!(db.best_block().number, 1);
 	}
 
+	#[test]
+	fn chain_schedules_headers_chain_with_most_work() {
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let mut chain = Chain::new(db.clone(), Arc::new(RwLock::new(MemoryPool::new())));
+
+		let headers: Vec<IndexedBlockHeader> = test_data::build_n_empty_blocks_from_genesis(4, 1)
+			.into_iter().map(|b| b.block_header.into()).collect();
+		let f1: IndexedBlockHeader = test_data::block_builder().header().parent(test_data::genesis().hash()).time(1).build().build().block_header.into();
+		let f2: IndexedBlockHeader = test_data::block_builder().header().parent(f1.hash.clone()).bits(Compact::new(0x1f07ffff)).build().build().block_header.into();
+
+		// longer chain is scheduled first
+		chain.schedule_best_blocks_headers(headers.clone());
+		assert_eq!(chain.information().scheduled, 4);
+		assert_eq!(chain.best_block_header().hash, headers[3].hash);
+
+		// fork with less work is remembered, but not scheduled
+		chain.schedule_best_blocks_headers(vec![f1.clone()]);
+		assert_eq!(chain.information().scheduled, 4);
+		assert!(chain.is_known_header(&f1.hash));
+		assert_eq!(chain.block_state(&f1.hash), BlockState::Unknown);
+
+		// fork with more work replaces the longer chain
+		chain.schedule_best_blocks_headers(vec![f2.clone()]);
+		assert_eq!(chain.information().scheduled, 2);
+		assert_eq!(chain.best_n_of_blocks_state(BlockState::Scheduled, 2), vec![f1.hash.clone(), f2.hash.clone()]);
+		assert_eq!(chain.best_block_header().hash, f2.hash);
+		assert_eq!(chain.best_block_header().number, 2);
+		assert_eq!(chain.block_state(&headers[0].hash), BlockState::Unknown);
+	}
+
 	#[test]
 	fn chain_block_locator_hashes() {
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
@@ -940,7 +1109,7 @@ mod tests {
 		let tx1_hash = tx1.hash();
 		let tx2_hash = tx2.hash();
 		chain.verify_transaction(tx1.into());
-		chain.insert_verified_transaction(tx2.into());
+		chain.insert_verified_transaction(tx2.into()).unwrap();
 
 		assert_eq!(chain.transaction_state(&genesis_block.transactions[0].hash()), TransactionState::Stored);
 		assert_eq!(chain.transaction_state(&block2.transactions[0].hash()), TransactionState::Unknown);
@@ -971,7 +1140,7 @@ mod tests {
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![b0.into()]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
 		chain.verify_transaction(tx1.into());
-		chain.insert_verified_transaction(tx2.into());
+		chain.insert_verified_transaction(tx2.into()).unwrap();
 
 		// only one transaction is in the memory pool
 		assert_eq!(chain.information().transactions.transactions_count, 1);
@@ -1021,10 +1190,10 @@ mod tests {
 			test_data::genesis().into(), test_data::block_h1().into(), test_data::block_h2().into(),
 		]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
-		chain.insert_verified_transaction(test_chain.at(0).into());
-		chain.insert_verified_transaction(test_chain.at(1).into());
-		chain.insert_verified_transaction(test_chain.at(2).into());
-		chain.insert_verified_transaction(test_chain.at(3).into());
+		chain.insert_verified_transaction(test_chain.at(0).into()).unwrap();
+		chain.insert_verified_transaction(test_chain.at(1).into()).unwrap();
+		chain.insert_verified_transaction(test_chain.at(2).into()).unwrap();
+		chain.insert_verified_transaction(test_chain.at(3).into()).unwrap();
 
 		let chain_transactions = chain.transactions_hashes_with_state(TransactionState::InMemory);
 		assert!(chain_transactions.contains(&test_chain.at(0).hash()));
@@ -1052,7 +1221,7 @@ mod tests {
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![b0.into()]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
 		chain.verify_transaction(tx1.into());
-		chain.insert_verified_transaction(tx2.into());
+		chain.insert_verified_transaction(tx2.into()).unwrap();
 
 		// no reorg
 		let result = chain.insert_best_block(b1.into()).expect("no error");
@@ -1104,9 +1273,9 @@ mod tests {
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into(), genesis.into()]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
 
-		chain.insert_verified_transaction(tx3.into());
-		chain.insert_verified_transaction(tx4.into());
-		chain.insert_verified_transaction(tx5.into());
+		chain.insert_verified_transaction(tx3.into()).unwrap();
+		chain.insert_verified_transaction(tx4.into()).unwrap();
+		chain.insert_verified_transaction(tx5.into()).unwrap();
 
 		assert_eq!(chain.insert_best_block(b0.clone().into()).expect("block accepted"), BlockInsertionResult::with_canonized_blocks(vec![b0.hash()]));
 		assert_eq!(chain.information().transactions.transactions_count, 3);
@@ -1146,7 +1315,7 @@ mod tests {
 		// insert tx2 to memory pool
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
-		chain.insert_verified_transaction(tx2.clone().into());
+		chain.insert_verified_transaction(tx2.clone().into()).unwrap();
 		// insert verified block with tx1
 		chain.insert_best_block(b0.into()).expect("no error");
 		// => tx2 is removed from memory pool, but tx3 remains
@@ -1165,10 +1334,36 @@ mod tests {
 
 		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into(), test_data::block_h1().into()]));
 		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
-		chain.insert_verified_transaction(data_chain.at(0).into());
-		chain.insert_verified_transaction(data_chain.at(1).into());
+		chain.insert_verified_transaction(data_chain.at(0).into()).unwrap();
+		chain.insert_verified_transaction(data_chain.at(1).into()).unwrap();
 		assert_eq!(chain.information().transactions.transactions_count, 2);
-		chain.insert_verified_transaction(data_chain.at(2).into());
+		chain.insert_verified_transaction(data_chain.at(2).into()).unwrap();
 		assert_eq!(chain.information().transactions.transactions_count, 2); // tx was replaced
 	}
+
+	#[test]
+	fn block_failure_category_from_verification_error() {
+		assert_eq!(BlockFailureCategory::from_verification_error(&VerificationError::Pow), BlockFailureCategory::Header);
+		assert_eq!(BlockFailureCategory::from_verification_error(&VerificationError::Difficulty { expected: 1.into(), actual: 2.into() }),
+			BlockFailureCategory::Header);
+		assert_eq!(BlockFailureCategory::from_verification_error(&VerificationError::InvalidTarget(1.into())), BlockFailureCategory::Header);
+		assert_eq!(BlockFailureCategory::from_verification_error(&VerificationError::Transaction(1, TransactionError::Signature(0, ScriptError::EvalFalse))),
+			BlockFailureCategory::Scripts);
+		assert_eq!(BlockFailureCategory::from_verification_error(&VerificationError::Transaction(1, TransactionError::Overspend)),
+			BlockFailureCategory::Transactions);
+		assert_eq!(BlockFailureCategory::from_verification_error(&VerificationError::MerkleRoot), BlockFailureCategory::Other);
+	}
+
+	#[test]
+	fn chain_reconsider_dead_end_blocks() {
+		let db = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let mut chain = Chain::new(db, Arc::new(RwLock::new(MemoryPool::new())));
+
+		chain.mark_dead_end_block(&H256::from(1), BlockFailureCategory::Scripts);
+		chain.mark_dead_end_block(&H256::from(2), BlockFailureCategory::Header);
+		assert_eq!(chain.reconsider_dead_end_blocks(BlockFailureCategory::Scripts), vec![H256::from(1)]);
+		assert_eq!(chain.block_state(&H256::from(1)), BlockState::Unknown);
+		assert_eq!(chain.block_state(&H256::from(2)), BlockState::DeadEnd);
+		assert_eq!(chain.reconsider_dead_end_blocks(BlockFailureCategory::Scripts), vec![]);
+	}
 }
@@ -2,6 +2,7 @@ use std::sync::Arc;
 use parking_lot::Mutex;
 use chain::{IndexedTransaction, IndexedBlock, IndexedBlockHeader};
 use message::types;
+use synchronization_chain::BlockFailureCategory;
 use synchronization_executor::TaskExecutor;
 use synchronization_verifier::{Verifier, TransactionVerificationSink};
 use synchronization_client_core::{ClientCore, SynchronizationClientCore};
@@ -128,9 +129,12 @@ pub trait Client : Send + Sync + 'static {
 	fn on_block(&self, peer_index: PeerIndex, block: IndexedBlock);
 	fn on_transaction(&self, peer_index: PeerIndex, transaction: IndexedTransaction);
 	fn on_notfound(&self, peer_index: PeerIndex, message: types::NotFound);
+	fn on_compact_block(&self, peer_index: PeerIndex, message: types::CompactBlock);
+	fn on_block_txn(&self, peer_index: PeerIndex, message: types::BlockTxn);
 	fn after_peer_nearly_blocks_verified(&self, peer_index: PeerIndex, future: EmptyBoxFuture);
 	fn accept_transaction(&self, transaction: IndexedTransaction, sink: Box<TransactionVerificationSink>) -> Result<(), String>;
 	fn install_sync_listener(&self, listener: SyncListenerRef);
+	fn reconsider_failed_blocks(&self, category: BlockFailureCategory) -> usize;
 }
 
 /// Synchronization client facade
@@ -215,6 +219,20 @@ impl<T, U> Client for SynchronizationClient<T, U> where T: TaskExecutor, U: Veri
 		self.core.lock().on_notfound(peer_index, message);
 	}
 
+	fn on_compact_block(&self, peer_index: PeerIndex, message: types::CompactBlock) {
+		let block = self.core.lock().on_compact_block(peer_index, message);
+		if let Some(block) = block {
+			self.on_block(peer_index, block);
+		}
+	}
+
+	fn on_block_txn(&self, peer_index: PeerIndex, message: types::BlockTxn) {
+		let block = self.core.lock().on_block_txn(peer_index, message);
+		if let Some(block) = block {
+			self.on_block(peer_index, block);
+		}
+	}
+
 	fn after_peer_nearly_blocks_verified(&self, peer_index: PeerIndex, future: EmptyBoxFuture) {
 		self.core.lock().after_peer_nearly_blocks_verified(peer_index, future);
 	}
@@ -232,6 +250,10 @@ impl<T, U> Client for SynchronizationClient<T, U> where T: TaskExecutor, U: Veri
 	fn install_sync_listener(&self, listener: SyncListenerRef) {
 		self.core.lock().install_sync_listener(listener);
 	}
+
+	fn reconsider_failed_blocks(&self, category: BlockFailureCategory) -> usize {
+		self.core.lock().reconsider_failed_blocks(category)
+	}
 }
 
 impl<T, U> SynchronizationClient<T, U> where T: TaskExecutor, U: Verifier {
@@ -5,22 +5,26 @@ use std::sync::Arc;
 use futures::Future;
 use parking_lot::Mutex;
 use rand::{thread_rng, Rng};
-use time::precise_time_s;
+use time::{self, precise_time_s};
 use chain::{IndexedBlockHeader, IndexedTransaction, IndexedBlock};
 use message::types;
-use message::common::{InventoryType, InventoryVector};
+use message::common::{InventoryType, InventoryVector, BlockHeaderAndIDs, BlockTransactionsRequest};
 use miner::transaction_fee_rate;
+use network::ConsensusParams;
+use p2p::TimeoutProfile;
 use primitives::hash::H256;
-use synchronization_chain::{Chain, BlockState, TransactionState, BlockInsertionResult};
+use synchronization_chain::{Chain, BlockState, BlockFailureCategory, TransactionState, BlockInsertionResult};
 use synchronization_executor::{Task, TaskExecutor};
-use synchronization_manager::ManagementWorker;
+use synchronization_manager::{ManagementWorker, ManagePeersConfig};
 use synchronization_peers_tasks::PeersTasks;
 use synchronization_verifier::{
 	VerificationSink, HeadersVerificationSink, BlockVerificationSink,
 	TransactionVerificationSink, VerificationTask, PartiallyVerifiedBlock,
 };
 use types::{BlockHeight, ClientCoreRef, PeersRef, PeerIndex, SynchronizationStateRef, EmptyBoxFuture, SyncListenerRef};
-use utils::{AverageSpeedMeter, OrphanBlocksPool, OrphanTransactionsPool, HashPosition};
+use verification::{Error as VerificationError, TransactionError, HeaderVerifier};
+use utils::{AverageSpeedMeter, OrphanBlocksPool, OrphanTransactionsPool, HashPosition, RelayIdFilter,
+	RollingBloomFilter, PartialBlock, PartialBlockError, KnownHashType};
 #[cfg(test)] use synchronization_peers_tasks::{Information as PeersTasksInformation};
 #[cfg(test)] use synchronization_chain::{Information as ChainInformation};
 
@@ -50,6 +54,18 @@ const MIN_HEADERS_DUPLICATION_INTERVAL_S: f64 = 2_f64;
 const MAX_BLOCKS_IN_DUPLICATE_REQUEST: BlockHeight = 4;
 /// Minimal number of blocks in duplicate requests.
 const MIN_BLOCKS_IN_DUPLICATE_REQUEST: BlockHeight = 8;
+/// Number of recently rejected transactions to remember.
+const MAX_RECENT_REJECTS: usize = 120_000;
+/// False-positive rate of recently rejected transactions filter.
+const RECENT_REJECTS_FP_RATE: f64 = 0.000001;
+/// Maximal number of headers in `headers` message, which is treated as new blocks announcement.
+pub const MAX_BLOCKS_TO_ANNOUNCE: usize = 8;
+/// Maximal number of unconnecting headers announcements we're trying to connect before penalizing peer.
+const MAX_UNCONNECTING_HEADERS: u32 = 10;
+/// Ban score of peer, which has sent us `blocktxn` message we have not asked for.
+const UNREQUESTED_BLOCKTXN_BAN_SCORE: u32 = 10;
+/// Time without new headers, after which headers chain is considered downloaded in headers-first mode.
+const HEADERS_FIRST_SILENCE_INTERVAL_S: f64 = 10_f64;
 
 /// Information on current synchronization state.
 #[cfg(test)]
@@ -76,6 +92,8 @@ pub trait ClientCore {
 	fn on_block(&mut self, peer_index: PeerIndex, block: IndexedBlock) -> Option<VecDeque<PartiallyVerifiedBlock>>;
 	fn on_transaction(&mut self, peer_index: PeerIndex, transaction: IndexedTransaction) -> Option<VecDeque<IndexedTransaction>>;
 	fn on_notfound(&mut self, peer_index: PeerIndex, message: types::NotFound);
+	fn on_compact_block(&mut self, peer_index: PeerIndex, message: types::CompactBlock) -> Option<IndexedBlock>;
+	fn on_block_txn(&mut self, peer_index: PeerIndex, message: types::BlockTxn) -> Option<IndexedBlock>;
 	fn after_peer_nearly_blocks_verified(&mut self, peer_index: PeerIndex, future: EmptyBoxFuture);
 	fn accept_transaction(&mut self, transaction: IndexedTransaction, sink: Box<TransactionVerificationSink>) -> Result<VecDeque<IndexedTransaction>, String>;
 	fn install_sync_listener(&mut self, listener: SyncListenerRef);
@@ -88,6 +106,23 @@ pub trait ClientCore {
 pub struct Config {
 	/// If true, connection to peer who has provided us with bad block is closed
 	pub close_connection_on_bad_block: bool,
+	/// If true, loose transactions are ignored, unless they're coming from peers with relay permission
+	pub blocks_only: bool,
+	/// Network timeouts profile.
+	pub timeouts: TimeoutProfile,
+	/// Blocks synchronization mode.
+	pub sync_mode: SyncMode,
+	/// Consensus parameters, used to check headers of compact blocks.
+	pub consensus: ConsensusParams,
+}
+
+/// Blocks synchronization mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SyncMode {
+	/// Blocks are requested as soon as their headers are verified.
+	Interleaved,
+	/// Blocks are only requested when headers chain is downloaded (or when scheduled queue is full).
+	HeadersFirst,
 }
 
 /// Synchronization client.
@@ -118,6 +153,10 @@ pub struct SynchronizationClientCore<T: TaskExecutor> {
 	verifying_transactions_sinks: HashMap<H256, Box<TransactionVerificationSink>>,
 	/// Hashes of items we do not want to relay after verification is completed
 	do_not_relay: HashSet<H256>,
+	/// Relay ids of recently received transactions
+	relay_ids: RelayIdFilter,
+	/// Hashes of recently rejected transactions
+	recent_rejects: RollingBloomFilter,
 	/// Block processing speed meter
 	block_speed_meter: AverageSpeedMeter,
 	/// Block synchronization speed meter
@@ -132,6 +171,12 @@ pub struct SynchronizationClientCore<T: TaskExecutor> {
 	new_headers_receival_timestamp: f64,
 	/// Best block number when the last headers request has been sent.
 	last_headers_request_best_number: u32,
+	/// Number of sequential unconnecting headers announcements, received from peers.
+	unconnecting_headers: HashMap<PeerIndex, u32>,
+	/// Compact blocks, waiting for missing transactions from peers.
+	partial_blocks: HashMap<PeerIndex, PartialBlock>,
+	/// Peers, whose last `headers` message has been full => they're likely to have more headers for us.
+	peers_with_more_headers: HashSet<PeerIndex>,
 }
 
 /// Verification sink for synchronization client core
@@ -209,6 +254,9 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		// sync tasks from these peers must be executed by other peers
 		let peer_tasks = self.peers_tasks.reset_blocks_tasks(peer_index);
 		self.peers_tasks.disconnect(peer_index);
+		self.unconnecting_headers.remove(&peer_index);
+		self.partial_blocks.remove(&peer_index);
+		self.peers_with_more_headers.remove(&peer_index);
 		self.execute_synchronization_tasks(Some(peer_tasks), None);
 	}
 
@@ -219,8 +267,10 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 				match item.inv_type {
 					// check that transaction is unknown to us
 					InventoryType::MessageTx =>
-						self.chain.transaction_state(&item.hash) == TransactionState::Unknown
-							&& !self.orphaned_transactions_pool.contains(&item.hash),
+						self.accepts_transactions_from(peer_index)
+							&& self.chain.transaction_state(&item.hash) == TransactionState::Unknown
+							&& !self.orphaned_transactions_pool.contains(&item.hash)
+							&& !self.recent_rejects.contains(&*item.hash),
 					// check that block is unknown to us
 					InventoryType::MessageBlock => match self.chain.block_state(&item.hash) {
 						BlockState::Unknown => !self.orphaned_blocks_pool.contains_unknown_block(&item.hash),
@@ -263,8 +313,10 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		// headers are ordered
 		// => if we know nothing about headers[0].parent
 		// => all headers are also unknown to us
+		// (in headers-first mode, headers could also be connected to the side headers chain)
 		let header0 = headers[0].clone();
-		if self.chain.block_state(&header0.raw.previous_header_hash) == BlockState::Unknown {
+		if self.chain.block_state(&header0.raw.previous_header_hash) == BlockState::Unknown
+			&& !(self.config.sync_mode == SyncMode::HeadersFirst && self.chain.is_known_header(&header0.raw.previous_header_hash)) {
 			warn!(
 				target: "sync",
 				"Previous header of the first header from peer#{} `headers` message is unknown. First: {}. Previous: {}",
@@ -273,10 +325,25 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 				header0.raw.previous_header_hash.to_reversed_str(),
 			);
 
+			// this could be a new block(s) announcement from peer, which is on the chain we have not yet
+			// heard of (i.e. we have missed some announcements) => ask for the headers chain, connecting
+			// announced blocks to our chain. Blocks will be requested once headers are received.
+			if headers.len() <= MAX_BLOCKS_TO_ANNOUNCE {
+				let unconnecting_headers = self.unconnecting_headers.entry(peer_index).or_insert(0);
+				if *unconnecting_headers < MAX_UNCONNECTING_HEADERS {
+					*unconnecting_headers += 1;
+
+					let block_locator_hashes = self.chain.block_locator_hashes();
+					self.executor.execute(Task::GetHeaders(peer_index, types::GetHeaders::with_block_locator_hashes(block_locator_hashes)));
+					self.peers_tasks.on_headers_requested(peer_index);
+					return None;
+				}
+			}
+
 			// there could be competing chains that are running the network with the same magic (like Zcash vs ZelCash)
 			// => providing unknown headers. Penalize node so that it'll disconnect
 			if self.peers_tasks.penalize(peer_index) {
-				self.peers.misbehaving(peer_index, "Too many failures.");
+				self.peers.disconnect(peer_index, "Too many failures.");
 			}
 
 			return None;
@@ -285,11 +352,21 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		// find first unknown header position
 		// optimization: normally, the first header will be unknown
 		let headers_in_message = headers.len();
+		self.unconnecting_headers.remove(&peer_index);
+		// full `headers` message => peer is likely to have more headers for us
+		let peer_had_more_headers = self.peers_with_more_headers.remove(&peer_index);
+		if headers_in_message == types::HEADERS_MAX_HEADERS_LEN {
+			self.peers_with_more_headers.insert(peer_index);
+		}
 		let headers = self.find_unknown_headers(headers);
 		if headers.is_empty() {
 			trace!(target: "sync", "Ignoring {} known headers from peer#{}", headers_in_message, peer_index);
 			// but this peer is still useful for synchronization
 			self.peers_tasks.useful_peer(peer_index);
+			// the last peer has sent us all its headers => blocks could be requested in headers-first mode
+			if peer_had_more_headers && self.peers_with_more_headers.is_empty() {
+				self.execute_synchronization_tasks(None, None);
+			}
 			return None;
 		}
 
@@ -362,6 +439,20 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		// remember headers that we're verifying
 		self.chain.verify_headers(&headers);
 
+		// headers are not extending the best headers chain (or headers that are currently verified)
+		// => blocks locator, sent to peers, won't lead to the rest of these headers. So ask the same
+		// peer for the continuation explicitly
+		let parent_hash = &headers[0].raw.previous_header_hash;
+		if self.config.sync_mode == SyncMode::HeadersFirst
+			&& headers_in_message == types::HEADERS_MAX_HEADERS_LEN
+			&& *parent_hash != self.chain.best_block_header().hash
+			&& self.chain.block_state(parent_hash) != BlockState::VerifyingHeader {
+			let mut block_locator_hashes = vec![headers[headers.len() - 1].hash.clone()];
+			block_locator_hashes.extend(self.chain.block_locator_hashes());
+			self.executor.execute(Task::GetHeaders(peer_index, types::GetHeaders::with_block_locator_hashes(block_locator_hashes)));
+			self.peers_tasks.on_headers_requested(peer_index);
+		}
+
 		Some(headers)
 	}
 
@@ -496,12 +587,39 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 	}
 
 	fn on_transaction(&mut self, peer_index: PeerIndex, transaction: IndexedTransaction) -> Option<VecDeque<IndexedTransaction>> {
+		// in blocks-only mode we only accept transactions from peers with relay permission
+		if !self.accepts_transactions_from(peer_index) {
+			trace!(target: "sync", "Ignoring transaction {} from peer#{} in blocks-only mode", transaction.hash.to_reversed_str(), peer_index);
+			return None;
+		}
+
 		// check if this transaction is already known
 		if self.orphaned_transactions_pool.contains(&transaction.hash) ||
 			self.chain.transaction_state(&transaction.hash) != TransactionState::Unknown {
 			return None;
 		}
 
+		// check if this transaction has been recently rejected
+		if self.recent_rejects.contains(&*transaction.hash) {
+			trace!(target: "sync", "Ignoring recently rejected transaction {} from peer#{}", transaction.hash.to_reversed_str(), peer_index);
+			return None;
+		}
+
+		// check if this transaction is a malleated variant of already verified transaction
+		let relay_id = transaction.raw.relay_id();
+		if let Some(known_hash) = self.relay_ids.malleated_variant_of(&relay_id).cloned() {
+			if self.orphaned_transactions_pool.contains(&known_hash) ||
+				self.chain.transaction_state(&known_hash) != TransactionState::Unknown {
+				trace!(target: "sync", "Ignoring transaction {} from peer#{}: malleated variant of {}",
+					transaction.hash.to_reversed_str(), peer_index, known_hash.to_reversed_str());
+				return None;
+			}
+		}
+
+		// remember relay id of the transaction before verification, so that its malleated variants
+		// are ignored while this transaction is verifying or waiting for its parents
+		self.relay_ids.insert(relay_id, transaction.hash.clone());
+
 		self.process_peer_transaction(Some(peer_index), transaction, true)
 	}
 
@@ -540,6 +658,73 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
+	fn on_compact_block(&mut self, peer_index: PeerIndex, message: types::CompactBlock) -> Option<IndexedBlock> {
+		let BlockHeaderAndIDs { header, nonce, short_ids, prefilled_transactions } = message.header;
+		let header = IndexedBlockHeader::from_raw(header);
+		self.peers.hash_known_as(peer_index, header.hash.clone(), KnownHashType::Block);
+
+		// we are only interested in new blocks, including blocks that are scheduled or requested
+		// (maybe from other peer): reconstructing them from memory pool is what compact blocks are for
+		match self.chain.block_state(&header.hash) {
+			BlockState::Unknown | BlockState::Scheduled | BlockState::Requested => (),
+			_ => return None,
+		}
+		if self.orphaned_blocks_pool.contains_unknown_block(&header.hash) {
+			return None;
+		}
+
+		// the block must be connecting to the known chain
+		match self.chain.block_state(&header.raw.previous_header_hash) {
+			BlockState::Unknown | BlockState::DeadEnd => {
+				trace!(target: "sync", "Ignoring compact block {} from peer#{}: parent {} is unknown",
+					header.hash.to_reversed_str(), peer_index, header.raw.previous_header_hash.to_reversed_str());
+				let block_locator_hashes = self.chain.block_locator_hashes();
+				self.executor.execute(Task::GetHeaders(peer_index, types::GetHeaders::with_block_locator_hashes(block_locator_hashes)));
+				return None;
+			},
+			_ => (),
+		}
+
+		// check header (including PoW) before looking for block transactions in the memory pool
+		let current_time = time::get_time().sec as u32;
+		if let Err(error) = HeaderVerifier::new(&header, &self.config.consensus, current_time).check() {
+			self.peers.dos(peer_index, &format!("Got 'cmpctblock' message with invalid header: {:?}", error));
+			return None;
+		}
+
+		let block_hash = header.hash.clone();
+		let memory_pool = self.chain.memory_pool();
+		let partial_block = PartialBlock::new(header, nonce, short_ids, prefilled_transactions, &*memory_pool.read());
+		match partial_block {
+			Ok(partial_block) => self.on_partial_block(peer_index, partial_block),
+			Err(PartialBlockError::ShortIdsCollision) => {
+				self.request_full_block(peer_index, block_hash);
+				None
+			},
+			Err(PartialBlockError::Malformed(error)) => {
+				self.peers.dos(peer_index, &format!("Got malformed 'cmpctblock' message: {}", error));
+				None
+			},
+		}
+	}
+
+	fn on_block_txn(&mut self, peer_index: PeerIndex, message: types::BlockTxn) -> Option<IndexedBlock> {
+		let mut partial_block = match self.partial_blocks.remove(&peer_index) {
+			Some(partial_block) if partial_block.hash() == &message.request.blockhash => partial_block,
+			_ => {
+				self.peers.penalize(peer_index, UNREQUESTED_BLOCKTXN_BAN_SCORE, "Got unrequested 'blocktxn' message");
+				return None;
+			},
+		};
+
+		if let Err(PartialBlockError::Malformed(error)) = partial_block.fill(message.request.transactions) {
+			self.peers.dos(peer_index, &format!("Got malformed 'blocktxn' message: {}", error));
+			return None;
+		}
+
+		self.on_partial_block(peer_index, partial_block)
+	}
+
 	/// Execute after last block from this peer in NearlySaturated state is verified.
 	/// If there are no verifying blocks from this peer or we are not in the NearlySaturated state => execute immediately.
 	fn after_peer_nearly_blocks_verified(&mut self, peer_index: PeerIndex, future: EmptyBoxFuture) {
@@ -716,7 +901,8 @@ impl<T> ClientCore for SynchronizationClientCore<T> where T: TaskExecutor {
 				{
 					// TODO: only request minimal number of blocks, if other urgent blocks are requested
 					let scheduled_hashes_len = self.chain.length_of_blocks_state(BlockState::Scheduled);
-					if requested_hashes_len + verifying_hashes_len < MAX_REQUESTED_BLOCKS + MAX_VERIFYING_BLOCKS && scheduled_hashes_len != 0 {
+					if requested_hashes_len + verifying_hashes_len < MAX_REQUESTED_BLOCKS + MAX_VERIFYING_BLOCKS && scheduled_hashes_len != 0
+						&& self.is_blocks_download_allowed() {
 						let chunk_size = min(limits.max_blocks_in_request, max(scheduled_hashes_len / blocks_idle_peers_len, limits.min_blocks_in_request));
 						let hashes_to_request_len = chunk_size * blocks_idle_peers_len;
 						let hashes_to_request = self.chain.request_blocks_hashes(hashes_to_request_len);
@@ -784,7 +970,7 @@ impl<T> BlockVerificationSink for CoreVerificationSink<T> where T: TaskExecutor
 	}
 
 	/// Process failed block verification
-	fn on_block_verification_error(&self, err: &str, hash: &H256) {
+	fn on_block_verification_error(&self, err: &VerificationError, hash: &H256) {
 		self.core.lock().on_block_verification_error(err, hash)
 	}
 }
@@ -796,7 +982,7 @@ impl<T> TransactionVerificationSink for CoreVerificationSink<T> where T: TaskExe
 	}
 
 	/// Process failed transaction verification
-	fn on_transaction_verification_error(&self, err: &str, hash: &H256) {
+	fn on_transaction_verification_error(&self, err: &TransactionError, hash: &H256) {
 		self.core.lock().on_transaction_verification_error(err, hash)
 	}
 }
@@ -804,6 +990,7 @@ impl<T> TransactionVerificationSink for CoreVerificationSink<T> where T: TaskExe
 impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 	/// Create new synchronization client core
 	pub fn new(config: Config, shared_state: SynchronizationStateRef, peers: PeersRef, executor: Arc<T>, chain: Chain) -> ClientCoreRef<Self> {
+		let peers_config = ManagePeersConfig::from(&config.timeouts);
 		let sync = Arc::new(Mutex::new(
 			SynchronizationClientCore {
 				shared_state: shared_state,
@@ -819,6 +1006,8 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 				verifying_blocks_futures: HashMap::new(),
 				verifying_transactions_sinks: HashMap::new(),
 				do_not_relay: HashSet::new(),
+				relay_ids: RelayIdFilter::default(),
+				recent_rejects: RollingBloomFilter::new(MAX_RECENT_REJECTS, RECENT_REJECTS_FP_RATE),
 				block_speed_meter: AverageSpeedMeter::with_inspect_items(SYNC_SPEED_BLOCKS_TO_INSPECT),
 				sync_speed_meter: AverageSpeedMeter::with_inspect_items(BLOCKS_SPEED_BLOCKS_TO_INSPECT),
 				config: config,
@@ -826,13 +1015,16 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 				last_dup_time: 0f64,
 				new_headers_receival_timestamp: 0f64,
 				last_headers_request_best_number: 0,
+				unconnecting_headers: HashMap::new(),
+				partial_blocks: HashMap::new(),
+				peers_with_more_headers: HashSet::new(),
 			}
 		));
 
 		{
 			let csync = Arc::downgrade(&sync);
 			let mut lsync = sync.lock();
-			lsync.management_worker = Some(ManagementWorker::new(csync));
+			lsync.management_worker = Some(ManagementWorker::new(csync, peers_config));
 		}
 
 		sync
@@ -880,6 +1072,37 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		&mut self.orphaned_transactions_pool
 	}
 
+	/// Convert compact block into the block if all transactions are known, or request missing transactions
+	fn on_partial_block(&mut self, peer_index: PeerIndex, partial_block: PartialBlock) -> Option<IndexedBlock> {
+		let missing_indexes = partial_block.missing_indexes();
+		if !missing_indexes.is_empty() {
+			let request = types::GetBlockTxn {
+				request: BlockTransactionsRequest {
+					blockhash: partial_block.hash().clone(),
+					indexes: missing_indexes,
+				},
+			};
+			self.executor.execute(Task::GetBlockTxn(peer_index, request));
+			self.partial_blocks.insert(peer_index, partial_block);
+			return None;
+		}
+
+		// memory pool transaction could have the same short id as the block transaction
+		// => merkle root mismatch => request full block
+		let block_hash = partial_block.hash().clone();
+		let block = partial_block.into_block();
+		if block.is_none() {
+			self.request_full_block(peer_index, block_hash);
+		}
+		block
+	}
+
+	/// Request full block from the peer
+	fn request_full_block(&mut self, peer_index: PeerIndex, block_hash: H256) {
+		let inventory = vec![InventoryVector::block(block_hash)];
+		self.executor.execute(Task::GetData(peer_index, types::GetData::with_inventory(inventory)));
+	}
+
 	/// Print synchronization information
 	pub fn print_synchronization_information(&mut self) {
 		if let State::Synchronizing(timestamp, num_of_blocks) = self.state {
@@ -911,6 +1134,22 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
+	/// Forget about blocks that have failed given category of checks, so that they are
+	/// synchronized and verified again when provided by peers. Returns number of reconsidered blocks.
+	pub fn reconsider_failed_blocks(&mut self, category: BlockFailureCategory) -> usize {
+		let reconsidered = self.chain.reconsider_dead_end_blocks(category);
+		if !reconsidered.is_empty() {
+			info!(target: "sync", "Reconsidering {} blocks that have failed {:?} checks", reconsidered.len(), category);
+			self.execute_synchronization_tasks(None, None);
+		}
+		reconsidered.len()
+	}
+
+	/// Returns true if loose transactions from this peer are accepted
+	fn accepts_transactions_from(&self, peer_index: PeerIndex) -> bool {
+		!self.config.blocks_only || self.peers.permissions(peer_index).relay
+	}
+
 	/// Process new peer transaction
 	fn process_peer_transaction(&mut self, _peer_index: Option<PeerIndex>, transaction: IndexedTransaction, relay: bool) -> Option<VecDeque<IndexedTransaction>> {
 		match self.try_append_transaction(transaction.clone(), relay) {
@@ -1091,7 +1330,10 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 				headers[0].hash.to_reversed_str(),
 				headers[headers.len() - 1].hash.to_reversed_str(),
 			);
-			self.chain.schedule_blocks_headers(headers);
+			match self.config.sync_mode {
+				SyncMode::Interleaved => self.chain.schedule_blocks_headers(headers),
+				SyncMode::HeadersFirst => self.chain.schedule_best_blocks_headers(headers),
+			}
 		}
 
 		// switch to synchronization state
@@ -1128,7 +1370,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 			);
 		}
 
-		self.chain.mark_dead_end_block(&hash);
+		self.chain.mark_dead_end_block(&hash, BlockFailureCategory::Header);
 		self.execute_synchronization_tasks(None, None);
 	}
 
@@ -1140,6 +1382,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		let needs_relay = !self.do_not_relay.remove(block.hash());
 
 		let block_hash = block.hash().clone();
+		let mut is_new_block = false;
 		// insert block to the storage
 		match {
 			// remove block from verification queue
@@ -1147,6 +1390,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 			// or it is removed earlier, when block was removed from the verifying queue
 			if self.chain.forget_block_with_state_leave_header(block.hash(), BlockState::Verifying) != HashPosition::Missing {
 				// block was in verification queue => insert to storage
+				is_new_block = true;
 				self.chain.insert_best_block(block)
 			} else {
 				Ok(BlockInsertionResult::default())
@@ -1156,6 +1400,11 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 				// update shared state
 				self.shared_state.update_best_storage_block_height(self.chain.best_storage_block().number);
 
+				// transactions, rejected at previous best block, could be valid now
+				if !insert_result.canonized_blocks_hashes.is_empty() {
+					self.recent_rejects.reset();
+				}
+
 				// notify listener
 				if let Some(best_block_hash) = insert_result.canonized_blocks_hashes.last() {
 					if let Some(ref listener) = self.listener {
@@ -1163,6 +1412,21 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 					}
 				}
 
+				// notify listener about expired transactions. They're not announced to peers: there's no message
+				// to withdraw announced transaction and peers evict expired transactions at the same height anyway
+				if !insert_result.expired_transactions_hashes.is_empty() {
+					if let Some(ref listener) = self.listener {
+						listener.transactions_expired(&insert_result.expired_transactions_hashes);
+					}
+				}
+
+				// peer has provided us with the new valid block => remember it (protects peer from eviction)
+				if is_new_block {
+					if let Some(connection) = self.verifying_blocks_by_peer.get(&block_hash).and_then(|peer_index| self.peers.connection(*peer_index)) {
+						connection.report_new_block();
+					}
+				}
+
 				// awake threads, waiting for this block insertion
 				self.awake_waiting_threads(&block_hash);
 
@@ -1171,10 +1435,11 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 
 				// relay block to our peers
 				if needs_relay && (self.state.is_saturated() || self.state.is_nearly_saturated()) {
-					for block_hash in insert_result.canonized_blocks_hashes {
-						if let Some(block) = self.chain.storage().block(block_hash.into()) {
-							self.executor.execute(Task::RelayNewBlock(block));
-						}
+					let blocks: Vec<_> = insert_result.canonized_blocks_hashes.into_iter()
+						.filter_map(|block_hash| self.chain.storage().block(block_hash.into()))
+						.collect();
+					if !blocks.is_empty() {
+						self.executor.execute(Task::RelayNewBlocks(blocks));
 					}
 				}
 
@@ -1197,7 +1462,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
-	fn on_block_verification_error(&mut self, err: &str, hash: &H256) {
+	fn on_block_verification_error(&mut self, err: &VerificationError, hash: &H256) {
 		warn!(target: "sync", "Block {:?} verification failed with error {:?}", hash.to_reversed_str(), err);
 
 		// remove flags
@@ -1217,7 +1482,7 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		self.chain.forget_block_with_children(hash);
 
 		// mark failed block as dead end (this branch won't be synchronized)
-		self.chain.mark_dead_end_block(hash);
+		self.chain.mark_dead_end_block(hash, BlockFailureCategory::from_verification_error(err));
 
 		// awake threads, waiting for this block insertion
 		self.awake_waiting_threads(hash);
@@ -1238,7 +1503,30 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 
 		// transaction was in verification queue => insert to memory pool
-		self.chain.insert_verified_transaction(transaction.clone());
+		let replaced = match self.chain.insert_verified_transaction(transaction.clone()) {
+			Ok(replaced) => replaced,
+			Err(err) => {
+				warn!(target: "sync", "Transaction {} replacement rejected: {}", transaction.hash.to_reversed_str(), err);
+				let err = TransactionError::ReplacementRejected(err);
+				self.recent_rejects.insert(&*transaction.hash);
+				if let Some(future_sink) = self.verifying_transactions_sinks.remove(&transaction.hash) {
+					future_sink.on_transaction_verification_error(&err, &transaction.hash);
+				}
+				return;
+			},
+		};
+
+		// remember relay id of accepted transaction (it is already known if transaction came from peer).
+		// Relay id of rejected transaction is left in the filter, but its variants are verified again,
+		// because rejected transaction is neither orphaned nor known to the chain
+		self.relay_ids.insert(transaction.raw.relay_id(), transaction.hash.clone());
+
+		// notify listener
+		if !replaced.is_empty() {
+			if let Some(ref listener) = self.listener {
+				listener.transactions_replaced(&replaced, &transaction.hash);
+			}
+		}
 
 		// calculate transaction fee rate
 		let transaction_fee_rate = transaction_fee_rate(&self.chain, &transaction.raw);
@@ -1254,12 +1542,18 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
-	fn on_transaction_verification_error(&mut self, err: &str, hash: &H256) {
+	fn on_transaction_verification_error(&mut self, err: &TransactionError, hash: &H256) {
 		warn!(target: "sync", "Transaction {} verification failed with error {:?}", hash.to_reversed_str(), err);
 
 		// remove flags
 		self.do_not_relay.remove(hash);
 
+		// do not download && verify this transaction again
+		// (unless verification has been aborted by the relay policy timeout - it could pass later)
+		if *err != TransactionError::VerificationTimeout {
+			self.recent_rejects.insert(&**hash);
+		}
+
 		// forget for this transaction and all its children
 		self.chain.forget_verifying_transaction_with_children(hash);
 
@@ -1297,6 +1591,21 @@ impl<T> SynchronizationClientCore<T> where T: TaskExecutor {
 		}
 	}
 
+	/// Returns true if scheduled blocks could be requested in current synchronization mode.
+	fn is_blocks_download_allowed(&self) -> bool {
+		match self.config.sync_mode {
+			SyncMode::Interleaved => true,
+			// headers chain is downloaded (from every peer) && verified
+			// || we have stopped requesting headers, because scheduled queue is full
+			// || peers have not sent us new headers for a while (i.e. last `headers` message has been full, but there are no more headers)
+			SyncMode::HeadersFirst => self.chain.length_of_blocks_state(BlockState::VerifyingHeader) == 0 && (
+				self.peers_with_more_headers.is_empty()
+					|| self.chain.length_of_blocks_state(BlockState::Scheduled) >= MAX_SCHEDULED_HASHES
+					|| precise_time_s() - self.new_headers_receival_timestamp > HEADERS_FIRST_SILENCE_INTERVAL_S
+			),
+		}
+	}
+
 	fn compute_num_peers_for_headers_request(&self) -> usize {
 		// if there are no active requests => ask immediately
 		if self.peers_tasks.ordered_headers_requests().is_empty() {
@@ -1337,16 +1646,18 @@ pub mod tests {
 
 	use std::sync::Arc;
 	use parking_lot::{Mutex, RwLock};
-	use chain::{Block, Transaction, IndexedBlock};
+	use chain::{Block, Transaction, IndexedBlock, IndexedBlockHeader};
 	use db::BlockChainDatabase;
-	use message::common::InventoryVector;
+	use message::common::{InventoryVector, BlockHeaderAndIDs, PrefilledTransaction, BlockTransactions, BlockTransactionsRequest};
 	use message::{Services, types};
-	use miner::MemoryPool;
+	use miner::{MemoryPool, MAX_BIP125_RBF_SEQUENCE};
 	use network::{ConsensusParams, Network};
+	use p2p::PeerPermissions;
+	use primitives::compact::Compact;
 	use primitives::hash::H256;
-	use verification::BackwardsCompatibleChainVerifier as ChainVerifier;
+	use verification::{BackwardsCompatibleChainVerifier as ChainVerifier, Error as VerificationError, TransactionError};
 	use inbound_connection::tests::DummyOutboundSyncConnection;
-	use synchronization_chain::{Chain, BlockState};
+	use synchronization_chain::{Chain, BlockState, BlockFailureCategory};
 	use synchronization_client::{SynchronizationClient, Client};
 	use synchronization_peers::PeersImpl;
 	use synchronization_executor::Task;
@@ -1354,13 +1665,16 @@ pub mod tests {
 	use synchronization_verifier::tests::DummyVerifier;
 	use utils::SynchronizationState;
 	use types::{PeerIndex, StorageRef, SynchronizationStateRef, ClientCoreRef};
-	use super::{Config, SynchronizationClientCore, ClientCore, CoreVerificationSink};
+	use synchronization_manager::{ManageSynchronizationStallConfig, SynchronizationProgress, manage_synchronization_stall};
+	use super::{Config, SyncMode, SynchronizationClientCore, ClientCore, CoreVerificationSink, MAX_BLOCKS_TO_ANNOUNCE};
 	use super::super::SyncListener;
 
 	#[derive(Default)]
 	struct DummySyncListenerData {
 		pub is_synchronizing: bool,
 		pub best_blocks: Vec<H256>,
+		pub replacements: Vec<(Vec<H256>, H256)>,
+		pub expired: Vec<H256>,
 	}
 
 	struct DummySyncListener {
@@ -1383,6 +1697,14 @@ pub mod tests {
 		fn best_storage_block_inserted(&self, block_hash: &H256) {
 			self.data.lock().best_blocks.push(block_hash.clone());
 		}
+
+		fn transactions_replaced(&self, replaced: &[H256], replacement: &H256) {
+			self.data.lock().replacements.push((replaced.to_vec(), replacement.clone()));
+		}
+
+		fn transactions_expired(&self, expired: &[H256]) {
+			self.data.lock().expired.extend(expired.iter().cloned());
+		}
 	}
 
 	fn storage_with_block1() -> StorageRef {
@@ -1390,6 +1712,10 @@ pub mod tests {
 	}
 
 	fn create_sync(storage: Option<StorageRef>, verifier: Option<DummyVerifier>) -> (Arc<DummyTaskExecutor>, ClientCoreRef<SynchronizationClientCore<DummyTaskExecutor>>, Arc<SynchronizationClient<DummyTaskExecutor, DummyVerifier>>) {
+		create_sync_with_mode(storage, verifier, SyncMode::Interleaved)
+	}
+
+	fn create_sync_with_mode(storage: Option<StorageRef>, verifier: Option<DummyVerifier>, sync_mode: SyncMode) -> (Arc<DummyTaskExecutor>, ClientCoreRef<SynchronizationClientCore<DummyTaskExecutor>>, Arc<SynchronizationClient<DummyTaskExecutor, DummyVerifier>>) {
 		let sync_peers = Arc::new(PeersImpl::default());
 		let storage = match storage {
 			Some(storage) => storage,
@@ -1399,7 +1725,7 @@ pub mod tests {
 		let memory_pool = Arc::new(RwLock::new(MemoryPool::new()));
 		let chain = Chain::new(storage.clone(), memory_pool.clone());
 		let executor = DummyTaskExecutor::new();
-		let config = Config { close_connection_on_bad_block: true };
+		let config = Config { close_connection_on_bad_block: true, blocks_only: false, timeouts: Default::default(), sync_mode: sync_mode, consensus: ConsensusParams::new(Network::Unitest) };
 
 		let chain_verifier = Arc::new(ChainVerifier::new(storage.clone(), ConsensusParams::new(Network::Unitest)));
 		let client_core = SynchronizationClientCore::new(config, sync_state.clone(), sync_peers.clone(), executor.clone(), chain);
@@ -1599,7 +1925,7 @@ pub mod tests {
 		assert!(tasks.iter().any(|t| t == &request_block_headers_genesis_and(2, vec![block.hash()])));
 		assert!(tasks.iter().any(|t| t == &Task::MemoryPool(1)));
 		assert!(tasks.iter().any(|t| t == &Task::MemoryPool(2)));
-		assert!(tasks.iter().any(|t| t == &Task::RelayNewBlock(block.clone().into())));
+		assert!(tasks.iter().any(|t| t == &Task::RelayNewBlocks(vec![block.clone().into()])));
 	}
 
 	#[test]
@@ -1691,7 +2017,10 @@ pub mod tests {
 		let (executor, core, sync) = create_sync(None, None);
 
 		let b169 = test_data::block_h169();
-		sync.on_headers(1, vec![b169.block_header.into()]);
+		let headers: Vec<IndexedBlockHeader> = (0..MAX_BLOCKS_TO_ANNOUNCE + 1)
+			.map(|_| b169.block_header.clone().into())
+			.collect();
+		sync.on_headers(1, headers);
 
 		let tasks = executor.take_tasks();
 		assert_eq!(tasks, vec![]);
@@ -1701,6 +2030,26 @@ pub mod tests {
 		assert_eq!(chain.information().headers.total, 0);
 	}
 
+	#[test]
+	fn synchronization_requests_headers_on_unconnecting_announcement() {
+		let (executor, core, sync) = create_sync(None, None);
+
+		let block1: Block = test_data::block_h1();
+		let block2: Block = test_data::block_h2();
+
+		// parent of announced block is unknown => ask for headers chain
+		sync.on_headers(1, vec![block2.block_header.clone().into()]);
+		let tasks = executor.take_tasks();
+		assert_eq!(tasks, vec![request_block_headers_genesis(1)]);
+		assert_eq!(core.lock().information().chain.requested, 0);
+
+		// when connecting headers are received => blocks are requested
+		sync.on_headers(1, vec![block1.block_header.clone().into(), block2.block_header.clone().into()]);
+		let tasks = executor.take_tasks();
+		assert_eq!(tasks, vec![request_block_headers_genesis_and(1, vec![block2.hash(), block1.hash()]),
+			request_blocks(1, vec![block1.hash(), block2.hash()])]);
+	}
+
 	#[test]
 	fn synchronization_works_for_forks_from_db_best_block() {
 		let genesis = test_data::genesis();
@@ -1994,6 +2343,33 @@ pub mod tests {
 		]))]);
 	}
 
+	#[test]
+	fn recently_rejected_transaction_is_not_requested() {
+		let (executor, core, sync) = create_sync(None, None);
+
+		core.lock().on_transaction_verification_error(&TransactionError::Misplaced, &H256::from(0));
+
+		sync.on_inventory(0, types::Inv::with_inventory(vec![
+			InventoryVector::tx(H256::from(0)),
+			InventoryVector::tx(H256::from(1)),
+		]));
+		assert_eq!(executor.take_tasks(), vec![Task::GetData(0, types::GetData::with_inventory(vec![
+			InventoryVector::tx(H256::from(1))
+		]))]);
+	}
+
+	#[test]
+	fn timed_out_transaction_is_requested_again() {
+		let (executor, core, sync) = create_sync(None, None);
+
+		core.lock().on_transaction_verification_error(&TransactionError::VerificationTimeout, &H256::from(0));
+
+		sync.on_inventory(0, types::Inv::with_inventory(vec![InventoryVector::tx(H256::from(0))]));
+		assert_eq!(executor.take_tasks(), vec![Task::GetData(0, types::GetData::with_inventory(vec![
+			InventoryVector::tx(H256::from(0))
+		]))]);
+	}
+
 	#[test]
 	fn known_transaction_is_not_requested() {
 		let (executor, _, sync) = create_sync(None, None);
@@ -2049,6 +2425,93 @@ pub mod tests {
 		assert_eq!(core.lock().information().orphaned_transactions, 1);
 	}
 
+	#[test]
+	fn transactions_are_ignored_in_blocks_only_mode() {
+		let (executor, core, sync) = create_sync(Some(storage_with_block1()), None);
+		{
+			let mut core = core.lock();
+			core.config.blocks_only = true;
+			core.peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+			core.peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+			core.peers.set_permissions(1, PeerPermissions { relay: true, ..Default::default() });
+		}
+
+		let input_tx = test_data::block_h1().transactions[0].clone();
+		let tx1: Transaction = test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(100).into();
+
+		// transactions from regular peers are neither requested, nor accepted
+		sync.on_inventory(0, types::Inv::with_inventory(vec![InventoryVector::tx(tx1.hash())]));
+		assert_eq!(executor.take_tasks(), vec![]);
+		sync.on_transaction(0, tx1.clone().into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 0);
+
+		// transactions from peers with relay permission are still processed
+		sync.on_inventory(1, types::Inv::with_inventory(vec![InventoryVector::tx(tx1.hash())]));
+		assert_eq!(executor.take_tasks(), vec![Task::GetData(1, types::GetData::with_inventory(vec![InventoryVector::tx(tx1.hash())]))]);
+		sync.on_transaction(1, tx1.into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 1);
+
+		// blocks are processed as usual
+		let b2 = test_data::block_h2();
+		sync.on_headers(0, vec![b2.block_header.clone().into()]);
+		sync.on_block(0, b2.into());
+		assert_eq!(core.lock().information().chain.stored, 3);
+	}
+
+	#[test]
+	fn malleated_transaction_variant_is_ignored() {
+		let (_, core, sync) = create_sync(Some(storage_with_block1()), None);
+
+		let input_tx = test_data::block_h1().transactions[0].clone();
+		let tx1: Transaction = test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(100).into();
+		let mut tx2 = tx1.clone();
+		tx2.inputs[0].script_sig = "51".into();
+		assert!(tx1.hash() != tx2.hash());
+
+		sync.on_transaction(0, tx1.into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 1);
+
+		// malleated variant is not verified again
+		sync.on_transaction(1, tx2.into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 1);
+	}
+
+	#[test]
+	fn malleated_variant_of_orphaned_transaction_is_ignored() {
+		let (_, core, sync) = create_sync(None, None);
+
+		let tx1: Transaction = test_data::TransactionBuilder::with_default_input(0).into();
+		let mut tx2 = tx1.clone();
+		tx2.inputs[0].script_sig = "51".into();
+
+		sync.on_transaction(0, tx1.into());
+		assert_eq!(core.lock().information().orphaned_transactions, 1);
+
+		// malleated variant of transaction, which is waiting for its parents, is ignored
+		sync.on_transaction(1, tx2.into());
+		assert_eq!(core.lock().information().orphaned_transactions, 1);
+	}
+
+	#[test]
+	fn malleated_variant_of_invalid_transaction_is_verified() {
+		let input_tx = test_data::block_h1().transactions[0].clone();
+		let tx1: Transaction = test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(100).into();
+		let mut tx2 = tx1.clone();
+		tx2.inputs[0].script_sig = "51".into();
+
+		let mut verifier = DummyVerifier::default();
+		verifier.error_when_verifying_transaction(tx1.hash(), TransactionError::Misplaced);
+		let (_, core, sync) = create_sync(Some(storage_with_block1()), Some(verifier));
+
+		// relay id of invalid transaction isn't remembered
+		sync.on_transaction(0, tx1.into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 0);
+
+		// => its malleated variant is verified
+		sync.on_transaction(1, tx2.into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 1);
+	}
+
 	#[test]
 	fn orphaned_transaction_is_verified_when_input_is_received() {
 		let input_tx = test_data::block_h1().transactions[0].clone();
@@ -2097,7 +2560,7 @@ pub mod tests {
 
 		// simulate verification during b21 verification
 		let mut dummy_verifier = DummyVerifier::default();
-		dummy_verifier.error_when_verifying(b21.hash(), "simulated");
+		dummy_verifier.error_when_verifying(b21.hash(), VerificationError::Empty);
 
 		let (_, _, sync) = create_sync(None, Some(dummy_verifier));
 
@@ -2159,7 +2622,7 @@ pub mod tests {
 			let tasks = executor.take_tasks();
 			assert_eq!(tasks, vec![
 				request_block_headers_genesis_and(2, vec![b2.hash(), b1.hash(), b0.hash()]),
-				Task::RelayNewBlock(b2.clone().into())
+				Task::RelayNewBlocks(vec![b2.clone().into()])
 			]);
 		}
 
@@ -2169,7 +2632,7 @@ pub mod tests {
 		// we were in nearly saturated state => block is relayed
 		{
 			let tasks = executor.take_tasks();
-			assert!(tasks.iter().any(|t| t == &Task::RelayNewBlock(b3.clone().into())));
+			assert!(tasks.iter().any(|t| t == &Task::RelayNewBlocks(vec![b3.clone().into()])));
 		}
 	}
 
@@ -2204,16 +2667,39 @@ pub mod tests {
 
 		// simulate verification error during b0 verification
 		let mut dummy_verifier = DummyVerifier::default();
-		dummy_verifier.error_when_verifying(b0.hash(), "simulated");
+		dummy_verifier.error_when_verifying(b0.hash(), VerificationError::Empty);
 
 		let (_, core, sync) = create_sync(None, Some(dummy_verifier));
 
-		core.lock().peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		let connection = DummyOutboundSyncConnection::new();
+		core.lock().peers.insert(0, Services::default(), connection.clone());
 		assert!(core.lock().peers.enumerate().contains(&0));
 
 		sync.on_block(0, b0.into());
 
 		assert!(!core.lock().peers.enumerate().contains(&0));
+		assert_eq!(connection.messages.lock().get("ban"), Some(&1));
+	}
+
+	#[test]
+	fn new_block_is_reported_to_connection_of_its_provider() {
+		let genesis = test_data::genesis();
+		let b0 = test_data::block_builder().header().parent(genesis.hash()).build().build();
+
+		let (_, core, sync) = create_sync(None, None);
+
+		let connection0 = DummyOutboundSyncConnection::new();
+		let connection1 = DummyOutboundSyncConnection::new();
+		core.lock().peers.insert(0, Services::default(), connection0.clone());
+		core.lock().peers.insert(1, Services::default(), connection1.clone());
+
+		// new block is accepted => reported
+		sync.on_block(0, b0.clone().into());
+		assert_eq!(connection0.messages.lock().get("newblock"), Some(&1));
+
+		// already known block isn't reported
+		sync.on_block(1, b0.into());
+		assert_eq!(connection1.messages.lock().get("newblock"), None);
 	}
 
 	#[test]
@@ -2226,7 +2712,7 @@ pub mod tests {
 		let (_, core, sync) = create_sync(None, None);
 		{
 			let mut core = core.lock(); let chain = core.chain();
-			chain.mark_dead_end_block(&b0.hash());
+			chain.mark_dead_end_block(&b0.hash(), BlockFailureCategory::Other);
 		}
 
 		core.lock().peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
@@ -2247,7 +2733,7 @@ pub mod tests {
 		let (_, core, sync) = create_sync(None, None);
 		{
 			let mut core = core.lock(); let chain = core.chain();
-			chain.mark_dead_end_block(&b1.hash());
+			chain.mark_dead_end_block(&b1.hash(), BlockFailureCategory::Other);
 		}
 
 //		core.lock().set_verify_headers(true);
@@ -2267,7 +2753,7 @@ pub mod tests {
 		let (_, core, sync) = create_sync(None, None);
 		{
 			let mut core = core.lock(); let chain = core.chain();
-			chain.mark_dead_end_block(&b0.hash());
+			chain.mark_dead_end_block(&b0.hash(), BlockFailureCategory::Other);
 		}
 
 		core.lock().peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
@@ -2287,7 +2773,7 @@ pub mod tests {
 		let (_, core, sync) = create_sync(None, None);
 		{
 			let mut core = core.lock(); let chain = core.chain();
-			chain.mark_dead_end_block(&b0.hash());
+			chain.mark_dead_end_block(&b0.hash(), BlockFailureCategory::Other);
 		}
 
 		core.lock().peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
@@ -2298,6 +2784,31 @@ pub mod tests {
 		assert!(!core.lock().peers.enumerate().contains(&0));
 	}
 
+	#[test]
+	fn reconsidered_failed_block_is_verified_again() {
+		let genesis = test_data::genesis();
+		let b0 = test_data::block_builder().header().parent(genesis.hash()).build().build();
+		let b1 = test_data::block_builder().header().parent(genesis.hash()).nonce(1.into()).build().build();
+
+		let (_, core, sync) = create_sync(None, None);
+		{
+			let mut core = core.lock(); let chain = core.chain();
+			chain.mark_dead_end_block(&b0.hash(), BlockFailureCategory::Scripts);
+			chain.mark_dead_end_block(&b1.hash(), BlockFailureCategory::Header);
+		}
+
+		// scripts verification has been fixed => reconsider blocks that have failed scripts checks
+		assert_eq!(core.lock().reconsider_failed_blocks(BlockFailureCategory::Scripts), 1);
+		assert_eq!(core.lock().chain().block_state(&b0.hash()), BlockState::Unknown);
+		assert_eq!(core.lock().chain().block_state(&b1.hash()), BlockState::DeadEnd);
+
+		// when block is provided again, it is verified and inserted into the storage
+		core.lock().peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		sync.on_block(0, b0.clone().into());
+		assert!(core.lock().peers.enumerate().contains(&0));
+		assert_eq!(core.lock().chain().block_state(&b0.hash()), BlockState::Stored);
+	}
+
 	#[test]
 	fn when_peer_does_not_respond_to_block_requests() {
 		let genesis = test_data::genesis();
@@ -2344,6 +2855,76 @@ pub mod tests {
 		sync.on_transaction(1, test_data::TransactionBuilder::with_default_input(0).into());
 	}
 
+	#[test]
+	fn when_transaction_replaces_memory_pool_transaction() {
+		let (_, core, sync) = create_sync(Some(storage_with_block1()), None);
+		let data = Arc::new(Mutex::new(DummySyncListenerData::default()));
+		sync.install_sync_listener(Box::new(DummySyncListener::new(data.clone())));
+
+		let input_tx = test_data::block_h1().transactions[0].clone();
+		let tx1: Transaction = test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(100).into();
+		let tx2: Transaction = test_data::TransactionBuilder::with_input(&tx1, 0).set_output(50).into();
+		let tx3: Transaction = test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(200).into();
+
+		sync.on_transaction(1, tx1.clone().into());
+		sync.on_transaction(1, tx2.clone().into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 2);
+		assert!(data.lock().replacements.is_empty());
+
+		// tx3 spends the same output as tx1 => both tx1 and its descendant tx2 are replaced
+		sync.on_transaction(1, tx3.clone().into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 1);
+		assert_eq!(data.lock().replacements, vec![(vec![tx1.hash(), tx2.hash()], tx3.hash())]);
+	}
+
+	#[test]
+	fn when_transaction_replaces_replaceable_memory_pool_transaction() {
+		let (_, core, sync) = create_sync(Some(storage_with_block1()), None);
+		let data = Arc::new(Mutex::new(DummySyncListenerData::default()));
+		sync.install_sync_listener(Box::new(DummySyncListener::new(data.clone())));
+
+		let input_tx = test_data::block_h1().transactions[0].clone();
+		let mut tx1: Transaction = test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(100).into();
+		tx1.inputs[0].sequence = MAX_BIP125_RBF_SEQUENCE;
+		let tx2: Transaction = test_data::TransactionBuilder::with_input(&tx1, 0).set_output(50).into();
+		let mut tx3: Transaction = test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(150).into();
+		tx3.inputs[0].sequence = MAX_BIP125_RBF_SEQUENCE;
+		let tx4: Transaction = test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(10).into();
+
+		sync.on_transaction(1, tx1.clone().into());
+		sync.on_transaction(1, tx2.clone().into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 2);
+
+		// tx3 pays less than tx1 + tx2 => replacement is rejected
+		sync.on_transaction(1, tx3.clone().into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 2);
+		assert!(data.lock().replacements.is_empty());
+
+		// tx4 pays more than tx1 + tx2 => both are replaced
+		sync.on_transaction(1, tx4.clone().into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 1);
+		assert_eq!(data.lock().replacements, vec![(vec![tx1.hash(), tx2.hash()], tx4.hash())]);
+	}
+
+	#[test]
+	fn when_memory_pool_transaction_expires() {
+		let (_, core, sync) = create_sync(Some(storage_with_block1()), None);
+		let data = Arc::new(Mutex::new(DummySyncListenerData::default()));
+		sync.install_sync_listener(Box::new(DummySyncListener::new(data.clone())));
+
+		let input_tx = test_data::block_h1().transactions[0].clone();
+		let tx: Transaction = test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(100).set_expiry_height(2).into();
+		sync.on_transaction(1, tx.clone().into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 1);
+
+		// transaction expires when block#2 is inserted => listener is notified
+		let b2 = test_data::block_h2();
+		sync.on_headers(0, vec![b2.block_header.clone().into()]);
+		sync.on_block(0, b2.into());
+		assert_eq!(core.lock().information().chain.transactions.transactions_count, 0);
+		assert_eq!(data.lock().expired, vec![tx.hash()]);
+	}
+
 	#[test]
 	fn when_transaction_replaces_locked_transaction() {
 		// TODO
@@ -2546,4 +3127,245 @@ pub mod tests {
 		assert_eq!(sync.chain().block_state(&hash1), BlockState::Stored);
 		assert_eq!(sync.chain().block_state(&hash2), BlockState::Stored); // pre-fix: Verifying
 	}
+
+	#[test]
+	fn compact_block_with_missing_transactions_is_completed_by_blocktxn() {
+		let (executor, core, _) = create_sync(None, None);
+
+		let coinbase: Transaction = test_data::TransactionBuilder::coinbase().into();
+		let transaction: Transaction = test_data::TransactionBuilder::with_output(10).into();
+		let block: IndexedBlock = test_data::block_builder()
+			.with_transactions(vec![coinbase.clone(), transaction.clone()])
+			.merkled_header().parent(test_data::genesis().hash()).build()
+			.build()
+			.into();
+
+		// compact block with unknown transaction is received => missing transaction is requested
+		let compact_block = types::CompactBlock {
+			header: BlockHeaderAndIDs {
+				header: block.header.raw.clone(),
+				nonce: 42,
+				short_ids: vec![1u8.into()],
+				prefilled_transactions: vec![PrefilledTransaction { index: 0, transaction: coinbase }],
+			},
+		};
+		assert_eq!(core.lock().on_compact_block(0, compact_block), None);
+		assert_eq!(executor.take_tasks(), vec![Task::GetBlockTxn(0, types::GetBlockTxn {
+			request: BlockTransactionsRequest {
+				blockhash: block.hash().clone(),
+				indexes: vec![1],
+			},
+		})]);
+
+		// missing transaction is received => block is reconstructed
+		let block_txn = types::BlockTxn {
+			request: BlockTransactions {
+				blockhash: block.hash().clone(),
+				transactions: vec![transaction],
+			},
+		};
+		assert_eq!(core.lock().on_block_txn(0, block_txn), Some(block));
+	}
+
+	#[test]
+	fn compact_block_of_requested_block_is_reconstructed() {
+		let (executor, core, _) = create_sync(None, None);
+
+		let coinbase: Transaction = test_data::TransactionBuilder::coinbase().into();
+		let transaction: Transaction = test_data::TransactionBuilder::with_output(10).into();
+		let block: IndexedBlock = test_data::block_builder()
+			.with_transactions(vec![coinbase.clone(), transaction.clone()])
+			.merkled_header().parent(test_data::genesis().hash()).build()
+			.build()
+			.into();
+
+		// block is requested after its header is verified
+		core.lock().on_headers(0, vec![block.header.clone()]);
+		core.lock().on_headers_verification_success(vec![block.header.clone()]);
+		assert_eq!(core.lock().chain().block_state(block.hash()), BlockState::Requested);
+		executor.take_tasks();
+
+		// compact block is received from other peer => missing transaction is requested
+		let compact_block = types::CompactBlock {
+			header: BlockHeaderAndIDs {
+				header: block.header.raw.clone(),
+				nonce: 42,
+				short_ids: vec![1u8.into()],
+				prefilled_transactions: vec![PrefilledTransaction { index: 0, transaction: coinbase }],
+			},
+		};
+		assert_eq!(core.lock().on_compact_block(1, compact_block), None);
+		assert_eq!(executor.take_tasks(), vec![Task::GetBlockTxn(1, types::GetBlockTxn {
+			request: BlockTransactionsRequest {
+				blockhash: block.hash().clone(),
+				indexes: vec![1],
+			},
+		})]);
+
+		// missing transaction is received => block is reconstructed
+		let block_txn = types::BlockTxn {
+			request: BlockTransactions {
+				blockhash: block.hash().clone(),
+				transactions: vec![transaction],
+			},
+		};
+		assert_eq!(core.lock().on_block_txn(1, block_txn), Some(block));
+	}
+
+	#[test]
+	fn compact_block_with_invalid_header_is_not_reconstructed() {
+		let (executor, core, _) = create_sync(None, None);
+
+		let coinbase: Transaction = test_data::TransactionBuilder::coinbase().into();
+		let compact_block = |block: &IndexedBlock| types::CompactBlock {
+			header: BlockHeaderAndIDs {
+				header: block.header.raw.clone(),
+				nonce: 42,
+				short_ids: vec![1u8.into()],
+				prefilled_transactions: vec![PrefilledTransaction { index: 0, transaction: coinbase.clone() }],
+			},
+		};
+
+		// block isn't connecting to the known chain => headers are requested
+		let orphan: IndexedBlock = test_data::block_builder()
+			.with_transaction(coinbase.clone())
+			.merkled_header().parent(1u8.into()).build()
+			.build()
+			.into();
+		assert_eq!(core.lock().on_compact_block(0, compact_block(&orphan)), None);
+		assert_eq!(executor.take_tasks(), vec![request_block_headers_genesis(0)]);
+
+		// block header has invalid PoW => missing transactions are not requested
+		let invalid_pow: IndexedBlock = test_data::block_builder()
+			.with_transaction(coinbase.clone())
+			.merkled_header().parent(test_data::genesis().hash()).bits(Compact::new(0x03000001)).build()
+			.build()
+			.into();
+		assert_eq!(core.lock().on_compact_block(0, compact_block(&invalid_pow)), None);
+		assert_eq!(executor.take_tasks(), vec![]);
+	}
+
+	#[test]
+	fn headers_first_sync_reassigns_blocks_of_stalled_peer() {
+		let (executor, core, sync) = create_sync_with_mode(None, None, SyncMode::HeadersFirst);
+		core.lock().peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+		core.lock().peers.insert(2, Services::default(), DummyOutboundSyncConnection::new());
+
+		let blocks = test_data::build_n_empty_blocks_from_genesis(types::HEADERS_MAX_HEADERS_LEN as u32 + 2, 1);
+		let headers: Vec<IndexedBlockHeader> = blocks.iter().map(|block| block.block_header.clone().into()).collect();
+		let blocks_requests = |tasks: Vec<Task>| -> Vec<(PeerIndex, Vec<H256>)> {
+			tasks.into_iter()
+				.filter_map(|task| match task {
+					Task::GetData(peer_index, getdata) => Some((peer_index, getdata.inventory.into_iter().map(|item| item.hash).collect())),
+					_ => None,
+				})
+				.collect()
+		};
+
+		// full `headers` messages => there are more headers to download => blocks are not requested yet
+		sync.on_headers(1, headers[..types::HEADERS_MAX_HEADERS_LEN].to_vec());
+		sync.on_headers(2, headers[..types::HEADERS_MAX_HEADERS_LEN].to_vec());
+		assert_eq!(core.lock().information().chain.scheduled, types::HEADERS_MAX_HEADERS_LEN as u32);
+		assert_eq!(core.lock().information().chain.requested, 0);
+		assert!(blocks_requests(executor.take_tasks()).is_empty());
+
+		// the rest of headers chain is received from peer#1, but peer#2 may still have more headers
+		sync.on_headers(1, headers[types::HEADERS_MAX_HEADERS_LEN..].to_vec());
+		assert_eq!(core.lock().information().chain.requested, 0);
+		assert!(blocks_requests(executor.take_tasks()).is_empty());
+
+		// peer#2 has no more headers too => blocks are requested from both peers
+		sync.on_headers(2, headers[types::HEADERS_MAX_HEADERS_LEN..].to_vec());
+		assert_eq!(core.lock().information().chain.requested, blocks.len() as u32);
+		let requests = blocks_requests(executor.take_tasks());
+		assert_eq!(requests.len(), 2);
+		let (stalling_peer, mut stalled_hashes) = requests.iter().find(|&&(_, ref hashes)| hashes.contains(&blocks[0].hash())).cloned().unwrap();
+		let (good_peer, good_hashes) = requests.iter().find(|&&(peer_index, _)| peer_index != stalling_peer).cloned().unwrap();
+
+		// good peer responds with all requested blocks, stalling peer is silent
+		for block in blocks.iter().filter(|block| good_hashes.contains(&block.hash())) {
+			sync.on_block(good_peer, block.clone().into());
+		}
+		assert_eq!(core.lock().information().chain.stored, 1);
+		executor.take_tasks();
+
+		// download is stalled => blocks of stalling peer are requested from the good peer
+		{
+			// same as in synchronization manager: stall is only detected when synchronizing
+			// && received blocks that are not connected to the chain (orphans) are not counted as downloaded
+			let mut core = core.lock();
+			assert!(core.state().is_synchronizing());
+			let config = ManageSynchronizationStallConfig { stall_timeout_ms: 1000 };
+			let mut progress = SynchronizationProgress::new();
+			let downloaded_blocks = core.chain().length_of_blocks_state(BlockState::Stored)
+				+ core.chain().length_of_blocks_state(BlockState::Verifying);
+			assert_eq!(downloaded_blocks, 1);
+			let lowest_requested_block = core.chain().best_n_of_blocks_state(BlockState::Requested, 1).pop();
+			assert_eq!(lowest_requested_block, Some(blocks[0].hash()));
+			assert_eq!(manage_synchronization_stall(&config, &mut progress, downloaded_blocks, lowest_requested_block.clone(), core.peers(), core.peers_tasks()), None);
+			progress.timestamp -= 2.0;
+			let blocks_to_request = manage_synchronization_stall(&config, &mut progress, downloaded_blocks, lowest_requested_block, core.peers(), core.peers_tasks()).unwrap();
+			core.execute_synchronization_tasks(Some(blocks_to_request), None);
+			assert!(!core.peers.enumerate().contains(&stalling_peer));
+		}
+
+		let mut requests = blocks_requests(executor.take_tasks());
+		assert_eq!(requests.len(), 1);
+		assert_eq!(requests[0].0, good_peer);
+		requests[0].1.sort();
+		stalled_hashes.sort();
+		assert_eq!(requests[0].1, stalled_hashes);
+
+		// good peer completes the download
+		for block in blocks.iter().filter(|block| stalled_hashes.contains(&block.hash())) {
+			sync.on_block(good_peer, block.clone().into());
+		}
+		assert_eq!(core.lock().information().chain.requested, 0);
+		assert_eq!(core.lock().information().chain.stored, blocks.len() as u32 + 1);
+		assert_eq!(core.lock().information().orphaned_blocks, 0);
+	}
+
+	#[test]
+	fn headers_first_sync_selects_headers_chain_with_most_work() {
+		let (executor, core, sync) = create_sync_with_mode(None, None, SyncMode::HeadersFirst);
+		core.lock().peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+		core.lock().peers.insert(2, Services::default(), DummyOutboundSyncConnection::new());
+
+		// peer#1 knows the longer chain, peer#2 knows the chain with more work
+		let genesis_hash = test_data::genesis().hash();
+		let longer: Vec<IndexedBlockHeader> = test_data::build_n_empty_blocks_from_genesis(4, 1).into_iter()
+			.map(|block| block.block_header.into())
+			.collect();
+		let b1: IndexedBlockHeader = test_data::block_builder().header().parent(genesis_hash).bits(Compact::new(0x1f07ffff)).build().build().block_header.into();
+		let b2: IndexedBlockHeader = test_data::block_builder().header().parent(b1.hash.clone()).bits(Compact::new(0x1f07ffff)).build().build().block_header.into();
+
+		// peer#2 is still sending us headers => blocks are not requested yet
+		core.lock().peers_with_more_headers.insert(2);
+		sync.on_headers(1, longer.clone());
+		assert_eq!(core.lock().information().chain.scheduled, 4);
+		assert_eq!(core.lock().information().chain.requested, 0);
+		executor.take_tasks();
+
+		// headers chain with more work replaces the longer one => only its blocks are requested
+		sync.on_headers(2, vec![b1.clone(), b2.clone()]);
+		assert_eq!(core.lock().information().chain.scheduled, 0);
+		assert_eq!(core.lock().information().chain.requested, 2);
+		assert_eq!(core.lock().chain().best_block_header().hash, b2.hash);
+		let mut requested: Vec<H256> = executor.take_tasks().into_iter()
+			.filter_map(|task| match task {
+				Task::GetData(_, getdata) => Some(getdata.inventory.into_iter().map(|item| item.hash)),
+				_ => None,
+			})
+			.flat_map(|hashes| hashes)
+			.collect();
+		requested.sort();
+		let mut expected = vec![b1.hash.clone(), b2.hash.clone()];
+		expected.sort();
+		assert_eq!(requested, expected);
+
+		// headers of the longer chain are received again => nothing is rescheduled
+		sync.on_headers(1, longer.clone());
+		assert_eq!(core.lock().information().chain.scheduled, 0);
+		assert_eq!(core.lock().information().chain.requested, 2);
+	}
 }
@@ -3,6 +3,7 @@ use chain::{IndexedBlock, IndexedTransaction};
 use message::common::InventoryVector;
 use message::types;
 use primitives::hash::H256;
+use synchronization_client_core::MAX_BLOCKS_TO_ANNOUNCE;
 use synchronization_peers::{BlockAnnouncementType, TransactionAnnouncementType};
 use types::{PeerIndex, PeersRef, RequestId};
 use utils::KnownHashType;
@@ -31,12 +32,16 @@ pub enum Task {
 	Transaction(PeerIndex, IndexedTransaction),
 	/// Send notfound
 	NotFound(PeerIndex, types::NotFound),
+	/// Request missing transactions of compact block
+	GetBlockTxn(PeerIndex, types::GetBlockTxn),
+	/// Send transactions of the block
+	BlockTxn(PeerIndex, types::BlockTxn),
 	/// Send inventory
 	Inventory(PeerIndex, types::Inv),
 	/// Send headers
 	Headers(PeerIndex, types::Headers, Option<RequestId>),
-	/// Relay new block to peers
-	RelayNewBlock(IndexedBlock),
+	/// Relay new blocks to peers. Blocks are connected to each other and ordered by height
+	RelayNewBlocks(Vec<IndexedBlock>),
 	/// Relay new transaction to peers
 	RelayNewTransaction(IndexedTransaction, u64),
 }
@@ -107,6 +112,7 @@ impl LocalSynchronizationTaskExecutor {
 	fn execute_transaction(&self, peer_index: PeerIndex, transaction: IndexedTransaction) {
 		if let Some(connection) = self.peers.connection(peer_index) {
 			trace!(target: "sync", "Sending transaction {} to peer#{}", transaction.hash.to_reversed_str(), peer_index);
+			self.peers.hash_known_as(peer_index, transaction.raw.relay_id().effects, KnownHashType::RelayId);
 			self.peers.hash_known_as(peer_index, transaction.hash, KnownHashType::Transaction);
 			let transaction = types::Tx {
 				transaction: transaction.raw,
@@ -122,6 +128,20 @@ impl LocalSynchronizationTaskExecutor {
 		}
 	}
 
+	fn execute_getblocktxn(&self, peer_index: PeerIndex, getblocktxn: types::GetBlockTxn) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Querying {} missing transactions of block {} from peer#{}", getblocktxn.request.indexes.len(), getblocktxn.request.blockhash.to_reversed_str(), peer_index);
+			connection.send_get_block_txn(&getblocktxn);
+		}
+	}
+
+	fn execute_blocktxn(&self, peer_index: PeerIndex, blocktxn: types::BlockTxn) {
+		if let Some(connection) = self.peers.connection(peer_index) {
+			trace!(target: "sync", "Sending {} transactions of block {} to peer#{}", blocktxn.request.transactions.len(), blocktxn.request.blockhash.to_reversed_str(), peer_index);
+			connection.send_block_txn(&blocktxn);
+		}
+	}
+
 	fn execute_inventory(&self, peer_index: PeerIndex, inventory: types::Inv) {
 		if let Some(connection) = self.peers.connection(peer_index) {
 			trace!(target: "sync", "Sending inventory to peer#{} with {} items", peer_index, inventory.inventory.len());
@@ -139,18 +159,31 @@ impl LocalSynchronizationTaskExecutor {
 		}
 	}
 
-	fn execute_relay_block(&self, block: IndexedBlock) {
+	fn execute_relay_blocks(&self, blocks: Vec<IndexedBlock>) {
 		for peer_index in self.peers.enumerate() {
-			match self.peers.filter_block(peer_index, &block) {
-				BlockAnnouncementType::SendInventory => {
-					self.execute_inventory(peer_index, types::Inv::with_inventory(vec![
-						InventoryVector::block(block.hash().clone()),
-					]));
+			// announce blocks, starting from the first block, unknown to the peer
+			// => announced headers are always connected to each other
+			let first_unknown_block = blocks.iter()
+				.position(|block| match self.peers.filter_block(peer_index, block) {
+					BlockAnnouncementType::DoNotAnnounce => false,
+					_ => true,
+				});
+			let blocks = match first_unknown_block {
+				Some(first_unknown_block) => &blocks[first_unknown_block..],
+				None => continue,
+			};
+
+			match self.peers.filter_block(peer_index, &blocks[0]) {
+				BlockAnnouncementType::SendHeaders if blocks.len() <= MAX_BLOCKS_TO_ANNOUNCE => {
+					self.execute_headers(peer_index, types::Headers::with_headers(blocks.iter()
+						.map(|block| block.header.raw.clone())
+						.collect()), None);
 				},
-				BlockAnnouncementType::SendHeaders => {
-					self.execute_headers(peer_index, types::Headers::with_headers(vec![
-						block.header.raw.clone(),
-					]), None);
+				// too many headers to announce => fall back to inventory announcement
+				BlockAnnouncementType::SendHeaders | BlockAnnouncementType::SendInventory => {
+					self.execute_inventory(peer_index, types::Inv::with_inventory(blocks.iter()
+						.map(|block| InventoryVector::block(block.hash().clone()))
+						.collect()));
 				},
 				BlockAnnouncementType::DoNotAnnounce => (),
 			}
@@ -158,7 +191,13 @@ impl LocalSynchronizationTaskExecutor {
 	}
 
 	fn execute_relay_transaction(&self, transaction: IndexedTransaction, fee_rate: u64) {
+		let relay_id = transaction.raw.relay_id();
 		for peer_index in self.peers.enumerate() {
+			// do not announce transaction to peers, which know its malleated variant
+			if self.peers.is_hash_known_as(peer_index, &relay_id.effects, KnownHashType::RelayId) {
+				continue;
+			}
+
 			match self.peers.filter_transaction(peer_index, &transaction, Some(fee_rate)) {
 				TransactionAnnouncementType::SendInventory => self.execute_inventory(peer_index, types::Inv::with_inventory(vec![
 					InventoryVector::tx(transaction.hash.clone()),
@@ -180,9 +219,11 @@ impl TaskExecutor for LocalSynchronizationTaskExecutor {
 			Task::MerkleBlock(peer_index, hash, block) => self.execute_merkleblock(peer_index, hash, block),
 			Task::Transaction(peer_index, transaction) => self.execute_transaction(peer_index, transaction),
 			Task::NotFound(peer_index, notfound) => self.execute_notfound(peer_index, notfound),
+			Task::GetBlockTxn(peer_index, getblocktxn) => self.execute_getblocktxn(peer_index, getblocktxn),
+			Task::BlockTxn(peer_index, blocktxn) => self.execute_blocktxn(peer_index, blocktxn),
 			Task::Inventory(peer_index, inventory) => self.execute_inventory(peer_index, inventory),
 			Task::Headers(peer_index, headers, request_id) => self.execute_headers(peer_index, headers, request_id),
-			Task::RelayNewBlock(block) => self.execute_relay_block(block),
+			Task::RelayNewBlocks(blocks) => self.execute_relay_blocks(blocks),
 			Task::RelayNewTransaction(transaction, fee_rate) => self.execute_relay_transaction(transaction, fee_rate),
 		}
 	}
@@ -198,6 +239,8 @@ pub mod tests {
 	use parking_lot::{Mutex, Condvar};
 	use chain::Transaction;
 	use message::{Services, types};
+	use miner::fee_rate;
+	use ser::Serializable;
 	use inbound_connection::tests::DummyOutboundSyncConnection;
 	use local_node::tests::{default_filterload, make_filteradd};
 	use synchronization_peers::{PeersImpl, PeersContainer, PeersFilters, PeersOptions, BlockAnnouncementType};
@@ -255,11 +298,64 @@ pub mod tests {
 		peers.insert(2, Services::default(), c2.clone());
 		peers.set_block_announcement_type(2, BlockAnnouncementType::SendHeaders);
 
-		executor.execute(Task::RelayNewBlock(test_data::genesis().into()));
+		executor.execute(Task::RelayNewBlocks(vec![test_data::genesis().into()]));
 		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
 		assert_eq!(*c2.messages.lock().entry("headers".to_owned()).or_insert(0), 1);
 	}
 
+	#[test]
+	fn relay_new_blocks_chain_after_sendheaders() {
+		let peers = Arc::new(PeersImpl::default());
+		let executor = LocalSynchronizationTaskExecutor::new(peers.clone());
+
+		let genesis = test_data::genesis();
+		let b1 = test_data::block_builder().header().parent(genesis.hash()).build().build();
+		let b2 = test_data::block_builder().header().parent(b1.hash()).build().build();
+
+		// peer#1 has opted in for headers announcements
+		let c1 = DummyOutboundSyncConnection::new();
+		peers.insert(1, Services::default(), c1.clone());
+		peers.set_block_announcement_type(1, BlockAnnouncementType::SendHeaders);
+		// peer#2 has opted in for headers announcements && already knows b1
+		let c2 = DummyOutboundSyncConnection::new();
+		peers.insert(2, Services::default(), c2.clone());
+		peers.set_block_announcement_type(2, BlockAnnouncementType::SendHeaders);
+		peers.hash_known_as(2, b1.hash(), KnownHashType::Block);
+		// peer#3 has opted in for headers announcements && already knows both blocks
+		let c3 = DummyOutboundSyncConnection::new();
+		peers.insert(3, Services::default(), c3.clone());
+		peers.set_block_announcement_type(3, BlockAnnouncementType::SendHeaders);
+		peers.hash_known_as(3, b1.hash(), KnownHashType::Block);
+		peers.hash_known_as(3, b2.hash(), KnownHashType::Block);
+
+		executor.execute(Task::RelayNewBlocks(vec![b1.into(), b2.into()]));
+
+		// connected headers are announced in single message
+		assert_eq!(*c1.messages.lock().entry("headers".to_owned()).or_insert(0), 1);
+		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 0);
+		assert_eq!(*c2.messages.lock().entry("headers".to_owned()).or_insert(0), 1);
+		assert_eq!(*c2.messages.lock().entry("inventory".to_owned()).or_insert(0), 0);
+		assert_eq!(*c3.messages.lock().entry("headers".to_owned()).or_insert(0), 0);
+		assert_eq!(*c3.messages.lock().entry("inventory".to_owned()).or_insert(0), 0);
+	}
+
+	#[test]
+	fn relay_too_many_new_blocks_after_sendheaders() {
+		let peers = Arc::new(PeersImpl::default());
+		let executor = LocalSynchronizationTaskExecutor::new(peers.clone());
+
+		let c1 = DummyOutboundSyncConnection::new();
+		peers.insert(1, Services::default(), c1.clone());
+		peers.set_block_announcement_type(1, BlockAnnouncementType::SendHeaders);
+
+		let blocks = test_data::build_n_empty_blocks_from_genesis(MAX_BLOCKS_TO_ANNOUNCE as u32 + 1, 1);
+		executor.execute(Task::RelayNewBlocks(blocks.into_iter().map(Into::into).collect()));
+
+		// too many headers => inventory is sent instead
+		assert_eq!(*c1.messages.lock().entry("headers".to_owned()).or_insert(0), 0);
+		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
+	}
+
 	#[test]
 	fn relay_new_transaction_with_bloom_filter() {
 		let peers = Arc::new(PeersImpl::default());
@@ -334,4 +430,46 @@ pub mod tests {
 		assert_eq!(*c3.messages.lock().entry("inventory".to_owned()).or_insert(0), 0);
 		assert_eq!(*c4.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
 	}
+
+	#[test]
+	fn relay_new_transaction_below_feefilter() {
+		let peers = Arc::new(PeersImpl::default());
+		let executor = LocalSynchronizationTaskExecutor::new(peers.clone());
+
+		// peer is filtering transactions paying less than 10 zatoshis per byte
+		let c1 = DummyOutboundSyncConnection::new();
+		peers.insert(1, Services::default(), c1.clone());
+		peers.set_fee_filter(1, types::FeeFilter::with_fee_rate(10_000));
+
+		// transaction is paying 1 zatoshi per byte
+		let transaction: Transaction = test_data::TransactionBuilder::with_output(10).into();
+		let transaction_fee_rate = fee_rate(transaction.serialized_size() as u64, transaction.serialized_size());
+		assert_eq!(transaction_fee_rate, 1_000);
+		executor.execute(Task::RelayNewTransaction(transaction.into(), transaction_fee_rate));
+
+		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 0);
+	}
+
+	#[test]
+	fn relay_new_transaction_skips_peers_knowing_malleated_variant() {
+		let peers = Arc::new(PeersImpl::default());
+		let executor = LocalSynchronizationTaskExecutor::new(peers.clone());
+
+		let input_tx = test_data::genesis().transactions[0].clone();
+		let tx1: Transaction = test_data::TransactionBuilder::with_input(&input_tx, 0).set_output(100).into();
+		let mut tx2 = tx1.clone();
+		tx2.inputs[0].script_sig = "51".into();
+
+		// peer#1 has sent us malleated variant of tx2
+		let c1 = DummyOutboundSyncConnection::new();
+		peers.insert(1, Services::default(), c1.clone());
+		peers.hash_known_as(1, tx1.relay_id().effects, KnownHashType::RelayId);
+		let c2 = DummyOutboundSyncConnection::new();
+		peers.insert(2, Services::default(), c2.clone());
+
+		executor.execute(Task::RelayNewTransaction(tx2.into(), 0));
+
+		assert_eq!(*c1.messages.lock().entry("inventory".to_owned()).or_insert(0), 0);
+		assert_eq!(*c2.messages.lock().entry("inventory".to_owned()).or_insert(0), 1);
+	}
 }
@@ -1,26 +1,21 @@
+use std::cmp;
 use std::collections::HashSet;
 use std::sync::{Arc, Weak};
 use std::thread;
 use std::time::Duration;
 use parking_lot::{Mutex, Condvar};
 use time::precise_time_s;
+use p2p::TimeoutProfile;
 use primitives::hash::H256;
+use synchronization_chain::BlockState;
 use synchronization_client_core::{ClientCore, SynchronizationClientCore};
 use synchronization_executor::TaskExecutor;
 use synchronization_peers_tasks::{PeersTasks, TrustLevel};
 use utils::{OrphanBlocksPool, OrphanTransactionsPool};
-use types::PeersRef;
+use types::{PeersRef, PeerIndex, BlockHeight};
 
 /// Management interval (in ms)
 const MANAGEMENT_INTERVAL_MS: u64 = 10 * 1000;
-/// Response time before getting block to decrease peer score
-const DEFAULT_NEW_PEER_BLOCK_FAILURE_INTERVAL_MS: u32 = 5 * 1000;
-/// Response time before getting headers to decrease peer score
-const DEFAULT_NEW_PEER_HEADERS_FAILURE_INTERVAL_MS: u32 = 5 * 1000;
-/// Response time before getting block to decrease peer score
-const DEFAULT_TRUSTED_PEER_BLOCK_FAILURE_INTERVAL_MS: u32 = 20 * 1000;
-/// Response time before getting headers to decrease peer score
-const DEFAULT_TRUSTED_PEER_HEADERS_FAILURE_INTERVAL_MS: u32 = 20 * 1000;
 /// Unknown orphan block removal time
 const DEFAULT_UNKNOWN_BLOCK_REMOVAL_TIME_MS: u32 = 20 * 60 * 1000;
 /// Maximal number of orphaned blocks
@@ -29,6 +24,8 @@ const DEFAULT_UNKNOWN_BLOCKS_MAX_LEN: usize = 16;
 const DEFAULT_ORPHAN_TRANSACTION_REMOVAL_TIME_MS: u32 = 10 * 60 * 1000;
 /// Maximal number of orphaned transactions
 const DEFAULT_ORPHAN_TRANSACTIONS_MAX_LEN: usize = 10000;
+/// Time without any block download progress before synchronization is considered stalled
+const DEFAULT_SYNCHRONIZATION_STALL_TIMEOUT_MS: u32 = 60 * 1000;
 
 /// Synchronization management worker
 pub struct ManagementWorker {
@@ -41,7 +38,7 @@ pub struct ManagementWorker {
 }
 
 impl ManagementWorker {
-	pub fn new<T: TaskExecutor>(core: Weak<Mutex<SynchronizationClientCore<T>>>) -> Self {
+	pub fn new<T: TaskExecutor>(core: Weak<Mutex<SynchronizationClientCore<T>>>, peers_config: ManagePeersConfig) -> Self {
 		let is_stopping = Arc::new(Mutex::new(false));
 		let stopping_event = Arc::new(Condvar::new());
 		ManagementWorker {
@@ -49,15 +46,16 @@ impl ManagementWorker {
 			stopping_event: stopping_event.clone(),
 			thread: Some(thread::Builder::new()
 				.name("Sync management thread".to_string())
-				.spawn(move || ManagementWorker::worker_proc(is_stopping, stopping_event, core))
+				.spawn(move || ManagementWorker::worker_proc(is_stopping, stopping_event, core, peers_config))
 				.expect("Error creating management thread"))
 		}
 	}
 
-	fn worker_proc<T: TaskExecutor>(is_stopping: Arc<Mutex<bool>>, stopping_event: Arc<Condvar>, core: Weak<Mutex<SynchronizationClientCore<T>>>) {
-		let peers_config = ManagePeersConfig::default();
+	fn worker_proc<T: TaskExecutor>(is_stopping: Arc<Mutex<bool>>, stopping_event: Arc<Condvar>, core: Weak<Mutex<SynchronizationClientCore<T>>>, peers_config: ManagePeersConfig) {
 		let unknown_config = ManageUnknownBlocksConfig::default();
 		let orphan_config = ManageOrphanTransactionsConfig::default();
+		let stall_config = ManageSynchronizationStallConfig::default();
+		let mut progress = SynchronizationProgress::new();
 
 		loop {
 			let mut lock = is_stopping.lock();
@@ -86,7 +84,16 @@ impl ManagementWorker {
 			core.print_synchronization_information();
 			// execute management tasks if not saturated
 			if core.state().is_synchronizing() || core.state().is_nearly_saturated() {
-				let (blocks_to_request, blocks_to_forget) = manage_synchronization_peers_blocks(&peers_config, core.peers(), core.peers_tasks());
+				let (mut blocks_to_request, blocks_to_forget) = manage_synchronization_peers_blocks(&peers_config, core.peers(), core.peers_tasks());
+				if core.state().is_synchronizing() {
+					let downloaded_blocks = core.chain().length_of_blocks_state(BlockState::Stored)
+						+ core.chain().length_of_blocks_state(BlockState::Verifying);
+					let lowest_requested_block = core.chain().best_n_of_blocks_state(BlockState::Requested, 1).pop();
+					if let Some(stalled_blocks) = manage_synchronization_stall(&stall_config, &mut progress, downloaded_blocks,
+						lowest_requested_block, core.peers(), core.peers_tasks()) {
+						blocks_to_request.extend(stalled_blocks);
+					}
+				}
 				core.forget_failed_blocks(&blocks_to_forget);
 				core.execute_synchronization_tasks(
 					if blocks_to_request.is_empty() { None } else { Some(blocks_to_request) },
@@ -122,6 +129,7 @@ impl Drop for ManagementWorker {
 
 /// Peers management configuration
 pub struct ManagePeersConfig {
+	/// Time interval (in milliseconds) to wait block from the peer before penalizing && reexecuting tasks
 	pub new_block_failure_interval_ms: u32,
 	/// Time interval (in milliseconds) to wait headers from the peer before penalizing && reexecuting tasks
 	pub new_headers_failure_interval_ms: u32,
@@ -133,11 +141,54 @@ pub struct ManagePeersConfig {
 
 impl Default for ManagePeersConfig {
 	fn default() -> Self {
+		ManagePeersConfig::from(&TimeoutProfile::default())
+	}
+}
+
+impl<'a> From<&'a TimeoutProfile> for ManagePeersConfig {
+	fn from(timeouts: &'a TimeoutProfile) -> Self {
 		ManagePeersConfig {
-			new_block_failure_interval_ms: DEFAULT_NEW_PEER_BLOCK_FAILURE_INTERVAL_MS,
-			new_headers_failure_interval_ms: DEFAULT_NEW_PEER_HEADERS_FAILURE_INTERVAL_MS,
-			trusted_block_failure_interval_ms: DEFAULT_TRUSTED_PEER_BLOCK_FAILURE_INTERVAL_MS,
-			trusted_headers_failure_interval_ms: DEFAULT_TRUSTED_PEER_HEADERS_FAILURE_INTERVAL_MS,
+			new_block_failure_interval_ms: duration_ms(&timeouts.block_request),
+			new_headers_failure_interval_ms: duration_ms(&timeouts.headers_request),
+			trusted_block_failure_interval_ms: duration_ms(&timeouts.trusted_block_request),
+			trusted_headers_failure_interval_ms: duration_ms(&timeouts.trusted_headers_request),
+		}
+	}
+}
+
+fn duration_ms(duration: &Duration) -> u32 {
+	let ms = duration.as_secs().saturating_mul(1000).saturating_add(duration.subsec_millis() as u64);
+	cmp::min(ms, u32::max_value() as u64) as u32
+}
+
+/// Synchronization stall detection configuration
+pub struct ManageSynchronizationStallConfig {
+	/// Time interval (in milliseconds) without blocks download progress before rotating the peer
+	/// responsible for the lowest requested block
+	pub stall_timeout_ms: u32,
+}
+
+impl Default for ManageSynchronizationStallConfig {
+	fn default() -> Self {
+		ManageSynchronizationStallConfig {
+			stall_timeout_ms: DEFAULT_SYNCHRONIZATION_STALL_TIMEOUT_MS,
+		}
+	}
+}
+
+/// Blocks download progress, tracked by stall detector
+pub struct SynchronizationProgress {
+	/// Number of downloaded (stored + verifying) blocks when progress was last noticed
+	downloaded_blocks: BlockHeight,
+	/// Time when progress was last noticed
+	pub timestamp: f64,
+}
+
+impl SynchronizationProgress {
+	pub fn new() -> Self {
+		SynchronizationProgress {
+			downloaded_blocks: 0,
+			timestamp: precise_time_s(),
 		}
 	}
 }
@@ -206,13 +257,48 @@ pub fn manage_synchronization_peers_blocks(config: &ManagePeersConfig, peers: Pe
 		if peers_tasks.on_peer_block_failure(worst_peer_index) {
 			warn!(target: "sync", "Too many failures for peer#{}. Excluding from synchronization.", worst_peer_index);
 			peers_tasks.unuseful_peer(worst_peer_index);
-			peers.misbehaving(worst_peer_index, &format!("Too many failures."));
+			peers.disconnect(worst_peer_index, "Too many failures.");
 		}
 	}
 
 	(blocks_to_request, blocks_to_forget)
 }
 
+/// Detect stalled blocks download. If no blocks have been downloaded for a given period, the peer
+/// responsible for the lowest requested block is disconnected and all its blocks are returned to be
+/// requested from other peers.
+pub fn manage_synchronization_stall(config: &ManageSynchronizationStallConfig, progress: &mut SynchronizationProgress, downloaded_blocks: BlockHeight,
+	lowest_requested_block: Option<H256>, peers: PeersRef, peers_tasks: &mut PeersTasks) -> Option<Vec<H256>> {
+	let now = precise_time_s();
+	if downloaded_blocks != progress.downloaded_blocks {
+		progress.downloaded_blocks = downloaded_blocks;
+		progress.timestamp = now;
+		return None;
+	}
+
+	let time_diff = now - progress.timestamp;
+	if time_diff <= config.stall_timeout_ms as f64 / 1000f64 {
+		return None;
+	}
+
+	// find peer, which is blocking the download window
+	let lowest_requested_block = lowest_requested_block?;
+	let stalling_peer: PeerIndex = peers_tasks.ordered_blocks_requests().iter()
+		.find(|&(_, request)| request.blocks.contains(&lowest_requested_block))
+		.map(|(peer_index, _)| *peer_index)?;
+
+	// give other peers a chance before the next rotation
+	progress.timestamp = now;
+
+	warn!(target: "sync", "Blocks download is stalled for {:.2} seconds, waiting for block {} from peer#{}. Rotating peer.",
+		time_diff, lowest_requested_block.to_reversed_str(), stalling_peer);
+	let blocks_to_request = peers_tasks.reset_blocks_tasks(stalling_peer);
+	peers_tasks.unuseful_peer(stalling_peer);
+	peers.disconnect(stalling_peer, "Stalled blocks download");
+
+	Some(blocks_to_request)
+}
+
 /// Manage stalled synchronization peers headers tasks
 pub fn manage_synchronization_peers_headers(config: &ManagePeersConfig, peers: PeersRef, peers_tasks: &mut PeersTasks) {
 	let now = precise_time_s();
@@ -235,7 +321,7 @@ pub fn manage_synchronization_peers_headers(config: &ManagePeersConfig, peers: P
 		// if peer failed many times => forget it
 		if peers_tasks.on_peer_headers_failure(worst_peer_index) {
 			warn!(target: "sync", "Too many header failures for peer#{}. Excluding from synchronization.", worst_peer_index);
-			peers.misbehaving(worst_peer_index, &format!("Too many header failures."));
+			peers.disconnect(worst_peer_index, "Too many header failures.");
 		}
 	}
 }
@@ -314,11 +400,16 @@ mod tests {
 
 	use std::sync::Arc;
 	use std::collections::HashSet;
+	use std::time::Duration;
 	use primitives::hash::H256;
-	use synchronization_peers::PeersImpl;
+	use message::common::Services;
+	use p2p::TimeoutProfile;
+	use inbound_connection::tests::DummyOutboundSyncConnection;
+	use synchronization_peers::{Peers, PeersContainer, PeersImpl};
 	use synchronization_peers_tasks::{PeersTasks, TrustLevel};
-	use super::{ManagePeersConfig, ManageUnknownBlocksConfig, ManageOrphanTransactionsConfig, manage_synchronization_peers_blocks,
-		manage_unknown_orphaned_blocks, manage_orphaned_transactions};
+	use super::{ManagePeersConfig, ManageUnknownBlocksConfig, ManageOrphanTransactionsConfig, ManageSynchronizationStallConfig,
+		SynchronizationProgress, manage_synchronization_peers_blocks, manage_unknown_orphaned_blocks, manage_orphaned_transactions,
+		manage_synchronization_stall, duration_ms};
 	use utils::{OrphanBlocksPool, OrphanTransactionsPool};
 
 	#[test]
@@ -352,6 +443,87 @@ mod tests {
 		assert!(idle_peers.contains(&2));
 	}
 
+	#[test]
+	fn manage_peers_config_uses_timeout_profile() {
+		let config = ManagePeersConfig::default();
+		assert_eq!(config.new_block_failure_interval_ms, 5_000);
+		assert_eq!(config.new_headers_failure_interval_ms, 5_000);
+		assert_eq!(config.trusted_block_failure_interval_ms, 20_000);
+		assert_eq!(config.trusted_headers_failure_interval_ms, 20_000);
+
+		let config = ManagePeersConfig::from(&TimeoutProfile::default().scaled(4));
+		assert_eq!(config.new_block_failure_interval_ms, 20_000);
+		assert_eq!(config.new_headers_failure_interval_ms, 20_000);
+		assert_eq!(config.trusted_block_failure_interval_ms, 80_000);
+		assert_eq!(config.trusted_headers_failure_interval_ms, 80_000);
+
+		assert_eq!(duration_ms(&Duration::from_millis(1_500)), 1_500);
+		assert_eq!(duration_ms(&Duration::from_secs(u64::max_value())), u32::max_value());
+	}
+
+	#[test]
+	fn manage_peers_with_scaled_timeout_profile() {
+		let profile = TimeoutProfile {
+			block_request: Duration::from_millis(100),
+			..Default::default()
+		};
+
+		// with tight timeouts, slow peer is penalized
+		let mut peers = PeersTasks::default();
+		peers.on_blocks_requested(1, &vec![H256::from(0)]);
+		peers.get_blocks_request_mut(1).unwrap().timestamp -= 1.0;
+		let config = ManagePeersConfig::from(&profile);
+		assert_eq!(manage_synchronization_peers_blocks(&config, Arc::new(PeersImpl::default()), &mut peers).0, vec![H256::from(0)]);
+
+		// with scaled-up timeouts, the same peer is still waited for
+		let mut peers = PeersTasks::default();
+		peers.on_blocks_requested(1, &vec![H256::from(0)]);
+		peers.get_blocks_request_mut(1).unwrap().timestamp -= 1.0;
+		let config = ManagePeersConfig::from(&profile.scaled(100));
+		assert_eq!(manage_synchronization_peers_blocks(&config, Arc::new(PeersImpl::default()), &mut peers), (vec![], vec![]));
+		assert_eq!(peers.idle_peers_for_blocks().len(), 0);
+	}
+
+	#[test]
+	fn manage_stalled_synchronization() {
+		let config = ManageSynchronizationStallConfig { stall_timeout_ms: 1000 };
+		let peers = Arc::new(PeersImpl::default());
+		let connection1 = DummyOutboundSyncConnection::new();
+		peers.insert(1, Services::default(), connection1.clone());
+		peers.insert(2, Services::default(), DummyOutboundSyncConnection::new());
+		let mut peers_tasks = PeersTasks::default();
+		peers_tasks.on_blocks_requested(1, &vec![H256::from(0), H256::from(1)]);
+		peers_tasks.on_blocks_requested(2, &vec![H256::from(2)]);
+
+		// progress is made => no rotation
+		let mut progress = SynchronizationProgress::new();
+		assert_eq!(manage_synchronization_stall(&config, &mut progress, 10, Some(H256::from(0)), peers.clone(), &mut peers_tasks), None);
+		progress.timestamp -= 2.0;
+		assert_eq!(manage_synchronization_stall(&config, &mut progress, 11, Some(H256::from(0)), peers.clone(), &mut peers_tasks), None);
+
+		// no progress for too long => peer#1, which blocks the lowest block, is rotated out
+		progress.timestamp -= 2.0;
+		let mut blocks_to_request = manage_synchronization_stall(&config, &mut progress, 11, Some(H256::from(0)), peers.clone(), &mut peers_tasks).unwrap();
+		blocks_to_request.sort();
+		assert_eq!(blocks_to_request, vec![H256::from(0), H256::from(1)]);
+		assert!(!peers.enumerate().contains(&1));
+		assert!(peers.enumerate().contains(&2));
+		assert_eq!(peers_tasks.useful_peers(), vec![2]);
+		// stalling peer is disconnected, but not banned
+		assert_eq!(connection1.messages.lock().get("close"), Some(&1));
+		assert_eq!(connection1.messages.lock().get("ban"), None);
+
+		// stall timer is restarted => peer#2 is not rotated out immediately
+		assert_eq!(manage_synchronization_stall(&config, &mut progress, 11, Some(H256::from(0)), peers.clone(), &mut peers_tasks), None);
+
+		// blocks are re-requested from peer#2 and progress resumes
+		peers_tasks.on_blocks_requested(2, &blocks_to_request);
+		peers_tasks.on_block_received(2, &H256::from(0));
+		progress.timestamp -= 2.0;
+		assert_eq!(manage_synchronization_stall(&config, &mut progress, 12, Some(H256::from(1)), peers.clone(), &mut peers_tasks), None);
+		assert!(peers.enumerate().contains(&2));
+	}
+
 	#[test]
 	fn manage_unknown_blocks_good() {
 		let config = ManageUnknownBlocksConfig { removal_time_ms: 1000, max_number: 100 };
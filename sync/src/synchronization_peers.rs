@@ -2,11 +2,14 @@ use std::collections::HashMap;
 use parking_lot::RwLock;
 use chain::{IndexedBlock, IndexedTransaction};
 use message::{types, Services};
-use p2p::OutboundSyncConnectionRef;
+use p2p::{OutboundSyncConnectionRef, PeerPermissions};
 use primitives::hash::H256;
 use types::PeerIndex;
 use utils::{KnownHashType, ConnectionFilter};
 
+/// Peer connection is closed once its ban score reaches this value.
+pub const MAX_BAN_SCORE: u32 = 100;
+
 /// Block announcement type
 #[derive(Debug, Clone, Copy)]
 pub enum BlockAnnouncementType {
@@ -50,10 +53,14 @@ pub trait PeersContainer {
 	fn insert(&self, peer_index: PeerIndex, services: Services, connection: OutboundSyncConnectionRef);
 	/// Remove peer connection
 	fn remove(&self, peer_index: PeerIndex);
-	/// Close and remove peer connection due to misbehaving
+	/// Increase peer ban score. Ban peer, close and remove its connection once it reaches `MAX_BAN_SCORE`
+	fn penalize(&self, peer_index: PeerIndex, score: u32, reason: &str);
+	/// Ban peer, close and remove its connection due to misbehaving
 	fn misbehaving(&self, peer_index: PeerIndex, reason: &str);
-	/// Close and remove peer connection due to detected DOS attempt
+	/// Ban peer, close and remove its connection due to detected DOS attempt
 	fn dos(&self, peer_index: PeerIndex, reason: &str);
+	/// Close and remove connection of the peer, which fails to serve us (stalls, timeouts). Peer isn't banned
+	fn disconnect(&self, peer_index: PeerIndex, reason: &str);
 }
 
 /// Filters for peers connections
@@ -84,6 +91,10 @@ pub trait PeersOptions {
 	fn set_block_announcement_type(&self, peer_index: PeerIndex, announcement_type: BlockAnnouncementType);
 	/// Set up new transaction announcement type for the connection
 	fn set_transaction_announcement_type(&self, peer_index: PeerIndex, announcement_type: TransactionAnnouncementType);
+	/// Set permissions, granted to the peer
+	fn set_permissions(&self, peer_index: PeerIndex, permissions: PeerPermissions);
+	/// Get permissions, granted to the peer
+	fn permissions(&self, peer_index: PeerIndex) -> PeerPermissions;
 }
 
 /// Single connected peer data
@@ -98,6 +109,10 @@ struct Peer {
 	pub block_announcement_type: BlockAnnouncementType,
 	/// Transaction announcement type
 	pub transaction_announcement_type: TransactionAnnouncementType,
+	/// Permissions, granted to this peer
+	pub permissions: PeerPermissions,
+	/// Accumulated misbehavior score
+	pub ban_score: u32,
 }
 
 /// Default implementation of connected peers container
@@ -116,6 +131,8 @@ impl Peer {
 			filter: ConnectionFilter::default(),
 			block_announcement_type: BlockAnnouncementType::SendInventory,
 			transaction_announcement_type: TransactionAnnouncementType::SendInventory,
+			permissions: PeerPermissions::default(),
+			ban_score: 0,
 		}
 	}
 }
@@ -142,16 +159,51 @@ impl PeersContainer for PeersImpl {
 		}
 	}
 
-	fn misbehaving(&self, peer_index: PeerIndex, reason: &str) {
-		if let Some(peer) = self.peers.write().remove(&peer_index) {
+	fn penalize(&self, peer_index: PeerIndex, score: u32, reason: &str) {
+		if self.permissions(peer_index).noban {
+			warn!(target: "sync", "Peer#{} has misbehaved: {}. Not disconnecting, because it has noban permission", peer_index, reason);
+			return;
+		}
+
+		let mut peers = self.peers.write();
+		let ban_score = match peers.get_mut(&peer_index) {
+			Some(peer) => {
+				peer.ban_score = peer.ban_score.saturating_add(score);
+				peer.ban_score
+			},
+			None => return,
+		};
+
+		if ban_score < MAX_BAN_SCORE {
+			warn!(target: "sync", "Peer#{} has misbehaved: {}. Ban score is {}", peer_index, reason, ban_score);
+			return;
+		}
+
+		if let Some(peer) = peers.remove(&peer_index) {
 			warn!(target: "sync", "Disconnecting from peer#{} due to misbehavior: {}", peer_index, reason);
-			peer.connection.close();
+			peer.connection.ban();
 		}
 	}
 
+	fn misbehaving(&self, peer_index: PeerIndex, reason: &str) {
+		self.penalize(peer_index, MAX_BAN_SCORE, reason);
+	}
+
 	fn dos(&self, peer_index: PeerIndex, reason: &str) {
+		if self.permissions(peer_index).noban {
+			warn!(target: "sync", "Peer#{} has tried to DoS us: {}. Not disconnecting, because it has noban permission", peer_index, reason);
+			return;
+		}
+
 		if let Some(peer) = self.peers.write().remove(&peer_index) {
 			warn!(target: "sync", "Disconnecting from peer#{} due to DoS: {}", peer_index, reason);
+			peer.connection.ban();
+		}
+	}
+
+	fn disconnect(&self, peer_index: PeerIndex, reason: &str) {
+		if let Some(peer) = self.peers.write().remove(&peer_index) {
+			warn!(target: "sync", "Disconnecting from peer#{}: {}", peer_index, reason);
 			peer.connection.close();
 		}
 	}
@@ -232,4 +284,74 @@ impl PeersOptions for PeersImpl {
 			peer.transaction_announcement_type = announcement_type;
 		}
 	}
+
+	fn set_permissions(&self, peer_index: PeerIndex, permissions: PeerPermissions) {
+		if let Some(peer) = self.peers.write().get_mut(&peer_index) {
+			peer.permissions = permissions;
+		}
+	}
+
+	fn permissions(&self, peer_index: PeerIndex) -> PeerPermissions {
+		self.peers.read().get(&peer_index)
+			.map(|peer| peer.permissions)
+			.unwrap_or_default()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use message::Services;
+	use p2p::PeerPermissions;
+	use inbound_connection::tests::DummyOutboundSyncConnection;
+	use super::{PeersImpl, PeersContainer, PeersOptions, MAX_BAN_SCORE};
+
+	#[test]
+	fn misbehaving_peer_is_disconnected() {
+		let peers = PeersImpl::default();
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		peers.misbehaving(0, "simulated");
+		assert!(!peers.enumerate().contains(&0));
+
+		peers.insert(1, Services::default(), DummyOutboundSyncConnection::new());
+		peers.dos(1, "simulated");
+		assert!(!peers.enumerate().contains(&1));
+	}
+
+	#[test]
+	fn peer_is_disconnected_when_ban_score_is_reached() {
+		let peers = PeersImpl::default();
+		let connection = DummyOutboundSyncConnection::new();
+		peers.insert(0, Services::default(), connection.clone());
+
+		peers.penalize(0, MAX_BAN_SCORE / 2, "simulated");
+		assert!(peers.enumerate().contains(&0));
+		assert_eq!(connection.messages.lock().get("ban"), None);
+
+		peers.penalize(0, MAX_BAN_SCORE / 2, "simulated");
+		assert!(!peers.enumerate().contains(&0));
+		assert_eq!(connection.messages.lock().get("ban"), Some(&1));
+	}
+
+	#[test]
+	fn disconnected_peer_is_not_banned() {
+		let peers = PeersImpl::default();
+		let connection = DummyOutboundSyncConnection::new();
+		peers.insert(0, Services::default(), connection.clone());
+
+		peers.disconnect(0, "simulated");
+		assert!(!peers.enumerate().contains(&0));
+		assert_eq!(connection.messages.lock().get("close"), Some(&1));
+		assert_eq!(connection.messages.lock().get("ban"), None);
+	}
+
+	#[test]
+	fn misbehaving_noban_peer_is_not_disconnected() {
+		let peers = PeersImpl::default();
+		peers.insert(0, Services::default(), DummyOutboundSyncConnection::new());
+		peers.set_permissions(0, PeerPermissions { noban: true, ..Default::default() });
+		peers.penalize(0, MAX_BAN_SCORE, "simulated");
+		peers.misbehaving(0, "simulated");
+		peers.dos(0, "simulated");
+		assert!(peers.enumerate().contains(&0));
+	}
 }
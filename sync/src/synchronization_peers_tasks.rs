@@ -4,7 +4,7 @@ use std::collections::{HashMap, HashSet};
 use linked_hash_map::LinkedHashMap;
 use time::precise_time_s;
 use primitives::hash::H256;
-use types::PeerIndex;
+use types::{PeerIndex, LOCAL_PEER_INDEX};
 use utils::AverageSpeedMeter;
 
 /// Max peer failures # before excluding from sync process
@@ -160,8 +160,19 @@ impl PeersTasks {
 		self.stats.get_mut(&peer_index)
 	}
 
+	/// Get mutable reference to active blocks request of the peer
+	#[cfg(test)]
+	pub fn get_blocks_request_mut(&mut self, peer_index: PeerIndex) -> Option<&mut BlocksRequest> {
+		self.blocks_requests.get_mut(&peer_index)
+	}
+
 	/// Mark peer as useful.
 	pub fn useful_peer(&mut self, peer_index: PeerIndex) {
+		// blocks, mined by the node itself, are not requested from anyone
+		if peer_index == LOCAL_PEER_INDEX {
+			return;
+		}
+
 		// if peer is unknown => insert to idle queue
 		// if peer is known && not useful => insert to idle queue
 		if self.all.insert(peer_index)
@@ -402,7 +413,7 @@ impl fmt::Debug for Information {
 mod tests {
 	use primitives::hash::H256;
 	use super::{PeersTasks, MAX_PEER_FAILURES, MAX_BLOCKS_FAILURES};
-	use types::PeerIndex;
+	use types::{PeerIndex, LOCAL_PEER_INDEX};
 
 	#[test]
 	fn peers_empty_on_start() {
@@ -415,6 +426,15 @@ mod tests {
 		assert_eq!(info.active, 0);
 	}
 
+	#[test]
+	fn local_peer_is_never_useful() {
+		let mut peers = PeersTasks::default();
+		peers.useful_peer(LOCAL_PEER_INDEX);
+		assert_eq!(peers.idle_peers_for_blocks().len(), 0);
+		assert_eq!(peers.idle_peers_for_headers().len(), 0);
+		assert!(peers.useful_peers().is_empty());
+	}
+
 	#[test]
 	fn peers_all_unuseful_after_reset() {
 		let mut peers = PeersTasks::default();
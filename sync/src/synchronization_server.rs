@@ -10,6 +10,10 @@ use primitives::hash::H256;
 use synchronization_executor::{Task, TaskExecutor};
 use types::{PeerIndex, RequestId, BlockHeight, StorageRef, ExecutorRef, MemoryPoolRef, PeersRef};
 
+/// Max depth of the block, for which 'getblocktxn' requests are served (BIP152).
+/// Full block is sent in response to requests for older blocks.
+pub const MAX_GETBLOCKTXN_DEPTH: BlockHeight = 10;
+
 /// Synchronization server task
 #[derive(Debug, PartialEq)]
 pub enum ServerTask {
@@ -23,6 +27,8 @@ pub enum ServerTask {
 	GetHeaders(PeerIndex, types::GetHeaders, RequestId),
 	/// Serve 'mempool' request
 	Mempool(PeerIndex),
+	/// Serve 'getblocktxn' request
+	GetBlockTxn(PeerIndex, types::GetBlockTxn),
 }
 
 /// Synchronization server
@@ -77,7 +83,8 @@ impl ServerTask {
 				| ServerTask::ReversedGetData(peer_index, _, _)
 				| ServerTask::GetBlocks(peer_index, _)
 				| ServerTask::GetHeaders(peer_index, _, _)
-				| ServerTask::Mempool(peer_index) => peer_index,
+				| ServerTask::Mempool(peer_index)
+				| ServerTask::GetBlockTxn(peer_index, _) => peer_index,
 		}
 	}
 }
@@ -230,6 +237,7 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 			ServerTask::GetBlocks(peer_index, message) => self.serve_get_blocks(peer_index, message),
 			ServerTask::GetHeaders(peer_index, message, request_id) => self.serve_get_headers(peer_index, message, request_id),
 			ServerTask::Mempool(peer_index) => self.serve_mempool(peer_index),
+			ServerTask::GetBlockTxn(peer_index, message) => self.serve_get_block_txn(peer_index, message),
 		}
 
 		None
@@ -302,6 +310,47 @@ impl<TExecutor> ServerTaskExecutor<TExecutor> where TExecutor: TaskExecutor {
 		Some(ServerTask::ReversedGetData(peer_index, message, notfound))
 	}
 
+	fn serve_get_block_txn(&self, peer_index: PeerIndex, message: types::GetBlockTxn) {
+		let request = message.request;
+		let block = match self.storage.block(request.blockhash.clone().into()) {
+			Some(block) => block,
+			None => {
+				trace!(target: "sync", "'getblocktxn' request from peer#{} is ignored as block {} is unknown", peer_index, request.blockhash.to_reversed_str());
+				return;
+			},
+		};
+
+		// only serve transactions of recent blocks (this could be used to fingerprint node otherwise)
+		let best_block_number = self.storage.best_block().number;
+		let is_recent_block = self.storage.block_number(&request.blockhash)
+			.map(|block_number| block_number + MAX_GETBLOCKTXN_DEPTH >= best_block_number)
+			.unwrap_or(false);
+		if !is_recent_block {
+			trace!(target: "sync", "'getblocktxn' request from peer#{} is for old block {} => responding with full block", peer_index, request.blockhash.to_reversed_str());
+			self.executor.execute(Task::Block(peer_index, block));
+			return;
+		}
+
+		let mut transactions = Vec::with_capacity(request.indexes.len());
+		for index in request.indexes {
+			match block.transactions().get(index) {
+				Some(transaction) => transactions.push(transaction.raw.clone()),
+				None => {
+					self.peers.dos(peer_index, &format!("Got 'getblocktxn' message with out-of-bounds transaction index {}", index));
+					return;
+				},
+			}
+		}
+
+		trace!(target: "sync", "'getblocktxn' response to peer#{} is ready with {} transactions", peer_index, transactions.len());
+		self.executor.execute(Task::BlockTxn(peer_index, types::BlockTxn {
+			request: common::BlockTransactions {
+				blockhash: request.blockhash,
+				transactions: transactions,
+			},
+		}));
+	}
+
 	fn serve_get_blocks(&self, peer_index: PeerIndex, message: types::GetBlocks) {
 		if let Some(block_height) = self.locate_best_common_block(&message.hash_stop, &message.block_locator_hashes) {
 			let inventory: Vec<_> = (block_height + 1..block_height + 1 + (types::GETBLOCKS_MAX_RESPONSE_HASHES as BlockHeight))
@@ -397,7 +446,7 @@ pub mod tests {
 	use parking_lot::{Mutex, RwLock};
 	use db::{BlockChainDatabase};
 	use message::types;
-	use message::common::{Services, InventoryVector, InventoryType};
+	use message::common::{Services, InventoryVector, InventoryType, BlockTransactions, BlockTransactionsRequest};
 	use primitives::hash::H256;
 	use chain::Transaction;
 	use inbound_connection::tests::DummyOutboundSyncConnection;
@@ -407,7 +456,7 @@ pub mod tests {
 	use synchronization_executor::tests::DummyTaskExecutor;
 	use synchronization_peers::{PeersContainer, PeersFilters, PeersImpl};
 	use types::{PeerIndex, StorageRef, MemoryPoolRef, PeersRef, ExecutorRef};
-	use super::{Server, ServerTask, ServerImpl, ServerTaskExecutor};
+	use super::{Server, ServerTask, ServerImpl, ServerTaskExecutor, MAX_GETBLOCKTXN_DEPTH};
 
 	pub struct DummyServer {
 		tasks: Mutex<Vec<ServerTask>>,
@@ -475,6 +524,49 @@ pub mod tests {
 		assert_eq!(tasks, vec![Task::Block(0, test_data::genesis().into())]);
 	}
 
+	#[test]
+	fn server_getblocktxn_responds_blocktxn() {
+		let (_, _, executor, _, server) = create_synchronization_server();
+		// when asking for genesis coinbase transaction
+		let genesis = test_data::genesis();
+		server.execute(ServerTask::GetBlockTxn(0, types::GetBlockTxn {
+			request: BlockTransactionsRequest {
+				blockhash: genesis.hash(),
+				indexes: vec![0],
+			},
+		}));
+		// => respond with transaction
+		let tasks = DummyTaskExecutor::wait_tasks(executor);
+		assert_eq!(tasks, vec![Task::BlockTxn(0, types::BlockTxn {
+			request: BlockTransactions {
+				blockhash: genesis.hash(),
+				transactions: vec![genesis.transactions[0].clone()],
+			},
+		})]);
+	}
+
+	#[test]
+	fn server_getblocktxn_responds_block_when_block_is_old() {
+		let (storage, _, executor, _, server) = create_synchronization_server();
+		for block in test_data::build_n_empty_blocks_from_genesis(MAX_GETBLOCKTXN_DEPTH + 1, 1) {
+			let block_hash = block.hash();
+			storage.insert(block.into()).expect("Db write error");
+			storage.canonize(&block_hash).unwrap();
+		}
+
+		// when asking for genesis coinbase transaction
+		let genesis = test_data::genesis();
+		server.execute(ServerTask::GetBlockTxn(0, types::GetBlockTxn {
+			request: BlockTransactionsRequest {
+				blockhash: genesis.hash(),
+				indexes: vec![0],
+			},
+		}));
+		// => respond with full block
+		let tasks = DummyTaskExecutor::wait_tasks(executor);
+		assert_eq!(tasks, vec![Task::Block(0, genesis.into())]);
+	}
+
 	#[test]
 	fn server_getblocks_do_not_responds_inventory_when_synchronized() {
 		let (_, _, executor, _, server) = create_synchronization_server();
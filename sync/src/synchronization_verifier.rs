@@ -9,7 +9,7 @@ use chain::{IndexedBlockHeader, IndexedBlock, IndexedTransaction};
 use network::ConsensusParams;
 use primitives::hash::H256;
 use verification::{BackwardsCompatibleChainVerifier as ChainVerifier, Verify as VerificationVerify,
-	Error as VerificationError, VerificationLevel};
+	Error as VerificationError, TransactionError, VerificationLevel};
 use types::{PeerIndex, BlockHeight, StorageRef, MemoryPoolRef};
 use utils::MemoryPoolTransactionOutputProvider;
 use VerificationParameters;
@@ -36,7 +36,7 @@ pub trait BlockVerificationSink : Send + Sync + 'static {
 	/// When block verification has completed successfully.
 	fn on_block_verification_success(&self, block: IndexedBlock) -> Option<Vec<VerificationTask>>;
 	/// When block verification has failed.
-	fn on_block_verification_error(&self, err: &str, hash: &H256);
+	fn on_block_verification_error(&self, err: &VerificationError, hash: &H256);
 }
 
 /// Transaction verification events sink
@@ -44,7 +44,7 @@ pub trait TransactionVerificationSink : Send + Sync + 'static {
 	/// When transaction verification has completed successfully.
 	fn on_transaction_verification_success(&self, transaction: IndexedTransaction);
 	/// When transaction verification has failed.
-	fn on_transaction_verification_error(&self, err: &str, hash: &H256);
+	fn on_transaction_verification_error(&self, err: &TransactionError, hash: &H256);
 }
 
 /// Verification events sink
@@ -248,7 +248,7 @@ impl AsyncVerifier {
 							}
 						},
 						Err(e) => {
-							sink.on_block_verification_error(&format!("{:?}", e), block.hash())
+							sink.on_block_verification_error(&e, block.hash())
 						}
 					}
 				},
@@ -256,14 +256,14 @@ impl AsyncVerifier {
 					// output provider must check previous outputs in both storage && memory pool
 					match MemoryPoolTransactionOutputProvider::for_transaction(storage.clone(), memory_pool, &transaction.raw) {
 						Err(e) => {
-							sink.on_transaction_verification_error(&format!("{:?}", e), &transaction.hash);
+							sink.on_transaction_verification_error(&e, &transaction.hash);
 							continue; // with new verification sub-task
 						},
 						Ok(tx_output_provider) => {
 							let time: u32 = get_time().sec as u32;
 							match verifier.verifier.verify_mempool_transaction(storage.as_block_header_provider(), &tx_output_provider, height, time, &transaction) {
 								Ok(_) => sink.on_transaction_verification_success(transaction.into()),
-								Err(e) => sink.on_transaction_verification_error(&format!("{:?}", e), &transaction.hash),
+								Err(e) => sink.on_transaction_verification_error(&e, &transaction.hash),
 							}
 						},
 					};
@@ -320,7 +320,10 @@ pub struct SyncVerifier<T: VerificationSink> {
 impl<T> SyncVerifier<T> where T: VerificationSink {
 	/// Create new sync verifier
 	pub fn new(consensus: ConsensusParams, storage: StorageRef, sink: Arc<T>, verification_params: VerificationParameters) -> Self {
-		let verifier = ChainVerifier::new(storage.clone(), consensus);
+		let verifier = ChainVerifier::new(storage.clone(), consensus)
+			.with_transaction_time_budget(verification_params.transaction_time_budget)
+			.with_sapling_anchor_window(verification_params.sapling_anchor_window)
+			.with_script_verification_pool(verification_params.script_verification_pool.clone());
 		let verifier = ChainVerifierWrapper::new(Arc::new(verifier), &storage, verification_params);
 		SyncVerifier {
 			verifier: verifier,
@@ -344,7 +347,7 @@ impl<T> Verifier for SyncVerifier<T> where T: VerificationSink {
 				// => we could ignore decanonized transactions
 				self.sink.on_block_verification_success(block.into());
 			},
-			Err(e) => self.sink.on_block_verification_error(&format!("{:?}", e), block.hash()),
+			Err(e) => self.sink.on_block_verification_error(&e, block.hash()),
 		}
 	}
 
@@ -377,7 +380,8 @@ pub mod tests {
 	#[derive(Default)]
 	pub struct DummyVerifier {
 		sink: Option<Arc<CoreVerificationSink<DummyTaskExecutor>>>,
-		errors: HashMap<H256, String>,
+		errors: HashMap<H256, VerificationError>,
+		transaction_errors: HashMap<H256, TransactionError>,
 		actual_checks: HashSet<H256>,
 		storage: Option<StorageRef>,
 		memory_pool: Option<MemoryPoolRef>,
@@ -401,11 +405,18 @@ pub mod tests {
 			self.verifier = Some(ChainVerifierWrapper::new(verifier, self.storage.as_ref().unwrap(), VerificationParameters {
 				verification_level: VerificationLevel::FULL,
 				verification_edge: 0u8.into(),
+				transaction_time_budget: None,
+				sapling_anchor_window: None,
+				script_verification_pool: None,
 			}));
 		}
 
-		pub fn error_when_verifying(&mut self, hash: H256, err: &str) {
-			self.errors.insert(hash, err.into());
+		pub fn error_when_verifying(&mut self, hash: H256, err: VerificationError) {
+			self.errors.insert(hash, err);
+		}
+
+		pub fn error_when_verifying_transaction(&mut self, hash: H256, err: TransactionError) {
+			self.transaction_errors.insert(hash, err);
 		}
 
 		pub fn actual_check_when_verifying(&mut self, hash: H256) {
@@ -424,7 +435,7 @@ pub mod tests {
 		fn verify_block(&self, block: PartiallyVerifiedBlock) {
 			match self.sink {
 				Some(ref sink) => match self.errors.get(&block.hash()) {
-					Some(err) => sink.on_block_verification_error(&err, &block.hash()),
+					Some(err) => sink.on_block_verification_error(err, &block.hash()),
 					None => {
 						if self.actual_checks.contains(block.hash()) {
 							AsyncVerifier::execute_single_task(sink, self.storage.as_ref().unwrap(), self.memory_pool.as_ref().unwrap(), self.verifier.as_ref().unwrap(), VerificationTask::VerifyBlock(block));
@@ -439,8 +450,8 @@ pub mod tests {
 
 		fn verify_transaction(&self, _height: BlockHeight, transaction: IndexedTransaction) {
 			match self.sink {
-				Some(ref sink) => match self.errors.get(&transaction.hash) {
-					Some(err) => sink.on_transaction_verification_error(&err, &transaction.hash),
+				Some(ref sink) => match self.transaction_errors.get(&transaction.hash) {
+					Some(err) => sink.on_transaction_verification_error(err, &transaction.hash),
 					None => {
 						if self.actual_checks.contains(&transaction.hash) {
 							let next_block_height = self.storage.as_ref().unwrap().best_block().number + 1;
@@ -464,12 +475,18 @@ pub mod tests {
 		assert_eq!(ChainVerifierWrapper::new(verifier.clone(), &storage, VerificationParameters {
 			verification_level: VerificationLevel::NO_VERIFICATION,
 			verification_edge: test_data::genesis().hash(),
+			transaction_time_budget: None,
+			sapling_anchor_window: None,
+			script_verification_pool: None,
 		}).enforce_full_verification.load(Ordering::Relaxed), true);
 
 		// switching to full verification when block with given hash is coming
 		let wrapper = ChainVerifierWrapper::new(verifier, &storage, VerificationParameters {
 			verification_level: VerificationLevel::NO_VERIFICATION,
 			verification_edge: test_data::block_h1().hash(),
+			transaction_time_budget: None,
+			sapling_anchor_window: None,
+			script_verification_pool: None,
 		});
 		assert_eq!(wrapper.enforce_full_verification.load(Ordering::Relaxed), false);
 		let block: IndexedBlock = test_data::block_h1().into();
@@ -500,7 +517,7 @@ pub mod tests {
 			blocks.push(next_block.into());
 		}
 
-		let coinbase_transaction_hash = blocks[1].transactions[0].hash.clone();
+		let coinbase_transaction_hash = blocks[1].transactions()[0].hash.clone();
 		let last_block_hash = blocks[blocks.len() - 1].hash().clone();
 		let storage: StorageRef = Arc::new(BlockChainDatabase::init_test_chain(blocks));
 		let verifier = Arc::new(ChainVerifier::new(storage.clone(), ConsensusParams::new(Network::Unitest)));
@@ -525,6 +542,9 @@ pub mod tests {
 		let wrapper = ChainVerifierWrapper::new(verifier.clone(), &storage, VerificationParameters {
 			verification_level: VerificationLevel::HEADER,
 			verification_edge: 1.into(),
+			transaction_time_budget: None,
+			sapling_anchor_window: None,
+			script_verification_pool: None,
 		});
 		assert_eq!(wrapper.verify_block(&bad_transaction_block.clone().into()), Ok(()));
 
@@ -532,6 +552,9 @@ pub mod tests {
 		let wrapper = ChainVerifierWrapper::new(verifier, &storage, VerificationParameters {
 			verification_level: VerificationLevel::FULL,
 			verification_edge: 1.into(),
+			transaction_time_budget: None,
+			sapling_anchor_window: None,
+			script_verification_pool: None,
 		});
 		assert_eq!(wrapper.verify_block(&bad_transaction_block.into()), Err(VerificationError::Transaction(1, TransactionError::Signature(0, ScriptError::InvalidStackOperation))));
 	}
@@ -546,6 +569,9 @@ pub mod tests {
 		let wrapper = ChainVerifierWrapper::new(verifier.clone(), &storage, VerificationParameters {
 			verification_level: VerificationLevel::NO_VERIFICATION,
 			verification_edge: 1.into(),
+			transaction_time_budget: None,
+			sapling_anchor_window: None,
+			script_verification_pool: None,
 		});
 		assert_eq!(wrapper.verify_block(&bad_block.clone().into()), Ok(()));
 
@@ -553,6 +579,9 @@ pub mod tests {
 		let wrapper = ChainVerifierWrapper::new(verifier, &storage, VerificationParameters {
 			verification_level: VerificationLevel::FULL,
 			verification_edge: 1.into(),
+			transaction_time_budget: None,
+			sapling_anchor_window: None,
+			script_verification_pool: None,
 		});
 		assert_eq!(wrapper.verify_block(&bad_block.into()), Err(VerificationError::Empty));
 	}
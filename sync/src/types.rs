@@ -20,6 +20,9 @@ pub type RequestId = u32;
 /// Peer is indexed using this type
 pub type PeerIndex = usize;
 
+/// Index of the virtual peer, which provides blocks, mined by the node itself
+pub const LOCAL_PEER_INDEX: PeerIndex = ::std::usize::MAX;
+
 // No-error, no-result future
 pub type EmptyBoxFuture = Box<Future<Item=(), Error=()> + Send>;
 
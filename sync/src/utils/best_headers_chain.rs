@@ -1,7 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use chain::IndexedBlockHeader;
+use primitives::bigint::U256;
 use primitives::hash::H256;
-use super::{HashQueue, HashPosition};
+use super::{HashQueue, HashPosition, HeadersStore, DEFAULT_MAX_IN_MEMORY_HEADERS, DEFAULT_MAX_HEADERS};
 
 /// Best headers chain information
 #[derive(Debug)]
@@ -12,25 +13,49 @@ pub struct Information {
 	pub total: u32,
 }
 
-// TODO: currently it supports first chain only (so whatever headers sequence came first, it is best)
-/// Builds the block-header-chain of in-memory blocks, for which only headers are currently known
+/// Route from the best headers chain to the side headers chain
+#[derive(Debug, PartialEq)]
+pub struct ForkRoute {
+	/// Best headers, which are replaced by side headers (ordered by height)
+	pub decanonized: Vec<H256>,
+	/// Side headers, which become best headers (ordered by height)
+	pub canonized: Vec<H256>,
+}
+
+/// Builds the block-header-chain of in-memory blocks, for which only headers are currently known.
+/// Side headers (forks of the best chain) are also kept, so that the best chain could be switched
+/// to the side chain with more work.
 #[derive(Debug)]
 pub struct BestHeadersChain {
 	/// Best hash in storage
 	storage_best_hash: H256,
+	/// Work of the best storage block, relative to the work of the storage block we have started with
+	storage_best_work: U256,
 	/// Headers by hash
-	headers: HashMap<H256, IndexedBlockHeader>,
+	headers: HeadersStore,
 	/// Best chain
 	best: HashQueue,
+	/// Side chains headers
+	side: HashSet<H256>,
+	/// Work of the chain, ending with the header, relative to the work of the storage block we have started with
+	work: HashMap<H256, U256>,
 }
 
 impl BestHeadersChain {
 	/// Create new best headers chain
 	pub fn new(storage_best_hash: H256) -> Self {
+		BestHeadersChain::with_limits(storage_best_hash, DEFAULT_MAX_IN_MEMORY_HEADERS, DEFAULT_MAX_HEADERS)
+	}
+
+	/// Create new best headers chain, keeping at most `max_in_memory` of `max_headers` headers in memory
+	pub fn with_limits(storage_best_hash: H256, max_in_memory: usize, max_headers: usize) -> Self {
 		BestHeadersChain {
 			storage_best_hash: storage_best_hash,
-			headers: HashMap::new(),
+			storage_best_work: U256::zero(),
+			headers: HeadersStore::new(max_in_memory, max_headers),
 			best: HashQueue::new(),
+			side: HashSet::new(),
+			work: HashMap::new(),
 		}
 	}
 
@@ -42,15 +67,25 @@ impl BestHeadersChain {
 		}
 	}
 
+	/// Get number of headers that could be inserted
+	pub fn capacity(&self) -> usize {
+		self.headers.capacity()
+	}
+
 	/// Get header from main chain at given position
 	pub fn at(&self, height: u32) -> Option<IndexedBlockHeader> {
 		self.best.at(height)
-			.and_then(|hash| self.headers.get(&hash).cloned())
+			.and_then(|hash| self.headers.get(&hash))
 	}
 
 	/// Get header by given hash
 	pub fn by_hash(&self, hash: &H256) -> Option<IndexedBlockHeader> {
-		self.headers.get(hash).cloned()
+		self.headers.get(hash)
+	}
+
+	/// Returns true if header (either best or side) with given hash is known
+	pub fn contains(&self, hash: &H256) -> bool {
+		self.headers.contains(hash)
 	}
 
 	/// Get height of main chain
@@ -73,15 +108,35 @@ impl BestHeadersChain {
 			.expect("storage_best_hash is always known")
 	}
 
+	/// Get relative work of the chain, ending with given header. Returns None if work is unknown
+	/// (i.e. header isn't in the headers chain && it isn't the best storage block).
+	pub fn work(&self, hash: &H256) -> Option<U256> {
+		if *hash == self.storage_best_hash {
+			return Some(self.storage_best_work);
+		}
+		self.work.get(hash).cloned()
+	}
+
+	/// Get relative work of the best chain
+	pub fn best_work(&self) -> U256 {
+		self.work(&self.best_block_hash()).expect("work of the best block is always known")
+	}
+
 	/// Insert new block header
 	pub fn insert(&mut self, header: IndexedBlockHeader) -> bool {
 		// append to the best chain
 		if self.best_block_hash() == header.raw.previous_header_hash {
-			let header_hash = header.hash.clone();
-			self.best.push_back(header_hash);
-			self.headers.insert(header_hash.clone(), header).is_some()
+			if self.headers.is_full() {
+				return false;
+			}
+
+			let work = self.best_work() + header.raw.work().unwrap_or_else(U256::zero);
+			self.work.insert(header.hash.clone(), work);
+			self.side.remove(&header.hash);
+			self.best.push_back(header.hash.clone());
+			self.headers.insert(header)
 		} else {
-			self.headers.contains_key(&header.hash)
+			self.headers.contains(&header.hash)
 		}
 	}
 
@@ -92,9 +147,74 @@ impl BestHeadersChain {
 		}
 	}
 
+	/// Insert side chain header. Returns false if header has not been inserted, because its parent
+	/// work is unknown or there's no more space for headers.
+	pub fn insert_side(&mut self, header: IndexedBlockHeader) -> bool {
+		if self.headers.contains(&header.hash) {
+			return true;
+		}
+
+		let work = match self.work(&header.raw.previous_header_hash) {
+			Some(parent_work) => parent_work + header.raw.work().unwrap_or_else(U256::zero),
+			None => return false,
+		};
+		if self.headers.is_full() {
+			return false;
+		}
+
+		self.work.insert(header.hash.clone(), work);
+		self.side.insert(header.hash.clone());
+		self.headers.insert(header);
+		true
+	}
+
+	/// Get route from the best chain to the side chain, ending with given header.
+	/// Returns None if header isn't a side header, connected to the best chain.
+	pub fn fork_route(&self, hash: &H256) -> Option<ForkRoute> {
+		let mut canonized = Vec::new();
+		let mut hash = hash.clone();
+		while hash != self.storage_best_hash && !self.best.contains(&hash) {
+			if !self.side.contains(&hash) {
+				return None;
+			}
+
+			let parent_hash = match self.headers.get(&hash) {
+				Some(header) => header.raw.previous_header_hash,
+				None => return None,
+			};
+			canonized.push(hash);
+			hash = parent_hash;
+		}
+		canonized.reverse();
+
+		let fork_position = self.best.position(&hash).map(|position| position + 1).unwrap_or(0);
+		let decanonized = (fork_position..self.best.len())
+			.map(|position| self.best.at(position).expect("position < len; qed"))
+			.collect();
+
+		Some(ForkRoute {
+			decanonized: decanonized,
+			canonized: canonized,
+		})
+	}
+
+	/// Switch best chain to the side chain, using route from `fork_route`
+	pub fn reorganize(&mut self, route: &ForkRoute) {
+		for _ in 0..route.decanonized.len() {
+			let hash = self.best.pop_back().expect("decanonized headers are at the end of the best chain; qed");
+			self.side.insert(hash);
+		}
+		for hash in &route.canonized {
+			self.side.remove(hash);
+			self.best.push_back(hash.clone());
+		}
+	}
+
 	/// Remove block header with given hash and all its children
 	pub fn remove(&mut self, hash: &H256) {
-		if self.headers.remove(hash).is_some() {
+		if self.headers.remove(hash) {
+			self.work.remove(hash);
+			self.side.remove(hash);
 			match self.best.remove(hash) {
 				HashPosition::Front => self.clear(),
 				HashPosition::Inside(position) => self.clear_after(position),
@@ -115,6 +235,12 @@ impl BestHeadersChain {
 		if self.best.front().map(|h| &h == hash).unwrap_or(false) {
 			self.best.pop_front();
 			self.headers.remove(hash);
+			if let Some(work) = self.work.remove(hash) {
+				self.storage_best_work = work;
+			}
+		} else if let Some(work) = self.work.get(storage_best_hash) {
+			// side header has been inserted to the storage && it has become the best block
+			self.storage_best_work = *work;
 		}
 		self.storage_best_hash = storage_best_hash.clone();
 	}
@@ -123,6 +249,8 @@ impl BestHeadersChain {
 	pub fn clear(&mut self) {
 		self.headers.clear();
 		self.best.clear();
+		self.side.clear();
+		self.work.clear();
 	}
 
 	/// Remove headers after position
@@ -131,7 +259,9 @@ impl BestHeadersChain {
 			self.clear()
 		} else {
 			while self.best.len() > position {
-				self.headers.remove(&self.best.pop_back().expect("len() > position; qed"));
+				let hash = self.best.pop_back().expect("len() > position; qed");
+				self.headers.remove(&hash);
+				self.work.remove(&hash);
 			}
 		}
 	}
@@ -141,8 +271,10 @@ impl BestHeadersChain {
 mod tests {
 	extern crate test_data;
 
+	use chain;
+	use primitives::compact::Compact;
 	use primitives::hash::H256;
-	use super::BestHeadersChain;
+	use super::{BestHeadersChain, ForkRoute};
 
 	#[test]
 	fn best_chain_empty() {
@@ -238,4 +370,95 @@ mod tests {
 		assert!(!chain.insert(b1.clone().into()));
 		assert!(chain.insert(b1.clone().into()));
 	}
+
+	#[test]
+	fn best_chain_memory_is_bounded() {
+		let b0 = test_data::block_builder().header().build().build();
+		let mut chain = BestHeadersChain::with_limits(b0.hash(), 10, 1_000);
+		let mut headers: Vec<chain::IndexedBlockHeader> = Vec::new();
+		let mut parent = b0.hash();
+		for i in 0..200 {
+			let header: chain::IndexedBlockHeader = test_data::block_builder()
+				.header().parent(parent).time(i).build()
+				.build()
+				.block_header
+				.into();
+			parent = header.hash.clone();
+			headers.push(header);
+		}
+		chain.insert_n(headers.clone());
+
+		assert_eq!(chain.information().best, 200);
+		assert_eq!(chain.information().total, 200);
+		assert_eq!(chain.headers.in_memory_len(), 10);
+
+		// locator-style lookups by height && parent lookups
+		for &height in &[199u32, 198, 190, 150, 70, 0] {
+			let header = chain.at(height).unwrap();
+			assert_eq!(header, headers[height as usize]);
+			assert_eq!(chain.height(&header.hash), Some(height));
+			if height != 0 {
+				assert_eq!(chain.by_hash(&header.raw.previous_header_hash), Some(headers[height as usize - 1].clone()));
+			}
+		}
+		assert_eq!(chain.best_block_hash(), headers[199].hash);
+	}
+
+	#[test]
+	fn best_chain_length_is_bounded() {
+		let b0 = test_data::block_builder().header().build().build();
+		let b1 = test_data::block_builder().header().parent(b0.hash()).build().build().block_header;
+		let b2 = test_data::block_builder().header().parent(b1.hash()).build().build().block_header;
+		let mut chain = BestHeadersChain::with_limits(b0.hash(), 10, 1);
+		assert!(!chain.insert(b1.clone().into()));
+		assert_eq!(chain.capacity(), 0);
+		assert!(!chain.insert(b2.clone().into()));
+		assert_eq!(chain.information().best, 1);
+		assert_eq!(chain.information().total, 1);
+		assert_eq!(chain.best_block_hash(), b1.hash());
+	}
+
+	#[test]
+	fn best_chain_is_switched_to_side_chain_with_more_work() {
+		let b0 = test_data::block_builder().header().build().build();
+		let b1 = test_data::block_builder().header().parent(b0.hash()).build().build().block_header;
+		let b2 = test_data::block_builder().header().parent(b1.hash()).build().build().block_header;
+		let b3 = test_data::block_builder().header().parent(b2.hash()).build().build().block_header;
+		let f2 = test_data::block_builder().header().parent(b1.hash()).bits(Compact::new(0x1f07ffff)).build().build().block_header;
+		let mut chain = BestHeadersChain::new(b0.hash());
+
+		chain.insert_n(vec![b1.clone().into(), b2.clone().into(), b3.clone().into()]);
+		assert_eq!(chain.best_work(), 3.into());
+
+		// side header of unknown chain is not inserted
+		assert!(!chain.insert_side(test_data::block_builder().header().parent(H256::from(1)).build().build().block_header.into()));
+
+		assert!(chain.insert_side(f2.clone().into()));
+		assert_eq!(chain.work(&f2.hash()), Some(8193.into()));
+		assert_eq!(chain.best_block_hash(), b3.hash());
+		assert_eq!(chain.information().best, 3);
+		assert_eq!(chain.information().total, 4);
+
+		let route = chain.fork_route(&f2.hash()).unwrap();
+		assert_eq!(route, ForkRoute {
+			decanonized: vec![b2.hash(), b3.hash()],
+			canonized: vec![f2.hash()],
+		});
+		chain.reorganize(&route);
+		assert_eq!(chain.best_block_hash(), f2.hash());
+		assert_eq!(chain.best_work(), 8193.into());
+		assert_eq!(chain.information().best, 2);
+		assert_eq!(chain.information().total, 4);
+
+		// previous best chain is now the side chain
+		assert_eq!(chain.fork_route(&b3.hash()), Some(ForkRoute {
+			decanonized: vec![f2.hash()],
+			canonized: vec![b2.hash(), b3.hash()],
+		}));
+
+		// work is tracked when blocks are inserted to the storage
+		chain.block_inserted_to_storage(&b1.hash(), &b1.hash());
+		assert_eq!(chain.work(&b1.hash()), Some(1.into()));
+		assert_eq!(chain.best_work(), 8193.into());
+	}
 }
@@ -0,0 +1,244 @@
+use std::collections::{HashMap, HashSet};
+use byteorder::{ByteOrder, LittleEndian};
+use bitcrypto::{sha256, siphash24};
+use chain::{BlockHeader, IndexedBlock, IndexedBlockHeader, IndexedTransaction, Transaction, ShortTransactionID};
+use message::common::PrefilledTransaction;
+use miner::MemoryPool;
+use primitives::hash::H256;
+use ser::Stream;
+
+/// Error of compact block (BIP152) reconstruction.
+#[derive(Debug, PartialEq)]
+pub enum PartialBlockError {
+	/// Compact block or block transactions message is malformed.
+	Malformed(&'static str),
+	/// Short ids of some block transactions are the same => the full block is required.
+	ShortIdsCollision,
+}
+
+/// Block, reconstructed from the compact block. Some of its transactions may be missing.
+#[derive(Debug)]
+pub struct PartialBlock {
+	/// Block header.
+	header: IndexedBlockHeader,
+	/// Block transactions. None if transaction is missing.
+	transactions: Vec<Option<IndexedTransaction>>,
+}
+
+impl PartialBlock {
+	/// Reconstruct block from the compact block, using prefilled transactions && transactions from the memory pool.
+	pub fn new(
+		header: IndexedBlockHeader,
+		nonce: u64,
+		short_ids: Vec<ShortTransactionID>,
+		prefilled_transactions: Vec<PrefilledTransaction>,
+		memory_pool: &MemoryPool,
+	) -> Result<Self, PartialBlockError> {
+		let transactions_len = short_ids.len() + prefilled_transactions.len();
+		let mut transactions: Vec<Option<IndexedTransaction>> = (0..transactions_len).map(|_| None).collect();
+		for prefilled_transaction in prefilled_transactions {
+			let transaction = transactions.get_mut(prefilled_transaction.index)
+				.ok_or(PartialBlockError::Malformed("prefilled transaction index is out of bounds"))?;
+			if transaction.is_some() {
+				return Err(PartialBlockError::Malformed("duplicate prefilled transaction index"));
+			}
+
+			*transaction = Some(IndexedTransaction::from_raw(prefilled_transaction.transaction));
+		}
+
+		// short ids are filling the gaps between prefilled transactions
+		let mut short_ids_indexes = HashMap::with_capacity(short_ids.len());
+		let missing_indexes = transactions.iter().enumerate().filter(|&(_, tx)| tx.is_none()).map(|(index, _)| index);
+		for (index, short_id) in missing_indexes.zip(short_ids) {
+			if short_ids_indexes.insert(short_id, index).is_some() {
+				return Err(PartialBlockError::ShortIdsCollision);
+			}
+		}
+
+		// if several memory pool transactions have the same short id => request transaction from peer
+		let (key0, key1) = short_transaction_id_keys(nonce, &header.raw);
+		let mut ambiguous_indexes = HashSet::new();
+		for hash in memory_pool.get_transactions_ids() {
+			let index = match short_ids_indexes.get(&short_transaction_id(key0, key1, &hash)) {
+				Some(index) => *index,
+				None => continue,
+			};
+
+			if transactions[index].is_some() {
+				ambiguous_indexes.insert(index);
+				continue;
+			}
+
+			if let Some(transaction) = memory_pool.get(&hash) {
+				transactions[index] = Some(IndexedTransaction::new(hash, transaction.clone()));
+			}
+		}
+		for index in ambiguous_indexes {
+			transactions[index] = None;
+		}
+
+		Ok(PartialBlock {
+			header: header,
+			transactions: transactions,
+		})
+	}
+
+	/// Hash of the block.
+	pub fn hash(&self) -> &H256 {
+		&self.header.hash
+	}
+
+	/// Indexes of missing transactions.
+	pub fn missing_indexes(&self) -> Vec<usize> {
+		self.transactions.iter()
+			.enumerate()
+			.filter(|&(_, tx)| tx.is_none())
+			.map(|(index, _)| index)
+			.collect()
+	}
+
+	/// Fill missing transactions with transactions from `blocktxn` message.
+	pub fn fill(&mut self, transactions: Vec<Transaction>) -> Result<(), PartialBlockError> {
+		if transactions.len() != self.missing_indexes().len() {
+			return Err(PartialBlockError::Malformed("unexpected number of block transactions"));
+		}
+
+		let mut transactions = transactions.into_iter();
+		for transaction in self.transactions.iter_mut().filter(|tx| tx.is_none()) {
+			*transaction = transactions.next().map(IndexedTransaction::from_raw);
+		}
+
+		Ok(())
+	}
+
+	/// Convert into block. Returns None if some transactions are missing or if memory pool
+	/// transaction has the same short id as the block transaction (i.e. merkle root mismatch).
+	pub fn into_block(self) -> Option<IndexedBlock> {
+		let transactions: Option<Vec<_>> = self.transactions.into_iter().collect();
+		let block = IndexedBlock::new(self.header, transactions?);
+		if block.merkle_root() != block.header.raw.merkle_root_hash {
+			return None;
+		}
+
+		Some(block)
+	}
+}
+
+/// Compute siphash keys for short transactions ids of the compact block (BIP152).
+pub fn short_transaction_id_keys(nonce: u64, header: &BlockHeader) -> (u64, u64) {
+	let mut stream = Stream::new();
+	stream.append(header).append(&nonce);
+	let keys_hash = sha256(&stream.out());
+	(LittleEndian::read_u64(&keys_hash[0..8]), LittleEndian::read_u64(&keys_hash[8..16]))
+}
+
+/// Compute short id of the transaction (BIP152).
+pub fn short_transaction_id(key0: u64, key1: u64, transaction_hash: &H256) -> ShortTransactionID {
+	// 2 most significant bytes of siphash are dropped
+	let mut short_id = [0u8; 8];
+	LittleEndian::write_u64(&mut short_id, siphash24(key0, key1, &**transaction_hash));
+	ShortTransactionID::from(&short_id[0..6])
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use std::collections::HashSet;
+	use chain::{IndexedBlock, IndexedBlockHeader, Transaction};
+	use message::common::{BlockHeaderAndIDs, PrefilledTransaction};
+	use miner::{MemoryPool, NonZeroFeeCalculator};
+	use super::{PartialBlock, PartialBlockError, short_transaction_id_keys, short_transaction_id};
+
+	/// Build compact block, prefilling coinbase and transactions at given indexes.
+	fn build_compact_block(block: &IndexedBlock, nonce: u64, prefilled_indexes: HashSet<usize>) -> BlockHeaderAndIDs {
+		let (key0, key1) = short_transaction_id_keys(nonce, &block.header.raw);
+		let mut short_ids = Vec::new();
+		let mut prefilled_transactions = Vec::new();
+		for (index, transaction) in block.transactions().iter().enumerate() {
+			if index == 0 || prefilled_indexes.contains(&index) {
+				prefilled_transactions.push(PrefilledTransaction {
+					index: index,
+					transaction: transaction.raw.clone(),
+				});
+			} else {
+				short_ids.push(short_transaction_id(key0, key1, &transaction.hash));
+			}
+		}
+
+		BlockHeaderAndIDs {
+			header: block.header.raw.clone(),
+			nonce: nonce,
+			short_ids: short_ids,
+			prefilled_transactions: prefilled_transactions,
+		}
+	}
+
+	fn block_with_transactions() -> IndexedBlock {
+		let coinbase: Transaction = test_data::TransactionBuilder::coinbase().into();
+		let tx1: Transaction = test_data::TransactionBuilder::with_output(10).into();
+		let tx2: Transaction = test_data::TransactionBuilder::with_output(20).into();
+		let tx3: Transaction = test_data::TransactionBuilder::with_output(30).into();
+		test_data::block_builder()
+			.with_transactions(vec![coinbase, tx1, tx2, tx3])
+			.merkled_header().parent(test_data::genesis().hash()).build()
+			.build()
+			.into()
+	}
+
+	fn partial_block(compact: BlockHeaderAndIDs, memory_pool: &MemoryPool) -> Result<PartialBlock, PartialBlockError> {
+		PartialBlock::new(IndexedBlockHeader::from_raw(compact.header), compact.nonce,
+			compact.short_ids, compact.prefilled_transactions, memory_pool)
+	}
+
+	#[test]
+	fn short_transaction_id_matches_bip152() {
+		// siphash-2-4 with key 000102..0f of 000102..1f is 0x7127512f72f27cce
+		let key0 = 0x0706050403020100;
+		let key1 = 0x0f0e0d0c0b0a0908;
+		let hash = "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f".into();
+		assert_eq!(short_transaction_id(key0, key1, &hash), "ce7cf2722f51".into());
+	}
+
+	#[test]
+	fn reconstruct_block_from_memory_pool() {
+		let block = block_with_transactions();
+		let mut memory_pool = MemoryPool::new();
+		for transaction in block.transactions().iter().skip(1) {
+			memory_pool.insert_verified(transaction.clone(), &NonZeroFeeCalculator);
+		}
+
+		let compact = build_compact_block(&block, 42, HashSet::new());
+		let partial_block = partial_block(compact, &memory_pool).unwrap();
+		assert_eq!(partial_block.missing_indexes(), Vec::<usize>::new());
+		assert_eq!(partial_block.into_block(), Some(block));
+	}
+
+	#[test]
+	fn reconstruct_block_with_missing_transactions() {
+		let block = block_with_transactions();
+		let mut memory_pool = MemoryPool::new();
+		memory_pool.insert_verified(block.transactions()[2].clone(), &NonZeroFeeCalculator);
+
+		let compact = build_compact_block(&block, 42, vec![3].into_iter().collect());
+		let mut partial_block = partial_block(compact, &memory_pool).unwrap();
+		assert_eq!(partial_block.missing_indexes(), vec![1]);
+
+		assert_eq!(partial_block.fill(vec![]), Err(PartialBlockError::Malformed("unexpected number of block transactions")));
+		partial_block.fill(vec![block.transactions()[1].raw.clone()]).unwrap();
+		assert_eq!(partial_block.into_block(), Some(block));
+	}
+
+	#[test]
+	fn reconstruct_block_rejects_malformed_compact_block() {
+		let block = block_with_transactions();
+		let mut compact = build_compact_block(&block, 42, HashSet::new());
+		compact.prefilled_transactions[0].index = 100;
+		assert_eq!(partial_block(compact, &MemoryPool::new()).unwrap_err(),
+			PartialBlockError::Malformed("prefilled transaction index is out of bounds"));
+
+		let mut compact = build_compact_block(&block, 42, HashSet::new());
+		compact.short_ids[1] = compact.short_ids[0].clone();
+		assert_eq!(partial_block(compact, &MemoryPool::new()).unwrap_err(), PartialBlockError::ShortIdsCollision);
+	}
+}
@@ -68,7 +68,7 @@ impl ConnectionFilter {
 		}
 
 		// prepare result
-		let all_len = block.transactions.len();
+		let all_len = block.transactions().len();
 		let mut result = MerkleBlockArtefacts {
 			merkleblock: types::MerkleBlock {
 				block_header: block.header.raw.clone(),
@@ -80,7 +80,7 @@ impl ConnectionFilter {
 		};
 
 		// calculate hashes && match flags for all transactions
-		let (all_hashes, all_flags) = block.transactions.iter()
+		let (all_hashes, all_flags) = block.transactions().iter()
 			.fold((Vec::<H256>::with_capacity(all_len), BitVec::with_capacity(all_len)), |(mut all_hashes, mut all_flags), t| {
 				let flag = self.bloom_filter.filter_transaction(t);
 				all_flags.push(flag);
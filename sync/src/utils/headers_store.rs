@@ -0,0 +1,327 @@
+use std::{env, fs, process};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use parking_lot::Mutex;
+use chain::{BlockHeader, IndexedBlockHeader};
+use primitives::hash::H256;
+use ser::{serialize, deserialize};
+
+/// Default maximal number of headers, kept in memory. Single header is ~1.5Kb (mostly Equihash solution).
+pub const DEFAULT_MAX_IN_MEMORY_HEADERS: usize = 50_000;
+/// Default maximal number of stored headers. Index entry of spilled header is ~100 bytes.
+pub const DEFAULT_MAX_HEADERS: usize = 1_000_000;
+
+/// Used to generate unique names for spill files.
+static SPILL_FILES_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Location of the stored header.
+#[derive(Debug, Clone, Copy)]
+enum Location {
+	/// Header is kept in memory.
+	Memory,
+	/// Header is spilled to disk: offset and length of serialized header.
+	Disk(u64, u32),
+}
+
+/// Header store with bounded memory usage.
+///
+/// Last `max_in_memory` inserted headers are kept in memory. Older headers are spilled to the
+/// temporary file, so only compact index (hash => offset) of these headers is kept in memory.
+/// At most `max_headers` headers are stored.
+#[derive(Debug)]
+pub struct HeadersStore {
+	/// Maximal number of headers, kept in memory.
+	max_in_memory: usize,
+	/// Maximal number of stored headers.
+	max_headers: usize,
+	/// Location of every stored header.
+	index: HashMap<H256, Location>,
+	/// Headers, kept in memory, with their insertion order.
+	memory: HashMap<H256, (u64, BlockHeader)>,
+	/// Hashes of headers, kept in memory, by insertion order.
+	memory_order: BTreeMap<u64, H256>,
+	/// Insertion order of the next header.
+	next_order: u64,
+	/// Spill file (created on first spill) and its path.
+	spill: Mutex<Option<(fs::File, PathBuf)>>,
+	/// Size of spill file.
+	spill_len: u64,
+}
+
+impl HeadersStore {
+	pub fn new(max_in_memory: usize, max_headers: usize) -> Self {
+		HeadersStore {
+			max_in_memory: max_in_memory,
+			max_headers: max_headers,
+			index: HashMap::new(),
+			memory: HashMap::new(),
+			memory_order: BTreeMap::new(),
+			next_order: 0,
+			spill: Mutex::new(None),
+			spill_len: 0,
+		}
+	}
+
+	/// Number of stored headers.
+	pub fn len(&self) -> usize {
+		self.index.len()
+	}
+
+	/// Number of headers, kept in memory.
+	pub fn in_memory_len(&self) -> usize {
+		self.memory.len()
+	}
+
+	/// Number of headers that could be stored.
+	pub fn capacity(&self) -> usize {
+		self.max_headers.saturating_sub(self.index.len())
+	}
+
+	/// Returns true if no more headers could be stored.
+	pub fn is_full(&self) -> bool {
+		self.capacity() == 0
+	}
+
+	/// Returns true if header with given hash is stored.
+	pub fn contains(&self, hash: &H256) -> bool {
+		self.index.contains_key(hash)
+	}
+
+	/// Inserts header. Returns true if header has been already stored.
+	/// Header is not inserted if the store `is_full`.
+	pub fn insert(&mut self, header: IndexedBlockHeader) -> bool {
+		if self.index.contains_key(&header.hash) {
+			return true;
+		}
+
+		if self.is_full() {
+			warn!(target: "sync", "Ignoring header {}: headers store is full", header.hash.to_reversed_str());
+			return false;
+		}
+
+		let order = self.next_order;
+		self.next_order += 1;
+		self.memory_order.insert(order, header.hash.clone());
+		self.memory.insert(header.hash.clone(), (order, header.raw));
+		self.index.insert(header.hash, Location::Memory);
+
+		// newest headers are the most likely to be read => spill the oldest ones
+		while self.memory.len() > self.max_in_memory {
+			if !self.spill_oldest() {
+				break;
+			}
+		}
+
+		false
+	}
+
+	/// Returns header with given hash.
+	pub fn get(&self, hash: &H256) -> Option<IndexedBlockHeader> {
+		match self.index.get(hash) {
+			Some(&Location::Memory) => self.memory.get(hash)
+				.map(|&(_, ref header)| IndexedBlockHeader::new(hash.clone(), header.clone())),
+			Some(&Location::Disk(offset, len)) => match self.read_spilled(offset, len) {
+				Ok(header) => Some(IndexedBlockHeader::new(hash.clone(), header)),
+				Err(err) => {
+					error!(target: "sync", "Failed to read spilled header {}: {}", hash.to_reversed_str(), err);
+					None
+				},
+			},
+			None => None,
+		}
+	}
+
+	/// Removes header with given hash. Returns true if header has been stored.
+	/// Space, occupied by spilled headers, is only reclaimed by `clear`.
+	pub fn remove(&mut self, hash: &H256) -> bool {
+		match self.index.remove(hash) {
+			Some(Location::Memory) => {
+				if let Some((order, _)) = self.memory.remove(hash) {
+					self.memory_order.remove(&order);
+				}
+				true
+			},
+			Some(Location::Disk(_, _)) => true,
+			None => false,
+		}
+	}
+
+	/// Removes all headers.
+	pub fn clear(&mut self) {
+		self.index.clear();
+		self.memory.clear();
+		self.memory_order.clear();
+		self.remove_spill_file();
+	}
+
+	/// Moves the oldest in-memory header to the spill file. Returns false if header has not been spilled.
+	fn spill_oldest(&mut self) -> bool {
+		let (order, hash) = match self.memory_order.iter().next() {
+			Some((order, hash)) => (*order, hash.clone()),
+			None => return false,
+		};
+
+		let (_, header) = self.memory.remove(&hash).expect("memory_order contains hashes of in-memory headers; qed");
+		match self.spill(&header) {
+			Ok(location) => {
+				self.memory_order.remove(&order);
+				self.index.insert(hash, location);
+				true
+			},
+			Err(err) => {
+				warn!(target: "sync", "Failed to spill header {} to disk: {}", hash.to_reversed_str(), err);
+				self.memory.insert(hash, (order, header));
+				false
+			},
+		}
+	}
+
+	/// Closes and removes the spill file (if it has been created).
+	fn remove_spill_file(&mut self) {
+		if let Some((file, path)) = self.spill.lock().take() {
+			drop(file);
+			if let Err(err) = fs::remove_file(&path) {
+				warn!(target: "sync", "Failed to remove headers spill file {}: {}", path.display(), err);
+			}
+		}
+		self.spill_len = 0;
+	}
+
+	fn spill(&mut self, header: &BlockHeader) -> Result<Location, io::Error> {
+		let mut spill = self.spill.lock();
+		if spill.is_none() {
+			let path = env::temp_dir().join(format!("pzec-headers-{}-{}",
+				process::id(), SPILL_FILES_COUNTER.fetch_add(1, Ordering::SeqCst)));
+			let file = fs::OpenOptions::new().read(true).write(true).create(true).truncate(true).open(&path)?;
+			*spill = Some((file, path));
+		}
+
+		let file = &mut spill.as_mut().expect("spill file is created above; qed").0;
+		let serialized = serialize(header);
+		file.seek(SeekFrom::Start(self.spill_len))?;
+		file.write_all(&serialized)?;
+
+		let location = Location::Disk(self.spill_len, serialized.len() as u32);
+		self.spill_len += serialized.len() as u64;
+		Ok(location)
+	}
+
+	fn read_spilled(&self, offset: u64, len: u32) -> Result<BlockHeader, io::Error> {
+		let mut spill = self.spill.lock();
+		let file = match *spill {
+			Some((ref mut file, _)) => file,
+			None => return Err(io::Error::new(io::ErrorKind::NotFound, "spill file is missing")),
+		};
+
+		let mut buffer = vec![0u8; len as usize];
+		file.seek(SeekFrom::Start(offset))?;
+		file.read_exact(&mut buffer)?;
+		deserialize(&buffer as &[u8])
+			.map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err)))
+	}
+}
+
+impl Drop for HeadersStore {
+	fn drop(&mut self) {
+		self.remove_spill_file();
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use chain::IndexedBlockHeader;
+	use primitives::hash::H256;
+	use super::HeadersStore;
+
+	fn headers(count: usize) -> Vec<IndexedBlockHeader> {
+		let mut parent = H256::default();
+		(0..count).map(|i| {
+			let header: IndexedBlockHeader = test_data::block_builder()
+				.header().parent(parent.clone()).nonce((i as u8).into()).time(i as u32).build()
+				.build()
+				.block_header
+				.into();
+			parent = header.hash.clone();
+			header
+		}).collect()
+	}
+
+	#[test]
+	fn headers_store_spills_headers_to_disk() {
+		let headers = headers(1_000);
+		let mut store = HeadersStore::new(100, 10_000);
+		for header in &headers {
+			assert!(!store.insert(header.clone()));
+		}
+		assert!(store.insert(headers[500].clone()));
+
+		// memory is bounded
+		assert_eq!(store.len(), 1_000);
+		assert_eq!(store.in_memory_len(), 100);
+
+		// newest headers are kept in memory
+		assert!(store.memory.contains_key(&headers[999].hash));
+		assert!(store.memory.contains_key(&headers[900].hash));
+		assert!(!store.memory.contains_key(&headers[899].hash));
+		assert!(!store.memory.contains_key(&headers[0].hash));
+
+		// lookups && parent lookups are working both for in-memory and spilled headers
+		for (i, header) in headers.iter().enumerate().skip(1) {
+			let stored = store.get(&header.hash).unwrap();
+			assert_eq!(stored, *header);
+			assert_eq!(store.get(&stored.raw.previous_header_hash), Some(headers[i - 1].clone()));
+		}
+
+		// removal
+		assert!(store.remove(&headers[10].hash));
+		assert!(store.remove(&headers[900].hash));
+		assert!(!store.remove(&headers[900].hash));
+		assert_eq!(store.get(&headers[900].hash), None);
+		assert_eq!(store.len(), 998);
+		assert_eq!(store.in_memory_len(), 99);
+
+		// spill file is removed on clear
+		let spill_path = store.spill.lock().as_ref().map(|&(_, ref path)| path.clone()).unwrap();
+		store.clear();
+		assert_eq!(store.len(), 0);
+		assert_eq!(store.in_memory_len(), 0);
+		assert_eq!(store.get(&headers[950].hash), None);
+		assert!(!spill_path.exists());
+
+		// spill file is recreated after clear
+		for header in &headers {
+			store.insert(header.clone());
+		}
+		assert_eq!(store.get(&headers[0].hash), Some(headers[0].clone()));
+		assert_eq!(store.get(&headers[999].hash), Some(headers[999].clone()));
+
+		// spill file is removed on drop
+		let spill_path = store.spill.lock().as_ref().map(|&(_, ref path)| path.clone()).unwrap();
+		assert!(spill_path.exists());
+		drop(store);
+		assert!(!spill_path.exists());
+	}
+
+	#[test]
+	fn headers_store_is_bounded() {
+		let headers = headers(30);
+		let mut store = HeadersStore::new(10, 20);
+		for header in &headers[..20] {
+			assert!(!store.insert(header.clone()));
+		}
+		assert!(store.is_full());
+
+		assert!(!store.insert(headers[20].clone()));
+		assert_eq!(store.len(), 20);
+		assert!(!store.contains(&headers[20].hash));
+
+		assert!(store.remove(&headers[0].hash));
+		assert!(!store.is_full());
+		assert!(!store.insert(headers[20].clone()));
+		assert!(store.contains(&headers[20].hash));
+	}
+}
@@ -11,6 +11,8 @@ pub enum KnownHashType {
 	Transaction,
 	/// Peer knows block with this hash
 	Block,
+	/// Peer knows transaction (or its malleated variant) with these relay id effects
+	RelayId,
 }
 
 /// Known-hashes filter
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use chain::{Transaction, TransactionOutput, OutPoint};
 use storage::TransactionOutputProvider;
-use miner::{DoubleSpendCheckResult, HashedOutPoint, NonFinalDoubleSpendSet};
+use miner::{DoubleSpendCheckResult, NonFinalDoubleSpendSet};
 use verification::TransactionError;
 use super::super::types::{MemoryPoolRef, StorageRef};
 
@@ -11,7 +11,7 @@ pub struct MemoryPoolTransactionOutputProvider {
 	/// Storage provider
 	storage_provider: StorageRef,
 	/// Transaction inputs from memory pool transactions
-	mempool_inputs: HashMap<HashedOutPoint, Option<TransactionOutput>>,
+	mempool_inputs: HashMap<OutPoint, Option<TransactionOutput>>,
 	/// Previous outputs, for which we should return 'Not spent' value.
 	/// These are used when new version of transaction is received.
 	nonfinal_spends: Option<NonFinalDoubleSpendSet>,
@@ -25,23 +25,24 @@ impl MemoryPoolTransactionOutputProvider {
 		let check_result = memory_pool.check_double_spend(transaction);
 		match check_result {
 			// input of transaction is already spent by another final transaction from memory pool
-			DoubleSpendCheckResult::DoubleSpend(_, hash, index) => Err(TransactionError::UsingSpentOutput(hash, index)),
+			DoubleSpendCheckResult::DoubleSpend(_, prevout) => Err(TransactionError::UsingSpentOutput(prevout.hash, prevout.index)),
 			// there are no transactions, which are spending same inputs in memory pool
 			DoubleSpendCheckResult::NoDoubleSpend => Ok(MemoryPoolTransactionOutputProvider {
 				storage_provider: storage,
 				mempool_inputs: transaction.inputs.iter()
 					.map(|input| (
-						input.previous_output.clone().into(),
+						input.previous_output.clone(),
 						memory_pool.transaction_output(&input.previous_output, usize::max_value()),
 					)).collect(),
 				nonfinal_spends: None,
 			}),
-			// there are non-final transactions, which are spending same inputs in memory pool
-			DoubleSpendCheckResult::NonFinalDoubleSpend(nonfinal_spends) => Ok(MemoryPoolTransactionOutputProvider {
+			// there are non-final (or replaceable) transactions, which are spending same inputs in memory pool
+			DoubleSpendCheckResult::NonFinalDoubleSpend(nonfinal_spends) |
+			DoubleSpendCheckResult::ReplaceableDoubleSpend(nonfinal_spends) => Ok(MemoryPoolTransactionOutputProvider {
 				storage_provider: storage,
 				mempool_inputs: transaction.inputs.iter()
 					.map(|input| (
-						input.previous_output.clone().into(),
+						input.previous_output.clone(),
 						memory_pool.transaction_output(&input.previous_output, usize::max_value()),
 					)).collect(),
 				nonfinal_spends: Some(nonfinal_spends),
@@ -52,11 +53,9 @@ impl MemoryPoolTransactionOutputProvider {
 
 impl TransactionOutputProvider for MemoryPoolTransactionOutputProvider {
 	fn transaction_output(&self, prevout: &OutPoint, transaction_index: usize) -> Option<TransactionOutput> {
-		let hashed_prevout: HashedOutPoint = prevout.clone().into();
-
 		// check if that is output of some transaction, which is virtually removed from memory pool
 		if let Some(ref nonfinal_spends) = self.nonfinal_spends {
-			if nonfinal_spends.dependent_spends.contains(&hashed_prevout) {
+			if nonfinal_spends.dependent_spends.contains(prevout) {
 				// transaction is trying to replace some nonfinal transaction
 				// + it also depends on this transaction
 				// => this is definitely an error
@@ -65,7 +64,7 @@ impl TransactionOutputProvider for MemoryPoolTransactionOutputProvider {
 		}
 
 		// check if this is output from memory pool transaction
-		if let Some(output) = self.mempool_inputs.get(&hashed_prevout) {
+		if let Some(output) = self.mempool_inputs.get(prevout) {
 			if let Some(ref output) = *output {
 				return Some(output.clone());
 			}
@@ -78,7 +77,7 @@ impl TransactionOutputProvider for MemoryPoolTransactionOutputProvider {
 	fn is_spent(&self, prevout: &OutPoint) -> bool {
 		// check if this output is spent by some non-final mempool transaction
 		if let Some(ref nonfinal_spends) = self.nonfinal_spends {
-			if nonfinal_spends.double_spends.contains(&prevout.clone().into()) {
+			if nonfinal_spends.double_spends.contains(prevout) {
 				return false;
 			}
 		}
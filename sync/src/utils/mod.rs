@@ -1,27 +1,39 @@
 mod average_speed_meter;
 mod best_headers_chain;
 mod bloom_filter;
+mod compact_block;
 mod connection_filter;
 mod fee_rate_filter;
 mod hash_queue;
+mod headers_store;
 mod known_hash_filter;
 mod memory_pool_transaction_provider;
+mod network_height_estimator;
 mod orphan_blocks_pool;
 mod orphan_transactions_pool;
+mod package_transaction_output_provider;
 mod partial_merkle_tree;
+mod relay_id_filter;
+mod rolling_bloom_filter;
 mod synchronization_state;
 
 pub use self::average_speed_meter::AverageSpeedMeter;
-pub use self::best_headers_chain::{BestHeadersChain, Information as BestHeadersChainInformation};
+pub use self::best_headers_chain::{BestHeadersChain, ForkRoute, Information as BestHeadersChainInformation};
 pub use self::bloom_filter::BloomFilter;
+pub use self::compact_block::{PartialBlock, PartialBlockError};
 pub use self::connection_filter::ConnectionFilter;
 pub use self::fee_rate_filter::FeeRateFilter;
 pub use self::hash_queue::{HashQueue, HashQueueChain, HashPosition};
+pub use self::headers_store::{HeadersStore, DEFAULT_MAX_IN_MEMORY_HEADERS, DEFAULT_MAX_HEADERS};
 pub use self::known_hash_filter::{KnownHashType, KnownHashFilter};
 pub use self::memory_pool_transaction_provider::MemoryPoolTransactionOutputProvider;
+pub use self::network_height_estimator::NetworkHeightEstimator;
 pub use self::orphan_blocks_pool::OrphanBlocksPool;
 pub use self::orphan_transactions_pool::{OrphanTransactionsPool, OrphanTransaction};
+pub use self::package_transaction_output_provider::PackageTransactionOutputProvider;
 pub use self::partial_merkle_tree::{PartialMerkleTree, build_partial_merkle_tree};
+pub use self::relay_id_filter::RelayIdFilter;
+pub use self::rolling_bloom_filter::RollingBloomFilter;
 pub use self::synchronization_state::SynchronizationState;
 
 /// Block height type
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+use super::super::types::{PeerIndex, BlockHeight};
+
+/// Number of peers that must advertise a height before it is trusted
+const HEIGHT_QUORUM: usize = 3;
+
+/// Estimates best height of the network, using starting heights advertised by peers.
+/// Peers can lie about their height => the estimate is the highest height, which is
+/// advertised by at least `HEIGHT_QUORUM` peers. There's no estimate until `HEIGHT_QUORUM`
+/// peers have advertised their heights.
+#[derive(Debug, Default)]
+pub struct NetworkHeightEstimator {
+	/// Starting heights, advertised by peers
+	heights: HashMap<PeerIndex, BlockHeight>,
+}
+
+impl NetworkHeightEstimator {
+	/// Remember starting height of the peer
+	pub fn insert(&mut self, peer_index: PeerIndex, height: BlockHeight) {
+		self.heights.insert(peer_index, height);
+	}
+
+	/// Forget height of the peer
+	pub fn remove(&mut self, peer_index: PeerIndex) {
+		self.heights.remove(&peer_index);
+	}
+
+	/// Estimate best height of the network
+	pub fn estimate(&self) -> Option<BlockHeight> {
+		if self.heights.len() < HEIGHT_QUORUM {
+			return None;
+		}
+
+		let mut heights: Vec<_> = self.heights.values().cloned().collect();
+		heights.sort_by(|a, b| b.cmp(a));
+		Some(heights[HEIGHT_QUORUM - 1])
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::NetworkHeightEstimator;
+
+	#[test]
+	fn network_height_estimator_empty() {
+		assert_eq!(NetworkHeightEstimator::default().estimate(), None);
+	}
+
+	#[test]
+	fn network_height_estimator_quorum_with_outlier() {
+		let mut estimator = NetworkHeightEstimator::default();
+		estimator.insert(0, 1000);
+		estimator.insert(1, 1001);
+		estimator.insert(2, 1000);
+		estimator.insert(3, 999);
+		assert_eq!(estimator.estimate(), Some(1000));
+
+		// absurdly high height must not affect the estimate
+		estimator.insert(4, 0x7fffffff);
+		assert_eq!(estimator.estimate(), Some(1000));
+
+		// the same goes for the second liar
+		estimator.insert(5, 0x7ffffffe);
+		assert_eq!(estimator.estimate(), Some(1001));
+
+		estimator.remove(4);
+		estimator.remove(5);
+		assert_eq!(estimator.estimate(), Some(1000));
+	}
+
+	#[test]
+	fn network_height_estimator_less_peers_than_quorum() {
+		let mut estimator = NetworkHeightEstimator::default();
+		estimator.insert(0, 1000);
+		assert_eq!(estimator.estimate(), None);
+		// single liar is not trusted until there's a quorum
+		estimator.insert(1, 0x7fffffff);
+		assert_eq!(estimator.estimate(), None);
+		estimator.insert(2, 1001);
+		assert_eq!(estimator.estimate(), Some(1000));
+		estimator.remove(2);
+		assert_eq!(estimator.estimate(), None);
+	}
+}
@@ -0,0 +1,76 @@
+use chain::{IndexedTransaction, TransactionOutput, OutPoint};
+use storage::TransactionOutputProvider;
+
+/// Transaction output provider, which also looks into outputs of transactions
+/// from the same package (i.e. transactions, which are verified before given one).
+pub struct PackageTransactionOutputProvider<'a> {
+	/// Outputs provider for transactions outside of the package
+	provider: &'a TransactionOutputProvider,
+	/// Transactions of the package, preceding verified transaction
+	package: &'a [IndexedTransaction],
+}
+
+impl<'a> PackageTransactionOutputProvider<'a> {
+	/// Create new provider for verifying transaction, which goes after given package
+	pub fn new(provider: &'a TransactionOutputProvider, package: &'a [IndexedTransaction]) -> Self {
+		PackageTransactionOutputProvider {
+			provider: provider,
+			package: package,
+		}
+	}
+}
+
+impl<'a> TransactionOutputProvider for PackageTransactionOutputProvider<'a> {
+	fn transaction_output(&self, prevout: &OutPoint, transaction_index: usize) -> Option<TransactionOutput> {
+		// check if this is output of package transaction
+		if let Some(transaction) = self.package.iter().find(|tx| tx.hash == prevout.hash) {
+			return transaction.raw.outputs.get(prevout.index as usize).cloned();
+		}
+
+		self.provider.transaction_output(prevout, transaction_index)
+	}
+
+	fn is_spent(&self, prevout: &OutPoint) -> bool {
+		// check if this output is spent by some package transaction
+		let is_spent_by_package = self.package.iter()
+			.flat_map(|tx| tx.raw.inputs.iter())
+			.any(|input| input.previous_output == *prevout);
+
+		is_spent_by_package || self.provider.is_spent(prevout)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use chain::{OutPoint, IndexedTransaction};
+	use storage::TransactionOutputProvider;
+	use db::BlockChainDatabase;
+	use super::PackageTransactionOutputProvider;
+
+	#[test]
+	fn package_transaction_output_provider_sees_package_outputs() {
+		let genesis = test_data::genesis();
+		let storage = BlockChainDatabase::init_test_chain(vec![genesis.clone().into()]);
+
+		let tx0: IndexedTransaction = test_data::TransactionBuilder::with_output(10).add_input(&genesis.transactions[0], 0).into();
+		let tx1: IndexedTransaction = test_data::TransactionBuilder::with_output(5).add_input(&tx0.raw, 0).into();
+		let package = vec![tx0.clone()];
+		let provider = PackageTransactionOutputProvider::new(&storage, &package);
+
+		let genesis_out = OutPoint { hash: genesis.transactions[0].hash(), index: 0 };
+		let tx0_out = OutPoint { hash: tx0.hash.clone(), index: 0 };
+		let tx1_out = OutPoint { hash: tx1.hash.clone(), index: 0 };
+
+		// output of package transaction is available && unspent
+		assert_eq!(provider.transaction_output(&tx0_out, 0), Some(tx0.raw.outputs[0].clone()));
+		assert!(!provider.is_spent(&tx0_out));
+		// output of storage transaction is available && spent by package transaction
+		assert_eq!(provider.transaction_output(&genesis_out, 0), Some(genesis.transactions[0].outputs[0].clone()));
+		assert!(provider.is_spent(&genesis_out));
+		// unknown output
+		assert_eq!(provider.transaction_output(&tx1_out, 0), None);
+		assert_eq!(provider.transaction_output(&OutPoint { hash: tx0.hash.clone(), index: 1 }, 0), None);
+	}
+}
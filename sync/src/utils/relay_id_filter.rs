@@ -0,0 +1,83 @@
+use linked_hash_map::LinkedHashMap;
+use chain::RelayId;
+use primitives::hash::H256;
+
+/// Maximal number of relay ids to store in relay-ids filter
+pub const MAX_RELAY_IDS_LEN: usize = 16384;
+
+/// Relay-ids filter. Remembers relay ids of recently received (verifying, orphaned or verified)
+/// transactions, so that malleated variants of these transactions could be detected.
+#[derive(Debug, Default)]
+pub struct RelayIdFilter {
+	/// Insertion-time ordered map of relay id effects => (relay id, transaction hash)
+	relay_ids: LinkedHashMap<H256, (RelayId, H256)>,
+}
+
+impl RelayIdFilter {
+	/// Remember relay id of the transaction. If variant with the same effects is already known,
+	/// it is replaced with this transaction.
+	pub fn insert(&mut self, relay_id: RelayId, hash: H256) {
+		self.relay_ids.insert(relay_id.effects.clone(), (relay_id, hash));
+		// remove oldest relay id, if limits overflow
+		if self.relay_ids.len() > MAX_RELAY_IDS_LEN {
+			self.relay_ids.pop_front();
+		}
+	}
+
+	/// Returns number of known relay ids
+	#[cfg(test)]
+	pub fn len(&self) -> usize {
+		self.relay_ids.len()
+	}
+
+	/// If transaction with given relay id is a malleated variant of the known transaction
+	/// (i.e. it has the same effects, but different signatures), returns hash of the known transaction
+	pub fn malleated_variant_of(&self, relay_id: &RelayId) -> Option<&H256> {
+		self.relay_ids.get(&relay_id.effects)
+			.and_then(|&(ref known_relay_id, ref known_hash)| if known_relay_id != relay_id { Some(known_hash) } else { None })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use chain::RelayId;
+	use primitives::hash::H256;
+	use super::{RelayIdFilter, MAX_RELAY_IDS_LEN};
+
+	fn relay_id(effects: u8, auth: u8) -> RelayId {
+		RelayId {
+			effects: H256::from(effects),
+			auth: H256::from(auth),
+		}
+	}
+
+	#[test]
+	fn relay_id_filter_detects_malleated_variant() {
+		let mut filter = RelayIdFilter::default();
+		filter.insert(relay_id(0, 0), H256::from(1));
+		assert_eq!(filter.malleated_variant_of(&relay_id(0, 0)), None);
+		assert_eq!(filter.malleated_variant_of(&relay_id(0, 1)), Some(&H256::from(1)));
+		assert_eq!(filter.malleated_variant_of(&relay_id(1, 0)), None);
+	}
+
+	#[test]
+	fn relay_id_filter_replaces_known_variant() {
+		let mut filter = RelayIdFilter::default();
+		filter.insert(relay_id(0, 0), H256::from(1));
+		filter.insert(relay_id(0, 1), H256::from(2));
+		assert_eq!(filter.len(), 1);
+		assert_eq!(filter.malleated_variant_of(&relay_id(0, 0)), Some(&H256::from(2)));
+		assert_eq!(filter.malleated_variant_of(&relay_id(0, 1)), None);
+	}
+
+	#[test]
+	fn relay_id_filter_limits() {
+		let mut filter = RelayIdFilter::default();
+		for i in 0..(MAX_RELAY_IDS_LEN + 1) as u64 {
+			let mut effects = H256::default();
+			effects[0..8].copy_from_slice(&[i as u8, (i >> 8) as u8, (i >> 16) as u8, 0, 0, 0, 0, 0]);
+			filter.insert(RelayId { effects: effects, auth: H256::default() }, H256::default());
+		}
+		assert_eq!(filter.len(), MAX_RELAY_IDS_LEN);
+	}
+}
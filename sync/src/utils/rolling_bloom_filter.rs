@@ -0,0 +1,143 @@
+use std::f64::consts::LN_2;
+use bit_vec::BitVec;
+use murmur3::murmur3_32;
+use rand::{thread_rng, Rng};
+
+/// Constant optimized to create large differences in the seed for different values of `hash_functions_num`.
+const SEED_OFFSET: u32 = 0xFBA4C795;
+/// Maximal number of hash functions.
+const MAX_HASH_FUNCTIONS_NUM: u32 = 50;
+
+/// Probabilistic filter, remembering (approximately) last `max_entries` inserted items.
+///
+/// Filter consists of two generations. When current generation is full, previous
+/// generation is dropped and current generation becomes previous. So every item is
+/// remembered for at least `max_entries / 2` insertions and at most `max_entries` insertions.
+#[derive(Debug)]
+pub struct RollingBloomFilter {
+	/// Current generation.
+	current: BitVec,
+	/// Previous generation.
+	previous: BitVec,
+	/// Number of items, inserted to the current generation.
+	current_len: usize,
+	/// Maximal number of items in single generation.
+	generation_len: usize,
+	/// Number of hash functions to use.
+	hash_functions_num: u32,
+	/// Value to add to Murmur3 hash seed when calculating hash.
+	tweak: u32,
+}
+
+impl RollingBloomFilter {
+	/// Create filter that remembers at least `max_entries / 2` last items with given false-positive rate.
+	pub fn new(max_entries: usize, fp_rate: f64) -> Self {
+		let generation_len = ::std::cmp::max(max_entries / 2, 1);
+		let filter_len = (-1f64 * generation_len as f64 * fp_rate.ln() / (LN_2 * LN_2)).ceil() as usize;
+		let filter_len = ::std::cmp::max(filter_len, 8);
+		let hash_functions_num = (filter_len as f64 / generation_len as f64 * LN_2).round() as u32;
+		let hash_functions_num = ::std::cmp::min(::std::cmp::max(hash_functions_num, 1), MAX_HASH_FUNCTIONS_NUM);
+
+		RollingBloomFilter {
+			current: BitVec::from_elem(filter_len, false),
+			previous: BitVec::from_elem(filter_len, false),
+			current_len: 0,
+			generation_len: generation_len,
+			hash_functions_num: hash_functions_num,
+			tweak: thread_rng().gen(),
+		}
+	}
+
+	/// Add bytes to the filter.
+	pub fn insert(&mut self, data: &[u8]) {
+		if self.current_len == self.generation_len {
+			::std::mem::swap(&mut self.current, &mut self.previous);
+			self.current.clear();
+			self.current_len = 0;
+		}
+
+		for hash_function_idx in 0..self.hash_functions_num {
+			let index = self.bit_index(hash_function_idx, data);
+			self.current.set(index, true);
+		}
+		self.current_len += 1;
+	}
+
+	/// True if filter (probably) contains given bytes.
+	pub fn contains(&self, data: &[u8]) -> bool {
+		Self::generation_contains(&self.current, self.hash_functions_num, self.tweak, data)
+			|| Self::generation_contains(&self.previous, self.hash_functions_num, self.tweak, data)
+	}
+
+	/// Forget all items.
+	pub fn reset(&mut self) {
+		self.current.clear();
+		self.previous.clear();
+		self.current_len = 0;
+	}
+
+	fn bit_index(&self, hash_function_idx: u32, data: &[u8]) -> usize {
+		bit_index(self.current.len(), hash_function_idx, self.tweak, data)
+	}
+
+	fn generation_contains(generation: &BitVec, hash_functions_num: u32, tweak: u32, data: &[u8]) -> bool {
+		(0..hash_functions_num).all(|hash_function_idx| {
+			let index = bit_index(generation.len(), hash_function_idx, tweak, data);
+			generation.get(index).expect("index is result of mod operation by filter len; qed")
+		})
+	}
+}
+
+fn bit_index(filter_len: usize, hash_function_idx: u32, tweak: u32, data: &[u8]) -> usize {
+	let murmur_seed = hash_function_idx.overflowing_mul(SEED_OFFSET).0.overflowing_add(tweak).0;
+	murmur3_32(&mut data.as_ref(), murmur_seed) as usize % filter_len
+}
+
+#[cfg(test)]
+mod tests {
+	use primitives::hash::H256;
+	use super::RollingBloomFilter;
+
+	fn hash(i: u64) -> H256 {
+		let mut hash = H256::default();
+		for byte in 0..8 {
+			hash[byte] = (i >> (byte * 8)) as u8;
+		}
+		hash
+	}
+
+	#[test]
+	fn rolling_bloom_filter_contains_inserted_items() {
+		let mut filter = RollingBloomFilter::new(100, 0.000001);
+		assert!(!filter.contains(&*hash(1)));
+		filter.insert(&*hash(1));
+		assert!(filter.contains(&*hash(1)));
+		assert!(!filter.contains(&*hash(2)));
+	}
+
+	#[test]
+	fn rolling_bloom_filter_ages_out_items() {
+		let mut filter = RollingBloomFilter::new(100, 0.000001);
+		filter.insert(&*hash(0));
+
+		// item is remembered while its generation is alive
+		for i in 1..100 {
+			filter.insert(&*hash(i));
+		}
+		assert!(filter.contains(&*hash(0)));
+		assert!(filter.contains(&*hash(99)));
+
+		// item is forgotten when its generation is dropped
+		filter.insert(&*hash(100));
+		assert!(!filter.contains(&*hash(0)));
+		assert!(filter.contains(&*hash(100)));
+	}
+
+	#[test]
+	fn rolling_bloom_filter_reset() {
+		let mut filter = RollingBloomFilter::new(100, 0.000001);
+		filter.insert(&*hash(1));
+		filter.reset();
+		assert!(!filter.contains(&*hash(1)));
+	}
+}
@@ -1,6 +1,8 @@
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use parking_lot::Mutex;
 use p2p::InboundSyncConnectionState;
-use super::super::types::{StorageRef, BlockHeight};
+use super::super::types::{StorageRef, BlockHeight, PeerIndex};
+use super::NetworkHeightEstimator;
 
 // AtomicU32 is unstable => using AtomicUsize here
 
@@ -12,6 +14,8 @@ pub struct SynchronizationState {
 	is_synchronizing: AtomicBool,
 	/// Height of best block in the storage
 	best_storage_block_height: AtomicUsize,
+	/// Estimator of the network best block height
+	network_height: Mutex<NetworkHeightEstimator>,
 }
 
 impl SynchronizationState {
@@ -20,6 +24,7 @@ impl SynchronizationState {
 		SynchronizationState {
 			is_synchronizing: AtomicBool::new(false),
 			best_storage_block_height: AtomicUsize::new(best_storage_block_height as usize),
+			network_height: Mutex::new(NetworkHeightEstimator::default()),
 		}
 	}
 
@@ -38,6 +43,18 @@ impl SynchronizationState {
 	pub fn update_best_storage_block_height(&self, height: BlockHeight) {
 		self.best_storage_block_height.store(height as usize, Ordering::SeqCst);
 	}
+
+	pub fn estimated_network_height(&self) -> Option<BlockHeight> {
+		self.network_height.lock().estimate()
+	}
+
+	pub fn on_peer_connected(&self, peer_index: PeerIndex, start_height: BlockHeight) {
+		self.network_height.lock().insert(peer_index, start_height);
+	}
+
+	pub fn on_peer_disconnected(&self, peer_index: PeerIndex) {
+		self.network_height.lock().remove(peer_index);
+	}
 }
 
 impl InboundSyncConnectionState for SynchronizationState {
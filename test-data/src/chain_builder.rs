@@ -195,7 +195,10 @@ impl TransactionBuilder {
 	}
 
 	pub fn add_default_join_split(mut self) -> Self {
-		self.transaction.join_split = Some(Default::default());
+		self.transaction.join_split = Some(JoinSplit {
+			descriptions: vec![Default::default()],
+			..Default::default()
+		});
 		self
 	}
 }
@@ -1,14 +1,14 @@
-use keys::Address;
 use network::{ConsensusParams};
 use storage::{DuplexTransactionOutputProvider, TransactionOutputProvider, BlockHeaderProvider,
 	TreeStateProvider, SaplingTreeState};
-use script::{self, Builder};
+use script::{self, Script};
 use sigops::transaction_sigops;
 use deployments::BlockDeployments;
 use canon::CanonBlock;
 use error::Error;
 use timestamp::median_timestamp;
 use fee::checked_transaction_fee;
+use subsidy::{block_subsidy, founders_reward_output};
 
 /// Flexible verification of ordered block
 pub struct BlockAcceptor<'a> {
@@ -80,7 +80,7 @@ impl<'a> BlockFinality<'a> {
 			self.block.header.raw.time
 		};
 
-		if self.block.transactions.iter().all(|tx| tx.raw.is_final_in_block(self.height, time_cutoff)) {
+		if self.block.raw().transactions().iter().all(|tx| tx.raw.is_final_in_block(self.height, time_cutoff)) {
 			Ok(())
 		} else {
 			Err(Error::NonFinalBlock)
@@ -137,7 +137,7 @@ impl<'a> BlockSigops<'a> {
 
 	fn check(&self) -> Result<(), Error> {
 		let store = DuplexTransactionOutputProvider::new(self.store, &*self.block);
-		let sigops = self.block.transactions.iter()
+		let sigops = self.block.raw().transactions().iter()
 			.map(|tx| transaction_sigops(&tx.raw, &store, self.bip16_active))
 			.fold(0, |acc, tx_sigops| (acc + tx_sigops));
 
@@ -165,7 +165,7 @@ impl<'a> BlockCoinbaseMinerReward<'a> {
 		BlockCoinbaseMinerReward {
 			block: block,
 			store: store,
-			max_reward: consensus.block_reward(height),
+			max_reward: block_subsidy(height, consensus),
 		}
 	}
 
@@ -174,7 +174,7 @@ impl<'a> BlockCoinbaseMinerReward<'a> {
 
 		let mut fees: u64 = 0;
 
-		for (tx_idx, tx) in self.block.transactions.iter().enumerate().skip(1) {
+		for (tx_idx, tx) in self.block.raw().transactions().iter().enumerate().skip(1) {
 			let tx_fee = checked_transaction_fee(&store, tx_idx, &tx.raw)
 				.map_err(|tx_err| Error::Transaction(tx_idx, tx_err))?;
 
@@ -187,7 +187,7 @@ impl<'a> BlockCoinbaseMinerReward<'a> {
 			fees = sum;
 		}
 
-		let claim = self.block.transactions[0].raw.total_spends();
+		let claim = self.block.raw().transactions()[0].raw.total_spends();
 
 		let (max_reward, overflow) = fees.overflowing_add(self.max_reward);
 		if overflow {
@@ -226,7 +226,7 @@ impl<'a> BlockCoinbaseScript<'a> {
 			.push_i64(self.height.into())
 			.into_script();
 
-		let matches = self.block.transactions.first()
+		let matches = self.block.raw().transactions().first()
 			.and_then(|tx| tx.raw.inputs.first())
 			.map(|input| input.script_sig.starts_with(&prefix))
 			.unwrap_or(false);
@@ -241,26 +241,23 @@ impl<'a> BlockCoinbaseScript<'a> {
 
 pub struct BlockFounderReward<'a> {
 	block: CanonBlock<'a>,
-	founder_address: Option<Address>,
-	founder_reward: u64,
+	founder_reward: Option<(Script, u64)>,
 }
 
 impl<'a> BlockFounderReward<'a> {
 	fn new(block: CanonBlock<'a>, consensus_params: &ConsensusParams, height: u32) -> Self {
 		BlockFounderReward {
 			block: block,
-			founder_address: consensus_params.founder_address(height),
-			founder_reward: consensus_params.founder_reward(height),
+			founder_reward: founders_reward_output(height, consensus_params),
 		}
 	}
 
 	fn check(&self) -> Result<(), Error> {
-		if let Some(ref founder_address) = self.founder_address {
-			let script = Builder::build_p2sh(&founder_address.hash);
-			let has_founder_reward = self.block.transactions.first()
+		if let Some((ref script, founder_reward)) = self.founder_reward {
+			let has_founder_reward = self.block.raw().transactions().first()
 				.map(|tx| tx.raw.outputs.iter().any(|output|
-					**output.script_pubkey == *script &&
-					output.value == self.founder_reward))
+					**output.script_pubkey == **script &&
+					output.value == founder_reward))
 				.unwrap_or(false);
 
 			if !has_founder_reward {
@@ -304,7 +301,7 @@ impl<'a> BlockSaplingRoot<'a> {
 				.ok_or(Error::MissingSaplingCommitmentTree)?
 		};
 
-		for tx in &self.block.transactions {
+		for tx in self.block.raw().transactions() {
 			if let Some(ref sapling) = tx.raw.sapling {
 				for out in &sapling.outputs {
 					sapling_tree.append(out.note_commitment.into())
@@ -1,14 +1,18 @@
+use std::sync::Arc;
+use rayon::ThreadPool;
 use rayon::prelude::{IntoParallelRefIterator, IndexedParallelIterator, ParallelIterator};
+use script::TransactionSignatureChecker;
 use storage::{
 	DuplexTransactionOutputProvider, TransactionOutputProvider, TransactionMetaProvider,
 	BlockHeaderProvider, TreeStateProvider, NullifierTracker,
 };
 use network::ConsensusParams;
-use error::Error;
+use error::{Error, TransactionError};
 use canon::CanonBlock;
 use accept_block::BlockAcceptor;
 use accept_header::HeaderAcceptor;
-use accept_transaction::TransactionAcceptor;
+use accept_transaction::{TransactionAcceptor, TransactionStage};
+use sapling::SaplingProofsBatch;
 use deployments::BlockDeployments;
 use VerificationLevel;
 
@@ -16,6 +20,9 @@ pub struct ChainAcceptor<'a> {
 	pub block: BlockAcceptor<'a>,
 	pub header: HeaderAcceptor<'a>,
 	pub transactions: Vec<TransactionAcceptor<'a>>,
+	pub sapling_proofs: Arc<SaplingProofsBatch<'a>>,
+	/// Pool, used to evaluate transactions scripts. Global pool is used if `None`.
+	pub script_verification_pool: Option<&'a ThreadPool>,
 }
 
 impl<'a> ChainAcceptor<'a> {
@@ -34,6 +41,10 @@ impl<'a> ChainAcceptor<'a> {
 	) -> Self {
 		trace!(target: "verification", "Block verification {}", block.hash().to_reversed_str());
 		let output_store = DuplexTransactionOutputProvider::new(tx_out_provider, block.raw());
+		let sapling_proofs = Arc::new(SaplingProofsBatch::new(
+			consensus.sapling_spend_verifying_key,
+			consensus.sapling_output_verifying_key,
+		));
 
 		ChainAcceptor {
 			block: BlockAcceptor::new(
@@ -61,22 +72,81 @@ impl<'a> ChainAcceptor<'a> {
 						tx_index,
 						deployments,
 						tree_state_provider,
+						sapling_proofs.clone(),
 				))
 				.collect(),
+			sapling_proofs: sapling_proofs,
+			script_verification_pool: None,
 		}
 	}
 
+	/// Evaluates transactions scripts using given pool.
+	pub fn with_script_verification_pool(mut self, pool: Option<&'a ThreadPool>) -> Self {
+		self.script_verification_pool = pool;
+		self
+	}
+
 	pub fn check(&self) -> Result<(), Error> {
 		try!(self.block.check());
 		try!(self.header.check());
 		try!(self.check_transactions());
+		try!(self.check_sapling_proofs());
 		Ok(())
 	}
 
 	fn check_transactions(&self) -> Result<(), Error> {
+		// make sure that all referenced outputs exist before evaluating (expensive) scripts of any transaction
+		try!(self.check_transactions_stages(&[TransactionStage::Structure, TransactionStage::Inputs]));
+		try!(self.check_transactions_scripts());
+		self.check_transactions_stages(&[TransactionStage::Shielded])
+	}
+
+	fn check_transactions_stages(&self, stages: &[TransactionStage]) -> Result<(), Error> {
 		self.transactions.par_iter()
 			.enumerate()
-			.fold(|| Ok(()), |result, (index, tx)| result.and_then(|_| tx.check().map_err(|err| Error::Transaction(index, err))))
+			.fold(|| Ok(()), |result, (index, tx)| result.and_then(|_| stages.iter()
+				.fold(Ok(()), |result, stage| result.and_then(|_| tx.check_stage(*stage)))
+				.map_err(|err| Error::Transaction(index, err))))
 			.reduce(|| Ok(()), |acc, check| acc.and(check))
 	}
+
+	/// Evaluates scripts of all transactions inputs. Every input is verified by the separate job, so
+	/// inputs of the same transaction are also verified in parallel. On failure, the error of the
+	/// first invalid input (in block order) is returned.
+	fn check_transactions_scripts(&self) -> Result<(), Error> {
+		let jobs: Vec<(usize, usize)> = self.transactions.iter()
+			.enumerate()
+			.flat_map(|(tx_index, tx)| (0..tx.eval.inputs_to_verify()).map(move |input_index| (tx_index, input_index)))
+			.collect();
+
+		let verify = || {
+			// signature checker is created once per transaction and then cloned (cheaply) by every input job
+			let checkers: Vec<Option<TransactionSignatureChecker>> = self.transactions.par_iter()
+				.map(|tx| match tx.eval.inputs_to_verify() {
+					0 => None,
+					_ => Some(tx.eval.signature_checker()),
+				})
+				.collect();
+
+			jobs.par_iter()
+				.map(|&(tx_index, input_index)| {
+					let mut checker = checkers[tx_index].clone()
+						.expect("checker is created for every transaction with inputs to verify; qed");
+					self.transactions[tx_index].eval.check_input_with(&mut checker, input_index)
+						.map_err(|err| Error::Transaction(tx_index, err))
+				})
+				.find_first(Result::is_err)
+		};
+		let first_error = match self.script_verification_pool {
+			Some(pool) => pool.install(verify),
+			None => verify(),
+		};
+
+		first_error.unwrap_or(Ok(()))
+	}
+
+	fn check_sapling_proofs(&self) -> Result<(), Error> {
+		self.sapling_proofs.verify()
+			.map_err(|index| Error::Transaction(index, TransactionError::InvalidSapling))
+	}
 }
@@ -1,12 +1,17 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
 use ser::Serializable;
 use crypto::Groth16VerifyingKey;
 use storage::{EpochRef, NullifierTracker, EpochTag, TransactionMetaProvider, TransactionOutputProvider,
-	DuplexTransactionOutputProvider, TreeStateProvider};
+	DuplexTransactionOutputProvider, TreeStateProvider, BlockHeaderProvider, BlockRef};
 use network::{ConsensusParams};
 use script::{Script, verify_script, VerificationFlags, TransactionSignatureChecker, TransactionInputSigner, SighashBase};
 use deployments::BlockDeployments;
-use sapling::accept_sapling;
+use sapling::{accept_sapling, accept_sapling_batched, SaplingProofsBatch};
 use sigops::transaction_sigops;
+use verify_transaction::MemoryPoolTransactionVerifier;
 use canon::CanonTransaction;
 use chain::{OVERWINTER_TX_VERSION, SAPLING_TX_VERSION, OVERWINTER_TX_VERSION_GROUP_ID, SAPLING_TX_VERSION_GROUP_ID};
 use constants::COINBASE_MATURITY;
@@ -15,6 +20,41 @@ use primitives::hash::H256;
 use {checked_transaction_fee, VerificationLevel};
 use tree_cache::TreeCache;
 
+/// Stage of the transaction verification.
+///
+/// Stages are executed in the order of `TRANSACTION_STAGES`. Every stage returns early on the first failure,
+/// so the whole pipeline reports the same error as if all checks were executed one-by-one.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransactionStage {
+	/// Rules, that do not depend on the chain state (see `MemoryPoolTransactionVerifier`).
+	ContextFree,
+	/// Transaction version, size and expiry rules.
+	Structure,
+	/// Rules, checking transparent inputs against the chain state.
+	Inputs,
+	/// Transparent scripts evaluation.
+	Scripts,
+	/// JoinSplit and Sapling rules.
+	Shielded,
+}
+
+/// All transaction verification stages, in order of execution.
+pub const TRANSACTION_STAGES: [TransactionStage; 5] = [
+	TransactionStage::ContextFree,
+	TransactionStage::Structure,
+	TransactionStage::Inputs,
+	TransactionStage::Scripts,
+	TransactionStage::Shielded,
+];
+
+/// Block transactions are pre-verified by `ChainVerifier` => there's no context-free stage.
+const BLOCK_TRANSACTION_STAGES: [TransactionStage; 4] = [
+	TransactionStage::Structure,
+	TransactionStage::Inputs,
+	TransactionStage::Scripts,
+	TransactionStage::Shielded,
+];
+
 pub struct TransactionAcceptor<'a> {
 	pub version: TransactionVersion<'a>,
 	pub size: TransactionSize<'a>,
@@ -22,6 +62,7 @@ pub struct TransactionAcceptor<'a> {
 	pub bip30: TransactionBip30<'a>,
 	pub missing_inputs: TransactionMissingInputs<'a>,
 	pub maturity: TransactionMaturity<'a>,
+	pub coinbase_spend: TransactionCoinbaseSpend<'a>,
 	pub double_spent: TransactionDoubleSpend<'a>,
 	pub eval: TransactionEval<'a>,
 	pub join_split: JoinSplitVerification<'a>,
@@ -44,6 +85,7 @@ impl<'a> TransactionAcceptor<'a> {
 		transaction_index: usize,
 		deployments: &'a BlockDeployments<'a>,
 		tree_state_provider: &'a TreeStateProvider,
+		sapling_proofs: Arc<SaplingProofsBatch<'a>>,
 	) -> Self {
 		trace!(target: "verification", "Tx verification {}", transaction.hash.to_reversed_str());
 		TransactionAcceptor {
@@ -53,32 +95,56 @@ impl<'a> TransactionAcceptor<'a> {
 			bip30: TransactionBip30::new_for_sync(transaction, meta_store),
 			missing_inputs: TransactionMissingInputs::new(transaction, output_store, transaction_index),
 			maturity: TransactionMaturity::new(transaction, meta_store, height),
+			coinbase_spend: TransactionCoinbaseSpend::new(transaction, meta_store, consensus),
 			double_spent: TransactionDoubleSpend::new(transaction, output_store),
 			eval: TransactionEval::new(transaction, output_store, consensus, verification_level, height, time, deployments),
 			join_split: JoinSplitVerification::new(consensus, transaction, nullifier_tracker, tree_state_provider),
-			sapling: SaplingVerification::new(
+			sapling: SaplingVerification::new_for_block(
 				nullifier_tracker,
-				consensus.sapling_spend_verifying_key,
-				consensus.sapling_output_verifying_key,
+				tree_state_provider,
+				sapling_proofs,
 				transaction,
+				transaction_index,
 			),
 		}
 	}
 
+	/// Verification stages, executed by this acceptor.
+	pub fn stages(&self) -> &'static [TransactionStage] {
+		&BLOCK_TRANSACTION_STAGES
+	}
+
 	pub fn check(&self) -> Result<(), TransactionError> {
-		self.version.check()?;
-		self.size.check()?;
-		self.expiry.check()?;
-		self.bip30.check()?;
-		self.missing_inputs.check()?;
-		self.maturity.check()?;
-		self.double_spent.check()?;
-
-		// to make sure we're using the sighash-cache, let's make all sighash-related
-		// calls from single checker && pass sighash to other checkers
-		let sighash = self.eval.check()?;
-		self.join_split.check(sighash)?;
-		self.sapling.check(sighash)?;
+		for stage in self.stages() {
+			self.check_stage(*stage)?;
+		}
+
+		Ok(())
+	}
+
+	/// Executes single verification stage.
+	pub fn check_stage(&self, stage: TransactionStage) -> Result<(), TransactionError> {
+		match stage {
+			TransactionStage::Structure => {
+				self.version.check()?;
+				self.size.check()?;
+				self.expiry.check()?;
+			},
+			TransactionStage::ContextFree => (),
+			TransactionStage::Inputs => {
+				self.bip30.check()?;
+				self.missing_inputs.check()?;
+				self.maturity.check()?;
+				self.coinbase_spend.check()?;
+				self.double_spent.check()?;
+			},
+			TransactionStage::Scripts => self.eval.check()?,
+			TransactionStage::Shielded => {
+				let sighash = self.eval.sighash();
+				self.join_split.check(sighash)?;
+				self.sapling.check(sighash)?;
+			},
+		}
 
 		Ok(())
 	}
@@ -88,11 +154,14 @@ pub struct MemoryPoolTransactionAcceptor<'a> {
 	pub version: TransactionVersion<'a>,
 	pub size: TransactionSize<'a>,
 	pub expiry: TransactionExpiry<'a>,
+	pub context_free: MemoryPoolTransactionVerifier<'a>,
 	pub missing_inputs: TransactionMissingInputs<'a>,
 	pub maturity: TransactionMaturity<'a>,
+	pub coinbase_spend: TransactionCoinbaseSpend<'a>,
 	pub overspent: TransactionOverspent<'a>,
 	pub sigops: TransactionSigops<'a>,
 	pub double_spent: TransactionDoubleSpend<'a>,
+	pub sapling_anchor_window: TransactionSaplingAnchorWindow<'a>,
 	pub eval: TransactionEval<'a>,
 	pub join_split: JoinSplitVerification<'a>,
 	pub sapling: SaplingVerification<'a>,
@@ -111,6 +180,8 @@ impl<'a> MemoryPoolTransactionAcceptor<'a> {
 		time: u32,
 		deployments: &'a BlockDeployments<'a>,
 		tree_state_provider: &'a TreeStateProvider,
+		header_provider: &'a BlockHeaderProvider,
+		sapling_anchor_window: Option<u32>,
 	) -> Self {
 		trace!(target: "verification", "Mempool-Tx verification {}", transaction.hash.to_reversed_str());
 		let transaction_index = 0;
@@ -119,15 +190,19 @@ impl<'a> MemoryPoolTransactionAcceptor<'a> {
 			version: TransactionVersion::new(transaction, consensus, height),
 			size: TransactionSize::new(transaction, consensus, height),
 			expiry: TransactionExpiry::new(transaction, consensus, height),
+			context_free: MemoryPoolTransactionVerifier::new(transaction.indexed(), consensus),
 			missing_inputs: TransactionMissingInputs::new(transaction, output_store, transaction_index),
 			maturity: TransactionMaturity::new(transaction, meta_store, height),
+			coinbase_spend: TransactionCoinbaseSpend::new(transaction, meta_store, consensus),
 			overspent: TransactionOverspent::new(transaction, output_store),
 			sigops: TransactionSigops::new(transaction, output_store, consensus, max_block_sigops, time),
 			double_spent: TransactionDoubleSpend::new(transaction, output_store),
+			sapling_anchor_window: TransactionSaplingAnchorWindow::new(transaction, header_provider, height, sapling_anchor_window),
 			eval: TransactionEval::new(transaction, output_store, consensus, VerificationLevel::FULL, height, time, deployments),
 			join_split: JoinSplitVerification::new(consensus, transaction, nullifier_tracker, tree_state_provider),
 			sapling: SaplingVerification::new(
 				nullifier_tracker,
+				tree_state_provider,
 				consensus.sapling_spend_verifying_key,
 				consensus.sapling_output_verifying_key,
 				transaction,
@@ -135,23 +210,75 @@ impl<'a> MemoryPoolTransactionAcceptor<'a> {
 		}
 	}
 
+	/// Verification stages, executed by this acceptor.
+	pub fn stages(&self) -> &'static [TransactionStage] {
+		&TRANSACTION_STAGES
+	}
+
 	pub fn check(&self) -> Result<(), TransactionError> {
-		// Bip30 is not checked because we don't need to allow tx pool acceptance of an unspent duplicate.
-		// Tx pool validation is not strictly a matter of consensus.
-		self.version.check()?;
-		self.size.check()?;
-		self.expiry.check()?;
-		self.missing_inputs.check()?;
-		self.maturity.check()?;
-		self.overspent.check()?;
-		self.sigops.check()?;
-		self.double_spent.check()?;
-
-		// to make sure we're using the sighash-cache, let's make all sighash-related
-		// calls from single checker && pass sighash to other checkers
-		let sighash = self.eval.check()?;
-		self.join_split.check(sighash)?;
-		self.sapling.check(sighash)?;
+		for stage in self.stages() {
+			self.check_stage(*stage)?;
+		}
+
+		Ok(())
+	}
+
+	/// Checks all stages of the transaction, aborting verification once it has taken more than `budget`.
+	/// Budget is checked after every stage and after evaluation of every input script. The
+	/// `TransactionError::VerificationTimeout` is transient - the same transaction could be accepted later.
+	pub fn check_within_budget(&self, budget: Option<Duration>) -> Result<(), TransactionError> {
+		let deadline = budget.map(|budget| Instant::now() + budget);
+		let check_deadline = || match deadline {
+			Some(deadline) if Instant::now() >= deadline => Err(TransactionError::VerificationTimeout),
+			_ => Ok(()),
+		};
+
+		for stage in self.stages() {
+			match *stage {
+				TransactionStage::Scripts => {
+					let mut checker = self.eval.signature_checker();
+					for index in 0..self.eval.inputs_to_verify() {
+						self.eval.check_input_with(&mut checker, index)?;
+						check_deadline()?;
+					}
+				},
+				stage => {
+					self.check_stage(stage)?;
+					check_deadline()?;
+				},
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Executes single verification stage.
+	pub fn check_stage(&self, stage: TransactionStage) -> Result<(), TransactionError> {
+		match stage {
+			TransactionStage::Structure => {
+				self.version.check()?;
+				self.size.check()?;
+				self.expiry.check()?;
+			},
+			TransactionStage::ContextFree => self.context_free.check()?,
+			TransactionStage::Inputs => {
+				// Bip30 is not checked because we don't need to allow tx pool acceptance of an unspent duplicate.
+				// Tx pool validation is not strictly a matter of consensus.
+				self.missing_inputs.check()?;
+				self.maturity.check()?;
+				self.coinbase_spend.check()?;
+				self.overspent.check()?;
+				self.sigops.check()?;
+				self.double_spent.check()?;
+				self.sapling_anchor_window.check()?;
+			},
+			TransactionStage::Scripts => self.eval.check()?,
+			TransactionStage::Shielded => {
+				let sighash = self.eval.sighash();
+				self.join_split.check(sighash)?;
+				self.sapling.check(sighash)?;
+			},
+		}
 
 		Ok(())
 	}
@@ -208,15 +335,15 @@ impl<'a> TransactionMissingInputs<'a> {
 	}
 
 	fn check(&self) -> Result<(), TransactionError> {
-		let missing_index = self.transaction.raw.inputs.iter()
-			.position(|input| {
+		let missing_input = self.transaction.raw.inputs.iter()
+			.find(|input| {
 				let is_not_null = !input.previous_output.is_null();
 				let is_missing = self.store.transaction_output(&input.previous_output, self.transaction_index).is_none();
 				is_not_null && is_missing
 			});
 
-		match missing_index {
-			Some(index) => Err(TransactionError::Input(index)),
+		match missing_input {
+			Some(input) => Err(TransactionError::MissingInputs(input.previous_output.clone())),
 			None => Ok(())
 		}
 	}
@@ -253,6 +380,41 @@ impl<'a> TransactionMaturity<'a> {
 	}
 }
 
+/// Transparent coinbase outputs could only be spent to the shielded pools (unless disabled for the network, e.g. on regtest).
+pub struct TransactionCoinbaseSpend<'a> {
+	transaction: CanonTransaction<'a>,
+	store: &'a TransactionMetaProvider,
+	coinbase_must_be_shielded: bool,
+}
+
+impl<'a> TransactionCoinbaseSpend<'a> {
+	fn new(transaction: CanonTransaction<'a>, store: &'a TransactionMetaProvider, consensus: &ConsensusParams) -> Self {
+		TransactionCoinbaseSpend {
+			transaction: transaction,
+			store: store,
+			coinbase_must_be_shielded: consensus.coinbase_must_be_shielded,
+		}
+	}
+
+	fn check(&self) -> Result<(), TransactionError> {
+		if !self.coinbase_must_be_shielded || self.transaction.raw.outputs.is_empty() {
+			return Ok(());
+		}
+
+		let spends_coinbase = self.transaction.raw.inputs.iter()
+			.any(|input| match self.store.transaction_meta(&input.previous_output.hash) {
+				Some(ref meta) => meta.is_coinbase(),
+				None => false,
+			});
+
+		if spends_coinbase {
+			Err(TransactionError::CoinbaseSpendHasTransparentOutputs)
+		} else {
+			Ok(())
+		}
+	}
+}
+
 pub struct TransactionOverspent<'a> {
 	transaction: CanonTransaction<'a>,
 	store: DuplexTransactionOutputProvider<'a>,
@@ -319,6 +481,8 @@ pub struct TransactionEval<'a> {
 	verify_sigpushonly: bool,
 	verify_cleanstack: bool,
 	consensus_branch_id: u32,
+	/// Signature checker, shared by all verification stages => sighash cache is computed once per transaction.
+	checker: Mutex<Option<TransactionSignatureChecker>>,
 }
 
 impl<'a> TransactionEval<'a> {
@@ -340,7 +504,7 @@ impl<'a> TransactionEval<'a> {
 		let verify_sigpushonly = false;
 		let verify_cleanstack = false;
 
-		let consensus_branch_id = params.consensus_branch_id(height);
+		let consensus_branch_id = params.branch_id(height);
 
 		TransactionEval {
 			transaction: transaction,
@@ -355,70 +519,100 @@ impl<'a> TransactionEval<'a> {
 			verify_sigpushonly: verify_sigpushonly,
 			verify_cleanstack: verify_cleanstack,
 			consensus_branch_id: consensus_branch_id,
+			checker: Mutex::new(None),
 		}
 	}
 
 	/// Returns no-input sighash for transactions that have non-empty JoinSplit
 	/// or non-empty Sapling.
-	fn check(&self) -> Result<H256, TransactionError> {
-		let signer: TransactionInputSigner = self.transaction.raw.clone().into();
-
-		let mut checker = TransactionSignatureChecker {
-			signer: signer,
-			input_index: 0,
-			input_amount: 0,
-			consensus_branch_id: self.consensus_branch_id,
-			cache: Default::default(),
-		};
-
-		// generate sighash that is not associated with a transparent input
+	fn sighash(&self) -> H256 {
 		let require_no_input_sighash = self.transaction.raw.join_split.is_some()
 			|| self.transaction.raw.sapling.is_some();
-		let no_input_sighash = match require_no_input_sighash {
-			true => checker.signer.signature_hash(
-				&mut checker.cache,
-				None,
-				0,
-				&From::from(vec![]),
-				SighashBase::All.into(),
-				checker.consensus_branch_id,
-			),
-			false => Default::default(),
-		};
+		if !require_no_input_sighash {
+			return Default::default();
+		}
+
+		let mut checker = self.checker.lock();
+		let checker = checker.get_or_insert_with(|| self.new_signature_checker());
+		checker.signer.signature_hash(
+			&mut checker.cache,
+			None,
+			0,
+			&From::from(vec![]),
+			SighashBase::All.into(),
+			checker.consensus_branch_id,
+		)
+	}
 
+	/// Returns number of transaction inputs, which scripts are evaluated by this check.
+	pub fn inputs_to_verify(&self) -> usize {
 		if self.verification_level.intersects(VerificationLevel::HEADER | VerificationLevel::NO_VERIFICATION) {
-			return Ok(no_input_sighash);
+			return 0;
 		}
 
 		if self.transaction.raw.is_coinbase() {
-			return Ok(no_input_sighash);
+			return 0;
 		}
 
-		for (index, input) in self.transaction.raw.inputs.iter().enumerate() {
-			let output = self.store.transaction_output(&input.previous_output, usize::max_value())
-				.ok_or_else(|| TransactionError::UnknownReference(input.previous_output.hash.clone()))?;
+		self.transaction.raw.inputs.len()
+	}
 
-			checker.input_index = index;
-			checker.input_amount = output.value;
+	fn check(&self) -> Result<(), TransactionError> {
+		let mut checker = self.signature_checker();
+		for index in 0..self.inputs_to_verify() {
+			self.check_input_with(&mut checker, index)?;
+		}
 
-			let input: Script = input.script_sig.clone().into();
-			let output: Script = output.script_pubkey.into();
+		Ok(())
+	}
+
+	/// Returns signature checker for transaction inputs. All reusable portions of the signature
+	/// hash are precomputed and the transaction is shared, so the checker is cheap to clone when
+	/// inputs are verified in parallel.
+	pub fn signature_checker(&self) -> TransactionSignatureChecker {
+		self.checker.lock()
+			.get_or_insert_with(|| self.new_signature_checker())
+			.clone()
+	}
 
-			let flags = VerificationFlags::default()
-				.verify_p2sh(self.verify_p2sh)
-				.verify_strictenc(self.verify_strictenc)
-				.verify_locktime(self.verify_locktime)
-				.verify_checksequence(self.verify_checksequence)
-				.verify_dersig(self.verify_dersig)
-				.verify_nulldummy(self.verify_nulldummy)
-				.verify_sigpushonly(self.verify_sigpushonly)
-				.verify_cleanstack(self.verify_cleanstack);
+	fn new_signature_checker(&self) -> TransactionSignatureChecker {
+		let signer: TransactionInputSigner = self.transaction.raw.clone().into();
+		let cache = signer.sighash_cache();
 
-			verify_script(&input, &output, &flags, &mut checker)
-				.map_err(|e| TransactionError::Signature(index, e))?;
+		TransactionSignatureChecker {
+			signer: Arc::new(signer),
+			input_index: 0,
+			input_amount: 0,
+			consensus_branch_id: self.consensus_branch_id,
+			cache: cache,
 		}
+	}
+
+	/// Evaluates script of single transaction input. Inputs are verified independently, so this
+	/// could be called for different inputs in parallel (with clones of the same checker).
+	pub fn check_input_with(&self, checker: &mut TransactionSignatureChecker, index: usize) -> Result<(), TransactionError> {
+		let input = &self.transaction.raw.inputs[index];
+		let output = self.store.transaction_output(&input.previous_output, usize::max_value())
+			.ok_or_else(|| TransactionError::UnknownReference(input.previous_output.hash.clone()))?;
+
+		checker.input_index = index;
+		checker.input_amount = output.value;
 
-		Ok(no_input_sighash)
+		let input: Script = input.script_sig.clone().into();
+		let output: Script = output.script_pubkey.into();
+
+		let flags = VerificationFlags::default()
+			.verify_p2sh(self.verify_p2sh)
+			.verify_strictenc(self.verify_strictenc)
+			.verify_locktime(self.verify_locktime)
+			.verify_checksequence(self.verify_checksequence)
+			.verify_dersig(self.verify_dersig)
+			.verify_nulldummy(self.verify_nulldummy)
+			.verify_sigpushonly(self.verify_sigpushonly)
+			.verify_cleanstack(self.verify_cleanstack);
+
+		verify_script(&input, &output, &flags, checker)
+			.map_err(|e| TransactionError::Signature(index, e))
 	}
 }
 
@@ -452,6 +646,52 @@ impl<'a> TransactionDoubleSpend<'a> {
 	}
 }
 
+/// Relay policy (not a consensus rule): anchors of all sapling spends must be final sapling roots
+/// of one of `window` most recent blocks. Consensus accepts any known anchor.
+pub struct TransactionSaplingAnchorWindow<'a> {
+	transaction: CanonTransaction<'a>,
+	header_provider: &'a BlockHeaderProvider,
+	height: u32,
+	window: Option<u32>,
+}
+
+impl<'a> TransactionSaplingAnchorWindow<'a> {
+	fn new(transaction: CanonTransaction<'a>, header_provider: &'a BlockHeaderProvider, height: u32, window: Option<u32>) -> Self {
+		TransactionSaplingAnchorWindow {
+			transaction: transaction,
+			header_provider: header_provider,
+			height: height,
+			window: window,
+		}
+	}
+
+	fn check(&self) -> Result<(), TransactionError> {
+		let window = match self.window {
+			Some(window) => window,
+			None => return Ok(()),
+		};
+
+		let spends = match self.transaction.raw.sapling {
+			Some(ref sapling) if !sapling.spends.is_empty() => &sapling.spends,
+			_ => return Ok(()),
+		};
+
+		let recent_anchors: HashSet<H256> = (self.height.saturating_sub(window)..self.height)
+			.filter_map(|number| self.header_provider.block_header(BlockRef::Number(number)))
+			.map(|header| header.raw.final_sapling_root)
+			.collect();
+
+		for spend in spends {
+			let anchor = H256::from(spend.anchor);
+			if !recent_anchors.contains(&anchor) {
+				return Err(TransactionError::OldSaplingAnchor(anchor));
+			}
+		}
+
+		Ok(())
+	}
+}
+
 /// The encoded size of the transaction MUST be less than or equal to current max limit.
 pub struct TransactionSize<'a> {
 	transaction: CanonTransaction<'a>,
@@ -608,15 +848,11 @@ impl<'a> JoinSplitNullifiers<'a> {
 	}
 
 	fn check(&self) -> Result<(), TransactionError> {
-		if let Some(ref join_split) = self.transaction.raw.join_split {
-			for description in join_split.descriptions.iter() {
-				for nullifier in &description.nullifiers[..] {
-					let check = EpochRef::new(EpochTag::Sprout, H256::from(&nullifier[..]));
+		for nullifier in self.transaction.raw.sprout_nullifiers() {
+			let check = EpochRef::new(EpochTag::Sprout, H256::from(&nullifier[..]));
 
-					if self.tracker.contains_nullifier(check) {
-						return Err(TransactionError::JoinSplitDeclared(*check.hash()))
-					}
-				}
+			if self.tracker.contains_nullifier(check) {
+				return Err(TransactionError::JoinSplitDeclared(*check.hash()))
 			}
 		}
 
@@ -683,12 +919,45 @@ impl<'a> SaplingNullifiers<'a> {
 	}
 }
 
+/// Anchors of all sapling spends must be final sapling roots of canon blocks.
+pub struct SaplingAnchors<'a> {
+	tree_state_provider: &'a TreeStateProvider,
+	transaction: CanonTransaction<'a>,
+}
+
+impl<'a> SaplingAnchors<'a> {
+	fn new(tree_state_provider: &'a TreeStateProvider, transaction: CanonTransaction<'a>) -> Self {
+		SaplingAnchors { tree_state_provider: tree_state_provider, transaction: transaction }
+	}
+
+	fn check(&self) -> Result<(), TransactionError> {
+		if let Some(ref sapling) = self.transaction.raw.sapling {
+			for spend in &sapling.spends {
+				let anchor = H256::from(spend.anchor);
+				if !self.tree_state_provider.contains_anchor(EpochRef::new(EpochTag::Sapling, anchor)) {
+					return Err(TransactionError::UnknownSaplingAnchor(anchor));
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
 
 /// Checks that sapling signatures/proofs are valid.
-pub struct SaplingProof<'a> {
-	spend_vk: &'a Groth16VerifyingKey,
-	output_vk: &'a Groth16VerifyingKey,
-	transaction: CanonTransaction<'a>,
+pub enum SaplingProof<'a> {
+	/// Proofs are verified immediately.
+	Individual {
+		spend_vk: &'a Groth16VerifyingKey,
+		output_vk: &'a Groth16VerifyingKey,
+		transaction: CanonTransaction<'a>,
+	},
+	/// Proofs are queued for batch verification of the whole block.
+	Batched {
+		batch: Arc<SaplingProofsBatch<'a>>,
+		transaction: CanonTransaction<'a>,
+		transaction_index: usize,
+	},
 }
 
 impl<'a> SaplingProof<'a> {
@@ -697,17 +966,39 @@ impl<'a> SaplingProof<'a> {
 		output_vk: &'a Groth16VerifyingKey,
 		transaction: CanonTransaction<'a>,
 	) -> Self {
-		SaplingProof {
+		SaplingProof::Individual {
 			spend_vk,
 			output_vk,
 			transaction: transaction,
 		}
 	}
 
+	fn new_batched(
+		batch: Arc<SaplingProofsBatch<'a>>,
+		transaction: CanonTransaction<'a>,
+		transaction_index: usize,
+	) -> Self {
+		SaplingProof::Batched {
+			batch: batch,
+			transaction: transaction,
+			transaction_index: transaction_index,
+		}
+	}
+
 	fn check(&self, sighash: H256) -> Result<(), TransactionError> {
-		if let Some(sapling) = self.transaction.raw.sapling.as_ref() {
-			accept_sapling(self.spend_vk, self.output_vk, &sighash, sapling)
-				.map_err(|_| TransactionError::InvalidSapling)?;
+		match *self {
+			SaplingProof::Individual { spend_vk, output_vk, ref transaction } => {
+				if let Some(sapling) = transaction.raw.sapling.as_ref() {
+					accept_sapling(spend_vk, output_vk, &sighash, sapling)
+						.map_err(|_| TransactionError::InvalidSapling)?;
+				}
+			},
+			SaplingProof::Batched { ref batch, ref transaction, transaction_index } => {
+				if let Some(sapling) = transaction.raw.sapling.as_ref() {
+					accept_sapling_batched(batch, transaction_index, &sighash, sapling)
+						.map_err(|_| TransactionError::InvalidSapling)?;
+				}
+			},
 		}
 
 		Ok(())
@@ -716,6 +1007,7 @@ impl<'a> SaplingProof<'a> {
 
 /// Sapling verification
 pub struct SaplingVerification<'a> {
+	anchors: SaplingAnchors<'a>,
 	proof: SaplingProof<'a>,
 	nullifiers: SaplingNullifiers<'a>,
 }
@@ -723,18 +1015,37 @@ pub struct SaplingVerification<'a> {
 impl<'a> SaplingVerification<'a> {
 	pub fn new(
 		tracker: &'a NullifierTracker,
+		tree_state_provider: &'a TreeStateProvider,
 		spend_vk: &'a Groth16VerifyingKey,
 		output_vk: &'a Groth16VerifyingKey,
 		transaction: CanonTransaction<'a>
 	) -> Self
 	{
 		SaplingVerification {
+			anchors: SaplingAnchors::new(tree_state_provider, transaction),
 			proof: SaplingProof::new(spend_vk, output_vk, transaction),
 			nullifiers: SaplingNullifiers::new(tracker, transaction),
 		}
 	}
 
+	/// Creates verification, which queues proofs to the block-wide batch.
+	pub fn new_for_block(
+		tracker: &'a NullifierTracker,
+		tree_state_provider: &'a TreeStateProvider,
+		batch: Arc<SaplingProofsBatch<'a>>,
+		transaction: CanonTransaction<'a>,
+		transaction_index: usize,
+	) -> Self
+	{
+		SaplingVerification {
+			anchors: SaplingAnchors::new(tree_state_provider, transaction),
+			proof: SaplingProof::new_batched(batch, transaction, transaction_index),
+			nullifiers: SaplingNullifiers::new(tracker, transaction),
+		}
+	}
+
 	pub fn check(&self, sighash: H256) -> Result<(), TransactionError> {
+		self.anchors.check()?;
 		self.proof.check(sighash)?;
 		self.nullifiers.check()
 	}
@@ -745,8 +1056,10 @@ mod tests {
 	extern crate test_data;
 
 
-	use chain::{BTC_TX_VERSION, Transaction, Sapling};
+	use chain::{BTC_TX_VERSION, Transaction, IndexedTransaction, IndexedBlock, OutPoint, Sapling, SaplingSpendDescription};
 	use db::BlockChainDatabase;
+	use storage::NoopStore;
+	use deployments::Deployments;
 	use network::{Network, ConsensusParams};
 	use script::{Script, VerificationFlags, TransactionSignatureChecker, TransactionInputSigner, verify_script};
 	use super::*;
@@ -769,7 +1082,7 @@ mod tests {
 		let signer: TransactionInputSigner = spending_tx.into();
 
 		let mut checker = TransactionSignatureChecker {
-			signer: signer,
+			signer: signer.into(),
 			input_index: 0,
 			input_amount: 0,
 			consensus_branch_id: 0,
@@ -811,6 +1124,206 @@ mod tests {
 		);
 	}
 
+	#[test]
+	fn sapling_anchors_works() {
+		let genesis = test_data::genesis();
+		let b1 = test_data::block_builder()
+			.header().parent(genesis.hash()).final_sapling_root(H256::from(1)).build()
+			.transaction().coinbase().build()
+			.build();
+		let storage = BlockChainDatabase::init_test_chain(vec![genesis.into(), b1.into()]);
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		let deployments = Deployments::new();
+		let block_deployments = BlockDeployments::new(&deployments, 2, &storage, &consensus);
+		let noop = NoopStore;
+		let output_store = DuplexTransactionOutputProvider::new(&storage, &noop);
+
+		let spending = |anchor: u8| -> IndexedTransaction {
+			test_data::TransactionBuilder::with_sapling(Sapling {
+				spends: vec![SaplingSpendDescription { anchor: *H256::from(anchor), ..Default::default() }],
+				..Default::default()
+			}).into()
+		};
+		let known_anchor_tx = spending(1);
+		let unknown_anchor_tx = spending(2);
+		let unknown_anchor = Err(TransactionError::UnknownSaplingAnchor(H256::from(2)));
+
+		// final sapling root of canon block is a valid anchor
+		assert_eq!(SaplingAnchors::new(&storage, CanonTransaction::new(&known_anchor_tx)).check(), Ok(()));
+		assert_eq!(SaplingAnchors::new(&storage, CanonTransaction::new(&unknown_anchor_tx)).check(), unknown_anchor);
+
+		// both block and memory pool transactions are checked
+		let sapling_proofs = Arc::new(SaplingProofsBatch::new(
+			consensus.sapling_spend_verifying_key,
+			consensus.sapling_output_verifying_key,
+		));
+		let block_acceptor = TransactionAcceptor::new(
+			&storage, output_store, &storage, &consensus, CanonTransaction::new(&unknown_anchor_tx), VerificationLevel::FULL,
+			2, 0, 1, &block_deployments, &storage, sapling_proofs,
+		);
+		assert_eq!(block_acceptor.check_stage(TransactionStage::Shielded), unknown_anchor);
+		let memory_pool_acceptor = MemoryPoolTransactionAcceptor::new(
+			&storage, output_store, &storage, &consensus, CanonTransaction::new(&unknown_anchor_tx), 2, 0, &block_deployments,
+			&storage, &storage, None,
+		);
+		assert_eq!(memory_pool_acceptor.check_stage(TransactionStage::Shielded), unknown_anchor);
+
+		// anchor of decanonized block isn't valid anymore
+		storage.decanonize().unwrap();
+		assert_eq!(
+			SaplingAnchors::new(&storage, CanonTransaction::new(&known_anchor_tx)).check(),
+			Err(TransactionError::UnknownSaplingAnchor(H256::from(1)))
+		);
+	}
+
+	#[test]
+	fn transaction_coinbase_spend_works() {
+		let coinbase_tx: Transaction = test_data::TransactionBuilder::coinbase().add_output(10).into();
+		let block = test_data::block_builder()
+			.header().parent(test_data::genesis().hash()).build()
+			.with_transaction(coinbase_tx.clone())
+			.build();
+		let storage = BlockChainDatabase::init_test_chain(vec![test_data::genesis().into(), block.into()]);
+
+		let mainnet = ConsensusParams::new(Network::Mainnet);
+		let regtest = ConsensusParams::new(Network::Regtest);
+
+		let transparent_spend: Transaction = test_data::TransactionBuilder::with_input(&coinbase_tx, 0)
+			.add_output(5)
+			.into();
+		let shielded_spend: Transaction = test_data::TransactionBuilder::with_input(&coinbase_tx, 0)
+			.set_sapling(Sapling {
+				outputs: vec![Default::default()],
+				..Default::default()
+			})
+			.into();
+		let transparent_spend = transparent_spend.into();
+		let shielded_spend = shielded_spend.into();
+
+		// on mainnet, coinbase couldn't be spent to transparent outputs
+		assert_eq!(TransactionCoinbaseSpend::new(
+			CanonTransaction::new(&transparent_spend), &storage, &mainnet
+		).check(), Err(TransactionError::CoinbaseSpendHasTransparentOutputs));
+
+		// on regtest the rule is disabled, so coinbase could be spent to transparent outputs
+		assert_eq!(TransactionCoinbaseSpend::new(
+			CanonTransaction::new(&transparent_spend), &storage, &regtest
+		).check(), Ok(()));
+
+		// coinbase could always be spent to shielded outputs
+		assert_eq!(TransactionCoinbaseSpend::new(
+			CanonTransaction::new(&shielded_spend), &storage, &mainnet
+		).check(), Ok(()));
+		assert_eq!(TransactionCoinbaseSpend::new(
+			CanonTransaction::new(&shielded_spend), &storage, &regtest
+		).check(), Ok(()));
+	}
+
+	#[test]
+	fn transaction_missing_inputs_works() {
+		let storage = BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]);
+		let tx0: Transaction = test_data::TransactionBuilder::coinbase().add_output(10).into();
+		let tx1: Transaction = test_data::TransactionBuilder::with_input(&tx0, 0).add_output(5).into();
+		let tx2: Transaction = test_data::TransactionBuilder::with_default_input(0).add_output(5).into();
+		let tx3: Transaction = test_data::TransactionBuilder::with_input(&tx1, 0).add_output(1).into();
+		let block: IndexedBlock = test_data::block_builder()
+			.with_transaction(tx0.clone())
+			.with_transaction(tx3.clone())
+			.with_transaction(tx1.clone())
+			.with_transaction(tx2.clone())
+			.merkled_header().build()
+			.build()
+			.into();
+		let output_store = DuplexTransactionOutputProvider::new(&storage, &block);
+
+		// output of earlier transaction of the same block could be spent
+		let tx1 = tx1.into();
+		assert_eq!(TransactionMissingInputs::new(CanonTransaction::new(&tx1), output_store, 2).check(), Ok(()));
+
+		// output of later transaction of the same block couldn't be spent
+		let tx3 = tx3.into();
+		assert_eq!(
+			TransactionMissingInputs::new(CanonTransaction::new(&tx3), output_store, 1).check(),
+			Err(TransactionError::MissingInputs(OutPoint { hash: tx1.hash.clone(), index: 0 }))
+		);
+
+		// unknown output couldn't be spent
+		let tx2 = tx2.into();
+		assert_eq!(
+			TransactionMissingInputs::new(CanonTransaction::new(&tx2), output_store, 3).check(),
+			Err(TransactionError::MissingInputs(OutPoint { hash: Transaction::default().hash(), index: 0 }))
+		);
+	}
+
+	#[test]
+	fn transaction_stages_work() {
+		let storage = BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]);
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		let deployments = Deployments::new();
+		let block_deployments = BlockDeployments::new(&deployments, 1, &storage, &consensus);
+		let noop = NoopStore;
+		let output_store = DuplexTransactionOutputProvider::new(&storage, &noop);
+
+		// transaction has no inputs (context-free rule) && is overwintered before overwinter activation (structure rule)
+		let tx: IndexedTransaction = test_data::TransactionBuilder::overwintered()
+			.set_version(OVERWINTER_TX_VERSION)
+			.set_version_group_id(OVERWINTER_TX_VERSION_GROUP_ID)
+			.add_output(10)
+			.into();
+		let acceptor = MemoryPoolTransactionAcceptor::new(
+			&storage, output_store, &storage, &consensus, CanonTransaction::new(&tx), 1, 0, &block_deployments, &storage,
+			&storage, Some(10),
+		);
+		assert_eq!(acceptor.check_stage(TransactionStage::ContextFree), Err(TransactionError::EmptyInputs));
+		assert_eq!(acceptor.check_stage(TransactionStage::Structure), Err(TransactionError::InvalidOverwintered));
+		// context-free rules are checked first
+		assert_eq!(acceptor.check(), Err(TransactionError::EmptyInputs));
+
+		// transaction spends unknown output
+		let tx: IndexedTransaction = test_data::TransactionBuilder::with_default_input(0)
+			.set_version(BTC_TX_VERSION)
+			.add_output(10)
+			.into();
+		let acceptor = MemoryPoolTransactionAcceptor::new(
+			&storage, output_store, &storage, &consensus, CanonTransaction::new(&tx), 1, 0, &block_deployments, &storage,
+			&storage, Some(10),
+		);
+		assert_eq!(acceptor.check_stage(TransactionStage::ContextFree), Ok(()));
+		assert_eq!(acceptor.check_stage(TransactionStage::Structure), Ok(()));
+		assert_eq!(
+			acceptor.check_stage(TransactionStage::Scripts),
+			Err(TransactionError::UnknownReference(Transaction::default().hash()))
+		);
+		// missing inputs are detected before scripts are evaluated
+		assert_eq!(acceptor.check(), Err(TransactionError::MissingInputs(tx.raw.inputs[0].previous_output.clone())));
+	}
+
+	#[test]
+	fn memory_pool_transaction_time_budget() {
+		let storage = BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]);
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		let deployments = Deployments::new();
+		let block_deployments = BlockDeployments::new(&deployments, 1, &storage, &consensus);
+		let noop = NoopStore;
+		let output_store = DuplexTransactionOutputProvider::new(&storage, &noop);
+
+		// transaction spends unknown output
+		let tx: IndexedTransaction = test_data::TransactionBuilder::with_default_input(0)
+			.set_version(BTC_TX_VERSION)
+			.add_output(10)
+			.into();
+		let acceptor = MemoryPoolTransactionAcceptor::new(
+			&storage, output_store, &storage, &consensus, CanonTransaction::new(&tx), 1, 0, &block_deployments, &storage,
+			&storage, None,
+		);
+
+		let missing_inputs = Err(TransactionError::MissingInputs(tx.raw.inputs[0].previous_output.clone()));
+		assert_eq!(acceptor.check_within_budget(None), missing_inputs);
+		assert_eq!(acceptor.check_within_budget(Some(Duration::from_secs(60))), missing_inputs);
+		// budget is exhausted after the first stage
+		assert_eq!(acceptor.check_within_budget(Some(Duration::from_secs(0))), Err(TransactionError::VerificationTimeout));
+	}
+
 	#[test]
 	fn transaction_expiry_works() {
 		let consensus = ConsensusParams::new(Network::Mainnet);
@@ -914,4 +1427,43 @@ mod tests {
 			CanonTransaction::new(&tx), &consensus, consensus.sapling_height + 1
 		).check(), Ok(()));
 	}
+
+	#[test]
+	fn sapling_anchor_window() {
+		let genesis = test_data::genesis();
+		let b1 = test_data::block_builder()
+			.header().parent(genesis.hash()).final_sapling_root(H256::from(1)).build()
+			.transaction().coinbase().build()
+			.build();
+		let b2 = test_data::block_builder()
+			.header().parent(b1.hash()).final_sapling_root(H256::from(2)).build()
+			.transaction().coinbase().build()
+			.build();
+		let b3 = test_data::block_builder()
+			.header().parent(b2.hash()).final_sapling_root(H256::from(3)).build()
+			.transaction().coinbase().build()
+			.build();
+		let storage = BlockChainDatabase::init_test_chain(vec![genesis.into(), b1.into(), b2.into(), b3.into()]);
+
+		let spending = |anchor: u8| -> IndexedTransaction {
+			test_data::TransactionBuilder::with_sapling(Sapling {
+				spends: vec![SaplingSpendDescription { anchor: *H256::from(anchor), ..Default::default() }],
+				..Default::default()
+			}).into()
+		};
+		let recent_anchor_tx = spending(3);
+		let old_anchor_tx = spending(1);
+		let check = |tx: &IndexedTransaction, window: Option<u32>| TransactionSaplingAnchorWindow::new(
+			CanonTransaction::new(tx), &storage, 4, window,
+		).check();
+
+		// recent anchor is relayed
+		assert_eq!(check(&recent_anchor_tx, Some(2)), Ok(()));
+
+		// old anchor is known (i.e. valid from consensus point of view), but it isn't relayed
+		assert!(storage.sapling_tree_at(&H256::from(1)).is_some());
+		assert_eq!(check(&old_anchor_tx, Some(2)), Err(TransactionError::OldSaplingAnchor(H256::from(1))));
+		assert_eq!(check(&old_anchor_tx, Some(3)), Ok(()));
+		assert_eq!(check(&old_anchor_tx, None), Ok(()));
+	}
 }
@@ -28,7 +28,7 @@ impl<'a> CanonBlock<'a> {
 	}
 
 	pub fn transactions<'b>(&'b self) -> Vec<CanonTransaction<'a>> where 'a: 'b {
-		self.block.transactions.iter().map(CanonTransaction::new).collect()
+		self.block.transactions().iter().map(CanonTransaction::new).collect()
 	}
 }
 
@@ -73,6 +73,10 @@ impl<'a> CanonTransaction<'a> {
 		}
 	}
 
+	pub fn indexed(&self) -> &'a IndexedTransaction {
+		self.transaction
+	}
+
 	pub fn join_split(&self) -> Option<&'a chain::JoinSplit> {
 		self.transaction.raw.join_split.as_ref()
 	}
@@ -1,5 +1,8 @@
 //! Bitcoin chain verifier
 
+use std::sync::Arc;
+use std::time::Duration;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use chain::{IndexedBlock, IndexedBlockHeader, IndexedTransaction};
 use storage::{SharedStore, TransactionOutputProvider, BlockHeaderProvider, BlockOrigin,
 	DuplexTransactionOutputProvider, NoopStore, CachedTransactionOutputProvider};
@@ -8,16 +11,37 @@ use error::{Error, TransactionError};
 use canon::{CanonBlock, CanonTransaction};
 use verify_chain::ChainVerifier;
 use verify_header::HeaderVerifier;
-use verify_transaction::MemoryPoolTransactionVerifier;
 use accept_chain::ChainAcceptor;
 use accept_transaction::MemoryPoolTransactionAcceptor;
 use deployments::{Deployments, BlockDeployments};
 use {Verify, VerificationLevel};
 
+/// Dedicated pool of workers, evaluating scripts of block transactions. Single pool is
+/// supposed to be shared by all verifiers of the node.
+#[derive(Debug)]
+pub struct ScriptVerificationPool {
+	pool: ThreadPool,
+}
+
+impl ScriptVerificationPool {
+	pub fn new(threads: usize) -> Self {
+		ScriptVerificationPool {
+			pool: ThreadPoolBuilder::new()
+				.num_threads(threads)
+				.thread_name(|index| format!("Script verification {}", index))
+				.build()
+				.expect("Failed to create script verification pool"),
+		}
+	}
+}
+
 pub struct BackwardsCompatibleChainVerifier {
 	store: SharedStore,
 	consensus: ConsensusParams,
 	deployments: Deployments,
+	transaction_time_budget: Option<Duration>,
+	sapling_anchor_window: Option<u32>,
+	script_verification_pool: Option<Arc<ScriptVerificationPool>>,
 }
 
 impl BackwardsCompatibleChainVerifier {
@@ -26,14 +50,42 @@ impl BackwardsCompatibleChainVerifier {
 			store: store,
 			consensus: consensus,
 			deployments: Deployments::new(),
+			transaction_time_budget: None,
+			sapling_anchor_window: None,
+			script_verification_pool: None,
 		}
 	}
 
+	/// Limits time, spent on verification of single memory pool transaction. This is a relay
+	/// policy - block transactions are always verified completely.
+	pub fn with_transaction_time_budget(mut self, budget: Option<Duration>) -> Self {
+		self.transaction_time_budget = budget;
+		self
+	}
+
+	/// Only relay memory pool transactions, which are spending sapling anchors of `window` most recent blocks.
+	pub fn with_sapling_anchor_window(mut self, window: Option<u32>) -> Self {
+		self.sapling_anchor_window = window;
+		self
+	}
+
+	/// Evaluates scripts of block transactions using given (shared) pool of workers.
+	/// If `None`, the global pool is used.
+	pub fn with_script_verification_pool(mut self, pool: Option<Arc<ScriptVerificationPool>>) -> Self {
+		self.script_verification_pool = pool;
+		self
+	}
+
 	fn verify_block(&self, verification_level: VerificationLevel, block: &IndexedBlock) -> Result<(), Error> {
 		if verification_level.intersects(VerificationLevel::NO_VERIFICATION) {
 			return Ok(());
 		}
 
+		// genesis block has no parent => it can't pass neither parent-related, nor difficulty checks
+		if block.header.raw.previous_header_hash.is_zero() {
+			return self.verify_genesis_block(block);
+		}
+
 		let current_time = ::time::get_time().sec as u32;
 		// first run pre-verification
 		let chain_verifier = ChainVerifier::new(block, &self.consensus, current_time, verification_level);
@@ -74,7 +126,7 @@ impl BackwardsCompatibleChainVerifier {
 					block_number,
 					block.header.raw.time,
 					&deployments,
-				);
+				).with_script_verification_pool(self.script_verification_pool.as_ref().map(|pool| &pool.pool));
 				chain_acceptor.check()?;
 			},
 			BlockOrigin::SideChain(origin) => {
@@ -98,7 +150,7 @@ impl BackwardsCompatibleChainVerifier {
 					block_number,
 					block.header.raw.time,
 					&deployments,
-				);
+				).with_script_verification_pool(self.script_verification_pool.as_ref().map(|pool| &pool.pool));
 				chain_acceptor.check()?;
 			},
 			BlockOrigin::SideChainBecomingCanonChain(origin) => {
@@ -122,7 +174,7 @@ impl BackwardsCompatibleChainVerifier {
 					block_number,
 					block.header.raw.time,
 					&deployments,
-				);
+				).with_script_verification_pool(self.script_verification_pool.as_ref().map(|pool| &pool.pool));
 				chain_acceptor.check()?;
 			},
 		};
@@ -131,6 +183,15 @@ impl BackwardsCompatibleChainVerifier {
 		Ok(())
 	}
 
+	/// The only block with null previous header hash is the configured genesis block.
+	fn verify_genesis_block(&self, block: &IndexedBlock) -> Result<(), Error> {
+		if *block.hash() != *self.consensus.network.genesis_block().hash() {
+			return Err(Error::InvalidGenesis);
+		}
+
+		Ok(())
+	}
+
 	pub fn verify_block_header(
 		&self,
 		header: &IndexedBlockHeader,
@@ -148,13 +209,9 @@ impl BackwardsCompatibleChainVerifier {
 		time: u32,
 		transaction: &IndexedTransaction,
 	) -> Result<(), TransactionError> where T: TransactionOutputProvider {
-		// let's do preverification first
 		let deployments = BlockDeployments::new(&self.deployments, height, block_header_provider, &self.consensus);
-		let tx_verifier = MemoryPoolTransactionVerifier::new(&transaction, &self.consensus);
-		try!(tx_verifier.check());
-
 		let canon_tx = CanonTransaction::new(&transaction);
-		// now let's do full verification
+		// acceptor does both preverification && full verification
 		let noop = NoopStore;
 		let output_store = DuplexTransactionOutputProvider::new(prevout_provider, &noop);
 		let tx_acceptor = MemoryPoolTransactionAcceptor::new(
@@ -167,8 +224,10 @@ impl BackwardsCompatibleChainVerifier {
 			time,
 			&deployments,
 			self.store.as_tree_state_provider(),
+			block_header_provider,
+			self.sapling_anchor_window,
 		);
-		tx_acceptor.check()
+		tx_acceptor.check_within_budget(self.transaction_time_budget)
 	}
 }
 
@@ -178,7 +237,7 @@ impl Verify for BackwardsCompatibleChainVerifier {
 		trace!(
 			target: "verification", "Block {} (transactions: {}) verification finished. Result {:?}",
 			block.hash().to_reversed_str(),
-			block.transactions.len(),
+			block.transactions().len(),
 			result,
 		);
 		result
@@ -195,7 +254,7 @@ mod tests {
 	use db::BlockChainDatabase;
 	use network::{Network, ConsensusParams};
 	use script;
-	use super::BackwardsCompatibleChainVerifier as ChainVerifier;
+	use super::{BackwardsCompatibleChainVerifier as ChainVerifier, ScriptVerificationPool};
 	use {Verify, Error, TransactionError, VerificationLevel};
 
 	#[test]
@@ -206,6 +265,28 @@ mod tests {
 		assert_eq!(Err(Error::Database(DBError::UnknownParent)), verifier.verify(VerificationLevel::FULL, &b2));
 	}
 
+	#[test]
+	fn verify_genesis_block() {
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let verifier = ChainVerifier::new(storage, ConsensusParams::new(Network::Mainnet));
+		assert_eq!(verifier.verify(VerificationLevel::FULL, &test_data::genesis().into()), Ok(()));
+	}
+
+	#[test]
+	fn verify_fake_genesis_block() {
+		let fake_genesis = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(50).build()
+				.build()
+			.merkled_header().build()
+			.build();
+
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
+		let verifier = ChainVerifier::new(storage, ConsensusParams::new(Network::Mainnet));
+		assert_eq!(verifier.verify(VerificationLevel::FULL, &fake_genesis.into()), Err(Error::InvalidGenesis));
+	}
+
 	#[test]
 	fn verify_smoky() {
 		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![test_data::genesis().into()]));
@@ -298,6 +379,62 @@ mod tests {
 		assert_eq!(verifier.verify(VerificationLevel::FULL, &block.into()), Ok(()));
 	}
 
+	#[test]
+	fn invalid_script_is_reported_regardless_of_workers_count() {
+		let consensus = ConsensusParams::new(Network::Unitest);
+
+		let genesis = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.output().value(1).build()
+				.build()
+			.transaction()
+				.output().value(10).build()
+				.output().value(10).build()
+				.output().value(10).build()
+				.output().value(10).build()
+				.output().value(10).build()
+				.build()
+			.merkled_header().build()
+			.build();
+
+		let storage = Arc::new(BlockChainDatabase::init_test_chain(vec![genesis.clone().into()]));
+		let reference_tx = genesis.transactions()[1].hash();
+
+		// second input of the second transaction and the only input of the third transaction
+		// are using OP_RETURN in signature script
+		let block: IndexedBlock = test_data::block_builder()
+			.transaction()
+				.coinbase()
+				.founder_reward(&consensus, 1)
+				.output().value(2).build()
+				.build()
+			.transaction()
+				.input().hash(reference_tx.clone()).index(0).build()
+				.input().hash(reference_tx.clone()).index(1).build()
+				.output().value(20).build()
+				.build()
+			.transaction()
+				.input().hash(reference_tx.clone()).index(2).build()
+				.input().hash(reference_tx.clone()).index(3).signature("6a").build()
+				.output().value(20).build()
+				.build()
+			.transaction()
+				.input().hash(reference_tx).index(4).signature("6a").build()
+				.output().value(10).build()
+				.build()
+			.merkled_header().parent(genesis.hash()).build()
+			.build()
+			.into();
+
+		let expected = Err(Error::Transaction(2, TransactionError::Signature(1, script::Error::ReturnOpcode)));
+		for threads in &[None, Some(1), Some(2), Some(4)] {
+			let verifier = ChainVerifier::new(storage.clone(), consensus.clone())
+				.with_script_verification_pool(threads.map(|threads| Arc::new(ScriptVerificationPool::new(threads))));
+			assert_eq!(verifier.verify(VerificationLevel::FULL, &block), expected);
+		}
+	}
+
 	#[test]
 	fn transaction_references_same_block_happy() {
 		let consensus = ConsensusParams::new(Network::Unitest);
@@ -1,18 +1,85 @@
 use chain::BlockHeader;
 use crypto::Blake2b;
 
+/// Equihash verification of the block header.
+pub trait BlockHeaderEquihash {
+	/// Verify header' equihash solution against its nonce, using Equihash(n, k) algorithm.
+	/// Returns false if parameters are not supported.
+	fn verify_equihash(&self, n: u32, k: u32) -> bool;
+}
+
+impl BlockHeaderEquihash for BlockHeader {
+	fn verify_equihash(&self, n: u32, k: u32) -> bool {
+		verify_equihash((n, k), &self.equihash_input(), self.equihash_solution())
+	}
+}
+
 /// Verify equihash solution of the block header.
 pub fn verify_block_equihash_solution(params: (u32, u32), header: &BlockHeader) -> bool {
-	debug_assert_eq!(
-		params,
-		(OnChainEquihash::N, OnChainEquihash::K),
+	debug_assert!(
+		params == (OnChainEquihash::N, OnChainEquihash::K) || params == (RegtestEquihash::N, RegtestEquihash::K),
 		"Wrong equihash parameters specified in consensus",
 	);
 
-	verify_equihash_solution::<OnChainEquihash>(
-		&header.equihash_input(),
-		header.solution.as_ref(),
-	)
+	verify_equihash(params, &header.equihash_input(), header.equihash_solution())
+}
+
+/// Verify equihash solution of given input, using Equihash(n, k) algorithm.
+/// Only on-chain Equihash(200, 9) and regtest Equihash(48, 5) are supported.
+pub fn verify_equihash(params: (u32, u32), input: &[u8], solution: &[u8]) -> bool {
+	if params == (OnChainEquihash::N, OnChainEquihash::K) {
+		verify_equihash_solution::<OnChainEquihash>(input, solution)
+	} else if params == (RegtestEquihash::N, RegtestEquihash::K) {
+		verify_equihash_solution::<RegtestEquihash>(input, solution)
+	} else {
+		false
+	}
+}
+
+/// Generator of Equihash BSTRs source hashes: BLAKE2b(ZcashPoW personalization, input ++ LE(index)).
+///
+/// Both verifier and solver must use this generator, so that they never diverge.
+#[derive(Clone)]
+pub struct EquihashGenerator {
+	/// Size of every generated hash.
+	hash_size: usize,
+	/// Blake2b context that has already consumed the input.
+	context: Blake2b,
+}
+
+impl EquihashGenerator {
+	/// Creates generator for Equihash(n, k) and given input (block header, excluding solution).
+	pub fn new(n: u32, k: u32, input: &[u8]) -> Self {
+		let hash_size = (512 / n as usize) * n as usize / 8;
+		let mut context = Blake2b::with_params(hash_size, &[], &[], &blake2b_personalization(n, k));
+		context.update(input);
+
+		EquihashGenerator {
+			hash_size: hash_size,
+			context: context,
+		}
+	}
+
+	/// Returns size of every generated hash.
+	pub fn hash_size(&self) -> usize {
+		self.hash_size
+	}
+
+	/// Generates hash with given index. The `hash` length must be equal to `hash_size()`.
+	pub fn generate(&self, index: u32, hash: &mut [u8]) {
+		let mut context = self.context.clone();
+		context.update(&index.to_le_bytes());
+		hash.copy_from_slice(context.finalize().as_bytes())
+	}
+}
+
+/// Returns BLAKE2b personalization used by Equihash(n, k): b"ZcashPoW" ++ LE(n) ++ LE(k).
+pub fn blake2b_personalization(n: u32, k: u32) -> [u8; 16] {
+	let mut personalization = [0u8; 16];
+	personalization[0..8].copy_from_slice(b"ZcashPoW");
+	personalization[8..12].copy_from_slice(&n.to_le_bytes());
+	personalization[12..16].copy_from_slice(&k.to_le_bytes());
+	personalization
 }
 
 /// Equihash algorithm instance.
@@ -33,8 +100,6 @@ trait Equihash {
 	const N: u32;
 	/// Parameter K of Equihash algorithm.
 	const K: u32;
-	/// Blake2b personalization used by the algorithm instance.
-	const BLAKE2B_PERSONALIZATION: [u8; 16];
 
 	/// The number of N-bit BSTRs that could be generated from the single computed hash.
 	const BSTRS_PER_HASH: usize = (512 / Self::N) as usize;
@@ -67,23 +132,33 @@ struct OnChainEquihash;
 impl Equihash for OnChainEquihash {
 	const N: u32 = 200;
 	const K: u32 = 9;
-	const BLAKE2B_PERSONALIZATION: [u8; 16] = [
-		0x5a, 0x63, 0x61, 0x73, 0x68, 0x50, 0x6f, 0x57,		// b"ZcashPoW"
-		0xc8, 0x00, 0x00, 0x00,								// LE(N)
-		0x09, 0x00, 0x00, 0x00,								// LE(K)
-	];
 
 	type Hash = self::on_chain_equihash::Hash;
 }
 
+/// Equihash algorithm instance that is used by zcashd regtest chains.
+struct RegtestEquihash;
+
+impl Equihash for RegtestEquihash {
+	const N: u32 = 48;
+	const K: u32 = 5;
+
+	type Hash = self::regtest_equihash::Hash;
+}
+
 /// Verify equihash solution.
 fn verify_equihash_solution<Algorithm: Equihash>(
 	input: &[u8],
 	solution: &[u8],
 ) -> bool {
-	// prepare Blake2b context with personalization
-	let mut context = Blake2b::with_params(Algorithm::HASH_SIZE, &[], &[], &Algorithm::BLAKE2B_PERSONALIZATION);
-	context.update(input);
+	// solution length depends on algorithm parameters
+	if solution.len() != Algorithm::SOLUTION_COMPRESSED_SIZE {
+		return false;
+	}
+
+	// prepare hash generator
+	let generator = EquihashGenerator::new(Algorithm::N, Algorithm::K, input);
+	debug_assert_eq!(generator.hash_size(), Algorithm::HASH_SIZE);
 
 	// we're using two dynamic vectors here && swap pointers when required
 	// for on-chain algorithm instance:
@@ -101,7 +176,7 @@ fn verify_equihash_solution<Algorithm: Equihash>(
 		solution,
 		&mut |index| {
 			let hash_half_index = (index as usize / Algorithm::BSTRS_PER_HASH) as u32;
-			generate_hash(&context, hash_half_index, hash.as_mut());
+			generator.generate(hash_half_index, hash.as_mut());
 
 			let hash_begin = (index as usize % Algorithm::BSTRS_PER_HASH) * Algorithm::N as usize / 8;
 			let hash_end = hash_begin + Algorithm::N as usize / 8;
@@ -229,12 +304,6 @@ fn expand_array<E: FnMut(&[u8; 4])>(
 	}
 }
 
-fn generate_hash(context: &Blake2b, index: u32, hash: &mut [u8]) {
-	let mut context = context.clone();
-	context.update(&index.to_le_bytes());
-	hash.copy_from_slice(context.finalize().as_bytes())
-}
-
 fn merge_rows(row1: &[u8], row2: &[u8], merged_row: &mut [u8], len: usize, indices_len: usize, trim: usize) {
 	let mut merged_row_pos = 0;
 	for i in trim..len {
@@ -311,10 +380,27 @@ mod on_chain_equihash {
 	}
 }
 
+mod regtest_equihash {
+	pub struct Hash(pub [u8; 60]);
+
+	impl Default for Hash {
+		fn default() -> Self { Hash([0; 60]) }
+	}
+
+	impl AsRef<[u8]> for Hash {
+		fn as_ref(&self) -> &[u8] { &self.0 }
+	}
+
+	impl AsMut<[u8]> for Hash {
+		fn as_mut(&mut self) -> &mut [u8] { &mut self.0 }
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
 
+	use hex::FromHex;
 	use primitives::bigint::U256;
 
 	struct TestEquihash;
@@ -322,11 +408,6 @@ mod tests {
 	impl Equihash for TestEquihash {
 		const N: u32 = 96;
 		const K: u32 = 5;
-		const BLAKE2B_PERSONALIZATION: [u8; 16] = [
-			0x5a, 0x63, 0x61, 0x73, 0x68, 0x50, 0x6f, 0x57,		// b"ZcashPoW"
-			0x60, 0x00, 0x00, 0x00,								// LE(N)
-			0x05, 0x00, 0x00, 0x00,								// LE(K)
-		];
 
 		type Hash = TestHash;
 	}
@@ -410,9 +491,65 @@ mod tests {
 		));
 	}
 
+	#[test]
+	fn equihash_generator_works() {
+		assert_eq!(&blake2b_personalization(200, 9), b"ZcashPoW\xc8\x00\x00\x00\x09\x00\x00\x00");
+
+		let mut input = b"block header".to_vec();
+		let mut le_nonce = vec![0; 32];
+		U256::one().to_little_endian(&mut le_nonce);
+		input.extend(le_nonce);
+
+		let generator = EquihashGenerator::new(200, 9, &input);
+		assert_eq!(generator.hash_size(), 50);
+		let mut hash = [0u8; 50];
+		generator.generate(0, &mut hash);
+		assert_eq!(hash.to_vec(), "b77dc78f77ecd4b37d91c930747b5d7a7b988c8ebc4448a1dc0a011906f6c1137b8dd1258c85c1bda5b5e2f553658d885bce".from_hex::<Vec<u8>>().unwrap());
+		generator.generate(1, &mut hash);
+		assert_eq!(hash.to_vec(), "b2d8fb41323f8f35887d2d4052199a319477c184702d5947966b02a90e4f13871fe485f550ada15d2d5036a1349adae89bca".from_hex::<Vec<u8>>().unwrap());
+
+		let generator = EquihashGenerator::new(48, 5, &input);
+		assert_eq!(generator.hash_size(), 60);
+		let mut hash = [0u8; 60];
+		generator.generate(3, &mut hash);
+		assert_eq!(hash.to_vec(), "2a39b444aff5460966b3ede7f1c69b70e700b14836778f1c45cf7f719f6997f84a408633d590ddde1b890fc76eb58235244be57cae75921196f8e8e3".from_hex::<Vec<u8>>().unwrap());
+	}
+
 	#[test]
 	fn test_equihash_on_real_block() {
 		let block = test_data::block_h170();
 		assert!(verify_block_equihash_solution((200, 9), &block.block_header));
 	}
+
+	fn regtest_header(nonce: u8) -> BlockHeader {
+		BlockHeader {
+			version: 4,
+			previous_header_hash: Default::default(),
+			merkle_root_hash: Default::default(),
+			final_sapling_root: Default::default(),
+			time: 1540000000,
+			bits: 0x200f0f0f.into(),
+			nonce: nonce.into(),
+			solution: "04dac245c2925d68b910fcd4eca42d61e1960f6505e3024f05b9d425319915131c72838c".from_hex::<Vec<u8>>().unwrap().into(),
+		}
+	}
+
+	#[test]
+	fn test_equihash_on_regtest_header() {
+		let header = regtest_header(0);
+		assert_eq!(header.equihash_solution().len(), RegtestEquihash::SOLUTION_COMPRESSED_SIZE);
+		assert!(header.verify_equihash(48, 5));
+		// solution is not valid for other nonce
+		assert!(!regtest_header(1).verify_equihash(48, 5));
+		// solution is not valid for other parameters
+		assert!(!header.verify_equihash(200, 9));
+		assert!(!header.verify_equihash(96, 5));
+	}
+
+	#[test]
+	fn test_equihash_on_real_block_header() {
+		let block = test_data::block_h170();
+		assert!(block.block_header.verify_equihash(200, 9));
+		assert!(!block.block_header.verify_equihash(48, 5));
+	}
 }
@@ -0,0 +1,241 @@
+use primitives::hash::H256;
+use equihash::EquihashGenerator;
+
+/// Rows of the single round of the Wagner's algorithm.
+struct Rows {
+	/// Number of not-yet-collided digits in every row.
+	width: usize,
+	/// Not-yet-collided digits of XORed BSTRs (`width` digits per row).
+	digits: Vec<u32>,
+	/// The first BSTR index of every row' indices subtree (it is also the lowest index of the subtree).
+	first_indices: Vec<u32>,
+}
+
+impl Rows {
+	fn with_capacity(width: usize, capacity: usize) -> Self {
+		Rows {
+			width: width,
+			digits: Vec::with_capacity(width * capacity),
+			first_indices: Vec::with_capacity(capacity),
+		}
+	}
+
+	fn len(&self) -> usize {
+		self.first_indices.len()
+	}
+
+	fn row(&self, index: usize) -> &[u32] {
+		&self.digits[index * self.width..(index + 1) * self.width]
+	}
+}
+
+/// Solve Equihash(n, k) for given input (block header, excluding nonce and solution) and nonce.
+///
+/// This is a straightforward (and slow) implementation of the Wagner's algorithm. Instead of full
+/// lists of BSTRs indices, every row only references colliding rows of the previous round, so that
+/// memory usage is proportional to 2^(n / (k + 1) + 1) * k. This is still only suitable for testing
+/// networks: regtest Equihash(200, 9) requires a few hundred megabytes. Returns compressed solution,
+/// that could be verified with `verify_equihash`, or None if there's no solution for given nonce.
+pub fn solve(input: &[u8], nonce: &H256, n: u32, k: u32) -> Option<Vec<u8>> {
+	let collision_bit_length = (n / (k + 1)) as usize;
+	if n % 8 != 0 || k < 3 || collision_bit_length == 0 || collision_bit_length >= 31 {
+		return None;
+	}
+
+	let mut input = input.to_vec();
+	input.extend_from_slice(&**nonce);
+	let generator = EquihashGenerator::new(n, k, &input);
+	let bstr_size = n as usize / 8;
+	let bstrs_per_hash = generator.hash_size() / bstr_size;
+
+	// generate initial list of 2^(collision_bit_length + 1) BSTRs
+	let initial_rows_count = 1usize << (collision_bit_length + 1);
+	let mut hash = vec![0u8; generator.hash_size()];
+	let mut rows = Rows::with_capacity(k as usize + 1, initial_rows_count);
+	for index in 0..initial_rows_count as u32 {
+		let bstr_index = index as usize % bstrs_per_hash;
+		if bstr_index == 0 {
+			generator.generate(index / bstrs_per_hash as u32, &mut hash);
+		}
+
+		let bstr = &hash[bstr_index * bstr_size..(bstr_index + 1) * bstr_size];
+		rows.digits.extend(expand_digits(bstr, collision_bit_length));
+		rows.first_indices.push(index);
+	}
+
+	// parents[round][row] are rows of the previous round (BSTRs indices for the first round), which
+	// have been collided to produce the row
+	let mut parents: Vec<Vec<(u32, u32)>> = Vec::with_capacity(k as usize);
+
+	// every round collides rows on the leading digit
+	// the last round requires collision on both remaining digits, so that XOR of all BSTRs is zero
+	for round in 0..k as usize {
+		let is_last_round = round == k as usize - 1;
+		let mut order: Vec<u32> = (0..rows.len() as u32).collect();
+		order.sort_unstable_by_key(|row| rows.row(*row as usize)[0]);
+
+		let mut next_rows = Rows::with_capacity(rows.width - 1, rows.len());
+		let mut round_parents = Vec::with_capacity(rows.len());
+		let mut group_begin = 0;
+		while group_begin < order.len() {
+			let group_digit = rows.row(order[group_begin] as usize)[0];
+			let mut group_end = group_begin + 1;
+			while group_end < order.len() && rows.row(order[group_end] as usize)[0] == group_digit {
+				group_end += 1;
+			}
+
+			for i in group_begin..group_end {
+				for j in i + 1..group_end {
+					// subtree with the lower first index must go first
+					let (first, second) = match rows.first_indices[order[i] as usize] < rows.first_indices[order[j] as usize] {
+						true => (order[i], order[j]),
+						false => (order[j], order[i]),
+					};
+					let (row1, row2) = (rows.row(first as usize), rows.row(second as usize));
+
+					if is_last_round {
+						if row1[1] != row2[1] {
+							continue;
+						}
+
+						let mut indices = Vec::with_capacity(1 << k);
+						collect_indices(&parents, round, first, &mut indices);
+						collect_indices(&parents, round, second, &mut indices);
+						if has_distinct_indices(&indices) {
+							return Some(compress_indices(&indices, collision_bit_length + 1));
+						}
+
+						continue;
+					}
+
+					// rows with equal remaining digits could only produce solutions with duplicate indices
+					if row1[1..] == row2[1..] {
+						continue;
+					}
+
+					next_rows.digits.extend(row1[1..].iter().zip(&row2[1..]).map(|(a, b)| a ^ b));
+					next_rows.first_indices.push(rows.first_indices[first as usize]);
+					round_parents.push((first, second));
+				}
+			}
+
+			group_begin = group_end;
+		}
+
+		parents.push(round_parents);
+		rows = next_rows;
+	}
+
+	None
+}
+
+/// Appends BSTRs indices of the row of given round (in solution order) to the `indices`.
+fn collect_indices(parents: &[Vec<(u32, u32)>], round: usize, row: u32, indices: &mut Vec<u32>) {
+	if round == 0 {
+		indices.push(row);
+		return;
+	}
+
+	let (first, second) = parents[round - 1][row as usize];
+	collect_indices(parents, round - 1, first, indices);
+	collect_indices(parents, round - 1, second, indices);
+}
+
+/// Split BSTR into big-endian digits of `digit_bits` bits each.
+fn expand_digits(bstr: &[u8], digit_bits: usize) -> Vec<u32> {
+	(0..bstr.len() * 8 / digit_bits)
+		.map(|digit| (digit * digit_bits..(digit + 1) * digit_bits)
+			.fold(0u32, |value, bit| (value << 1) | ((bstr[bit / 8] >> (7 - bit % 8)) & 1) as u32))
+		.collect()
+}
+
+/// Pack indices into big-endian bit array, using `index_bits` bits per index.
+fn compress_indices(indices: &[u32], index_bits: usize) -> Vec<u8> {
+	let mut compressed = vec![0u8; (indices.len() * index_bits + 7) / 8];
+	let mut bit_pos = 0;
+	for index in indices {
+		for bit in (0..index_bits).rev() {
+			if (index >> bit) & 1 == 1 {
+				compressed[bit_pos / 8] |= 0x80 >> (bit_pos % 8);
+			}
+			bit_pos += 1;
+		}
+	}
+
+	compressed
+}
+
+fn has_distinct_indices(indices: &[u32]) -> bool {
+	let mut sorted = indices.to_vec();
+	sorted.sort_unstable();
+	sorted.windows(2).all(|pair| pair[0] != pair[1])
+}
+
+#[cfg(test)]
+mod tests {
+	use chain::BlockHeader;
+	use equihash::{BlockHeaderEquihash, verify_equihash};
+	use network::{ConsensusParams, Network};
+	use super::{solve, compress_indices, expand_digits};
+
+	fn regtest_header() -> BlockHeader {
+		BlockHeader {
+			version: 4,
+			previous_header_hash: Default::default(),
+			merkle_root_hash: Default::default(),
+			final_sapling_root: Default::default(),
+			time: 1540000000,
+			bits: 0x200f0f0f.into(),
+			nonce: Default::default(),
+			solution: Vec::new().into(),
+		}
+	}
+
+	#[test]
+	fn expand_and_compress_work() {
+		assert_eq!(expand_digits(&[0xab, 0xcd, 0xef], 8), vec![0xab, 0xcd, 0xef]);
+		assert_eq!(expand_digits(&[0xab, 0xcd, 0xef], 12), vec![0xabc, 0xdef]);
+		assert_eq!(compress_indices(&[0x1ff, 0x000, 0x155], 9), vec![0xff, 0x80, 0x2a, 0xa0]);
+	}
+
+	fn solve_header(header: &mut BlockHeader, n: u32, k: u32) {
+		let input = header.equihash_input();
+		// input is followed by the 32-byte nonce
+		let input = &input[..input.len() - 32];
+
+		let solution = (0u8..32)
+			.filter_map(|nonce| {
+				header.nonce = nonce.into();
+				solve(input, &header.nonce, n, k)
+			})
+			.next()
+			.expect("there's a solution for one of the first 32 nonces");
+
+		assert!(verify_equihash((n, k), &header.equihash_input(), &solution));
+		header.solution = solution.into();
+	}
+
+	#[test]
+	fn solve_small_parameters_roundtrip() {
+		let mut header = regtest_header();
+		solve_header(&mut header, 48, 5);
+		assert!(header.verify_equihash(48, 5));
+		assert!(!header.verify_equihash(200, 9));
+	}
+
+	#[test]
+	fn solve_regtest_header_roundtrip() {
+		let (n, k) = ConsensusParams::new(Network::Regtest).equihash_params
+			.expect("regtest blocks are checked with equihash");
+		let mut header = regtest_header();
+		solve_header(&mut header, n, k);
+		assert!(header.verify_equihash(n, k));
+		assert!(!header.verify_equihash(48, 5));
+	}
+
+	#[test]
+	fn solve_rejects_unsupported_parameters() {
+		assert_eq!(solve(&[], &Default::default(), 47, 5), None);
+		assert_eq!(solve(&[], &Default::default(), 48, 0), None);
+	}
+}
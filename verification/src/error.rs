@@ -1,4 +1,6 @@
+use std::fmt;
 use hash::H256;
+use chain::OutPoint;
 use compact::Compact;
 use storage::Error as DBError;
 use script::Error as SignatureError;
@@ -24,6 +26,10 @@ pub enum Error {
 	Transaction(usize, TransactionError),
 	/// nBits do not match difficulty rules
 	Difficulty { expected: Compact, actual: Compact },
+	/// nBits encode negative, zero or overflowed target
+	InvalidTarget(Compact),
+	/// nBits encode target that is above network proof-of-work limit
+	TargetAboveLimit { limit: Compact, actual: Compact },
 	/// Invalid merkle root
 	MerkleRoot,
 	/// Coinbase spends too much
@@ -57,6 +63,8 @@ pub enum Error {
 	InvalidEquihashSolution,
 	/// Invalid block version
 	InvalidVersion,
+	/// Block has null previous header hash, but it isn't the genesis block.
+	InvalidGenesis,
 	/// Block' coinbase is missing founders reward output.
 	MissingFoundersReward,
 	/// Failed to read sapling commitment tree state from parent block.
@@ -76,8 +84,10 @@ impl From<DBError> for Error {
 #[derive(Debug, PartialEq)]
 /// Possible transactions verification errors
 pub enum TransactionError {
-	/// Transaction has no inputs or no outputs
-	Empty,
+	/// Transaction has no transparent inputs, no JoinSplits and no Sapling spends
+	EmptyInputs,
+	/// Transaction has no transparent outputs, no JoinSplits and no Sapling outputs
+	EmptyOutputs,
 	/// Transaction is not coinbase transaction but has null inputs
 	NullNonCoinbase,
 	/// Coinbase signature is not in the range 2-100
@@ -92,8 +102,12 @@ pub enum TransactionError {
 	MemoryPoolCoinbase,
 	/// Not found corresponding output for transaction input
 	Input(usize),
+	/// Previous output, referenced by transaction input, is not found in the chain, memory pool or block
+	MissingInputs(OutPoint),
 	/// Referenced coinbase output for the transaction input is not mature enough
 	Maturity,
+	/// Transaction spends coinbase output, but has transparent outputs.
+	CoinbaseSpendHasTransparentOutputs,
 	/// Signature invalid for given input
 	Signature(usize, SignatureError),
 	/// Unknown previous transaction referenced
@@ -142,6 +156,8 @@ pub enum TransactionError {
 	JoinSplitVersionInvalid,
 	/// Invalid join split signature
 	JoinSplitSignature(::crypto::Error),
+	/// Sapling spends or outputs are not supported in the transaction version.
+	SaplingVersionInvalid,
 	/// Transaction sapling verification has failed.
 	InvalidSapling,
 	/// Sapling nullifier already revealed earlier in the chain.
@@ -154,4 +170,44 @@ pub enum TransactionError {
 	InvalidJoinSplit(usize),
 	/// Unknown anchor used in join split
 	UnknownAnchor(H256),
+	/// Memory pool transaction verification has exceeded configured time budget.
+	/// This is a relay policy error: the same transaction could be accepted later.
+	VerificationTimeout,
+	/// Sapling spend anchor is known, but is too old to relay the transaction.
+	OldSaplingAnchor(H256),
+	/// Sapling spend anchor isn't a final sapling root of any canon block.
+	UnknownSaplingAnchor(H256),
+	/// Signature script of transaction input contains non-push operations.
+	NonPushScriptSig(usize),
+	/// Transaction conflicts with memory pool transactions, but it doesn't satisfy replacement (BIP125) rules.
+	ReplacementRejected(RbfError),
+}
+
+/// Reason why replacement transaction has been rejected.
+/// Fees are modified fees, i.e. they include virtual fees, set by `prioritisetransaction`.
+#[derive(Debug, PartialEq)]
+pub enum RbfError {
+	/// Conflicting memory-pool transaction does not signal replaceability
+	NotReplaceable(H256),
+	/// Replacement transaction spends outputs of conflicting memory-pool transaction
+	SpendsConflictingTransaction(H256),
+	/// Replacement transaction fee is not greater than fee of all replaced transactions
+	InsufficientFee(i64, i64),
+	/// Replacement transaction fee rate is not greater than fee rate of conflicting memory-pool transaction
+	InsufficientFeeRate(H256),
+}
+
+impl fmt::Display for RbfError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			RbfError::NotReplaceable(ref hash) =>
+				write!(f, "Conflicting memory-pool transaction {} does not signal replaceability", hash.to_reversed_str()),
+			RbfError::SpendsConflictingTransaction(ref hash) =>
+				write!(f, "Replacement transaction spends outputs of conflicting memory-pool transaction {}", hash.to_reversed_str()),
+			RbfError::InsufficientFee(fee, replaced_fee) =>
+				write!(f, "Replacement transaction fee {} is not greater than fee {} of all replaced transactions", fee, replaced_fee),
+			RbfError::InsufficientFeeRate(ref hash) =>
+				write!(f, "Replacement transaction fee rate is not greater than fee rate of conflicting memory-pool transaction {}", hash.to_reversed_str()),
+		}
+	}
 }
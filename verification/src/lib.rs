@@ -84,11 +84,14 @@ pub mod constants;
 mod canon;
 mod deployments;
 mod equihash;
+#[cfg(feature = "solver")]
+mod equihash_solver;
 mod error;
 mod fee;
 mod sapling;
 mod sigops;
 mod sprout;
+mod subsidy;
 mod timestamp;
 mod work;
 
@@ -115,18 +118,22 @@ pub use canon::{CanonBlock, CanonHeader, CanonTransaction};
 pub use accept_block::BlockAcceptor;
 pub use accept_chain::ChainAcceptor;
 pub use accept_header::HeaderAcceptor;
-pub use accept_transaction::{TransactionAcceptor, MemoryPoolTransactionAcceptor};
+pub use accept_transaction::{TransactionAcceptor, MemoryPoolTransactionAcceptor, TransactionStage, TRANSACTION_STAGES};
 
 pub use verify_block::BlockVerifier;
 pub use verify_chain::ChainVerifier;
 pub use verify_header::HeaderVerifier;
 pub use verify_transaction::{TransactionVerifier, MemoryPoolTransactionVerifier};
 
-pub use chain_verifier::BackwardsCompatibleChainVerifier;
-pub use error::{Error, TransactionError};
+pub use chain_verifier::{BackwardsCompatibleChainVerifier, ScriptVerificationPool};
+pub use error::{Error, TransactionError, RbfError};
 pub use fee::checked_transaction_fee;
 pub use sigops::transaction_sigops;
+pub use subsidy::{block_subsidy, founders_reward_script, is_founders_reward_script, founders_reward_output};
 pub use timestamp::{median_timestamp, median_timestamp_inclusive};
+pub use equihash::{BlockHeaderEquihash, EquihashGenerator, blake2b_personalization, verify_equihash};
+#[cfg(feature = "solver")]
+pub use equihash_solver::solve as solve_equihash;
 pub use work::{work_required, is_valid_proof_of_work, is_valid_proof_of_work_hash};
 pub use deployments::Deployments;
 pub use tree_cache::TreeCache;
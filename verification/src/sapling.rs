@@ -1,7 +1,8 @@
 use std::io::Error as IoError;
+use parking_lot::Mutex;
 use chain::{Sapling, SaplingSpendDescription, SaplingOutputDescription};
 use crypto::{
-	Groth16VerifyingKey, JUBJUB,
+	Groth16VerifyingKey, Groth16BatchVerifier, JUBJUB,
 	pairing::{bls12_381::{Bls12, Fr, FrRepr}, PrimeField, PrimeFieldRepr, PrimeFieldDecodingError},
 	bellman::{SynthesisError, groth16::{verify_proof, Proof}},
 	sapling_crypto::{circuit::multipack, redjubjub::{self, Signature}},
@@ -71,6 +72,35 @@ pub enum ProofError {
 	Failed,
 }
 
+/// Sapling proofs of multiple transactions, queued for batch verification.
+/// Every proof is tagged with the index of transaction it belongs to.
+pub struct SaplingProofsBatch<'a> {
+	spends: Mutex<Groth16BatchVerifier<'a, usize>>,
+	outputs: Mutex<Groth16BatchVerifier<'a, usize>>,
+}
+
+impl<'a> SaplingProofsBatch<'a> {
+	pub fn new(spend_vk: &'a Groth16VerifyingKey, output_vk: &'a Groth16VerifyingKey) -> Self {
+		SaplingProofsBatch {
+			spends: Mutex::new(Groth16BatchVerifier::new(spend_vk)),
+			outputs: Mutex::new(Groth16BatchVerifier::new(output_vk)),
+		}
+	}
+
+	/// Verify all queued proofs. Returns index of the first transaction with invalid proof.
+	pub fn verify(&self) -> Result<(), usize> {
+		let spends = self.spends.lock();
+		let outputs = self.outputs.lock();
+		let invalid_tx_index = spends.verify().err().into_iter()
+			.chain(outputs.verify().err())
+			.min();
+		match invalid_tx_index {
+			Some(index) => Err(*index),
+			None => Ok(()),
+		}
+	}
+}
+
 /// Verify sapling proofs/signatures validity.
 pub fn accept_sapling(
 	spend_vk: &Groth16VerifyingKey,
@@ -97,6 +127,52 @@ pub fn accept_sapling(
 	accept_sapling_final(sighash, total, sapling)
 }
 
+/// Verify sapling signatures validity and queue proofs for batch verification.
+pub fn accept_sapling_batched(
+	batch: &SaplingProofsBatch,
+	tx_index: usize,
+	sighash: &[u8; 32],
+	sapling: &Sapling,
+) -> Result<(), Error> {
+	// binding verification key is not encoded explicitly in transaction and must be recalculated
+	let mut total = edwards::Point::zero();
+
+	// check each spend description and queue its proof
+	let mut spend_proofs = Vec::with_capacity(sapling.spends.len());
+	for (idx, spend) in sapling.spends.iter().enumerate() {
+		let (zkproof, public_input) = prepare_spend(sighash, &mut total, spend)
+			.map_err(|err| Error::Spend(idx, err))?;
+		spend_proofs.push((zkproof, public_input));
+	}
+
+	// check each output description and queue its proof
+	let mut output_proofs = Vec::with_capacity(sapling.outputs.len());
+	for (idx, output) in sapling.outputs.iter().enumerate() {
+		let (zkproof, public_input) = prepare_output(&mut total, output)
+			.map_err(|err| Error::Output(idx, err))?;
+		output_proofs.push((zkproof, public_input));
+	}
+
+	// check binding signature
+	accept_sapling_final(sighash, total, sapling)?;
+
+	// proofs are only queued when everything else is valid
+	{
+		let mut spends = batch.spends.lock();
+		for (zkproof, public_input) in spend_proofs {
+			spends.queue(tx_index, zkproof, public_input);
+		}
+	}
+	{
+		let mut outputs = batch.outputs.lock();
+		for (zkproof, public_input) in output_proofs {
+			outputs.queue(tx_index, zkproof, public_input);
+		}
+	}
+
+	Ok(())
+}
+
 /// Verify sapling spend description.
 fn accept_spend(
 	spend_vk: &Groth16VerifyingKey,
@@ -104,6 +180,24 @@ fn accept_spend(
 	total: &mut Point,
 	spend: &SaplingSpendDescription,
 ) -> Result<(), SpendError> {
+	let (zkproof, public_input) = prepare_spend(sighash, total, spend)?;
+
+	// check the proof
+	let is_verification_ok = verify_proof(&spend_vk.0, &zkproof, &public_input[..])
+		.map_err(|err| SpendError::Proof(ProofError::Synthesis(err)))?;
+	if !is_verification_ok {
+		return Err(SpendError::Proof(ProofError::Failed));
+	}
+
+	Ok(())
+}
+
+/// Check sapling spend description, except the proof. Returns the proof and its public input.
+fn prepare_spend(
+	sighash: &[u8; 32],
+	total: &mut Point,
+	spend: &SaplingSpendDescription,
+) -> Result<(Proof<Bls12>, Vec<Fr>), SpendError> {
 	// deserialize and check value commitment
 	let value_commitment = require_non_small_order_point(&spend.value_commitment)
 		.map_err(SpendError::ValueCommitment)?;
@@ -144,7 +238,7 @@ fn accept_spend(
 	// construct public input for circuit
 	let (randomized_key_x, randomized_key_y) = randomized_key.0.into_xy();
 	let (value_commitment_x, value_commitment_y) = value_commitment.into_xy();
-	let public_input = [
+	let public_input = vec![
 		randomized_key_x,
 		randomized_key_y,
 		value_commitment_x,
@@ -158,21 +252,31 @@ fn accept_spend(
 	let zkproof = Proof::<Bls12>::read(&spend.zkproof[..])
 		.map_err(|err| SpendError::Proof(ProofError::Invalid(err)))?;
 
+	Ok((zkproof, public_input))
+}
+
+fn accept_output(
+	output_vk: &Groth16VerifyingKey,
+	total: &mut Point,
+	output: &SaplingOutputDescription,
+) -> Result<(), OutputError> {
+	let (zkproof, public_input) = prepare_output(total, output)?;
+
 	// check the proof
-	let is_verification_ok = verify_proof(&spend_vk.0, &zkproof, &public_input[..])
-		.map_err(|err| SpendError::Proof(ProofError::Synthesis(err)))?;
+	let is_verification_ok = verify_proof(&output_vk.0, &zkproof, &public_input[..])
+		.map_err(|err| OutputError::Proof(ProofError::Synthesis(err)))?;
 	if !is_verification_ok {
-		return Err(SpendError::Proof(ProofError::Failed));
+		return Err(OutputError::Proof(ProofError::Failed));
 	}
 
 	Ok(())
 }
 
-fn accept_output(
-	output_vk: &Groth16VerifyingKey,
+/// Check sapling output description, except the proof. Returns the proof and its public input.
+fn prepare_output(
 	total: &mut Point,
 	output: &SaplingOutputDescription,
-) -> Result<(), OutputError> {
+) -> Result<(Proof<Bls12>, Vec<Fr>), OutputError> {
 	// deserialize and check value commitment
 	let value_commitment = require_non_small_order_point(&output.value_commitment)
 		.map_err(OutputError::ValueCommitment)?;
@@ -191,7 +295,7 @@ fn accept_output(
 	// construct public input for circuit
 	let (ephemeral_key_x, ephemeral_key_y) = ephemeral_key.into_xy();
 	let (value_commitment_x, value_commitment_y) = value_commitment.into_xy();
-	let public_input = [
+	let public_input = vec![
 		value_commitment_x,
 		value_commitment_y,
 		ephemeral_key_x,
@@ -203,14 +307,7 @@ fn accept_output(
 	let zkproof = Proof::<Bls12>::read(&output.zkproof[..])
 		.map_err(|err| OutputError::Proof(ProofError::Invalid(err)))?;
 
-	// check the proof
-	let is_verification_ok = verify_proof(&output_vk.0, &zkproof, &public_input[..])
-		.map_err(|err| OutputError::Proof(ProofError::Synthesis(err)))?;
-	if !is_verification_ok {
-		return Err(OutputError::Proof(ProofError::Failed));
-	}
-
-	Ok(())
+	Ok((zkproof, public_input))
 }
 
 fn accept_sapling_final(sighash: &[u8; 32], total: Point, sapling: &Sapling) -> Result<(), Error> {
@@ -296,6 +393,7 @@ mod tests {
 	extern crate test_data;
 
 	use chain::Transaction;
+	use network::NetworkUpgrade;
 	use script::{TransactionInputSigner, SighashBase};
 	use super::*;
 
@@ -306,7 +404,7 @@ mod tests {
 
 	fn compute_sighash(tx: Transaction) -> [u8; 32] {
 		let signer: TransactionInputSigner = tx.into();
-		signer.signature_hash(&mut Default::default(), None, 0, &From::from(vec![]), SighashBase::All.into(), 0x76b809bb).into()
+		signer.signature_hash(&mut Default::default(), None, 0, &From::from(vec![]), SighashBase::All.into(), NetworkUpgrade::Sapling.branch_id()).into()
 	}
 
 	fn run_accept_sapling(tx: Transaction) -> Result<(), Error> {
@@ -344,9 +442,9 @@ mod tests {
 
 	fn bad_verifying_key() -> Groth16VerifyingKey {
 		use crypto::pairing::{CurveAffine, bls12_381::{G1Affine, G2Affine}};
-		use crypto::bellman::groth16::{VerifyingKey, prepare_verifying_key};
+		use crypto::bellman::groth16::VerifyingKey;
 
-		Groth16VerifyingKey(prepare_verifying_key(&VerifyingKey {
+		VerifyingKey {
 			alpha_g1: G1Affine::zero(),
 			beta_g1: G1Affine::zero(),
 			beta_g2: G2Affine::zero(),
@@ -354,7 +452,7 @@ mod tests {
 			delta_g1: G1Affine::zero(),
 			delta_g2: G2Affine::zero(),
 			ic: vec![],
-		}))
+		}.into()
 	}
 
 	#[test]
@@ -362,6 +460,43 @@ mod tests {
 		run_accept_sapling(test_tx()).unwrap();
 	}
 
+	#[test]
+	fn batched_verification_of_valid_proofs() {
+		let spend_vk = crypto::load_sapling_spend_verifying_key().unwrap();
+		let output_vk = crypto::load_sapling_output_verifying_key().unwrap();
+		let sighash = compute_sighash(test_tx());
+		let sapling = test_tx().sapling.unwrap();
+
+		assert_matches!(accept_sapling(&spend_vk, &output_vk, &sighash, &sapling), Ok(()));
+
+		let batch = SaplingProofsBatch::new(&spend_vk, &output_vk);
+		accept_sapling_batched(&batch, 0, &sighash, &sapling).unwrap();
+		accept_sapling_batched(&batch, 1, &sighash, &sapling).unwrap();
+		assert_eq!(batch.verify(), Ok(()));
+	}
+
+	#[test]
+	fn batched_verification_of_proofs_with_single_invalid() {
+		let spend_vk = crypto::load_sapling_spend_verifying_key().unwrap();
+		let output_vk = crypto::load_sapling_output_verifying_key().unwrap();
+		let sighash = compute_sighash(test_tx());
+		let sapling = test_tx().sapling.unwrap();
+		// nullifier isn't covered by signatures => only the proof is invalid
+		let mut bad_sapling = sapling.clone();
+		bad_sapling.spends[0].nullifier = [0; 32];
+
+		assert_matches!(
+			accept_sapling(&spend_vk, &output_vk, &sighash, &bad_sapling),
+			Err(Error::Spend(0, SpendError::Proof(ProofError::Failed)))
+		);
+
+		let batch = SaplingProofsBatch::new(&spend_vk, &output_vk);
+		accept_sapling_batched(&batch, 0, &sighash, &sapling).unwrap();
+		accept_sapling_batched(&batch, 1, &sighash, &bad_sapling).unwrap();
+		accept_sapling_batched(&batch, 2, &sighash, &sapling).unwrap();
+		assert_eq!(batch.verify(), Err(1));
+	}
+
 	#[test]
 	fn accept_spend_fails() {
 		let spend_vk = crypto::load_sapling_spend_verifying_key().unwrap();
@@ -0,0 +1,101 @@
+use network::ConsensusParams;
+use script::{Builder, Script};
+
+/// Total block subsidy at given height, including founders reward and funding streams.
+pub fn block_subsidy(height: u32, consensus: &ConsensusParams) -> u64 {
+	consensus.block_reward(height)
+}
+
+/// P2SH script of the founders address that receives founders reward at given height.
+pub fn founders_reward_script(height: u32, consensus: &ConsensusParams) -> Option<Script> {
+	consensus.founder_address(height)
+		.map(|address| Builder::build_p2sh(&address.hash))
+}
+
+/// Is given script the script that must receive founders reward at given height?
+pub fn is_founders_reward_script(script: &[u8], height: u32, consensus: &ConsensusParams) -> bool {
+	founders_reward_script(height, consensus)
+		.map(|founders_script| &*founders_script == script)
+		.unwrap_or(false)
+}
+
+/// Output (script and value) that coinbase transaction must include to pay founders reward at given height.
+pub fn founders_reward_output(height: u32, consensus: &ConsensusParams) -> Option<(Script, u64)> {
+	founders_reward_script(height, consensus)
+		.map(|script| (script, consensus.founder_reward(height)))
+}
+
+#[cfg(test)]
+mod tests {
+	use network::{ConsensusParams, Network};
+	use script::{Builder, Script};
+	use super::{block_subsidy, founders_reward_output, founders_reward_script, is_founders_reward_script};
+
+	#[test]
+	fn block_subsidy_slow_start() {
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		assert_eq!(block_subsidy(0, &consensus), 0);
+		assert_eq!(block_subsidy(1, &consensus), 62_500);
+		assert_eq!(block_subsidy(9_999, &consensus), 624_937_500);
+		assert_eq!(block_subsidy(10_000, &consensus), 625_062_500);
+		assert_eq!(block_subsidy(19_999, &consensus), 1_250_000_000);
+
+		assert_eq!(founders_reward_output(0, &consensus), None);
+		assert_eq!(founders_reward_output(1, &consensus), Some((
+			Builder::build_p2sh(&consensus.founders_addresses[0].hash),
+			12_500,
+		)));
+	}
+
+	#[test]
+	fn block_subsidy_pre_halving() {
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		assert_eq!(block_subsidy(20_000, &consensus), 1_250_000_000);
+		assert_eq!(block_subsidy(consensus.blossom_height - 1, &consensus), 1_250_000_000);
+		assert_eq!(founders_reward_output(consensus.blossom_height - 1, &consensus), Some((
+			Builder::build_p2sh(&consensus.founders_addresses[36].hash),
+			250_000_000,
+		)));
+	}
+
+	#[test]
+	fn block_subsidy_post_blossom() {
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		assert_eq!(block_subsidy(consensus.blossom_height, &consensus), 625_000_000);
+		assert_eq!(founders_reward_output(consensus.blossom_height, &consensus), Some((
+			Builder::build_p2sh(&consensus.founders_addresses[36].hash),
+			125_000_000,
+		)));
+
+		// last block with founders reward is the last block before the first halving
+		assert_eq!(block_subsidy(1_046_399, &consensus), 625_000_000);
+		assert_eq!(founders_reward_output(1_046_399, &consensus), Some((
+			Builder::build_p2sh(&consensus.founders_addresses[47].hash),
+			125_000_000,
+		)));
+		assert_eq!(block_subsidy(1_046_400, &consensus), 312_500_000);
+		assert_eq!(founders_reward_output(1_046_400, &consensus), None);
+	}
+
+	#[test]
+	fn founders_reward_script_rotation() {
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		// t3Vz22vK5z2LcKEdg16Yv4FFneEL1zg9ojd, paid by mainnet block #1
+		let first: Script = "a9147d46a730d31f97b1930d3368a967c309bd4d136a87".into();
+		// t3cL9AucCajm3HXDhb5jBnJK2vapVoXsop3
+		let second: Script = "a914c2e5af6fa0864ceb5ab93d88813772cebe04c62087".into();
+
+		// every address receives (849_999 + 48) / 48 = 17_709 blocks worth of founders reward
+		assert_eq!(founders_reward_script(0, &consensus), None);
+		assert_eq!(founders_reward_script(1, &consensus).as_ref(), Some(&first));
+		assert_eq!(founders_reward_script(17_708, &consensus).as_ref(), Some(&first));
+		assert_eq!(founders_reward_script(17_709, &consensus).as_ref(), Some(&second));
+
+		assert!(is_founders_reward_script(&first, 1, &consensus));
+		assert!(!is_founders_reward_script(&second, 1, &consensus));
+		assert!(is_founders_reward_script(&second, 17_709, &consensus));
+		assert!(!is_founders_reward_script(&first, 17_709, &consensus));
+		assert!(!is_founders_reward_script(&first, 0, &consensus));
+		assert!(!is_founders_reward_script(&first, 1_046_400, &consensus));
+	}
+}
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 
 use chain::hash::H256;
-use storage::{TreeStateProvider, SproutTreeState, SaplingTreeState};
+use storage::{TreeStateProvider, SproutTreeState, SaplingTreeState, EpochRef, EpochTag};
 use error::TransactionError;
 
 
@@ -23,6 +23,8 @@ impl TreeStateProvider for NoPersistentStorage {
 	fn sprout_block_root(&self, _block_hash: &H256) -> Option<H256> { None }
 
 	fn sapling_block_root(&self, _block_hash: &H256) -> Option<H256> { None }
+
+	fn contains_anchor(&self, _anchor: EpochRef) -> bool { false }
 }
 
 impl<'a> TreeCache<'a> {
@@ -44,6 +46,11 @@ impl<'a> TreeCache<'a> {
 		let mut tree = match self.interstitial.get(root) {
 			Some(tree) => tree.clone(),
 			None => {
+				// trees of blocks from side chains are also stored => only accept canon anchors
+				if !self.persistent.contains_anchor(EpochRef::new(EpochTag::Sprout, *root)) {
+					return Err(TransactionError::UnknownAnchor(*root));
+				}
+
 				self.persistent.sprout_tree_at(root).ok_or(TransactionError::UnknownAnchor(*root))?
 			}
 		};
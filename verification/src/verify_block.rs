@@ -52,7 +52,7 @@ impl<'a> BlockEmpty<'a> {
 	}
 
 	fn check(&self) -> Result<(), Error> {
-		if self.block.transactions.is_empty() {
+		if self.block.transactions().is_empty() {
 			Err(Error::Empty)
 		} else {
 			Ok(())
@@ -95,7 +95,7 @@ impl<'a> BlockCoinbase<'a> {
 	}
 
 	fn check(&self) -> Result<(), Error> {
-		if self.block.transactions.first().map(|tx| tx.raw.is_coinbase()).unwrap_or(false) {
+		if self.block.transactions().first().map(|tx| tx.raw.is_coinbase()).unwrap_or(false) {
 			Ok(())
 		} else {
 			Err(Error::Coinbase)
@@ -115,7 +115,7 @@ impl<'a> BlockExtraCoinbases<'a> {
 	}
 
 	fn check(&self) -> Result<(), Error> {
-		let misplaced = self.block.transactions.iter()
+		let misplaced = self.block.transactions().iter()
 			.skip(1)
 			.position(|tx| tx.raw.is_coinbase());
 
@@ -138,8 +138,8 @@ impl<'a> BlockTransactionsUniqueness<'a> {
 	}
 
 	fn check(&self) -> Result<(), Error> {
-		let hashes = self.block.transactions.iter().map(|tx| tx.hash.clone()).collect::<HashSet<_>>();
-		if hashes.len() == self.block.transactions.len() {
+		let hashes = self.block.transactions().iter().map(|tx| tx.hash.clone()).collect::<HashSet<_>>();
+		if hashes.len() == self.block.transactions().len() {
 			Ok(())
 		} else {
 			Err(Error::DuplicatedTransactions)
@@ -162,7 +162,7 @@ impl<'a> BlockSigops<'a> {
 
 	fn check(&self) -> Result<(), Error> {
 		// We cannot know if bip16 is enabled at this point so we disable it.
-		let sigops = self.block.transactions.iter()
+		let sigops = self.block.transactions().iter()
 			.map(|tx| transaction_sigops(&tx.raw, &NoopStore, false))
 			.sum::<usize>();
 
@@ -193,3 +193,35 @@ impl<'a> BlockMerkleRoot<'a> {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	extern crate test_data;
+
+	use chain::IndexedBlock;
+	use network::{ConsensusParams, Network};
+	use error::Error;
+	use super::BlockSerializedSize;
+
+	fn block_with_transactions(count: usize) -> IndexedBlock {
+		let mut builder = test_data::block_builder()
+			.transaction().coinbase().build();
+		for i in 1..count {
+			builder = builder.transaction().output().value(i as u64).build().build();
+		}
+		builder.merkled_header().build().build().into()
+	}
+
+	#[test]
+	fn block_serialized_size_limit() {
+		let block = block_with_transactions(3);
+		let size = block.size();
+		let mut consensus = ConsensusParams::new(Network::Unitest);
+
+		consensus.max_block_size = size;
+		assert_eq!(BlockSerializedSize::new(&block, &consensus).check(), Ok(()));
+
+		consensus.max_block_size = size - 1;
+		assert_eq!(BlockSerializedSize::new(&block, &consensus).check(), Err(Error::Size(size)));
+	}
+}
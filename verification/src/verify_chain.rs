@@ -28,7 +28,7 @@ impl<'a> ChainVerifier<'a> {
 			} else {
 				None
 			},
-			transactions: block.transactions.iter().map(|tx| TransactionVerifier::new(tx, consensus)).collect(),
+			transactions: block.transactions().iter().map(|tx| TransactionVerifier::new(tx, consensus)).collect(),
 		}
 	}
 
@@ -1,8 +1,9 @@
+use primitives::bigint::U256;
 use primitives::compact::Compact;
 use chain::IndexedBlockHeader;
 use equihash::verify_block_equihash_solution;
 use network::ConsensusParams;
-use work::is_valid_proof_of_work;
+use work::is_valid_proof_of_work_hash;
 use error::Error;
 use constants::BLOCK_MAX_FUTURE;
 
@@ -34,19 +35,29 @@ impl<'a> HeaderVerifier<'a> {
 
 pub struct HeaderProofOfWork<'a> {
 	header: &'a IndexedBlockHeader,
-	max_work_bits: Compact,
+	pow_limit: U256,
 }
 
 impl<'a> HeaderProofOfWork<'a> {
 	fn new(header: &'a IndexedBlockHeader, consensus: &ConsensusParams) -> Self {
 		HeaderProofOfWork {
 			header: header,
-			max_work_bits: consensus.network.max_bits().into(),
+			pow_limit: consensus.network.max_bits(),
 		}
 	}
 
 	fn check(&self) -> Result<(), Error> {
-		if is_valid_proof_of_work(self.max_work_bits, self.header.raw.bits, &self.header.hash) {
+		// target bounds are checked independently of the retargeting rules
+		let bits = self.header.raw.bits;
+		let target = match bits.to_u256() {
+			Ok(ref target) if !target.is_zero() => *target,
+			_ => return Err(Error::InvalidTarget(bits)),
+		};
+		if target > self.pow_limit {
+			return Err(Error::TargetAboveLimit { limit: Compact::from_u256(self.pow_limit), actual: bits });
+		}
+
+		if is_valid_proof_of_work_hash(bits, &self.header.hash) {
 			Ok(())
 		} else {
 			Err(Error::Pow)
@@ -128,9 +139,10 @@ impl<'a> HeaderEquihashSolution<'a> {
 mod tests {
 	extern crate test_data;
 
+	use primitives::compact::Compact;
 	use network::{Network, ConsensusParams};
 	use error::Error;
-	use super::HeaderVersion;
+	use super::{HeaderVersion, HeaderProofOfWork};
 
 	#[test]
 	fn header_version_works() {
@@ -143,4 +155,36 @@ mod tests {
 		assert_eq!(HeaderVersion::new(&test_data::block_builder().header().version(consensus.min_block_version() + 1)
 			.build().build().block_header.into(), &consensus).check(), Ok(()));
 	}
+
+	#[test]
+	fn header_proof_of_work_rejects_target_above_pow_limit() {
+		let consensus = ConsensusParams::new(Network::Mainnet);
+		let pow_limit = Compact::from_u256(consensus.network.max_bits());
+
+		// 0x2007ffff encodes target, which is 256 times greater than mainnet powLimit (0x1f07ffff)
+		let header = test_data::block_builder().header().bits(0x2007ffff.into()).build().build().block_header.into();
+		assert_eq!(HeaderProofOfWork::new(&header, &consensus).check(),
+			Err(Error::TargetAboveLimit { limit: pow_limit, actual: 0x2007ffff.into() }));
+
+		// target at powLimit passes the bound check (and fails on hash check)
+		let header = test_data::block_builder().header().bits(pow_limit).build().build().block_header.into();
+		assert_eq!(HeaderProofOfWork::new(&header, &consensus).check(), Err(Error::Pow));
+	}
+
+	#[test]
+	fn header_proof_of_work_rejects_invalid_target() {
+		let consensus = ConsensusParams::new(Network::Mainnet);
+
+		// negative target: sign bit is set
+		let header = test_data::block_builder().header().bits(0x1d800001.into()).build().build().block_header.into();
+		assert_eq!(HeaderProofOfWork::new(&header, &consensus).check(), Err(Error::InvalidTarget(0x1d800001.into())));
+
+		// overflowed target
+		let header = test_data::block_builder().header().bits(0xff123456.into()).build().build().block_header.into();
+		assert_eq!(HeaderProofOfWork::new(&header, &consensus).check(), Err(Error::InvalidTarget(0xff123456.into())));
+
+		// zero target
+		let header = test_data::block_builder().header().bits(0x1d000000.into()).build().build().block_header.into();
+		assert_eq!(HeaderProofOfWork::new(&header, &consensus).check(), Err(Error::InvalidTarget(0x1d000000.into())));
+	}
 }
@@ -1,9 +1,10 @@
 use std::{collections::HashMap, ops};
 use ser::Serializable;
-use chain::{IndexedTransaction, BTC_TX_VERSION, OVERWINTER_TX_VERSION,
+use chain::{IndexedTransaction, BTC_TX_VERSION, OVERWINTER_TX_VERSION, SAPLING_TX_VERSION,
 	OVERWINTER_TX_VERSION_GROUP_ID, SAPLING_TX_VERSION_GROUP_ID};
 use network::{ConsensusParams};
 use storage::NoopStore;
+use script::Script;
 use sigops::transaction_sigops;
 use error::TransactionError;
 use constants::{MIN_COINBASE_SIZE, MAX_COINBASE_SIZE};
@@ -80,6 +81,7 @@ pub struct MemoryPoolTransactionVerifier<'a> {
 	pub duplicate_inputs: TransactionDuplicateInputs<'a>,
 	pub duplicate_join_split_nullifiers: TransactionDuplicateJoinSplitNullifiers<'a>,
 	pub duplicate_sapling_nullifiers: TransactionDuplicateSaplingNullifiers<'a>,
+	pub push_only_script_sig: TransactionPushOnlyScriptSig<'a>,
 }
 
 impl<'a> MemoryPoolTransactionVerifier<'a> {
@@ -100,6 +102,7 @@ impl<'a> MemoryPoolTransactionVerifier<'a> {
 			duplicate_inputs: TransactionDuplicateInputs::new(transaction),
 			duplicate_join_split_nullifiers: TransactionDuplicateJoinSplitNullifiers::new(transaction),
 			duplicate_sapling_nullifiers: TransactionDuplicateSaplingNullifiers::new(transaction),
+			push_only_script_sig: TransactionPushOnlyScriptSig::new(transaction),
 		}
 	}
 
@@ -118,6 +121,7 @@ impl<'a> MemoryPoolTransactionVerifier<'a> {
 		self.duplicate_inputs.check()?;
 		self.duplicate_join_split_nullifiers.check()?;
 		self.duplicate_sapling_nullifiers.check()?;
+		self.push_only_script_sig.check()?;
 		Ok(())
 	}
 }
@@ -137,21 +141,24 @@ impl<'a> TransactionEmpty<'a> {
 	}
 
 	fn check(&self) -> Result<(), TransactionError> {
+		let transaction = &self.transaction.raw;
+		let has_join_splits = transaction.join_split.as_ref().map(|js| !js.descriptions.is_empty()).unwrap_or(false);
+
 		// Transactions containing empty `vin` must have either non-empty `vjoinsplit` or non-empty `vShieldedSpend`.
-		if self.transaction.raw.inputs.is_empty() {
-			let is_empty_join_split = self.transaction.raw.join_split.is_none();
-			let is_empty_shielded_spends = self.transaction.raw.sapling.as_ref().map(|s| s.spends.is_empty()).unwrap_or(true);
-			if is_empty_join_split && is_empty_shielded_spends {
-				return Err(TransactionError::Empty);
+		if transaction.inputs.is_empty() {
+			let has_shielded_spends = transaction.sapling.as_ref().map(|s| !s.spends.is_empty()).unwrap_or(false);
+			if !has_join_splits && !has_shielded_spends {
+				return Err(TransactionError::EmptyInputs);
 			}
 		}
 
 		// Transactions containing empty `vout` must have either non-empty `vjoinsplit` or non-empty `vShieldedOutput`.
-		if self.transaction.raw.outputs.is_empty() {
-			let is_empty_join_split = self.transaction.raw.join_split.is_none();
-			let is_empty_shielded_outputs = self.transaction.raw.sapling.as_ref().map(|s| s.outputs.is_empty()).unwrap_or(true);
-			if is_empty_join_split && is_empty_shielded_outputs {
-				return Err(TransactionError::Empty);
+		// I.e. purely transparent transaction must have outputs, but the transaction that moves all value
+		// to the shielded pool may have no transparent outputs at all.
+		if transaction.outputs.is_empty() {
+			let has_shielded_outputs = transaction.sapling.as_ref().map(|s| !s.outputs.is_empty()).unwrap_or(false);
+			if !has_join_splits && !has_shielded_outputs {
+				return Err(TransactionError::EmptyOutputs);
 			}
 		}
 
@@ -359,6 +366,14 @@ impl<'a> TransactionSapling<'a> {
 			if sapling.balancing_value != 0 && sapling.spends.is_empty() && sapling.outputs.is_empty() {
 				return Err(TransactionError::EmptySaplingHasBalance);
 			}
+
+			// spends and outputs are only allowed in sapling transactions
+			let is_sapling_tx = self.transaction.raw.overwintered
+				&& self.transaction.raw.version >= SAPLING_TX_VERSION
+				&& self.transaction.raw.version_group_id == SAPLING_TX_VERSION_GROUP_ID;
+			if !is_sapling_tx && (!sapling.spends.is_empty() || !sapling.outputs.is_empty()) {
+				return Err(TransactionError::SaplingVersionInvalid);
+			}
 		}
 
 		Ok(())
@@ -611,18 +626,47 @@ impl<'a> TransactionDuplicateSaplingNullifiers<'a> {
 	}
 }
 
+/// Standardness: signature scripts of transaction inputs must only push data to the stack.
+/// Non-push operations are still allowed by consensus rules, so this is only checked for
+/// memory pool transactions.
+pub struct TransactionPushOnlyScriptSig<'a> {
+	transaction: &'a IndexedTransaction,
+}
+
+impl<'a> TransactionPushOnlyScriptSig<'a> {
+	fn new(transaction: &'a IndexedTransaction) -> Self {
+		TransactionPushOnlyScriptSig {
+			transaction: transaction,
+		}
+	}
+
+	fn check(&self) -> Result<(), TransactionError> {
+		for (idx, input) in self.transaction.raw.inputs.iter().enumerate() {
+			let script_sig: Script = input.script_sig.clone().into();
+			if !script_sig.is_push_only() {
+				return Err(TransactionError::NonPushScriptSig(idx));
+			}
+		}
+
+		Ok(())
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	extern crate test_data;
 
 	use chain::{BTC_TX_VERSION, OVERWINTER_TX_VERSION, OVERWINTER_TX_VERSION_GROUP_ID,
-		SAPLING_TX_VERSION_GROUP_ID, Sapling, JoinSplit, JoinSplitDescription};
+		SAPLING_TX_VERSION_GROUP_ID, Sapling, SaplingSpendDescription, JoinSplit, JoinSplitDescription,
+		IndexedTransaction, Transaction};
 	use network::{Network, ConsensusParams};
+	use script::{Builder, Opcode};
 	use error::TransactionError;
 	use super::{TransactionEmpty, TransactionVersion, TransactionNonTransparentCoinbase,
 		TransactionOutputValueOverflow, TransactionExpiry, TransactionSapling, TransactionJoinSplit,
 		TransactionInputValueOverflow, TransactionDuplicateInputs, TransactionDuplicateJoinSplitNullifiers,
-		TransactionDuplicateSaplingNullifiers};
+		TransactionDuplicateSaplingNullifiers, TransactionPushOnlyScriptSig, TransactionVerifier,
+		MemoryPoolTransactionVerifier};
 
 	#[test]
 	fn transaction_empty_works() {
@@ -630,7 +674,7 @@ mod tests {
 
 		assert_eq!(TransactionEmpty::new(&test_data::TransactionBuilder::with_version(2)
 			.add_output(0)
-			.into()).check(), Err(TransactionError::Empty));
+			.into()).check(), Err(TransactionError::EmptyInputs));
 
 		assert_eq!(TransactionEmpty::new(&test_data::TransactionBuilder::with_version(2)
 			.add_output(0)
@@ -646,7 +690,7 @@ mod tests {
 
 		assert_eq!(TransactionEmpty::new(&test_data::TransactionBuilder::with_version(2)
 			.add_default_input(0)
-			.into()).check(), Err(TransactionError::Empty));
+			.into()).check(), Err(TransactionError::EmptyOutputs));
 
 		assert_eq!(TransactionEmpty::new(&test_data::TransactionBuilder::with_version(2)
 			.add_default_input(0)
@@ -657,6 +701,23 @@ mod tests {
 			.add_default_input(0)
 			.set_sapling(Sapling { outputs: vec![Default::default()], ..Default::default() })
 			.into()).check(), Ok(()));
+
+		// join split without descriptions doesn't count
+
+		assert_eq!(TransactionEmpty::new(&test_data::TransactionBuilder::with_version(2)
+			.add_default_input(0)
+			.set_join_split(Default::default())
+			.into()).check(), Err(TransactionError::EmptyOutputs));
+
+		// fully shielded transaction has neither transparent inputs, nor transparent outputs
+
+		assert_eq!(TransactionEmpty::new(&test_data::TransactionBuilder::with_version(4)
+			.set_sapling(Sapling {
+				spends: vec![Default::default()],
+				outputs: vec![Default::default()],
+				..Default::default()
+			})
+			.into()).check(), Ok(()));
 	}
 
 	#[test]
@@ -845,29 +906,47 @@ mod tests {
 
 	#[test]
 	fn transaction_sapling_works() {
-		assert_eq!(TransactionSapling::new(&test_data::TransactionBuilder::with_sapling(Sapling {
+		fn sapling_tx(sapling: Sapling) -> test_data::TransactionBuilder {
+			test_data::TransactionBuilder::with_sapling(sapling)
+				.set_overwintered(true)
+				.set_version(4)
+				.set_version_group_id(SAPLING_TX_VERSION_GROUP_ID)
+		}
+
+		assert_eq!(TransactionSapling::new(&sapling_tx(Sapling {
 				balancing_value: 100,
 				spends: vec![Default::default()],
 				..Default::default()
 			}).into()).check(), Ok(()));
 
-		assert_eq!(TransactionSapling::new(&test_data::TransactionBuilder::with_sapling(Sapling {
+		assert_eq!(TransactionSapling::new(&sapling_tx(Sapling {
 				balancing_value: 100,
 				outputs: vec![Default::default()],
 				..Default::default()
 			}).into()).check(), Ok(()));
 
-		assert_eq!(TransactionSapling::new(&test_data::TransactionBuilder::with_sapling(Sapling {
+		assert_eq!(TransactionSapling::new(&sapling_tx(Sapling {
 				balancing_value: 100,
 				outputs: vec![Default::default()],
 				spends: vec![Default::default()],
 				..Default::default()
 			}).into()).check(), Ok(()));
 
-		assert_eq!(TransactionSapling::new(&test_data::TransactionBuilder::with_sapling(Sapling {
+		assert_eq!(TransactionSapling::new(&sapling_tx(Sapling {
 				balancing_value: 100,
 				..Default::default()
 			}).into()).check(), Err(TransactionError::EmptySaplingHasBalance));
+
+		// spends && outputs are not allowed in non-sapling transactions
+		assert_eq!(TransactionSapling::new(&test_data::TransactionBuilder::with_sapling(Sapling {
+				spends: vec![Default::default()],
+				..Default::default()
+			}).into()).check(), Err(TransactionError::SaplingVersionInvalid));
+
+		assert_eq!(TransactionSapling::new(&sapling_tx(Sapling {
+				outputs: vec![Default::default()],
+				..Default::default()
+			}).set_version(3).set_version_group_id(OVERWINTER_TX_VERSION_GROUP_ID).into()).check(), Err(TransactionError::SaplingVersionInvalid));
 	}
 
 	#[test]
@@ -937,4 +1016,46 @@ mod tests {
 			..Default::default()
 		}).into()).check(), Err(TransactionError::DuplicateSaplingSpendNullifier(0, 1)));
 	}
+
+	#[test]
+	fn transaction_verifier_rejects_duplicate_sapling_nullifiers() {
+		let spend = SaplingSpendDescription {
+			nullifier: [42; 32],
+			..Default::default()
+		};
+		let tx: IndexedTransaction = test_data::TransactionBuilder::with_sapling(Sapling {
+				spends: vec![spend.clone(), spend],
+				..Default::default()
+			})
+			.set_overwintered(true)
+			.set_version(4)
+			.set_version_group_id(SAPLING_TX_VERSION_GROUP_ID)
+			.add_default_input(0)
+			.add_output(10)
+			.into();
+		let consensus = ConsensusParams::new(Network::Mainnet);
+
+		assert_eq!(TransactionVerifier::new(&tx, &consensus).check(), Err(TransactionError::DuplicateSaplingSpendNullifier(0, 1)));
+		assert_eq!(MemoryPoolTransactionVerifier::new(&tx, &consensus).check(), Err(TransactionError::DuplicateSaplingSpendNullifier(0, 1)));
+	}
+
+	#[test]
+	fn transaction_push_only_script_sig_works() {
+		let mut tx: Transaction = test_data::TransactionBuilder::with_default_input(0)
+			.add_default_input(1)
+			.add_output(10)
+			.into();
+
+		tx.inputs[0].script_sig = Builder::default()
+			.push_data(&[1; 72])
+			.push_opcode(Opcode::OP_1)
+			.into_bytes();
+		assert_eq!(TransactionPushOnlyScriptSig::new(&tx.clone().into()).check(), Ok(()));
+
+		tx.inputs[1].script_sig = Builder::default()
+			.push_data(&[1; 33])
+			.push_opcode(Opcode::OP_DUP)
+			.into_bytes();
+		assert_eq!(TransactionPushOnlyScriptSig::new(&tx.into()).check(), Err(TransactionError::NonPushScriptSig(1)));
+	}
 }